@@ -0,0 +1,429 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::process::{Command, Output};
+use std::sync::RwLock;
+
+// overlay2 stacks at most this many layers, builds fail past it
+pub const MAX_LAYERS: usize = 127;
+// Layer counts that get a warning, the last few layers go quickly once a
+// pipeline appends one per build
+pub const WARN_LAYERS: usize = 100;
+pub const CRITICAL_LAYERS: usize = 120;
+
+// DOCKER_HOST from the settings. It's set on each docker command rather than
+// in the process environment, which other threads read while it changes.
+static DOCKER_HOST: RwLock<Option<String>> = RwLock::new(None);
+
+// History entries for these instructions never produce a filesystem layer
+const METADATA_INSTRUCTIONS: [&str; 11] = [
+    "ENV",
+    "LABEL",
+    "CMD",
+    "ENTRYPOINT",
+    "EXPOSE",
+    "ARG",
+    "USER",
+    "VOLUME",
+    "STOPSIGNAL",
+    "HEALTHCHECK",
+    "SHELL",
+];
+
+// Why a docker command failed, sorted where docker reports it
+#[derive(Debug, Clone)]
+pub enum DockerError {
+    // The docker CLI is missing or can't reach the daemon
+    Unavailable(String),
+    // The user can't use the docker socket
+    PermissionDenied(String),
+    ImageNotFound { image: String, message: String },
+    Failed(String),
+}
+
+impl DockerError {
+    pub fn message(&self) -> &str {
+        match self {
+            DockerError::Unavailable(message)
+            | DockerError::PermissionDenied(message)
+            | DockerError::ImageNotFound { message, .. }
+            | DockerError::Failed(message) => message,
+        }
+    }
+
+    // The docker binary couldn't be started at all
+    pub fn spawn(context: &str, error: io::Error) -> Self {
+        let message = format!("{}: {}", context, error);
+        match error.kind() {
+            io::ErrorKind::NotFound => DockerError::Unavailable(message),
+            io::ErrorKind::PermissionDenied => DockerError::PermissionDenied(message),
+            _ => DockerError::Failed(message),
+        }
+    }
+
+    // A docker command that exited with an error, told apart by what docker
+    // wrote to stderr. `image` is the image the command was about, if any.
+    pub fn output(context: &str, image: Option<&str>, stderr: &[u8]) -> Self {
+        let stderr = String::from_utf8_lossy(stderr);
+        let message = format!("{}: {}", context, stderr.trim());
+        let lower = stderr.to_lowercase();
+        if lower.contains("cannot connect to the docker daemon")
+            || lower.contains("is the docker daemon running")
+            || lower.contains("error during connect")
+        {
+            DockerError::Unavailable(message)
+        } else if lower.contains("permission denied") && lower.contains("docker daemon socket") {
+            DockerError::PermissionDenied(message)
+        } else if let Some(image) =
+            image.filter(|_| lower.contains("no such image") || lower.contains("no such object"))
+        {
+            DockerError::ImageNotFound {
+                image: image.to_string(),
+                message,
+            }
+        } else {
+            DockerError::Failed(message)
+        }
+    }
+}
+
+impl fmt::Display for DockerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for DockerError {}
+
+// Callers that only report the error keep working with strings
+impl From<DockerError> for String {
+    fn from(error: DockerError) -> Self {
+        error.message().to_string()
+    }
+}
+
+// Overrides the DOCKER_HOST the app was started with, None goes back to it
+pub fn set_docker_host(host: Option<String>) {
+    *DOCKER_HOST.write().unwrap() = host;
+}
+
+// DOCKER_HOST docker commands run with, None for the default socket
+pub fn docker_host() -> Option<String> {
+    DOCKER_HOST
+        .read()
+        .unwrap()
+        .clone()
+        .or_else(|| std::env::var("DOCKER_HOST").ok())
+}
+
+// `Command::new("docker")` pointed at the configured host
+pub fn docker_command() -> Command {
+    let mut command = Command::new("docker");
+    if let Some(host) = DOCKER_HOST.read().unwrap().as_deref() {
+        command.env("DOCKER_HOST", host);
+    }
+    command
+}
+
+// Runs a docker command, failing with the reason docker gave
+fn run_docker(args: &[&str], context: &str, image: Option<&str>) -> Result<Output, DockerError> {
+    let output = docker_command()
+        .args(args)
+        .output()
+        .map_err(|e| DockerError::spawn(context, e))?;
+    if !output.status.success() {
+        return Err(DockerError::output(context, image, &output.stderr));
+    }
+    Ok(output)
+}
+
+// A single entry of `docker history`, newest first
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub created: String,
+    pub size: String,
+    pub size_bytes: u64,
+    pub created_by: String,
+}
+
+pub fn get_image_history(image: &str) -> Result<Vec<HistoryEntry>, DockerError> {
+    let history_output = run_docker(
+        &[
+            "history",
+            image,
+            "--no-trunc",
+            "--format",
+            "{{.ID}}|{{.CreatedAt}}|{{.Size}}|{{.CreatedBy}}",
+        ],
+        "Failed to get image history",
+        Some(image),
+    )?;
+
+    Ok(String::from_utf8_lossy(&history_output.stdout)
+        .lines()
+        .filter_map(|line| {
+            // CreatedBy is last since it may itself contain '|'
+            let parts: Vec<&str> = line.splitn(4, '|').collect();
+            if parts.len() < 4 {
+                return None;
+            }
+            Some(HistoryEntry {
+                id: parts[0].to_string(),
+                created: parts[1].to_string(),
+                size: parts[2].to_string(),
+                size_bytes: parse_docker_size(parts[2]),
+                created_by: parts[3].to_string(),
+            })
+        })
+        .collect())
+}
+
+// Convert docker's human readable sizes ("77.8MB", "1.23kB") to bytes
+pub fn parse_docker_size(size: &str) -> u64 {
+    let size = size.trim();
+    let split_at = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split_at);
+    let number = number.parse::<f64>().unwrap_or(0.0);
+
+    // Docker uses decimal (SI) units for display
+    let multiplier = match unit.trim().to_uppercase().as_str() {
+        "KB" => 1_000.0,
+        "MB" => 1_000_000.0,
+        "GB" => 1_000_000_000.0,
+        "TB" => 1_000_000_000_000.0,
+        _ => 1.0,
+    };
+
+    (number * multiplier) as u64
+}
+
+pub fn inspect_image(image: &str) -> Result<serde_json::Value, DockerError> {
+    let mut inspect = inspect_images(&[image.to_string()])?;
+    if inspect.is_empty() {
+        return Err(DockerError::ImageNotFound {
+            image: image.to_string(),
+            message: format!("No image found with name: {}", image),
+        });
+    }
+    Ok(inspect.remove(0))
+}
+
+// `docker image inspect` of several images in one call, in the same order
+pub fn inspect_images(images: &[String]) -> Result<Vec<serde_json::Value>, DockerError> {
+    if images.is_empty() {
+        return Ok(Vec::new());
+    }
+    let image = match images {
+        [image] => Some(image.as_str()),
+        _ => None,
+    };
+    let context = match image {
+        Some(image) => format!("Failed to inspect image {}", image),
+        None => "Failed to inspect images".to_string(),
+    };
+    let mut args = vec!["image", "inspect"];
+    args.extend(images.iter().map(String::as_str));
+    let output = run_docker(&args, &context, image)?;
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| DockerError::Failed(format!("Failed to parse docker inspect output: {}", e)))
+}
+
+// Full IDs of every local image
+pub fn list_image_ids() -> Result<Vec<String>, DockerError> {
+    let output = run_docker(
+        &["image", "ls", "-q", "--no-trunc"],
+        "Failed to list images",
+        None,
+    )?;
+    let mut ids: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    // An image with several tags is listed once per tag
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+// Get the RootFS diff IDs of an image, base layer first
+pub fn image_diff_ids(image: &str) -> Result<Vec<String>, DockerError> {
+    let context = format!("Failed to inspect image {}", image);
+    let output = run_docker(
+        &[
+            "image",
+            "inspect",
+            image,
+            "--format",
+            "{{json .RootFS.Layers}}",
+        ],
+        &context,
+        Some(image),
+    )?;
+
+    serde_json::from_slice(&output.stdout).map_err(|e| {
+        DockerError::Failed(format!("Failed to parse layers of image {}: {}", image, e))
+    })
+}
+
+// One history entry of an image, told apart from the RootFS layer it produced
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImageLayer {
+    // RootFS diff ID, or the history ID (usually "<missing>") for entries that
+    // didn't change the filesystem
+    pub id: String,
+    pub created_by: String,
+    pub created: String,
+    pub size: String,
+    pub size_bytes: u64,
+    // Metadata only, see is_empty_history_entry
+    pub empty: bool,
+}
+
+// History entries newest first, like `docker history`. Entries that changed
+// the filesystem are paired with their RootFS diff ID oldest first.
+pub fn history_layers(history: &[HistoryEntry], diff_ids: &[String]) -> Vec<ImageLayer> {
+    let mut remaining_diff_ids = diff_ids.iter();
+    let mut layers: Vec<ImageLayer> = history
+        .iter()
+        .rev()
+        .map(|entry| {
+            let empty = is_empty_history_entry(&entry.created_by, entry.size_bytes);
+            let id = if empty {
+                entry.id.clone()
+            } else {
+                remaining_diff_ids
+                    .next()
+                    .cloned()
+                    .unwrap_or_else(|| entry.id.clone())
+            };
+            ImageLayer {
+                id,
+                created_by: entry.created_by.clone(),
+                created: entry.created.clone(),
+                size: entry.size.clone(),
+                size_bytes: entry.size_bytes,
+                empty,
+            }
+        })
+        .collect();
+    layers.reverse();
+    layers
+}
+
+// Layers of a local image with the history entry that created each, newest
+// first
+pub fn image_layers(image: &str) -> Result<Vec<ImageLayer>, DockerError> {
+    let history = get_image_history(image)?;
+    let diff_ids = image_diff_ids(image)?;
+    Ok(history_layers(&history, &diff_ids))
+}
+
+// Whether a history entry is metadata only, i.e. has no layer in RootFS
+pub fn is_empty_history_entry(created_by: &str, size: u64) -> bool {
+    if size > 0 {
+        return false;
+    }
+
+    let command = created_by
+        .trim()
+        .trim_start_matches("/bin/sh -c #(nop)")
+        .trim();
+    let instruction = command.split_whitespace().next().unwrap_or("");
+    METADATA_INSTRUCTIONS.contains(&instruction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, size_bytes: u64, created_by: &str) -> HistoryEntry {
+        HistoryEntry {
+            id: id.to_string(),
+            created: String::new(),
+            size: format!("{}B", size_bytes),
+            size_bytes,
+            created_by: created_by.to_string(),
+        }
+    }
+
+    #[test]
+    fn history_is_paired_with_diff_ids_oldest_first() {
+        // Newest first, as docker history lists them
+        let history = [
+            entry("<missing>", 0, "CMD [\"nginx\"]"),
+            entry("<missing>", 2_000, "COPY app /app # buildkit"),
+            entry("<missing>", 0, "ENV PATH=/usr/bin"),
+            entry("<missing>", 0, "RUN /bin/sh -c mkdir /data # buildkit"),
+            entry("<missing>", 5_000, "/bin/sh -c #(nop) ADD file:abc in / "),
+        ];
+        let diff_ids = ["sha256:base", "sha256:mkdir", "sha256:app"].map(String::from);
+
+        let layers = history_layers(&history, &diff_ids);
+        let ids: Vec<&str> = layers.iter().map(|layer| layer.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "<missing>",
+                "sha256:app",
+                "<missing>",
+                "sha256:mkdir",
+                "sha256:base"
+            ]
+        );
+        // A RUN that wrote nothing still has a layer
+        assert!(!layers[3].empty);
+        assert!(layers[2].empty);
+        assert_eq!(layers[1].size_bytes, 2_000);
+        assert_eq!(layers[1].created_by, "COPY app /app # buildkit");
+    }
+
+    #[test]
+    fn history_without_diff_ids_keeps_history_ids() {
+        let history = [entry("sha256:abc", 1_000, "RUN make")];
+        let layers = history_layers(&history, &[]);
+        assert_eq!(layers[0].id, "sha256:abc");
+    }
+
+    #[test]
+    fn docker_failures_are_sorted_by_stderr() {
+        let unavailable = b"Cannot connect to the Docker daemon at unix:///var/run/docker.sock. Is the docker daemon running?";
+        assert!(matches!(
+            DockerError::output("Failed", Some("nginx"), unavailable),
+            DockerError::Unavailable(_)
+        ));
+
+        let socket = b"permission denied while trying to connect to the Docker daemon socket";
+        assert!(matches!(
+            DockerError::output("Failed", None, socket),
+            DockerError::PermissionDenied(_)
+        ));
+
+        match DockerError::output("Failed", Some("nginx:1"), b"Error: No such image: nginx:1") {
+            DockerError::ImageNotFound { image, message } => {
+                assert_eq!(image, "nginx:1");
+                assert_eq!(message, "Failed: Error: No such image: nginx:1");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Without an image there is nothing that could be missing
+        assert!(matches!(
+            DockerError::output("Failed", None, b"No such image: nginx"),
+            DockerError::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn missing_docker_cli_is_unavailable() {
+        let error = io::Error::new(io::ErrorKind::NotFound, "No such file or directory");
+        assert!(matches!(
+            DockerError::spawn("Failed to run docker", error),
+            DockerError::Unavailable(_)
+        ));
+    }
+}
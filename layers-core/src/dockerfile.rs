@@ -0,0 +1,793 @@
+use serde::{Deserialize, Serialize};
+
+use crate::docker::{MAX_LAYERS, WARN_LAYERS};
+
+// RUN instructions longer than this are hard to review and to cache well
+const MAX_RUN_LENGTH: usize = 2000;
+// Commands chained with && in one RUN before it is worth splitting
+const MAX_RUN_COMMANDS: usize = 30;
+
+// Every instruction docker knows, used to tell a Dockerfile from other text
+const KNOWN_INSTRUCTIONS: [&str; 18] = [
+    "FROM",
+    "RUN",
+    "CMD",
+    "LABEL",
+    "MAINTAINER",
+    "EXPOSE",
+    "ENV",
+    "ADD",
+    "COPY",
+    "ENTRYPOINT",
+    "VOLUME",
+    "USER",
+    "WORKDIR",
+    "ARG",
+    "ONBUILD",
+    "STOPSIGNAL",
+    "HEALTHCHECK",
+    "SHELL",
+];
+
+// Instructions that add a filesystem layer to the image
+const LAYER_INSTRUCTIONS: [&str; 3] = ["RUN", "COPY", "ADD"];
+
+// Manifests and lockfiles change far less often than the sources next to them
+const DEPENDENCY_MANIFESTS: [&str; 12] = [
+    "package.json",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "requirements.txt",
+    "poetry.lock",
+    "Pipfile",
+    "go.mod",
+    "go.sum",
+    "Cargo.toml",
+    "Cargo.lock",
+    "Gemfile",
+];
+// Build args named like this usually get a new value on every build
+const VOLATILE_ARG_HINTS: [&str; 6] = ["DATE", "TIME", "COMMIT", "SHA", "BUILD", "REVISION"];
+const PACKAGE_INSTALLS: [&str; 5] = [
+    "apt-get install",
+    "apt install",
+    "apk add",
+    "yum install",
+    "dnf install",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DockerfileInstruction {
+    pub instruction: String,
+    pub arguments: String,
+    // Line the instruction starts on, continuation lines are folded into it
+    pub line_number: usize,
+}
+
+// One FROM and the instructions up to the next one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildStage {
+    pub index: usize,
+    // The name given with "FROM ... AS name"
+    pub name: Option<String>,
+    // Image or earlier stage the stage starts from
+    pub base: String,
+    // Index of the stage `base` refers to, None for an image
+    pub base_stage: Option<usize>,
+    pub line_number: usize,
+    // Without the FROM itself
+    pub instructions: Vec<DockerfileInstruction>,
+    // Earlier stages this one copies files out of with COPY --from
+    pub copies_from: Vec<usize>,
+    // Images copied from directly, e.g. COPY --from=nginx:latest
+    pub copies_from_images: Vec<String>,
+    // Whether any of its files end up in the final image, the rest are
+    // builder-only stages
+    pub in_final_image: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Dockerfile {
+    pub instructions: Vec<DockerfileInstruction>,
+    // Base of the last FROM, see `stages` for the others
+    pub base_image: Option<String>,
+    pub stages: Vec<BuildStage>,
+}
+
+// How likely an instruction's layer comes from the build cache
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStability {
+    pub line_number: usize,
+    pub instruction: String,
+    // 0.0 rebuilt on nearly every build, 1.0 practically always cached.
+    // Includes the instructions before it, a miss rebuilds everything after.
+    pub score: f32,
+    pub reasons: Vec<String>,
+}
+
+impl Dockerfile {
+    pub fn parse(content: &str) -> Self {
+        let mut instructions = Vec::new();
+        let mut base_image = None;
+
+        let mut current_instruction = String::new();
+        let mut current_args = String::new();
+        let mut start_line = 0;
+        let mut in_multiline = false;
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            // Skip empty lines and comments
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if in_multiline {
+                if let Some(continued) = line.strip_suffix('\\') {
+                    // Drop the trailing backslash and keep collecting
+                    current_args.push_str(continued.trim_end());
+                    current_args.push(' ');
+                    continue;
+                }
+
+                current_args.push_str(line);
+                in_multiline = false;
+
+                // Check if this is the FROM instruction to extract base image
+                if current_instruction == "FROM" {
+                    base_image = Some(current_args.trim().to_string());
+                }
+
+                instructions.push(DockerfileInstruction {
+                    instruction: std::mem::take(&mut current_instruction),
+                    arguments: std::mem::take(&mut current_args).trim().to_string(),
+                    line_number: start_line,
+                });
+            } else {
+                let parts: Vec<&str> = line.splitn(2, char::is_whitespace).collect();
+                if parts.len() < 2 {
+                    continue;
+                }
+
+                let instruction = parts[0].to_uppercase();
+                let args = parts[1].trim();
+
+                if let Some(continued) = args.strip_suffix('\\') {
+                    in_multiline = true;
+                    start_line = i + 1;
+                    current_instruction = instruction;
+                    current_args = continued.trim_end().to_string() + " ";
+                } else {
+                    // Check if this is the FROM instruction to extract base image
+                    if instruction == "FROM" {
+                        base_image = Some(args.to_string());
+                    }
+
+                    instructions.push(DockerfileInstruction {
+                        instruction,
+                        arguments: args.to_string(),
+                        line_number: i + 1,
+                    });
+                }
+            }
+        }
+
+        // A trailing backslash on the last line still ends the instruction
+        if in_multiline && !current_instruction.is_empty() {
+            instructions.push(DockerfileInstruction {
+                instruction: current_instruction,
+                arguments: current_args.trim().to_string(),
+                line_number: start_line,
+            });
+        }
+
+        Dockerfile {
+            stages: build_stages(&instructions),
+            instructions,
+            base_image,
+        }
+    }
+
+    // Whether pasted text is a Dockerfile rather than prose or a script that
+    // happens to mention FROM
+    pub fn looks_like_dockerfile(content: &str) -> bool {
+        let dockerfile = Dockerfile::parse(content);
+        if dockerfile.stages.is_empty() || dockerfile.instructions.len() < 2 {
+            return false;
+        }
+        let known = dockerfile
+            .instructions
+            .iter()
+            .filter(|i| KNOWN_INSTRUCTIONS.contains(&i.instruction.as_str()))
+            .count();
+        known * 10 >= dockerfile.instructions.len() * 9
+    }
+
+    pub fn final_stage(&self) -> Option<&BuildStage> {
+        self.stages.last()
+    }
+
+    // Stages nothing in the final image comes from, e.g. compiler stages
+    pub fn builder_only_stages(&self) -> Vec<&BuildStage> {
+        self.stages.iter().filter(|s| !s.in_final_image).collect()
+    }
+
+    // Instructions of the last stage, i.e. the ones that end up in the built image
+    pub fn final_stage_instructions(&self) -> &[DockerfileInstruction] {
+        match self.final_stage() {
+            Some(stage) => &stage.instructions,
+            None => &self.instructions,
+        }
+    }
+
+    // Layers the final stage adds on top of its base image
+    pub fn estimated_layers(&self) -> usize {
+        self.final_stage_instructions()
+            .iter()
+            .filter(|i| LAYER_INSTRUCTIONS.contains(&i.instruction.as_str()))
+            .count()
+    }
+
+    // Warning when the base image plus this Dockerfile get close to the layer
+    // limit, as (title, description)
+    pub fn layer_limit_suggestion(&self, base_layers: usize) -> Option<(String, String)> {
+        let added = self.estimated_layers();
+        let total = base_layers + added;
+        if total < WARN_LAYERS {
+            return None;
+        }
+
+        let runs: Vec<String> = self
+            .final_stage_instructions()
+            .iter()
+            .filter(|i| i.instruction == "RUN")
+            .map(|i| i.line_number.to_string())
+            .collect();
+        let merge = if runs.len() > 1 {
+            format!(
+                "Merge the RUN instructions on lines {} into fewer layers.",
+                runs.join(", ")
+            )
+        } else {
+            "Squash the base image or start from one with fewer layers.".to_string()
+        };
+        let title = if total > MAX_LAYERS {
+            format!("Exceeds the {} layer limit", MAX_LAYERS)
+        } else {
+            format!("Close to the {} layer limit", MAX_LAYERS)
+        };
+        Some((
+            title,
+            format!(
+                "The image will have about {} layers ({} from the base image, {} from this Dockerfile). {}",
+                total, base_layers, added, merge
+            ),
+        ))
+    }
+
+    // What each instruction does to the image, as (line, instruction, impact)
+    pub fn analyze_layer_impact(&self) -> Vec<(usize, String, String)> {
+        self.instructions
+            .iter()
+            .map(|instruction| {
+                let arguments = &instruction.arguments;
+                let impact = match instruction.instruction.as_str() {
+                    "FROM" => format!(
+                        "Base image: {}. Starts a new stage from its layers.",
+                        arguments
+                    ),
+                    "RUN" => format!("Creates a new layer with changes from: {}", arguments),
+                    "COPY" | "ADD" => format!("Creates a new layer with files: {}", arguments),
+                    "ENV" | "LABEL" | "WORKDIR" | "USER" | "EXPOSE" | "VOLUME" | "ENTRYPOINT"
+                    | "CMD" | "ARG" | "HEALTHCHECK" | "SHELL" | "STOPSIGNAL" | "ONBUILD" => {
+                        format!("Metadata change only, no new layer: {}", arguments)
+                    }
+                    _ => format!("Unknown instruction: {}", arguments),
+                };
+                (
+                    instruction.line_number,
+                    instruction.instruction.clone(),
+                    impact,
+                )
+            })
+            .collect()
+    }
+
+    // Suggestions as (title, description), checked against the final stage
+    // since earlier stages don't end up in the image
+    pub fn optimize_suggestions(&self) -> Vec<(String, String)> {
+        let mut suggestions = Vec::new();
+        let instructions = self.final_stage_instructions();
+
+        // Runs of consecutive RUN instructions that could share a layer
+        let mut consecutive_runs = Vec::new();
+        let mut current_run: Vec<usize> = Vec::new();
+        for instruction in instructions {
+            if instruction.instruction == "RUN" {
+                current_run.push(instruction.line_number);
+            } else if !current_run.is_empty() {
+                consecutive_runs.push(std::mem::take(&mut current_run));
+            }
+        }
+        consecutive_runs.push(current_run);
+        for lines in consecutive_runs.iter().filter(|lines| lines.len() > 1) {
+            let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+            suggestions.push((
+                format!("Lines {}: Combine RUN instructions", lines.join(", ")),
+                format!(
+                    "{} consecutive RUN instructions each create a layer. Combine them with && to reduce layers.",
+                    lines.len()
+                ),
+            ));
+        }
+
+        for instruction in instructions {
+            let arguments = &instruction.arguments;
+            match instruction.instruction.as_str() {
+                "RUN"
+                    if arguments.contains("apt-get install")
+                        && !arguments.contains("rm -rf /var/lib/apt/lists") =>
+                {
+                    suggestions.push((
+                        format!("Line {}: Missing cleanup", instruction.line_number),
+                        "apt-get install without cleanup. Add 'rm -rf /var/lib/apt/lists/*' in the same RUN to reduce layer size.".to_string(),
+                    ));
+                }
+                "RUN" if arguments.contains("apk add") && !arguments.contains("--no-cache") => {
+                    suggestions.push((
+                        format!("Line {}: apk cache kept", instruction.line_number),
+                        "apk add without --no-cache leaves the package index in the layer."
+                            .to_string(),
+                    ));
+                }
+                "RUN"
+                    if arguments.contains("pip install")
+                        && !arguments.contains("--no-cache-dir") =>
+                {
+                    suggestions.push((
+                        format!("Line {}: pip cache kept", instruction.line_number),
+                        "pip install without --no-cache-dir keeps downloaded packages in the layer.".to_string(),
+                    ));
+                }
+                "RUN" if arguments.len() > MAX_RUN_LENGTH => {
+                    suggestions.push((
+                        format!("Line {}: Very long RUN", instruction.line_number),
+                        format!(
+                            "This RUN is {} characters long. Move the commands into a script and COPY it in, or split unrelated steps into their own RUN.",
+                            arguments.len()
+                        ),
+                    ));
+                }
+                "RUN" if arguments.matches("&&").count() + 1 > MAX_RUN_COMMANDS => {
+                    suggestions.push((
+                        format!("Line {}: Too many chained commands", instruction.line_number),
+                        format!(
+                            "This RUN chains {} commands. Split steps that change at different rates so edits don't rebuild all of them.",
+                            arguments.matches("&&").count() + 1
+                        ),
+                    ));
+                }
+                "ADD" if !arguments.contains("://") && !is_archive(arguments) => {
+                    suggestions.push((
+                        format!("Line {}: Prefer COPY", instruction.line_number),
+                        "ADD of local files behaves like COPY with extra magic. Use COPY unless you need URL or archive handling.".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        // Copying the whole context before installing dependencies means any
+        // source change invalidates the dependency layer
+        let copy_all = instructions
+            .iter()
+            .position(|i| i.instruction == "COPY" && copies_whole_context(&i.arguments));
+        if let Some(copy_index) = copy_all {
+            if instructions[copy_index..]
+                .iter()
+                .any(|i| i.instruction == "RUN" && installs_dependencies(&i.arguments))
+            {
+                suggestions.push((
+                    format!("Line {}: Dependency caching", instructions[copy_index].line_number),
+                    "Dependencies are installed after copying the whole build context. Copy only the manifest files first, install, then copy the rest to keep the dependency layer cached.".to_string(),
+                ));
+            }
+        }
+
+        // Volatile build args used early rebuild everything after them
+        let mut build_args: Vec<&str> = Vec::new();
+        for (index, instruction) in instructions.iter().enumerate() {
+            if instruction.instruction == "ARG" {
+                if let Some(name) = instruction.arguments.split(['=', ' ']).next() {
+                    build_args.push(name.trim());
+                }
+                continue;
+            }
+            let remaining_layers = instructions[index + 1..]
+                .iter()
+                .filter(|i| LAYER_INSTRUCTIONS.contains(&i.instruction.as_str()))
+                .count();
+            let early_args: Vec<&str> = build_args
+                .iter()
+                .filter(|name| {
+                    uses_variable(&instruction.arguments, name)
+                        && VOLATILE_ARG_HINTS
+                            .iter()
+                            .any(|hint| name.to_uppercase().contains(hint))
+                })
+                .copied()
+                .collect();
+            if !early_args.is_empty() && remaining_layers > 0 {
+                suggestions.push((
+                    format!("Line {}: Cache busting build arg", instruction.line_number),
+                    format!(
+                        "{} likely changes on every build and invalidates the {} layers after this line. Use it as late as possible, e.g. in a LABEL at the end.",
+                        early_args.join(", "),
+                        remaining_layers
+                    ),
+                ));
+                build_args.retain(|name| !early_args.contains(name));
+            }
+        }
+
+        for instruction in instructions.iter().filter(|i| i.instruction == "RUN") {
+            if let Some(packages) = unsorted_packages(&instruction.arguments) {
+                let mut sorted = packages.clone();
+                sorted.sort();
+                suggestions.push((
+                    format!("Line {}: Sort packages", instruction.line_number),
+                    format!(
+                        "Listing packages alphabetically ({}) keeps diffs small and avoids cache misses from reordering.",
+                        sorted.join(" ")
+                    ),
+                ));
+            }
+        }
+
+        if let Some(base_image) = self.final_base_image() {
+            let name = base_image.rsplit('/').next().unwrap_or(base_image);
+            let unpinned =
+                (!name.contains(':') && !name.contains('@')) || name.ends_with(":latest");
+            if unpinned && base_image != "scratch" && !self.is_stage_name(base_image) {
+                suggestions.push((
+                    "Unpinned base image".to_string(),
+                    format!(
+                        "{} follows the latest tag, builds may change underneath you. Pin a version or digest.",
+                        base_image
+                    ),
+                ));
+            }
+        }
+
+        if !instructions.iter().any(|i| i.instruction == "USER") {
+            suggestions.push((
+                "Runs as root".to_string(),
+                "The final stage sets no USER, containers will run as root.".to_string(),
+            ));
+        }
+
+        // Stages the final image neither starts from nor copies out of.
+        // BuildKit skips them, the legacy builder still builds them.
+        let final_index = self.stages.len().saturating_sub(1);
+        for stage in &self.stages {
+            let used = stage.index == final_index
+                || self.stages.iter().any(|other| {
+                    other.base_stage == Some(stage.index)
+                        || other.copies_from.contains(&stage.index)
+                });
+            if !used {
+                suggestions.push((
+                    format!("Line {}: Unused stage", stage.line_number),
+                    format!(
+                        "Stage {} isn't used by any other stage. Remove it, or build it with --target if it's meant to be built on its own.",
+                        stage.name.clone().unwrap_or_else(|| stage.index.to_string())
+                    ),
+                ));
+            }
+        }
+
+        let stages = self.stages.len();
+        let has_build_step = instructions
+            .iter()
+            .any(|i| i.instruction == "RUN" && is_build_command(&i.arguments));
+        if stages <= 1 && has_build_step {
+            suggestions.push((
+                "Use multi-stage builds".to_string(),
+                "The image compiles in its final stage, so compilers and build caches ship with it. Build in a separate stage and copy only the output.".to_string(),
+            ));
+        }
+
+        suggestions
+    }
+
+    // Estimated cache stability of every instruction, in file order
+    pub fn cache_stability(&self) -> Vec<CacheStability> {
+        let mut result = Vec::new();
+        let mut stage_score = 1.0f32;
+        let mut stage_breaker: Option<usize> = None;
+        let mut build_args: Vec<String> = Vec::new();
+
+        for instruction in &self.instructions {
+            let arguments = &instruction.arguments;
+            let mut score = 1.0f32;
+            let mut reasons = Vec::new();
+
+            match instruction.instruction.as_str() {
+                "FROM" => {
+                    // Each stage starts from its base image's cache again
+                    stage_score = 1.0;
+                    stage_breaker = None;
+                    build_args.clear();
+                }
+                "ARG" => {
+                    if let Some(name) = arguments.split(['=', ' ']).next() {
+                        build_args.push(name.trim().to_string());
+                    }
+                }
+                "COPY" | "ADD" if copies_whole_context(arguments) => {
+                    score = 0.2;
+                    reasons.push(
+                        "Copies the whole build context, any changed file invalidates it"
+                            .to_string(),
+                    );
+                }
+                "ADD" if arguments.contains("://") => {
+                    score = 0.5;
+                    reasons.push(
+                        "Downloads a URL, invalidated when the remote file changes".to_string(),
+                    );
+                }
+                "COPY" | "ADD" if copies_only_manifests(arguments) => {
+                    score = 0.85;
+                    reasons.push("Copies dependency manifests, which change rarely".to_string());
+                }
+                "COPY" | "ADD" => {
+                    score = 0.6;
+                    reasons.push("Invalidated when the copied files change".to_string());
+                }
+                "RUN" => {
+                    if let Some(packages) = unsorted_packages(arguments) {
+                        score = score.min(0.9);
+                        reasons.push(format!(
+                            "Packages aren't sorted ({}), reordering them busts the cache",
+                            packages.join(" ")
+                        ));
+                    }
+                }
+                _ => {}
+            }
+
+            // A different build arg value invalidates the first instruction using it
+            if instruction.instruction != "ARG" && instruction.instruction != "FROM" {
+                for name in build_args
+                    .iter()
+                    .filter(|name| uses_variable(arguments, name))
+                {
+                    let volatile = VOLATILE_ARG_HINTS
+                        .iter()
+                        .any(|hint| name.to_uppercase().contains(hint));
+                    score = score.min(if volatile { 0.2 } else { 0.7 });
+                    reasons.push(format!(
+                        "Uses build arg {}, a different value rebuilds this and every later layer",
+                        name
+                    ));
+                }
+            }
+
+            if stage_score < score {
+                if let Some(line) = stage_breaker {
+                    reasons.push(format!("Rebuilt whenever line {} is", line));
+                }
+            }
+            if score < stage_score {
+                stage_score = score;
+                stage_breaker = Some(instruction.line_number);
+            }
+            result.push(CacheStability {
+                line_number: instruction.line_number,
+                instruction: instruction.instruction.clone(),
+                score: stage_score,
+                reasons,
+            });
+        }
+        result
+    }
+
+    // Whether a FROM refers to an earlier stage rather than an image
+    fn is_stage_name(&self, name: &str) -> bool {
+        self.stages
+            .iter()
+            .filter_map(|stage| stage.name.as_deref())
+            .any(|stage| stage.eq_ignore_ascii_case(name))
+    }
+
+    // Image the final stage starts from, None for scratch or an earlier stage
+    pub fn base_image_reference(&self) -> Option<&str> {
+        self.final_base_image()
+            .filter(|base| *base != "scratch" && !self.is_stage_name(base))
+    }
+
+    // Image the final stage starts from, without "AS name" or flags
+    fn final_base_image(&self) -> Option<&str> {
+        let from = self
+            .instructions
+            .iter()
+            .rev()
+            .find(|i| i.instruction == "FROM")?;
+        from.arguments
+            .split_whitespace()
+            .find(|part| !part.starts_with("--"))
+    }
+}
+
+fn is_archive(arguments: &str) -> bool {
+    [".tar", ".tar.gz", ".tgz", ".tar.bz2", ".tar.xz"]
+        .iter()
+        .any(|extension| arguments.contains(extension))
+}
+
+pub(crate) fn copies_whole_context(arguments: &str) -> bool {
+    let sources: Vec<&str> = arguments
+        .split_whitespace()
+        .filter(|part| !part.starts_with("--"))
+        .collect();
+    sources.len() >= 2
+        && sources[..sources.len() - 1]
+            .iter()
+            .any(|s| *s == "." || *s == "./")
+}
+
+pub(crate) fn installs_dependencies(arguments: &str) -> bool {
+    [
+        "npm install",
+        "npm ci",
+        "yarn install",
+        "pnpm install",
+        "pip install",
+        "poetry install",
+        "bundle install",
+        "go mod download",
+        "composer install",
+    ]
+    .iter()
+    .any(|command| arguments.contains(command))
+}
+
+pub(crate) fn is_build_command(arguments: &str) -> bool {
+    [
+        "go build",
+        "cargo build",
+        "npm run build",
+        "yarn build",
+        "mvn package",
+        "gradle build",
+        "make",
+        "gcc ",
+    ]
+    .iter()
+    .any(|command| arguments.contains(command))
+}
+
+pub(crate) fn copies_only_manifests(arguments: &str) -> bool {
+    let sources: Vec<&str> = arguments
+        .split_whitespace()
+        .filter(|part| !part.starts_with("--"))
+        .collect();
+    sources.len() >= 2
+        && sources[..sources.len() - 1].iter().all(|source| {
+            let file = source.rsplit('/').next().unwrap_or(source);
+            DEPENDENCY_MANIFESTS.contains(&file)
+        })
+}
+
+// $NAME or ${NAME}, not $NAME_SUFFIX
+fn uses_variable(arguments: &str, name: &str) -> bool {
+    if name.is_empty() {
+        return false;
+    }
+    arguments.contains(&format!("${{{}}}", name))
+        || arguments
+            .match_indices(&format!("${}", name))
+            .any(|(i, m)| {
+                !arguments[i + m.len()..]
+                    .starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+            })
+}
+
+// Packages of the first install command, if they aren't in alphabetical order
+fn unsorted_packages(arguments: &str) -> Option<Vec<String>> {
+    let (_, rest) = PACKAGE_INSTALLS
+        .iter()
+        .find_map(|command| arguments.split_once(command))?;
+    let packages: Vec<String> = rest
+        .split(['&', ';', '|'])
+        .next()
+        .unwrap_or("")
+        .split_whitespace()
+        .filter(|part| !part.starts_with('-'))
+        .map(|part| part.to_string())
+        .collect();
+    let sorted = packages.windows(2).all(|pair| pair[0] <= pair[1]);
+    (!sorted).then_some(packages)
+}
+
+// Split the instructions into stages and work out which reach the final image
+fn build_stages(instructions: &[DockerfileInstruction]) -> Vec<BuildStage> {
+    let mut stages: Vec<BuildStage> = Vec::new();
+    for instruction in instructions {
+        if instruction.instruction == "FROM" {
+            let parts: Vec<&str> = instruction
+                .arguments
+                .split_whitespace()
+                .filter(|part| !part.starts_with("--"))
+                .collect();
+            let base = parts.first().copied().unwrap_or_default().to_string();
+            let name = parts
+                .iter()
+                .position(|part| part.eq_ignore_ascii_case("as"))
+                .and_then(|as_index| parts.get(as_index + 1))
+                .map(|name| name.to_string());
+            stages.push(BuildStage {
+                index: stages.len(),
+                base_stage: find_stage(&stages, &base),
+                name,
+                base,
+                line_number: instruction.line_number,
+                instructions: Vec::new(),
+                copies_from: Vec::new(),
+                copies_from_images: Vec::new(),
+                in_final_image: false,
+            });
+            continue;
+        }
+
+        // ARGs before the first FROM only parameterize the FROM lines
+        let Some(stage_index) = stages.len().checked_sub(1) else {
+            continue;
+        };
+        if instruction.instruction == "COPY" || instruction.instruction == "ADD" {
+            let source = instruction
+                .arguments
+                .split_whitespace()
+                .find_map(|part| part.strip_prefix("--from="));
+            if let Some(source) = source {
+                match find_stage(&stages[..stage_index], source) {
+                    Some(index) => stages[stage_index].copies_from.push(index),
+                    None => stages[stage_index]
+                        .copies_from_images
+                        .push(source.to_string()),
+                }
+            }
+        }
+        stages[stage_index].instructions.push(instruction.clone());
+    }
+
+    // Walk back from the final stage through everything it builds on
+    let mut pending: Vec<usize> = stages.len().checked_sub(1).into_iter().collect();
+    while let Some(index) = pending.pop() {
+        if stages[index].in_final_image {
+            continue;
+        }
+        stages[index].in_final_image = true;
+        pending.extend(stages[index].base_stage);
+        pending.extend(stages[index].copies_from.iter().copied());
+    }
+    stages
+}
+
+// An earlier stage by name or index, as FROM and COPY --from refer to them
+fn find_stage(stages: &[BuildStage], reference: &str) -> Option<usize> {
+    if let Ok(index) = reference.parse::<usize>() {
+        return (index < stages.len()).then_some(index);
+    }
+    stages.iter().rposition(|stage| {
+        stage
+            .name
+            .as_deref()
+            .is_some_and(|name| name.eq_ignore_ascii_case(reference))
+    })
+}
@@ -0,0 +1,322 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+// Marks a path deleted by a layer, e.g. "etc/.wh.passwd"
+pub const WHITEOUT_PREFIX: &str = ".wh.";
+// Hides everything lower layers put in its directory
+pub const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+// Path of a tar entry without the leading "./" or "/"
+pub fn entry_relative_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Raw bytes of a path. Tar names don't have to be UTF-8 and may contain
+// newlines, so they are kept as bytes until they're shown.
+#[cfg(unix)]
+pub fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+pub fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).to_string())
+}
+
+fn escape_char(c: char, out: &mut String) {
+    match c {
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        c if c.is_control() && (c as u32) < 0x100 => out.push_str(&format!("\\x{:02x}", c as u32)),
+        c => out.push(c),
+    }
+}
+
+// Readable form of a path: invalid UTF-8 becomes U+FFFD and control
+// characters are written out, so a name can't break a line of the tree
+pub fn display_path(path: &Path) -> String {
+    display_bytes(&path_bytes(path))
+}
+
+pub fn display_bytes(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for c in String::from_utf8_lossy(bytes).chars() {
+        escape_char(c, &mut out);
+    }
+    out
+}
+
+// Exact form of a path that survives JSON. Backslashes are doubled, control
+// characters and bytes that aren't UTF-8 are written as \n, \r, \t or \xNN,
+// where \xNN is always one raw byte. unescape_path turns it back into the
+// original bytes.
+pub fn escape_path(path: &Path) -> String {
+    escape_bytes(&path_bytes(path))
+}
+
+pub fn escape_bytes(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                // C1 controls take two bytes in UTF-8, both are written out
+                c if c.is_control() && (c as u32) >= 0x80 => {
+                    for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                        out.push_str(&format!("\\x{:02x}", byte));
+                    }
+                }
+                c => escape_char(c, &mut out),
+            }
+        }
+        for byte in chunk.invalid() {
+            out.push_str(&format!("\\x{:02x}", byte));
+        }
+    }
+    out
+}
+
+// Reverses escape_bytes. Unknown escapes are kept as they are, so plain paths
+// with a stray backslash still come through.
+pub fn unescape_path(escaped: &str) -> Vec<u8> {
+    let bytes = escaped.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' || i + 1 == bytes.len() {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let (byte, len) = match bytes[i + 1] {
+            b'\\' => (Some(b'\\'), 2),
+            b'n' => (Some(b'\n'), 2),
+            b'r' => (Some(b'\r'), 2),
+            b't' => (Some(b'\t'), 2),
+            b'x' => (
+                escaped
+                    .get(i + 2..i + 4)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+                4,
+            ),
+            _ => (None, 0),
+        };
+        match byte {
+            Some(byte) => {
+                out.push(byte);
+                i += len;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+// Path sent back by the UI, which uses the exact form when there is one
+pub fn path_from_escaped(escaped: &str) -> PathBuf {
+    path_from_bytes(&unescape_path(escaped))
+}
+
+// The escaped form of a path when the display form loses something
+pub fn exact_path(path: &Path) -> Option<String> {
+    let bytes = path_bytes(path);
+    let escaped = escape_bytes(&bytes);
+    (escaped != display_bytes(&bytes)).then_some(escaped)
+}
+
+// What unpack_entries unpacked
+pub struct Extraction {
+    pub extracted: usize,
+    // Entries the host can't create and why, e.g. device nodes, names over
+    // its length limit or, on Windows, names with reserved characters and
+    // symlinks without developer mode
+    pub skipped: Vec<(String, String)>,
+}
+
+// Entry path relative to the container root, "./etc" and "/etc" are "etc"
+pub fn container_relative(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+// Unpacks the tar entries `keep` accepts below `destination`, an error from `keep` stops it
+pub fn unpack_entries(
+    reader: impl Read,
+    destination: &Path,
+    preserve_permissions: bool,
+    mut keep: impl FnMut(&Path) -> Result<bool, String>,
+) -> Result<Extraction, String> {
+    fs::create_dir_all(destination)
+        .map_err(|e| format!("Failed to create {:?}: {}", destination, e))?;
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(preserve_permissions);
+
+    let mut extraction = Extraction {
+        extracted: 0,
+        skipped: Vec::new(),
+    };
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let path = container_relative(
+            &entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?,
+        );
+        if path.as_os_str().is_empty() || !keep(&path)? {
+            continue;
+        }
+        match entry.unpack_in(destination) {
+            Ok(_) => extraction.extracted += 1,
+            Err(e) => extraction
+                .skipped
+                .push((display_path(&path), e.to_string())),
+        }
+    }
+    Ok(extraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) {
+        let escaped = escape_bytes(bytes);
+        assert!(
+            !escaped.contains(['\n', '\r', '\t']),
+            "{:?} was escaped to {:?}",
+            bytes,
+            escaped
+        );
+        assert_eq!(unescape_path(&escaped), bytes, "escaped as {:?}", escaped);
+    }
+
+    #[test]
+    fn plain_paths_are_left_alone() {
+        assert_eq!(escape_bytes(b"usr/lib/libssl.so.3"), "usr/lib/libssl.so.3");
+        assert_eq!(escape_bytes("etc/caf\u{e9}".as_bytes()), "etc/caf\u{e9}");
+        assert_eq!(exact_path(Path::new("usr/lib/libssl.so.3")), None);
+        round_trip("srv/\u{65e5}\u{672c}/\u{1f600}".as_bytes());
+    }
+
+    #[test]
+    fn non_utf8_bytes_round_trip() {
+        round_trip(b"data/latin1-\xe9t\xe9");
+        round_trip(b"\xff\xfe\x80");
+        assert_eq!(escape_bytes(b"a\xffb"), "a\\xffb");
+    }
+
+    #[test]
+    fn newlines_and_tabs_round_trip() {
+        round_trip(b"tmp/two\nlines");
+        round_trip(b"tmp/carriage\rreturn\tand tab");
+        assert_eq!(escape_bytes(b"a\nb"), "a\\nb");
+        assert_eq!(display_bytes(b"a\nb"), "a\\nb");
+    }
+
+    #[test]
+    fn backslashes_round_trip() {
+        round_trip(b"windows\\style\\path");
+        // Looks like an escape but is a literal backslash followed by "x41"
+        round_trip(b"odd\\x41name");
+        round_trip(b"trailing\\");
+        assert_eq!(escape_bytes(b"a\\b"), "a\\\\b");
+    }
+
+    #[test]
+    fn c0_and_c1_controls_round_trip() {
+        round_trip(b"bell\x07\x1b[0m\x7f");
+        round_trip("a\u{85}b".as_bytes());
+        round_trip("next\u{80}\u{9f}line".as_bytes());
+        assert_eq!(escape_bytes("a\u{85}b".as_bytes()), "a\\xc2\\x85b");
+    }
+
+    #[test]
+    fn exact_path_only_when_display_loses_something() {
+        let exotic = path_from_bytes("a\u{85}b".as_bytes());
+        let exact = exact_path(&exotic).expect("C1 names need an exact form");
+        assert_eq!(path_from_escaped(&exact), exotic);
+
+        let invalid = path_from_bytes(b"bad\xffname");
+        assert_eq!(display_path(&invalid), "bad\u{fffd}name");
+        assert_eq!(path_from_escaped(&exact_path(&invalid).unwrap()), invalid);
+    }
+
+    #[test]
+    fn unknown_escapes_are_kept() {
+        assert_eq!(unescape_path("C:\\data"), b"C:\\data");
+        assert_eq!(unescape_path("bad\\xzz"), b"bad\\xzz");
+        assert_eq!(unescape_path("end\\"), b"end\\");
+    }
+
+    fn layer_tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn unpack_entries_keeps_accepted_paths() {
+        let tar = layer_tar(&[("./etc/hostname", b"box"), ("usr/bin/tool", b"bin")]);
+        let destination =
+            std::env::temp_dir().join(format!("layers-core-unpack-{}", std::process::id()));
+        let mut seen = Vec::new();
+        let extraction = unpack_entries(tar.as_slice(), &destination, false, |path| {
+            seen.push(path.to_path_buf());
+            Ok(path.starts_with("etc"))
+        })
+        .unwrap();
+
+        assert_eq!(seen, [Path::new("etc/hostname"), Path::new("usr/bin/tool")]);
+        assert_eq!(extraction.extracted, 1);
+        assert!(extraction.skipped.is_empty());
+        assert_eq!(fs::read(destination.join("etc/hostname")).unwrap(), b"box");
+        assert!(!destination.join("usr").exists());
+        fs::remove_dir_all(&destination).unwrap();
+    }
+
+    #[test]
+    fn unpack_entries_stops_on_keep_error() {
+        let tar = layer_tar(&[("a", b"1"), ("b", b"2")]);
+        let destination =
+            std::env::temp_dir().join(format!("layers-core-unpack-cancel-{}", std::process::id()));
+        let result = unpack_entries(tar.as_slice(), &destination, false, |_| {
+            Err("Cancelled".to_string())
+        });
+
+        assert_eq!(result.err().as_deref(), Some("Cancelled"));
+        assert!(!destination.join("a").exists());
+        fs::remove_dir_all(&destination).unwrap();
+    }
+}
@@ -0,0 +1,7 @@
+// Domain types and analysis shared by the Tauri backend and the gpui app
+pub mod docker;
+pub mod dockerfile;
+pub mod layer_tar;
+pub mod lint;
+pub mod rewrite;
+pub mod size_estimate;
@@ -0,0 +1,358 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::dockerfile::{Dockerfile, DockerfileInstruction};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum LintSeverity {
+    Style,
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct LintRule {
+    // hadolint's ID where the rule matches one of its rules, LY otherwise
+    pub id: &'static str,
+    pub severity: LintSeverity,
+    pub title: &'static str,
+}
+
+pub const RULES: [LintRule; 8] = [
+    LintRule {
+        id: "DL3006",
+        severity: LintSeverity::Warning,
+        title: "Always tag the version of an image explicitly",
+    },
+    LintRule {
+        id: "DL3007",
+        severity: LintSeverity::Warning,
+        title: "Using latest is prone to errors if the image will ever update",
+    },
+    LintRule {
+        id: "DL3015",
+        severity: LintSeverity::Info,
+        title: "Avoid additional packages by specifying --no-install-recommends",
+    },
+    LintRule {
+        id: "DL3009",
+        severity: LintSeverity::Info,
+        title: "Delete the apt-get lists after installing something",
+    },
+    LintRule {
+        id: "DL3002",
+        severity: LintSeverity::Warning,
+        title: "Last USER should not be root",
+    },
+    LintRule {
+        id: "LY001",
+        severity: LintSeverity::Warning,
+        title: "The final stage sets no USER, the container runs as root",
+    },
+    LintRule {
+        id: "DL3020",
+        severity: LintSeverity::Error,
+        title: "Use COPY instead of ADD for files and folders",
+    },
+    LintRule {
+        id: "DL3025",
+        severity: LintSeverity::Warning,
+        title: "Use JSON notation for CMD and ENTRYPOINT arguments",
+    },
+];
+
+// Which rules run and how severe they are, everything is on by default
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub disabled_rules: Vec<String>,
+    #[serde(default)]
+    pub severity_overrides: HashMap<String, LintSeverity>,
+    // Drop issues below this severity
+    pub min_severity: Option<LintSeverity>,
+}
+
+// One offending instruction, by line so an editor can underline it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LintIssue {
+    pub rule_id: String,
+    pub severity: LintSeverity,
+    pub line_number: usize,
+    pub instruction: String,
+    pub message: String,
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    issues: Vec<LintIssue>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule_id: &str, instruction: &DockerfileInstruction, message: String) {
+        if self.config.disabled_rules.iter().any(|id| id == rule_id) {
+            return;
+        }
+        let Some(rule) = RULES.iter().find(|rule| rule.id == rule_id) else {
+            return;
+        };
+        let severity = self
+            .config
+            .severity_overrides
+            .get(rule_id)
+            .copied()
+            .unwrap_or(rule.severity);
+        if self.config.min_severity.is_some_and(|min| severity < min) {
+            return;
+        }
+        self.issues.push(LintIssue {
+            rule_id: rule_id.to_string(),
+            severity,
+            line_number: instruction.line_number,
+            instruction: instruction.instruction.clone(),
+            message,
+        });
+    }
+}
+
+// Image a FROM starts from, None for scratch, earlier stages and build args
+fn from_image<'a>(dockerfile: &Dockerfile, arguments: &'a str) -> Option<&'a str> {
+    let image = arguments
+        .split_whitespace()
+        .find(|part| !part.starts_with("--"))?;
+    let is_stage = dockerfile
+        .stages
+        .iter()
+        .filter_map(|stage| stage.name.as_deref())
+        .any(|name| name.eq_ignore_ascii_case(image));
+    (image != "scratch" && !is_stage && !image.starts_with('$')).then_some(image)
+}
+
+fn is_root(user: &str) -> bool {
+    let user = user.split(':').next().unwrap_or(user).trim();
+    user == "root" || user == "0"
+}
+
+pub fn lint(dockerfile: &Dockerfile, config: &LintConfig) -> Vec<LintIssue> {
+    let mut linter = Linter {
+        config,
+        issues: Vec::new(),
+    };
+
+    for instruction in &dockerfile.instructions {
+        let arguments = &instruction.arguments;
+        match instruction.instruction.as_str() {
+            "FROM" => {
+                let Some(image) = from_image(dockerfile, arguments) else {
+                    continue;
+                };
+                let name = image.rsplit('/').next().unwrap_or(image);
+                if name.ends_with(":latest") {
+                    linter.report(
+                        "DL3007",
+                        instruction,
+                        format!("{} follows the latest tag, pin a version", image),
+                    );
+                } else if !name.contains(':') && !name.contains('@') {
+                    linter.report(
+                        "DL3006",
+                        instruction,
+                        format!("{} has no tag and defaults to latest", image),
+                    );
+                }
+            }
+            "RUN" => {
+                if arguments.contains("apt-get install")
+                    && !arguments.contains("--no-install-recommends")
+                {
+                    linter.report(
+                        "DL3015",
+                        instruction,
+                        "apt-get install without --no-install-recommends pulls in recommended packages".to_string(),
+                    );
+                }
+                if arguments.contains("apt-get install")
+                    && !arguments.contains("rm -rf /var/lib/apt/lists")
+                {
+                    linter.report(
+                        "DL3009",
+                        instruction,
+                        "Add 'rm -rf /var/lib/apt/lists/*' to the same RUN".to_string(),
+                    );
+                }
+            }
+            "ADD" => {
+                let local = !arguments.contains("://");
+                let archive = [".tar", ".tgz", ".tar.gz", ".tar.bz2", ".tar.xz"]
+                    .iter()
+                    .any(|extension| arguments.contains(extension));
+                if local && !archive {
+                    linter.report(
+                        "DL3020",
+                        instruction,
+                        "ADD of local files, use COPY".to_string(),
+                    );
+                }
+            }
+            "CMD" | "ENTRYPOINT" if !arguments.trim_start().starts_with('[') => {
+                linter.report(
+                    "DL3025",
+                    instruction,
+                    format!(
+                        "Shell form {} runs through /bin/sh, which doesn't pass on signals",
+                        instruction.instruction
+                    ),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    // USER only matters for the stage that becomes the image
+    if let Some(stage) = dockerfile.final_stage() {
+        let last_user = stage
+            .instructions
+            .iter()
+            .rev()
+            .find(|i| i.instruction == "USER");
+        match last_user {
+            Some(user) if is_root(&user.arguments) => {
+                linter.report(
+                    "DL3002",
+                    user,
+                    "The image runs as root, switch to an unprivileged user at the end".to_string(),
+                );
+            }
+            Some(_) => {}
+            None => {
+                let from = DockerfileInstruction {
+                    instruction: "FROM".to_string(),
+                    arguments: stage.base.clone(),
+                    line_number: stage.line_number,
+                };
+                linter.report(
+                    "LY001",
+                    &from,
+                    "Add a USER instruction so containers don't run as root".to_string(),
+                );
+            }
+        }
+    }
+
+    linter
+        .issues
+        .sort_by_key(|issue| (issue.line_number, issue.rule_id.clone()));
+    linter.issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_ids(content: &str, config: &LintConfig) -> Vec<String> {
+        lint(&Dockerfile::parse(content), config)
+            .into_iter()
+            .map(|issue| issue.rule_id)
+            .collect()
+    }
+
+    fn default_rule_ids(content: &str) -> Vec<String> {
+        rule_ids(content, &LintConfig::default())
+    }
+
+    #[test]
+    fn from_needs_a_pinned_tag() {
+        assert_eq!(default_rule_ids("FROM ubuntu\nUSER app\n"), ["DL3006"]);
+        assert_eq!(
+            default_rule_ids("FROM ubuntu:latest\nUSER app\n"),
+            ["DL3007"]
+        );
+        assert!(default_rule_ids("FROM ubuntu:24.04\nUSER app\n").is_empty());
+        assert!(default_rule_ids("FROM ubuntu@sha256:0123\nUSER app\n").is_empty());
+        // The port of a registry isn't a tag
+        assert_eq!(
+            default_rule_ids("FROM registry:5000/team/app\nUSER app\n"),
+            ["DL3006"]
+        );
+    }
+
+    #[test]
+    fn scratch_stages_and_build_args_are_not_images() {
+        let content = "FROM golang:1.22 AS build\n\
+                       FROM build\n\
+                       FROM $BASE\n\
+                       FROM --platform=$BUILDPLATFORM scratch\n\
+                       USER 65532\n";
+        assert!(default_rule_ids(content).is_empty());
+    }
+
+    #[test]
+    fn apt_get_install_needs_flags_and_cleanup() {
+        let content = "FROM debian:12\n\
+                       RUN apt-get update && apt-get install -y curl\n\
+                       RUN apt-get update && apt-get install -y --no-install-recommends jq \\\n    \
+                       && rm -rf /var/lib/apt/lists/*\n\
+                       USER app\n";
+        let issues = lint(&Dockerfile::parse(content), &LintConfig::default());
+        let found: Vec<(&str, usize)> = issues
+            .iter()
+            .map(|issue| (issue.rule_id.as_str(), issue.line_number))
+            .collect();
+        assert_eq!(found, [("DL3009", 2), ("DL3015", 2)]);
+    }
+
+    #[test]
+    fn add_is_only_for_archives_and_urls() {
+        let content = "FROM alpine:3.20\n\
+                       ADD app.conf /etc/app.conf\n\
+                       ADD rootfs.tar.gz /\n\
+                       ADD https://example.com/tool /usr/bin/tool\n\
+                       USER app\n";
+        assert_eq!(default_rule_ids(content), ["DL3020"]);
+    }
+
+    #[test]
+    fn cmd_and_entrypoint_use_json_form() {
+        let content = "FROM alpine:3.20\n\
+                       USER app\n\
+                       ENTRYPOINT [\"/app\"]\n\
+                       CMD serve --port 80\n";
+        assert_eq!(default_rule_ids(content), ["DL3025"]);
+    }
+
+    #[test]
+    fn final_stage_user_decides_root() {
+        assert_eq!(default_rule_ids("FROM alpine:3.20\n"), ["LY001"]);
+        assert_eq!(
+            default_rule_ids("FROM alpine:3.20\nUSER app\nUSER root:root\n"),
+            ["DL3002"]
+        );
+        // Only the stage that becomes the image counts
+        let content = "FROM alpine:3.20 AS build\nUSER 0\nFROM alpine:3.20\nUSER app\n";
+        assert!(default_rule_ids(content).is_empty());
+    }
+
+    #[test]
+    fn config_disables_overrides_and_filters_rules() {
+        let content = "FROM ubuntu\nRUN apt-get install -y curl\n";
+        let config = LintConfig {
+            disabled_rules: vec!["LY001".to_string()],
+            severity_overrides: HashMap::from([("DL3015".to_string(), LintSeverity::Error)]),
+            min_severity: Some(LintSeverity::Warning),
+        };
+        let issues = lint(&Dockerfile::parse(content), &config);
+        let found: Vec<(&str, LintSeverity)> = issues
+            .iter()
+            .map(|issue| (issue.rule_id.as_str(), issue.severity))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("DL3006", LintSeverity::Warning),
+                ("DL3015", LintSeverity::Error)
+            ]
+        );
+    }
+}
@@ -0,0 +1,536 @@
+use serde::{Deserialize, Serialize};
+
+use crate::dockerfile::{
+    copies_only_manifests, copies_whole_context, installs_dependencies, is_build_command,
+};
+
+// Files an install command reads, copied ahead of the sources so the install
+// layer stays cached until they change
+const INSTALL_MANIFESTS: [(&str, &str); 7] = [
+    ("npm ci", "package*.json"),
+    ("npm install", "package*.json"),
+    ("yarn install", "package.json yarn.lock"),
+    ("pnpm install", "package.json pnpm-lock.yaml"),
+    ("bundle install", "Gemfile Gemfile.lock"),
+    ("go mod download", "go.mod go.sum"),
+    ("composer install", "composer.json composer.lock"),
+];
+// Instructions that only matter to the container, moved to the runtime stage
+const RUNTIME_INSTRUCTIONS: [&str; 5] =
+    ["EXPOSE", "HEALTHCHECK", "STOPSIGNAL", "ENTRYPOINT", "CMD"];
+// Commands whose effect would leak into the next command once RUNs are merged
+const SHELL_STATE: [&str; 5] = [";", "||", "cd ", "export ", "set "];
+const CONTINUATION: &str = " \\\n    && ";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RewriteKind {
+    MergeRuns,
+    Cleanup,
+    ReorderCopy,
+    MultiStage,
+}
+
+// One independent edit, rejecting it leaves the lines it touches as they were
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RewriteChange {
+    // Stays the same as long as the original lines don't move, so it can be
+    // passed back to reject the change
+    pub id: String,
+    pub kind: RewriteKind,
+    pub title: String,
+    pub description: String,
+    pub line_numbers: Vec<usize>,
+    pub before: String,
+    // The lines as this change alone would leave them
+    pub after: String,
+    pub applied: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DockerfileRewrite {
+    // The Dockerfile with every change that wasn't rejected
+    pub content: String,
+    pub changes: Vec<RewriteChange>,
+}
+
+// An instruction with its continuation lines, or a comment or blank line,
+// as written in the file
+struct Block {
+    text: String,
+    // Upper case, None for comments and blank lines
+    instruction: Option<String>,
+    // Continuation lines folded into one
+    arguments: String,
+    line_number: usize,
+    heredoc: bool,
+}
+
+impl Block {
+    fn is(&self, instruction: &str) -> bool {
+        self.instruction.as_deref() == Some(instruction)
+    }
+
+    fn is_blank(&self) -> bool {
+        self.instruction.is_none() && self.text.trim().is_empty()
+    }
+}
+
+// The delimiter of a heredoc in "RUN <<EOF" or "RUN <<-'EOF'"
+fn heredoc_delimiter(arguments: &str) -> Option<String> {
+    let (_, rest) = arguments.split_once("<<")?;
+    let rest = rest.trim_start_matches('-').trim_start_matches(['"', '\'']);
+    let delimiter: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    (!delimiter.is_empty()).then_some(delimiter)
+}
+
+fn split_blocks(content: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut lines = content.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            blocks.push(Block {
+                text: line.to_string(),
+                instruction: None,
+                arguments: String::new(),
+                line_number: i + 1,
+                heredoc: false,
+            });
+            continue;
+        }
+
+        let mut text = line.to_string();
+        let mut folded = trimmed.trim_end_matches('\\').trim_end().to_string();
+        let mut continued = trimmed.ends_with('\\');
+        while continued {
+            let Some((_, next)) = lines.next() else {
+                break;
+            };
+            text.push('\n');
+            text.push_str(next);
+            let next = next.trim();
+            // Docker drops comments and blank lines inside an instruction
+            if next.is_empty() || next.starts_with('#') {
+                continue;
+            }
+            folded.push(' ');
+            folded.push_str(next.trim_end_matches('\\').trim_end());
+            continued = next.ends_with('\\');
+        }
+
+        let (instruction, arguments) = folded
+            .split_once(char::is_whitespace)
+            .unwrap_or((folded.as_str(), ""));
+        let heredoc = heredoc_delimiter(arguments);
+        if let Some(delimiter) = &heredoc {
+            for (_, next) in lines.by_ref() {
+                text.push('\n');
+                text.push_str(next);
+                if next.trim() == delimiter {
+                    break;
+                }
+            }
+        }
+        blocks.push(Block {
+            instruction: Some(instruction.to_uppercase()),
+            arguments: arguments.trim().to_string(),
+            text,
+            line_number: i + 1,
+            heredoc: heredoc.is_some(),
+        });
+    }
+    blocks
+}
+
+// The shell command of a RUN as written, without the keyword
+fn run_body(text: &str) -> &str {
+    let text = text.trim_start();
+    text[text.find(char::is_whitespace).unwrap_or(text.len())..].trim_start()
+}
+
+// Whether a RUN can be chained onto another one with &&
+fn is_mergeable_run(block: &Block) -> bool {
+    block.is("RUN")
+        && !block.heredoc
+        && !block.arguments.starts_with('[')
+        && !block.arguments.starts_with("--")
+        // Dependency installs are worth their own cached layer
+        && !installs_dependencies(&block.arguments)
+}
+
+fn merge_runs(texts: &[&str]) -> String {
+    let bodies: Vec<String> = texts
+        .iter()
+        .map(|text| {
+            let body = run_body(text);
+            if SHELL_STATE.iter().any(|state| body.contains(state)) {
+                // Separate RUNs don't share a shell, a subshell keeps it that way
+                format!("( {} )", body)
+            } else {
+                body.to_string()
+            }
+        })
+        .collect();
+    format!("RUN {}", bodies.join(CONTINUATION))
+}
+
+// The RUN with package manager caches removed, and what was fixed
+fn with_cleanup(block: &Block) -> Option<(String, Vec<&'static str>)> {
+    if !block.is("RUN") || block.heredoc || block.arguments.starts_with('[') {
+        return None;
+    }
+    let mut text = block.text.clone();
+    let mut fixes = Vec::new();
+    if block.arguments.contains("apk add") && !block.arguments.contains("--no-cache") {
+        text = text.replacen("apk add", "apk add --no-cache", 1);
+        fixes.push("apk add --no-cache");
+    }
+    for pip in ["pip install", "pip3 install"] {
+        if block.arguments.contains(pip) && !block.arguments.contains("--no-cache-dir") {
+            text = text.replacen(pip, &format!("{} --no-cache-dir", pip), 1);
+            fixes.push("pip install --no-cache-dir");
+            break;
+        }
+    }
+    if block.arguments.contains("apt-get install")
+        && !block.arguments.contains("/var/lib/apt/lists")
+    {
+        text.push_str(CONTINUATION);
+        text.push_str("rm -rf /var/lib/apt/lists/*");
+        fixes.push("removing the apt lists");
+    }
+    (!fixes.is_empty()).then_some((text, fixes))
+}
+
+// "COPY <manifests> <dest>" for the install right after a "COPY . <dest>"
+fn manifest_copy(copy: &Block, install: &Block) -> Option<String> {
+    let (_, manifests) = INSTALL_MANIFESTS
+        .iter()
+        .find(|(command, _)| install.arguments.contains(command))?;
+    let parts: Vec<&str> = copy.arguments.split_whitespace().collect();
+    let flags: Vec<&str> = parts
+        .iter()
+        .copied()
+        .filter(|part| part.starts_with("--"))
+        .collect();
+    let destination = *parts.last()?;
+    let destination = match destination {
+        "." => "./".to_string(),
+        destination if destination.ends_with('/') => destination.to_string(),
+        destination => format!("{}/", destination),
+    };
+    let mut line = vec!["COPY"];
+    line.extend(flags);
+    line.push(manifests);
+    line.push(&destination);
+    Some(line.join(" "))
+}
+
+fn joined(blocks: &[&Block]) -> String {
+    blocks
+        .iter()
+        .map(|block| block.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+struct Rewriter<'a> {
+    blocks: &'a [Block],
+    rejected: &'a [String],
+    // Current text of every block, None once it's removed or moved
+    texts: Vec<Option<String>>,
+    // Lines to put in front of a block
+    inserted: Vec<Vec<String>>,
+    appended: Vec<String>,
+    changes: Vec<RewriteChange>,
+}
+
+impl Rewriter<'_> {
+    // Records the change, true if it should be applied
+    fn propose(
+        &mut self,
+        id: String,
+        kind: RewriteKind,
+        title: &str,
+        description: String,
+        blocks: &[&Block],
+        after: String,
+    ) -> bool {
+        let applied = !self.rejected.contains(&id);
+        self.changes.push(RewriteChange {
+            id,
+            kind,
+            title: title.to_string(),
+            description,
+            line_numbers: blocks.iter().map(|block| block.line_number).collect(),
+            before: joined(blocks),
+            after,
+            applied,
+        });
+        applied
+    }
+
+    fn cleanup(&mut self) {
+        for (index, block) in self.blocks.iter().enumerate() {
+            let Some((text, fixes)) = with_cleanup(block) else {
+                continue;
+            };
+            let description = format!(
+                "Keep package manager caches out of the layer by {}.",
+                fixes.join(", ")
+            );
+            let id = format!("cleanup-{}", block.line_number);
+            if self.propose(
+                id,
+                RewriteKind::Cleanup,
+                "Clean up package caches",
+                description,
+                &[block],
+                text.clone(),
+            ) {
+                self.texts[index] = Some(text);
+            }
+        }
+    }
+
+    fn reorder_copies(&mut self) {
+        let blocks = self.blocks;
+        let mut manifests_copied = false;
+        for (index, block) in blocks.iter().enumerate() {
+            if block.is("FROM") {
+                manifests_copied = false;
+            }
+            if block.is("COPY") && copies_only_manifests(&block.arguments) {
+                manifests_copied = true;
+            }
+            if manifests_copied
+                || !block.is("COPY")
+                || block.arguments.contains("--from")
+                || !copies_whole_context(&block.arguments)
+            {
+                continue;
+            }
+            let Some(install_index) = (index + 1..blocks.len())
+                .find(|&next| blocks[next].instruction.is_some())
+                .filter(|&next| blocks[next].is("RUN"))
+            else {
+                continue;
+            };
+            let install = &blocks[install_index];
+            let Some(manifest_copy) = manifest_copy(block, install) else {
+                continue;
+            };
+
+            let after = [
+                manifest_copy.as_str(),
+                install.text.as_str(),
+                block.text.as_str(),
+            ]
+            .join("\n");
+            let id = format!("reorder-copy-{}", block.line_number);
+            if self.propose(
+                id,
+                RewriteKind::ReorderCopy,
+                "Copy dependency manifests first",
+                "Any source change invalidates the install layer when it runs after copying the whole context. Copy the manifests, install, then copy the rest.".to_string(),
+                &[block, install],
+                after,
+            ) {
+                let install_text = self.texts[install_index].take().unwrap_or_default();
+                self.inserted[index].push(manifest_copy);
+                self.inserted[index].push(install_text);
+            }
+        }
+    }
+
+    fn merge_groups(&mut self) {
+        let blocks = self.blocks;
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut group: Vec<usize> = Vec::new();
+        let mut custom_shell = false;
+        for (index, block) in blocks.iter().enumerate() {
+            if block.is_blank() {
+                continue;
+            }
+            if block.is("FROM") {
+                custom_shell = false;
+            }
+            if block.is("SHELL") {
+                custom_shell = true;
+            }
+            if !custom_shell && is_mergeable_run(block) && self.texts[index].is_some() {
+                group.push(index);
+            } else {
+                groups.push(std::mem::take(&mut group));
+            }
+        }
+        groups.push(group);
+
+        for group in groups.into_iter().filter(|group| group.len() > 1) {
+            let members: Vec<&Block> = group.iter().map(|&index| &blocks[index]).collect();
+            let original: Vec<&str> = members.iter().map(|block| block.text.as_str()).collect();
+            let lines: Vec<String> = members
+                .iter()
+                .map(|block| block.line_number.to_string())
+                .collect();
+            let description = format!(
+                "Lines {} each create a layer, chained with && they share one.",
+                lines.join(", ")
+            );
+            let id = format!("merge-runs-{}", members[0].line_number);
+            if self.propose(
+                id,
+                RewriteKind::MergeRuns,
+                "Merge RUN instructions",
+                description,
+                &members,
+                merge_runs(&original),
+            ) {
+                let current: Vec<String> = group
+                    .iter()
+                    .map(|&index| self.texts[index].take().unwrap_or_default())
+                    .collect();
+                let current: Vec<&str> = current.iter().map(String::as_str).collect();
+                self.texts[group[0]] = Some(merge_runs(&current));
+                // Blank lines between the merged RUNs go with them
+                let (first, last) = (group[0], group[group.len() - 1]);
+                for (offset, block) in blocks[first..=last].iter().enumerate() {
+                    if block.is_blank() {
+                        self.texts[first + offset] = None;
+                    }
+                }
+            }
+        }
+    }
+
+    fn multi_stage(&mut self) {
+        let blocks = self.blocks;
+        let froms: Vec<usize> = (0..blocks.len())
+            .filter(|&index| blocks[index].is("FROM"))
+            .collect();
+        let [from_index] = froms[..] else {
+            return;
+        };
+        if !blocks
+            .iter()
+            .any(|block| block.is("RUN") && is_build_command(&block.arguments))
+        {
+            return;
+        }
+
+        let from = &blocks[from_index];
+        let parts: Vec<&str> = from.arguments.split_whitespace().collect();
+        let base = parts
+            .iter()
+            .find(|part| !part.starts_with("--"))
+            .copied()
+            .unwrap_or("scratch");
+        let named = parts
+            .iter()
+            .position(|part| part.eq_ignore_ascii_case("as"));
+        let (stage_name, from_text) = match named.and_then(|index| parts.get(index + 1)) {
+            Some(name) => (name.to_string(), from.text.clone()),
+            None => ("build".to_string(), format!("{} AS build", from.text)),
+        };
+
+        let last_layer = blocks
+            .iter()
+            .rposition(|block| block.is("RUN") || block.is("COPY") || block.is("ADD"))
+            .unwrap_or(0);
+        // USER only moves if nothing in the build stage runs as that user
+        let moved: Vec<usize> = (from_index + 1..blocks.len())
+            .filter(|&index| {
+                let block = &blocks[index];
+                block.instruction.as_deref().is_some_and(|instruction| {
+                    RUNTIME_INSTRUCTIONS.contains(&instruction)
+                        || (instruction == "USER" && index > last_layer)
+                })
+            })
+            .collect();
+        let workdir = blocks
+            .iter()
+            .rev()
+            .find(|block| block.is("WORKDIR"))
+            .map(|block| block.arguments.clone());
+
+        let mut scaffold = vec![
+            String::new(),
+            "# Runtime stage, only the build output ends up in the image.".to_string(),
+            "# TODO: pick a slimmer base and copy only what the app needs to run.".to_string(),
+            format!("FROM {}", base),
+        ];
+        scaffold.extend(
+            blocks
+                .iter()
+                .filter(|block| block.is("ENV"))
+                .map(|block| block.text.clone()),
+        );
+        let output = workdir.unwrap_or_else(|| "/app".to_string());
+        scaffold.push(format!("WORKDIR {}", output));
+        scaffold.push(format!("COPY --from={} {} {}", stage_name, output, output));
+        scaffold.extend(moved.iter().map(|&index| blocks[index].text.clone()));
+
+        let mut touched = vec![from];
+        touched.extend(moved.iter().map(|&index| &blocks[index]));
+        let after = format!("{}\n{}", from_text, scaffold[1..].join("\n"));
+        let id = format!("multi-stage-{}", from.line_number);
+        if self.propose(
+            id,
+            RewriteKind::MultiStage,
+            "Split into build and runtime stages",
+            "The image compiles in its only stage, so compilers and build caches ship with it. Build in one stage and copy the output into a runtime stage.".to_string(),
+            &touched,
+            after,
+        ) {
+            self.texts[from_index] = Some(from_text);
+            for index in moved {
+                self.texts[index] = None;
+            }
+            self.appended = scaffold;
+        }
+    }
+
+    fn render(self, trailing_newline: bool) -> DockerfileRewrite {
+        let mut lines = Vec::new();
+        for (inserted, text) in self.inserted.into_iter().zip(self.texts) {
+            lines.extend(inserted);
+            lines.extend(text);
+        }
+        lines.extend(self.appended);
+        let mut content = lines.join("\n");
+        if trailing_newline {
+            content.push('\n');
+        }
+        DockerfileRewrite {
+            content,
+            changes: self.changes,
+        }
+    }
+}
+
+// Rewrites the Dockerfile with the fixes the suggestions describe. Changes
+// whose ID is in `rejected` are listed but not applied.
+pub fn optimize(content: &str, rejected: &[String]) -> DockerfileRewrite {
+    let blocks = split_blocks(content);
+    let mut rewriter = Rewriter {
+        blocks: &blocks,
+        rejected,
+        texts: blocks
+            .iter()
+            .map(|block| Some(block.text.clone()))
+            .collect(),
+        inserted: vec![Vec::new(); blocks.len()],
+        appended: Vec::new(),
+        changes: Vec::new(),
+    };
+    rewriter.cleanup();
+    rewriter.reorder_copies();
+    rewriter.merge_groups();
+    rewriter.multi_stage();
+    rewriter.render(content.ends_with('\n'))
+}
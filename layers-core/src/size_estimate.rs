@@ -0,0 +1,538 @@
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::dockerfile::{Dockerfile, DockerfileInstruction};
+
+const KB: u64 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PackageManager {
+    Apt,
+    Apk,
+    Pip,
+    Npm,
+}
+
+// Installed size in KB of common packages together with the dependencies
+// they usually pull in on a slim base image
+const PACKAGE_SIZES: [(PackageManager, &str, u64); 72] = [
+    (PackageManager::Apt, "build-essential", 250_000),
+    (PackageManager::Apt, "ca-certificates", 2_000),
+    (PackageManager::Apt, "curl", 10_000),
+    (PackageManager::Apt, "default-jre-headless", 200_000),
+    (PackageManager::Apt, "ffmpeg", 400_000),
+    (PackageManager::Apt, "g++", 120_000),
+    (PackageManager::Apt, "gcc", 100_000),
+    (PackageManager::Apt, "git", 40_000),
+    (PackageManager::Apt, "gnupg", 10_000),
+    (PackageManager::Apt, "imagemagick", 30_000),
+    (PackageManager::Apt, "iputils-ping", 300),
+    (PackageManager::Apt, "jq", 1_000),
+    (PackageManager::Apt, "less", 500),
+    (PackageManager::Apt, "libpq-dev", 5_000),
+    (PackageManager::Apt, "locales", 17_000),
+    (PackageManager::Apt, "make", 1_500),
+    (PackageManager::Apt, "nano", 2_500),
+    (PackageManager::Apt, "netcat-openbsd", 200),
+    (PackageManager::Apt, "nodejs", 80_000),
+    (PackageManager::Apt, "openssh-client", 5_000),
+    (PackageManager::Apt, "postgresql-client", 15_000),
+    (PackageManager::Apt, "procps", 2_000),
+    (PackageManager::Apt, "python3", 30_000),
+    (PackageManager::Apt, "python3-pip", 60_000),
+    (PackageManager::Apt, "sudo", 6_000),
+    (PackageManager::Apt, "tzdata", 3_500),
+    (PackageManager::Apt, "unzip", 400),
+    (PackageManager::Apt, "vim", 35_000),
+    (PackageManager::Apt, "wget", 4_000),
+    (PackageManager::Apt, "zip", 600),
+    (PackageManager::Apk, "bash", 2_500),
+    (PackageManager::Apk, "build-base", 200_000),
+    (PackageManager::Apk, "ca-certificates", 700),
+    (PackageManager::Apk, "curl", 3_000),
+    (PackageManager::Apk, "gcc", 100_000),
+    (PackageManager::Apk, "git", 15_000),
+    (PackageManager::Apk, "jq", 800),
+    (PackageManager::Apk, "make", 300),
+    (PackageManager::Apk, "musl-dev", 5_000),
+    (PackageManager::Apk, "nodejs", 60_000),
+    (PackageManager::Apk, "npm", 10_000),
+    (PackageManager::Apk, "openssh-client", 3_000),
+    (PackageManager::Apk, "py3-pip", 12_000),
+    (PackageManager::Apk, "python3", 45_000),
+    (PackageManager::Apk, "tzdata", 3_000),
+    (PackageManager::Pip, "boto3", 90_000),
+    (PackageManager::Pip, "django", 40_000),
+    (PackageManager::Pip, "fastapi", 5_000),
+    (PackageManager::Pip, "flask", 4_000),
+    (PackageManager::Pip, "gunicorn", 600),
+    (PackageManager::Pip, "matplotlib", 40_000),
+    (PackageManager::Pip, "numpy", 60_000),
+    (PackageManager::Pip, "opencv-python", 90_000),
+    (PackageManager::Pip, "pandas", 70_000),
+    (PackageManager::Pip, "pillow", 12_000),
+    (PackageManager::Pip, "psycopg2-binary", 9_000),
+    (PackageManager::Pip, "pyyaml", 1_000),
+    (PackageManager::Pip, "requests", 1_500),
+    (PackageManager::Pip, "scikit-learn", 40_000),
+    (PackageManager::Pip, "scipy", 110_000),
+    (PackageManager::Pip, "sqlalchemy", 10_000),
+    (PackageManager::Pip, "tensorflow", 500_000),
+    (PackageManager::Pip, "torch", 800_000),
+    (PackageManager::Pip, "uvicorn", 2_000),
+    (PackageManager::Npm, "@types/node", 2_500),
+    (PackageManager::Npm, "eslint", 12_000),
+    (PackageManager::Npm, "express", 2_000),
+    (PackageManager::Npm, "lodash", 1_400),
+    (PackageManager::Npm, "next", 100_000),
+    (PackageManager::Npm, "prisma", 15_000),
+    (PackageManager::Npm, "sharp", 30_000),
+    (PackageManager::Npm, "typescript", 23_000),
+];
+// Assumed for packages missing from the table
+const DEFAULT_PACKAGE_KB: [(PackageManager, u64); 4] = [
+    (PackageManager::Apt, 5_000),
+    (PackageManager::Apk, 2_000),
+    (PackageManager::Pip, 5_000),
+    (PackageManager::Npm, 2_000),
+];
+// Package lists apt-get update leaves behind unless they are removed
+const APT_LISTS_KB: u64 = 20_000;
+const APK_INDEX_KB: u64 = 2_500;
+// Install flags followed by a value that isn't a package, e.g. "-r file"
+const VALUE_FLAGS: [&str; 12] = [
+    "-r",
+    "--requirement",
+    "-c",
+    "--constraint",
+    "-e",
+    "-i",
+    "--index-url",
+    "--extra-index-url",
+    "-o",
+    "-t",
+    "--target",
+    "--virtual",
+];
+// Archives ADD extracts take about this many times their size
+const ARCHIVE_EXPANSION: u64 = 3;
+
+// Predicted size of the layer an instruction creates
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerSizeEstimate {
+    pub line_number: usize,
+    // 0 for instructions that only change metadata, None when there is
+    // nothing to base an estimate on, e.g. COPY --from or a remote ADD
+    pub bytes: Option<u64>,
+    // What went into the estimate, for display next to it
+    pub basis: Vec<String>,
+}
+
+fn package_kb(manager: PackageManager, name: &str) -> (u64, bool) {
+    let name = name.to_lowercase();
+    PACKAGE_SIZES
+        .iter()
+        .find(|(m, package, _)| *m == manager && *package == name)
+        .map(|(_, _, kb)| (*kb, true))
+        .unwrap_or_else(|| {
+            let default = DEFAULT_PACKAGE_KB
+                .iter()
+                .find(|(m, _)| *m == manager)
+                .map(|(_, kb)| *kb)
+                .unwrap_or(0);
+            (default, false)
+        })
+}
+
+// Name without version constraints, e.g. "numpy" from "numpy==1.26" or
+// "curl" from "curl=7.88.1-10"
+fn package_name(spec: &str) -> &str {
+    let end = spec
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| matches!(c, '=' | '<' | '>' | '~' | '!' | '[' | ';'))
+        .map(|(i, _)| i)
+        .unwrap_or(spec.len());
+    // npm's name@version, keeping the @ of a scope
+    let spec = &spec[..end];
+    match spec.char_indices().skip(1).find(|(_, c)| *c == '@') {
+        Some((at, _)) => &spec[..at],
+        None => spec,
+    }
+}
+
+// Arguments of every "<command> <args>" in a shell command, up to the next
+// && ; or |
+fn command_arguments<'a>(run: &'a str, command: &str) -> Vec<Vec<&'a str>> {
+    run.match_indices(command)
+        .map(|(i, _)| {
+            run[i + command.len()..]
+                .split(['&', ';', '|'])
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect()
+        })
+        .collect()
+}
+
+// Package names among install arguments, skipping flags and their values
+fn packages(arguments: &[&str]) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut arguments = arguments.iter();
+    while let Some(arg) = arguments.next() {
+        if VALUE_FLAGS.contains(arg) {
+            arguments.next();
+        } else if !arg.starts_with(['-', '.', '/']) && !arg.contains('$') && !arg.contains("://") {
+            names.push(arg.to_string());
+        }
+    }
+    names
+}
+
+// Relative paths and sizes of the files the build context sends, after
+// .dockerignore
+struct BuildContext {
+    files: Vec<(String, u64)>,
+}
+
+impl BuildContext {
+    fn load(dir: &Path) -> Self {
+        let ignore = fs::read_to_string(dir.join(".dockerignore"))
+            .map(|content| ignore_patterns(&content))
+            .unwrap_or_default();
+        let mut files = Vec::new();
+        walk(dir, dir, &ignore, &mut files);
+        BuildContext { files }
+    }
+
+    fn read(dir: &Path, file: &str) -> Option<String> {
+        fs::read_to_string(dir.join(file)).ok()
+    }
+
+    // Bytes the COPY/ADD sources match and how many files that is
+    fn measure(&self, source: &str) -> (u64, usize) {
+        let source = source.trim_start_matches("./").trim_end_matches('/');
+        let matcher = glob(source);
+        let matched = self.files.iter().filter(|(path, _)| {
+            source.is_empty()
+                || source == "."
+                || path == source
+                || path.starts_with(&format!("{}/", source))
+                || matcher.as_ref().is_some_and(|matcher| {
+                    // A glob can match a directory, which copies everything in it
+                    let mut prefix = path.as_str();
+                    loop {
+                        if matcher.is_match(prefix) {
+                            return true;
+                        }
+                        match prefix.rsplit_once('/') {
+                            Some((parent, _)) => prefix = parent,
+                            None => return false,
+                        }
+                    }
+                })
+        });
+        matched.fold((0, 0), |(bytes, count), (_, size)| {
+            (bytes + size, count + 1)
+        })
+    }
+}
+
+// .dockerignore patterns, "!" ones re-include what an earlier one excluded
+fn ignore_patterns(content: &str) -> Vec<(GlobMatcher, bool)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (pattern, include) = match line.strip_prefix('!') {
+                Some(pattern) => (pattern, true),
+                None => (line, false),
+            };
+            let pattern = pattern.trim_start_matches('/').trim_end_matches('/');
+            glob(pattern).map(|matcher| (matcher, include))
+        })
+        .collect()
+}
+
+fn glob(pattern: &str) -> Option<GlobMatcher> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .ok()
+        .map(|glob| glob.compile_matcher())
+}
+
+fn is_ignored(relative: &str, ignore: &[(GlobMatcher, bool)]) -> bool {
+    // The last matching pattern wins, patterns on a directory cover its files
+    let mut ignored = false;
+    for (matcher, include) in ignore {
+        let mut prefix = relative;
+        let matches = loop {
+            if matcher.is_match(prefix) {
+                break true;
+            }
+            match prefix.rsplit_once('/') {
+                Some((parent, _)) => prefix = parent,
+                None => break false,
+            }
+        };
+        if matches {
+            ignored = !include;
+        }
+    }
+    ignored
+}
+
+fn walk(root: &Path, dir: &Path, ignore: &[(GlobMatcher, bool)], files: &mut Vec<(String, u64)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        if is_ignored(&relative, ignore) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            walk(root, &path, ignore, files);
+        } else {
+            files.push((relative, metadata.len()));
+        }
+    }
+}
+
+// Sources of a COPY or ADD, in shell or JSON form, without the destination
+fn copy_sources(arguments: &str) -> Vec<String> {
+    let mut parts: Vec<String> = if arguments.trim_start().starts_with('[') {
+        serde_json::from_str::<Vec<String>>(arguments).unwrap_or_default()
+    } else {
+        arguments
+            .split_whitespace()
+            .filter(|part| !part.starts_with("--"))
+            .map(|part| part.to_string())
+            .collect()
+    };
+    parts.pop();
+    parts
+}
+
+struct Estimator<'a> {
+    context_dir: Option<&'a Path>,
+    context: Option<BuildContext>,
+}
+
+impl Estimator<'_> {
+    fn context(&mut self) -> Option<&BuildContext> {
+        let dir = self.context_dir?;
+        Some(self.context.get_or_insert_with(|| BuildContext::load(dir)))
+    }
+
+    fn install(
+        &self,
+        manager: PackageManager,
+        names: Vec<String>,
+        label: &str,
+        bytes: &mut u64,
+        basis: &mut Vec<String>,
+    ) {
+        if names.is_empty() {
+            return;
+        }
+        let mut unknown = 0;
+        for name in &names {
+            let (kb, known) = package_kb(manager, package_name(name));
+            *bytes += kb * KB;
+            if !known {
+                unknown += 1;
+            }
+        }
+        let plural = if names.len() == 1 { "" } else { "s" };
+        basis.push(if unknown > 0 {
+            format!(
+                "{} {} package{}, {} at a typical size",
+                names.len(),
+                label,
+                plural,
+                unknown
+            )
+        } else {
+            format!("{} {} package{}", names.len(), label, plural)
+        });
+    }
+
+    // Dependencies package.json lists, without devDependencies when the
+    // install skips them
+    fn npm_dependencies(&self, production: bool) -> Vec<String> {
+        let Some(content) = self
+            .context_dir
+            .and_then(|dir| BuildContext::read(dir, "package.json"))
+        else {
+            return Vec::new();
+        };
+        let Ok(manifest) = serde_json::from_str::<serde_json::Value>(&content) else {
+            return Vec::new();
+        };
+        let mut sections = vec!["dependencies"];
+        if !production {
+            sections.push("devDependencies");
+        }
+        sections
+            .iter()
+            .filter_map(|section| manifest[*section].as_object())
+            .flat_map(|dependencies| dependencies.keys().cloned())
+            .collect()
+    }
+
+    fn requirements(&self, file: &str) -> Vec<String> {
+        self.context_dir
+            .and_then(|dir| BuildContext::read(dir, file))
+            .map(|content| {
+                content
+                    .lines()
+                    .map(|line| line.split('#').next().unwrap_or("").trim())
+                    .filter(|line| !line.is_empty() && !line.starts_with('-'))
+                    .map(|line| package_name(line).to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn run(&self, arguments: &str) -> (Option<u64>, Vec<String>) {
+        let mut bytes = 0;
+        let mut basis = Vec::new();
+
+        for command in ["apt-get install", "apt install"] {
+            for args in command_arguments(arguments, command) {
+                let names = packages(&args);
+                self.install(PackageManager::Apt, names, "apt", &mut bytes, &mut basis);
+            }
+        }
+        if arguments.contains("apt-get update") && !arguments.contains("/var/lib/apt/lists") {
+            bytes += APT_LISTS_KB * KB;
+            basis.push("apt lists left in the layer".to_string());
+        }
+
+        for args in command_arguments(arguments, "apk add") {
+            let names = packages(&args);
+            self.install(PackageManager::Apk, names, "apk", &mut bytes, &mut basis);
+        }
+        if arguments.contains("apk add") && !arguments.contains("--no-cache") {
+            bytes += APK_INDEX_KB * KB;
+            basis.push("apk index left in the layer".to_string());
+        }
+
+        for command in ["pip install", "pip3 install"] {
+            for args in command_arguments(arguments, command) {
+                let mut names = packages(&args);
+                names.retain(|name| name != ".");
+                if let Some(index) = args.iter().position(|arg| *arg == "-r") {
+                    if let Some(file) = args.get(index + 1) {
+                        names.extend(self.requirements(file));
+                    }
+                }
+                let before = bytes;
+                self.install(PackageManager::Pip, names, "pip", &mut bytes, &mut basis);
+                if !arguments.contains("--no-cache-dir") && bytes > before {
+                    // The downloaded wheels stay in the pip cache
+                    bytes += (bytes - before) / 2;
+                    basis.push("pip cache left in the layer".to_string());
+                }
+            }
+        }
+
+        for command in ["npm install", "npm ci", "npm i "] {
+            for args in command_arguments(arguments, command) {
+                let mut names = packages(&args);
+                if names.is_empty() {
+                    let production = args
+                        .iter()
+                        .any(|arg| *arg == "--production" || *arg == "--omit=dev");
+                    names = self.npm_dependencies(production);
+                }
+                self.install(PackageManager::Npm, names, "npm", &mut bytes, &mut basis);
+            }
+        }
+
+        if basis.is_empty() {
+            (None, basis)
+        } else {
+            (Some(bytes), basis)
+        }
+    }
+
+    fn copy(&mut self, instruction: &DockerfileInstruction) -> (Option<u64>, Vec<String>) {
+        let arguments = &instruction.arguments;
+        if arguments.contains("--from=") {
+            return (None, vec!["Copied from another stage or image".to_string()]);
+        }
+        let sources = copy_sources(arguments);
+        if sources.iter().any(|source| source.contains("://")) {
+            return (None, vec!["Downloaded at build time".to_string()]);
+        }
+        let extracts = instruction.instruction == "ADD";
+        let Some(context) = self.context() else {
+            return (None, vec!["No build context to measure".to_string()]);
+        };
+
+        let mut bytes = 0;
+        let mut files = 0;
+        let mut basis = Vec::new();
+        for source in &sources {
+            let (size, count) = context.measure(source);
+            let archive = [".tar", ".tar.gz", ".tgz", ".tar.bz2", ".tar.xz"]
+                .iter()
+                .any(|extension| source.ends_with(extension));
+            if extracts && archive {
+                bytes += size * ARCHIVE_EXPANSION;
+                basis.push(format!("{} extracted by ADD", source));
+            } else {
+                bytes += size;
+            }
+            files += count;
+        }
+        let plural = if files == 1 { "" } else { "s" };
+        basis.insert(0, format!("{} file{} in the build context", files, plural));
+        (Some(bytes), basis)
+    }
+}
+
+// Predicted layer size of every instruction, in file order, before anything
+// is built. Package installs come from a table of typical sizes, COPY and
+// ADD are measured in `context_dir` if there is one.
+pub fn estimate_layer_sizes(
+    dockerfile: &Dockerfile,
+    context_dir: Option<&Path>,
+) -> Vec<LayerSizeEstimate> {
+    let mut estimator = Estimator {
+        context_dir,
+        context: None,
+    };
+    dockerfile
+        .instructions
+        .iter()
+        .map(|instruction| {
+            let (bytes, basis) = match instruction.instruction.as_str() {
+                "FROM" => (None, vec!["Size of the base image".to_string()]),
+                "RUN" => estimator.run(&instruction.arguments),
+                "COPY" | "ADD" => estimator.copy(instruction),
+                _ => (Some(0), Vec::new()),
+            };
+            LayerSizeEstimate {
+                line_number: instruction.line_number,
+                bytes,
+                basis,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,132 @@
+use layers_core::docker::inspect_image;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::error::LayersError;
+use crate::undo::{UndoAction, UndoHistory};
+
+// Results of images not opened for a while are dropped past this many images
+const MAX_IMAGES: usize = 32;
+
+// Finished analyses by image ID, analyzer version and parameters, managed as Tauri state
+pub struct AnalysisCache {
+    dir: PathBuf,
+    // Keeps pruning from racing a write into the same directory
+    lock: Mutex<()>,
+}
+
+// Where one analysis result is stored
+pub(crate) struct AnalysisKey {
+    path: PathBuf,
+}
+
+impl AnalysisCache {
+    pub fn new(dir: PathBuf) -> Self {
+        AnalysisCache {
+            dir,
+            lock: Mutex::new(()),
+        }
+    }
+
+    // Image IDs are digests of the image config, so a rebuilt or retagged
+    // image gets a new key. Analyzers bump their version when their rules
+    // change. None when the image can't be inspected, e.g. one loaded from
+    // an archive without docker.
+    pub(crate) fn key<P: Serialize>(
+        &self,
+        image: &str,
+        analysis: &str,
+        version: u32,
+        params: &P,
+    ) -> Option<AnalysisKey> {
+        let inspect = inspect_image(image).ok()?;
+        let image_id = inspect["Id"].as_str()?;
+        let hex = image_id.rsplit(':').next().unwrap_or(image_id);
+        let params = serde_json::to_string(params).ok()?;
+        let digest = Sha256::digest(format!("{}\n{}", version, params).as_bytes());
+        let name = format!("{}-{}.json", analysis, &format!("{:x}", digest)[..16]);
+        Some(AnalysisKey {
+            path: self.dir.join(hex).join(name),
+        })
+    }
+
+    pub(crate) fn get<T: DeserializeOwned>(&self, key: &AnalysisKey) -> Option<T> {
+        let _lock = self.lock.lock().unwrap();
+        let content = fs::read(&key.path).ok()?;
+        match serde_json::from_slice(&content) {
+            Ok(result) => {
+                info!("Using cached analysis {:?}", key.path);
+                Some(result)
+            }
+            Err(e) => {
+                // Written by an older build with a different result layout
+                info!("Ignoring cached analysis {:?}: {}", key.path, e);
+                None
+            }
+        }
+    }
+
+    // Caching is best effort, failures are logged and the result still returned
+    pub(crate) fn put<T: Serialize>(&self, key: &AnalysisKey, result: &T) {
+        let _lock = self.lock.lock().unwrap();
+        let Some(image_dir) = key.path.parent() else {
+            return;
+        };
+        let written = fs::create_dir_all(image_dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| serde_json::to_vec(result).map_err(|e| e.to_string()))
+            .and_then(|content| fs::write(&key.path, content).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            warn!("Failed to cache analysis {:?}: {}", key.path, e);
+            return;
+        }
+        self.prune();
+    }
+
+    // Drop the images whose results were written longest ago
+    fn prune(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut images: Vec<(std::time::SystemTime, PathBuf)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some((modified, entry.path()))
+            })
+            .collect();
+        if images.len() <= MAX_IMAGES {
+            return;
+        }
+        images.sort();
+        for (_, path) in &images[..images.len() - MAX_IMAGES] {
+            info!("Evicting cached analyses {:?}", path);
+            let _ = fs::remove_dir_all(path);
+        }
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn clear_analysis_cache(
+    analysis_cache: tauri::State<'_, AnalysisCache>,
+    undo: tauri::State<'_, UndoHistory>,
+) -> Result<(), LayersError> {
+    let _lock = analysis_cache.lock.lock().unwrap();
+    info!("Clearing analysis cache at {:?}", analysis_cache.dir);
+
+    if analysis_cache.dir.exists() {
+        fs::remove_dir_all(&analysis_cache.dir)
+            .map_err(|e| format!("Failed to clear analysis cache: {}", e))?;
+    }
+    undo.record(
+        "Cleared the analysis cache".to_string(),
+        UndoAction::Irreversible,
+    );
+    Ok(())
+}
@@ -0,0 +1,947 @@
+use layers_core::layer_tar::{entry_relative_path, OPAQUE_WHITEOUT, WHITEOUT_PREFIX};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Emitter;
+use tracing::{info, warn};
+
+use crate::blob::LayerBlob;
+use crate::digest_verify::sha256_reader;
+use crate::error::LayersError;
+use crate::exec_safety;
+use crate::rootfs_export::{layer_number, save_image};
+use crate::session::{ImageSession, SessionState};
+use crate::tasks::{Task, TaskRegistry};
+use crate::{finish_task, run_config, DockerImageInfo, DockerLayer, FileItem, TaskStatus};
+
+// docker save tarballs are unpacked here, below the extraction root, so
+// blobs can be read in any order. Each load gets its own directory by task,
+// sessions may load archives side by side.
+const UNPACK_DIR: &str = "archive";
+// Written to a layer directory when its blob could only be read in part,
+// holds the error that stopped the read
+const PARTIAL_FILE: &str = "partial.txt";
+// The unpacked image in a session directory that layer tars are built from
+const IMAGE_DIR: &str = "image";
+// Holds the path of an OCI layout read in place, used instead of IMAGE_DIR
+const IMAGE_ROOT_FILE: &str = "image_root.txt";
+
+// Layers opened side by side would otherwise both save the image
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+const OCI_INDEX_MEDIA_TYPES: [&str; 2] = [
+    "application/vnd.oci.image.index.v1+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+];
+
+// Entry of the manifest.json written by `docker save`
+#[derive(Debug, Deserialize)]
+struct DockerArchiveManifest {
+    #[serde(rename = "Config")]
+    config: String,
+    #[serde(rename = "RepoTags", default)]
+    repo_tags: Option<Vec<String>>,
+    #[serde(rename = "Layers")]
+    layers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OciDescriptor {
+    #[serde(rename = "mediaType", default)]
+    media_type: String,
+    digest: String,
+    #[serde(default)]
+    annotations: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    platform: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OciIndex {
+    manifests: Vec<OciDescriptor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OciManifest {
+    config: OciDescriptor,
+    layers: Vec<OciDescriptor>,
+}
+
+// An image read from disk, independent of the archive format
+struct ArchiveImage {
+    name: String,
+    config: Vec<u8>,
+    // Layer blobs, base layer first
+    layers: Vec<LayerBlob>,
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    serde_json::from_slice(&content).map_err(|e| format!("Failed to parse {:?}: {}", path, e))
+}
+
+// Digests come from the archive, both parts must be plain names so one
+// can't point outside the blobs directory
+fn blob_path(root: &Path, digest: &str) -> Result<PathBuf, String> {
+    let is_name = |part: &str| {
+        !part.is_empty()
+            && part
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+    };
+    match digest.split_once(':') {
+        Some((algorithm, hex)) if is_name(algorithm) && is_name(hex) => {
+            Ok(root.join("blobs").join(algorithm).join(hex))
+        }
+        _ => Err(format!("Invalid digest: {}", digest)),
+    }
+}
+
+// Paths in manifest.json come from the archive, they must be relative,
+// without `..`, and still inside the root once symlinks are resolved
+fn archive_path(root: &Path, name: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(name);
+    let plain = relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if name.is_empty() || !plain {
+        return Err(format!("Invalid archive path: {}", name));
+    }
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {:?}: {}", root, e))?;
+    let path = root
+        .join(relative)
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", name, e))?;
+    if !path.starts_with(&root) {
+        return Err(format!("Archive path leaves the archive: {}", name));
+    }
+    Ok(path)
+}
+
+fn read_docker_archive(root: &Path) -> Result<ArchiveImage, String> {
+    let manifests: Vec<DockerArchiveManifest> = read_json(&root.join("manifest.json"))?;
+    let manifest = manifests
+        .into_iter()
+        .next()
+        .ok_or_else(|| "manifest.json lists no images".to_string())?;
+
+    let config = fs::read(archive_path(root, &manifest.config)?)
+        .map_err(|e| format!("Failed to read image config: {}", e))?;
+
+    Ok(ArchiveImage {
+        name: manifest
+            .repo_tags
+            .and_then(|tags| tags.into_iter().next())
+            .unwrap_or_else(|| "<archive>".to_string()),
+        config,
+        layers: manifest
+            .layers
+            .iter()
+            .map(|l| {
+                Ok(LayerBlob {
+                    path: archive_path(root, l)?,
+                    media_type: None,
+                })
+            })
+            .collect::<Result<_, String>>()?,
+    })
+}
+
+// OCI name of the architecture the app runs on, images run in a linux VM
+// elsewhere but always on the host's architecture
+fn host_architecture() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        "powerpc64" if cfg!(target_endian = "little") => "ppc64le",
+        "loongarch64" => "loong64",
+        arch => arch,
+    }
+}
+
+// Index of the manifest for linux on `architecture`, the first one otherwise
+fn platform_position(manifests: &[OciDescriptor], architecture: &str) -> usize {
+    manifests
+        .iter()
+        .position(|descriptor| {
+            descriptor
+                .platform
+                .as_ref()
+                .is_some_and(|p| p["os"] == "linux" && p["architecture"] == architecture)
+        })
+        .unwrap_or(0)
+}
+
+fn read_oci_layout(root: &Path) -> Result<ArchiveImage, String> {
+    let index: OciIndex = read_json(&root.join("index.json"))?;
+    let mut descriptor = index
+        .manifests
+        .into_iter()
+        .next()
+        .ok_or_else(|| "index.json lists no manifests".to_string())?;
+
+    let name = descriptor
+        .annotations
+        .as_ref()
+        .and_then(|a| a.get("org.opencontainers.image.ref.name"))
+        .and_then(|n| n.as_str())
+        .map(String::from)
+        .unwrap_or_else(|| "<oci-layout>".to_string());
+
+    // Multi-platform images point at another index, pick the host's platform
+    // or the first entry
+    while OCI_INDEX_MEDIA_TYPES.contains(&descriptor.media_type.as_str()) {
+        let nested: OciIndex = read_json(&blob_path(root, &descriptor.digest)?)?;
+        let mut manifests = nested.manifests;
+        let position = platform_position(&manifests, host_architecture());
+        if manifests.is_empty() {
+            return Err("Image index lists no manifests".to_string());
+        }
+        descriptor = manifests.swap_remove(position);
+    }
+
+    let manifest: OciManifest = read_json(&blob_path(root, &descriptor.digest)?)?;
+    let config = fs::read(blob_path(root, &manifest.config.digest)?)
+        .map_err(|e| format!("Failed to read image config: {}", e))?;
+
+    Ok(ArchiveImage {
+        name,
+        config,
+        layers: manifest
+            .layers
+            .iter()
+            .map(|l| {
+                Ok(LayerBlob {
+                    path: blob_path(root, &l.digest)?,
+                    media_type: Some(l.media_type.clone()).filter(|m| !m.is_empty()),
+                })
+            })
+            .collect::<Result<_, String>>()?,
+    })
+}
+
+fn read_image(root: &Path) -> Result<ArchiveImage, String> {
+    if root.join("manifest.json").exists() {
+        read_docker_archive(root)
+    } else if root.join("index.json").exists() {
+        read_oci_layout(root)
+    } else {
+        Err(format!(
+            "{:?} is neither a docker save archive nor an OCI image layout",
+            root
+        ))
+    }
+}
+
+// Layer blobs of an unpacked docker save archive or OCI layout, base layer first
+pub(crate) fn archive_layers(root: &Path) -> Result<Vec<LayerBlob>, String> {
+    Ok(read_image(root)?.layers)
+}
+
+// Config history has one entry per instruction, flagged when it didn't
+// produce a layer. Images without history get one entry per layer.
+fn config_history(config: &serde_json::Value, layer_count: usize) -> Vec<serde_json::Value> {
+    config["history"]
+        .as_array()
+        .cloned()
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| vec![serde_json::json!({}); layer_count])
+}
+
+fn is_empty_layer(entry: &serde_json::Value) -> bool {
+    entry["empty_layer"].as_bool().unwrap_or(false)
+}
+
+// Blobs that make up the filesystem as of layer `number`, base layer first.
+// Metadata-only instructions have no blob, the filesystem as of one of them
+// is the one of the layer below.
+fn layers_up_to(root: &Path, number: usize) -> Result<Vec<LayerBlob>, String> {
+    let mut image = read_image(root)?;
+    let config: serde_json::Value = serde_json::from_slice(&image.config)
+        .map_err(|e| format!("Failed to parse image config: {}", e))?;
+    let history = config_history(&config, image.layers.len());
+    if number == 0 || number > history.len() {
+        return Err(format!("Image has no layer_{}", number));
+    }
+
+    // Layers are numbered newest first
+    let count = history[..=history.len() - number]
+        .iter()
+        .filter(|entry| !is_empty_layer(entry))
+        .count();
+    if count > image.layers.len() {
+        return Err("Image config lists more layers than the archive".to_string());
+    }
+    image.layers.truncate(count);
+    Ok(image.layers)
+}
+
+// The unpacked image a session's layer tars are built from. Archives are
+// kept from loading, images of the daemon are saved on first use.
+fn image_root(task: &Task, session: &ImageSession) -> Result<PathBuf, String> {
+    let _guard = SAVE_LOCK.lock().unwrap();
+    let root = session.dir().join(IMAGE_DIR);
+    if root.exists() {
+        return Ok(root);
+    }
+    if let Ok(path) = fs::read_to_string(session.dir().join(IMAGE_ROOT_FILE)) {
+        return Ok(PathBuf::from(path));
+    }
+
+    info!("Saving {} to build layer filesystems", session.image_id());
+    // Saved next to the final directory so an interrupted save is never used
+    let staging = session.dir().join(format!("{}.tmp", IMAGE_DIR));
+    let _ = fs::remove_dir_all(&staging);
+    task.track_path(&staging);
+    let result = save_image(task, session.image_id(), &staging)
+        .and_then(|()| fs::rename(&staging, &root).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(format!("Failed to save {}: {}", session.image_id(), e));
+    }
+    Ok(root)
+}
+
+// Make sure the filesystem tar of a layer exists. layer_N/fs.tar is the
+// filesystem as of layer N: every layer up to it applied with its whiteouts,
+// the same for daemon images and archives, so two of them can be compared.
+pub(crate) fn ensure_layer_tar(
+    task: &Task,
+    session: &ImageSession,
+    layer_id: &str,
+) -> Result<PathBuf, String> {
+    let number = layer_number(layer_id)?;
+    let layer_dir = session.dir().join(format!("layer_{}", number));
+    let tar_path = layer_dir.join("fs.tar");
+    if tar_path.exists() {
+        return Ok(tar_path);
+    }
+
+    info!("Building the filesystem as of {}", layer_id);
+    fs::create_dir_all(&layer_dir)
+        .map_err(|e| format!("Failed to create layer directory: {}", e))?;
+    let blobs = layers_up_to(&image_root(task, session)?, number)?;
+
+    // Written under another name first, a tar that exists is always complete
+    let staging = layer_dir.join("fs.tar.tmp");
+    task.track_path(&staging);
+    let result = flatten_layers(task, &blobs, &staging).and_then(|_| {
+        fs::rename(&staging, &tar_path)
+            .map_err(|e| format!("Failed to write {:?}: {}", tar_path, e))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    result?;
+    Ok(tar_path)
+}
+
+fn copy_entry<W: io::Write, R: Read>(
+    builder: &mut tar::Builder<W>,
+    entry: &mut tar::Entry<R>,
+    path: &str,
+) -> io::Result<()> {
+    let mut header = entry.header().clone();
+    let entry_type = header.entry_type();
+    if entry_type.is_symlink() || entry_type.is_hard_link() {
+        let target = entry.link_name()?.unwrap_or_default().into_owned();
+        builder.append_link(&mut header, path, target)
+    } else {
+        builder.append_data(&mut header, path, entry)
+    }
+}
+
+// Copy an entry whose data may end early. A truncated entry is dropped
+// again so the output stays a valid tar.
+fn append_entry<R: Read>(
+    builder: &mut tar::Builder<File>,
+    entry: &mut tar::Entry<R>,
+    path: &str,
+) -> io::Result<()> {
+    let start = builder.get_mut().stream_position()?;
+    let result = copy_entry(builder, entry, path);
+    if result.is_err() {
+        let output = builder.get_mut();
+        output.set_len(start)?;
+        output.seek(SeekFrom::Start(start))?;
+    }
+    result
+}
+
+// Layers of a session that could only be read in part, as (layer ID, error)
+pub(crate) fn partial_layers(session_dir: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(session_dir) else {
+        return Vec::new();
+    };
+    let mut layers: Vec<(String, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let error = fs::read_to_string(entry.path().join(PARTIAL_FILE)).ok()?;
+            Some((entry.file_name().to_string_lossy().to_string(), error))
+        })
+        .collect();
+    layers.sort();
+    layers
+}
+
+// What flatten_layers could read of one layer
+#[derive(Debug, Default)]
+pub(crate) struct LayerRead {
+    // Size of the layer's own entries, including the ones upper layers hide
+    pub size_bytes: u64,
+    // The error that stopped reading the layer, what came before it is merged
+    pub partial: Option<String>,
+}
+
+// Merge layers into the filesystem a container would see. Layers are walked
+// from the top so the newest version of a path wins, whiteouts hide the
+// paths below them and are left out. Returns what was read of every layer,
+// base layer first like `layers`.
+pub(crate) fn flatten_layers(
+    task: &Task,
+    layers: &[LayerBlob],
+    destination: &Path,
+) -> Result<Vec<LayerRead>, String> {
+    let output = File::create(destination)
+        .map_err(|e| format!("Failed to create {:?}: {}", destination, e))?;
+    let mut builder = tar::Builder::new(output);
+
+    let mut seen: HashSet<String> = HashSet::new();
+    // Paths deleted by an upper layer, hidden together with their children
+    let mut deleted: Vec<String> = Vec::new();
+    // Directories whose lower layer contents were replaced by an upper layer
+    let mut opaque: Vec<String> = Vec::new();
+    let mut reads = Vec::with_capacity(layers.len());
+
+    for layer in layers.iter().rev() {
+        let mut archive = tar::Archive::new(layer.open()?);
+        let mut read = LayerRead::default();
+        let mut layer_deleted = Vec::new();
+        let mut layer_opaque = Vec::new();
+
+        let entries = archive
+            .entries()
+            .map_err(|e| format!("Failed to read layer {:?}: {}", layer.path, e))?;
+        for entry in entries {
+            task.check_cancelled()?;
+            // A truncated or corrupt layer keeps the entries before the bad one
+            let mut entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    read.partial = Some(format!("Failed to read layer entry: {}", e));
+                    break;
+                }
+            };
+            let path = match entry.path() {
+                Ok(path) => entry_relative_path(&path),
+                Err(e) => {
+                    read.partial = Some(format!("Failed to read layer entry path: {}", e));
+                    break;
+                }
+            };
+            if path.is_empty() {
+                continue;
+            }
+            read.size_bytes += entry.size();
+
+            let (parent, name) = path.rsplit_once('/').unwrap_or(("", &path));
+            if name == OPAQUE_WHITEOUT {
+                layer_opaque.push(parent.to_string());
+                continue;
+            }
+            if let Some(target) = name.strip_prefix(WHITEOUT_PREFIX) {
+                layer_deleted.push(if parent.is_empty() {
+                    target.to_string()
+                } else {
+                    format!("{}/{}", parent, target)
+                });
+                continue;
+            }
+
+            let is_hidden = deleted
+                .iter()
+                .any(|d| path == *d || path.starts_with(&format!("{}/", d)))
+                || opaque
+                    .iter()
+                    .any(|o| o.is_empty() || path.starts_with(&format!("{}/", o)));
+            if is_hidden || !seen.insert(path.clone()) {
+                continue;
+            }
+
+            if let Err(e) = append_entry(&mut builder, &mut entry, &path) {
+                // Another layer may still have the path
+                seen.remove(&path);
+                read.partial = Some(format!("Failed to read {}: {}", path, e));
+                break;
+            }
+        }
+
+        if let Some(error) = &read.partial {
+            warn!(
+                "Layer {:?} is partial, merging what was read: {}",
+                layer.path, error
+            );
+        }
+        // Whiteouts only affect the layers below the one that contains them
+        deleted.extend(layer_deleted);
+        opaque.extend(layer_opaque);
+        reads.push(read);
+    }
+
+    builder
+        .finish()
+        .map_err(|e| format!("Failed to finish {:?}: {}", destination, e))?;
+    reads.reverse();
+    Ok(reads)
+}
+
+pub(crate) fn format_size(size_bytes: u64) -> String {
+    if size_bytes < 1024 {
+        format!("{}B", size_bytes)
+    } else if size_bytes < 1024 * 1024 {
+        format!("{:.1}KB", size_bytes as f64 / 1024.0)
+    } else if size_bytes < 1024 * 1024 * 1024 {
+        format!("{:.1}MB", size_bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1}GB", size_bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+    }
+}
+
+fn unpack_dir(task: &Task) -> PathBuf {
+    exec_safety::extraction_root()
+        .join(UNPACK_DIR)
+        .join(task.id.to_string())
+}
+
+// Unpack tarballs, directories (OCI layouts) are read in place
+fn prepare_archive_root(task: &Task, path: &Path) -> Result<PathBuf, String> {
+    if path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+
+    let unpack_dir = &unpack_dir(task);
+    if unpack_dir.exists() {
+        fs::remove_dir_all(unpack_dir)
+            .map_err(|e| format!("Failed to clean up {:?}: {}", unpack_dir, e))?;
+    }
+    fs::create_dir_all(unpack_dir)
+        .map_err(|e| format!("Failed to create {:?}: {}", unpack_dir, e))?;
+    task.track_path(unpack_dir);
+
+    let file = File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    tar::Archive::new(file)
+        .unpack(unpack_dir)
+        .map_err(|e| format!("Failed to unpack {:?}: {}", path, e))?;
+    exec_safety::strip_execute_bits(unpack_dir);
+    Ok(unpack_dir.to_path_buf())
+}
+
+async fn load_image_archive_task(
+    window: &tauri::Window,
+    task: &Task,
+    sessions: &SessionState,
+    path: String,
+) -> Result<DockerImageInfo, String> {
+    let update_status = |message: &str, progress: f32, is_complete: bool, error: Option<String>| {
+        let _ = window.emit(
+            "task_status",
+            TaskStatus {
+                task_id: task.id,
+                message: message.to_string(),
+                progress,
+                is_complete,
+                error,
+                phase: None,
+                transfer: None,
+            },
+        );
+    };
+
+    update_status("Reading image archive...", 0.0, false, None);
+
+    let root = prepare_archive_root(task, Path::new(&path))?;
+    info!("Loading image archive from {}", path);
+    let image = read_image(&root)?;
+
+    let config: serde_json::Value = serde_json::from_slice(&image.config)
+        .map_err(|e| format!("Failed to parse image config: {}", e))?;
+    let image_id = sha256_reader(image.config.as_slice())
+        .map_err(|e| format!("Failed to hash image config: {}", e))?;
+    let session = sessions.open_selected(image_id.clone(), image.name.clone())?;
+
+    // Layer tars are built from the archive when a layer is opened, so it
+    // stays with the session. OCI layout directories are read in place.
+    let image = if Path::new(&path).is_dir() {
+        fs::write(
+            session.dir().join(IMAGE_ROOT_FILE),
+            root.to_string_lossy().as_bytes(),
+        )
+        .map_err(|e| format!("Failed to write image root file: {}", e))?;
+        image
+    } else {
+        let kept = session.dir().join(IMAGE_DIR);
+        fs::rename(&root, &kept)
+            .map_err(|e| format!("Failed to move {:?} to {:?}: {}", root, kept, e))?;
+        read_image(&kept)?
+    };
+
+    let history = config_history(&config, image.layers.len());
+    let diff_ids: Vec<String> = config["rootfs"]["diff_ids"]
+        .as_array()
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| id.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // The file browser works on the merged filesystem, build it without a
+    // daemon. Reading every layer for it also gives their sizes and which
+    // ones could only be read in part.
+    update_status("Merging layers...", 0.1, false, None);
+    let layers_dir = session.dir();
+    let current_dir = layers_dir.join("current_layer");
+    if current_dir.exists() {
+        fs::remove_dir_all(&current_dir)
+            .map_err(|e| format!("Failed to clean up layer directory: {}", e))?;
+    }
+    fs::create_dir_all(current_dir.join("fs"))
+        .map_err(|e| format!("Failed to create layer directory: {}", e))?;
+    let reads = flatten_layers(task, &image.layers, &current_dir.join("fs.tar"))?;
+
+    let mut layers = Vec::new();
+    let mut total_size = 0;
+    let mut next_blob = 0;
+
+    // Layers are numbered newest first, like export_image_layers does
+    let total_layers = history.len();
+    for (position, entry) in history.iter().enumerate() {
+        task.check_cancelled()?;
+        let number = total_layers - position;
+        let layer_dir_name = format!("layer_{}", number);
+        let layer_dir = layers_dir.join(&layer_dir_name);
+        fs::create_dir_all(&layer_dir)
+            .map_err(|e| format!("Failed to create layer directory: {}", e))?;
+        task.track_path(&layer_dir);
+
+        update_status(
+            &format!("Reading layer {} of {}", position + 1, total_layers),
+            0.8 + 0.2 * (position as f32 / total_layers as f32),
+            false,
+            None,
+        );
+
+        let command = entry["created_by"].as_str().unwrap_or("").to_string();
+        let created = entry["created"].as_str().unwrap_or("").to_string();
+
+        let mut partial = None;
+        let (id, size_bytes) = if is_empty_layer(entry) {
+            ("<missing>".to_string(), 0)
+        } else {
+            let read = reads
+                .get(next_blob)
+                .ok_or_else(|| "Image config lists more layers than the archive".to_string())?;
+            partial = read.partial.clone();
+            if let Some(error) = &partial {
+                warn!("Layer {} is partial: {}", number, error);
+                fs::write(layer_dir.join(PARTIAL_FILE), error)
+                    .map_err(|e| format!("Failed to write partial layer file: {}", e))?;
+            }
+            let id = diff_ids.get(next_blob).cloned().unwrap_or_default();
+            next_blob += 1;
+            (id, read.size_bytes)
+        };
+        total_size += size_bytes;
+        let size = format_size(size_bytes);
+
+        fs::write(layer_dir.join("command.txt"), &command)
+            .map_err(|e| format!("Failed to write command file: {}", e))?;
+        fs::write(
+            layer_dir.join("layer_info.txt"),
+            format!(
+                "ID: {}\nCreated: {}\nSize: {}\nCommand: {}",
+                id, created, size, command
+            ),
+        )
+        .map_err(|e| format!("Failed to write layer info file: {}", e))?;
+
+        layers.push(DockerLayer {
+            id,
+            name: format!("Layer {}", number),
+            command,
+            size,
+            size_bytes,
+            createdAt: created,
+            partial,
+            files: vec![
+                FileItem {
+                    name: "layer_info.txt".to_string(),
+                    file_type: "file".to_string(),
+                    path: layer_dir
+                        .join("layer_info.txt")
+                        .to_string_lossy()
+                        .to_string(),
+                    exact_path: None,
+                    size: Some("1KB".to_string()),
+                    size_bytes: Some(1024),
+                    link_target: None,
+                    broken_link: false,
+                    metadata: None,
+                    attributes: None,
+                    owner: None,
+                },
+                FileItem {
+                    name: "command.txt".to_string(),
+                    file_type: "file".to_string(),
+                    path: layer_dir.join("command.txt").to_string_lossy().to_string(),
+                    exact_path: None,
+                    size: Some("512B".to_string()),
+                    size_bytes: Some(512),
+                    link_target: None,
+                    broken_link: false,
+                    metadata: None,
+                    attributes: None,
+                    owner: None,
+                },
+            ],
+        });
+    }
+
+    // Newest layer first, matching the layer numbering
+    layers.reverse();
+
+    let partial = layers
+        .iter()
+        .filter(|layer| layer.partial.is_some())
+        .count();
+    let message = if partial > 0 {
+        format!(
+            "Image archive loaded, {} layers could only be read in part",
+            partial
+        )
+    } else {
+        "Image archive loaded".to_string()
+    };
+    update_status(&message, 1.0, true, None);
+    info!("Loaded {} with {} layers", image.name, layers.len());
+    Ok(DockerImageInfo {
+        id: image_id,
+        name: image.name,
+        created: config["created"].as_str().unwrap_or("Unknown").to_string(),
+        size: format_size(total_size),
+        size_bytes: total_size,
+        layers,
+        config: Some(run_config(&config["config"])),
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn load_image_archive(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    path: String,
+) -> Result<DockerImageInfo, LayersError> {
+    let task = tasks.start();
+    let result = load_image_archive_task(&window, &task, &session, path).await;
+    // A loaded archive was moved to its session, only a failed load leaves it
+    let _ = fs::remove_dir_all(unpack_dir(&task));
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::scratch_dir;
+
+    fn write_json(path: &Path, value: serde_json::Value) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, serde_json::to_vec(&value).unwrap()).unwrap();
+    }
+
+    fn descriptor(media_type: &str, digest: &str, architecture: &str) -> serde_json::Value {
+        serde_json::json!({
+            "mediaType": media_type,
+            "digest": digest,
+            "platform": { "os": "linux", "architecture": architecture }
+        })
+    }
+
+    // An OCI layout whose index points at a multi-platform index, the
+    // manifest of each platform has a config naming its architecture
+    fn write_oci_layout(root: &Path) {
+        write_json(
+            &root.join("index.json"),
+            serde_json::json!({ "manifests": [{
+                "mediaType": OCI_INDEX_MEDIA_TYPES[0],
+                "digest": "sha256:index",
+                "annotations": { "org.opencontainers.image.ref.name": "app:1" }
+            }] }),
+        );
+        let blobs = root.join("blobs").join("sha256");
+        let manifest = "application/vnd.oci.image.manifest.v1+json";
+        write_json(
+            &blobs.join("index"),
+            serde_json::json!({ "manifests": [
+                descriptor(manifest, "sha256:amd64", "amd64"),
+                descriptor(manifest, "sha256:arm64", "arm64"),
+            ] }),
+        );
+        for architecture in ["amd64", "arm64"] {
+            write_json(
+                &blobs.join(architecture),
+                serde_json::json!({
+                    "config": { "digest": format!("sha256:config{}", architecture) },
+                    "layers": [{
+                        "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+                        "digest": format!("sha256:layer{}", architecture)
+                    }]
+                }),
+            );
+            write_json(
+                &blobs.join(format!("config{}", architecture)),
+                serde_json::json!({ "architecture": architecture }),
+            );
+        }
+    }
+
+    #[test]
+    fn docker_save_archives_are_read_from_their_manifest() {
+        let root = scratch_dir("archive-docker");
+        write_json(
+            &root.join("manifest.json"),
+            serde_json::json!([{
+                "Config": "config.json",
+                "RepoTags": ["app:1"],
+                "Layers": ["base/layer.tar", "top/layer.tar"]
+            }]),
+        );
+        write_json(&root.join("config.json"), serde_json::json!({}));
+        for layer in ["base", "top"] {
+            fs::create_dir_all(root.join(layer)).unwrap();
+            fs::write(root.join(layer).join("layer.tar"), b"").unwrap();
+        }
+
+        let image = read_image(&root).unwrap();
+        assert_eq!(image.name, "app:1");
+        assert_eq!(image.config, b"{}");
+        let layers: Vec<PathBuf> = image.layers.into_iter().map(|l| l.path).collect();
+        let resolved = root.canonicalize().unwrap();
+        assert_eq!(
+            layers,
+            vec![
+                resolved.join("base/layer.tar"),
+                resolved.join("top/layer.tar")
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn docker_save_manifests_cannot_point_outside_the_archive() {
+        let root = scratch_dir("archive-escape");
+        let outside = scratch_dir("archive-escape-outside");
+        write_json(&outside.join("config.json"), serde_json::json!({}));
+        fs::create_dir_all(root.join("inside")).unwrap();
+        fs::write(root.join("inside/config.json"), b"{}").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(outside.join("config.json"), root.join("link.json")).unwrap();
+
+        let sibling = outside.file_name().unwrap().to_string_lossy();
+        let mut configs = vec![
+            outside.join("config.json").to_string_lossy().into_owned(),
+            format!("../{}/config.json", sibling),
+            format!("inside/../../{}/config.json", sibling),
+        ];
+        if cfg!(unix) {
+            configs.push("link.json".to_string());
+        }
+        for config in configs {
+            write_json(
+                &root.join("manifest.json"),
+                serde_json::json!([{ "Config": config, "Layers": [] }]),
+            );
+            assert!(read_image(&root).is_err(), "{}", config);
+        }
+
+        write_json(
+            &root.join("manifest.json"),
+            serde_json::json!([{ "Config": "inside/config.json", "Layers": ["/etc/passwd"] }]),
+        );
+        assert!(read_image(&root).is_err());
+        fs::remove_dir_all(root).unwrap();
+        fs::remove_dir_all(outside).unwrap();
+    }
+
+    #[test]
+    fn oci_layouts_open_the_host_platform() {
+        let root = scratch_dir("archive-oci");
+        write_oci_layout(&root);
+
+        // Hosts with neither architecture get the first entry
+        let expected = match host_architecture() {
+            "arm64" => "arm64",
+            _ => "amd64",
+        };
+        let image = read_image(&root).unwrap();
+        let config: serde_json::Value = serde_json::from_slice(&image.config).unwrap();
+        assert_eq!(image.name, "app:1");
+        assert_eq!(config["architecture"], expected);
+        assert_eq!(
+            image.layers[0].path,
+            root.join("blobs/sha256").join(format!("layer{}", expected))
+        );
+        assert_eq!(
+            image.layers[0].media_type.as_deref(),
+            Some("application/vnd.oci.image.layer.v1.tar+gzip")
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn other_directories_are_not_images() {
+        let root = scratch_dir("archive-neither");
+        assert!(read_image(&root).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn platform_position_prefers_the_architecture() {
+        let manifests: Vec<OciDescriptor> = ["amd64", "arm64"]
+            .iter()
+            .map(|architecture| {
+                serde_json::from_value(descriptor("", "sha256:ab", architecture)).unwrap()
+            })
+            .collect();
+        assert_eq!(platform_position(&manifests, "arm64"), 1);
+        assert_eq!(platform_position(&manifests, "amd64"), 0);
+        assert_eq!(platform_position(&manifests, "s390x"), 0);
+    }
+
+    #[test]
+    fn blob_paths_stay_in_the_blobs_directory() {
+        let root = Path::new("/layout");
+        assert_eq!(
+            blob_path(root, "sha256:0a1b").unwrap(),
+            root.join("blobs/sha256/0a1b")
+        );
+        for digest in [
+            "sha256:../../x",
+            "../sha256:ab",
+            "sha256:",
+            "sha256:AB",
+            "sha256:a/b",
+            "0a1b",
+        ] {
+            assert!(blob_path(root, digest).is_err(), "{}", digest);
+        }
+    }
+}
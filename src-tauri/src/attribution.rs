@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::search_index::session_search_index;
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerShare {
+    // Matches the "layer_N" IDs produced by export_image_layers
+    layer_id: String,
+    created_by: String,
+    bytes: u64,
+    // Share of the directory's bytes, the segments of a row add up to 1
+    fraction: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DirectoryAttribution {
+    path: String,
+    total_bytes: u64,
+    file_count: usize,
+    // Base layer first, layers that contribute nothing are left out
+    layers: Vec<LayerShare>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttributionResult {
+    directory: DirectoryAttribution,
+    // Immediate subdirectories, largest first
+    children: Vec<DirectoryAttribution>,
+}
+
+// Bytes per layer of the files below one directory of the merged filesystem
+#[derive(Default)]
+struct Totals {
+    bytes_by_layer: HashMap<usize, u64>,
+    file_count: usize,
+}
+
+impl Totals {
+    fn add(&mut self, layer: usize, size: u64) {
+        *self.bytes_by_layer.entry(layer).or_default() += size;
+        self.file_count += 1;
+    }
+
+    fn into_attribution(self, path: String, layers: &[(&str, &str)]) -> DirectoryAttribution {
+        let total_bytes: u64 = self.bytes_by_layer.values().sum();
+        let mut shares: Vec<(usize, u64)> = self.bytes_by_layer.into_iter().collect();
+        shares.sort_by_key(|(layer, _)| *layer);
+        DirectoryAttribution {
+            path,
+            total_bytes,
+            file_count: self.file_count,
+            layers: shares
+                .into_iter()
+                .map(|(layer, bytes)| LayerShare {
+                    layer_id: layers[layer].0.to_string(),
+                    created_by: layers[layer].1.to_string(),
+                    bytes,
+                    fraction: bytes as f64 / total_bytes.max(1) as f64,
+                })
+                .collect(),
+        }
+    }
+}
+
+// Which layers the bytes below a directory of the final image come from, for
+// the directory itself and each of its subdirectories
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_directory_attribution(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    path: Option<String>,
+) -> Result<AttributionResult, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let path = path.unwrap_or_else(|| "/".to_string());
+    let prefix = format!("{}/", path.trim_end_matches('/'));
+    info!("Attributing {} to layers", path);
+
+    let index = session_search_index(&tasks, &session, false)?;
+    let layers = index.layer_info();
+
+    let mut directory = Totals::default();
+    let mut children: HashMap<String, Totals> = HashMap::new();
+    for file in index.merged_files() {
+        let Some(relative) = file.path.strip_prefix(&prefix) else {
+            continue;
+        };
+        directory.add(file.layer, file.size);
+        // Files directly in the directory have no row of their own
+        if let Some((child, _)) = relative.split_once('/') {
+            children
+                .entry(format!("{}{}", prefix, child))
+                .or_default()
+                .add(file.layer, file.size);
+        }
+    }
+
+    let mut children: Vec<DirectoryAttribution> = children
+        .into_iter()
+        .map(|(child, totals)| totals.into_attribution(child, &layers))
+        .collect();
+    children.sort_by(|a, b| {
+        b.total_bytes
+            .cmp(&a.total_bytes)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+
+    Ok(AttributionResult {
+        directory: directory.into_attribution(path, &layers),
+        children,
+    })
+}
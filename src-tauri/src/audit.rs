@@ -0,0 +1,320 @@
+use layers_core::docker::docker_command;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::error::LayersError;
+use crate::permissions;
+
+// Docker is driven from helpers without access to Tauri state, so the log
+// location is set once at startup
+static LOG_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+// Keeps appends from interleaving
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+const DEFAULT_LIMIT: usize = 500;
+
+// Docker subcommands that change what the daemon holds
+const MUTATING_SUBCOMMANDS: [&str; 14] = [
+    "build", "commit", "cp", "create", "import", "kill", "load", "pull", "push", "rm", "rmi",
+    "run", "start", "tag",
+];
+// Network commands, named with their group by docker_subcommand
+const MUTATING_NETWORK_SUBCOMMANDS: [&str; 5] = [
+    "network create",
+    "network rm",
+    "network connect",
+    "network disconnect",
+    "network prune",
+];
+// Read-only unless they write a file with -o
+const WRITING_SUBCOMMANDS: [&str; 2] = ["export", "save"];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEntry {
+    timestamp_ms: u64,
+    // e.g. "docker pull" or "write_file"
+    operation: String,
+    // Image, container or path the operation touched
+    target: String,
+    success: bool,
+    error: Option<String>,
+}
+
+pub fn init(path: PathBuf) {
+    info!("Recording operations to {:?}", path);
+    *LOG_PATH.write().unwrap() = Some(path);
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// The log is append-only, entries are never rewritten or removed
+fn append(operation: String, target: String, error: Option<String>) {
+    let Some(path) = LOG_PATH.read().unwrap().clone() else {
+        return;
+    };
+    let entry = AuditEntry {
+        timestamp_ms: now_ms(),
+        operation,
+        target,
+        success: error.is_none(),
+        error,
+    };
+
+    let _lock = WRITE_LOCK.lock().unwrap();
+    let written = serde_json::to_string(&entry)
+        .map_err(|e| e.to_string())
+        .and_then(|line| {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| e.to_string())?;
+            writeln!(file, "{}", line).map_err(|e| e.to_string())
+        });
+    if let Err(e) = written {
+        warn!("Failed to write audit log entry: {}", e);
+    }
+}
+
+// Record an operation the app performed itself, e.g. writing an export
+pub(crate) fn record<T, E: Display>(operation: &str, target: &str, result: &Result<T, E>) {
+    append(
+        operation.to_string(),
+        target.to_string(),
+        result.as_ref().err().map(|e| e.to_string()),
+    );
+}
+
+pub(crate) fn record_write<T, E: Display>(path: &Path, result: &Result<T, E>) {
+    record("write_file", &path.to_string_lossy(), result);
+}
+
+// Write `content` next to `path` and rename it over the file, so a crash
+// halfway through leaves the previous content in place
+pub(crate) fn write_atomically(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(".tmp");
+    let staging = PathBuf::from(staging);
+    let written = fs::write(&staging, content).and_then(|_| fs::rename(&staging, path));
+    record_write(path, &written);
+    if written.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    written
+}
+
+// The subcommand of a docker command line and its arguments, "docker image
+// rm x" gives "rmi" like "docker rmi x". Network commands keep their group,
+// "docker network rm x" gives "network rm".
+pub(crate) fn docker_subcommand(args: &[String]) -> Option<(&str, &[String])> {
+    let (subcommand, rest) = match args {
+        [group, subcommand, rest @ ..]
+            if group == "image" || group == "container" || group == "network" =>
+        {
+            (subcommand.as_str(), rest)
+        }
+        [subcommand, rest @ ..] => (subcommand.as_str(), rest),
+        [] => return None,
+    };
+    let subcommand = match (args[0].as_str(), subcommand) {
+        ("image", "rm") => "rmi",
+        ("network", "create") => "network create",
+        ("network", "rm" | "remove") => "network rm",
+        ("network", "connect") => "network connect",
+        ("network", "disconnect") => "network disconnect",
+        ("network", "prune") => "network prune",
+        ("network", _) => "network",
+        (_, subcommand) => subcommand,
+    };
+    Some((subcommand, rest))
+}
+
+fn docker_operation(args: &[String]) -> Option<(String, Vec<String>)> {
+    let (subcommand, rest) = docker_subcommand(args)?;
+    let writes_file = WRITING_SUBCOMMANDS.contains(&subcommand)
+        && rest.iter().any(|arg| arg == "-o" || arg == "--output");
+    let mutates = MUTATING_SUBCOMMANDS.contains(&subcommand)
+        || MUTATING_NETWORK_SUBCOMMANDS.contains(&subcommand);
+    if !mutates && !writes_file {
+        return None;
+    }
+    Some((format!("docker {}", subcommand), rest.to_vec()))
+}
+
+// Downloads of shared rulesets, git clone and pull or a curl of one file.
+// curl is only logged with the URL it fetched.
+fn fetch_operation(program: &str, args: &[String]) -> Option<(String, Vec<String>)> {
+    match program {
+        "git" => {
+            // "-C <dir>" comes before the subcommand
+            let rest = match args {
+                [flag, _, rest @ ..] if flag == "-C" => rest,
+                rest => rest,
+            };
+            let (subcommand, rest) = rest.split_first()?;
+            matches!(subcommand.as_str(), "clone" | "pull")
+                .then(|| (format!("git {}", subcommand), rest.to_vec()))
+        }
+        "curl" => Some((
+            "curl".to_string(),
+            args.last().cloned().into_iter().collect(),
+        )),
+        _ => None,
+    }
+}
+
+// Record a docker command or ruleset download once it has run, other
+// commands are ignored
+pub(crate) fn record_command(command: &Command, result: &io::Result<Output>) {
+    let program = command.get_program().to_string_lossy();
+    let args: Vec<String> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect();
+    let operation = if program == "docker" {
+        docker_operation(&args)
+    } else {
+        fetch_operation(&program, &args)
+    };
+    let Some((operation, target)) = operation else {
+        return;
+    };
+
+    let error = match result {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => Some(e.to_string()),
+    };
+    append(operation, target.join(" "), error);
+}
+
+// `Command::output` for docker calls that aren't run through a task
+pub(crate) fn docker(args: &[&str]) -> io::Result<Output> {
+    let mut command = docker_command();
+    command.args(args);
+    let result = permissions::check_command(&command).and_then(|_| command.output());
+    record_command(&command, &result);
+    result
+}
+
+fn read_entries() -> Result<Vec<AuditEntry>, String> {
+    let Some(path) = LOG_PATH.read().unwrap().clone() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read audit log: {}", e))?;
+    // A line cut short by a crash is skipped rather than hiding the rest
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+// Newest entries first, optionally only one kind of operation
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_audit_log(
+    operation: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<AuditEntry>, LayersError> {
+    let mut entries = read_entries()?;
+    if let Some(operation) = &operation {
+        entries.retain(|entry| &entry.operation == operation);
+    }
+    entries.reverse();
+    entries.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(entries)
+}
+
+// Write the whole log as a JSON array, oldest entry first
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn export_audit_log(destination: String) -> Result<usize, LayersError> {
+    info!("Exporting audit log to {}", destination);
+    let entries = read_entries()?;
+    let content = serde_json::to_string_pretty(&entries)
+        .map_err(|e| format!("Failed to serialize audit log: {}", e))?;
+
+    let destination = PathBuf::from(destination);
+    let result = fs::write(&destination, content);
+    record_write(&destination, &result);
+    result.map_err(|e| format!("Failed to write {:?}: {}", destination, e))?;
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::scratch_dir;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn ruleset_downloads_are_fetch_operations() {
+        assert_eq!(
+            fetch_operation("git", &args("clone --depth 1 -- https://x/r.git /c")),
+            Some((
+                "git clone".to_string(),
+                args("--depth 1 -- https://x/r.git /c")
+            ))
+        );
+        assert_eq!(
+            fetch_operation("git", &args("-C /c pull --ff-only")),
+            Some(("git pull".to_string(), args("--ff-only")))
+        );
+        assert_eq!(fetch_operation("git", &args("-C /c rev-parse HEAD")), None);
+        assert_eq!(
+            fetch_operation(
+                "curl",
+                &args("--silent --output /c/lint.json https://x/lint.json")
+            ),
+            Some(("curl".to_string(), args("https://x/lint.json")))
+        );
+        assert_eq!(fetch_operation("tar", &args("-xf a.tar")), None);
+    }
+
+    #[test]
+    fn atomic_writes_replace_the_file() {
+        let dir = scratch_dir("audit-atomic");
+        let path = dir.join("settings.json");
+        fs::write(&path, "old").unwrap();
+
+        write_atomically(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!dir.join("settings.json.tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_atomic_writes_leave_no_staging_file() {
+        let dir = scratch_dir("audit-atomic-failed");
+        // A rename can't replace a non-empty directory
+        let path = dir.join("settings.json");
+        fs::create_dir_all(path.join("inner")).unwrap();
+
+        assert!(write_atomically(&path, "new").is_err());
+        assert!(!dir.join("settings.json.tmp").exists());
+        assert!(path.join("inner").is_dir());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
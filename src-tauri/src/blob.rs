@@ -0,0 +1,168 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    // From an OCI or Docker layer media type, None for other kinds of blobs
+    pub(crate) fn from_media_type(media_type: &str) -> Option<Self> {
+        if media_type.ends_with("+gzip") || media_type.ends_with(".tar.gzip") {
+            Some(Compression::Gzip)
+        } else if media_type.ends_with("+zstd") {
+            Some(Compression::Zstd)
+        } else if media_type.ends_with(".tar") {
+            Some(Compression::None)
+        } else {
+            None
+        }
+    }
+
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        if magic.starts_with(&GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if magic.starts_with(&ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+}
+
+// A layer blob of a docker save archive or OCI layout
+#[derive(Debug, Clone)]
+pub(crate) struct LayerBlob {
+    pub(crate) path: PathBuf,
+    // Known for OCI layouts only, manifest.json doesn't list them
+    pub(crate) media_type: Option<String>,
+}
+
+impl LayerBlob {
+    pub(crate) fn open(&self) -> Result<Box<dyn Read>, String> {
+        open(&self.path, self.media_type.as_deref())
+    }
+}
+
+// Streaming decoder picked by the blob's magic bytes, reads every gzip member
+pub(crate) fn decompress<'a, R: BufRead + 'a>(
+    mut reader: R,
+    media_type: Option<&str>,
+) -> io::Result<Box<dyn Read + 'a>> {
+    let compression = Compression::from_magic(reader.fill_buf()?).unwrap_or(Compression::None);
+    let labelled = media_type.and_then(Compression::from_media_type);
+    if labelled.is_some_and(|labelled| labelled != compression) {
+        info!(
+            "Blob labelled {} is {:?} compressed",
+            media_type.unwrap_or_default(),
+            compression
+        );
+    }
+
+    Ok(match compression {
+        Compression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+        Compression::None => Box::new(reader),
+    })
+}
+
+// Open a layer blob from disk as a plain tar stream
+pub(crate) fn open(path: &Path, media_type: Option<&str>) -> Result<Box<dyn Read>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open layer {:?}: {}", path, e))?;
+    decompress(BufReader::new(file), media_type)
+        .map_err(|e| format!("Failed to read layer {:?}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn read_all(blob: &[u8], media_type: Option<&str>) -> Vec<u8> {
+        let mut out = Vec::new();
+        decompress(blob, media_type)
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn gzip_blobs_are_decompressed() {
+        let blob = gzip(b"layer contents");
+        assert_eq!(read_all(&blob, None), b"layer contents");
+    }
+
+    #[test]
+    fn every_gzip_member_is_read() {
+        let mut blob = gzip(b"first member, ");
+        blob.extend(gzip(b"second member"));
+        assert_eq!(read_all(&blob, None), b"first member, second member");
+    }
+
+    #[test]
+    fn zstd_blobs_are_decompressed() {
+        let blob = zstd::encode_all(&b"layer contents"[..], 0).unwrap();
+        assert_eq!(
+            read_all(&blob, Some("application/vnd.oci.image.layer.v1.tar+zstd")),
+            b"layer contents"
+        );
+    }
+
+    #[test]
+    fn plain_tars_pass_through_whatever_their_label() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        builder
+            .append_data(&mut header, "file", &b"data"[..])
+            .unwrap();
+        let blob = builder.into_inner().unwrap();
+
+        assert_eq!(
+            read_all(
+                &blob,
+                Some("application/vnd.docker.image.rootfs.diff.tar.gzip")
+            ),
+            blob
+        );
+    }
+
+    #[test]
+    fn compression_is_read_from_media_types() {
+        assert_eq!(
+            Compression::from_media_type("application/vnd.oci.image.layer.v1.tar+gzip"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_media_type("application/vnd.docker.image.rootfs.diff.tar.gzip"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_media_type("application/vnd.oci.image.layer.v1.tar+zstd"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::from_media_type("application/vnd.oci.image.layer.v1.tar"),
+            Some(Compression::None)
+        );
+        assert_eq!(
+            Compression::from_media_type("application/vnd.oci.image.config.v1+json"),
+            None
+        );
+    }
+}
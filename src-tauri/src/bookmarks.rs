@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::session::{resolve_image, SessionState};
+
+// Reports include the bookmarks of their image and are built without Tauri
+// state, so the file location is set once at startup
+static BOOKMARKS_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+// Keeps read-modify-write cycles from losing bookmarks
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+// A file or directory marked while auditing an image
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Bookmark {
+    id: u64,
+    // Full image ID, bookmarks stay with the image when tags move
+    image_id: String,
+    // Tag or short ID the image was bookmarked under
+    reference: String,
+    // "layer_N" as in export_image_layers, None for the whole image
+    pub(crate) layer_id: Option<String>,
+    // Absolute container path
+    pub(crate) path: String,
+    pub(crate) note: String,
+    // Seconds since the epoch
+    created_at: u64,
+}
+
+pub fn init(path: PathBuf) {
+    info!("Reading bookmarks from {:?}", path);
+    *BOOKMARKS_PATH.write().unwrap() = Some(path);
+}
+
+// A file that can't be read or parsed is an error rather than an empty list,
+// the next save would otherwise replace every bookmark in it
+fn load(path: &Path) -> Result<Vec<Bookmark>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read(path).map_err(|e| format!("Failed to read bookmarks: {}", e))?;
+    serde_json::from_slice(&content).map_err(|e| format!("Failed to parse bookmarks: {}", e))
+}
+
+fn bookmarks_path() -> Result<PathBuf, String> {
+    BOOKMARKS_PATH
+        .read()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| "Bookmarks are not available".to_string())
+}
+
+fn save(path: &Path, bookmarks: &[Bookmark]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+    }
+    let content = serde_json::to_vec_pretty(bookmarks)
+        .map_err(|e| format!("Failed to serialize bookmarks: {}", e))?;
+    audit::write_atomically(path, content).map_err(|e| format!("Failed to save bookmarks: {}", e))
+}
+
+// Bookmarks of an image, oldest first
+pub(crate) fn for_image(image_id: &str) -> Result<Vec<Bookmark>, String> {
+    let Ok(path) = bookmarks_path() else {
+        return Ok(Vec::new());
+    };
+    Ok(load(&path)?
+        .into_iter()
+        .filter(|bookmark| bookmark.image_id == image_id)
+        .collect())
+}
+
+// Accepts "layer_3" as well as "3", like the layer commands
+fn normalize_layer(layer: &str) -> Result<String, String> {
+    let number = layer.strip_prefix("layer_").unwrap_or(layer);
+    match number.parse::<usize>() {
+        Ok(number) if number > 0 => Ok(format!("layer_{}", number)),
+        _ => Err(format!("Invalid layer ID: {}", layer)),
+    }
+}
+
+// Mark a path of an image, or of one of its layers, to come back to later.
+// "/" with a layer pins the whole layer. Bookmarks are listed in reports
+// exported for the image.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn add_bookmark(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+    layer: Option<String>,
+    path: String,
+    note: Option<String>,
+) -> Result<Bookmark, LayersError> {
+    let image = session.image_or_selected(image)?;
+    let (image_id, reference) = resolve_image(&image)?;
+    let layer_id = layer.as_deref().map(normalize_layer).transpose()?;
+    let path = format!("/{}", path.trim().trim_start_matches('/'));
+    info!("Bookmarking {} in {}", path, reference);
+
+    let _guard = WRITE_LOCK.lock().unwrap();
+    let file = bookmarks_path()?;
+    let mut bookmarks = load(&file)?;
+    let bookmark = Bookmark {
+        id: bookmarks
+            .iter()
+            .map(|bookmark| bookmark.id)
+            .max()
+            .unwrap_or(0)
+            + 1,
+        image_id,
+        reference,
+        layer_id,
+        path,
+        note: note.unwrap_or_default(),
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    };
+    bookmarks.push(bookmark.clone());
+    save(&file, &bookmarks)?;
+    Ok(bookmark)
+}
+
+// Bookmarks of an image, or of every image with `all`
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_bookmarks(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+    all: Option<bool>,
+) -> Result<Vec<Bookmark>, LayersError> {
+    if all.unwrap_or(false) {
+        return Ok(load(&bookmarks_path()?)?);
+    }
+    let image = session.image_or_selected(image)?;
+    let (image_id, _) = resolve_image(&image)?;
+    Ok(for_image(&image_id)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn remove_bookmark(id: u64) -> Result<(), LayersError> {
+    info!("Removing bookmark {}", id);
+    let _guard = WRITE_LOCK.lock().unwrap();
+    let file = bookmarks_path()?;
+    let mut bookmarks = load(&file)?;
+    let count = bookmarks.len();
+    bookmarks.retain(|bookmark| bookmark.id != id);
+    if bookmarks.len() == count {
+        return Err(format!("No bookmark with ID {}", id).into());
+    }
+    save(&file, &bookmarks)?;
+    Ok(())
+}
@@ -0,0 +1,389 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::config;
+use crate::error::LayersError;
+use crate::seekable;
+use crate::undo::{UndoAction, UndoHistory};
+
+// Least recently used entries are evicted past this size, unless layers.toml
+// sets another
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CacheEntry {
+    size_bytes: u64,
+    // Seconds since the epoch, updated on every hit
+    last_used: u64,
+    // Stored as seekable zstd instead of a plain tar, restored by
+    // decompressing it since sessions read plain tars
+    #[serde(default)]
+    seekable: bool,
+    // A full-text index directory built by content_index, not a filesystem
+    #[serde(default)]
+    content_index: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct CacheIndex {
+    entries: HashMap<String, CacheEntry>,
+    // Compress new entries, costs CPU once when storing but keeps several
+    // times more images on disk
+    #[serde(default)]
+    compress: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheStats {
+    directory: String,
+    entries: usize,
+    total_bytes: u64,
+    max_bytes: u64,
+    // Full-text indexes, counted in entries and total_bytes as well
+    content_indexes: usize,
+    content_index_bytes: u64,
+    compress: bool,
+    // Since the app started
+    hits: u64,
+    misses: u64,
+}
+
+// Exported image filesystems by the chain ID of their layers, managed as Tauri state
+pub struct ExtractionCache {
+    dir: PathBuf,
+    index: Mutex<CacheIndex>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// OCI ChainID of the topmost layer, which identifies the whole filesystem:
+// ChainID(L0) = DiffID(L0), ChainID(Ln) = sha256(ChainID(Ln-1) + " " + DiffID(Ln))
+pub(crate) fn chain_id(diff_ids: &[String]) -> Option<String> {
+    let mut layers = diff_ids.iter();
+    let mut chain = layers.next()?.clone();
+    for diff_id in layers {
+        let digest = Sha256::digest(format!("{} {}", chain, diff_id).as_bytes());
+        chain = format!("sha256:{:x}", digest);
+    }
+    Some(chain)
+}
+
+// Content indexes share the key of the filesystem they were built from
+fn content_index_key(key: &str) -> String {
+    format!("content_index:{}", key)
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+// Hard links make restoring instant, fall back to copying across filesystems
+fn link_or_copy(from: &Path, to: &Path) -> std::io::Result<()> {
+    if to.exists() {
+        fs::remove_file(to)?;
+    }
+    fs::hard_link(from, to).or_else(|_| fs::copy(from, to).map(|_| ()))
+}
+
+impl ExtractionCache {
+    pub fn new(dir: PathBuf) -> Self {
+        let index = fs::read(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default();
+        ExtractionCache {
+            dir,
+            index: Mutex::new(index),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn max_bytes(&self) -> u64 {
+        config::cache_max_bytes().unwrap_or(DEFAULT_MAX_BYTES)
+    }
+
+    fn entry_path(&self, key: &str, seekable: bool) -> PathBuf {
+        let hex = key.rsplit(':').next().unwrap_or(key);
+        let extension = if seekable { "tar.zst" } else { "tar" };
+        self.dir.join(format!("{}.{}", hex, extension))
+    }
+
+    fn index_path(&self, key: &str) -> PathBuf {
+        let hex = key.rsplit(':').next().unwrap_or(key);
+        self.dir.join(format!("{}.index", hex))
+    }
+
+    fn remove_entry_files(&self, key: &str, entry: &CacheEntry) {
+        let _ = if entry.content_index {
+            fs::remove_dir_all(self.index_path(key))
+        } else {
+            fs::remove_file(self.entry_path(key, entry.seekable))
+        };
+    }
+
+    fn save_index(&self, index: &CacheIndex) -> Result<(), String> {
+        let content = serde_json::to_vec(index)
+            .map_err(|e| format!("Failed to serialize cache index: {}", e))?;
+        fs::write(self.dir.join(INDEX_FILE), content)
+            .map_err(|e| format!("Failed to write cache index: {}", e))
+    }
+
+    // Copy a cached filesystem to `destination`, returns false on a miss.
+    // The index isn't locked while copying, an entry evicted meanwhile stays
+    // readable through the open file or fails the restore.
+    pub(crate) fn restore(&self, key: &str, destination: &Path) -> Result<bool, String> {
+        let seekable = {
+            let mut index = self.index.lock().unwrap();
+            let seekable = index.entries.get(key).map(|e| e.seekable);
+            if seekable.is_none_or(|seekable| !self.entry_path(key, seekable).exists()) {
+                index.entries.remove(key);
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                return Ok(false);
+            }
+            seekable == Some(true)
+        };
+
+        let path = self.entry_path(key, seekable);
+        if seekable {
+            seekable::decompress_seekable(&path, destination).map(|_| ())
+        } else {
+            link_or_copy(&path, destination)
+        }
+        .map_err(|e| format!("Failed to restore {} from cache: {}", key, e))?;
+
+        let mut index = self.index.lock().unwrap();
+        if let Some(entry) = index.entries.get_mut(key) {
+            entry.last_used = now();
+        }
+        self.save_index(&index)?;
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        info!("Restored {} from extraction cache", key);
+        Ok(true)
+    }
+
+    pub(crate) fn store(&self, key: &str, source: &Path) -> Result<(), String> {
+        let mut index = self.index.lock().unwrap();
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+
+        // Compression may have been toggled since the key was last stored
+        if let Some(old) = index.entries.get(key) {
+            self.remove_entry_files(key, old);
+        }
+
+        let seekable = index.compress;
+        let path = self.entry_path(key, seekable);
+        if seekable {
+            File::open(source)
+                .and_then(|file| seekable::compress_seekable(file, &path))
+                .map(|_| ())
+        } else {
+            link_or_copy(source, &path)
+        }
+        .map_err(|e| format!("Failed to cache {}: {}", key, e))?;
+        let size_bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        index.entries.insert(
+            key.to_string(),
+            CacheEntry {
+                size_bytes,
+                last_used: now(),
+                seekable,
+                content_index: false,
+            },
+        );
+
+        self.evict(&mut index, key);
+        self.save_index(&index)
+    }
+
+    // Directory of the full-text index of a cached filesystem, None on a miss
+    pub(crate) fn content_index(&self, key: &str) -> Option<PathBuf> {
+        let mut index = self.index.lock().unwrap();
+        let index_key = content_index_key(key);
+        let path = self.index_path(&index_key);
+        let Some(entry) = index.entries.get_mut(&index_key).filter(|_| path.is_dir()) else {
+            index.entries.remove(&index_key);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        entry.last_used = now();
+        let _ = self.save_index(&index);
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(path)
+    }
+
+    // Where a content index is built before store_content_index takes it over
+    pub(crate) fn content_index_staging(&self, key: &str) -> PathBuf {
+        let mut path = self.index_path(&content_index_key(key));
+        path.set_extension("index.partial");
+        path
+    }
+
+    pub(crate) fn store_content_index(&self, key: &str) -> Result<PathBuf, String> {
+        let mut index = self.index.lock().unwrap();
+        let index_key = content_index_key(key);
+        let path = self.index_path(&index_key);
+        if path.exists() {
+            let _ = fs::remove_dir_all(&path);
+        }
+        fs::rename(self.content_index_staging(key), &path)
+            .map_err(|e| format!("Failed to cache content index of {}: {}", key, e))?;
+        index.entries.insert(
+            index_key.clone(),
+            CacheEntry {
+                size_bytes: dir_size(&path),
+                last_used: now(),
+                seekable: false,
+                content_index: true,
+            },
+        );
+
+        self.evict(&mut index, &index_key);
+        self.save_index(&index)?;
+        Ok(path)
+    }
+
+    // Drop least recently used entries until the cache fits, never the one just stored
+    fn evict(&self, index: &mut CacheIndex, keep: &str) {
+        let max_bytes = self.max_bytes();
+        let mut total: u64 = index.entries.values().map(|e| e.size_bytes).sum();
+        let mut by_age: Vec<(String, CacheEntry)> = index
+            .entries
+            .iter()
+            .filter(|(key, _)| key.as_str() != keep)
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect();
+        by_age.sort_by_key(|(_, entry)| entry.last_used);
+
+        for (key, entry) in by_age {
+            if total <= max_bytes {
+                break;
+            }
+            info!("Evicting {} from extraction cache", key);
+            self.remove_entry_files(&key, &entry);
+            index.entries.remove(&key);
+            total -= entry.size_bytes;
+        }
+    }
+
+    fn stats(&self, index: &CacheIndex) -> CacheStats {
+        let content_indexes: Vec<&CacheEntry> = index
+            .entries
+            .values()
+            .filter(|entry| entry.content_index)
+            .collect();
+        CacheStats {
+            directory: self.dir.to_string_lossy().to_string(),
+            entries: index.entries.len(),
+            total_bytes: index.entries.values().map(|e| e.size_bytes).sum(),
+            max_bytes: self.max_bytes(),
+            content_indexes: content_indexes.len(),
+            content_index_bytes: content_indexes.iter().map(|e| e.size_bytes).sum(),
+            compress: index.compress,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_cache_stats(
+    cache: tauri::State<'_, ExtractionCache>,
+) -> Result<CacheStats, LayersError> {
+    let index = cache.index.lock().unwrap();
+    Ok(cache.stats(&index))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn clear_cache(
+    cache: tauri::State<'_, ExtractionCache>,
+    undo: tauri::State<'_, UndoHistory>,
+) -> Result<CacheStats, LayersError> {
+    let mut index = cache.index.lock().unwrap();
+    info!("Clearing extraction cache at {:?}", cache.dir);
+
+    if cache.dir.exists() {
+        fs::remove_dir_all(&cache.dir)
+            .map_err(|e| format!("Failed to clear cache directory: {}", e))?;
+    }
+    index.entries.clear();
+    // Keep the compression setting
+    if index.compress {
+        fs::create_dir_all(&cache.dir)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        cache.save_index(&index)?;
+    }
+    undo.record(
+        "Cleared the extraction cache".to_string(),
+        UndoAction::Irreversible,
+    );
+    Ok(cache.stats(&index))
+}
+
+// Entries already in the cache keep their format until they're stored again
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_cache_compression(
+    cache: tauri::State<'_, ExtractionCache>,
+    enabled: bool,
+) -> Result<CacheStats, LayersError> {
+    let mut index = cache.index.lock().unwrap();
+    info!("Setting extraction cache compression to {}", enabled);
+
+    index.compress = enabled;
+    fs::create_dir_all(&cache.dir)
+        .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    cache.save_index(&index)?;
+    Ok(cache.stats(&index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{scratch_dir, write_exotic_tar};
+
+    #[test]
+    fn compressed_entries_restore_the_same_tar() {
+        let dir = scratch_dir("cache-compressed");
+        let tar_path = dir.join("fs.tar");
+        write_exotic_tar(&tar_path);
+        let cache = ExtractionCache::new(dir.join("cache"));
+        cache.index.lock().unwrap().compress = true;
+
+        let key = "sha256:0123abcd";
+        cache.store(key, &tar_path).unwrap();
+        assert!(cache.entry_path(key, true).exists());
+
+        let restored = dir.join("restored.tar");
+        assert!(cache.restore(key, &restored).unwrap());
+        assert_eq!(fs::read(&restored).unwrap(), fs::read(&tar_path).unwrap());
+        assert!(!cache.restore("sha256:missing", &restored).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,86 @@
+use layers_core::dockerfile::Dockerfile;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::error::LayersError;
+
+// Longer text is a log or a document, not something to import
+const MAX_CLIPBOARD_LENGTH: usize = 256 * 1024;
+// Prefixes people paste along with the reference, e.g. from a chat message
+const COMMAND_PREFIXES: [&str; 3] = ["docker pull ", "docker run ", "docker image inspect "];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClipboardContent {
+    Dockerfile {
+        content: String,
+        instructions: usize,
+        base_image: Option<String>,
+    },
+    ImageReference {
+        reference: String,
+    },
+    Unrecognized,
+}
+
+// [registry[:port]/]path[:tag][@digest], following docker's reference grammar
+pub(crate) fn reference_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"^(?:[a-zA-Z0-9.-]+(?::[0-9]+)?/)?[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*(?:/[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*)*(?::[A-Za-z0-9_][A-Za-z0-9_.-]{0,127})?(?:@sha256:[a-f0-9]{64})?$",
+        )
+        .unwrap()
+    })
+}
+
+// The image reference in a pasted snippet, if that's all it is
+fn image_reference(text: &str) -> Option<String> {
+    let text = text.trim().trim_matches('`').trim();
+    if text.contains('\n') {
+        return None;
+    }
+    let command = COMMAND_PREFIXES
+        .iter()
+        .find_map(|prefix| text.strip_prefix(prefix));
+    let reference = command
+        .and_then(|rest| rest.split_whitespace().find(|arg| !arg.starts_with('-')))
+        .unwrap_or(text);
+
+    // A lone word is more likely prose than an image, unless it came with a
+    // docker command
+    let qualified = reference.contains([':', '/', '@']);
+    (reference_pattern().is_match(reference) && (qualified || command.is_some()))
+        .then(|| reference.to_string())
+}
+
+// Classify clipboard text so the UI can offer to analyze or inspect it
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn detect_clipboard_content(text: String) -> Result<ClipboardContent, LayersError> {
+    if text.trim().is_empty() || text.len() > MAX_CLIPBOARD_LENGTH {
+        return Ok(ClipboardContent::Unrecognized);
+    }
+
+    if let Some(reference) = image_reference(&text) {
+        return Ok(ClipboardContent::ImageReference { reference });
+    }
+
+    // Code fences are common when a Dockerfile is pasted through chat
+    let content = text
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if Dockerfile::looks_like_dockerfile(&content) {
+        let dockerfile = Dockerfile::parse(&content);
+        return Ok(ClipboardContent::Dockerfile {
+            instructions: dockerfile.instructions.len(),
+            base_image: dockerfile.base_image,
+            content,
+        });
+    }
+
+    Ok(ClipboardContent::Unrecognized)
+}
@@ -0,0 +1,218 @@
+use layers_core::docker::{get_image_history, image_diff_ids, is_empty_history_entry};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::pull_time::{compressed_layer_sizes, CompressedSizeSource, DEFAULT_COMPRESSION_RATIO};
+use crate::session::SessionState;
+
+// Base images most nodes in a fleet already have cached
+const DEFAULT_BASE_IMAGES: [&str; 4] = [
+    "alpine:latest",
+    "debian:bookworm-slim",
+    "ubuntu:latest",
+    "gcr.io/distroless/base",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColdStartLayer {
+    diff_id: String,
+    size_bytes: u64,
+    compressed_bytes: u64,
+    cached_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BaseImageMatch {
+    name: String,
+    available: bool,
+    shared_layers: usize,
+    shared_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColdStartReport {
+    image: String,
+    total_layers: usize,
+    total_bytes: u64,
+    cached_layers: usize,
+    cached_bytes: u64,
+    marginal_layers: usize,
+    marginal_bytes: u64,
+    // What a node without the cached layers downloads
+    marginal_compressed_bytes: u64,
+    compressed_sizes: CompressedSizeSource,
+    base_images: Vec<BaseImageMatch>,
+    layers: Vec<ColdStartLayer>,
+}
+
+// Pair each RootFS diff ID with the size reported by docker history.
+// History has an entry per instruction while RootFS only has the ones that
+// changed the filesystem, so metadata-only entries are skipped first.
+fn image_layer_sizes(image: &str) -> Result<Vec<(String, u64)>, LayersError> {
+    let diff_ids = image_diff_ids(image)?;
+
+    // History is newest first, RootFS base layer first
+    let sizes: Vec<u64> = get_image_history(image)?
+        .into_iter()
+        .rev()
+        .filter(|entry| !is_empty_history_entry(&entry.created_by, entry.size_bytes))
+        .map(|entry| entry.size_bytes)
+        .collect();
+
+    if sizes.len() != diff_ids.len() {
+        info!(
+            "History has {} layer entries but RootFS has {} layers, sizes may be misattributed",
+            sizes.len(),
+            diff_ids.len()
+        );
+    }
+
+    Ok(diff_ids
+        .into_iter()
+        .enumerate()
+        .map(|(i, diff_id)| {
+            let size = sizes.get(i).copied().unwrap_or(0);
+            (diff_id, size)
+        })
+        .collect())
+}
+
+// Layers the image shares with a base image, counted from the bottom. Docker
+// only reuses a layer when every layer below it matches too, the same diff ID
+// on another stack is downloaded again.
+fn shared_base_layers(layers: &[(String, u64)], base_layers: &[String]) -> usize {
+    layers
+        .iter()
+        .zip(base_layers)
+        .take_while(|((diff_id, _), base_diff_id)| diff_id == *base_diff_id)
+        .count()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn analyze_cold_start(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+    base_images: Option<Vec<String>>,
+) -> Result<ColdStartReport, LayersError> {
+    let image = session.image_or_selected(image)?;
+    let base_images =
+        base_images.unwrap_or_else(|| DEFAULT_BASE_IMAGES.iter().map(|s| s.to_string()).collect());
+
+    info!(
+        "Analyzing cold start cost of {} against {} base images",
+        image,
+        base_images.len()
+    );
+
+    let layers = image_layer_sizes(&image)?;
+    let uncompressed: Vec<u64> = layers.iter().map(|(_, size)| *size).collect();
+    let (compressed, compressed_sizes) =
+        compressed_layer_sizes(&image, &uncompressed, DEFAULT_COMPRESSION_RATIO);
+
+    // Collect which base image provides each cached layer
+    let mut cached: HashMap<String, String> = HashMap::new();
+    let mut base_matches = Vec::new();
+
+    for base in &base_images {
+        match image_diff_ids(base) {
+            Ok(base_layers) => {
+                let shared_layers = shared_base_layers(&layers, &base_layers);
+                let mut shared_bytes = 0;
+                for (diff_id, size) in &layers[..shared_layers] {
+                    shared_bytes += size;
+                    cached
+                        .entry(diff_id.clone())
+                        .or_insert_with(|| base.clone());
+                }
+
+                base_matches.push(BaseImageMatch {
+                    name: base.clone(),
+                    available: true,
+                    shared_layers,
+                    shared_bytes,
+                });
+            }
+            Err(e) => {
+                // Base images that aren't present locally can't be compared
+                info!("Skipping base image {}: {}", base, e);
+                base_matches.push(BaseImageMatch {
+                    name: base.clone(),
+                    available: false,
+                    shared_layers: 0,
+                    shared_bytes: 0,
+                });
+            }
+        }
+    }
+
+    let layers: Vec<ColdStartLayer> = layers
+        .into_iter()
+        .zip(compressed)
+        .map(|((diff_id, size_bytes), compressed_bytes)| ColdStartLayer {
+            cached_by: cached.get(&diff_id).cloned(),
+            diff_id,
+            size_bytes,
+            compressed_bytes,
+        })
+        .collect();
+
+    let total_bytes: u64 = layers.iter().map(|l| l.size_bytes).sum();
+    let cached_layers = layers.iter().filter(|l| l.cached_by.is_some()).count();
+    let cached_bytes: u64 = layers
+        .iter()
+        .filter(|l| l.cached_by.is_some())
+        .map(|l| l.size_bytes)
+        .sum();
+    let marginal_bytes = total_bytes - cached_bytes;
+    let marginal_compressed_bytes = layers
+        .iter()
+        .filter(|l| l.cached_by.is_none())
+        .map(|l| l.compressed_bytes)
+        .sum();
+
+    Ok(ColdStartReport {
+        image,
+        total_layers: layers.len(),
+        total_bytes,
+        cached_layers,
+        cached_bytes,
+        marginal_layers: layers.len() - cached_layers,
+        marginal_bytes,
+        marginal_compressed_bytes,
+        compressed_sizes,
+        base_images: base_matches,
+        layers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(diff_ids: &[&str]) -> Vec<(String, u64)> {
+        diff_ids.iter().map(|d| (d.to_string(), 1)).collect()
+    }
+
+    fn ids(diff_ids: &[&str]) -> Vec<String> {
+        diff_ids.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn base_layers_are_shared_from_the_bottom() {
+        let image = layers(&["a", "b", "c"]);
+        assert_eq!(shared_base_layers(&image, &ids(&["a", "b"])), 2);
+        assert_eq!(shared_base_layers(&image, &ids(&["a", "b", "c", "d"])), 3);
+    }
+
+    #[test]
+    fn layers_above_a_difference_are_not_shared() {
+        let image = layers(&["a", "b", "c"]);
+        // "c" is in the base image but on top of another layer
+        assert_eq!(shared_base_layers(&image, &ids(&["a", "x", "c"])), 1);
+        assert_eq!(shared_base_layers(&image, &ids(&["b", "c"])), 0);
+        assert_eq!(shared_base_layers(&image, &[]), 0);
+    }
+}
@@ -0,0 +1,191 @@
+use layers_core::docker::{image_diff_ids, inspect_image, inspect_images, list_image_ids};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::finish_task;
+use crate::image_compare::{compare_images_task, ImageCompareOptions, ImageComparison};
+use crate::session::SessionState;
+use crate::tag_history::previous_local_image;
+use crate::tasks::TaskRegistry;
+
+// Set by buildx and most CI pipelines
+const BASE_NAME_LABEL: &str = "org.opencontainers.image.base.name";
+const BASE_DIGEST_LABEL: &str = "org.opencontainers.image.base.digest";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonPreset {
+    // The image the selected one was built from vs the selected image
+    BaseVsFinal,
+    // What the tag pointed to before vs what it points to now
+    PreviousTagVsCurrent,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ComparisonPair {
+    preset: ComparisonPreset,
+    image_a: String,
+    image_b: String,
+    // Name to show for each side, a tag when there is one
+    label_a: String,
+    label_b: String,
+    // How side A was found, e.g. "base image label"
+    resolved_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PresetComparison {
+    pair: ComparisonPair,
+    comparison: ImageComparison,
+}
+
+struct LocalImage {
+    id: String,
+    tags: Vec<String>,
+    layers: Vec<String>,
+}
+
+fn local_images() -> Result<Vec<LocalImage>, String> {
+    let images = inspect_images(&list_image_ids()?)?;
+    Ok(images
+        .iter()
+        .filter_map(|image| {
+            Some(LocalImage {
+                id: image["Id"].as_str()?.to_string(),
+                tags: serde_json::from_value(image["RepoTags"].clone()).unwrap_or_default(),
+                layers: serde_json::from_value(image["RootFS"]["Layers"].clone())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+// The base image an image was built from, as (image, label, resolved_by)
+fn resolve_base_image(image: &str) -> Result<(String, String, String), String> {
+    let inspect = inspect_image(image)?;
+    let labels = &inspect["Config"]["Labels"];
+    for label in [BASE_DIGEST_LABEL, BASE_NAME_LABEL] {
+        if let Some(reference) = labels[label].as_str().filter(|r| !r.is_empty()) {
+            let name = labels[BASE_NAME_LABEL].as_str().unwrap_or(reference);
+            // A bare digest label needs the repository to be pulled by
+            let reference = if reference.starts_with("sha256:") {
+                format!("{}@{}", name.split(':').next().unwrap_or(name), reference)
+            } else {
+                reference.to_string()
+            };
+            if let Ok(base) = inspect_image(&reference) {
+                let id = base["Id"].as_str().unwrap_or(&reference).to_string();
+                return Ok((id, name.to_string(), format!("{} label", label)));
+            }
+        }
+    }
+
+    // Otherwise the local image whose layers are the longest prefix of this one's
+    let layers = image_diff_ids(image)?;
+    let image_id = inspect["Id"].as_str().unwrap_or(image);
+    let base = local_images()?
+        .into_iter()
+        .filter(|base| {
+            base.id != image_id
+                && !base.layers.is_empty()
+                && base.layers.len() < layers.len()
+                && layers.starts_with(&base.layers)
+        })
+        // Prefer tagged images over dangling intermediates of the same length
+        .max_by_key(|base| (base.layers.len(), !base.tags.is_empty()));
+
+    match base {
+        Some(base) => {
+            let label = base
+                .tags
+                .first()
+                .cloned()
+                .unwrap_or_else(|| base.id.clone());
+            let resolved_by = format!("shares its first {} layers", base.layers.len());
+            Ok((base.id, label, resolved_by))
+        }
+        None => Err(format!(
+            "Couldn't find the base image of {} locally, pull it first",
+            image
+        )),
+    }
+}
+
+fn resolve_pair(
+    image_id: &str,
+    reference: &str,
+    preset: ComparisonPreset,
+) -> Result<ComparisonPair, String> {
+    let (image_a, label_a, resolved_by) = match preset {
+        ComparisonPreset::BaseVsFinal => resolve_base_image(image_id)?,
+        ComparisonPreset::PreviousTagVsCurrent => {
+            let previous = previous_local_image(reference, image_id)?.ok_or_else(|| {
+                format!(
+                    "No earlier image of {} is known and available locally, check_tag_mutation records them",
+                    reference
+                )
+            })?;
+            (
+                previous,
+                format!("{} (previous)", reference),
+                "tag history".to_string(),
+            )
+        }
+    };
+    info!("Resolved {:?} to {} vs {}", preset, image_a, image_id);
+
+    Ok(ComparisonPair {
+        preset,
+        image_a,
+        image_b: image_id.to_string(),
+        label_a,
+        label_b: reference.to_string(),
+        resolved_by,
+    })
+}
+
+// Which two images a preset would compare, without comparing them
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn resolve_comparison_preset(
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    preset: ComparisonPreset,
+) -> Result<ComparisonPair, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    Ok(resolve_pair(
+        session.image_id(),
+        session.reference(),
+        preset,
+    )?)
+}
+
+// Resolve the pair for a preset and compare it in one go
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn compare_with_preset(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    preset: ComparisonPreset,
+    options: Option<ImageCompareOptions>,
+) -> Result<PresetComparison, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let pair = resolve_pair(session.image_id(), session.reference(), preset)?;
+
+    let task = tasks.start();
+    let result = compare_images_task(
+        &window,
+        &task,
+        &pair.image_a,
+        &pair.image_b,
+        options.unwrap_or_default(),
+    );
+    finish_task(&window, &tasks, &task);
+    Ok(PresetComparison {
+        comparison: result?,
+        pair,
+    })
+}
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::resources::ResourceLimits;
+
+const DEFAULT_BINARY_SAMPLE_BYTES: usize = 1000;
+const DEFAULT_NON_ASCII_RATIO: f64 = 0.3;
+
+// Docker commands, the cache and binary detection run in helpers without
+// access to Tauri state, so the loaded settings live here
+static SETTINGS: RwLock<Option<Settings>> = RwLock::new(None);
+static PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+// Content is binary when it has a NUL byte or too many non-ASCII bytes in
+// its first sample_bytes
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct BinaryDetection {
+    sample_bytes: usize,
+    non_ascii_ratio: f64,
+}
+
+// Settings kept in layers.toml in the platform config directory
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Settings {
+    // DOCKER_HOST for every docker command, e.g. "tcp://build-host:2375". A
+    // bare path is taken as a unix socket.
+    docker_host: Option<String>,
+    // Extraction cache size before least recently used images are dropped
+    cache_max_bytes: Option<u64>,
+    // Layers extracted or scanned at once, caps the scan threads
+    extraction_concurrency: Option<usize>,
+    // docker-credential-<helper> asked for registry logins when pulling
+    // through the engine API, e.g. "osxkeychain" or "pass"
+    credentials_helper: Option<String>,
+    // Tables come last in TOML
+    binary_detection: BinaryDetection,
+}
+
+impl Default for BinaryDetection {
+    fn default() -> Self {
+        BinaryDetection {
+            sample_bytes: DEFAULT_BINARY_SAMPLE_BYTES,
+            non_ascii_ratio: DEFAULT_NON_ASCII_RATIO,
+        }
+    }
+}
+
+fn docker_host_url(host: &str) -> String {
+    if host.starts_with('/') {
+        format!("unix://{}", host)
+    } else {
+        host.to_string()
+    }
+}
+
+// Set on every docker command, clearing the setting goes back to the
+// DOCKER_HOST the app was started with
+fn apply_docker_host(settings: &Settings) {
+    layers_core::docker::set_docker_host(settings.docker_host.as_deref().map(docker_host_url));
+}
+
+fn read_settings() -> Settings {
+    SETTINGS.read().unwrap().clone().unwrap_or_default()
+}
+
+// Loads layers.toml, falls back to the defaults when it doesn't parse
+pub fn init(path: PathBuf) {
+    info!("Reading settings from {:?}", path);
+    let settings = match fs::read_to_string(&path) {
+        Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+            warn!("Failed to parse {:?}: {}", path, e);
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    };
+    apply_docker_host(&settings);
+    *SETTINGS.write().unwrap() = Some(settings);
+    *PATH.write().unwrap() = Some(path);
+}
+
+pub(crate) fn cache_max_bytes() -> Option<u64> {
+    read_settings().cache_max_bytes
+}
+
+pub(crate) fn extraction_concurrency() -> Option<usize> {
+    read_settings().extraction_concurrency
+}
+
+pub(crate) fn credentials_helper() -> Option<String> {
+    read_settings().credentials_helper
+}
+
+// (sample_bytes, non_ascii_ratio) for is_binary_content
+pub(crate) fn binary_detection() -> (usize, f64) {
+    let detection = read_settings().binary_detection;
+    (detection.sample_bytes.max(1), detection.non_ascii_ratio)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_settings() -> Result<Settings, LayersError> {
+    Ok(read_settings())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn update_settings(
+    resources: tauri::State<'_, ResourceLimits>,
+    settings: Settings,
+) -> Result<Settings, LayersError> {
+    info!("Updating settings: {:?}", settings);
+    let path = PATH
+        .read()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| "Settings are not initialized".to_string())?;
+    let settings = Settings {
+        docker_host: settings
+            .docker_host
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty()),
+        credentials_helper: settings
+            .credentials_helper
+            .map(|helper| helper.trim().to_string())
+            .filter(|helper| !helper.is_empty()),
+        extraction_concurrency: settings.extraction_concurrency.map(|n| n.max(1)),
+        ..settings
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+    }
+    let content = toml::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    audit::write_atomically(&path, content)
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+
+    apply_docker_host(&settings);
+    *SETTINGS.write().unwrap() = Some(settings.clone());
+    // The scan pool is sized when it's built
+    resources.apply()?;
+    Ok(settings)
+}
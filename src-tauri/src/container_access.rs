@@ -0,0 +1,435 @@
+use layers_core::docker::get_image_history;
+use layers_core::layer_tar::entry_relative_path;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use tracing::info;
+
+use crate::blob;
+use crate::error::LayersError;
+use crate::findings::{SecurityFinding, Severity};
+use crate::layer_tar_path;
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+// Scripts and configs that mention the socket are small, skip anything bigger
+const MAX_TEXT_SIZE: u64 = 256 * 1024;
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+const CONTAINER_CLIENTS: [&str; 6] = [
+    "usr/bin/docker",
+    "usr/local/bin/docker",
+    "bin/docker",
+    "usr/bin/podman",
+    "usr/bin/nerdctl",
+    "usr/local/bin/nerdctl",
+];
+const CONTAINER_DAEMONS: [&str; 4] = [
+    "usr/bin/dockerd",
+    "usr/local/bin/dockerd",
+    "usr/bin/containerd",
+    "usr/local/bin/containerd",
+];
+// Image stores of a docker or podman daemon that ran during the build
+const IMAGE_STORE_DIRS: [&str; 2] = ["var/lib/docker/", "var/lib/containers/storage/"];
+const KUBECONFIG_PATHS: [&str; 4] = [
+    "etc/kubernetes/admin.conf",
+    "etc/kubernetes/kubelet.conf",
+    "etc/kubernetes/controller-manager.conf",
+    "etc/kubernetes/scheduler.conf",
+];
+const SERVICE_ACCOUNT_TOKEN: &str = "var/run/secrets/kubernetes.io/serviceaccount/token";
+// Library code mentions the socket all the time, only configs and scripts count
+const LIBRARY_DIRS: [&str; 5] = [
+    "usr/lib/",
+    "usr/share/",
+    "node_modules/",
+    "site-packages/",
+    "dist-packages/",
+];
+const TEXT_EXTENSIONS: [&str; 9] = [
+    ".sh", ".bash", ".yml", ".yaml", ".json", ".env", ".conf", ".toml", ".service",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddedImageFormat {
+    DockerArchive,
+    OciArchive,
+    OciLayout,
+    ImageStore,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmbeddedImage {
+    path: String,
+    format: EmbeddedImageFormat,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SocketReference {
+    // File path, or the Dockerfile instruction for references in the history
+    source: String,
+    snippet: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContainerAccessReport {
+    layer_id: String,
+    embedded_images: Vec<EmbeddedImage>,
+    container_clients: Vec<String>,
+    container_daemons: Vec<String>,
+    socket_references: Vec<SocketReference>,
+    kubeconfigs: Vec<String>,
+    findings: Vec<SecurityFinding>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DockerArchiveManifest {
+    #[serde(default)]
+    repo_tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OciIndex {
+    #[serde(default)]
+    manifests: Vec<OciDescriptor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OciDescriptor {
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+}
+
+fn is_archive_path(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.ends_with(".tar") || path.ends_with(".tar.gz") || path.ends_with(".tgz")
+}
+
+fn is_kubeconfig_path(path: &str) -> bool {
+    path.ends_with(".kube/config") || KUBECONFIG_PATHS.contains(&path)
+}
+
+fn is_script_or_config(path: &str, executable: bool) -> bool {
+    if LIBRARY_DIRS.iter().any(|dir| path.contains(dir)) {
+        return false;
+    }
+    let name = path.rsplit('/').next().unwrap_or(path);
+    executable
+        || path.starts_with("etc/")
+        || name.starts_with(".env")
+        || name == "Dockerfile"
+        || TEXT_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+}
+
+// Kubeconfigs can live anywhere, recognize them by their top level keys
+fn looks_like_kubeconfig(content: &str) -> bool {
+    content.contains("kind: Config")
+        && content.contains("clusters:")
+        && (content.contains("users:") || content.contains("contexts:"))
+}
+
+fn tags_from_index(content: &[u8]) -> Vec<String> {
+    serde_json::from_slice::<OciIndex>(content)
+        .map(|index| {
+            index
+                .manifests
+                .into_iter()
+                .filter_map(|m| {
+                    m.annotations
+                        .get("org.opencontainers.image.ref.name")
+                        .cloned()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Look inside a tar found in the image for docker save or OCI archive metadata
+fn inspect_nested_archive<R: Read>(reader: R) -> Option<(EmbeddedImageFormat, Vec<String>)> {
+    let reader = blob::decompress(BufReader::new(reader), None).ok()?;
+    let mut archive = tar::Archive::new(reader);
+    let mut format = None;
+    let mut tags = Vec::new();
+    for entry in archive.entries().ok()? {
+        let Ok(mut entry) = entry else {
+            break;
+        };
+        let Ok(path) = entry.path().map(|p| entry_relative_path(&p)) else {
+            continue;
+        };
+        match path.as_str() {
+            "manifest.json" => {
+                let mut content = Vec::new();
+                entry.read_to_end(&mut content).ok()?;
+                let Ok(manifests) = serde_json::from_slice::<Vec<DockerArchiveManifest>>(&content)
+                else {
+                    continue;
+                };
+                format = Some(EmbeddedImageFormat::DockerArchive);
+                tags.extend(
+                    manifests
+                        .into_iter()
+                        .flat_map(|m| m.repo_tags.unwrap_or_default()),
+                );
+            }
+            "oci-layout" => {
+                format = format.or(Some(EmbeddedImageFormat::OciArchive));
+            }
+            "index.json" => {
+                let mut content = Vec::new();
+                entry.read_to_end(&mut content).ok()?;
+                if tags.is_empty() {
+                    tags = tags_from_index(&content);
+                }
+            }
+            _ => {}
+        }
+    }
+    format.map(|format| (format, tags))
+}
+
+fn socket_snippet(content: &str) -> Option<String> {
+    content
+        .lines()
+        .find(|line| line.contains(DOCKER_SOCKET) || line.contains("DOCKER_HOST="))
+        .map(|line| line.trim().chars().take(200).collect())
+}
+
+fn finding(
+    rule_id: &str,
+    severity: Severity,
+    title: String,
+    description: &str,
+    path: Option<String>,
+    layer_id: &str,
+) -> SecurityFinding {
+    SecurityFinding {
+        rule_id: rule_id.to_string(),
+        severity,
+        title,
+        description: description.to_string(),
+        path,
+        layer_id: Some(layer_id.to_string()),
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn inspect_container_access(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+) -> Result<ContainerAccessReport, LayersError> {
+    info!("Inspecting container runtime access in layer {}", layer_id);
+
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let tar_path = layer_tar_path(&task, &session, &layer_id);
+    tasks.finish(task.id);
+    let tar_path = tar_path?;
+
+    let file = File::open(&tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+
+    let mut embedded_images = Vec::new();
+    let mut container_clients = Vec::new();
+    let mut container_daemons = Vec::new();
+    let mut socket_references = Vec::new();
+    let mut kubeconfigs = Vec::new();
+    let mut service_account_token = None;
+    let mut image_stores: Vec<&str> = Vec::new();
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let path = entry_relative_path(
+            &entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?,
+        );
+
+        if let Some(store) = IMAGE_STORE_DIRS
+            .iter()
+            .find(|dir| path.starts_with(*dir) && path.len() > dir.len())
+        {
+            if !image_stores.contains(store) {
+                image_stores.push(store);
+            }
+            continue;
+        }
+
+        let entry_type = entry.header().entry_type();
+        if CONTAINER_CLIENTS.contains(&path.as_str()) {
+            container_clients.push(format!("/{}", path));
+        } else if CONTAINER_DAEMONS.contains(&path.as_str()) {
+            container_daemons.push(format!("/{}", path));
+        }
+        if !entry_type.is_file() {
+            continue;
+        }
+
+        if path == SERVICE_ACCOUNT_TOKEN {
+            service_account_token = Some(format!("/{}", path));
+        } else if path.ends_with("/oci-layout") || path == "oci-layout" {
+            let dir = path.trim_end_matches("oci-layout").trim_end_matches('/');
+            embedded_images.push(EmbeddedImage {
+                path: format!("/{}", dir),
+                format: EmbeddedImageFormat::OciLayout,
+                tags: Vec::new(),
+            });
+        } else if is_archive_path(&path) {
+            if let Some((format, tags)) = inspect_nested_archive(&mut entry) {
+                embedded_images.push(EmbeddedImage {
+                    path: format!("/{}", path),
+                    format,
+                    tags,
+                });
+            }
+        } else if entry.size() <= MAX_TEXT_SIZE {
+            let executable = entry.header().mode().unwrap_or(0) & 0o111 != 0;
+            let kubeconfig_path = is_kubeconfig_path(&path);
+            if !kubeconfig_path && !is_script_or_config(&path, executable) {
+                continue;
+            }
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .map_err(|e| format!("Failed to read /{}: {}", path, e))?;
+            // Binaries that happen to be small
+            if content.contains(&0) {
+                continue;
+            }
+            let content = String::from_utf8_lossy(&content);
+            if kubeconfig_path || looks_like_kubeconfig(&content) {
+                kubeconfigs.push(format!("/{}", path));
+            } else if let Some(snippet) = socket_snippet(&content) {
+                socket_references.push(SocketReference {
+                    source: format!("/{}", path),
+                    snippet,
+                });
+            }
+        }
+    }
+
+    embedded_images.extend(image_stores.into_iter().map(|dir| EmbeddedImage {
+        path: format!("/{}", dir.trim_end_matches('/')),
+        format: EmbeddedImageFormat::ImageStore,
+        tags: Vec::new(),
+    }));
+
+    // VOLUME /var/run/docker.sock and ENV DOCKER_HOST only show up in the history
+    if let Ok(history) = get_image_history(session.image_id()) {
+        for entry in history {
+            if let Some(snippet) = socket_snippet(&entry.created_by) {
+                socket_references.push(SocketReference {
+                    source: "Dockerfile".to_string(),
+                    snippet,
+                });
+            }
+        }
+    }
+
+    let mut findings = Vec::new();
+    for image in &embedded_images {
+        findings.push(finding(
+            "embedded-container-image",
+            Severity::Medium,
+            format!("Container image stored at {}", image.path),
+            "The image carries another container image. That usually means images are loaded \
+             or run from inside this container, which needs a docker daemon or privileged mode. \
+             The embedded image also isn't covered by scans of this one.",
+            Some(image.path.clone()),
+            &layer_id,
+        ));
+    }
+    if !container_clients.is_empty() && !socket_references.is_empty() {
+        findings.push(finding(
+            "docker-socket-access",
+            Severity::High,
+            "Container CLI installed and configured for the docker socket".to_string(),
+            "Mounting the host's docker socket gives the container full control over the host's \
+             docker daemon, which is equivalent to root on the host. Review whether the socket \
+             mount is needed or can be replaced with a rootless builder.",
+            Some(socket_references[0].source.clone()).filter(|s| s.starts_with('/')),
+            &layer_id,
+        ));
+    } else if let Some(reference) = socket_references.first() {
+        findings.push(finding(
+            "docker-socket-reference",
+            Severity::Medium,
+            format!("{} refers to the docker socket", reference.source),
+            "Something in the image expects the docker socket to be mounted, which gives the \
+             container control over the host's docker daemon.",
+            Some(reference.source.clone()).filter(|s| s.starts_with('/')),
+            &layer_id,
+        ));
+    } else if let Some(client) = container_clients.first() {
+        findings.push(finding(
+            "container-cli-installed",
+            Severity::Info,
+            format!("Container CLI installed at {}", client),
+            "A container CLI is only useful with access to a container daemon. Check how the \
+             container is run and whether the CLI is needed at runtime.",
+            Some(client.clone()),
+            &layer_id,
+        ));
+    }
+    if let Some(daemon) = container_daemons.first() {
+        findings.push(finding(
+            "docker-in-docker",
+            Severity::Medium,
+            format!("Container daemon installed at {}", daemon),
+            "Running a container daemon inside a container requires --privileged, which \
+             disables most of the isolation between the container and the host.",
+            Some(daemon.clone()),
+            &layer_id,
+        ));
+    }
+    for path in &kubeconfigs {
+        findings.push(finding(
+            "embedded-kubeconfig",
+            Severity::High,
+            format!("Kubernetes credentials at {}", path),
+            "Kubeconfig files hold cluster addresses and usually credentials. Anyone who can \
+             pull the image can use them, mount them at runtime instead.",
+            Some(path.clone()),
+            &layer_id,
+        ));
+    }
+    if let Some(path) = service_account_token {
+        findings.push(finding(
+            "embedded-service-account-token",
+            Severity::High,
+            "Kubernetes service account token baked into the image".to_string(),
+            "Kubernetes mounts this token at runtime. A copy in the image was most likely \
+             captured from a running pod and still grants that pod's permissions.",
+            Some(path),
+            &layer_id,
+        ));
+    }
+
+    info!(
+        "Found {} embedded images, {} socket references, {} kubeconfigs",
+        embedded_images.len(),
+        socket_references.len(),
+        kubeconfigs.len()
+    );
+    Ok(ContainerAccessReport {
+        layer_id,
+        embedded_images,
+        container_clients,
+        container_daemons,
+        socket_references,
+        kubeconfigs,
+        findings,
+    })
+}
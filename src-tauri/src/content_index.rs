@@ -0,0 +1,300 @@
+use layers_core::docker::image_diff_ids;
+use layers_core::layer_tar::entry_relative_path;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tantivy::collector::{Count, TopDocs};
+use tantivy::query::QueryParser;
+use tantivy::schema::{Schema, Value, STORED, STRING, TEXT};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{doc, Index, IndexWriter, TantivyDocument};
+use tauri::Manager;
+use tracing::info;
+
+use crate::cache::{self, ExtractionCache};
+use crate::error::LayersError;
+use crate::phases::{phase, Phase, PhaseProgress};
+use crate::session::{ImageSession, SessionState};
+use crate::tasks::{ProgressReader, Task, TaskRegistry};
+use crate::{finish_task, is_binary_content};
+
+// Same cut-off as grep, bigger files are data or logs
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+const WRITER_HEAP_BYTES: usize = 100 * 1024 * 1024;
+const DEFAULT_SEARCH_LIMIT: usize = 100;
+const SNIPPET_CHARS: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContentIndexJob {
+    // Follow progress through task_status events, None when the image was
+    // already indexed
+    task_id: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ContentSearchOptions {
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContentMatch {
+    path: String,
+    score: f32,
+    // Best matching part of the file, with the byte ranges of the terms
+    snippet: String,
+    highlights: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContentSearchResult {
+    query: String,
+    // Files matching the query, best first
+    matches: Vec<ContentMatch>,
+    total_hits: usize,
+}
+
+// The index is keyed like the extraction cache, by the chain ID of the layers
+fn cache_key(session: &ImageSession) -> Result<String, String> {
+    image_diff_ids(session.image_id())
+        .ok()
+        .and_then(|diff_ids| cache::chain_id(&diff_ids))
+        .ok_or_else(|| format!("Failed to identify the layers of {}", session.image_id()))
+}
+
+fn schema() -> Schema {
+    let mut builder = Schema::builder();
+    builder.add_text_field("path", STRING | STORED);
+    builder.add_text_field("body", TEXT | STORED);
+    builder.build()
+}
+
+// Text files of the merged filesystem, read straight from the exported tar
+fn index_files(
+    task: &Task,
+    tar_path: &Path,
+    index_dir: &Path,
+    on_progress: &dyn Fn(usize, f32),
+) -> Result<usize, String> {
+    let schema = schema();
+    let path_field = schema.get_field("path").map_err(|e| e.to_string())?;
+    let body_field = schema.get_field("body").map_err(|e| e.to_string())?;
+    fs::create_dir_all(index_dir)
+        .map_err(|e| format!("Failed to create index directory: {}", e))?;
+    let index = Index::create_in_dir(index_dir, schema)
+        .map_err(|e| format!("Failed to create content index: {}", e))?;
+    let mut writer: IndexWriter = index
+        .writer(WRITER_HEAP_BYTES)
+        .map_err(|e| format!("Failed to create content index: {}", e))?;
+
+    let tar_size = fs::metadata(tar_path)
+        .map_err(|e| format!("Failed to read metadata for {:?}: {}", tar_path, e))?
+        .len()
+        .max(1);
+    let bytes_read = AtomicU64::new(0);
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(ProgressReader::new(file, |bytes| {
+        bytes_read.fetch_add(bytes, Ordering::Relaxed);
+    }));
+    let mut indexed = 0;
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for (position, entry) in entries.enumerate() {
+        task.check_cancelled()?;
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        if !entry.header().entry_type().is_file() || entry.size() > MAX_FILE_SIZE {
+            continue;
+        }
+        let path = format!(
+            "/{}",
+            entry_relative_path(
+                &entry
+                    .path()
+                    .map_err(|e| format!("Failed to read tar entry path: {}", e))?
+            )
+        );
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if bytes.is_empty() || is_binary_content(&bytes) {
+            continue;
+        }
+
+        writer
+            .add_document(doc!(
+                path_field => path.clone(),
+                body_field => String::from_utf8_lossy(&bytes).to_string(),
+            ))
+            .map_err(|e| format!("Failed to index {}: {}", path, e))?;
+        indexed += 1;
+        if position.is_multiple_of(200) {
+            on_progress(
+                indexed,
+                bytes_read.load(Ordering::Relaxed) as f32 / tar_size as f32,
+            );
+        }
+    }
+
+    task.check_cancelled()?;
+    writer
+        .commit()
+        .map_err(|e| format!("Failed to write content index: {}", e))?;
+    writer
+        .wait_merging_threads()
+        .map_err(|e| format!("Failed to write content index: {}", e))?;
+    Ok(indexed)
+}
+
+const CONTENT_INDEX_PHASES: &[Phase] = &[phase("index", 0.9), phase("save", 0.1)];
+
+fn content_index_task(
+    window: &tauri::Window,
+    task: &Task,
+    progress: &PhaseProgress,
+    session: &ImageSession,
+    key: &str,
+) -> Result<(), String> {
+    let tar_path = session.dir().join("current_layer").join("fs.tar");
+    if !tar_path.exists() {
+        return Err(format!("Tar file does not exist: {:?}", tar_path));
+    }
+
+    let cache = window.state::<ExtractionCache>();
+    let staging = cache.content_index_staging(key);
+    // A build that was cancelled or crashed leaves its files behind
+    let _ = fs::remove_dir_all(&staging);
+    task.track_path(&staging);
+
+    progress.begin("index", "Indexing file contents...");
+    let indexed = index_files(task, &tar_path, &staging, &|indexed, fraction| {
+        progress.update(&format!("Indexed {} files...", indexed), fraction);
+    })?;
+
+    progress.begin("save", "Saving content index...");
+    let path = cache.store_content_index(key)?;
+    info!("Indexed {} text files into {:?}", indexed, path);
+    Ok(())
+}
+
+// Index the text files of the selected image for search_content_index.
+// Returns right away, the work runs in the background as a cancellable task.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn build_content_index(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    cache: tauri::State<'_, ExtractionCache>,
+    session_id: Option<String>,
+    rebuild: Option<bool>,
+) -> Result<ContentIndexJob, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let key = cache_key(&session)?;
+    if !rebuild.unwrap_or(false) && cache.content_index(&key).is_some() {
+        info!("{} is already indexed", session.image_id());
+        return Ok(ContentIndexJob { task_id: None });
+    }
+    info!("Building content index of {}", session.image_id());
+
+    let task: Arc<Task> = tasks.start();
+    let job = ContentIndexJob {
+        task_id: Some(task.id),
+    };
+
+    thread::spawn(move || {
+        let progress = PhaseProgress::new(&window, task.id, CONTENT_INDEX_PHASES);
+        let result = content_index_task(&window, &task, &progress, &session, &key);
+        if let Err(e) = &result {
+            info!("Content indexing stopped: {}", e);
+        }
+        if !task.is_cancelled() {
+            match result {
+                Ok(()) => progress.complete("Content index ready"),
+                Err(e) => progress.fail(&e),
+            }
+        }
+        let tasks = window.state::<TaskRegistry>();
+        finish_task(&window, &tasks, &task);
+    });
+
+    Ok(job)
+}
+
+// Ranked full-text search over an image indexed with build_content_index.
+// Queries use tantivy's syntax, e.g. "password AND NOT example" or "\"api key\"".
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn search_content_index(
+    session: tauri::State<'_, SessionState>,
+    cache: tauri::State<'_, ExtractionCache>,
+    session_id: Option<String>,
+    query: String,
+    options: Option<ContentSearchOptions>,
+) -> Result<ContentSearchResult, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let limit = options
+        .unwrap_or_default()
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .max(1);
+    info!("Searching indexed contents for '{}'", query);
+
+    let index_dir = cache
+        .content_index(&cache_key(&session)?)
+        .ok_or_else(|| format!("{} has no content index yet", session.image_id()))?;
+    let index = Index::open_in_dir(&index_dir)
+        .map_err(|e| format!("Failed to open content index: {}", e))?;
+    let schema = index.schema();
+    let path_field = schema.get_field("path").map_err(|e| e.to_string())?;
+    let body_field = schema.get_field("body").map_err(|e| e.to_string())?;
+    let searcher = index
+        .reader()
+        .map_err(|e| format!("Failed to open content index: {}", e))?
+        .searcher();
+
+    // Typos in the query syntax search for what could be parsed instead of failing
+    let (parsed, _errors) =
+        QueryParser::for_index(&index, vec![body_field]).parse_query_lenient(&query);
+    let (top_docs, total_hits) = searcher
+        .search(&parsed, &(TopDocs::with_limit(limit), Count))
+        .map_err(|e| format!("Failed to search content index: {}", e))?;
+    let mut snippets = SnippetGenerator::create(&searcher, &parsed, body_field)
+        .map_err(|e| format!("Failed to search content index: {}", e))?;
+    snippets.set_max_num_chars(SNIPPET_CHARS);
+
+    let mut matches = Vec::new();
+    for (score, address) in top_docs {
+        let document: TantivyDocument = searcher
+            .doc(address)
+            .map_err(|e| format!("Failed to read content index: {}", e))?;
+        let snippet = snippets.snippet_from_doc(&document);
+        matches.push(ContentMatch {
+            path: document
+                .get_first(path_field)
+                .and_then(|value| value.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            score,
+            snippet: snippet.fragment().to_string(),
+            highlights: snippet
+                .highlighted()
+                .iter()
+                .map(|range| (range.start, range.end))
+                .collect(),
+        });
+    }
+
+    info!("{} files match '{}'", total_hits, query);
+    Ok(ContentSearchResult {
+        query,
+        matches,
+        total_hits,
+    })
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::Emitter;
+use tauri_plugin_deep_link::DeepLinkExt;
+use tracing::info;
+
+use crate::clipboard::reference_pattern;
+use crate::error::LayersError;
+use crate::findings::{finding_anchor, path_anchor, SecurityFinding};
+use crate::session::SessionState;
+
+// Registered with the OS in tauri.conf.json
+pub(crate) const SCHEME: &str = "layers";
+
+// Where a deep link points, links are layers://image/<reference>/finding/<anchor>
+// or layers://image/<reference>/path/<container path>
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeepLinkTarget {
+    Finding {
+        image: String,
+        anchor: String,
+    },
+    Path {
+        image: String,
+        path: String,
+        anchor: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeepLink {
+    url: String,
+    // Fragment of the same finding or path in an exported report
+    anchor: String,
+}
+
+// Percent-encodes everything but unreserved characters, and '/' if asked to
+fn encode(value: &str, keep_slashes: bool) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slashes => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn decode(value: &str) -> Result<String, String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("Invalid escape in link: {}", value))?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|e| format!("Failed to decode link: {}", e))
+}
+
+impl DeepLinkTarget {
+    fn url(&self) -> String {
+        match self {
+            DeepLinkTarget::Finding { image, anchor } => format!(
+                "{}://image/{}/finding/{}",
+                SCHEME,
+                encode(image, false),
+                anchor
+            ),
+            DeepLinkTarget::Path { image, path, .. } => format!(
+                "{}://image/{}/path/{}",
+                SCHEME,
+                encode(image, false),
+                encode(path.trim_start_matches('/'), true)
+            ),
+        }
+    }
+
+    fn anchor(&self) -> &str {
+        match self {
+            DeepLinkTarget::Finding { anchor, .. } | DeepLinkTarget::Path { anchor, .. } => anchor,
+        }
+    }
+
+    pub(crate) fn parse(url: &str) -> Result<DeepLinkTarget, String> {
+        let rest = url
+            .strip_prefix(SCHEME)
+            .and_then(|rest| rest.strip_prefix("://image/"))
+            .ok_or_else(|| format!("Not a {} image link: {}", SCHEME, url))?;
+        // Browsers and chat apps sometimes append a slash or a fragment
+        let rest = rest.split(['#', '?']).next().unwrap_or(rest);
+        let (image, rest) = rest
+            .split_once('/')
+            .ok_or_else(|| format!("Link has no target: {}", url))?;
+        let image = decode(image)?;
+        if image.is_empty() {
+            return Err(format!("Link has no image: {}", url));
+        }
+        // Opening the link pulls the image, only accept what docker would
+        if !reference_pattern().is_match(&image) {
+            return Err(format!("Link has an invalid image reference: {}", image));
+        }
+
+        match rest.split_once('/') {
+            Some(("finding", anchor)) if anchor.trim_end_matches('/').starts_with("finding-") => {
+                Ok(DeepLinkTarget::Finding {
+                    image,
+                    anchor: anchor.trim_end_matches('/').to_string(),
+                })
+            }
+            Some(("path", path)) => {
+                let path = format!("/{}", decode(path.trim_end_matches('/'))?);
+                Ok(DeepLinkTarget::Path {
+                    image,
+                    anchor: path_anchor(&path),
+                    path,
+                })
+            }
+            _ => Err(format!("Unknown link target: {}", url)),
+        }
+    }
+}
+
+impl From<DeepLinkTarget> for DeepLink {
+    fn from(target: DeepLinkTarget) -> Self {
+        DeepLink {
+            url: target.url(),
+            anchor: target.anchor().to_string(),
+        }
+    }
+}
+
+// Links are shared with teammates, so they name the image by reference
+// rather than by a local image ID
+fn link_image(session: &SessionState, image: Option<String>) -> Result<String, String> {
+    match image {
+        Some(image) => Ok(image),
+        None => session.image_reference(),
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn create_finding_link(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+    finding: SecurityFinding,
+) -> Result<DeepLink, LayersError> {
+    let target = DeepLinkTarget::Finding {
+        image: link_image(&session, image)?,
+        anchor: finding_anchor(&finding),
+    };
+    Ok(target.into())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn create_path_link(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+    path: String,
+) -> Result<DeepLink, LayersError> {
+    // Paths from the file tree are absolute paths into the extract directory
+    let extract_root = session
+        .get(None)
+        .map(|session| session.dir().join("current_layer").join("fs"))
+        .ok();
+    let relative = extract_root
+        .and_then(|root| {
+            Path::new(&path)
+                .strip_prefix(root)
+                .ok()
+                .map(Path::to_path_buf)
+        })
+        .map(|relative| relative.to_string_lossy().into_owned())
+        .unwrap_or(path);
+    let path = format!("/{}", relative.trim_start_matches('/'));
+
+    let target = DeepLinkTarget::Path {
+        image: link_image(&session, image)?,
+        anchor: path_anchor(&path),
+        path,
+    };
+    Ok(target.into())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn parse_deep_link(url: String) -> Result<DeepLinkTarget, LayersError> {
+    Ok(DeepLinkTarget::parse(&url)?)
+}
+
+// Forwards links opened while the app is running to the frontend
+pub(crate) fn listen(app: &tauri::App) -> Result<(), String> {
+    // macOS registers the scheme from the bundle, elsewhere it's done at runtime
+    #[cfg(any(windows, target_os = "linux"))]
+    app.deep_link()
+        .register_all()
+        .map_err(|e| format!("Failed to register {}:// links: {}", SCHEME, e))?;
+
+    let handle = app.handle().clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            match DeepLinkTarget::parse(url.as_str()) {
+                Ok(target) => {
+                    info!("Opening deep link {}", url);
+                    let _ = handle.emit("deep_link_opened", target);
+                }
+                Err(e) => info!("Ignoring deep link: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+// The link the app was launched with, before the frontend could listen
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_launch_deep_link(
+    app: tauri::AppHandle,
+) -> Result<Option<DeepLinkTarget>, LayersError> {
+    let urls = app
+        .deep_link()
+        .get_current()
+        .map_err(|e| format!("Failed to read launch link: {}", e))?;
+    Ok(urls
+        .unwrap_or_default()
+        .iter()
+        .find_map(|url| DeepLinkTarget::parse(url.as_str()).ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_path_link() {
+        let target = DeepLinkTarget::parse("layers://image/nginx%3A1.27/path/etc/nginx/").unwrap();
+        match target {
+            DeepLinkTarget::Path { image, path, .. } => {
+                assert_eq!(image, "nginx:1.27");
+                assert_eq!(path, "/etc/nginx");
+            }
+            other => panic!("unexpected target {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_an_invalid_image_reference() {
+        assert!(DeepLinkTarget::parse("layers://image/--privileged/path/etc").is_err());
+        assert!(DeepLinkTarget::parse("layers://image/Nginx%20latest/path/etc").is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use layers_core::docker::get_image_history;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::findings::{SecurityFinding, Severity};
+use crate::layer_mapping::normalize_created_by;
+use crate::layer_tar_path;
+use crate::provenance::split_commands;
+use crate::sbom::{scan_packages, Ecosystem, SbomComponent};
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+// pip options that take a value, so the value isn't mistaken for a package
+const PIP_VALUE_OPTIONS: [&str; 14] = [
+    "-r",
+    "--requirement",
+    "-c",
+    "--constraint",
+    "-i",
+    "--index-url",
+    "--extra-index-url",
+    "-f",
+    "--find-links",
+    "-t",
+    "--target",
+    "--prefix",
+    "--root",
+    "-e",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RequestedDependency {
+    layer_id: String,
+    ecosystem: Ecosystem,
+    // As written in the RUN instruction, e.g. "requests>=2"
+    spec: String,
+    name: String,
+    pinned: bool,
+    // Version found in the final image's metadata
+    installed_version: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DependencyAudit {
+    requested: Vec<RequestedDependency>,
+    prereleases: Vec<SbomComponent>,
+    findings: Vec<SecurityFinding>,
+}
+
+// A package named on an install command line
+struct InstallSpec {
+    spec: String,
+    name: String,
+    pinned: bool,
+}
+
+fn pep440_prerelease() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)\d[._-]?(a|b|c|rc|alpha|beta|pre|preview|dev)[._-]?\d*($|[.+])").unwrap()
+    })
+}
+
+fn exact_semver() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^v?\d+\.\d+\.\d+(-[0-9A-Za-z.-]+)?(\+[0-9A-Za-z.-]+)?$").unwrap()
+    })
+}
+
+pub(crate) fn is_prerelease(ecosystem: Ecosystem, version: &str) -> bool {
+    match ecosystem {
+        Ecosystem::Pypi => pep440_prerelease().is_match(version),
+        // Semver pre-releases have a "-" after the core version, build metadata a "+"
+        Ecosystem::Npm => version
+            .split('+')
+            .next()
+            .is_some_and(|core| core.contains('-')),
+        _ => false,
+    }
+}
+
+// PyPI and npm names compare case insensitively, PyPI also treats - _ . alike
+fn normalize_name(ecosystem: Ecosystem, name: &str) -> String {
+    let name = name.to_lowercase();
+    match ecosystem {
+        Ecosystem::Pypi => name.replace(['_', '.'], "-"),
+        _ => name,
+    }
+}
+
+// Local paths, URLs and variables can't be checked against a registry
+fn is_registry_spec(spec: &str) -> bool {
+    !(spec.starts_with('.')
+        || spec.starts_with('/')
+        || spec.starts_with('$')
+        || spec.contains("://")
+        || spec.starts_with("git+")
+        || spec.starts_with("file:")
+        || spec.ends_with(".whl")
+        || spec.ends_with(".tar.gz")
+        || spec.ends_with(".tgz"))
+}
+
+// "requests[socks]==2.31.0" -> ("requests", "==2.31.0")
+fn parse_pip_spec(spec: &str) -> (String, String) {
+    let end = spec
+        .find(|c: char| "<>=!~;[ @".contains(c))
+        .unwrap_or(spec.len());
+    let mut constraint = &spec[end..];
+    // Drop extras
+    if constraint.starts_with('[') {
+        constraint = constraint
+            .find(']')
+            .map(|close| &constraint[close + 1..])
+            .unwrap_or("");
+    }
+    (spec[..end].to_string(), constraint.trim().to_string())
+}
+
+fn pip_requested(args: &[&str]) -> Vec<InstallSpec> {
+    let mut requested = Vec::new();
+    let mut skip_next = false;
+    for arg in args {
+        if skip_next {
+            skip_next = false;
+            continue;
+        }
+        if arg.starts_with('-') {
+            skip_next = PIP_VALUE_OPTIONS.contains(arg);
+            continue;
+        }
+        let spec = arg.trim_matches(['"', '\'']);
+        if !is_registry_spec(spec) {
+            continue;
+        }
+        let (name, constraint) = parse_pip_spec(spec);
+        let pinned = (constraint.starts_with("==") && !constraint.contains('*'))
+            || constraint.starts_with("===");
+        requested.push(InstallSpec {
+            spec: spec.to_string(),
+            name,
+            pinned,
+        });
+    }
+    requested
+}
+
+// "@types/node@20.1.0" -> ("@types/node", Some("20.1.0"))
+fn npm_requested(args: &[&str]) -> Vec<InstallSpec> {
+    args.iter()
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| arg.trim_matches(['"', '\'']))
+        .filter(|spec| is_registry_spec(spec))
+        // Unscoped names with a slash are GitHub shorthands
+        .filter(|spec| spec.starts_with('@') || !spec.contains('/'))
+        .map(|spec| {
+            // A leading @ belongs to the scope, not the version
+            let (name, version) = match spec.rfind('@').filter(|at| *at > 0) {
+                Some(at) => (&spec[..at], Some(&spec[at + 1..])),
+                None => (spec, None),
+            };
+            let pinned = version.is_some_and(|v| exact_semver().is_match(v));
+            InstallSpec {
+                spec: spec.to_string(),
+                name: name.to_string(),
+                pinned,
+            }
+        })
+        .collect()
+}
+
+// Package installs in one command of a RUN instruction
+fn requested_in(command: &str) -> Option<(Ecosystem, Vec<InstallSpec>)> {
+    let tokens: Vec<&str> = command.split_whitespace().collect();
+    for (i, token) in tokens.iter().enumerate() {
+        let program = token.rsplit('/').next().unwrap_or(token);
+        let rest = &tokens[i + 1..];
+
+        // pip, pip3, pip3.11 and python -m pip
+        let is_pip = program.starts_with("pip")
+            && program[3..].chars().all(|c| c.is_ascii_digit() || c == '.');
+        if is_pip && rest.first() == Some(&"install") {
+            return Some((Ecosystem::Pypi, pip_requested(&rest[1..])));
+        }
+        if program == "-m" && rest.len() >= 2 && rest[0] == "pip" && rest[1] == "install" {
+            return Some((Ecosystem::Pypi, pip_requested(&rest[2..])));
+        }
+
+        let npm_args = match (program, rest) {
+            ("npm", [sub, args @ ..]) if ["install", "i", "add"].contains(sub) => Some(args),
+            ("yarn", ["global", "add", args @ ..]) | ("yarn", ["add", args @ ..]) => Some(args),
+            ("pnpm", [sub, args @ ..]) if ["add", "install", "i"].contains(sub) => Some(args),
+            _ => None,
+        };
+        if let Some(args) = npm_args {
+            return Some((Ecosystem::Npm, npm_requested(args)));
+        }
+    }
+    None
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn audit_language_dependencies(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+) -> Result<DependencyAudit, LayersError> {
+    info!("Auditing pip and npm dependencies");
+
+    // Installed versions come from the final filesystem's metadata
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let inventory = layer_tar_path(&task, &session, "current_layer")
+        .and_then(|tar_path| scan_packages(&task, &tar_path, false));
+    tasks.finish(task.id);
+    let installed: Vec<SbomComponent> = inventory?
+        .components
+        .into_iter()
+        .filter(|c| c.ecosystem == Ecosystem::Pypi || c.ecosystem == Ecosystem::Npm)
+        .collect();
+
+    let installed_version = |ecosystem: Ecosystem, name: &str| {
+        let name = normalize_name(ecosystem, name);
+        installed
+            .iter()
+            .find(|c| c.ecosystem == ecosystem && normalize_name(ecosystem, &c.name) == name)
+            .map(|c| c.version.clone())
+    };
+
+    let mut requested = Vec::new();
+    for (index, entry) in get_image_history(session.image_id())?.iter().enumerate() {
+        let (instruction, body) = normalize_created_by(&entry.created_by);
+        if instruction != "RUN" {
+            continue;
+        }
+        let layer_id = format!("layer_{}", index + 1);
+        for command in split_commands(&body) {
+            let Some((ecosystem, specs)) = requested_in(command) else {
+                continue;
+            };
+            for install in specs {
+                requested.push(RequestedDependency {
+                    layer_id: layer_id.clone(),
+                    ecosystem,
+                    installed_version: installed_version(ecosystem, &install.name),
+                    spec: install.spec,
+                    name: install.name,
+                    pinned: install.pinned,
+                });
+            }
+        }
+    }
+
+    let mut findings: Vec<SecurityFinding> = requested
+        .iter()
+        .filter(|r| !r.pinned)
+        .map(|r| SecurityFinding {
+            rule_id: "unpinned-dependency".to_string(),
+            severity: Severity::Medium,
+            title: format!("{} is installed without a pinned version", r.name),
+            description: format!(
+                "`{}` resolves to whatever is newest when the image is built{}. \
+                 Pin an exact version, e.g. {}, or install from a lock file.",
+                r.spec,
+                r.installed_version
+                    .as_ref()
+                    .map(|v| format!(", currently {}", v))
+                    .unwrap_or_default(),
+                match r.ecosystem {
+                    Ecosystem::Npm => format!(
+                        "{}@{}",
+                        r.name,
+                        r.installed_version.as_deref().unwrap_or("<version>")
+                    ),
+                    _ => format!(
+                        "{}=={}",
+                        r.name,
+                        r.installed_version.as_deref().unwrap_or("<version>")
+                    ),
+                }
+            ),
+            path: None,
+            layer_id: Some(r.layer_id.clone()),
+        })
+        .collect();
+
+    let prereleases: Vec<SbomComponent> = installed
+        .iter()
+        .filter(|c| is_prerelease(c.ecosystem, &c.version))
+        .cloned()
+        .collect();
+    findings.extend(prereleases.iter().map(|c| {
+        SecurityFinding {
+            rule_id: "prerelease-dependency".to_string(),
+            severity: Severity::Low,
+            title: format!("Pre-release {} {} is installed", c.name, c.version),
+            description: "Pre-release versions haven't gone through a full release cycle and \
+                      usually don't get security fixes once the final version is out."
+                .to_string(),
+            path: Some(c.path.clone()),
+            layer_id: None,
+        }
+    }));
+
+    info!(
+        "Found {} requested dependencies and {} pre-releases",
+        requested.len(),
+        prereleases.len()
+    );
+    Ok(DependencyAudit {
+        requested,
+        prereleases,
+        findings,
+    })
+}
@@ -0,0 +1,376 @@
+use layers_core::docker::{docker_command, inspect_image};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufReader, Read};
+use tracing::info;
+
+use crate::blob;
+use crate::error::LayersError;
+use crate::session::SessionState;
+use crate::tasks::{ProgressReader, Task, TaskRegistry};
+use crate::transfer::Transfer;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerDigestCheck {
+    index: usize,
+    expected_diff_id: String,
+    computed_diff_id: Option<String>,
+    matches: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteDigestCheck {
+    reference: String,
+    remote_manifest_digest: Option<String>,
+    remote_config_digest: Option<String>,
+    local_repo_digests: Vec<String>,
+    // The remote config is the same one the local image was built from
+    config_matches: bool,
+    // The tag now points at a different manifest than the one we pulled
+    tag_moved: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DigestVerificationReport {
+    image: String,
+    image_id: String,
+    layers: Vec<LayerDigestCheck>,
+    all_layers_match: bool,
+    remote: Option<RemoteDigestCheck>,
+    remote_error: Option<String>,
+}
+
+// Entry of the manifest.json written by `docker save`
+#[derive(Debug, Deserialize)]
+pub(crate) struct SaveManifestEntry {
+    #[serde(rename = "Layers")]
+    pub(crate) layers: Vec<String>,
+}
+
+pub(crate) fn sha256_reader<R: Read>(mut reader: R) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+    Ok(format!("sha256:{:x}", hasher.finalize()))
+}
+
+// What hash_save_archive found, the manifest and the digest of every blob
+type SaveDigests = (Option<Vec<SaveManifestEntry>>, HashMap<String, String>);
+
+// Stream `docker save` and hash every layer tar it contains. The uncompressed
+// layer tar digest is by definition the layer's diff ID. `size` is the image
+// size docker reports, close to what the archive holds.
+fn compute_saved_diff_ids(
+    window: &tauri::Window,
+    task: &Task,
+    image: &str,
+    size: Option<u64>,
+) -> Result<Vec<String>, String> {
+    let (manifest, digests) = task
+        .stream(docker_command().args(["save", image]), |stdout| {
+            hash_save_archive(window, task, stdout, size)
+        })
+        .map_err(|e| format!("Failed to save image {}: {}", image, e))?;
+
+    let manifest = manifest
+        .and_then(|m| m.into_iter().next())
+        .ok_or_else(|| "docker save archive has no manifest.json".to_string())?;
+
+    Ok(manifest
+        .layers
+        .iter()
+        .map(|path| digests.get(path).cloned().unwrap_or_default())
+        .collect())
+}
+
+fn hash_save_archive(
+    window: &tauri::Window,
+    task: &Task,
+    stdout: &mut dyn Read,
+    size: Option<u64>,
+) -> Result<SaveDigests, String> {
+    let transfer = RefCell::new(Transfer::new(size));
+    let stdout = ProgressReader::new(stdout, |bytes| {
+        let mut transfer = transfer.borrow_mut();
+        transfer.advance(bytes);
+        if transfer.due() {
+            let fraction = transfer.fraction().unwrap_or(0.0);
+            transfer.emit(window, task.id, "Hashing layers...", fraction);
+        }
+    });
+    let mut archive = tar::Archive::new(stdout);
+    let mut digests: HashMap<String, String> = HashMap::new();
+    let mut manifest: Option<Vec<SaveManifestEntry>> = None;
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read docker save archive: {}", e))?;
+
+    for entry in entries {
+        task.check_cancelled()?;
+
+        let mut entry = entry.map_err(|e| format!("Failed to read archive entry: {}", e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry
+            .path()
+            .map_err(|e| format!("Invalid archive entry path: {}", e))?
+            .to_string_lossy()
+            .to_string();
+        transfer.borrow_mut().set_item(path.clone());
+
+        if path == "manifest.json" {
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .map_err(|e| format!("Failed to read manifest.json: {}", e))?;
+            manifest = Some(
+                serde_json::from_slice(&content)
+                    .map_err(|e| format!("Failed to parse manifest.json: {}", e))?,
+            );
+        } else if path.ends_with(".json") || path.ends_with("VERSION") || path == "repositories" {
+            continue;
+        } else {
+            // The containerd image store saves compressed blobs, diff IDs
+            // are digests of the uncompressed tar
+            let digest = blob::decompress(BufReader::new(&mut entry), None)
+                .and_then(sha256_reader)
+                .map_err(|e| format!("Failed to hash {}: {}", path, e))?;
+            digests.insert(path, digest);
+        }
+    }
+    Ok((manifest, digests))
+}
+
+// The registry's manifest for `reference`, for the platform of the local image
+fn remote_manifest(
+    reference: &str,
+    local: &serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let output = docker_command()
+        .args(["manifest", "inspect", "--verbose", reference])
+        .output()
+        .map_err(|e| format!("Failed to run docker manifest inspect: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to fetch remote manifest for {}: {}",
+            reference,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let remote: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse remote manifest: {}", e))?;
+
+    let os = local["Os"].as_str().unwrap_or("linux");
+    let arch = local["Architecture"].as_str().unwrap_or("amd64");
+    platform_entry(remote, os, arch)
+        .ok_or_else(|| format!("{} has no manifest for {}/{}", reference, os, arch))
+}
+
+// Multi-platform tags return one entry per platform, only the one matching
+// the local image is comparable
+fn platform_entry(remote: serde_json::Value, os: &str, arch: &str) -> Option<serde_json::Value> {
+    match remote {
+        serde_json::Value::Array(entries) => entries.into_iter().find(|e| {
+            e["Descriptor"]["platform"]["os"].as_str() == Some(os)
+                && e["Descriptor"]["platform"]["architecture"].as_str() == Some(arch)
+        }),
+        remote => Some(remote),
+    }
+}
+
+// layers[].size of a manifest entry, the compressed size of every blob
+fn manifest_layer_sizes(entry: &serde_json::Value) -> Option<Vec<u64>> {
+    ["SchemaV2Manifest", "OCIManifest"]
+        .iter()
+        .find_map(|key| entry[key]["layers"].as_array())?
+        .iter()
+        .map(|layer| layer["size"].as_u64())
+        .collect()
+}
+
+// Compressed size of every layer as the registry serves it, base layer
+// first. Only images pulled from a registry have a manifest to read.
+pub(crate) fn registry_layer_sizes(image: &str) -> Result<Vec<u64>, String> {
+    let local = inspect_image(image)?;
+    let reference = local["RepoDigests"]
+        .as_array()
+        .and_then(|digests| digests.first())
+        .and_then(|digest| digest.as_str())
+        .ok_or_else(|| format!("{} was not pulled from a registry", image))?;
+    let entry = remote_manifest(reference, &local)?;
+    manifest_layer_sizes(&entry)
+        .ok_or_else(|| format!("The manifest of {} has no layer sizes", reference))
+}
+
+fn check_remote(reference: &str, local: &serde_json::Value) -> Result<RemoteDigestCheck, String> {
+    let entry = remote_manifest(reference, local)?;
+
+    let remote_manifest_digest = entry["Descriptor"]["digest"].as_str().map(String::from);
+    let remote_config_digest = ["SchemaV2Manifest", "OCIManifest"]
+        .iter()
+        .find_map(|key| entry[key]["config"]["digest"].as_str())
+        .map(String::from);
+
+    let local_repo_digests: Vec<String> = local["RepoDigests"]
+        .as_array()
+        .map(|digests| {
+            digests
+                .iter()
+                .filter_map(|d| d.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let image_id = local["Id"].as_str().unwrap_or_default();
+    let config_matches = remote_config_digest.as_deref() == Some(image_id);
+
+    // A tag only moved if we know what we pulled and the registry now disagrees
+    let tag_moved = match &remote_manifest_digest {
+        Some(remote_digest) if !local_repo_digests.is_empty() => {
+            !config_matches
+                && !local_repo_digests
+                    .iter()
+                    .any(|d| d.ends_with(&format!("@{}", remote_digest)))
+        }
+        _ => false,
+    };
+
+    Ok(RemoteDigestCheck {
+        reference: reference.to_string(),
+        remote_manifest_digest,
+        remote_config_digest,
+        local_repo_digests,
+        config_matches,
+        tag_moved,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn verify_layer_digests(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+    reference: Option<String>,
+    check_registry: Option<bool>,
+) -> Result<DigestVerificationReport, LayersError> {
+    let image = session.image_or_selected(image)?;
+    info!("Verifying layer digests of {}", image);
+
+    let local = inspect_image(&image)?;
+    let image_id = local["Id"].as_str().unwrap_or_default().to_string();
+    let expected: Vec<String> = local["RootFS"]["Layers"]
+        .as_array()
+        .ok_or_else(|| "Failed to get image layers".to_string())?
+        .iter()
+        .filter_map(|l| l.as_str().map(String::from))
+        .collect();
+
+    let task = tasks.start();
+    let computed = compute_saved_diff_ids(&window, &task, &image, local["Size"].as_u64());
+    tasks.finish(task.id);
+    let computed = computed?;
+
+    let layers: Vec<LayerDigestCheck> = expected
+        .into_iter()
+        .enumerate()
+        .map(|(index, expected_diff_id)| {
+            let computed_diff_id = computed.get(index).filter(|d| !d.is_empty()).cloned();
+            LayerDigestCheck {
+                index,
+                matches: computed_diff_id.as_deref() == Some(expected_diff_id.as_str()),
+                expected_diff_id,
+                computed_diff_id,
+            }
+        })
+        .collect();
+    let all_layers_match = layers.iter().all(|l| l.matches);
+
+    // Image IDs are never remote references, check the image's tag instead
+    let reference = reference.or_else(|| {
+        local["RepoTags"]
+            .as_array()
+            .and_then(|tags| tags.first())
+            .and_then(|tag| tag.as_str())
+            .map(String::from)
+    });
+
+    let (remote, remote_error) = match (check_registry.unwrap_or(true), reference) {
+        (true, Some(reference)) => match check_remote(&reference, &local) {
+            Ok(remote) => (Some(remote), None),
+            Err(e) => (None, Some(e)),
+        },
+        (true, None) => (None, Some("Image has no tag to check".to_string())),
+        (false, _) => (None, None),
+    };
+
+    Ok(DigestVerificationReport {
+        image,
+        image_id,
+        layers,
+        all_layers_match,
+        remote,
+        remote_error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_layer_sizes_reads_docker_and_oci_manifests() {
+        let docker = serde_json::json!({
+            "SchemaV2Manifest": {
+                "layers": [{ "size": 3_000_000 }, { "size": 120 }]
+            }
+        });
+        assert_eq!(manifest_layer_sizes(&docker), Some(vec![3_000_000, 120]));
+
+        let oci = serde_json::json!({ "OCIManifest": { "layers": [{ "size": 42 }] } });
+        assert_eq!(manifest_layer_sizes(&oci), Some(vec![42]));
+    }
+
+    #[test]
+    fn manifest_layer_sizes_needs_every_size() {
+        let missing = serde_json::json!({
+            "SchemaV2Manifest": { "layers": [{ "size": 10 }, { "digest": "sha256:ab" }] }
+        });
+        assert_eq!(manifest_layer_sizes(&missing), None);
+        assert_eq!(manifest_layer_sizes(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn platform_entry_picks_the_local_platform() {
+        let remote = serde_json::json!([
+            { "Descriptor": { "digest": "sha256:amd", "platform": { "os": "linux", "architecture": "amd64" } } },
+            { "Descriptor": { "digest": "sha256:arm", "platform": { "os": "linux", "architecture": "arm64" } } }
+        ]);
+        let entry = platform_entry(remote.clone(), "linux", "arm64").unwrap();
+        assert_eq!(entry["Descriptor"]["digest"], "sha256:arm");
+
+        // Another architecture's digests would never match, so none is picked
+        assert_eq!(platform_entry(remote, "linux", "s390x"), None);
+
+        // Single-platform tags return the manifest itself
+        let single = serde_json::json!({ "Descriptor": { "digest": "sha256:one" } });
+        assert_eq!(
+            platform_entry(single.clone(), "linux", "amd64"),
+            Some(single)
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use layers_core::docker::{docker_command, get_image_history};
+use layers_core::dockerfile::Dockerfile;
+use layers_core::size_estimate::estimate_layer_sizes;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tracing::info;
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::exec_safety::{self, ExecutionKind};
+use crate::permissions::{self, DockerCapability};
+use crate::tasks::{Task, TaskRegistry};
+use crate::{finish_task, TaskStatus};
+
+// How often the build is checked for cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// BuildKit prints a lot of output, don't flood the UI
+const EMIT_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Cached,
+    Done,
+    Failed,
+}
+
+// One instruction of the Dockerfile as BuildKit works through it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BuildStep {
+    line_number: usize,
+    instruction: String,
+    arguments: String,
+    stage: usize,
+    status: StepStatus,
+    duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BuildProgress {
+    task_id: u64,
+    steps: Vec<BuildStep>,
+    // Latest line of build output, e.g. what a RUN is printing
+    message: String,
+    is_complete: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MeasuredInstruction {
+    line_number: usize,
+    instruction: String,
+    arguments: String,
+    stage: usize,
+    // Size of its layer in the built image, None for builder-only stages
+    size_bytes: Option<u64>,
+    // What analyze_dockerfile predicted, to compare against
+    predicted_bytes: Option<u64>,
+    duration_ms: Option<u64>,
+    cached: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuildAnalysis {
+    image_id: String,
+    build_duration_ms: u64,
+    total_bytes: u64,
+    instructions: Vec<MeasuredInstruction>,
+}
+
+// "#5 [build 2/4] RUN make", the stage is left out for single stage builds
+fn vertex_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^#(\d+) \[(?:(\S+) )?(\d+)/\d+\] ").unwrap())
+}
+
+// "#5 DONE 1.2s", "#5 CACHED" or "#5 ERROR: ..."
+fn result_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^#(\d+) (DONE ([\d.]+)s|CACHED|ERROR|CANCELED)").unwrap())
+}
+
+struct BuildLog<'a> {
+    dockerfile: &'a Dockerfile,
+    progress: BuildProgress,
+    // BuildKit vertex numbers and the step each one runs
+    vertices: Vec<(u64, usize)>,
+}
+
+impl BuildLog<'_> {
+    fn new(dockerfile: &Dockerfile, task_id: u64) -> BuildLog<'_> {
+        let mut steps = Vec::new();
+        for stage in &dockerfile.stages {
+            let from = dockerfile
+                .instructions
+                .iter()
+                .find(|i| i.line_number == stage.line_number);
+            for instruction in from.into_iter().chain(&stage.instructions) {
+                steps.push(BuildStep {
+                    line_number: instruction.line_number,
+                    instruction: instruction.instruction.clone(),
+                    arguments: instruction.arguments.clone(),
+                    stage: stage.index,
+                    status: StepStatus::Pending,
+                    duration_ms: None,
+                });
+            }
+        }
+        BuildLog {
+            dockerfile,
+            progress: BuildProgress {
+                task_id,
+                steps,
+                message: String::new(),
+                is_complete: false,
+            },
+            vertices: Vec::new(),
+        }
+    }
+
+    // The step BuildKit calls "[<stage> <n>/<total>]", n counts the FROM
+    fn find_step(&self, stage: Option<&str>, number: usize) -> Option<usize> {
+        let stages = &self.dockerfile.stages;
+        let stage_index = match stage {
+            None => stages.len().checked_sub(1)?,
+            Some(stage) => stages
+                .iter()
+                .position(|s| s.name.as_deref() == Some(stage))
+                .or_else(|| stage.strip_prefix("stage-")?.parse().ok())?,
+        };
+        let stage_line = stages.get(stage_index)?.line_number;
+        let first = self
+            .progress
+            .steps
+            .iter()
+            .position(|step| step.line_number == stage_line)?;
+        let step = first + number.checked_sub(1)?;
+        (self.progress.steps.get(step)?.stage == stage_index).then_some(step)
+    }
+
+    // Whether the line changed a step, other lines only update the message
+    fn apply(&mut self, line: &str) -> bool {
+        if let Some(captures) = vertex_pattern().captures(line) {
+            let vertex: u64 = captures[1].parse().unwrap_or(0);
+            let number: usize = captures[3].parse().unwrap_or(0);
+            if let Some(step) = self.find_step(captures.get(2).map(|m| m.as_str()), number) {
+                self.vertices.push((vertex, step));
+                self.progress.steps[step].status = StepStatus::Running;
+                return true;
+            }
+        }
+        if let Some(captures) = result_pattern().captures(line) {
+            let vertex: u64 = captures[1].parse().unwrap_or(0);
+            let step = self
+                .vertices
+                .iter()
+                .rev()
+                .find(|(v, _)| *v == vertex)
+                .map(|(_, step)| *step);
+            if let Some(step) = step {
+                let step = &mut self.progress.steps[step];
+                match &captures[2] {
+                    "CACHED" => step.status = StepStatus::Cached,
+                    "ERROR" | "CANCELED" => step.status = StepStatus::Failed,
+                    _ => {
+                        step.status = StepStatus::Done;
+                        step.duration_ms = captures
+                            .get(3)
+                            .and_then(|seconds| seconds.as_str().parse::<f64>().ok())
+                            .map(|seconds| (seconds * 1000.0) as u64);
+                    }
+                }
+                return true;
+            }
+        }
+        if let Some((_, message)) = line.split_once(' ') {
+            self.progress.message = message.trim().to_string();
+        }
+        false
+    }
+}
+
+// Runs the build, streaming BuildKit's plain progress output into `log`.
+// Returns the built image's ID.
+fn run_build(
+    window: &tauri::Window,
+    task: &Task,
+    log: &mut BuildLog,
+    content: &str,
+    context_dir: &Path,
+) -> Result<String, String> {
+    // Spawned directly to stream progress, so the permission is checked here
+    permissions::require(DockerCapability::BuildImages)?;
+    let iid_file = std::env::temp_dir().join(format!("layers-build-{}.iid", task.id));
+    task.track_path(&iid_file);
+
+    let mut child = docker_command()
+        .env("DOCKER_BUILDKIT", "1")
+        .args(["build", "--progress=plain", "--iidfile"])
+        .arg(&iid_file)
+        .args(["-f", "-"])
+        .arg(context_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start docker build: {}", e))?;
+
+    // The Dockerfile comes in on stdin, so unsaved edits can be built
+    let mut stdin = child.stdin.take();
+    let content = content.to_string();
+    thread::spawn(move || {
+        if let Some(stdin) = stdin.as_mut() {
+            let _ = stdin.write_all(content.as_bytes());
+        }
+    });
+    let (sender, lines) = channel();
+    let stderr = child.stderr.take();
+    thread::spawn(move || {
+        if let Some(stderr) = stderr {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut last_emit = Instant::now();
+    // The last lines are kept for the error message if the build fails
+    let mut tail: Vec<String> = Vec::new();
+    loop {
+        match lines.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => {
+                let changed = log.apply(&line);
+                if changed || last_emit.elapsed() >= EMIT_INTERVAL {
+                    let _ = window.emit("build_progress", log.progress.clone());
+                    last_emit = Instant::now();
+                }
+                tail.push(line);
+                if tail.len() > 20 {
+                    tail.remove(0);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if task.is_cancelled() {
+            info!("Stopping build for cancelled task {}", task.id);
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("Task {} was cancelled", task.id));
+        }
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to run docker build: {}", e))?;
+    if !status.success() {
+        return Err(format!("Failed to build image: {}", tail.join("\n")));
+    }
+    fs::read_to_string(&iid_file)
+        .map(|id| id.trim().to_string())
+        .map_err(|e| format!("Failed to read built image ID: {}", e))
+}
+
+async fn build_and_analyze_task(
+    window: &tauri::Window,
+    task: &Task,
+    content: &str,
+    context_dir: &Path,
+) -> Result<BuildAnalysis, String> {
+    let update_status = |message: &str, progress: f32, is_complete: bool, error: Option<String>| {
+        let _ = window.emit(
+            "task_status",
+            TaskStatus {
+                task_id: task.id,
+                message: message.to_string(),
+                progress,
+                is_complete,
+                error,
+                phase: None,
+                transfer: None,
+            },
+        );
+    };
+
+    let dockerfile = Dockerfile::parse(content);
+    if dockerfile.stages.is_empty() {
+        return Err("The Dockerfile has no FROM instruction".to_string());
+    }
+    if !context_dir.is_dir() {
+        return Err(format!(
+            "Build context {:?} is not a directory",
+            context_dir
+        ));
+    }
+    update_status("Building image", 0.0, false, None);
+
+    let started = Instant::now();
+    let mut log = BuildLog::new(&dockerfile, task.id);
+    let result = run_build(window, task, &mut log, content, context_dir);
+    audit::record("docker build", &context_dir.to_string_lossy(), &result);
+    log.progress.is_complete = true;
+    let _ = window.emit("build_progress", log.progress.clone());
+    let image_id = match result {
+        Ok(image_id) => image_id,
+        Err(e) => {
+            update_status("Build failed", 0.0, true, Some(e.clone()));
+            return Err(e);
+        }
+    };
+    let build_duration_ms = started.elapsed().as_millis() as u64;
+    update_status("Measuring layers", 0.9, false, None);
+
+    // Every instruction of the final stage adds a history entry, metadata
+    // ones included, so the newest entries line up with it
+    let history = get_image_history(&image_id)?;
+    let final_stage = dockerfile.stages.len() - 1;
+    let final_steps: Vec<usize> = (0..log.progress.steps.len())
+        .filter(|&step| {
+            log.progress.steps[step].stage == final_stage
+                && log.progress.steps[step].instruction != "FROM"
+        })
+        .collect();
+    let mut sizes = vec![None; log.progress.steps.len()];
+    for (step, entry) in final_steps.iter().rev().zip(&history) {
+        sizes[*step] = Some(entry.size_bytes);
+    }
+
+    let predictions = estimate_layer_sizes(&dockerfile, Some(context_dir));
+    let instructions: Vec<MeasuredInstruction> = log
+        .progress
+        .steps
+        .into_iter()
+        .zip(sizes)
+        .map(|(step, size_bytes)| MeasuredInstruction {
+            predicted_bytes: predictions
+                .iter()
+                .find(|prediction| prediction.line_number == step.line_number)
+                .and_then(|prediction| prediction.bytes),
+            cached: step.status == StepStatus::Cached,
+            line_number: step.line_number,
+            instruction: step.instruction,
+            arguments: step.arguments,
+            stage: step.stage,
+            size_bytes,
+            duration_ms: step.duration_ms,
+        })
+        .collect();
+
+    info!(
+        "Built {} in {}ms with {} instructions",
+        image_id,
+        build_duration_ms,
+        instructions.len()
+    );
+    update_status("Build analyzed", 1.0, true, None);
+    Ok(BuildAnalysis {
+        total_bytes: history.iter().map(|entry| entry.size_bytes).sum(),
+        image_id,
+        build_duration_ms,
+        instructions,
+    })
+}
+
+// Builds the Dockerfile and measures what each instruction really cost,
+// progress is streamed as "build_progress" events
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn build_and_analyze_dockerfile(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    content: String,
+    context_dir: String,
+) -> Result<BuildAnalysis, LayersError> {
+    info!("Building Dockerfile in {}", context_dir);
+    exec_safety::confirm_execution(
+        &window,
+        ExecutionKind::DockerBuild,
+        &format!("Build the Dockerfile in {}", context_dir),
+    )?;
+
+    let task = tasks.start();
+    let result = build_and_analyze_task(&window, &task, &content, Path::new(&context_dir)).await;
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
@@ -0,0 +1,27 @@
+use layers_core::dockerfile::Dockerfile;
+use layers_core::lint::{lint, LintConfig, LintIssue, LintRule, RULES};
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::ruleset_sync;
+
+// Lint issues by line, sorted so the editor can underline them in order. A
+// synced team ruleset takes the place of `config`.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn lint_dockerfile(
+    content: String,
+    config: Option<LintConfig>,
+) -> Result<Vec<LintIssue>, LayersError> {
+    let dockerfile = Dockerfile::parse(&content);
+    let issues = lint(&dockerfile, &ruleset_sync::lint_config(config));
+    info!("Found {} lint issues", issues.len());
+    Ok(issues)
+}
+
+// Every rule with its default severity, for the settings screen
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_lint_rules() -> Result<Vec<LintRule>, LayersError> {
+    Ok(RULES.to_vec())
+}
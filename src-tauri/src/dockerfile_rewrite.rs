@@ -0,0 +1,25 @@
+use layers_core::rewrite::{optimize, DockerfileRewrite};
+use tracing::info;
+
+use crate::error::LayersError;
+
+// The Dockerfile rewritten with every suggested change except the rejected
+// ones, call again with a change's ID in `rejected` to leave it out
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn optimize_dockerfile(
+    content: String,
+    rejected: Option<Vec<String>>,
+) -> Result<DockerfileRewrite, LayersError> {
+    let rewrite = optimize(&content, &rejected.unwrap_or_default());
+    info!(
+        "Proposed {} Dockerfile changes, {} applied",
+        rewrite.changes.len(),
+        rewrite
+            .changes
+            .iter()
+            .filter(|change| change.applied)
+            .count()
+    );
+    Ok(rewrite)
+}
@@ -0,0 +1,151 @@
+use layers_core::dockerfile::Dockerfile;
+use layers_core::lint::{lint, LintIssue};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::Emitter;
+use tracing::{info, warn};
+
+use crate::error::LayersError;
+use crate::ruleset_sync;
+use crate::{dockerfile_analysis, DockerfileAnalysis};
+
+// Editors save in several steps (truncate, write, rename), changes this close
+// together are analyzed once
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+// Emitted as "dockerfile_analysis_updated" whenever a watched file changes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DockerfileAnalysisUpdate {
+    path: String,
+    content: String,
+    // None when the file has no instructions yet, see error
+    analysis: Option<DockerfileAnalysis>,
+    lint_issues: Vec<LintIssue>,
+    error: Option<String>,
+}
+
+// Dockerfiles being watched by path, dropping a watcher stops it, managed as Tauri state
+#[derive(Default)]
+pub struct DockerfileWatchers {
+    watchers: Mutex<HashMap<PathBuf, RecommendedWatcher>>,
+}
+
+fn analyze_file(path: &Path, context_dir: &Path) -> Result<DockerfileAnalysisUpdate, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let lint_issues = lint(
+        &Dockerfile::parse(&content),
+        &ruleset_sync::lint_config(None),
+    );
+    let (analysis, error) = match dockerfile_analysis(&content, Some(context_dir)) {
+        Ok(analysis) => (Some(analysis), None),
+        Err(e) => (None, Some(e)),
+    };
+    Ok(DockerfileAnalysisUpdate {
+        path: path.to_string_lossy().to_string(),
+        content,
+        analysis,
+        lint_issues,
+        error,
+    })
+}
+
+// Whether a change in the watched directory touched the Dockerfile itself
+fn touches(event: &Event, path: &Path) -> bool {
+    (event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove())
+        && event
+            .paths
+            .iter()
+            .any(|changed| changed.file_name() == path.file_name())
+}
+
+// Re-analyze on every batch of changes until the watcher is dropped
+fn analyze_changes(
+    window: tauri::Window,
+    path: PathBuf,
+    context_dir: PathBuf,
+    changes: Receiver<()>,
+) {
+    while changes.recv().is_ok() {
+        // Wait until the editor is done writing
+        loop {
+            match changes.recv_timeout(DEBOUNCE) {
+                Ok(()) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        // Renamed away mid-save, the new file triggers another change
+        if !path.exists() {
+            continue;
+        }
+        match analyze_file(&path, &context_dir) {
+            Ok(update) => {
+                info!("Dockerfile {:?} changed, re-analyzed", path);
+                let _ = window.emit("dockerfile_analysis_updated", update);
+            }
+            Err(e) => warn!("{}", e),
+        }
+    }
+    info!("Stopped watching {:?}", path);
+}
+
+// Watches a Dockerfile on disk and emits "dockerfile_analysis_updated" with a
+// fresh analysis whenever it changes. Returns the current analysis.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn watch_dockerfile(
+    window: tauri::Window,
+    watchers: tauri::State<'_, DockerfileWatchers>,
+    path: String,
+    context_dir: Option<String>,
+) -> Result<DockerfileAnalysisUpdate, LayersError> {
+    let path = fs::canonicalize(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let parent = path
+        .parent()
+        .ok_or_else(|| format!("{:?} has no parent directory", path))?
+        .to_path_buf();
+    // The directory holding the Dockerfile is the usual build context
+    let context_dir = context_dir
+        .map(PathBuf::from)
+        .unwrap_or_else(|| parent.clone());
+    let update = analyze_file(&path, &context_dir)?;
+
+    let (sender, changes) = channel();
+    let watched = path.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if event.is_ok_and(|event| touches(&event, &watched)) {
+            let _ = sender.send(());
+        }
+    })
+    .map_err(|e| format!("Failed to watch {:?}: {}", path, e))?;
+    // Editors that save by renaming replace the file, watching the directory
+    // keeps working across that
+    watcher
+        .watch(&parent, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {:?}: {}", parent, e))?;
+
+    let thread_path = path.clone();
+    thread::spawn(move || analyze_changes(window, thread_path, context_dir, changes));
+    info!("Watching {:?}", path);
+    // Watching the same file again replaces the old watcher
+    watchers.watchers.lock().unwrap().insert(path, watcher);
+    Ok(update)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn unwatch_dockerfile(
+    watchers: tauri::State<'_, DockerfileWatchers>,
+    path: String,
+) -> Result<bool, LayersError> {
+    let path = fs::canonicalize(&path).unwrap_or_else(|_| PathBuf::from(&path));
+    Ok(watchers.watchers.lock().unwrap().remove(&path).is_some())
+}
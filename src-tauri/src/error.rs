@@ -0,0 +1,100 @@
+use layers_core::docker::DockerError;
+use layers_core::layer_tar::display_path;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+// Error of every command, `kind` tells the frontend which fix to offer
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LayersError {
+    // The docker CLI is missing or can't reach the daemon
+    DaemonUnavailable {
+        message: String,
+    },
+    ImageNotFound {
+        image: Option<String>,
+        message: String,
+    },
+    // Exporting or unpacking an image filesystem failed
+    ExtractionFailed {
+        path: Option<String>,
+        message: String,
+    },
+    PermissionDenied {
+        path: Option<String>,
+        message: String,
+    },
+    Cancelled {
+        message: String,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl LayersError {
+    pub fn extraction_failed(path: &Path, message: String) -> Self {
+        LayersError::ExtractionFailed {
+            path: Some(display_path(path)),
+            message,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            LayersError::DaemonUnavailable { message }
+            | LayersError::ImageNotFound { message, .. }
+            | LayersError::ExtractionFailed { message, .. }
+            | LayersError::PermissionDenied { message, .. }
+            | LayersError::Cancelled { message }
+            | LayersError::Other { message } => message,
+        }
+    }
+}
+
+impl fmt::Display for LayersError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for LayersError {}
+
+impl From<DockerError> for LayersError {
+    fn from(error: DockerError) -> Self {
+        match error {
+            DockerError::Unavailable(message) => LayersError::DaemonUnavailable { message },
+            DockerError::PermissionDenied(message) => LayersError::PermissionDenied {
+                path: None,
+                message,
+            },
+            DockerError::ImageNotFound { image, message } => LayersError::ImageNotFound {
+                image: Some(image),
+                message,
+            },
+            DockerError::Failed(message) => LayersError::Other { message },
+        }
+    }
+}
+
+// Internal helpers report errors as strings, which carry no kind. Errors that
+// have one are built where they happen.
+impl From<String> for LayersError {
+    fn from(message: String) -> Self {
+        LayersError::Other { message }
+    }
+}
+
+// Lets helpers that return strings pass a typed error on as its message
+impl From<LayersError> for String {
+    fn from(error: LayersError) -> Self {
+        error.message().to_string()
+    }
+}
+
+impl From<&str> for LayersError {
+    fn from(message: &str) -> Self {
+        LayersError::from(message.to_string())
+    }
+}
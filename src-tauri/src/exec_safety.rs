@@ -0,0 +1,164 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tracing::{info, warn};
+
+// Programs that run the file they're given, matched on the file name so
+// "python3.12" counts as python
+const INTERPRETERS: [&str; 16] = [
+    "sh", "bash", "dash", "ash", "zsh", "ksh", "fish", "python", "perl", "ruby", "node", "deno",
+    "php", "lua", "java", "pwsh",
+];
+
+// Features that run image-derived code, each run needs confirm_execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExecutionKind {
+    // A user script given the extracted filesystem
+    UserScript,
+    // RUN instructions execute inside the base image
+    DockerBuild,
+    // The image's own entrypoint runs in a container
+    RunImage,
+}
+
+impl ExecutionKind {
+    fn description(&self) -> &'static str {
+        match self {
+            ExecutionKind::UserScript => {
+                "The script runs on this machine with your user's permissions and \
+                 gets read access to the files extracted from the image."
+            }
+            ExecutionKind::DockerBuild => {
+                "The build runs every RUN instruction inside containers of the base \
+                 image, executing whatever that image ships."
+            }
+            ExecutionKind::RunImage => {
+                "The image's entrypoint runs in a container on an internal network \
+                 with no route out, without capabilities and with memory, CPU and \
+                 process limits. It can still use anything the container runtime \
+                 itself exposes."
+            }
+        }
+    }
+}
+
+// docker run arguments for containers running image code, callers add an internal network
+pub(crate) const RUN_LIMITS: [&str; 10] = [
+    "--cap-drop",
+    "ALL",
+    "--security-opt",
+    "no-new-privileges",
+    "--memory",
+    "512m",
+    "--cpus",
+    "1",
+    "--pids-limit",
+    "256",
+];
+
+// Extraction, session and scratch directories live below this one in the temp directory
+pub(crate) fn extraction_root() -> PathBuf {
+    std::env::temp_dir().join("layers")
+}
+
+// Canonical form of the extraction root, /tmp is a symlink on macOS
+fn canonical_extraction_root() -> PathBuf {
+    let root = extraction_root();
+    root.canonicalize().unwrap_or(root)
+}
+
+fn is_extracted(path: &Path) -> bool {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    path.starts_with(canonical_extraction_root())
+}
+
+fn is_interpreter(program: &OsStr) -> bool {
+    let name = Path::new(program)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let name = name.trim_end_matches(".exe");
+    INTERPRETERS.iter().any(|interpreter| {
+        name.strip_prefix(interpreter)
+            .is_some_and(|version| version.chars().all(|c| c.is_ascii_digit() || c == '.'))
+    })
+}
+
+// Refuses commands that would run image content, checked for every task command
+pub(crate) fn check_command(command: &Command) -> io::Result<()> {
+    let program = Path::new(command.get_program());
+    let runs_extracted = program.components().count() > 1 && is_extracted(program);
+    let interprets_extracted = is_interpreter(command.get_program())
+        && command.get_args().any(|arg| is_extracted(Path::new(arg)));
+    if runs_extracted || interprets_extracted {
+        warn!("Refusing to execute image content: {:?}", command);
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "refusing to execute files extracted from an image",
+        ));
+    }
+    Ok(())
+}
+
+// Clears execute bits below `path`, files that can't be changed are logged
+#[cfg(unix)]
+pub(crate) fn strip_execute_bits(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        // Symlinks are skipped, their targets are visited on their own
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = fs::read_dir(&path) {
+                pending.extend(entries.flatten().map(|entry| entry.path()));
+            }
+        } else if metadata.is_file() {
+            let mode = metadata.permissions().mode();
+            if mode & 0o111 == 0 {
+                continue;
+            }
+            if let Err(e) = fs::set_permissions(&path, fs::Permissions::from_mode(mode & !0o111)) {
+                warn!("Failed to clear execute bits of {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
+// Windows has no execute bits, it goes by file extension
+#[cfg(not(unix))]
+pub(crate) fn strip_execute_bits(_path: &Path) {}
+
+// Asks before every run of image-derived code, errors when the user declines
+pub(crate) fn confirm_execution(
+    window: &tauri::Window,
+    kind: ExecutionKind,
+    target: &str,
+) -> Result<(), String> {
+    let confirmed = window
+        .dialog()
+        .message(format!("{}\n\n{}", target, kind.description()))
+        .title("Run code from an image?")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Run".to_string(),
+            "Cancel".to_string(),
+        ))
+        .blocking_show();
+    info!(
+        "Execution of {:?} ({}) {}",
+        kind,
+        target,
+        if confirmed { "confirmed" } else { "declined" }
+    );
+    if confirmed {
+        Ok(())
+    } else {
+        Err(format!("Running {} was not confirmed", target))
+    }
+}
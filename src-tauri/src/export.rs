@@ -0,0 +1,759 @@
+use layers_core::layer_tar::{display_path, path_from_escaped};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tauri::Emitter;
+use tracing::info;
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::exec_safety;
+use crate::session::{ImageSession, SessionState};
+use crate::tasks::{ProgressReader, Task, TaskRegistry};
+use crate::{finish_task, TaskStatus};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Directory,
+    Zip,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportResult {
+    destination: String,
+    files_exported: usize,
+    directories_exported: usize,
+    bytes_exported: u64,
+    // Entries the destination can't hold, e.g. names over 255 bytes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skipped: Vec<String>,
+}
+
+// The current_layer directory of a session
+fn current_layer_dir(session: &ImageSession) -> PathBuf {
+    session.dir().join("current_layer")
+}
+
+fn extract_root(layer_dir: &Path) -> PathBuf {
+    layer_dir.join("fs")
+}
+
+// Paths from the file tree are absolute paths into the extract directory,
+// in their exact form when the readable one isn't
+fn container_relative_path(layer_dir: &Path, path: &str) -> Result<PathBuf, String> {
+    let exact = path_from_escaped(path);
+    let relative = exact
+        .strip_prefix(extract_root(layer_dir))
+        .or_else(|_| exact.strip_prefix("/"))
+        .unwrap_or(&exact)
+        .to_path_buf();
+
+    if relative.as_os_str().is_empty()
+        || relative
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(format!("Invalid export path: {}", path));
+    }
+    Ok(relative)
+}
+
+// Drop selections already covered by a selected parent directory
+fn dedupe_selection(mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.sort();
+    paths.dedup();
+
+    let mut selected: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        if !selected.iter().any(|parent| path.starts_with(parent)) {
+            selected.push(path);
+        }
+    }
+    selected
+}
+
+// Where an export goes, picked by each command's format
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportTarget {
+    Directory,
+    Zip,
+    TarGz,
+}
+
+struct ExportRequest {
+    layer_id: String,
+    paths: Vec<String>,
+    destination: String,
+    target: ExportTarget,
+    // Only applies to directories, archives keep the modes from the layer
+    keep_execute_bits: bool,
+}
+
+// Path inside the layer without "./" or a leading slash
+fn normal_components(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| matches!(c, std::path::Component::Normal(_)))
+        .collect()
+}
+
+// Zip entry names are UTF-8 and always use forward slashes
+fn zip_name(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| display_path(Path::new(c.as_os_str())))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Joins a hard link's name onto the export root for writing. The first pass
+// may have unpacked symlinks, so a parent that is one is refused rather than
+// followed out of the root, and whatever is at the name itself is replaced.
+fn contained_path(root: &Path, relative: &Path) -> io::Result<PathBuf> {
+    let mut target = root.to_path_buf();
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        let std::path::Component::Normal(name) = component else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} is not a relative path", relative),
+            ));
+        };
+        target.push(name);
+        if components.peek().is_none() {
+            break;
+        }
+        match fs::symlink_metadata(&target) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{:?} passes through a symlink", relative),
+                ));
+            }
+            Ok(meta) if meta.is_dir() => {}
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{:?} passes through a file", relative),
+                ));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => fs::create_dir(&target)?,
+            Err(e) => return Err(e),
+        }
+    }
+
+    match fs::symlink_metadata(&target) {
+        Ok(meta) if meta.is_dir() => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} is a directory", relative),
+        )),
+        Ok(_) => fs::remove_file(&target).map(|_| target),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(target),
+        Err(e) => Err(e),
+    }
+}
+
+fn zip_options(header: &tar::Header) -> zip::write::SimpleFileOptions {
+    zip::write::SimpleFileOptions::default().unix_permissions(header.mode().unwrap_or(0o644))
+}
+
+// Writes entries of the layer tar into the export destination
+enum ExportWriter {
+    Directory(PathBuf),
+    Zip(zip::ZipWriter<fs::File>),
+    TarGz(tar::Builder<flate2::write::GzEncoder<fs::File>>),
+}
+
+impl ExportWriter {
+    fn create(task: &Task, target: ExportTarget, destination: &Path) -> Result<Self, String> {
+        if target == ExportTarget::Directory {
+            fs::create_dir_all(destination)
+                .map_err(|e| format!("Failed to create directory {:?}: {}", destination, e))?;
+            return Ok(Self::Directory(destination.to_path_buf()));
+        }
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+        }
+        // Opened for reading too, the zip writer reads back files it copies
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(destination)
+            .map_err(|e| format!("Failed to create archive {:?}: {}", destination, e))?;
+        task.track_path(destination);
+
+        Ok(match target {
+            ExportTarget::Zip => Self::Zip(zip::ZipWriter::new(file)),
+            _ => Self::TarGz(tar::Builder::new(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            ))),
+        })
+    }
+
+    // Zips have no hard links, their data is written under each link's name
+    fn keeps_hard_links(&self) -> bool {
+        !matches!(self, Self::Zip(_))
+    }
+
+    // Writes one entry, false when the destination has no way to hold it
+    fn add<R: io::Read>(&mut self, entry: &mut tar::Entry<R>, relative: &Path) -> io::Result<bool> {
+        let entry_type = entry.header().entry_type();
+        match self {
+            Self::Directory(root) => {
+                // Device nodes and fifos can't be created without root
+                if !(entry_type.is_file()
+                    || entry_type.is_dir()
+                    || entry_type.is_symlink()
+                    || entry_type.is_hard_link())
+                {
+                    return Ok(false);
+                }
+                // unpack_in refuses paths that would escape the destination
+                entry.unpack_in(root)
+            }
+            Self::Zip(zip) => {
+                let name = zip_name(relative);
+                let options = zip_options(entry.header());
+                if entry_type.is_dir() {
+                    zip.add_directory(name, options).map_err(io::Error::other)?;
+                } else if entry_type.is_symlink() {
+                    let target = entry
+                        .link_name()?
+                        .map(|target| display_path(&target))
+                        .unwrap_or_default();
+                    zip.add_symlink(name, target, options)
+                        .map_err(io::Error::other)?;
+                } else if entry_type.is_file() {
+                    zip.start_file(name, options).map_err(io::Error::other)?;
+                    io::copy(entry, zip)?;
+                } else {
+                    // Device nodes and fifos can't be represented in a zip
+                    return Ok(false);
+                }
+                Ok(true)
+            }
+            Self::TarGz(builder) => {
+                // Keep the original header so ownership, modes and links survive
+                let mut header = entry.header().clone();
+                if entry_type.is_hard_link() || entry_type.is_symlink() {
+                    let mut target = entry.link_name()?.unwrap_or_default().into_owned();
+                    // Hard links name another entry, which lost its "./" prefix here
+                    if entry_type.is_hard_link() {
+                        target = normal_components(&target);
+                    }
+                    builder.append_link(&mut header, relative, target)?;
+                } else {
+                    builder.append_data(&mut header, relative, entry)?;
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    // Writes the data of a file under a hard link's name
+    fn add_as<R: io::Read>(&mut self, entry: &mut tar::Entry<R>, link: &Path) -> io::Result<()> {
+        match self {
+            Self::Directory(root) => {
+                let target = contained_path(root, link)?;
+                entry.unpack(&target).map(|_| ())
+            }
+            Self::Zip(zip) => {
+                zip.start_file(zip_name(link), zip_options(entry.header()))
+                    .map_err(io::Error::other)?;
+                io::copy(entry, zip).map(|_| ())
+            }
+            Self::TarGz(builder) => {
+                let mut header = entry.header().clone();
+                builder.append_data(&mut header, link, entry)
+            }
+        }
+    }
+
+    // Adds another copy of a file add_as already wrote
+    fn copy(&mut self, header: &tar::Header, from: &Path, to: &Path) -> io::Result<()> {
+        match self {
+            Self::Directory(root) => {
+                // `from` was written by add_as, so it's a plain file in the root
+                let target = contained_path(root, to)?;
+                fs::copy(root.join(from), target).map(|_| ())
+            }
+            Self::Zip(zip) => zip
+                .deep_copy_file(&zip_name(from), &zip_name(to))
+                .map_err(io::Error::other),
+            Self::TarGz(builder) => {
+                let mut header = header.clone();
+                header.set_entry_type(tar::EntryType::Link);
+                header.set_size(0);
+                builder.append_link(&mut header, to, from)
+            }
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Directory(_) => Ok(()),
+            Self::Zip(zip) => zip.finish().map(|_| ()).map_err(io::Error::other),
+            Self::TarGz(builder) => builder.into_inner()?.finish().map(|_| ()),
+        }
+    }
+}
+
+// Streams the selected paths out of a layer tar into the destination, every
+// export command ends up here. `on_progress` gets a message and the share of
+// the tar read so far.
+fn export_entries(
+    task: &Task,
+    tar_path: &Path,
+    selection: &[PathBuf],
+    target: ExportTarget,
+    destination: &Path,
+    keep_execute_bits: bool,
+    on_progress: &dyn Fn(&str, f32),
+) -> Result<ExportResult, String> {
+    let tar_size = fs::metadata(tar_path)
+        .map_err(|e| format!("Failed to read metadata for {:?}: {}", tar_path, e))?
+        .len()
+        .max(1);
+    let mut writer = ExportWriter::create(task, target, destination)?;
+
+    let open_archive = |bytes_read: Arc<AtomicU64>| {
+        let source = fs::File::open(tar_path)
+            .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+        let mut archive = tar::Archive::new(ProgressReader::new(source, move |bytes| {
+            bytes_read.fetch_add(bytes, Ordering::Relaxed);
+        }));
+        // Keep modes and timestamps, ownership would need root on the host
+        archive.set_preserve_permissions(true);
+        archive.set_preserve_mtime(true);
+        archive.set_overwrite(true);
+        Ok::<_, String>(archive)
+    };
+
+    let mut result = ExportResult {
+        destination: destination.to_string_lossy().to_string(),
+        files_exported: 0,
+        directories_exported: 0,
+        bytes_exported: 0,
+        skipped: Vec::new(),
+    };
+    // (target, link) for hard links written in a second pass
+    let mut pending_links: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+    // Stream the layer tar once and write matching entries straight out
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let mut archive = open_archive(bytes_read.clone())?;
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for (i, entry) in entries.enumerate() {
+        task.check_cancelled()?;
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let relative = entry
+            .path()
+            .map(|path| normal_components(&path))
+            .map_err(|e| format!("Failed to read tar entry path: {}", e))?;
+
+        if i % 100 == 0 {
+            on_progress(
+                &format!("Exporting {}", display_path(&relative)),
+                bytes_read.load(Ordering::Relaxed) as f32 / tar_size as f32,
+            );
+        }
+
+        if !selection.iter().any(|s| relative.starts_with(s)) {
+            continue;
+        }
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_hard_link() {
+            let target = entry
+                .link_name()
+                .map_err(|e| format!("Failed to read link target: {}", e))?
+                .map(|target| normal_components(&target))
+                .unwrap_or_default();
+            if !writer.keeps_hard_links() || !selection.iter().any(|s| target.starts_with(s)) {
+                pending_links.push((target, relative));
+                continue;
+            }
+        }
+
+        let added = match writer.add(&mut entry, &relative) {
+            Ok(added) => added,
+            Err(e) if e.kind() == io::ErrorKind::InvalidFilename => {
+                info!("Skipping {}: {}", display_path(&relative), e);
+                result.skipped.push(display_path(&relative));
+                continue;
+            }
+            Err(e) => return Err(format!("Failed to export {:?}: {}", relative, e)),
+        };
+        if !added {
+            continue;
+        }
+        if entry_type.is_dir() {
+            result.directories_exported += 1;
+        } else {
+            result.bytes_exported += entry.size();
+            result.files_exported += 1;
+        }
+    }
+
+    // Hard links point at data earlier in the tar, fetch it for each link
+    if !pending_links.is_empty() {
+        on_progress("Resolving hard links...", 0.95);
+        let mut archive = open_archive(Arc::new(AtomicU64::new(0)))?;
+        let entries = archive
+            .entries()
+            .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+        for entry in entries {
+            task.check_cancelled()?;
+            let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let relative = entry
+                .path()
+                .map(|path| normal_components(&path))
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?;
+            let links = pending_links
+                .iter()
+                .filter(|(target, _)| *target == relative)
+                .map(|(_, link)| link);
+            // The first link written holds the data, later ones copy it
+            let mut written: Option<&PathBuf> = None;
+            for link in links {
+                let added = match written {
+                    None => writer.add_as(&mut entry, link),
+                    Some(first) => writer.copy(entry.header(), first, link),
+                };
+                match added {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::InvalidInput => {
+                        info!("Skipping {}: {}", display_path(link), e);
+                        result.skipped.push(display_path(link));
+                        continue;
+                    }
+                    Err(e) => return Err(format!("Failed to export {:?}: {}", link, e)),
+                }
+                written.get_or_insert(link);
+                result.bytes_exported += entry.size();
+                result.files_exported += 1;
+            }
+        }
+    }
+
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to finish export {:?}: {}", destination, e))?;
+
+    if result.files_exported == 0 && result.directories_exported == 0 {
+        if target != ExportTarget::Directory {
+            let _ = fs::remove_file(destination);
+        }
+        return Err("None of the selected paths exist in this layer".to_string());
+    }
+    if target == ExportTarget::Directory && !keep_execute_bits {
+        for relative in selection {
+            exec_safety::strip_execute_bits(&destination.join(relative));
+        }
+    }
+    Ok(result)
+}
+
+async fn export_task(
+    window: &tauri::Window,
+    task: &Task,
+    session: &ImageSession,
+    request: ExportRequest,
+) -> Result<ExportResult, String> {
+    let update_status = |message: &str, progress: f32, is_complete: bool, error: Option<String>| {
+        let _ = window.emit(
+            "task_status",
+            TaskStatus {
+                task_id: task.id,
+                message: message.to_string(),
+                progress,
+                is_complete,
+                error,
+                phase: None,
+                transfer: None,
+            },
+        );
+    };
+
+    if request.paths.is_empty() {
+        return Err("No paths selected for export".to_string());
+    }
+
+    update_status("Preparing export...", 0.0, false, None);
+    let layer_dir = current_layer_dir(session);
+
+    let selection = dedupe_selection(
+        request
+            .paths
+            .iter()
+            .map(|p| container_relative_path(&layer_dir, p))
+            .collect::<Result<Vec<_>, String>>()?,
+    );
+    let tar_path = crate::layer_tar_path(task, session, &request.layer_id)?;
+
+    info!(
+        "Exporting {} selected paths from {} to {}",
+        selection.len(),
+        request.layer_id,
+        request.destination
+    );
+
+    let mut result = export_entries(
+        task,
+        &tar_path,
+        &selection,
+        request.target,
+        Path::new(&request.destination),
+        request.keep_execute_bits,
+        &|message, progress| update_status(message, progress, false, None),
+    )?;
+    result.destination = request.destination;
+
+    update_status(
+        &format!("Exported {} files", result.files_exported),
+        1.0,
+        true,
+        None,
+    );
+    Ok(result)
+}
+
+async fn run_export(
+    window: &tauri::Window,
+    tasks: &TaskRegistry,
+    session: &ImageSession,
+    command: &str,
+    request: ExportRequest,
+) -> Result<ExportResult, LayersError> {
+    let destination = request.destination.clone();
+    let task = tasks.start();
+    let result = export_task(window, &task, session, request).await;
+    finish_task(window, tasks, &task);
+    audit::record(command, &destination, &result);
+    result.map_err(|e| task.error(e))
+}
+
+// Exports from the image's final filesystem, as a directory or zip
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn export_selected_paths(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    paths: Vec<String>,
+    destination: String,
+    format: ExportFormat,
+) -> Result<ExportResult, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let target = match format {
+        ExportFormat::Directory => ExportTarget::Directory,
+        ExportFormat::Zip => ExportTarget::Zip,
+    };
+    let request = ExportRequest {
+        layer_id: "current_layer".to_string(),
+        paths,
+        destination,
+        target,
+        keep_execute_bits: false,
+    };
+    run_export(&window, &tasks, &session, "export_selected_paths", request).await
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
+pub async fn archive_paths(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+    paths: Vec<String>,
+    format: ArchiveFormat,
+    dest: String,
+) -> Result<ExportResult, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let target = match format {
+        ArchiveFormat::Zip => ExportTarget::Zip,
+        ArchiveFormat::TarGz => ExportTarget::TarGz,
+    };
+    let request = ExportRequest {
+        layer_id,
+        paths,
+        destination: dest,
+        target,
+        keep_execute_bits: true,
+    };
+    run_export(&window, &tasks, &session, "archive_paths", request).await
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_files(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+    paths: Vec<String>,
+    destination: String,
+    // Exported files aren't executable unless asked for
+    keep_execute_bits: Option<bool>,
+) -> Result<ExportResult, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let request = ExportRequest {
+        layer_id,
+        paths,
+        destination,
+        target: ExportTarget::Directory,
+        keep_execute_bits: keep_execute_bits.unwrap_or(false),
+    };
+    run_export(&window, &tasks, &session, "export_files", request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{exotic_names, scratch_dir, write_exotic_tar};
+    use layers_core::layer_tar::{display_bytes, exact_path, path_bytes, path_from_bytes};
+    use std::io::Read;
+
+    #[test]
+    fn exotic_names_can_be_exported() {
+        let dir = scratch_dir("export");
+        let tar_path = dir.join("fs.tar");
+        write_exotic_tar(&tar_path);
+        let task = TaskRegistry::default().start();
+
+        // Paths as the file tree sends them, exact forms where there are any
+        let selection: Vec<PathBuf> = exotic_names()
+            .iter()
+            .map(|name| {
+                let full = extract_root(&dir).join(path_from_bytes(name));
+                let sent = exact_path(&full).unwrap_or_else(|| display_path(&full));
+                container_relative_path(&dir, &sent).unwrap()
+            })
+            .collect();
+        let export = |target, destination: &Path| {
+            export_entries(
+                &task,
+                &tar_path,
+                &selection,
+                target,
+                destination,
+                false,
+                &|_, _| {},
+            )
+            .unwrap()
+        };
+
+        let out = dir.join("out");
+        let result = export(ExportTarget::Directory, &out);
+        assert_eq!(result.files_exported, exotic_names().len());
+        assert!(result.skipped.is_empty());
+        for name in exotic_names() {
+            assert_eq!(fs::read(out.join(path_from_bytes(&name))).unwrap(), name);
+        }
+
+        // tar.gz keeps the exact bytes of every name
+        let tar_gz = dir.join("out.tar.gz");
+        export(ExportTarget::TarGz, &tar_gz);
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(
+            fs::File::open(&tar_gz).unwrap(),
+        ));
+        let mut names: Vec<Vec<u8>> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| path_bytes(&entry.unwrap().path().unwrap()))
+            .collect();
+        let mut expected = exotic_names();
+        names.sort();
+        expected.sort();
+        assert_eq!(names, expected);
+
+        // Zip names are UTF-8, so they hold the readable form
+        let zip_path = dir.join("out.zip");
+        export(ExportTarget::Zip, &zip_path);
+        let mut zip = zip::ZipArchive::new(fs::File::open(&zip_path).unwrap()).unwrap();
+        for name in exotic_names() {
+            let mut contents = Vec::new();
+            zip.by_name(&display_bytes(&name))
+                .unwrap_or_else(|e| panic!("{}: {}", display_bytes(&name), e))
+                .read_to_end(&mut contents)
+                .unwrap();
+            assert_eq!(contents, name);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn hard_links_are_not_written_through_exported_symlinks() {
+        let dir = scratch_dir("export-symlink-hardlink");
+        let outside = dir.join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        let tar_path = dir.join("fs.tar");
+
+        // "dir" points out of the export, then a hard link is named under it
+        let mut builder = tar::Builder::new(fs::File::create(&tar_path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(5);
+        builder
+            .append_data(&mut header, "etc/bashrc", &b"evil\n"[..])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder.append_link(&mut header, "dir", &outside).unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "dir/.bashrc", "etc/bashrc")
+            .unwrap();
+        builder.finish().unwrap();
+
+        let task = TaskRegistry::default().start();
+        let out = dir.join("out");
+        let result = export_entries(
+            &task,
+            &tar_path,
+            &[PathBuf::from("dir")],
+            ExportTarget::Directory,
+            &out,
+            false,
+            &|_, _| {},
+        )
+        .unwrap();
+
+        assert!(!outside.join(".bashrc").exists());
+        assert_eq!(result.skipped, vec!["dir/.bashrc".to_string()]);
+        assert!(fs::symlink_metadata(out.join("dir"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
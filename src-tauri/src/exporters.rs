@@ -0,0 +1,759 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+use crate::archive_loader::format_size;
+use crate::error::LayersError;
+use crate::reports::{Report, ReportLayerDiff};
+use crate::sbom::format_timestamp;
+use crate::startup_check::{StartupCheck, StartupOutcome};
+
+pub(crate) struct ExportedReport {
+    pub(crate) bytes: Vec<u8>,
+    pub(crate) mime_type: String,
+}
+
+// Turns the report into a file, plugins implement it as WASM modules
+pub(crate) trait ReportExporter: Send + Sync {
+    fn id(&self) -> &str;
+    fn name(&self) -> &str;
+    // Suggested file extension, without the dot
+    fn extension(&self) -> &str;
+    fn export(&self, report: &Report) -> Result<ExportedReport, String>;
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExporterInfo {
+    id: String,
+    name: String,
+    extension: String,
+    builtin: bool,
+}
+
+struct JsonExporter;
+
+impl ReportExporter for JsonExporter {
+    fn id(&self) -> &str {
+        "json"
+    }
+
+    fn name(&self) -> &str {
+        "JSON"
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn export(&self, report: &Report) -> Result<ExportedReport, String> {
+        let bytes = serde_json::to_vec_pretty(report)
+            .map_err(|e| format!("Failed to serialize report: {}", e))?;
+        Ok(ExportedReport {
+            bytes,
+            mime_type: "application/json".to_string(),
+        })
+    }
+}
+
+// Lowercase name of a severity or change kind, as it appears in JSON
+fn label<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(String::from))
+        .unwrap_or_default()
+}
+
+fn diff_for<'a>(report: &'a Report, layer_id: &str) -> Option<&'a ReportLayerDiff> {
+    report.diffs.iter().find(|diff| diff.layer_id == layer_id)
+}
+
+// Created-by lines can be whole scripts, tables only get the start
+fn short_instruction(created_by: &str) -> String {
+    let line = created_by.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() > 80 {
+        format!("{}...", line.chars().take(80).collect::<String>())
+    } else {
+        line
+    }
+}
+
+// e.g. "healthy after 12.3s" or "exited with code 1"
+fn startup_summary(check: &StartupCheck) -> String {
+    let seconds = |ms: u64| format!("{:.1}s", ms as f64 / 1000.0);
+    match (check.outcome, check.ready_ms) {
+        (StartupOutcome::Exited, _) => format!(
+            "exited with code {} after {}",
+            check.exit_code.unwrap_or_default(),
+            seconds(check.duration_ms)
+        ),
+        (outcome, Some(ready_ms)) => format!("{} after {}", label(&outcome), seconds(ready_ms)),
+        (outcome, None) => format!(
+            "{}, watched for {}",
+            label(&outcome),
+            seconds(check.duration_ms)
+        ),
+    }
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+// For pasting into pull requests, anchors use inline HTML since Markdown has
+// no syntax for them
+struct MarkdownExporter;
+
+impl MarkdownExporter {
+    fn render(report: &Report) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Image report: {}\n", report.image.reference);
+        let _ = writeln!(out, "| | |\n|---|---|");
+        let _ = writeln!(out, "| Image | `{}` |", report.image.image_id);
+        let _ = writeln!(
+            out,
+            "| Generated | {} |",
+            format_timestamp(report.generated_at)
+        );
+        let _ = writeln!(
+            out,
+            "| Size | {} in {} layers |",
+            format_size(report.total_bytes),
+            report.layers.iter().filter(|layer| !layer.empty).count()
+        );
+        let _ = writeln!(
+            out,
+            "| Efficiency | {}% ({} wasted) |",
+            report.efficiency.score,
+            format_size(report.efficiency.wasted_bytes)
+        );
+        if let Some(check) = &report.startup {
+            let _ = writeln!(out, "| Startup | {} |", startup_summary(check));
+        }
+        let _ = writeln!(out);
+
+        if !report.partial_layers.is_empty() {
+            let _ = writeln!(
+                out,
+                "> **Partial data:** these layers could only be read in part, \
+                 diffs and findings may be incomplete.\n>"
+            );
+            for layer in &report.partial_layers {
+                let _ = writeln!(
+                    out,
+                    "> - {}: {}",
+                    layer.layer_id,
+                    markdown_cell(&layer.error)
+                );
+            }
+            let _ = writeln!(out);
+        }
+
+        let _ = writeln!(out, "## Layers\n");
+        let _ = writeln!(
+            out,
+            "| Layer | Size | Added | Modified | Removed | Wasted | Instruction |"
+        );
+        let _ = writeln!(out, "|---|---|---|---|---|---|---|");
+        for layer in report.layers.iter().filter(|layer| !layer.empty) {
+            let (added, modified, removed, wasted) = diff_for(report, &layer.layer_id)
+                .map(|diff| {
+                    (
+                        diff.added_files.to_string(),
+                        diff.modified_files.to_string(),
+                        diff.removed_files.to_string(),
+                        format_size(diff.wasted_bytes),
+                    )
+                })
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | `{}` |",
+                layer.layer_id,
+                format_size(layer.size_bytes),
+                added,
+                modified,
+                removed,
+                wasted,
+                markdown_cell(&short_instruction(&layer.created_by))
+            );
+        }
+
+        let _ = writeln!(out, "\n## Largest changes");
+        for diff in report
+            .diffs
+            .iter()
+            .filter(|diff| !diff.largest_changes.is_empty())
+        {
+            let _ = writeln!(out, "\n### {}\n", diff.layer_id);
+            for change in &diff.largest_changes {
+                let _ = writeln!(
+                    out,
+                    "- <a id=\"{}\"></a>{} `{}` ({})",
+                    change.anchor,
+                    label(&change.change),
+                    change.path,
+                    format_size(change.size_bytes)
+                );
+            }
+        }
+
+        let _ = writeln!(out, "\n## Findings\n");
+        if report.findings.is_empty() {
+            let _ = writeln!(out, "No findings.");
+        }
+        for item in &report.findings {
+            let finding = &item.finding;
+            let _ = writeln!(
+                out,
+                "### <a id=\"{}\"></a>[{}] {}\n\n{}\n",
+                item.anchor,
+                label(&finding.severity),
+                finding.title,
+                finding.description
+            );
+            if let Some(path) = &finding.path {
+                let _ = writeln!(out, "Path: `{}`\n", path);
+            }
+        }
+
+        if !report.dockerfile_findings.is_empty() {
+            let _ = writeln!(out, "## Dockerfile findings\n");
+            let _ = writeln!(
+                out,
+                "| Line | Severity | Rule | Message |\n|---|---|---|---|"
+            );
+            for issue in &report.dockerfile_findings {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} |",
+                    issue.line_number,
+                    label(&issue.severity),
+                    issue.rule_id,
+                    markdown_cell(&issue.message)
+                );
+            }
+        }
+
+        if let Some(check) = &report.startup {
+            let _ = writeln!(out, "\n## Startup\n");
+            let _ = writeln!(out, "Command: `{}`\n", check.command.join(" "));
+            for port in &check.ports {
+                let listening = match port.listening {
+                    Some(true) => "listening",
+                    Some(false) => "not listening",
+                    None => "unknown",
+                };
+                let _ = writeln!(out, "- Port {}/{}: {}", port.port, port.protocol, listening);
+            }
+            let _ = writeln!(out, "\n```\n{}\n```", check.logs.join("\n"));
+            if check.logs_truncated {
+                let _ = writeln!(
+                    out,
+                    "\nOnly the first {} lines are shown.",
+                    check.logs.len()
+                );
+            }
+        }
+
+        if !report.bookmarks.is_empty() {
+            let _ = writeln!(out, "\n## Bookmarks\n");
+            let _ = writeln!(out, "| Path | Layer | Note |\n|---|---|---|");
+            for bookmark in &report.bookmarks {
+                let _ = writeln!(
+                    out,
+                    "| `{}` | {} | {} |",
+                    bookmark.path,
+                    bookmark.layer_id.as_deref().unwrap_or("all"),
+                    markdown_cell(&bookmark.note)
+                );
+            }
+        }
+        out
+    }
+}
+
+impl ReportExporter for MarkdownExporter {
+    fn id(&self) -> &str {
+        "markdown"
+    }
+
+    fn name(&self) -> &str {
+        "Markdown"
+    }
+
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn export(&self, report: &Report) -> Result<ExportedReport, String> {
+        Ok(ExportedReport {
+            bytes: Self::render(report).into_bytes(),
+            mime_type: "text/markdown".to_string(),
+        })
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem auto;max-width:64rem;\
+color:#1f2328}table{border-collapse:collapse;width:100%;margin:1rem 0}th,td{border:1px solid \
+#d0d7de;padding:.3rem .6rem;text-align:left;vertical-align:top}code{font-size:.85em}\
+.severity{font-weight:600;text-transform:uppercase}:target{background:#fff8c5}";
+
+// A single file with inline styles, so it can be attached to a review as is
+struct HtmlExporter;
+
+impl HtmlExporter {
+    fn render(report: &Report) -> String {
+        let reference = escape_html(&report.image.reference);
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Image report: {}</title>\
+             <style>{}</style></head><body>",
+            reference, HTML_STYLE
+        );
+        let _ = writeln!(out, "<h1>Image report: {}</h1>", reference);
+        let _ = writeln!(
+            out,
+            "<table><tr><th>Image</th><td><code>{}</code></td></tr>\
+             <tr><th>Generated</th><td>{}</td></tr>\
+             <tr><th>Size</th><td>{} in {} layers</td></tr>\
+             <tr><th>Efficiency</th><td>{}% ({} wasted)</td></tr>{}</table>",
+            escape_html(&report.image.image_id),
+            format_timestamp(report.generated_at),
+            format_size(report.total_bytes),
+            report.layers.iter().filter(|layer| !layer.empty).count(),
+            report.efficiency.score,
+            format_size(report.efficiency.wasted_bytes),
+            report
+                .startup
+                .as_ref()
+                .map(|check| format!(
+                    "<tr><th>Startup</th><td>{}</td></tr>",
+                    startup_summary(check)
+                ))
+                .unwrap_or_default()
+        );
+
+        if !report.partial_layers.is_empty() {
+            let _ = writeln!(
+                out,
+                "<p><strong>Partial data:</strong> these layers could only be read in part, \
+                 diffs and findings may be incomplete.</p><ul>"
+            );
+            for layer in &report.partial_layers {
+                let _ = writeln!(
+                    out,
+                    "<li><a href=\"#{}\">{}</a>: {}</li>",
+                    layer.layer_id,
+                    layer.layer_id,
+                    escape_html(&layer.error)
+                );
+            }
+            let _ = writeln!(out, "</ul>");
+        }
+
+        let _ = writeln!(
+            out,
+            "<h2>Layers</h2><table><tr><th>Layer</th><th>Size</th><th>Added</th>\
+             <th>Modified</th><th>Removed</th><th>Wasted</th><th>Instruction</th></tr>"
+        );
+        for layer in report.layers.iter().filter(|layer| !layer.empty) {
+            let diff = diff_for(report, &layer.layer_id);
+            let count = |files: fn(&ReportLayerDiff) -> usize| {
+                diff.map(|diff| files(diff).to_string()).unwrap_or_default()
+            };
+            let _ = writeln!(
+                out,
+                "<tr id=\"{id}\"><td>{id}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+                 <td>{}</td><td><code>{}</code></td></tr>",
+                format_size(layer.size_bytes),
+                count(|diff| diff.added_files),
+                count(|diff| diff.modified_files),
+                count(|diff| diff.removed_files),
+                diff.map(|diff| format_size(diff.wasted_bytes))
+                    .unwrap_or_default(),
+                escape_html(&short_instruction(&layer.created_by)),
+                id = layer.layer_id
+            );
+        }
+        let _ = writeln!(out, "</table>");
+
+        let _ = writeln!(out, "<h2>Largest changes</h2>");
+        for diff in report
+            .diffs
+            .iter()
+            .filter(|diff| !diff.largest_changes.is_empty())
+        {
+            let _ = writeln!(out, "<h3>{}</h3><ul>", diff.layer_id);
+            for change in &diff.largest_changes {
+                let _ = writeln!(
+                    out,
+                    "<li id=\"{}\">{} <code>{}</code> ({})</li>",
+                    change.anchor,
+                    label(&change.change),
+                    escape_html(&change.path),
+                    format_size(change.size_bytes)
+                );
+            }
+            let _ = writeln!(out, "</ul>");
+        }
+
+        let _ = writeln!(out, "<h2>Findings</h2>");
+        if report.findings.is_empty() {
+            let _ = writeln!(out, "<p>No findings.</p>");
+        }
+        for item in &report.findings {
+            let finding = &item.finding;
+            let _ = writeln!(
+                out,
+                "<section id=\"{}\"><h3><span class=\"severity\">{}</span> {}</h3><p>{}</p>",
+                item.anchor,
+                label(&finding.severity),
+                escape_html(&finding.title),
+                escape_html(&finding.description)
+            );
+            if let Some(path) = &finding.path {
+                let _ = writeln!(out, "<p>Path: <code>{}</code></p>", escape_html(path));
+            }
+            let _ = writeln!(out, "</section>");
+        }
+
+        if !report.dockerfile_findings.is_empty() {
+            let _ = writeln!(
+                out,
+                "<h2>Dockerfile findings</h2><table><tr><th>Line</th><th>Severity</th>\
+                 <th>Rule</th><th>Message</th></tr>"
+            );
+            for issue in &report.dockerfile_findings {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td class=\"severity\">{}</td><td>{}</td><td>{}</td></tr>",
+                    issue.line_number,
+                    label(&issue.severity),
+                    escape_html(&issue.rule_id),
+                    escape_html(&issue.message)
+                );
+            }
+            let _ = writeln!(out, "</table>");
+        }
+
+        if let Some(check) = &report.startup {
+            let _ = writeln!(
+                out,
+                "<h2>Startup</h2><p>Command: <code>{}</code></p><ul>",
+                escape_html(&check.command.join(" "))
+            );
+            for port in &check.ports {
+                let listening = match port.listening {
+                    Some(true) => "listening",
+                    Some(false) => "not listening",
+                    None => "unknown",
+                };
+                let _ = writeln!(
+                    out,
+                    "<li>Port {}/{}: {}</li>",
+                    port.port, port.protocol, listening
+                );
+            }
+            let _ = writeln!(
+                out,
+                "</ul><pre>{}</pre>",
+                escape_html(&check.logs.join("\n"))
+            );
+        }
+
+        if !report.bookmarks.is_empty() {
+            let _ = writeln!(
+                out,
+                "<h2>Bookmarks</h2><table><tr><th>Path</th><th>Layer</th><th>Note</th></tr>"
+            );
+            for bookmark in &report.bookmarks {
+                let _ = writeln!(
+                    out,
+                    "<tr><td><code>{}</code></td><td>{}</td><td>{}</td></tr>",
+                    escape_html(&bookmark.path),
+                    bookmark.layer_id.as_deref().unwrap_or("all"),
+                    escape_html(&bookmark.note)
+                );
+            }
+            let _ = writeln!(out, "</table>");
+        }
+        let _ = writeln!(out, "</body></html>");
+        out
+    }
+}
+
+impl ReportExporter for HtmlExporter {
+    fn id(&self) -> &str {
+        "html"
+    }
+
+    fn name(&self) -> &str {
+        "HTML"
+    }
+
+    fn extension(&self) -> &str {
+        "html"
+    }
+
+    fn export(&self, report: &Report) -> Result<ExportedReport, String> {
+        Ok(ExportedReport {
+            bytes: Self::render(report).into_bytes(),
+            mime_type: "text/html".to_string(),
+        })
+    }
+}
+
+// <id>.json next to <id>.wasm in the plugin directory
+#[derive(Debug, Deserialize)]
+#[cfg_attr(not(feature = "wasm-exporters"), allow(dead_code))]
+struct PluginManifest {
+    name: String,
+    mime_type: String,
+    extension: String,
+}
+
+// A plugin is a WASM module exporting its `memory` and two functions:
+//   alloc(len: i32) -> i32, returning a buffer of `len` bytes for the input
+//   export(ptr: i32, len: i32) -> i64, taking the report as JSON and
+//   returning the output's pointer in the upper and length in the lower 32 bits
+#[cfg(feature = "wasm-exporters")]
+mod wasm {
+    use wasmi::{Config, Engine, Linker, Module, Store};
+
+    use super::{ExportedReport, PluginManifest, ReportExporter};
+    use crate::reports::Report;
+
+    // Instructions a plugin may run per export, so a broken one can't hang
+    // the app
+    const FUEL: u64 = 10_000_000_000;
+
+    pub(super) struct WasmExporter {
+        id: String,
+        manifest: PluginManifest,
+        engine: Engine,
+        module: Module,
+    }
+
+    impl WasmExporter {
+        pub(super) fn load(
+            id: String,
+            manifest: PluginManifest,
+            wasm: &[u8],
+        ) -> Result<Self, String> {
+            let mut config = Config::default();
+            config.consume_fuel(true);
+            let engine = Engine::new(&config);
+            let module = Module::new(&engine, wasm)
+                .map_err(|e| format!("Failed to compile plugin {}: {}", id, e))?;
+            Ok(WasmExporter {
+                id,
+                manifest,
+                engine,
+                module,
+            })
+        }
+
+        fn run(&self, input: &[u8]) -> Result<Vec<u8>, wasmi::Error> {
+            // A fresh instance per export, plugins keep no state between runs
+            let mut store = Store::new(&self.engine, ());
+            store.set_fuel(FUEL)?;
+            // No imports, plugins can't reach the file system or network
+            let linker = Linker::<()>::new(&self.engine);
+            let instance = linker
+                .instantiate(&mut store, &self.module)?
+                .start(&mut store)?;
+            let memory = instance
+                .get_memory(&store, "memory")
+                .ok_or_else(|| wasmi::Error::new("plugin exports no memory"))?;
+            let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc")?;
+            let export = instance.get_typed_func::<(i32, i32), i64>(&store, "export")?;
+
+            let input_ptr = alloc.call(&mut store, input.len() as i32)?;
+            memory
+                .write(&mut store, input_ptr as u32 as usize, input)
+                .map_err(|e| wasmi::Error::new(e.to_string()))?;
+            let packed = export.call(&mut store, (input_ptr, input.len() as i32))?;
+            let output_ptr = (packed as u64 >> 32) as usize;
+            let output_len = (packed as u64 & 0xffff_ffff) as usize;
+            let mut output = vec![0; output_len];
+            memory
+                .read(&store, output_ptr, &mut output)
+                .map_err(|e| wasmi::Error::new(e.to_string()))?;
+            Ok(output)
+        }
+    }
+
+    impl ReportExporter for WasmExporter {
+        fn id(&self) -> &str {
+            &self.id
+        }
+
+        fn name(&self) -> &str {
+            &self.manifest.name
+        }
+
+        fn extension(&self) -> &str {
+            &self.manifest.extension
+        }
+
+        fn export(&self, report: &Report) -> Result<ExportedReport, String> {
+            let input = serde_json::to_vec(report)
+                .map_err(|e| format!("Failed to serialize report: {}", e))?;
+            let bytes = self
+                .run(&input)
+                .map_err(|e| format!("Plugin {} failed: {}", self.id, e))?;
+            Ok(ExportedReport {
+                bytes,
+                mime_type: self.manifest.mime_type.clone(),
+            })
+        }
+    }
+}
+
+// Built-in and plugin exporters by ID, managed as Tauri state
+pub struct ExporterRegistry {
+    plugin_dir: PathBuf,
+    exporters: RwLock<Vec<Arc<dyn ReportExporter>>>,
+}
+
+fn builtin_exporters() -> Vec<Arc<dyn ReportExporter>> {
+    vec![
+        Arc::new(JsonExporter),
+        Arc::new(MarkdownExporter),
+        Arc::new(HtmlExporter),
+    ]
+}
+
+// The plugins in a directory, a broken one is logged and skipped
+fn load_plugins(dir: &Path) -> Vec<Arc<dyn ReportExporter>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut plugins: Vec<Arc<dyn ReportExporter>> = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("wasm") {
+            continue;
+        }
+        let Some(id) = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+        else {
+            continue;
+        };
+        match load_plugin(&path, id) {
+            Ok(plugin) => {
+                info!("Loaded report exporter plugin {:?}", path);
+                plugins.push(plugin);
+            }
+            Err(e) => info!("Skipping report exporter plugin {:?}: {}", path, e),
+        }
+    }
+    plugins
+}
+
+fn load_plugin(path: &Path, id: String) -> Result<Arc<dyn ReportExporter>, String> {
+    let manifest = fs::read_to_string(path.with_extension("json"))
+        .map_err(|e| format!("Failed to read plugin manifest: {}", e))?;
+    let manifest: PluginManifest = serde_json::from_str(&manifest)
+        .map_err(|e| format!("Failed to parse plugin manifest: {}", e))?;
+
+    #[cfg(feature = "wasm-exporters")]
+    {
+        let wasm = fs::read(path).map_err(|e| format!("Failed to read plugin: {}", e))?;
+        let plugin = wasm::WasmExporter::load(id, manifest, &wasm)?;
+        Ok(Arc::new(plugin))
+    }
+    #[cfg(not(feature = "wasm-exporters"))]
+    {
+        let _ = (id, manifest);
+        Err("built without the wasm-exporters feature".to_string())
+    }
+}
+
+impl ExporterRegistry {
+    pub fn new(plugin_dir: PathBuf) -> Self {
+        let registry = ExporterRegistry {
+            plugin_dir,
+            exporters: RwLock::new(builtin_exporters()),
+        };
+        registry.reload();
+        registry
+    }
+
+    // Adds an exporter, replacing one with the same ID
+    pub(crate) fn register(&self, exporter: Arc<dyn ReportExporter>) {
+        let mut exporters = self.exporters.write().unwrap();
+        exporters.retain(|existing| existing.id() != exporter.id());
+        exporters.push(exporter);
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Option<Arc<dyn ReportExporter>> {
+        self.exporters
+            .read()
+            .unwrap()
+            .iter()
+            .find(|exporter| exporter.id() == id)
+            .cloned()
+    }
+
+    // Drops the plugins and loads them again, built-in exporters win over a
+    // plugin with the same ID
+    fn reload(&self) {
+        *self.exporters.write().unwrap() = Vec::new();
+        for plugin in load_plugins(&self.plugin_dir) {
+            self.register(plugin);
+        }
+        for exporter in builtin_exporters() {
+            self.register(exporter);
+        }
+    }
+
+    fn list(&self) -> Vec<ExporterInfo> {
+        let builtin: Vec<String> = builtin_exporters()
+            .iter()
+            .map(|exporter| exporter.id().to_string())
+            .collect();
+        self.exporters
+            .read()
+            .unwrap()
+            .iter()
+            .map(|exporter| ExporterInfo {
+                id: exporter.id().to_string(),
+                name: exporter.name().to_string(),
+                extension: exporter.extension().to_string(),
+                builtin: builtin.iter().any(|id| id == exporter.id()),
+            })
+            .collect()
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_report_exporters(
+    exporters: tauri::State<'_, ExporterRegistry>,
+) -> Result<Vec<ExporterInfo>, LayersError> {
+    Ok(exporters.list())
+}
+
+// Picks up plugins added to or removed from the plugin directory
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn reload_report_exporters(
+    exporters: tauri::State<'_, ExporterRegistry>,
+) -> Result<Vec<ExporterInfo>, LayersError> {
+    info!("Reloading report exporters from {:?}", exporters.plugin_dir);
+    exporters.reload();
+    Ok(exporters.list())
+}
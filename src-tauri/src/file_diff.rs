@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tracing::info;
+
+use crate::archive_loader::ensure_layer_tar;
+use crate::error::LayersError;
+use crate::is_binary_content;
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+// Diffs of bigger files are too long to read anyway
+const MAX_DIFF_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileDiffStatus {
+    Added,
+    Removed,
+    Modified,
+    Unchanged,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileDiff {
+    path: String,
+    layer1_id: String,
+    layer2_id: String,
+    status: FileDiffStatus,
+    is_binary: bool,
+    old_size: Option<u64>,
+    new_size: Option<u64>,
+    // Unified diff, only set for modified text files
+    diff: Option<String>,
+}
+
+// Tar entries are stored without a leading slash, sometimes prefixed with "./"
+fn normalize_entry_path(path: &str) -> &str {
+    path.trim_start_matches("./").trim_start_matches('/')
+}
+
+// Read one file out of a layer tar without extracting the whole layer
+fn read_file_from_tar(tar_path: &Path, file_path: &str) -> Result<Option<Vec<u8>>, String> {
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open layer tar {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+    let wanted = normalize_entry_path(file_path);
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read layer tar {:?}: {}", tar_path, e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let entry_path = entry
+            .path()
+            .map_err(|e| format!("Failed to read tar entry path: {}", e))?
+            .to_string_lossy()
+            .to_string();
+        if normalize_entry_path(&entry_path) != wanted {
+            continue;
+        }
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            return Err(format!("Path is a directory: {}", file_path));
+        }
+        // Compare links by their target rather than following them
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            let target = entry
+                .link_name()
+                .map_err(|e| format!("Failed to read link target: {}", e))?
+                .map(|t| t.to_string_lossy().to_string())
+                .unwrap_or_default();
+            return Ok(Some(format!("-> {}\n", target).into_bytes()));
+        }
+
+        if entry.size() > MAX_DIFF_FILE_SIZE {
+            return Err(format!(
+                "File is too large to diff: {} ({} bytes)",
+                file_path,
+                entry.size()
+            ));
+        }
+
+        let mut content = Vec::with_capacity(entry.size() as usize);
+        entry
+            .read_to_end(&mut content)
+            .map_err(|e| format!("Failed to read {} from layer tar: {}", file_path, e))?;
+        return Ok(Some(content));
+    }
+
+    Ok(None)
+}
+
+// Compare a file as of two layers. Each side is the filesystem with every
+// layer up to it applied, so a file deleted in between shows as removed.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn diff_file_between_layers(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    path: String,
+    layer1_id: String,
+    layer2_id: String,
+) -> Result<FileDiff, LayersError> {
+    info!(
+        "Diffing {} between layers {} and {}",
+        path, layer1_id, layer2_id
+    );
+
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let tar_paths = ensure_layer_tar(&task, &session, &layer1_id)
+        .and_then(|tar1| ensure_layer_tar(&task, &session, &layer2_id).map(|tar2| (tar1, tar2)));
+    tasks.finish(task.id);
+    let (tar1, tar2) = tar_paths?;
+
+    let old = read_file_from_tar(&tar1, &path)?;
+    let new = read_file_from_tar(&tar2, &path)?;
+
+    let status = match (&old, &new) {
+        (None, None) => return Err(format!("File not found in either layer: {}", path).into()),
+        (None, Some(_)) => FileDiffStatus::Added,
+        (Some(_), None) => FileDiffStatus::Removed,
+        (Some(a), Some(b)) if a == b => FileDiffStatus::Unchanged,
+        _ => FileDiffStatus::Modified,
+    };
+
+    let is_binary = old.as_deref().map(is_binary_content).unwrap_or(false)
+        || new.as_deref().map(is_binary_content).unwrap_or(false);
+
+    let diff = if status != FileDiffStatus::Unchanged && !is_binary {
+        let old_text = String::from_utf8_lossy(old.as_deref().unwrap_or_default());
+        let new_text = String::from_utf8_lossy(new.as_deref().unwrap_or_default());
+        let header_path = normalize_entry_path(&path);
+        Some(
+            TextDiff::from_lines(old_text.as_ref(), new_text.as_ref())
+                .unified_diff()
+                .context_radius(3)
+                .header(
+                    &format!("{}/{}", layer1_id, header_path),
+                    &format!("{}/{}", layer2_id, header_path),
+                )
+                .to_string(),
+        )
+    } else {
+        None
+    };
+
+    Ok(FileDiff {
+        old_size: old.as_ref().map(|c| c.len() as u64),
+        new_size: new.as_ref().map(|c| c.len() as u64),
+        path,
+        layer1_id,
+        layer2_id,
+        status,
+        is_binary,
+        diff,
+    })
+}
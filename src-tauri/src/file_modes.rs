@@ -0,0 +1,320 @@
+use layers_core::layer_tar::entry_relative_path;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::Path;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::findings::{SecurityFinding, Severity};
+use crate::layer_tar_path;
+use crate::ownership::{capture_user_database, FileOwner, UserDatabase};
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+const SETUID: u32 = 0o4000;
+const SETGID: u32 = 0o2000;
+const STICKY: u32 = 0o1000;
+const WORLD_WRITABLE: u32 = 0o002;
+
+// Mode and ownership from the tar header, flattened into FileItem
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileMetadata {
+    // Permission bits including setuid, setgid and sticky, e.g. 0o4755
+    mode: u32,
+    // ls style, e.g. "-rwsr-xr-x"
+    permissions: String,
+    uid: u64,
+    gid: u64,
+    // Seconds since the epoch
+    mtime: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModeMatch {
+    path: String,
+    #[serde(flatten)]
+    metadata: FileMetadata,
+    owner: FileOwner,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModeScan {
+    layer_id: String,
+    files_checked: usize,
+    files: Vec<ModeMatch>,
+    findings: Vec<SecurityFinding>,
+}
+
+// ls puts s/t over the x bit, upper case when x isn't set
+fn symbolic_permissions(kind: char, mode: u32) -> String {
+    let mut permissions = String::from(kind);
+    for (shift, special, flag) in [(6, SETUID, 's'), (3, SETGID, 's'), (0, STICKY, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        permissions.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        permissions.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        permissions.push(match (mode & special != 0, bits & 0o1 != 0) {
+            (true, true) => flag,
+            (true, false) => flag.to_ascii_uppercase(),
+            (false, true) => 'x',
+            (false, false) => '-',
+        });
+    }
+    permissions
+}
+
+impl FileMetadata {
+    fn new(kind: char, mode: u32, uid: u64, gid: u64, mtime: u64) -> Self {
+        let mode = mode & 0o7777;
+        FileMetadata {
+            mode,
+            permissions: symbolic_permissions(kind, mode),
+            uid,
+            gid,
+            mtime,
+        }
+    }
+
+    pub(crate) fn from_header(header: &tar::Header) -> Self {
+        let entry_type = header.entry_type();
+        let kind = if entry_type.is_dir() {
+            'd'
+        } else if entry_type.is_symlink() {
+            'l'
+        } else if entry_type.is_character_special() {
+            'c'
+        } else if entry_type.is_block_special() {
+            'b'
+        } else if entry_type.is_fifo() {
+            'p'
+        } else {
+            '-'
+        };
+        FileMetadata::new(
+            kind,
+            header.mode().unwrap_or(0),
+            header.uid().unwrap_or(0),
+            header.gid().unwrap_or(0),
+            header.mtime().unwrap_or(0),
+        )
+    }
+
+    // Names from the image's own /etc/passwd and /etc/group
+    pub(crate) fn owner(&self, database: &UserDatabase) -> FileOwner {
+        database.resolve(self.uid, self.gid)
+    }
+
+    // For files read off disk when the layer tar isn't around. Extracting as a
+    // regular user loses the owner, so this is a fallback only.
+    #[cfg(unix)]
+    pub(crate) fn from_disk(metadata: &fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_dir() {
+            'd'
+        } else if file_type.is_symlink() {
+            'l'
+        } else {
+            '-'
+        };
+        Some(FileMetadata::new(
+            kind,
+            metadata.mode(),
+            metadata.uid() as u64,
+            metadata.gid() as u64,
+            metadata.mtime().max(0) as u64,
+        ))
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn from_disk(_metadata: &fs::Metadata) -> Option<Self> {
+        None
+    }
+}
+
+// Every entry of the layer whose header matches, with owners resolved once
+// /etc/passwd has been read
+fn scan_modes(
+    tar_path: &Path,
+    matches: fn(&tar::Header) -> bool,
+) -> Result<(usize, Vec<ModeMatch>), String> {
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+    let mut database = UserDatabase::default();
+    let mut matched = Vec::new();
+    let mut files_checked = 0;
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let relative = entry_relative_path(
+            &entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?,
+        );
+        if relative.is_empty() {
+            continue;
+        }
+        files_checked += 1;
+        if matches(entry.header()) {
+            matched.push((
+                format!("/{}", relative),
+                FileMetadata::from_header(entry.header()),
+            ));
+        }
+        capture_user_database(&mut database, &relative, &mut entry)?;
+    }
+
+    let files = matched
+        .into_iter()
+        .map(|(path, metadata)| ModeMatch {
+            owner: database.resolve(metadata.uid, metadata.gid),
+            path,
+            metadata,
+        })
+        .collect();
+    Ok((files_checked, files))
+}
+
+fn is_setuid(header: &tar::Header) -> bool {
+    header.entry_type().is_file() && header.mode().unwrap_or(0) & (SETUID | SETGID) != 0
+}
+
+// Symlinks are always rwxrwxrwx, their mode means nothing
+fn is_world_writable(header: &tar::Header) -> bool {
+    let entry_type = header.entry_type();
+    (entry_type.is_file() || entry_type.is_dir())
+        && header.mode().unwrap_or(0) & WORLD_WRITABLE != 0
+}
+
+fn setuid_finding(file: &ModeMatch, layer_id: &str) -> SecurityFinding {
+    let mode = file.metadata.mode;
+    let (rule_id, severity, runs_as) = if mode & SETUID != 0 {
+        // Setuid to anyone but root only hands out that user
+        let severity = if file.metadata.uid == 0 {
+            Severity::High
+        } else {
+            Severity::Medium
+        };
+        ("setuid-binary", severity, "its owner")
+    } else {
+        ("setgid-binary", Severity::Medium, "its group")
+    };
+    SecurityFinding {
+        rule_id: rule_id.to_string(),
+        severity,
+        title: format!("{} is {}", file.path, rule_id.trim_end_matches("-binary")),
+        description: format!(
+            "{} ({}, owned by {}) runs with the rights of {}, whoever starts it. Remove the bit with \
+             chmod u-s,g-s unless the image needs it.",
+            file.path, file.metadata.permissions, file.owner.display, runs_as
+        ),
+        path: Some(file.path.clone()),
+        layer_id: Some(layer_id.to_string()),
+    }
+}
+
+// Directories with the sticky bit, like /tmp, are world-writable on purpose
+fn world_writable_finding(file: &ModeMatch, layer_id: &str) -> Option<SecurityFinding> {
+    let is_dir = file.metadata.permissions.starts_with('d');
+    if is_dir && file.metadata.mode & STICKY != 0 {
+        return None;
+    }
+    let (rule_id, what) = if is_dir {
+        (
+            "world-writable-directory",
+            "any user in the container can add, replace or delete files in it",
+        )
+    } else {
+        (
+            "world-writable-file",
+            "any user in the container can change it",
+        )
+    };
+    Some(SecurityFinding {
+        rule_id: rule_id.to_string(),
+        severity: Severity::Medium,
+        title: format!("{} is world-writable", file.path),
+        description: format!(
+            "{} has mode {}, {}. Drop the o+w bit or set the sticky bit on shared directories.",
+            file.path, file.metadata.permissions, what
+        ),
+        path: Some(file.path.clone()),
+        layer_id: Some(layer_id.to_string()),
+    })
+}
+
+fn layer_tar(
+    tasks: &TaskRegistry,
+    session: &SessionState,
+    session_id: Option<&str>,
+    layer_id: &str,
+) -> Result<std::path::PathBuf, String> {
+    let session = &session.get(session_id)?;
+    let task = tasks.start();
+    let tar_path = layer_tar_path(&task, session, layer_id);
+    tasks.finish(task.id);
+    tar_path
+}
+
+// Setuid and setgid files of a layer, for security review
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn find_setuid(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+) -> Result<ModeScan, LayersError> {
+    info!("Finding setuid and setgid files in layer {}", layer_id);
+    let tar_path = layer_tar(&tasks, &session, session_id.as_deref(), &layer_id)?;
+    let (files_checked, files) = scan_modes(&tar_path, is_setuid)?;
+    let findings = files
+        .iter()
+        .map(|file| setuid_finding(file, &layer_id))
+        .collect();
+
+    info!(
+        "Checked {} files, {} setuid or setgid",
+        files_checked,
+        files.len()
+    );
+    Ok(ModeScan {
+        layer_id,
+        files_checked,
+        files,
+        findings,
+    })
+}
+
+// World-writable files and directories of a layer, for security review
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn find_world_writable(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+) -> Result<ModeScan, LayersError> {
+    info!("Finding world-writable files in layer {}", layer_id);
+    let tar_path = layer_tar(&tasks, &session, session_id.as_deref(), &layer_id)?;
+    let (files_checked, files) = scan_modes(&tar_path, is_world_writable)?;
+    let findings = files
+        .iter()
+        .filter_map(|file| world_writable_finding(file, &layer_id))
+        .collect();
+
+    info!(
+        "Checked {} files, {} world-writable",
+        files_checked,
+        files.len()
+    );
+    Ok(ModeScan {
+        layer_id,
+        files_checked,
+        files,
+        findings,
+    })
+}
@@ -0,0 +1,261 @@
+use layers_core::layer_tar::path_from_escaped;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+use tracing::debug;
+
+use crate::error::LayersError;
+use crate::is_binary_content;
+
+const DEFAULT_LIMIT: u64 = 64 * 1024;
+const MAX_LIMIT: u64 = 1024 * 1024;
+// Bytes looked at to decide between the text and the hex view
+const BINARY_SNIFF: u64 = 8000;
+const HEX_ROW: usize = 16;
+const THEME: &str = "base16-ocean.dark";
+
+// Loading the bundled syntaxes takes a while, it's done once on first use
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HighlightedSpan {
+    text: String,
+    // "#rrggbb"
+    color: String,
+    bold: bool,
+    italic: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PreviewLine {
+    spans: Vec<HighlightedSpan>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HexRow {
+    offset: u64,
+    // Space separated bytes
+    hex: String,
+    // Printable ASCII, dots for everything else
+    ascii: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PreviewContent {
+    Text {
+        // Name of the syntax used, None for plain text
+        language: Option<String>,
+        lines: Vec<PreviewLine>,
+    },
+    Hex {
+        rows: Vec<HexRow>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FilePreview {
+    path: String,
+    // Range actually returned, text previews are trimmed to whole lines so
+    // the next page starts at offset + length
+    offset: u64,
+    length: u64,
+    total_size: u64,
+    eof: bool,
+    content: PreviewContent,
+}
+
+fn syntaxes() -> &'static SyntaxSet {
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    &THEMES.get_or_init(ThemeSet::load_defaults).themes[THEME]
+}
+
+// By extension, then by the first line for scripts without one
+fn find_syntax(path: &Path, first_line: &str) -> Option<&'static SyntaxReference> {
+    let syntaxes = syntaxes();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_string());
+    extension
+        .and_then(|extension| syntaxes.find_syntax_by_extension(&extension))
+        .or_else(|| {
+            let name = path.file_name()?.to_string_lossy();
+            syntaxes.find_syntax_by_extension(&name)
+        })
+        .or_else(|| syntaxes.find_syntax_by_first_line(first_line))
+}
+
+fn highlight(path: &Path, text: &str) -> PreviewContent {
+    let first_line = text.lines().next().unwrap_or_default();
+    let Some(syntax) = find_syntax(path, first_line) else {
+        return PreviewContent::Text {
+            language: None,
+            lines: text
+                .lines()
+                .map(|line| PreviewLine {
+                    spans: vec![HighlightedSpan {
+                        text: line.to_string(),
+                        color: "#c0c5ce".to_string(),
+                        bold: false,
+                        italic: false,
+                    }],
+                })
+                .collect(),
+        };
+    };
+
+    let mut highlighter = HighlightLines::new(syntax, theme());
+    let lines = LinesWithEndings::from(text)
+        .map(|line| {
+            let spans = highlighter
+                .highlight_line(line, syntaxes())
+                .map(|regions| {
+                    regions
+                        .into_iter()
+                        .map(|(style, text)| HighlightedSpan {
+                            text: text.trim_end_matches(['\n', '\r']).to_string(),
+                            color: format!(
+                                "#{:02x}{:02x}{:02x}",
+                                style.foreground.r, style.foreground.g, style.foreground.b
+                            ),
+                            bold: style.font_style.contains(FontStyle::BOLD),
+                            italic: style.font_style.contains(FontStyle::ITALIC),
+                        })
+                        .filter(|span| !span.text.is_empty())
+                        .collect()
+                })
+                // A line the grammar chokes on is still shown, just plain
+                .unwrap_or_else(|_| {
+                    vec![HighlightedSpan {
+                        text: line.trim_end_matches(['\n', '\r']).to_string(),
+                        color: "#c0c5ce".to_string(),
+                        bold: false,
+                        italic: false,
+                    }]
+                });
+            PreviewLine { spans }
+        })
+        .collect();
+    PreviewContent::Text {
+        language: Some(syntax.name.clone()),
+        lines,
+    }
+}
+
+fn hex_rows(offset: u64, bytes: &[u8]) -> Vec<HexRow> {
+    bytes
+        .chunks(HEX_ROW)
+        .enumerate()
+        .map(|(index, chunk)| HexRow {
+            offset: offset + (index * HEX_ROW) as u64,
+            hex: chunk
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" "),
+            ascii: chunk
+                .iter()
+                .map(|byte| {
+                    if byte.is_ascii_graphic() || *byte == b' ' {
+                        *byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+// Cuts a page of text to whole lines: a partial first line belongs to the
+// previous page, a partial last one to the next. Returns the byte range kept.
+fn whole_lines(bytes: &[u8], at_start: bool, eof: bool) -> (usize, usize) {
+    let start = if at_start {
+        0
+    } else {
+        bytes
+            .iter()
+            .position(|b| *b == b'\n')
+            .map(|i| i + 1)
+            .unwrap_or(0)
+    };
+    let end = if eof {
+        bytes.len()
+    } else {
+        bytes
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map(|i| i + 1)
+            .filter(|end| *end > start)
+            .unwrap_or(bytes.len())
+    };
+    (start, end)
+}
+
+// Reads part of an extracted file for the preview pane. Text comes back
+// highlighted by file type, binary files as a hex dump.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn read_file_preview(
+    path: String,
+    offset: Option<u64>,
+    limit: Option<u64>,
+) -> Result<FilePreview, LayersError> {
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    debug!("Previewing {} bytes at {} of {}", limit, offset, path);
+
+    let file_path = &path_from_escaped(&path);
+    let metadata =
+        fs::metadata(file_path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
+    if !metadata.is_file() {
+        return Err(format!("Path is not a file: {}", path).into());
+    }
+    let total_size = metadata.len();
+
+    let mut file = File::open(file_path).map_err(|e| format!("Failed to open file: {}", e))?;
+    // Decided on the start of the file, so every page uses the same view
+    let mut head = Vec::new();
+    (&mut file)
+        .take(BINARY_SNIFF)
+        .read_to_end(&mut head)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let is_binary = is_binary_content(&head);
+
+    let start = offset.min(total_size);
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(start))
+        .and_then(|_| (&mut file).take(limit).read_to_end(&mut bytes))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let read_to_end = start + bytes.len() as u64 >= total_size;
+
+    let (offset, bytes, content) = if is_binary {
+        let rows = hex_rows(start, &bytes);
+        (start, bytes, PreviewContent::Hex { rows })
+    } else {
+        let (from, to) = whole_lines(&bytes, start == 0, read_to_end);
+        let page = &bytes[from..to];
+        let content = highlight(file_path, &String::from_utf8_lossy(page));
+        (start + from as u64, page.to_vec(), content)
+    };
+    let length = bytes.len() as u64;
+
+    Ok(FilePreview {
+        path,
+        offset,
+        length,
+        total_size,
+        eof: offset + length >= total_size,
+        content,
+    })
+}
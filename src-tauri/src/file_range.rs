@@ -0,0 +1,217 @@
+use layers_core::layer_tar::path_from_escaped;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::{debug, info};
+
+use crate::error::LayersError;
+use crate::is_binary_content;
+use crate::tar_index::{FileRange, DEFAULT_RANGE_LENGTH, MAX_RANGE_LENGTH};
+
+// Every this many lines the byte offset is kept, so any line is at most this
+// many lines of reading away
+const LINE_CHECKPOINT: u64 = 1000;
+const DEFAULT_LINE_COUNT: u64 = 500;
+const MAX_LINE_COUNT: u64 = 5000;
+// Minified files can have megabyte long lines, the rest is cut off
+const MAX_LINE_LENGTH: usize = 16 * 1024;
+const READ_BUFFER: usize = 1024 * 1024;
+
+// Line offsets of one file, valid as long as its size and mtime don't change
+struct LineIndex {
+    size: u64,
+    modified: Option<SystemTime>,
+    line_count: u64,
+    // Byte offset of line i * LINE_CHECKPOINT
+    checkpoints: Vec<u64>,
+}
+
+// Line indexes of the files opened so far, managed as Tauri state
+#[derive(Default)]
+pub struct LineIndexes {
+    indexes: Mutex<HashMap<PathBuf, Arc<LineIndex>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LineIndexInfo {
+    path: String,
+    total_size: u64,
+    line_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileLines {
+    path: String,
+    start_line: u64,
+    // Byte offset of start_line
+    offset: u64,
+    lines: Vec<String>,
+    line_count: u64,
+    eof: bool,
+}
+
+fn file_metadata(path: &Path) -> Result<fs::Metadata, String> {
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
+    if !metadata.is_file() {
+        return Err(format!("Path is not a file: {:?}", path));
+    }
+    Ok(metadata)
+}
+
+// One pass over the file, never holding more than the read buffer
+fn build_line_index(path: &Path, metadata: &fs::Metadata) -> Result<LineIndex, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut buffer = vec![0u8; READ_BUFFER];
+    let mut checkpoints = vec![0];
+    let mut offset = 0u64;
+    let mut newlines = 0u64;
+    let mut last_byte = None;
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        for (i, byte) in buffer[..read].iter().enumerate() {
+            if *byte == b'\n' {
+                newlines += 1;
+                if newlines.is_multiple_of(LINE_CHECKPOINT) {
+                    checkpoints.push(offset + i as u64 + 1);
+                }
+            }
+        }
+        offset += read as u64;
+        last_byte = Some(buffer[read - 1]);
+    }
+    // A last line without a newline still counts
+    let line_count = match last_byte {
+        Some(b'\n') | None => newlines,
+        Some(_) => newlines + 1,
+    };
+    Ok(LineIndex {
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+        line_count,
+        checkpoints,
+    })
+}
+
+impl LineIndexes {
+    fn get(&self, path: &Path) -> Result<Arc<LineIndex>, String> {
+        let metadata = file_metadata(path)?;
+        if let Some(index) = self.indexes.lock().unwrap().get(path) {
+            if index.size == metadata.len() && index.modified == metadata.modified().ok() {
+                return Ok(index.clone());
+            }
+        }
+        info!("Indexing lines of {:?}", path);
+        let index = Arc::new(build_line_index(path, &metadata)?);
+        self.indexes
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), index.clone());
+        Ok(index)
+    }
+}
+
+// Reads part of an extracted file without loading the rest, for files far
+// too big to open whole
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn read_file_range(
+    path: String,
+    offset: Option<u64>,
+    length: Option<u64>,
+) -> Result<FileRange, LayersError> {
+    let offset = offset.unwrap_or(0);
+    let length = length.unwrap_or(DEFAULT_RANGE_LENGTH).min(MAX_RANGE_LENGTH);
+    debug!("Reading {} bytes at {} of {}", length, offset, path);
+
+    let file_path = path_from_escaped(&path);
+    let total_size = file_metadata(&file_path)?.len();
+    let mut file = File::open(&file_path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(offset.min(total_size)))
+        .and_then(|_| (&mut file).take(length).read_to_end(&mut bytes))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let length = bytes.len() as u64;
+    Ok(FileRange {
+        is_binary: is_binary_content(&bytes[..bytes.len().min(8000)]),
+        content: String::from_utf8_lossy(&bytes).to_string(),
+        path,
+        offset,
+        length,
+        total_size,
+        eof: offset + length >= total_size,
+    })
+}
+
+// Line count of a file, indexing it on first use so any line can be jumped to
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_line_index(
+    indexes: tauri::State<'_, LineIndexes>,
+    path: String,
+) -> Result<LineIndexInfo, LayersError> {
+    let index = indexes.get(&path_from_escaped(&path))?;
+    Ok(LineIndexInfo {
+        path,
+        total_size: index.size,
+        line_count: index.line_count,
+    })
+}
+
+// Lines start_line.. of a file, zero based, for scrolling through it
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn read_file_lines(
+    indexes: tauri::State<'_, LineIndexes>,
+    path: String,
+    start_line: u64,
+    count: Option<u64>,
+) -> Result<FileLines, LayersError> {
+    let count = count.unwrap_or(DEFAULT_LINE_COUNT).clamp(1, MAX_LINE_COUNT);
+    let index = indexes.get(&path_from_escaped(&path))?;
+    let start_line = start_line.min(index.line_count);
+
+    let checkpoint = ((start_line / LINE_CHECKPOINT) as usize).min(index.checkpoints.len() - 1);
+    let mut offset = index.checkpoints[checkpoint];
+    let mut file =
+        File::open(path_from_escaped(&path)).map_err(|e| format!("Failed to open file: {}", e))?;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut reader = BufReader::new(file);
+
+    let mut line = Vec::new();
+    let mut read_line = |line: &mut Vec<u8>| {
+        line.clear();
+        reader
+            .read_until(b'\n', line)
+            .map_err(|e| format!("Failed to read file: {}", e))
+    };
+    for _ in checkpoint as u64 * LINE_CHECKPOINT..start_line {
+        offset += read_line(&mut line)? as u64;
+    }
+    let mut lines = Vec::new();
+    while (lines.len() as u64) < count && read_line(&mut line)? > 0 {
+        let end = line.len().min(MAX_LINE_LENGTH);
+        let text = String::from_utf8_lossy(&line[..end]);
+        lines.push(text.trim_end_matches(['\n', '\r']).to_string());
+    }
+
+    Ok(FileLines {
+        path,
+        start_line,
+        offset,
+        eof: start_line + lines.len() as u64 >= index.line_count,
+        lines,
+        line_count: index.line_count,
+    })
+}
@@ -0,0 +1,417 @@
+use layers_core::layer_tar::{
+    display_bytes, display_path, escape_path, exact_path, path_from_escaped, unescape_path,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Component, Path};
+use tracing::{debug, info};
+
+use crate::error::LayersError;
+use crate::links::{resolve_link, LinkInfo, LinkKind, MAX_LINK_HOPS};
+use crate::ownership::{capture_user_database, FileOwner, UserDatabase};
+use crate::session::SessionState;
+use crate::xattrs::{read_entry_attributes, FileAttributes};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileTreeNode {
+    name: String,
+    // Absolute path into the extract directory, same as FileItem.path
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exact_path: Option<String>,
+    #[serde(rename = "type")]
+    node_type: String,
+    // For directories, the total size of everything below them
+    size_bytes: u64,
+    // Number of non-directory entries below this node (1 for files)
+    file_count: usize,
+    link_target: Option<String>,
+    // The link points to nothing in this layer, or loops
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    broken_link: bool,
+    // uid/gid resolved against the image's /etc/passwd and /etc/group
+    owner: FileOwner,
+    // PAX extended attributes such as SELinux labels and file capabilities
+    attributes: Option<FileAttributes>,
+    // Whether the entry has been extracted to disk yet
+    extracted: bool,
+    children: Vec<FileTreeNode>,
+    // Total number of direct children, before depth and pagination limits
+    child_count: usize,
+    has_more: bool,
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    node_type: &'static str,
+    size: u64,
+    // Escaped like the names of children, see path_components
+    link_target: Option<String>,
+    uid: u64,
+    gid: u64,
+    attributes: Option<FileAttributes>,
+    children: BTreeMap<String, TreeBuilder>,
+}
+
+// Shared by every node when converting the builder into FileTreeNodes
+struct NodeContext<'a> {
+    root: &'a TreeBuilder,
+    extract_dir: &'a Path,
+    users: &'a UserDatabase,
+}
+
+impl TreeBuilder {
+    fn directory() -> Self {
+        TreeBuilder {
+            node_type: "directory",
+            ..Default::default()
+        }
+    }
+
+    fn insert(&mut self, components: &[String], leaf: TreeBuilder) {
+        let Some((name, rest)) = components.split_first() else {
+            return;
+        };
+
+        if rest.is_empty() {
+            // Keep children that were listed before their directory entry
+            let children = self
+                .children
+                .remove(name)
+                .map(|existing| existing.children)
+                .unwrap_or_default();
+            self.children
+                .insert(name.clone(), TreeBuilder { children, ..leaf });
+        } else {
+            // Parents listed after their children still end up as directories
+            let child = self
+                .children
+                .entry(name.clone())
+                .or_insert_with(TreeBuilder::directory);
+            child.node_type = "directory";
+            child.insert(rest, leaf);
+        }
+    }
+
+    fn find(&self, components: &[String]) -> Option<&TreeBuilder> {
+        match components.split_first() {
+            None => Some(self),
+            Some((name, rest)) => self.children.get(name)?.find(rest),
+        }
+    }
+
+    // Follows the link at `relative` through the tree, like LinkIndex::is_broken
+    fn is_broken_link(&self, relative: &Path) -> bool {
+        let mut current = path_components(relative).join("/");
+        for _ in 0..MAX_LINK_HOPS {
+            let components: Vec<String> = current
+                .split('/')
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect();
+            let Some(node) = self.find(&components) else {
+                return true;
+            };
+            let kind = match node.node_type {
+                "symlink" => LinkKind::Symlink,
+                "hardlink" => LinkKind::Hardlink,
+                _ => return false,
+            };
+            let Some(target) = node.link_target.clone() else {
+                return true;
+            };
+            current = resolve_link(&current, &LinkInfo { kind, target });
+        }
+        true
+    }
+
+    // (total size, file count) for the whole subtree
+    fn totals(&self) -> (u64, usize) {
+        if self.node_type != "directory" {
+            return (self.size, 1);
+        }
+        self.children
+            .values()
+            .map(TreeBuilder::totals)
+            .fold((0, 0), |(size, count), (s, c)| (size + s, count + c))
+    }
+
+    fn to_node(
+        &self,
+        name: &str,
+        relative: &Path,
+        context: &NodeContext,
+        depth: usize,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> FileTreeNode {
+        let (size_bytes, file_count) = self.totals();
+        let full_path = context.extract_dir.join(relative);
+
+        // Pagination only applies to the node that was requested, deeper
+        // levels are returned whole up to the depth limit
+        let mut children = Vec::new();
+        if depth > 0 {
+            let page = self
+                .children
+                .iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX));
+            for (child_name, child) in page {
+                children.push(child.to_node(
+                    child_name,
+                    &relative.join(path_from_escaped(child_name)),
+                    context,
+                    depth - 1,
+                    0,
+                    None,
+                ));
+            }
+        }
+
+        FileTreeNode {
+            name: display_bytes(&unescape_path(name)),
+            path: display_path(&full_path),
+            exact_path: exact_path(&full_path),
+            node_type: self.node_type.to_string(),
+            size_bytes,
+            file_count,
+            link_target: self
+                .link_target
+                .as_deref()
+                .map(|target| display_bytes(&unescape_path(target))),
+            broken_link: self.link_target.is_some() && context.root.is_broken_link(relative),
+            owner: context.users.resolve(self.uid, self.gid),
+            attributes: self.attributes.clone(),
+            // A link counts as extracted even when its target isn't
+            extracted: fs::symlink_metadata(&full_path).is_ok(),
+            has_more: offset + children.len() < self.children.len(),
+            child_count: self.children.len(),
+            children,
+        }
+    }
+}
+
+// Names in their escaped form, so names that only differ in bytes that
+// aren't UTF-8 stay apart
+fn path_components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(escape_path(Path::new(name))),
+            _ => None,
+        })
+        .collect()
+}
+
+// Tree of the tar entries `keep` accepts, with the directories leading to them
+fn build_tree(
+    tar_path: &Path,
+    keep: impl Fn(&Path) -> bool,
+) -> Result<(TreeBuilder, UserDatabase), String> {
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+    let mut root = TreeBuilder::directory();
+    let mut users = UserDatabase::default();
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to list tar contents: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let path = entry
+            .path()
+            .map_err(|e| format!("Failed to read tar entry path: {}", e))?;
+        let components = path_components(&path);
+        if components.is_empty() {
+            continue;
+        }
+        let kept = keep(&path);
+
+        let entry_type = entry.header().entry_type();
+        let node_type = if entry_type.is_dir() {
+            "directory"
+        } else if entry_type.is_symlink() {
+            "symlink"
+        } else if entry_type.is_hard_link() {
+            "hardlink"
+        } else {
+            "file"
+        };
+        let link = if entry_type.is_symlink() || entry_type.is_hard_link() {
+            entry
+                .link_name()
+                .ok()
+                .flatten()
+                .map(|target| escape_path(&target))
+        } else {
+            None
+        };
+        let size = if entry_type.is_file() {
+            entry.size()
+        } else {
+            0
+        };
+
+        let uid = entry.header().uid().unwrap_or(0);
+        let gid = entry.header().gid().unwrap_or(0);
+        let attributes = read_entry_attributes(&mut entry)
+            .map_err(|e| format!("Failed to read PAX header: {}", e))?;
+        // Owners resolve against the whole image, not just the kept entries
+        capture_user_database(&mut users, &components.join("/"), &mut entry)?;
+        if !kept {
+            continue;
+        }
+
+        root.insert(
+            &components,
+            TreeBuilder {
+                node_type,
+                size,
+                link_target: link,
+                uid,
+                gid,
+                attributes,
+                children: BTreeMap::new(),
+            },
+        );
+    }
+
+    Ok((root, users))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_layer_files(
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+    path: Option<String>,
+    depth: Option<usize>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<FileTreeNode, LayersError> {
+    info!(
+        "Getting file tree for layer: '{}' (path: {:?}, depth: {:?})",
+        layer_id, path, depth
+    );
+
+    let layer_dir = session
+        .get(session_id.as_deref())?
+        .dir()
+        .join("current_layer");
+    let tar_path = layer_dir.join("fs.tar");
+    let extract_dir = layer_dir.join("fs");
+
+    if !tar_path.exists() {
+        return Err(format!("Tar file does not exist: {:?}", tar_path).into());
+    }
+
+    let (root, users) = build_tree(&tar_path, |_| true)?;
+
+    // Accept both absolute extract paths (FileItem.path) and container paths
+    let relative = path
+        .as_deref()
+        .map(|p| {
+            let p = path_from_escaped(p);
+            p.strip_prefix(&extract_dir).unwrap_or(&p).to_path_buf()
+        })
+        .unwrap_or_default();
+    let components = path_components(&relative);
+
+    let subtree = root
+        .find(&components)
+        .ok_or_else(|| format!("Path not found in layer: {}", display_path(&relative)))?;
+    let name = components
+        .last()
+        .cloned()
+        .unwrap_or_else(|| "/".to_string());
+
+    let node = subtree.to_node(
+        &name,
+        &components
+            .iter()
+            .map(|name| path_from_escaped(name))
+            .collect::<std::path::PathBuf>(),
+        &NodeContext {
+            root: &root,
+            extract_dir: &extract_dir,
+            users: &users,
+        },
+        depth.unwrap_or(1),
+        offset.unwrap_or(0),
+        limit,
+    );
+
+    debug!(
+        "Returning {} of {} children ({} files, {} bytes)",
+        node.children.len(),
+        node.child_count,
+        node.file_count,
+        node.size_bytes
+    );
+    Ok(node)
+}
+
+// The whole tree of the entries `keep` accepts, for listings that only cover
+// part of a layer
+pub(crate) fn filtered_tree(
+    tar_path: &Path,
+    extract_dir: &Path,
+    keep: impl Fn(&Path) -> bool,
+) -> Result<FileTreeNode, String> {
+    let (root, users) = build_tree(tar_path, keep)?;
+    Ok(root.to_node(
+        "/",
+        Path::new(""),
+        &NodeContext {
+            root: &root,
+            extract_dir,
+            users: &users,
+        },
+        usize::MAX,
+        0,
+        None,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{exotic_names, scratch_dir, write_exotic_tar};
+    use layers_core::layer_tar::{display_bytes, path_from_bytes};
+
+    #[test]
+    fn exotic_names_can_be_browsed() {
+        let dir = scratch_dir("browse");
+        let tar_path = dir.join("fs.tar");
+        write_exotic_tar(&tar_path);
+        let extract_dir = dir.join("fs");
+
+        let (root, users) = build_tree(&tar_path, |_| true).unwrap();
+        let data = root.find(&["data".to_string()]).unwrap();
+        let context = NodeContext {
+            root: &root,
+            extract_dir: &extract_dir,
+            users: &users,
+        };
+        let node = data.to_node("data", Path::new("data"), &context, 1, 0, None);
+        assert_eq!(node.children.len(), exotic_names().len());
+
+        for name in exotic_names() {
+            // The UI sends the exact form back when there is one
+            let expected = extract_dir.join(path_from_bytes(&name));
+            let child = node
+                .children
+                .iter()
+                .find(|child| {
+                    path_from_escaped(child.exact_path.as_deref().unwrap_or(&child.path))
+                        == expected
+                })
+                .unwrap_or_else(|| panic!("{} is missing", display_bytes(&name)));
+            assert!(!child.name.contains(['\n', '\r', '\t']));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+// A single security issue found while inspecting an image, shared by all the
+// scanners so the frontend can show them in one list
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityFinding {
+    // Stable identifier of the check that produced the finding, e.g. "unexpected-capability"
+    pub rule_id: String,
+    pub severity: Severity,
+    pub title: String,
+    pub description: String,
+    // Container path the finding refers to, if any
+    pub path: Option<String>,
+    pub layer_id: Option<String>,
+}
+
+// Stable anchor of a finding in exported reports and deep links. It only
+// depends on what the finding is about, so it's the same on every scan.
+pub(crate) fn finding_anchor(finding: &SecurityFinding) -> String {
+    let key = format!(
+        "{}\n{}\n{}\n{}",
+        finding.rule_id,
+        finding.path.as_deref().unwrap_or(""),
+        finding.layer_id.as_deref().unwrap_or(""),
+        finding.title
+    );
+    format!("finding-{}", short_hash(&key))
+}
+
+// Stable anchor of a container path in exported reports
+pub(crate) fn path_anchor(path: &str) -> String {
+    format!("path-{}", short_hash(path))
+}
+
+fn short_hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))[..12].to_string()
+}
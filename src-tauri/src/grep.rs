@@ -0,0 +1,412 @@
+use globset::{Glob, GlobMatcher};
+use layers_core::layer_tar::entry_relative_path;
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+use tauri::Emitter;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::resources;
+use crate::session::{ImageSession, SessionState};
+use crate::tasks::{ProgressReader, Task, TaskRegistry};
+use crate::{finish_task, is_binary_content, layer_tar_path, TaskStatus};
+
+const DEFAULT_CONTEXT_LINES: usize = 2;
+const DEFAULT_MAX_MATCHES: usize = 1000;
+// Bigger files are usually data or logs, not worth grepping by default
+const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+// Minified files can have megabyte long lines, keep snippets readable
+const MAX_LINE_LENGTH: usize = 500;
+// Files read ahead of the workers
+const QUEUE_LENGTH: usize = 64;
+
+// Searches asked to stop early and return what they found, by task ID
+static STOP_REQUESTS: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct GrepOptions {
+    // Treat the pattern as a regular expression instead of a literal string
+    #[serde(default)]
+    regex: bool,
+    #[serde(default)]
+    case_sensitive: bool,
+    context_lines: Option<usize>,
+    max_matches: Option<usize>,
+    max_file_size: Option<u64>,
+    // Only search files whose container path matches this glob, e.g. "/etc/**"
+    path_glob: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GrepMatch {
+    path: String,
+    line_number: usize,
+    line: String,
+    context_before: Vec<String>,
+    context_after: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GrepResult {
+    layer_id: String,
+    pattern: String,
+    matches: Vec<GrepMatch>,
+    files_searched: usize,
+    binary_files_skipped: usize,
+    large_files_skipped: usize,
+    // Stopped early because max_matches was reached
+    truncated: bool,
+    // Stopped early with stop_grep
+    stopped: bool,
+}
+
+// Emitted as "grep_matches" while the search runs
+#[derive(Debug, Serialize, Clone)]
+struct GrepMatchBatch {
+    task_id: u64,
+    matches: Vec<GrepMatch>,
+}
+
+fn stop_requested(task_id: u64) -> bool {
+    STOP_REQUESTS.lock().unwrap().contains(&task_id)
+}
+
+// Returns whether a stop was requested
+fn clear_stop_request(task_id: u64) -> bool {
+    let mut requests = STOP_REQUESTS.lock().unwrap();
+    let requested = requests.contains(&task_id);
+    requests.retain(|id| *id != task_id);
+    requested
+}
+
+fn truncate_line(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_LENGTH) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+fn build_pattern(pattern: &str, options: &GrepOptions) -> Result<Regex, String> {
+    let pattern = if options.regex {
+        pattern.to_string()
+    } else {
+        regex::escape(pattern)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+fn grep_content(
+    path: &str,
+    content: &str,
+    pattern: &Regex,
+    context_lines: usize,
+    remaining: usize,
+) -> Vec<GrepMatch> {
+    let lines: Vec<&str> = content.lines().collect();
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| pattern.is_match(line))
+        .take(remaining)
+        .map(|(i, line)| GrepMatch {
+            path: path.to_string(),
+            line_number: i + 1,
+            line: truncate_line(line),
+            context_before: lines[i.saturating_sub(context_lines)..i]
+                .iter()
+                .map(|l| truncate_line(l))
+                .collect(),
+            context_after: lines[i + 1..(i + 1 + context_lines).min(lines.len())]
+                .iter()
+                .map(|l| truncate_line(l))
+                .collect(),
+        })
+        .collect()
+}
+
+// Holds the options of one search and what the workers found so far
+struct Searcher {
+    regex: Regex,
+    path_filter: Option<GlobMatcher>,
+    context_lines: usize,
+    max_matches: usize,
+    max_file_size: u64,
+    matches: Mutex<Vec<GrepMatch>>,
+    files_searched: AtomicUsize,
+    binary_files_skipped: AtomicUsize,
+    large_files_skipped: AtomicUsize,
+    truncated: AtomicBool,
+}
+
+impl Searcher {
+    // Nothing more is read or searched once this is true
+    fn is_done(&self, task: &Task) -> bool {
+        self.truncated.load(Ordering::Relaxed) || task.is_cancelled() || stop_requested(task.id)
+    }
+
+    // Stream the tar and hand every candidate file to the workers, nothing
+    // is extracted to disk
+    fn queue_files(
+        &self,
+        task: &Task,
+        tar_path: &Path,
+        sender: SyncSender<(String, Vec<u8>)>,
+        on_progress: &(dyn Fn(f32) + Sync),
+    ) -> Result<(), String> {
+        let tar_size = std::fs::metadata(tar_path)
+            .map_err(|e| format!("Failed to read metadata for {:?}: {}", tar_path, e))?
+            .len()
+            .max(1);
+        let bytes_read = AtomicU64::new(0);
+        let file = File::open(tar_path)
+            .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+        let mut archive = tar::Archive::new(ProgressReader::new(file, |bytes| {
+            bytes_read.fetch_add(bytes, Ordering::Relaxed);
+        }));
+
+        let entries = archive
+            .entries()
+            .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+        for (index, entry) in entries.enumerate() {
+            if self.is_done(task) {
+                break;
+            }
+            let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let path = format!(
+                "/{}",
+                entry_relative_path(
+                    &entry
+                        .path()
+                        .map_err(|e| format!("Failed to read tar entry path: {}", e))?
+                )
+            );
+            if let Some(filter) = &self.path_filter {
+                if !filter.is_match(&path) {
+                    continue;
+                }
+            }
+            if entry.size() > self.max_file_size {
+                self.large_files_skipped.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            let mut bytes = Vec::with_capacity(entry.size() as usize);
+            entry
+                .read_to_end(&mut bytes)
+                .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            if index.is_multiple_of(200) {
+                on_progress(bytes_read.load(Ordering::Relaxed) as f32 / tar_size as f32);
+            }
+            // The workers only hang up once the search is done
+            if sender.send((path, bytes)).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Runs on the rayon pool, matches are streamed to the frontend per file
+    fn search_file(&self, window: &tauri::Window, task: &Task, path: &str, bytes: &[u8]) {
+        if self.is_done(task) {
+            return;
+        }
+        if is_binary_content(bytes) {
+            self.binary_files_skipped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.files_searched.fetch_add(1, Ordering::Relaxed);
+
+        let content = String::from_utf8_lossy(bytes);
+        let found = grep_content(
+            path,
+            &content,
+            &self.regex,
+            self.context_lines,
+            self.max_matches,
+        );
+        if found.is_empty() {
+            return;
+        }
+
+        let mut matches = self.matches.lock().unwrap();
+        let remaining = self.max_matches.saturating_sub(matches.len());
+        let found: Vec<GrepMatch> = found.into_iter().take(remaining).collect();
+        if found.is_empty() {
+            return;
+        }
+        if matches.len() + found.len() >= self.max_matches {
+            self.truncated.store(true, Ordering::Relaxed);
+        }
+        let _ = window.emit(
+            "grep_matches",
+            GrepMatchBatch {
+                task_id: task.id,
+                matches: found.clone(),
+            },
+        );
+        matches.extend(found);
+    }
+}
+
+async fn grep_layer_task(
+    window: &tauri::Window,
+    task: &Task,
+    session: &ImageSession,
+    layer_id: String,
+    pattern: String,
+    options: GrepOptions,
+) -> Result<GrepResult, String> {
+    let update_status = |message: &str, progress: f32, is_complete: bool, error: Option<String>| {
+        let _ = window.emit(
+            "task_status",
+            TaskStatus {
+                task_id: task.id,
+                message: message.to_string(),
+                progress,
+                is_complete,
+                error,
+                phase: None,
+                transfer: None,
+            },
+        );
+    };
+
+    let path_filter: Option<GlobMatcher> = options
+        .path_glob
+        .as_deref()
+        .map(|glob| {
+            Glob::new(glob)
+                .map(|g| g.compile_matcher())
+                .map_err(|e| format!("Invalid path glob {}: {}", glob, e))
+        })
+        .transpose()?;
+    let searcher = Searcher {
+        regex: build_pattern(&pattern, &options)?,
+        path_filter,
+        context_lines: options.context_lines.unwrap_or(DEFAULT_CONTEXT_LINES),
+        max_matches: options.max_matches.unwrap_or(DEFAULT_MAX_MATCHES),
+        max_file_size: options.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE),
+        matches: Mutex::new(Vec::new()),
+        files_searched: AtomicUsize::new(0),
+        binary_files_skipped: AtomicUsize::new(0),
+        large_files_skipped: AtomicUsize::new(0),
+        truncated: AtomicBool::new(false),
+    };
+
+    update_status(
+        &format!("Searching layer {}...", layer_id),
+        0.0,
+        false,
+        None,
+    );
+
+    let tar_path = layer_tar_path(task, session, &layer_id)?;
+    let on_progress = |progress: f32| {
+        update_status(
+            &format!(
+                "Searched {} files...",
+                searcher.files_searched.load(Ordering::Relaxed)
+            ),
+            progress,
+            false,
+            None,
+        );
+    };
+
+    // Reading the tar is sequential, one thread reads while the scan pool
+    // searches. The bounded queue keeps memory flat when the workers lag.
+    let (sender, receiver) = sync_channel::<(String, Vec<u8>)>(QUEUE_LENGTH);
+    let queued = thread::scope(|scope| {
+        let reader = scope.spawn(|| searcher.queue_files(task, &tar_path, sender, &on_progress));
+        resources::install(|| {
+            receiver
+                .into_iter()
+                .par_bridge()
+                .for_each(|(path, bytes)| searcher.search_file(window, task, &path, &bytes))
+        });
+        reader
+            .join()
+            .unwrap_or_else(|_| Err("Failed to read tar file: reader panicked".to_string()))
+    });
+    let stopped = clear_stop_request(task.id);
+    queued?;
+    task.check_cancelled()?;
+
+    // Workers finish in any order, list matches by file
+    let mut matches = searcher.matches.into_inner().unwrap();
+    matches.sort_by(|a, b| a.path.cmp(&b.path).then(a.line_number.cmp(&b.line_number)));
+    let result = GrepResult {
+        layer_id,
+        pattern,
+        matches,
+        files_searched: searcher.files_searched.into_inner(),
+        binary_files_skipped: searcher.binary_files_skipped.into_inner(),
+        large_files_skipped: searcher.large_files_skipped.into_inner(),
+        truncated: searcher.truncated.into_inner(),
+        stopped,
+    };
+
+    update_status(
+        &format!(
+            "Found {} matches in {} files",
+            result.matches.len(),
+            result.files_searched
+        ),
+        1.0,
+        true,
+        None,
+    );
+    Ok(result)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn grep_layer(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+    pattern: String,
+    options: Option<GrepOptions>,
+) -> Result<GrepResult, LayersError> {
+    info!("Searching layer {} for '{}'", layer_id, pattern);
+    let session = session.get(session_id.as_deref())?;
+
+    let task = tasks.start();
+    let result = grep_layer_task(
+        &window,
+        &task,
+        &session,
+        layer_id,
+        pattern,
+        options.unwrap_or_default(),
+    )
+    .await;
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
+
+// Unlike cancel_task the search returns the matches found so far
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn stop_grep(task_id: u64) -> Result<(), LayersError> {
+    info!("Stopping search {}", task_id);
+    STOP_REQUESTS.lock().unwrap().push(task_id);
+    Ok(())
+}
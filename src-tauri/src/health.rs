@@ -0,0 +1,477 @@
+use layers_core::docker::{get_image_history, inspect_image, HistoryEntry};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::findings::Severity;
+use crate::reports::build_report;
+use crate::ruleset_sync;
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+use crate::trends::TrendStore;
+
+// Base images older than this miss months of security updates
+const STALE_BASE_DAYS: u64 = 180;
+const OUTDATED_BASE_DAYS: u64 = 365;
+
+// Points each problem takes off the score of 100, kept in the settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ScoringWeights {
+    // Per percent of shipped bytes hidden by later layers
+    wasted_percent: f64,
+    // Per GB of image size
+    size_gb: f64,
+    // Per finding of each severity
+    critical: f64,
+    high: f64,
+    medium: f64,
+    low: f64,
+    info: f64,
+    root_user: f64,
+    // Base older than STALE_BASE_DAYS, or OUTDATED_BASE_DAYS
+    stale_base: f64,
+    outdated_base: f64,
+}
+
+// What one factor took off the score, so the grade can be explained
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScoreFactor {
+    factor: String,
+    detail: String,
+    points: f64,
+}
+
+// Scoring weights in use, managed as Tauri state
+pub struct HealthScoring {
+    path: PathBuf,
+    weights: Mutex<ScoringWeights>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct SeverityCounts {
+    critical: usize,
+    high: usize,
+    medium: usize,
+    low: usize,
+    info: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BaseFreshness {
+    // Date of the newest layer built before the image's own build
+    created: String,
+    age_days: u64,
+    stale: bool,
+}
+
+// Scored overview of the open image for the summary card
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageHealth {
+    // 0 to 100, see health_score
+    score: u32,
+    grade: char,
+    // Factors that took points off, largest first
+    breakdown: Vec<ScoreFactor>,
+    total_bytes: u64,
+    wasted_bytes: u64,
+    efficiency_score: f64,
+    layer_count: usize,
+    findings: SeverityCounts,
+    // None when the history doesn't tell the base apart from the image
+    base: Option<BaseFreshness>,
+    // Empty when the image runs as root by default
+    user: String,
+    runs_as_root: bool,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        ScoringWeights {
+            wasted_percent: 0.2,
+            size_gb: 5.0,
+            critical: 25.0,
+            high: 15.0,
+            medium: 5.0,
+            low: 2.0,
+            info: 0.0,
+            root_user: 10.0,
+            stale_base: 10.0,
+            outdated_base: 20.0,
+        }
+    }
+}
+
+impl HealthScoring {
+    pub fn new(path: PathBuf) -> Self {
+        let weights = fs::read(&path)
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default();
+        HealthScoring {
+            path,
+            weights: Mutex::new(weights),
+        }
+    }
+}
+
+impl SeverityCounts {
+    fn add(&mut self, severity: Severity) {
+        match severity {
+            Severity::Critical => self.critical += 1,
+            Severity::High => self.high += 1,
+            Severity::Medium => self.medium += 1,
+            Severity::Low => self.low += 1,
+            Severity::Info => self.info += 1,
+        }
+    }
+}
+
+// Days since the epoch of a "YYYY-MM-DD..." timestamp, as docker prints them.
+// See http://howardhinnant.github.io/date_algorithms.html
+pub(crate) fn days_since_epoch(timestamp: &str) -> Option<u64> {
+    let date = timestamp.get(..10)?;
+    let mut parts = date.split('-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    u64::try_from(era * 146097 + day_of_era - 719468).ok()
+}
+
+// History is newest first. The image's own build shares the newest entry's
+// date, the first entry built on an earlier day belongs to the base.
+fn base_freshness(history: &[HistoryEntry], today: u64) -> Option<BaseFreshness> {
+    let built = days_since_epoch(&history.first()?.created)?;
+    let (entry, created) = history.iter().find_map(|entry| {
+        days_since_epoch(&entry.created)
+            .filter(|created| *created < built)
+            .map(|created| (entry, created))
+    })?;
+    let age_days = today.saturating_sub(created);
+    Some(BaseFreshness {
+        created: entry.created.clone(),
+        age_days,
+        stale: age_days > STALE_BASE_DAYS,
+    })
+}
+
+// No USER, or one that resolves to uid 0
+fn is_root_user(user: &str) -> bool {
+    let name = user.split(':').next().unwrap_or_default();
+    name.is_empty() || name == "root" || name == "0"
+}
+
+// Everything that takes points off, with zero-point factors left out
+fn score_factors(
+    weights: &ScoringWeights,
+    efficiency_score: f64,
+    total_bytes: u64,
+    findings: &SeverityCounts,
+    base: Option<&BaseFreshness>,
+    runs_as_root: bool,
+) -> Vec<ScoreFactor> {
+    let wasted_percent = 100.0 - efficiency_score;
+    let size_gb = total_bytes as f64 / 1_000_000_000.0;
+    let mut factors = vec![
+        ScoreFactor {
+            factor: "wasted_bytes".to_string(),
+            detail: format!("{:.1}% of shipped bytes are hidden", wasted_percent),
+            points: wasted_percent * weights.wasted_percent,
+        },
+        ScoreFactor {
+            factor: "size".to_string(),
+            detail: format!("{:.2} GB image", size_gb),
+            points: size_gb * weights.size_gb,
+        },
+    ];
+    for (severity, count, weight) in [
+        ("critical", findings.critical, weights.critical),
+        ("high", findings.high, weights.high),
+        ("medium", findings.medium, weights.medium),
+        ("low", findings.low, weights.low),
+        ("info", findings.info, weights.info),
+    ] {
+        factors.push(ScoreFactor {
+            factor: format!("{}_findings", severity),
+            detail: format!("{} {} severity findings", count, severity),
+            points: count as f64 * weight,
+        });
+    }
+    if runs_as_root {
+        factors.push(ScoreFactor {
+            factor: "root_user".to_string(),
+            detail: "Runs as root by default".to_string(),
+            points: weights.root_user,
+        });
+    }
+    if let Some(base) = base {
+        let weight = match base.age_days {
+            age if age > OUTDATED_BASE_DAYS => weights.outdated_base,
+            age if age > STALE_BASE_DAYS => weights.stale_base,
+            _ => 0.0,
+        };
+        factors.push(ScoreFactor {
+            factor: "base_age".to_string(),
+            detail: format!("Base layers are {} days old", base.age_days),
+            points: weight,
+        });
+    }
+
+    factors.retain(|factor| factor.points > 0.0);
+    for factor in &mut factors {
+        factor.points = (factor.points * 10.0).round() / 10.0;
+    }
+    factors.sort_by(|a, b| b.points.total_cmp(&a.points));
+    factors
+}
+
+// Starts at 100 and loses the points of every factor
+fn health_score(factors: &[ScoreFactor]) -> u32 {
+    let penalty: f64 = factors.iter().map(|factor| factor.points).sum();
+    (100.0 - penalty).clamp(0.0, 100.0).round() as u32
+}
+
+fn grade(score: u32) -> char {
+    match score {
+        90.. => 'A',
+        75..=89 => 'B',
+        60..=74 => 'C',
+        40..=59 => 'D',
+        _ => 'F',
+    }
+}
+
+// Summary card of the selected image. Reuses the report model, so the file
+// index is built now if the image hasn't been indexed yet.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_image_health(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    scoring: tauri::State<'_, HealthScoring>,
+    trends: tauri::State<'_, TrendStore>,
+    session_id: Option<String>,
+) -> Result<ImageHealth, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let image_id = session.image_id().to_string();
+    info!("Computing health summary of {}", image_id);
+    let report = build_report(&tasks, &session, None)?;
+    let history = get_image_history(&image_id)?;
+    let user = inspect_image(&image_id)?["Config"]["User"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let runs_as_root = is_root_user(&user);
+
+    let today = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or(0);
+    let base = base_freshness(&history, today);
+
+    let mut findings = SeverityCounts::default();
+    for finding in &report.findings {
+        findings.add(finding.finding.severity);
+    }
+
+    // The team's weights, when synced, so everyone gets the same grade
+    let weights =
+        ruleset_sync::scoring_weights().unwrap_or_else(|| scoring.weights.lock().unwrap().clone());
+    let breakdown = score_factors(
+        &weights,
+        report.efficiency.score,
+        report.total_bytes,
+        &findings,
+        base.as_ref(),
+        runs_as_root,
+    );
+    let score = health_score(&breakdown);
+    // The card still shows when the history can't be kept
+    if let Err(e) = trends.record(&report, Some(score)) {
+        warn!("{}", e);
+    }
+    Ok(ImageHealth {
+        score,
+        grade: grade(score),
+        breakdown,
+        total_bytes: report.total_bytes,
+        wasted_bytes: report.efficiency.wasted_bytes,
+        efficiency_score: report.efficiency.score,
+        layer_count: report.layers.iter().filter(|layer| !layer.empty).count(),
+        findings,
+        base,
+        user,
+        runs_as_root,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_scoring_weights(
+    scoring: tauri::State<'_, HealthScoring>,
+) -> Result<ScoringWeights, LayersError> {
+    Ok(scoring.weights.lock().unwrap().clone())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_scoring_weights(
+    scoring: tauri::State<'_, HealthScoring>,
+    weights: ScoringWeights,
+) -> Result<ScoringWeights, LayersError> {
+    info!("Setting scoring weights: {:?}", weights);
+
+    if let Some(parent) = scoring.path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+    }
+    let content = serde_json::to_vec_pretty(&weights)
+        .map_err(|e| format!("Failed to serialize scoring weights: {}", e))?;
+    audit::write_atomically(&scoring.path, content)
+        .map_err(|e| format!("Failed to save scoring weights: {}", e))?;
+
+    *scoring.weights.lock().unwrap() = weights.clone();
+    Ok(weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(created: &str) -> HistoryEntry {
+        HistoryEntry {
+            id: "<missing>".to_string(),
+            created: created.to_string(),
+            size: "0B".to_string(),
+            size_bytes: 0,
+            created_by: "RUN true".to_string(),
+        }
+    }
+
+    fn factor_points(factors: &[ScoreFactor]) -> Vec<(&str, f64)> {
+        factors
+            .iter()
+            .map(|factor| (factor.factor.as_str(), factor.points))
+            .collect()
+    }
+
+    #[test]
+    fn days_since_epoch_reads_docker_timestamps() {
+        assert_eq!(days_since_epoch("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(days_since_epoch("2000-03-01"), Some(11_017));
+        assert_eq!(days_since_epoch("2024-02-29T12:00:00+01:00"), Some(19_782));
+        assert_eq!(days_since_epoch("1969-12-31"), None);
+        assert_eq!(days_since_epoch("yesterday"), None);
+    }
+
+    #[test]
+    fn base_is_the_first_entry_from_an_earlier_day() {
+        let history = [
+            entry("2024-06-10T08:00:00Z"),
+            entry("2024-06-10T07:59:00Z"),
+            entry("2024-01-02T00:00:00Z"),
+            entry("2023-12-01T00:00:00Z"),
+        ];
+        let today = days_since_epoch("2024-07-31").unwrap();
+        let base = base_freshness(&history, today).unwrap();
+        assert_eq!(base.created, "2024-01-02T00:00:00Z");
+        assert_eq!(base.age_days, 211);
+        assert!(base.stale);
+
+        // Built in one day, nothing tells the base apart
+        assert!(base_freshness(&history[..2], today).is_none());
+    }
+
+    #[test]
+    fn root_user_includes_no_user_and_uid_zero() {
+        assert!(is_root_user(""));
+        assert!(is_root_user("root"));
+        assert!(is_root_user("0:0"));
+        assert!(!is_root_user("nobody"));
+        assert!(!is_root_user("1000:0"));
+    }
+
+    #[test]
+    fn factors_are_weighed_and_sorted() {
+        let findings = SeverityCounts {
+            critical: 1,
+            medium: 2,
+            info: 4,
+            ..Default::default()
+        };
+        let base = BaseFreshness {
+            created: "2023-01-01".to_string(),
+            age_days: 400,
+            stale: true,
+        };
+        let factors = score_factors(
+            &ScoringWeights::default(),
+            90.0,
+            500_000_000,
+            &findings,
+            Some(&base),
+            true,
+        );
+
+        // Info findings weigh nothing by default and are left out
+        assert_eq!(
+            factor_points(&factors),
+            [
+                ("critical_findings", 25.0),
+                ("base_age", 20.0),
+                ("medium_findings", 10.0),
+                ("root_user", 10.0),
+                ("size", 2.5),
+                ("wasted_bytes", 2.0),
+            ]
+        );
+        assert_eq!(health_score(&factors), 31);
+        assert_eq!(grade(health_score(&factors)), 'F');
+    }
+
+    #[test]
+    fn clean_image_scores_full_marks() {
+        let factors = score_factors(
+            &ScoringWeights::default(),
+            100.0,
+            0,
+            &SeverityCounts::default(),
+            None,
+            false,
+        );
+        assert!(factors.is_empty());
+        assert_eq!(health_score(&factors), 100);
+    }
+
+    #[test]
+    fn score_never_drops_below_zero() {
+        let findings = SeverityCounts {
+            critical: 10,
+            ..Default::default()
+        };
+        let factors = score_factors(&ScoringWeights::default(), 100.0, 0, &findings, None, false);
+        assert_eq!(health_score(&factors), 0);
+    }
+
+    #[test]
+    fn grades_follow_the_score_bands() {
+        assert_eq!(grade(100), 'A');
+        assert_eq!(grade(90), 'A');
+        assert_eq!(grade(89), 'B');
+        assert_eq!(grade(75), 'B');
+        assert_eq!(grade(74), 'C');
+        assert_eq!(grade(60), 'C');
+        assert_eq!(grade(59), 'D');
+        assert_eq!(grade(40), 'D');
+        assert_eq!(grade(39), 'F');
+    }
+}
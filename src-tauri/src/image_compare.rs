@@ -0,0 +1,494 @@
+use layers_core::docker::{docker_command, image_diff_ids, inspect_image};
+use layers_core::layer_tar::entry_relative_path;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::process::Stdio;
+use tracing::info;
+
+use crate::audit;
+use crate::digest_verify::sha256_reader;
+use crate::error::LayersError;
+use crate::finish_task;
+use crate::phases::{phase, Phase, PhaseProgress};
+use crate::resources;
+use crate::search_index::filesystem_layers;
+use crate::tasks::{Task, TaskRegistry};
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ImageCompareOptions {
+    // Also pair up the layers of both images, e.g. to see how much of the
+    // base is shared
+    #[serde(default)]
+    include_layers: bool,
+    // Report unchanged paths too, they are only counted otherwise
+    #[serde(default)]
+    include_unchanged: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileChange {
+    path: String,
+    size_a: u64,
+    size_b: u64,
+    // What differs, any of "content", "mode", "owner", "link_target", "type"
+    changes: Vec<String>,
+}
+
+// A config value that differs, keyed values like env and labels get one
+// entry per key
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataChange {
+    field: String,
+    key: Option<String>,
+    value_a: Option<String>,
+    value_b: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ComparedLayer {
+    diff_id: String,
+    created_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerPair {
+    index: usize,
+    layer_a: Option<ComparedLayer>,
+    layer_b: Option<ComparedLayer>,
+    identical: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageComparison {
+    image_a: String,
+    image_b: String,
+    added: Vec<String>,
+    removed: Vec<String>,
+    modified: Vec<FileChange>,
+    unchanged: Vec<String>,
+    unchanged_count: usize,
+    // Total size of the regular files in each final filesystem
+    size_a: u64,
+    size_b: u64,
+    metadata: Vec<MetadataChange>,
+    // Layers both images start with, usually the shared base image
+    shared_base_layers: Option<usize>,
+    layers: Option<Vec<LayerPair>>,
+}
+
+// What is compared of each path of the final filesystem
+#[derive(Debug, PartialEq)]
+struct FileState {
+    kind: char,
+    size: u64,
+    mode: u32,
+    owner: (u64, u64),
+    hash: Option<String>,
+    link_target: Option<String>,
+}
+
+// Stream `docker export` of a throwaway container and record every path
+fn read_final_filesystem(task: &Task, image: &str) -> Result<HashMap<String, FileState>, String> {
+    let container_name = format!("layers_compare_{}_{}", task.id, short_id(image));
+    let _ = audit::docker(&["rm", "-f", &container_name]);
+
+    let create_output = task
+        .run(docker_command().args(["create", "--name", &container_name, image, "true"]))
+        .map_err(|e| format!("Failed to create container for {}: {}", image, e))?;
+    task.track_container(&container_name);
+    if !create_output.status.success() {
+        return Err(format!(
+            "Failed to create container for {}: {}",
+            image,
+            String::from_utf8_lossy(&create_output.stderr)
+        ));
+    }
+
+    let result = read_container_export(task, &container_name);
+
+    let _ = audit::docker(&["rm", "-f", &container_name]);
+    result
+}
+
+fn read_container_export(
+    task: &Task,
+    container_name: &str,
+) -> Result<HashMap<String, FileState>, String> {
+    let mut child = docker_command()
+        .args(["export", container_name])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run docker export: {}", e))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "Failed to capture docker export output".to_string())?;
+
+    let mut archive = tar::Archive::new(stdout);
+    let mut files = HashMap::new();
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read container export: {}", e))?;
+    for entry in entries {
+        if task.is_cancelled() {
+            let _ = child.kill();
+            task.check_cancelled()?;
+        }
+
+        let mut entry = entry.map_err(|e| format!("Failed to read export entry: {}", e))?;
+        let path = entry_relative_path(
+            &entry
+                .path()
+                .map_err(|e| format!("Invalid export entry path: {}", e))?,
+        );
+        if path.is_empty() {
+            continue;
+        }
+
+        let header = entry.header();
+        let entry_type = header.entry_type();
+        let kind = if entry_type.is_dir() {
+            'd'
+        } else if entry_type.is_symlink() {
+            'l'
+        } else if entry_type.is_hard_link() {
+            'h'
+        } else if entry_type.is_file() {
+            'f'
+        } else {
+            'o'
+        };
+        let mode = header.mode().unwrap_or(0);
+        let owner = (header.uid().unwrap_or(0), header.gid().unwrap_or(0));
+        let link_target = entry
+            .link_name()
+            .ok()
+            .flatten()
+            .map(|target| target.to_string_lossy().to_string());
+        let size = entry.size();
+        let hash = if kind == 'f' {
+            Some(sha256_reader(&mut entry).map_err(|e| format!("Failed to hash {}: {}", path, e))?)
+        } else {
+            None
+        };
+
+        files.insert(
+            path,
+            FileState {
+                kind,
+                size,
+                mode,
+                owner,
+                hash,
+                link_target,
+            },
+        );
+    }
+
+    let _ = child.wait();
+    Ok(files)
+}
+
+// Enough of the image reference to tell the two containers apart
+fn short_id(image: &str) -> String {
+    image
+        .rsplit(':')
+        .next()
+        .unwrap_or(image)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(12)
+        .collect()
+}
+
+fn file_changes(a: &FileState, b: &FileState) -> Vec<String> {
+    if a.kind != b.kind {
+        return vec!["type".to_string()];
+    }
+    let mut changes = Vec::new();
+    if a.hash != b.hash || a.size != b.size {
+        changes.push("content".to_string());
+    }
+    if a.mode != b.mode {
+        changes.push("mode".to_string());
+    }
+    if a.owner != b.owner {
+        changes.push("owner".to_string());
+    }
+    if a.link_target != b.link_target {
+        changes.push("link_target".to_string());
+    }
+    changes
+}
+
+fn config_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(s) if s.is_empty() => None,
+        serde_json::Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+// "KEY=value" pairs of Env, or the entries of an object like Labels
+fn config_map(value: &serde_json::Value) -> BTreeMap<String, String> {
+    match value {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str())
+            .map(|item| {
+                let (key, value) = item.split_once('=').unwrap_or((item, ""));
+                (key.to_string(), value.to_string())
+            })
+            .collect(),
+        serde_json::Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| {
+                let value = value
+                    .as_str()
+                    .map(String::from)
+                    .unwrap_or_else(|| value.to_string());
+                (key.clone(), value)
+            })
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+fn compare_metadata(a: &serde_json::Value, b: &serde_json::Value) -> Vec<MetadataChange> {
+    let mut changes = Vec::new();
+
+    // Whole values, e.g. the entrypoint array is compared as a unit
+    for (field, value_a, value_b) in [
+        (
+            "Entrypoint",
+            &a["Config"]["Entrypoint"],
+            &b["Config"]["Entrypoint"],
+        ),
+        ("Cmd", &a["Config"]["Cmd"], &b["Config"]["Cmd"]),
+        ("User", &a["Config"]["User"], &b["Config"]["User"]),
+        (
+            "WorkingDir",
+            &a["Config"]["WorkingDir"],
+            &b["Config"]["WorkingDir"],
+        ),
+        (
+            "StopSignal",
+            &a["Config"]["StopSignal"],
+            &b["Config"]["StopSignal"],
+        ),
+        (
+            "Healthcheck",
+            &a["Config"]["Healthcheck"],
+            &b["Config"]["Healthcheck"],
+        ),
+        ("Os", &a["Os"], &b["Os"]),
+        ("Architecture", &a["Architecture"], &b["Architecture"]),
+    ] {
+        let (value_a, value_b) = (config_string(value_a), config_string(value_b));
+        if value_a != value_b {
+            changes.push(MetadataChange {
+                field: field.to_string(),
+                key: None,
+                value_a,
+                value_b,
+            });
+        }
+    }
+
+    for field in ["Env", "Labels", "ExposedPorts", "Volumes"] {
+        let map_a = config_map(&a["Config"][field]);
+        let map_b = config_map(&b["Config"][field]);
+        let mut keys: Vec<&String> = map_a.keys().chain(map_b.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let (value_a, value_b) = (map_a.get(key), map_b.get(key));
+            if value_a != value_b {
+                changes.push(MetadataChange {
+                    field: field.to_string(),
+                    key: Some(key.clone()),
+                    value_a: value_a.cloned(),
+                    value_b: value_b.cloned(),
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+// Diff IDs paired with their history entries, base layer first
+fn image_layers(image: &str) -> Result<Vec<ComparedLayer>, String> {
+    let diff_ids = image_diff_ids(image)?;
+    let history = filesystem_layers(image)?;
+    Ok(diff_ids
+        .into_iter()
+        .enumerate()
+        .map(|(i, diff_id)| ComparedLayer {
+            diff_id,
+            created_by: history
+                .get(i)
+                .map(|(_, created_by)| created_by.clone())
+                .unwrap_or_default(),
+        })
+        .collect())
+}
+
+fn pair_layers(layers_a: Vec<ComparedLayer>, layers_b: Vec<ComparedLayer>) -> Vec<LayerPair> {
+    let count = layers_a.len().max(layers_b.len());
+    (0..count)
+        .map(|index| {
+            let layer_a = layers_a.get(index).cloned();
+            let layer_b = layers_b.get(index).cloned();
+            let identical = match (&layer_a, &layer_b) {
+                (Some(a), Some(b)) => a.diff_id == b.diff_id,
+                _ => false,
+            };
+            LayerPair {
+                index,
+                layer_a,
+                layer_b,
+                identical,
+            }
+        })
+        .collect()
+}
+
+// Diff the final filesystems and configs of two images, e.g. before and
+// after a base image upgrade
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn compare_images(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    image_a: String,
+    image_b: String,
+    options: Option<ImageCompareOptions>,
+) -> Result<ImageComparison, LayersError> {
+    let task = tasks.start();
+    let result = compare_images_task(
+        &window,
+        &task,
+        &image_a,
+        &image_b,
+        options.unwrap_or_default(),
+    );
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
+
+const COMPARE_IMAGES_PHASES: &[Phase] = &[
+    phase("resolve", 0.1),
+    phase("fetch", 0.7),
+    phase("analyze", 0.2),
+];
+
+pub(crate) fn compare_images_task(
+    window: &tauri::Window,
+    task: &Task,
+    image_a: &str,
+    image_b: &str,
+    options: ImageCompareOptions,
+) -> Result<ImageComparison, String> {
+    info!("Comparing images {} and {}", image_a, image_b);
+
+    let progress = PhaseProgress::new(window, task.id, COMPARE_IMAGES_PHASES);
+    progress.begin("resolve", "Inspecting images...");
+    let inspect_a = inspect_image(image_a)?;
+    let inspect_b = inspect_image(image_b)?;
+    let metadata = compare_metadata(&inspect_a, &inspect_b);
+
+    progress.begin(
+        "fetch",
+        &format!("Reading filesystems of {} and {}...", image_a, image_b),
+    );
+    let (files_a, files_b) = resources::install(|| {
+        rayon::join(
+            || read_final_filesystem(task, image_a),
+            || read_final_filesystem(task, image_b),
+        )
+    });
+    let (files_a, files_b) = (files_a?, files_b?);
+
+    task.check_cancelled()?;
+    progress.begin("analyze", "Comparing filesystems...");
+
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut modified = Vec::new();
+    let mut unchanged = Vec::new();
+    let mut unchanged_count = 0;
+    for (path, state_b) in &files_b {
+        match files_a.get(path) {
+            None => added.push(path.clone()),
+            Some(state_a) => {
+                let changes = file_changes(state_a, state_b);
+                if !changes.is_empty() {
+                    modified.push(FileChange {
+                        path: path.clone(),
+                        size_a: state_a.size,
+                        size_b: state_b.size,
+                        changes,
+                    });
+                } else {
+                    unchanged_count += 1;
+                    if options.include_unchanged {
+                        unchanged.push(path.clone());
+                    }
+                }
+            }
+        }
+    }
+    for path in files_a.keys() {
+        if !files_b.contains_key(path) {
+            removed.push(path.clone());
+        }
+    }
+    added.sort();
+    removed.sort();
+    modified.sort_by(|a, b| a.path.cmp(&b.path));
+    unchanged.sort();
+
+    let regular_size = |files: &HashMap<String, FileState>| -> u64 {
+        files
+            .values()
+            .filter(|state| state.kind == 'f')
+            .map(|state| state.size)
+            .sum()
+    };
+
+    let (shared_base_layers, layers) = if options.include_layers {
+        progress.update("Comparing layer histories...", 0.5);
+        let layers = pair_layers(image_layers(image_a)?, image_layers(image_b)?);
+        let shared = layers.iter().take_while(|pair| pair.identical).count();
+        (Some(shared), Some(layers))
+    } else {
+        (None, None)
+    };
+
+    info!(
+        "Images differ in {} added, {} removed, {} modified paths and {} config values",
+        added.len(),
+        removed.len(),
+        modified.len(),
+        metadata.len()
+    );
+    progress.complete("Comparison complete");
+
+    Ok(ImageComparison {
+        image_a: image_a.to_string(),
+        image_b: image_b.to_string(),
+        added,
+        removed,
+        modified,
+        unchanged,
+        unchanged_count,
+        size_a: regular_size(&files_a),
+        size_b: regular_size(&files_b),
+        metadata,
+        shared_base_layers,
+        layers,
+    })
+}
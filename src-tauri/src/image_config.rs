@@ -0,0 +1,212 @@
+use layers_core::docker::{get_image_history, inspect_image};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::layer_mapping::normalize_created_by;
+use crate::session::SessionState;
+use crate::{run_config, ImageRunConfig};
+
+const NANOS_PER_SECOND: f64 = 1_000_000_000.0;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthcheckConfig {
+    // e.g. ["CMD-SHELL", "curl -f http://localhost/"], ["NONE"] disables it
+    test: Vec<String>,
+    interval_seconds: Option<f64>,
+    timeout_seconds: Option<f64>,
+    start_period_seconds: Option<f64>,
+    retries: Option<u64>,
+}
+
+// A config value set by a history entry
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigChange {
+    // Same names as the ImageConfig fields, e.g. "env" or "exposed_ports"
+    field: String,
+    // Variable, label or port for map-like fields
+    key: Option<String>,
+    value: String,
+    // What an earlier entry had set, None when the value is new
+    previous: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryConfigChanges {
+    // Matches the "layer_N" IDs of export_image_layers
+    layer_id: String,
+    created_by: String,
+    instruction: String,
+    changes: Vec<ConfigChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageConfig {
+    image: String,
+    #[serde(flatten)]
+    run: ImageRunConfig,
+    volumes: Vec<String>,
+    stop_signal: String,
+    healthcheck: Option<HealthcheckConfig>,
+    // Oldest first, only entries that changed the config
+    history: Vec<HistoryConfigChanges>,
+}
+
+fn seconds(value: &serde_json::Value) -> Option<f64> {
+    value
+        .as_u64()
+        .filter(|nanos| *nanos > 0)
+        .map(|nanos| nanos as f64 / NANOS_PER_SECOND)
+}
+
+fn healthcheck(config: &serde_json::Value) -> Option<HealthcheckConfig> {
+    let healthcheck = config.get("Healthcheck").filter(|h| !h.is_null())?;
+    Some(HealthcheckConfig {
+        test: healthcheck["Test"]
+            .as_array()
+            .map(|test| {
+                test.iter()
+                    .filter_map(|part| part.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        interval_seconds: seconds(&healthcheck["Interval"]),
+        timeout_seconds: seconds(&healthcheck["Timeout"]),
+        start_period_seconds: seconds(&healthcheck["StartPeriod"]),
+        retries: healthcheck["Retries"]
+            .as_u64()
+            .filter(|retries| *retries > 0),
+    })
+}
+
+// Legacy builder history prints maps the Go way, "map[80/tcp:{}]", BuildKit
+// lists them, "[/data]" or ["/data"]
+fn go_map_keys(args: &str) -> Vec<String> {
+    let args = args
+        .strip_prefix("map[")
+        .and_then(|rest| rest.strip_suffix(']'))
+        .or_else(|| {
+            args.strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+        })
+        .unwrap_or(args);
+    args.split_whitespace()
+        .map(|key| {
+            key.trim_end_matches(":{}")
+                .trim_matches(|c| c == '"' || c == ',')
+                .to_string()
+        })
+        .collect()
+}
+
+// KEY=value pairs of ENV and LABEL, or the old "KEY value" form
+fn key_values(args: &str) -> Vec<(String, String)> {
+    let unquote = |text: &str| text.trim_matches('"').to_string();
+    match args.split_once(char::is_whitespace) {
+        Some((key, value)) if !key.contains('=') => vec![(key.to_string(), unquote(value))],
+        _ => args
+            .split_whitespace()
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (unquote(key), unquote(value)))
+            .collect(),
+    }
+}
+
+// Config fields an instruction sets, as (field, key, value)
+fn instruction_changes(
+    instruction: &str,
+    args: &str,
+) -> Vec<(&'static str, Option<String>, String)> {
+    match instruction {
+        "ENV" => key_values(args)
+            .into_iter()
+            .map(|(key, value)| ("env", Some(key), value))
+            .collect(),
+        "LABEL" => key_values(args)
+            .into_iter()
+            .map(|(key, value)| ("labels", Some(key), value))
+            .collect(),
+        "EXPOSE" => go_map_keys(args)
+            .into_iter()
+            .map(|port| ("exposed_ports", Some(port), String::new()))
+            .collect(),
+        "VOLUME" => go_map_keys(args)
+            .into_iter()
+            .map(|volume| ("volumes", Some(volume), String::new()))
+            .collect(),
+        "ENTRYPOINT" => vec![("entrypoint", None, args.to_string())],
+        "CMD" => vec![("cmd", None, args.to_string())],
+        "WORKDIR" => vec![("working_dir", None, args.to_string())],
+        "USER" => vec![("user", None, args.to_string())],
+        "STOPSIGNAL" => vec![("stop_signal", None, args.to_string())],
+        "HEALTHCHECK" => vec![("healthcheck", None, args.to_string())],
+        _ => Vec::new(),
+    }
+}
+
+// What every history entry changed in the config, replayed oldest first so
+// overrides know the value they replace
+fn history_changes(image: &str) -> Result<Vec<HistoryConfigChanges>, String> {
+    let history = get_image_history(image)?;
+    let mut values: HashMap<(&'static str, Option<String>), String> = HashMap::new();
+    let mut entries = Vec::new();
+    for (index, entry) in history.iter().enumerate().rev() {
+        let (instruction, args) = normalize_created_by(&entry.created_by);
+        let changes: Vec<ConfigChange> = instruction_changes(&instruction, &args)
+            .into_iter()
+            .map(|(field, key, value)| {
+                let previous = values.insert((field, key.clone()), value.clone());
+                ConfigChange {
+                    field: field.to_string(),
+                    key,
+                    value,
+                    previous,
+                }
+            })
+            .collect();
+        if changes.is_empty() {
+            continue;
+        }
+        entries.push(HistoryConfigChanges {
+            layer_id: format!("layer_{}", index + 1),
+            created_by: entry.created_by.clone(),
+            instruction,
+            changes,
+        });
+    }
+    Ok(entries)
+}
+
+// The OCI config of an image, with the history entries that set each part
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_image_config(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+) -> Result<ImageConfig, LayersError> {
+    let image = session.image_or_selected(image)?;
+    info!("Reading config of {}", image);
+
+    let inspect = inspect_image(&image)?;
+    let config = &inspect["Config"];
+    let mut volumes: Vec<String> = config["Volumes"]
+        .as_object()
+        .map(|volumes| volumes.keys().cloned().collect())
+        .unwrap_or_default();
+    volumes.sort();
+
+    let history = history_changes(&image)?;
+    info!("{} history entries changed the config", history.len());
+    Ok(ImageConfig {
+        run: run_config(config),
+        volumes,
+        stop_signal: config["StopSignal"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        healthcheck: healthcheck(config),
+        history,
+        image,
+    })
+}
@@ -0,0 +1,430 @@
+use layers_core::layer_tar::{entry_relative_path, WHITEOUT_PREFIX};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{self, Cursor, Read};
+use tracing::info;
+
+use crate::analysis_cache::AnalysisCache;
+use crate::error::LayersError;
+use crate::findings::{SecurityFinding, Severity};
+use crate::sbom::{component, Ecosystem, SbomComponent};
+use crate::search_index::read_saved_layers;
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+// Fat JARs nest libraries in BOOT-INF/lib or WEB-INF/lib, rarely any deeper
+const MAX_NESTING: usize = 3;
+// Bump when KNOWN_VULNERABILITIES or the archive parsing change, cached
+// audits are keyed by it
+const ANALYZER_VERSION: u32 = 1;
+const MAX_ARCHIVE_SIZE: u64 = 512 * 1024 * 1024;
+
+// Libraries with well known vulnerabilities. Affected versions are
+// introduced <= version < fixed, a missing fix means every later release.
+struct KnownVulnerability {
+    group: &'static str,
+    artifact: &'static str,
+    introduced: &'static str,
+    fixed: Option<&'static str>,
+    id: &'static str,
+    severity: Severity,
+    summary: &'static str,
+}
+
+const KNOWN_VULNERABILITIES: [KnownVulnerability; 9] = [
+    KnownVulnerability {
+        group: "org.apache.logging.log4j",
+        artifact: "log4j-core",
+        introduced: "2.0-beta9",
+        fixed: Some("2.15.0"),
+        id: "CVE-2021-44228",
+        severity: Severity::Critical,
+        summary: "Log4Shell: JNDI lookups in logged messages allow remote code execution",
+    },
+    KnownVulnerability {
+        group: "org.apache.logging.log4j",
+        artifact: "log4j-core",
+        introduced: "2.0-beta9",
+        fixed: Some("2.16.0"),
+        id: "CVE-2021-45046",
+        severity: Severity::Critical,
+        summary: "Incomplete fix for Log4Shell in non-default configurations",
+    },
+    KnownVulnerability {
+        group: "org.apache.logging.log4j",
+        artifact: "log4j-core",
+        introduced: "2.0-alpha1",
+        fixed: Some("2.17.0"),
+        id: "CVE-2021-45105",
+        severity: Severity::High,
+        summary: "Uncontrolled recursion in lookups allows denial of service",
+    },
+    KnownVulnerability {
+        group: "org.apache.logging.log4j",
+        artifact: "log4j-core",
+        introduced: "2.0-alpha7",
+        fixed: Some("2.17.1"),
+        id: "CVE-2021-44832",
+        severity: Severity::Medium,
+        summary: "JDBC appender configurations allow remote code execution",
+    },
+    KnownVulnerability {
+        group: "log4j",
+        artifact: "log4j",
+        introduced: "1.2",
+        fixed: None,
+        id: "CVE-2019-17571",
+        severity: Severity::Critical,
+        summary: "SocketServer deserializes untrusted data, log4j 1.x is end of life",
+    },
+    KnownVulnerability {
+        group: "org.springframework",
+        artifact: "spring-beans",
+        introduced: "5.3.0",
+        fixed: Some("5.3.18"),
+        id: "CVE-2022-22965",
+        severity: Severity::Critical,
+        summary: "Spring4Shell: data binding allows remote code execution on JDK 9+",
+    },
+    KnownVulnerability {
+        group: "org.springframework",
+        artifact: "spring-beans",
+        introduced: "0",
+        fixed: Some("5.2.20"),
+        id: "CVE-2022-22965",
+        severity: Severity::Critical,
+        summary: "Spring4Shell: data binding allows remote code execution on JDK 9+",
+    },
+    KnownVulnerability {
+        group: "org.apache.commons",
+        artifact: "commons-text",
+        introduced: "1.5",
+        fixed: Some("1.10.0"),
+        id: "CVE-2022-42889",
+        severity: Severity::Critical,
+        summary: "Text4Shell: string interpolation allows remote code execution",
+    },
+    KnownVulnerability {
+        group: "commons-collections",
+        artifact: "commons-collections",
+        introduced: "3.0",
+        fixed: Some("3.2.2"),
+        id: "CVE-2015-7501",
+        severity: Severity::Critical,
+        summary: "InvokerTransformer allows remote code execution through Java deserialization",
+    },
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JavaLibrary {
+    // Layer that added the archive the library was found in
+    layer_id: String,
+    #[serde(flatten)]
+    component: SbomComponent,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JavaAudit {
+    archives_scanned: usize,
+    libraries: Vec<JavaLibrary>,
+    findings: Vec<SecurityFinding>,
+}
+
+pub(crate) fn is_java_archive(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.ends_with(".jar") || path.ends_with(".war") || path.ends_with(".ear")
+}
+
+// Compare dotted versions numerically, "2.9" < "2.15.0". Qualifiers like
+// -beta9 or -rc1 sort before the release they precede.
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |version: &str| -> (Vec<u64>, bool) {
+        let (release, qualifier) = match version.find(|c: char| c == '-' || c.is_alphabetic()) {
+            Some(at) => (&version[..at], version[at..].trim_start_matches(['-', '.'])),
+            None => (version, ""),
+        };
+        let numbers = release
+            .split('.')
+            .map(|n| n.parse().unwrap_or(0))
+            .collect::<Vec<u64>>();
+        // Spring's 5.3.18.RELEASE and Hibernate's .Final are releases too
+        let prerelease = !qualifier.is_empty()
+            && !["release", "final", "ga"].contains(&qualifier.to_lowercase().as_str());
+        (numbers, prerelease)
+    };
+    let ((a_numbers, a_prerelease), (b_numbers, b_prerelease)) = (parts(a), parts(b));
+    for i in 0..a_numbers.len().max(b_numbers.len()) {
+        let ordering = a_numbers
+            .get(i)
+            .unwrap_or(&0)
+            .cmp(b_numbers.get(i).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    // A pre-release comes before the plain version
+    b_prerelease.cmp(&a_prerelease)
+}
+
+fn parse_properties(content: &str) -> HashMap<&str, &str> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#') && !line.starts_with('!'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect()
+}
+
+// MANIFEST.MF is "Key: value" with long values continued on lines starting with a space
+fn manifest_attribute(content: &str, key: &str) -> Option<String> {
+    let mut lines = content.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some(value) = line
+            .strip_prefix(key)
+            .and_then(|rest| rest.strip_prefix(": "))
+        else {
+            continue;
+        };
+        let mut value = value.to_string();
+        while let Some(continuation) = lines.next_if(|l| l.starts_with(' ')) {
+            value.push_str(&continuation[1..]);
+        }
+        return Some(value.trim().to_string());
+    }
+    None
+}
+
+// "log4j-core-2.14.1.jar" -> ("log4j-core", "2.14.1")
+fn name_from_file(path: &str) -> Option<(String, String)> {
+    let file = path.rsplit(['/', '!']).next()?;
+    let stem = file.rsplit_once('.')?.0;
+    let at = stem
+        .match_indices('-')
+        .find(|(at, _)| stem[at + 1..].starts_with(|c: char| c.is_ascii_digit()))?
+        .0;
+    Some((stem[..at].to_string(), stem[at + 1..].to_string()))
+}
+
+fn read_zip_entry<R: Read>(file: &mut R, size: u64) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(size.min(MAX_ARCHIVE_SIZE) as usize);
+    file.read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+fn scan_nested(path: &str, data: &[u8], depth: usize, components: &mut Vec<SbomComponent>) {
+    let Ok(mut archive) = zip::ZipArchive::new(Cursor::new(data)) else {
+        return;
+    };
+
+    let mut found_pom = false;
+    let mut manifest = None;
+    let mut nested = Vec::new();
+    for i in 0..archive.len() {
+        let Ok(mut file) = archive.by_index(i) else {
+            continue;
+        };
+        let name = file.name().to_string();
+        let size = file.size();
+
+        // Shaded JARs carry one pom.properties per bundled library
+        if name.starts_with("META-INF/maven/") && name.ends_with("/pom.properties") {
+            let Some(content) = read_zip_entry(&mut file, size) else {
+                continue;
+            };
+            let content = String::from_utf8_lossy(&content);
+            let properties = parse_properties(&content);
+            if let (Some(group), Some(artifact), Some(version)) = (
+                properties.get("groupId"),
+                properties.get("artifactId"),
+                properties.get("version"),
+            ) {
+                found_pom = true;
+                components.push(component(
+                    &format!("{}:{}", group, artifact),
+                    version,
+                    Ecosystem::Maven,
+                    None,
+                    path,
+                ));
+            }
+        } else if name == "META-INF/MANIFEST.MF" {
+            manifest = read_zip_entry(&mut file, size);
+        } else if depth < MAX_NESTING && is_java_archive(&name) && size <= MAX_ARCHIVE_SIZE {
+            if let Some(bytes) = read_zip_entry(&mut file, size) {
+                nested.push((format!("{}!/{}", path, name), bytes));
+            }
+        }
+    }
+
+    // Without Maven metadata fall back to the manifest, then the file name
+    if !found_pom {
+        let manifest = manifest.map(|m| String::from_utf8_lossy(&m).to_string());
+        let from_manifest = manifest.as_deref().and_then(|m| {
+            let title = manifest_attribute(m, "Implementation-Title")
+                .or_else(|| manifest_attribute(m, "Bundle-SymbolicName"))?;
+            let version = manifest_attribute(m, "Implementation-Version")
+                .or_else(|| manifest_attribute(m, "Bundle-Version"))?;
+            // Bundle-SymbolicName may carry directives, e.g. "foo;singleton:=true"
+            Some((title.split(';').next()?.trim().to_string(), version))
+        });
+        if let Some((name, version)) = from_manifest.or_else(|| name_from_file(path)) {
+            components.push(component(&name, &version, Ecosystem::Maven, None, path));
+        }
+    }
+
+    for (nested_path, bytes) in nested {
+        scan_nested(&nested_path, &bytes, depth + 1, components);
+    }
+}
+
+// Libraries in a JAR, WAR or EAR and the archives nested inside it. Nested
+// libraries get paths like app.jar!/BOOT-INF/lib/log4j-core-2.14.1.jar.
+pub(crate) fn scan_java_archive(path: &str, data: &[u8]) -> Vec<SbomComponent> {
+    let mut components = Vec::new();
+    scan_nested(path, data, 0, &mut components);
+    components
+}
+
+pub(crate) fn known_vulnerabilities(library: &SbomComponent) -> Vec<SecurityFinding> {
+    if library.ecosystem != Ecosystem::Maven {
+        return Vec::new();
+    }
+    // Libraries identified by file name alone have no group
+    let (group, artifact) = match library.name.split_once(':') {
+        Some((group, artifact)) => (Some(group), artifact),
+        None => (None, library.name.as_str()),
+    };
+    KNOWN_VULNERABILITIES
+        .iter()
+        .filter(|v| v.artifact == artifact && group.is_none_or(|g| g == v.group))
+        .filter(|v| compare_versions(&library.version, v.introduced) != Ordering::Less)
+        .filter(|v| {
+            v.fixed
+                .is_none_or(|fixed| compare_versions(&library.version, fixed) == Ordering::Less)
+        })
+        .map(|v| SecurityFinding {
+            rule_id: "vulnerable-java-library".to_string(),
+            severity: v.severity,
+            title: format!(
+                "{} {} is affected by {}",
+                library.name, library.version, v.id
+            ),
+            description: match v.fixed {
+                Some(fixed) => format!("{}. Fixed in {}.", v.summary, fixed),
+                None => format!("{}. There is no fixed release.", v.summary),
+            },
+            path: Some(library.path.clone()),
+            layer_id: None,
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct LayerArchives {
+    // (container path, libraries found in it)
+    archives: Vec<(String, Vec<SbomComponent>)>,
+    whiteouts: Vec<String>,
+}
+
+fn read_layer_archives(reader: &mut dyn Read) -> io::Result<LayerArchives> {
+    let mut archive = tar::Archive::new(reader);
+    let mut layer = LayerArchives::default();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry_relative_path(&entry.path()?);
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", &path));
+        if let Some(deleted) = name.strip_prefix(WHITEOUT_PREFIX) {
+            layer.whiteouts.push(format!("{}/{}", parent, deleted));
+            continue;
+        }
+        if !entry.header().entry_type().is_file()
+            || !is_java_archive(&path)
+            || entry.size() > MAX_ARCHIVE_SIZE
+        {
+            continue;
+        }
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut data)?;
+        let libraries = scan_java_archive(&path, &data);
+        layer.archives.push((path, libraries));
+    }
+    Ok(layer)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn audit_java_dependencies(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    analysis_cache: tauri::State<'_, AnalysisCache>,
+) -> Result<JavaAudit, LayersError> {
+    info!("Auditing Java libraries");
+
+    let image_id = session.image_id()?;
+    let cache_key = analysis_cache.key(&image_id, "java", ANALYZER_VERSION, &());
+    if let Some(audit) = cache_key.as_ref().and_then(|key| analysis_cache.get(key)) {
+        return Ok(audit);
+    }
+
+    // Per layer contents, so each library is attributed to the layer that added it
+    let task = tasks.start();
+    let layers = read_saved_layers(&task, &image_id, read_layer_archives);
+    tasks.finish(task.id);
+    let layers = layers?;
+
+    // Archive path -> (layer, libraries), later layers replace or delete earlier files
+    let mut archives: HashMap<String, (String, Vec<SbomComponent>)> = HashMap::new();
+    for layer in layers {
+        for whiteout in &layer.contents.whiteouts {
+            let prefix = format!("{}/", whiteout.trim_start_matches('/'));
+            archives.retain(|path, _| {
+                path != whiteout.trim_start_matches('/') && !path.starts_with(&prefix)
+            });
+        }
+        for (path, libraries) in layer.contents.archives {
+            archives.insert(path, (layer.layer_id.clone(), libraries));
+        }
+    }
+
+    let archives_scanned = archives.len();
+    let mut libraries: Vec<JavaLibrary> = archives
+        .into_values()
+        .flat_map(|(layer_id, components)| {
+            components.into_iter().map(move |component| JavaLibrary {
+                layer_id: layer_id.clone(),
+                component,
+            })
+        })
+        .collect();
+    libraries.sort_by(|a, b| a.component.path.cmp(&b.component.path));
+
+    let findings: Vec<SecurityFinding> = libraries
+        .iter()
+        .flat_map(|library| {
+            known_vulnerabilities(&library.component)
+                .into_iter()
+                .map(|finding| SecurityFinding {
+                    layer_id: Some(library.layer_id.clone()),
+                    ..finding
+                })
+        })
+        .collect();
+
+    info!(
+        "Found {} Java libraries in {} archives, {} known vulnerabilities",
+        libraries.len(),
+        archives_scanned,
+        findings.len()
+    );
+    let audit = JavaAudit {
+        archives_scanned,
+        libraries,
+        findings,
+    };
+    if let Some(key) = &cache_key {
+        analysis_cache.put(key, &audit);
+    }
+    Ok(audit)
+}
@@ -0,0 +1,307 @@
+use layers_core::docker::{get_image_history, HistoryEntry};
+use layers_core::dockerfile::{Dockerfile, DockerfileInstruction};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::session::SessionState;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchConfidence {
+    // Instruction and arguments match the history entry
+    Exact,
+    // Instruction type matches but the arguments could only be partially compared
+    Partial,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstructionLayerMapping {
+    line_number: usize,
+    instruction: String,
+    arguments: String,
+    // Matches the "layer_N" IDs produced by export_image_layers
+    layer_id: Option<String>,
+    history_id: Option<String>,
+    created_by: Option<String>,
+    size: Option<String>,
+    size_bytes: Option<u64>,
+    confidence: Option<MatchConfidence>,
+}
+
+// Turn a history CreatedBy field into (instruction, arguments), handling both
+// the legacy builder and BuildKit formats
+pub(crate) fn normalize_created_by(created_by: &str) -> (String, String) {
+    let created_by = created_by.trim();
+    let created_by = created_by
+        .strip_suffix("# buildkit")
+        .unwrap_or(created_by)
+        .trim();
+
+    if let Some(rest) = created_by.strip_prefix("/bin/sh -c #(nop)") {
+        return split_instruction(rest.trim());
+    }
+    if let Some(rest) = created_by.strip_prefix("/bin/sh -c ") {
+        return ("RUN".to_string(), normalize_whitespace(rest));
+    }
+
+    let (instruction, args) = split_instruction(created_by);
+    if instruction == "RUN" {
+        return ("RUN".to_string(), strip_run_prefix(&args));
+    }
+    (instruction, args)
+}
+
+fn split_instruction(text: &str) -> (String, String) {
+    let mut parts = text.splitn(2, char::is_whitespace);
+    let instruction = parts.next().unwrap_or("").to_uppercase();
+    let args = normalize_whitespace(parts.next().unwrap_or(""));
+    (instruction, args)
+}
+
+// BuildKit records RUN as "RUN |2 A=1 B=2 /bin/sh -c cmd", keep only "cmd"
+fn strip_run_prefix(args: &str) -> String {
+    let mut rest = args;
+    if rest.starts_with('|') {
+        rest = rest.find("/bin/sh -c").map(|i| &rest[i..]).unwrap_or(rest);
+    }
+    let rest = rest.strip_prefix("/bin/sh -c").unwrap_or(rest);
+    normalize_whitespace(rest)
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn match_instruction(
+    instruction: &DockerfileInstruction,
+    entry: &HistoryEntry,
+) -> Option<MatchConfidence> {
+    let (history_instruction, history_args) = normalize_created_by(&entry.created_by);
+    if history_instruction != instruction.instruction {
+        return None;
+    }
+
+    let args = normalize_whitespace(&instruction.arguments);
+    match instruction.instruction.as_str() {
+        // Legacy builder records sources as content hashes ("file:abc in /app"),
+        // so only the destination can be compared reliably
+        "COPY" | "ADD" => {
+            let destination = args.split_whitespace().last().unwrap_or("");
+            if history_args == args {
+                Some(MatchConfidence::Exact)
+            } else if !destination.is_empty() && history_args.trim_end().ends_with(destination) {
+                Some(MatchConfidence::Partial)
+            } else {
+                None
+            }
+        }
+        _ => {
+            // An empty side is contained in anything, it can't vouch for a match
+            if history_args == args {
+                Some(MatchConfidence::Exact)
+            } else if !args.is_empty()
+                && !history_args.is_empty()
+                && (history_args.contains(&args) || args.contains(&history_args))
+            {
+                Some(MatchConfidence::Partial)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+pub(crate) fn map_instructions(
+    dockerfile: &Dockerfile,
+    history: &[HistoryEntry],
+) -> Vec<InstructionLayerMapping> {
+    let instructions = dockerfile.final_stage_instructions();
+    let mut mappings = Vec::with_capacity(instructions.len());
+
+    // Both lists are walked from the end: the last instruction produced the
+    // newest history entry (index 0), and base image history sits at the bottom
+    let mut next_history = 0;
+    for instruction in instructions.iter().rev() {
+        let mut matched = None;
+
+        for (index, entry) in history.iter().enumerate().skip(next_history) {
+            if let Some(confidence) = match_instruction(instruction, entry) {
+                matched = Some((index, entry, confidence));
+                break;
+            }
+        }
+
+        let mapping = match matched {
+            Some((index, entry, confidence)) => {
+                next_history = index + 1;
+                InstructionLayerMapping {
+                    line_number: instruction.line_number,
+                    instruction: instruction.instruction.clone(),
+                    arguments: instruction.arguments.clone(),
+                    layer_id: Some(format!("layer_{}", index + 1)),
+                    history_id: Some(entry.id.clone()),
+                    created_by: Some(entry.created_by.clone()),
+                    size: Some(entry.size.clone()),
+                    size_bytes: Some(entry.size_bytes),
+                    confidence: Some(confidence),
+                }
+            }
+            None => InstructionLayerMapping {
+                line_number: instruction.line_number,
+                instruction: instruction.instruction.clone(),
+                arguments: instruction.arguments.clone(),
+                layer_id: None,
+                history_id: None,
+                created_by: None,
+                size: None,
+                size_bytes: None,
+                confidence: None,
+            },
+        };
+        mappings.push(mapping);
+    }
+
+    mappings.reverse();
+    mappings
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn map_dockerfile_to_layers(
+    session: tauri::State<'_, SessionState>,
+    content: String,
+    image: Option<String>,
+) -> Result<Vec<InstructionLayerMapping>, LayersError> {
+    let image = session.image_or_selected(image)?;
+    info!("Mapping Dockerfile instructions to layers of {}", image);
+
+    let dockerfile = Dockerfile::parse(&content);
+    let history = get_image_history(&image)?;
+    let mappings = map_instructions(&dockerfile, &history);
+
+    info!(
+        "Mapped {} of {} instructions",
+        mappings.iter().filter(|m| m.layer_id.is_some()).count(),
+        mappings.len()
+    );
+    Ok(mappings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, created_by: &str) -> HistoryEntry {
+        HistoryEntry {
+            id: id.to_string(),
+            created: String::new(),
+            size: "0B".to_string(),
+            size_bytes: 0,
+            created_by: created_by.to_string(),
+        }
+    }
+
+    fn instruction(instruction: &str, arguments: &str) -> DockerfileInstruction {
+        DockerfileInstruction {
+            instruction: instruction.to_string(),
+            arguments: arguments.to_string(),
+            line_number: 1,
+        }
+    }
+
+    #[test]
+    fn legacy_created_by_is_normalized() {
+        assert_eq!(
+            normalize_created_by("/bin/sh -c #(nop)  WORKDIR /app"),
+            ("WORKDIR".to_string(), "/app".to_string())
+        );
+        assert_eq!(
+            normalize_created_by("/bin/sh -c apk add   --no-cache curl"),
+            ("RUN".to_string(), "apk add --no-cache curl".to_string())
+        );
+        assert_eq!(
+            normalize_created_by("/bin/sh -c #(nop) COPY file:abc123 in /app "),
+            ("COPY".to_string(), "file:abc123 in /app".to_string())
+        );
+    }
+
+    #[test]
+    fn buildkit_created_by_is_normalized() {
+        assert_eq!(
+            normalize_created_by("RUN /bin/sh -c npm ci # buildkit"),
+            ("RUN".to_string(), "npm ci".to_string())
+        );
+        // Build args are recorded as a "|N" prefix before the shell
+        assert_eq!(
+            normalize_created_by(
+                "RUN |2 VERSION=1.2 TARGET=prod /bin/sh -c make $TARGET # buildkit"
+            ),
+            ("RUN".to_string(), "make $TARGET".to_string())
+        );
+        assert_eq!(
+            normalize_created_by("ENV PORT=8080"),
+            ("ENV".to_string(), "PORT=8080".to_string())
+        );
+    }
+
+    #[test]
+    fn empty_arguments_are_no_partial_match() {
+        let cmd = entry("a", "/bin/sh -c #(nop)  CMD [\"nginx\"]");
+        assert_eq!(match_instruction(&instruction("CMD", ""), &cmd), None);
+        assert_eq!(
+            match_instruction(&instruction("CMD", "[\"nginx\"]"), &cmd),
+            Some(MatchConfidence::Exact)
+        );
+
+        let run = entry(
+            "b",
+            "RUN /bin/sh -c apt-get update && apt-get install -y curl # buildkit",
+        );
+        assert_eq!(match_instruction(&instruction("RUN", ""), &run), None);
+        assert_eq!(
+            match_instruction(&instruction("RUN", "apt-get update"), &run),
+            Some(MatchConfidence::Partial)
+        );
+
+        let copy = entry("c", "COPY . /app # buildkit");
+        assert_eq!(match_instruction(&instruction("COPY", ""), &copy), None);
+    }
+
+    #[test]
+    fn instructions_map_to_history_newest_first() {
+        let dockerfile = Dockerfile::parse(
+            "FROM alpine\nWORKDIR /app\nCOPY . /app\nRUN make build\nCMD [\"./app\"]\n",
+        );
+        // Newest first, like docker history, with the base image at the bottom
+        let history = [
+            entry("4", "CMD [\"./app\"]"),
+            entry("3", "RUN /bin/sh -c make build # buildkit"),
+            entry("2", "COPY src /app # buildkit"),
+            entry("1", "WORKDIR /app"),
+            entry("0", "/bin/sh -c #(nop)  CMD [\"/bin/sh\"]"),
+        ];
+        let mappings = map_instructions(&dockerfile, &history);
+        let mapped: Vec<(&str, Option<&str>, Option<&MatchConfidence>)> = mappings
+            .iter()
+            .map(|m| {
+                (
+                    m.instruction.as_str(),
+                    m.history_id.as_deref(),
+                    m.confidence.as_ref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            mapped,
+            [
+                ("WORKDIR", Some("1"), Some(&MatchConfidence::Exact)),
+                ("COPY", Some("2"), Some(&MatchConfidence::Partial)),
+                ("RUN", Some("3"), Some(&MatchConfidence::Exact)),
+                ("CMD", Some("4"), Some(&MatchConfidence::Exact)),
+            ]
+        );
+        assert_eq!(mappings[3].layer_id.as_deref(), Some("layer_1"));
+        assert_eq!(mappings[0].layer_id.as_deref(), Some("layer_4"));
+    }
+}
@@ -0,0 +1,143 @@
+use layers_core::docker::{
+    get_image_history, image_diff_ids, is_empty_history_entry, CRITICAL_LAYERS, MAX_LAYERS,
+    WARN_LAYERS,
+};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::findings::{SecurityFinding, Severity};
+use crate::session::SessionState;
+use crate::{history_layers, DockerLayer};
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const LARGEST_LAYERS: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerSize {
+    // Matches the "layer_N" IDs produced by export_image_layers
+    layer_id: String,
+    created_by: String,
+    size_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerStats {
+    // Every history entry, metadata-only instructions included
+    history_entries: usize,
+    filesystem_layers: usize,
+    empty_layers: usize,
+    total_bytes: u64,
+    average_bytes: u64,
+    largest: Vec<LayerSize>,
+    layer_limit: usize,
+    remaining_layers: usize,
+    findings: Vec<SecurityFinding>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerPage {
+    total: usize,
+    offset: usize,
+    layers: Vec<DockerLayer>,
+}
+
+// Warning about how close the image is to the layer limit, if it is close
+pub(crate) fn layer_limit_finding(filesystem_layers: usize) -> Option<SecurityFinding> {
+    let severity = if filesystem_layers >= CRITICAL_LAYERS {
+        Severity::High
+    } else if filesystem_layers >= WARN_LAYERS {
+        Severity::Medium
+    } else {
+        return None;
+    };
+    Some(SecurityFinding {
+        rule_id: "layer-limit-proximity".to_string(),
+        severity,
+        title: format!(
+            "{} of {} layers used",
+            filesystem_layers.min(MAX_LAYERS),
+            MAX_LAYERS
+        ),
+        description: format!(
+            "Images built on top of this one can add only {} more layers before builds fail. Squash the image or merge RUN instructions.",
+            MAX_LAYERS.saturating_sub(filesystem_layers)
+        ),
+        path: None,
+        layer_id: None,
+    })
+}
+
+// Aggregates over all layers, so images with a hundred layers can be judged
+// without listing them
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_layer_stats(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+) -> Result<LayerStats, LayersError> {
+    let image = session.image_or_selected(image)?;
+    let history = get_image_history(&image)?;
+    let filesystem_layers = image_diff_ids(&image)?.len();
+
+    let total_bytes: u64 = history.iter().map(|entry| entry.size_bytes).sum();
+    let empty_layers = history
+        .iter()
+        .filter(|entry| is_empty_history_entry(&entry.created_by, entry.size_bytes))
+        .count();
+
+    let mut largest: Vec<LayerSize> = history
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| LayerSize {
+            layer_id: format!("layer_{}", index + 1),
+            created_by: entry.created_by.clone(),
+            size_bytes: entry.size_bytes,
+        })
+        .collect();
+    largest.sort_by_key(|layer| Reverse(layer.size_bytes));
+    largest.truncate(LARGEST_LAYERS);
+
+    info!(
+        "{} has {} history entries, {} filesystem layers",
+        image,
+        history.len(),
+        filesystem_layers
+    );
+    Ok(LayerStats {
+        history_entries: history.len(),
+        filesystem_layers,
+        empty_layers,
+        total_bytes,
+        average_bytes: total_bytes / filesystem_layers.max(1) as u64,
+        largest,
+        layer_limit: MAX_LAYERS,
+        remaining_layers: MAX_LAYERS.saturating_sub(filesystem_layers),
+        findings: layer_limit_finding(filesystem_layers).into_iter().collect(),
+    })
+}
+
+// A slice of the layer list, newest first like export_image_layers
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_image_layers_page(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<LayerPage, LayersError> {
+    let image = session.image_or_selected(image)?;
+    let history = get_image_history(&image)?;
+    let diff_ids = image_diff_ids(&image)?;
+    let layers = history_layers(&history, &diff_ids);
+
+    let total = layers.len();
+    let offset = offset.unwrap_or(0).min(total);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    Ok(LayerPage {
+        total,
+        offset,
+        layers: layers.into_iter().skip(offset).take(limit).collect(),
+    })
+}
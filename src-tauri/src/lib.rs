@@ -1,18 +1,153 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+use layers_core::docker::{
+    docker_command, get_image_history, image_diff_ids, inspect_image, parse_docker_size,
+    HistoryEntry,
+};
+use layers_core::dockerfile::{BuildStage, CacheStability, Dockerfile};
+use layers_core::layer_tar::{display_path, entry_relative_path, exact_path, path_from_escaped};
+use layers_core::size_estimate::estimate_layer_sizes;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
-use std::process::Command;
-use tauri::Emitter;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use tracing::{debug, error, info, warn};
+
+mod analysis_cache;
+mod archive_loader;
+mod attribution;
+mod audit;
+mod blob;
+mod bookmarks;
+mod cache;
+mod clipboard;
+mod cold_start;
+mod compare_presets;
+mod config;
+mod container_access;
+mod content_index;
+mod deep_link;
+mod dependency_audit;
+mod digest_verify;
+mod dockerfile_build;
+mod dockerfile_lint;
+mod dockerfile_rewrite;
+mod dockerfile_watch;
+mod error;
+mod exec_safety;
+mod export;
+mod exporters;
+mod file_diff;
+mod file_modes;
+mod file_preview;
+mod file_range;
+mod file_tree;
+mod findings;
+mod grep;
+mod health;
+mod image_compare;
+mod image_config;
+mod java_packages;
+mod layer_mapping;
+mod layer_stats;
+mod links;
+mod logging;
+mod os_packages;
+mod ownership;
+mod permissions;
+mod phases;
+mod prefetch;
+mod provenance;
+mod pull;
+mod pull_time;
+mod remote_image;
+mod repo_trust;
+mod reports;
+mod resources;
+mod rootfs_export;
+mod ruleset_sync;
+mod run_snippet;
+mod runtime_writes;
+mod sbom;
+mod script_hook;
+mod search_index;
+mod secrets;
+mod seekable;
+mod selective_extract;
+mod services;
+mod session;
+mod shell_lint;
+mod size_breakdown;
+mod slim_proposal;
+mod squash;
+mod startup_check;
+mod tag_history;
+mod tar_extract;
+mod tar_index;
+mod tasks;
+#[cfg(test)]
+mod test_fixtures;
+mod timeline;
+mod transfer;
+mod trends;
+mod undo;
+mod vulnerabilities;
+mod xattrs;
+
+use analysis_cache::AnalysisCache;
+use archive_loader::{ensure_layer_tar, format_size};
+use cache::ExtractionCache;
+use dockerfile_watch::DockerfileWatchers;
+use error::LayersError;
+use exporters::ExporterRegistry;
+use file_modes::FileMetadata;
+use file_range::LineIndexes;
+use health::HealthScoring;
+use links::{is_broken_on_disk, LinkIndex, LinkInfo, LinkKind};
+use ownership::FileOwner;
+use phases::{phase, Phase, PhaseProgress, PhaseStatus};
+use remote_image::RemoteImages;
+use reports::ReportStore;
+use resources::ResourceLimits;
+use session::{ImageSession, SessionState};
+use tasks::{ProgressReader, Task, TaskRegistry};
+use transfer::{Transfer, TransferStatus};
+use trends::TrendStore;
+use undo::UndoHistory;
+use xattrs::FileAttributes;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileItem {
     name: String,
+    // "file", "directory", "symlink" or "hardlink"
     #[serde(rename = "type")]
     file_type: String,
+    // Readable form, see layer_tar::display_path
     path: String,
+    // Exact form for paths the readable one mangles, e.g. names that aren't
+    // UTF-8. Commands take either form.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exact_path: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     size: Option<String>,
+    // Same as size in bytes, for sorting and summing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size_bytes: Option<u64>,
+    // Where a symlink or hard link points, as stored in the image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link_target: Option<String>,
+    // The link points to nothing in the image, or loops
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    broken_link: bool,
+    // Mode, uid/gid and mtime from the tar header
+    #[serde(flatten)]
+    metadata: Option<FileMetadata>,
+    // File capabilities, SELinux label and other xattrs from the PAX header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    attributes: Option<FileAttributes>,
+    // uid/gid with the names the image's /etc/passwd and /etc/group give them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<FileOwner>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -21,7 +156,13 @@ pub struct DockerLayer {
     name: String,
     command: String,
     size: String,
+    #[serde(default)]
+    size_bytes: u64,
     createdAt: String,
+    // Why the layer could only be read in part, files after the bad entry
+    // are missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    partial: Option<String>,
     files: Vec<FileItem>,
 }
 
@@ -31,7 +172,23 @@ pub struct DockerImageInfo {
     name: String,
     created: String,
     size: String,
+    #[serde(default)]
+    size_bytes: u64,
     layers: Vec<DockerLayer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    config: Option<ImageRunConfig>,
+}
+
+// The parts of the image config that decide how a container starts
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageRunConfig {
+    entrypoint: Vec<String>,
+    cmd: Vec<String>,
+    env: Vec<String>,
+    working_dir: String,
+    user: String,
+    exposed_ports: Vec<String>,
+    labels: std::collections::HashMap<String, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,33 +198,50 @@ pub struct DockerImage {
     tag: String,
     created: String,
     size: String,
+    // docker images only prints rounded sizes, so this is approximate
+    size_bytes: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct DockerfileAnalysisItem {
     line_number: u32,
     instruction: String,
     impact: String,
+    // Estimated size of the layer before building, None if unknown
+    predicted_mb: Option<f64>,
+    prediction_basis: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DockerfileOptimizationSuggestion {
     title: String,
     description: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct DockerfileAnalysis {
     layer_impact: Vec<DockerfileAnalysisItem>,
     optimization_suggestions: Vec<DockerfileOptimizationSuggestion>,
+    cache_stability: Vec<CacheStability>,
+    stages: Vec<BuildStage>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TaskStatus {
+    task_id: u64,
     message: String,
     progress: f32, // 0.0 to 1.0
     is_complete: bool,
     error: Option<String>,
+    // Step of a multi-phase operation, None for single step tasks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    phase: Option<PhaseStatus>,
+    // Byte counts and ETA of extraction, hashing and pulls, sent as
+    // bytes_processed, bytes_total, current_item and eta_seconds
+    #[serde(flatten)]
+    transfer: Option<TransferStatus>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -89,19 +263,77 @@ pub struct LayerDiff {
 pub struct FileHash {
     path: String,
     hash: String,
+    algorithm: String,
     is_dir: bool,
     size: u64,
 }
 
+// Order of a listing sorted by size, entries without one (directories) come
+// last either way
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeOrder {
+    Ascending,
+    Descending,
+}
+
+fn sort_by_size<T>(items: &mut [T], order: Option<SizeOrder>, size: impl Fn(&T) -> Option<u64>) {
+    let Some(order) = order else {
+        return;
+    };
+    items.sort_by(|a, b| match (size(a), size(b)) {
+        (Some(a), Some(b)) if order == SizeOrder::Ascending => a.cmp(&b),
+        (Some(a), Some(b)) => b.cmp(&a),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HashMode {
+    // SHA-256 over the full file content
+    Full,
+    // SHA-256 over the size plus the first and last chunk of the file
+    Fast,
+}
+
+impl HashMode {
+    // Size of the chunks read from each end of the file in fast mode
+    const SAMPLE_SIZE: u64 = 64 * 1024;
+
+    fn algorithm(&self) -> &'static str {
+        match self {
+            HashMode::Full => "sha256",
+            HashMode::Fast => "sha256-sampled",
+        }
+    }
+
+    // Whether a file of `size` is hashed from samples of its ends only
+    fn samples(&self, size: u64) -> bool {
+        *self == HashMode::Fast && size > Self::SAMPLE_SIZE * 2
+    }
+
+    // Bytes read to hash a file of `size`
+    fn bytes_read(&self, size: u64) -> u64 {
+        if self.samples(size) {
+            Self::SAMPLE_SIZE * 2
+        } else {
+            size
+        }
+    }
+}
+
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
 #[tauri::command]
-async fn get_docker_images() -> Result<Vec<DockerImage>, String> {
+#[tracing::instrument(skip_all)]
+async fn get_docker_images(sort: Option<SizeOrder>) -> Result<Vec<DockerImage>, LayersError> {
     // Execute docker images command to get list of images
-    let output = Command::new("docker")
+    let output = docker_command()
         .args([
             "images",
             "--format",
@@ -114,7 +346,8 @@ async fn get_docker_images() -> Result<Vec<DockerImage>, String> {
         return Err(format!(
             "Failed to list docker images: {}",
             String::from_utf8_lossy(&output.stderr)
-        ));
+        )
+        .into());
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -122,7 +355,7 @@ async fn get_docker_images() -> Result<Vec<DockerImage>, String> {
 
     for line in stdout.lines() {
         let parts: Vec<&str> = line.split('|').collect();
-        println!("Parts: {:?}", parts);
+        debug!("Parts: {:?}", parts);
         if parts.len() >= 5 {
             // Skip images with <none> repository or tag, and also skip images with repository "layers"
             if (parts[1] != "<none>" || parts[2] != "<none>") && parts[1] != "layers" {
@@ -132,154 +365,87 @@ async fn get_docker_images() -> Result<Vec<DockerImage>, String> {
                     tag: parts[2].to_string(),
                     created: parts[3].to_string(),
                     size: parts[4].to_string(),
+                    size_bytes: parse_docker_size(parts[4]),
                 });
             }
         }
     }
 
+    sort_by_size(&mut images, sort, |image| Some(image.size_bytes));
     Ok(images)
 }
 
-#[tauri::command]
-async fn retag_image_for_layers(image_id: String) -> Result<String, String> {
-    println!("Retagging image with ID: '{}' as layers:latest", image_id);
-
-    if image_id.is_empty() {
-        let error = "Image ID is empty".to_string();
-        println!("Error: {}", error);
-        return Err(error);
-    }
-
-    // First, ensure the /tmp/layers directory exists
-    let layers_dir = Path::new("/tmp/layers");
-    if !layers_dir.exists() {
-        println!("Creating layers directory: {:?}", layers_dir);
-        fs::create_dir_all(layers_dir)
-            .map_err(|e| format!("Failed to create /tmp/layers directory: {}", e))?;
-    } else {
-        // Clean up any existing files
-        println!("Cleaning up layers directory: {:?}", layers_dir);
-        fs::remove_dir_all(layers_dir)
-            .map_err(|e| format!("Failed to clean up /tmp/layers directory: {}", e))?;
-        fs::create_dir_all(layers_dir)
-            .map_err(|e| format!("Failed to recreate /tmp/layers directory: {}", e))?;
-    }
-
-    // Remove any existing layers:latest tag to avoid conflicts
-    println!("Removing any existing layers:latest tag");
-    let _ = Command::new("docker")
-        .args(["rmi", "layers:latest"])
-        .output();
-    // Ignore errors as the tag might not exist
-
-    // Tag the image with 'layers' tag
-    println!("Tagging image {} as layers:latest", image_id);
-    let tag_output = Command::new("docker")
-        .args(["tag", &image_id, "layers:latest"])
-        .output()
-        .map_err(|e| format!("Failed to tag image: {}", e))?;
-
-    if !tag_output.status.success() {
-        let error = format!(
-            "Failed to tag image: {}",
-            String::from_utf8_lossy(&tag_output.stderr)
-        );
-        println!("Error: {}", error);
-        return Err(error);
-    }
-
-    // Verify the tag was created
-    println!("Verifying tag was created");
-    let verify_output = Command::new("docker")
-        .args(["images", "layers:latest", "-q"])
-        .output()
-        .map_err(|e| format!("Failed to verify tag: {}", e))?;
-
-    let tagged_id = String::from_utf8_lossy(&verify_output.stdout)
-        .trim()
-        .to_string();
-    if tagged_id.is_empty() {
-        let error = "Failed to verify tag: No image found with tag layers:latest".to_string();
-        println!("Error: {}", error);
-        return Err(error);
-    }
-
-    println!("Successfully tagged image {} as layers:latest", image_id);
-    Ok(format!(
-        "Successfully tagged image {} as layers:latest",
-        image_id
-    ))
-}
-
-#[tauri::command]
-async fn export_image_layers(window: tauri::Window) -> Result<DockerImageInfo, String> {
-    println!("Starting export_image_layers");
-
-    // Create a function to update status
-    let update_status = |message: &str, progress: f32, is_complete: bool, error: Option<String>| {
-        println!(
-            "Status update: {}, progress: {}, complete: {}",
-            message, progress, is_complete
-        );
+// Clean up after a task and let the frontend know if it was cancelled
+fn finish_task(window: &tauri::Window, tasks: &TaskRegistry, task: &Task) {
+    if task.is_cancelled() {
+        task.cleanup();
         let _ = window.emit(
             "task_status",
             TaskStatus {
-                message: message.to_string(),
-                progress,
-                is_complete,
-                error,
+                task_id: task.id,
+                message: "Task cancelled".to_string(),
+                progress: 0.0,
+                is_complete: true,
+                error: Some(format!("Task {} was cancelled", task.id)),
+                phase: None,
+                transfer: None,
             },
         );
-    };
+    }
+    tasks.finish(task.id);
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+async fn export_image_layers(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+) -> Result<DockerImageInfo, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let result = export_image_layers_task(&window, &task, &session).await;
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
 
-    update_status("Starting layer export process...", 0.0, false, None);
+// Reading history is quick, indexing streams the whole image through docker save
+const EXPORT_IMAGE_PHASES: &[Phase] = &[
+    phase("resolve", 0.05),
+    phase("fetch", 0.15),
+    phase("index", 0.8),
+];
+
+async fn export_image_layers_task(
+    window: &tauri::Window,
+    task: &Task,
+    session: &ImageSession,
+) -> Result<DockerImageInfo, String> {
+    info!("Starting export_image_layers");
+    let progress = PhaseProgress::new(window, task.id, EXPORT_IMAGE_PHASES);
+    progress.begin("resolve", "Starting layer export process...");
 
-    // First, ensure the /tmp/layers directory exists
-    let layers_dir = Path::new("/tmp/layers");
-    println!("Layers directory: {:?}", layers_dir);
+    // First, ensure the session directory exists
+    let layers_dir = session.dir();
+    debug!("Layers directory: {:?}", layers_dir);
 
     if !layers_dir.exists() {
-        println!("Creating layers directory: {:?}", layers_dir);
+        debug!("Creating layers directory: {:?}", layers_dir);
         fs::create_dir_all(layers_dir)
-            .map_err(|e| format!("Failed to create /tmp/layers directory: {}", e))?;
+            .map_err(|e| format!("Failed to create layers directory: {}", e))?;
     }
 
-    // Get the image ID for layers:latest
-    println!("Getting image ID for layers:latest");
-    let image_id_output = Command::new("docker")
-        .args(["images", "layers:latest", "-q"])
-        .output()
-        .map_err(|e| format!("Failed to get image ID: {}", e))?;
-
-    if !image_id_output.status.success() {
-        let error = format!(
-            "Failed to get image ID: {}",
-            String::from_utf8_lossy(&image_id_output.stderr)
-        );
-        println!("Error: {}", error);
-        update_status("Failed to get image ID", 0.0, true, Some(error.clone()));
-        return Err(error);
-    }
-
-    let image_id = String::from_utf8_lossy(&image_id_output.stdout)
-        .trim()
-        .to_string();
-    if image_id.is_empty() {
-        let error = "No image found with tag layers:latest".to_string();
-        println!("Error: {}", error);
-        update_status(&error, 0.0, true, Some(error.clone()));
-        return Err(error);
-    }
-
-    println!("Found image ID: {}", image_id);
-    update_status("Inspecting image layers...", 0.1, false, None);
+    let image_id = session.image_id();
+    debug!("Found image ID: {}", image_id);
+    progress.update("Inspecting image layers...", 0.5);
 
     // Get image history to identify layers
-    println!("Getting image history");
-    let history_output = Command::new("docker")
+    debug!("Getting image history");
+    let history_output = docker_command()
         .args([
             "history",
-            "layers:latest",
+            image_id,
             "--no-trunc",
             "--format",
             "{{.ID}}|{{.CreatedSince}}|{{.Size}}|{{.CreatedBy}}",
@@ -292,36 +458,37 @@ async fn export_image_layers(window: tauri::Window) -> Result<DockerImageInfo, S
             "Failed to get image history: {}",
             String::from_utf8_lossy(&history_output.stderr)
         );
-        println!("Error: {}", error);
-        update_status(&error, 0.1, true, Some(error.clone()));
+        error!("{}", error);
+        progress.fail(&error);
         return Err(error);
     }
 
     let history = String::from_utf8_lossy(&history_output.stdout);
-    println!("Image history: {}", history);
+    debug!("Image history: {}", history);
 
     let mut layers = Vec::new();
     let history_lines: Vec<&str> = history.lines().collect();
     let total_layers = history_lines.len();
-    println!("Total layers: {}", total_layers);
+    debug!("Total layers: {}", total_layers);
 
     if total_layers == 0 {
         let error = "No layers found in the image".to_string();
-        println!("Error: {}", error);
-        update_status(&error, 0.1, true, Some(error.clone()));
+        error!("{}", error);
+        progress.fail(&error);
         return Err(error);
     }
 
     let mut current_layer = 0;
+    progress.begin("fetch", "Reading layers...");
 
     for line in history_lines {
+        task.check_cancelled()?;
         current_layer += 1;
-        let progress = 0.1 + (0.8 * (current_layer as f32 / total_layers as f32));
-        println!("Processing layer {} of {}", current_layer, total_layers);
+        info!("Processing layer {} of {}", current_layer, total_layers);
 
         let parts: Vec<&str> = line.split('|').collect();
         if parts.len() < 4 {
-            println!("Invalid layer data: {}", line);
+            warn!("Invalid layer data: {}", line);
             continue;
         }
 
@@ -330,34 +497,33 @@ async fn export_image_layers(window: tauri::Window) -> Result<DockerImageInfo, S
         let size = parts[2].to_string();
         let command = parts[3].to_string();
 
-        println!("Layer ID: '{}'", layer_id);
-        println!("Layer ID length: {}", layer_id.len());
-        println!("Created: {}", created);
-        println!("Size: {}", size);
-        println!("Command: {}", command);
+        debug!("Layer ID: '{}'", layer_id);
+        debug!("Layer ID length: {}", layer_id.len());
+        debug!("Created: {}", created);
+        debug!("Size: {}", size);
+        debug!("Command: {}", command);
 
         // Use a generic layer name based on the layer number
         let layer_dir_name = format!("layer_{}", current_layer);
-        println!("Using generic layer directory name: {}", layer_dir_name);
+        debug!("Using generic layer directory name: {}", layer_dir_name);
 
-        update_status(
+        progress.update(
             &format!(
                 "Processing layer {} of {}: {}",
                 current_layer, total_layers, layer_dir_name
             ),
-            progress,
-            false,
-            None,
+            current_layer as f32 / total_layers as f32,
         );
 
         // Create a directory for this layer
         let layer_dir = layers_dir.join(&layer_dir_name);
-        println!("Layer directory: {:?}", layer_dir);
+        debug!("Layer directory: {:?}", layer_dir);
 
         if !layer_dir.exists() {
-            println!("Creating layer directory: {:?}", layer_dir);
+            debug!("Creating layer directory: {:?}", layer_dir);
             fs::create_dir_all(&layer_dir)
                 .map_err(|e| format!("Failed to create layer directory: {}", e))?;
+            task.track_path(&layer_dir);
         }
 
         // Export layer contents (this is a simplified approach)
@@ -369,19 +535,36 @@ async fn export_image_layers(window: tauri::Window) -> Result<DockerImageInfo, S
             FileItem {
                 name: "layer_info.txt".to_string(),
                 file_type: "file".to_string(),
-                path: format!("/tmp/layers/{}/layer_info.txt", layer_dir_name),
+                path: layer_dir
+                    .join("layer_info.txt")
+                    .to_string_lossy()
+                    .to_string(),
+                exact_path: None,
                 size: Some("1KB".to_string()),
+                size_bytes: Some(1024),
+                link_target: None,
+                broken_link: false,
+                metadata: None,
+                attributes: None,
+                owner: None,
             },
             FileItem {
                 name: "command.txt".to_string(),
                 file_type: "file".to_string(),
-                path: format!("/tmp/layers/{}/command.txt", layer_dir_name),
+                path: layer_dir.join("command.txt").to_string_lossy().to_string(),
+                exact_path: None,
                 size: Some("512B".to_string()),
+                size_bytes: Some(512),
+                link_target: None,
+                broken_link: false,
+                metadata: None,
+                attributes: None,
+                owner: None,
             },
         ];
 
         // Write the command to a file
-        println!(
+        debug!(
             "Writing command to file: {:?}",
             layer_dir.join("command.txt")
         );
@@ -389,7 +572,7 @@ async fn export_image_layers(window: tauri::Window) -> Result<DockerImageInfo, S
             .map_err(|e| format!("Failed to write command file: {}", e))?;
 
         // Write layer info to a file
-        println!(
+        debug!(
             "Writing layer info to file: {:?}",
             layer_dir.join("layer_info.txt")
         );
@@ -406,33 +589,67 @@ async fn export_image_layers(window: tauri::Window) -> Result<DockerImageInfo, S
             id: layer_id,
             name: format!("Layer {}", current_layer),
             command,
+            size_bytes: parse_docker_size(&size),
             size,
             createdAt: created,
+            partial: None,
             files,
         });
     }
 
-    println!("Layer export completed successfully");
-    update_status("Layer export completed successfully", 1.0, true, None);
+    // Index every layer's files so searches don't have to re-read the image
+    progress.begin("index", "Indexing layer files...");
+    match search_index::build_search_index(task, image_id).and_then(|index| {
+        search_index::save_search_index(layers_dir, &index)?;
+        Ok(index)
+    }) {
+        Ok(index) => {
+            info!("File index written");
+            // Layers are numbered like the index, layer_1 is the newest
+            for (layer_id, error) in index.partial_layers() {
+                let layer = layer_id
+                    .strip_prefix("layer_")
+                    .and_then(|number| number.parse::<usize>().ok())
+                    .and_then(|number| layers.get_mut(number.checked_sub(1)?));
+                if let Some(layer) = layer {
+                    warn!("{} is partial: {}", layer_id, error);
+                    layer.partial = Some(error);
+                }
+            }
+        }
+        Err(e) => {
+            task.check_cancelled()?;
+            warn!("Failed to build file index: {}", e);
+        }
+    }
+
+    info!("Layer export completed successfully");
+    progress.complete("Layer export completed successfully");
 
     // Return the image info with layers
-    println!("Returning image info with {} layers", layers.len());
+    debug!("Returning image info with {} layers", layers.len());
+    let size_bytes = layers.iter().map(|layer| layer.size_bytes).sum();
     Ok(DockerImageInfo {
-        id: image_id,
-        name: "layers:latest".to_string(),
+        id: image_id.to_string(),
+        name: session.reference().to_string(),
         created: "Now".to_string(), // This would be more accurate in a real implementation
-        size: "Unknown".to_string(), // This would be more accurate in a real implementation
+        size: format_size(size_bytes),
+        size_bytes,
         layers,
+        config: None,
     })
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 async fn inspect_docker_image(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
     image_name: String,
     tag: Option<String>,
-) -> Result<DockerImageInfo, String> {
+) -> Result<DockerImageInfo, LayersError> {
     // First, check if the image exists
-    let output = Command::new("docker")
+    let output = docker_command()
         .args(["image", "ls", &image_name, "--format", "{{.ID}}"])
         .output()
         .map_err(|e| format!("Failed to execute docker command: {}", e))?;
@@ -440,360 +657,360 @@ async fn inspect_docker_image(
     let image_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
 
     if image_id.is_empty() {
-        // Pull the image if it doesn't exist
-        let pull_output = Command::new("docker")
-            .args(["pull", &image_name])
-            .output()
-            .map_err(|e| format!("Failed to pull docker image: {}", e))?;
-
-        if !pull_output.status.success() {
-            return Err(format!(
-                "Failed to pull image: {}",
-                String::from_utf8_lossy(&pull_output.stderr)
-            ));
-        }
+        // Pull the image if it doesn't exist, reporting progress as pull_progress events
+        let task = tasks.start();
+        let result = pull::pull_task(&window, &task, &image_name);
+        finish_task(&window, &tasks, &task);
+        result?;
     }
 
     // Tag the image with 'layers' if requested
     if let Some(tag_value) = tag {
-        let tag_name = format!("{}:{}", image_name, tag_value);
-        let _ = Command::new("docker")
-            .args(["tag", &image_name, &tag_name])
-            .output()
+        // The new tag replaces the one in the name, "nginx:1.25" becomes
+        // "nginx:<tag>" and "nginx@sha256:..." drops the digest
+        let (repository, _) = pull::split_reference(&image_name);
+        let repository = repository
+            .split_once('@')
+            .map_or(repository, |(name, _)| name);
+        let tag_name = format!("{}:{}", repository, tag_value);
+        let output = audit::docker(&["tag", &image_name, &tag_name])
             .map_err(|e| format!("Failed to tag image: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to tag image as {}: {}",
+                tag_name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
     }
 
-    // Get image details
-    let inspect_output = Command::new("docker")
-        .args(["image", "inspect", &image_name])
-        .output()
-        .map_err(|e| format!("Failed to inspect docker image: {}", e))?;
+    let inspect = inspect_image(&image_name)?;
+    let history = get_image_history(&image_name)?;
+    let diff_ids = image_diff_ids(&image_name)?;
 
-    if !inspect_output.status.success() {
-        return Err(format!(
-            "Failed to inspect image: {}",
-            String::from_utf8_lossy(&inspect_output.stderr)
-        ));
-    }
-
-    // For now, return mock data
-    // In a real implementation, you would parse the JSON output from docker inspect
     Ok(DockerImageInfo {
-        id: "sha256:d123456789".to_string(),
+        id: inspect["Id"].as_str().unwrap_or_default().to_string(),
         name: image_name,
-        created: "2025-03-14T04:25:00Z".to_string(),
-        size: "258.2 MB".to_string(),
-        layers: vec![
-            DockerLayer {
-                id: "sha256:a123456789".to_string(),
-                name: "Base Layer".to_string(),
-                command: "FROM node:16-alpine".to_string(),
-                size: "5.8 MB".to_string(),
-                createdAt: "2025-03-14T04:23:45Z".to_string(),
-                files: vec![
-                    FileItem {
-                        name: "etc".to_string(),
-                        file_type: "directory".to_string(),
-                        path: "/etc".to_string(),
-                        size: None,
-                    },
-                    FileItem {
-                        name: "usr".to_string(),
-                        file_type: "directory".to_string(),
-                        path: "/usr".to_string(),
-                        size: None,
-                    },
-                    FileItem {
-                        name: "bin".to_string(),
-                        file_type: "directory".to_string(),
-                        path: "/bin".to_string(),
-                        size: None,
-                    },
-                ],
-            },
-            DockerLayer {
-                id: "sha256:b123456789".to_string(),
-                name: "Dependencies".to_string(),
-                command: "RUN npm install".to_string(),
-                size: "250 MB".to_string(),
-                createdAt: "2025-03-14T04:24:15Z".to_string(),
-                files: vec![
-                    FileItem {
-                        name: "node_modules".to_string(),
-                        file_type: "directory".to_string(),
-                        path: "/app/node_modules".to_string(),
-                        size: None,
-                    },
-                    FileItem {
-                        name: "package-lock.json".to_string(),
-                        file_type: "file".to_string(),
-                        path: "/app/package-lock.json".to_string(),
-                        size: Some("250 KB".to_string()),
-                    },
-                ],
-            },
-            DockerLayer {
-                id: "sha256:c123456789".to_string(),
-                name: "App".to_string(),
-                command: "COPY . .".to_string(),
-                size: "2.4 MB".to_string(),
-                createdAt: "2025-03-14T04:24:45Z".to_string(),
-                files: vec![
-                    FileItem {
-                        name: "index.js".to_string(),
-                        file_type: "file".to_string(),
-                        path: "/app/index.js".to_string(),
-                        size: Some("4.5 KB".to_string()),
-                    },
-                    FileItem {
-                        name: "app.js".to_string(),
-                        file_type: "file".to_string(),
-                        path: "/app/app.js".to_string(),
-                        size: Some("12.3 KB".to_string()),
-                    },
-                    FileItem {
-                        name: "public".to_string(),
-                        file_type: "directory".to_string(),
-                        path: "/app/public".to_string(),
-                        size: None,
-                    },
-                ],
-            },
-        ],
+        created: inspect["Created"].as_str().unwrap_or_default().to_string(),
+        size: format_size(inspect["Size"].as_u64().unwrap_or(0)),
+        size_bytes: inspect["Size"].as_u64().unwrap_or(0),
+        layers: history_layers(&history, &diff_ids),
+        config: Some(run_config(&inspect["Config"])),
     })
 }
 
-#[tauri::command]
-async fn analyze_dockerfile(_content: String) -> Result<DockerfileAnalysis, String> {
-    // In a real implementation, you would analyze the Dockerfile content
-    // For now, return mock data
+// History entries newest first, like the layer_N directories of an export,
+// see layers_core::docker::history_layers
+pub(crate) fn history_layers(history: &[HistoryEntry], diff_ids: &[String]) -> Vec<DockerLayer> {
+    layers_core::docker::history_layers(history, diff_ids)
+        .into_iter()
+        .enumerate()
+        .map(|(index, layer)| DockerLayer {
+            id: layer.id,
+            name: format!("Layer {}", index + 1),
+            command: layer.created_by,
+            size: layer.size,
+            size_bytes: layer.size_bytes,
+            createdAt: layer.created,
+            partial: None,
+            files: Vec::new(),
+        })
+        .collect()
+}
+
+pub(crate) fn run_config(config: &serde_json::Value) -> ImageRunConfig {
+    let strings = |value: &serde_json::Value| -> Vec<String> {
+        value
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.as_str())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let mut exposed_ports: Vec<String> = config["ExposedPorts"]
+        .as_object()
+        .map(|ports| ports.keys().cloned().collect())
+        .unwrap_or_default();
+    exposed_ports.sort();
+
+    ImageRunConfig {
+        entrypoint: strings(&config["Entrypoint"]),
+        cmd: strings(&config["Cmd"]),
+        env: strings(&config["Env"]),
+        working_dir: config["WorkingDir"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        user: config["User"].as_str().unwrap_or_default().to_string(),
+        exposed_ports,
+        labels: config["Labels"]
+            .as_object()
+            .map(|labels| {
+                labels
+                    .iter()
+                    .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+// Shared by analyze_dockerfile and the Dockerfile watcher
+pub(crate) fn dockerfile_analysis(
+    content: &str,
+    context_dir: Option<&Path>,
+) -> Result<DockerfileAnalysis, String> {
+    let dockerfile = Dockerfile::parse(content);
+    if dockerfile.instructions.is_empty() {
+        return Err("No instructions found in the Dockerfile".to_string());
+    }
+
+    // Layers of the base image count towards the limit too, if it's pulled
+    let base_layers = dockerfile
+        .base_image_reference()
+        .and_then(|base| image_diff_ids(base).ok())
+        .map(|diff_ids| diff_ids.len())
+        .unwrap_or(0);
+    let mut suggestions = dockerfile.optimize_suggestions();
+    suggestions.extend(dockerfile.layer_limit_suggestion(base_layers));
+
+    // COPY and ADD are measured in the build context if one is given
+    let estimates = estimate_layer_sizes(&dockerfile, context_dir);
+
     Ok(DockerfileAnalysis {
-        layer_impact: vec![
-            DockerfileAnalysisItem {
-                line_number: 1,
-                instruction: "FROM alpine:latest".to_string(),
-                impact: "Creates base layer from Alpine Linux (~5MB)".to_string(),
-            },
-            DockerfileAnalysisItem {
-                line_number: 4,
-                instruction: "WORKDIR /app".to_string(),
-                impact: "Sets working directory for the container".to_string(),
-            },
-            DockerfileAnalysisItem {
-                line_number: 7,
-                instruction: "ENV".to_string(),
-                impact: "Sets environment variables (negligible size impact)".to_string(),
-            },
-        ],
-        optimization_suggestions: vec![
-            DockerfileOptimizationSuggestion {
-                title: "Combine RUN commands".to_string(),
-                description: "Consider combining the user creation and curl installation into a single RUN command to reduce layers.".to_string(),
-            },
-            DockerfileOptimizationSuggestion {
-                title: "Use multi-stage builds".to_string(),
-                description: "For real applications, consider multi-stage builds to keep the final image as small as possible.".to_string(),
-            },
-        ],
+        layer_impact: dockerfile
+            .analyze_layer_impact()
+            .into_iter()
+            .zip(estimates)
+            .map(
+                |((line_number, instruction, impact), estimate)| DockerfileAnalysisItem {
+                    line_number: line_number as u32,
+                    instruction,
+                    impact,
+                    predicted_mb: estimate.bytes.map(|bytes| bytes as f64 / (1024.0 * 1024.0)),
+                    prediction_basis: estimate.basis,
+                },
+            )
+            .collect(),
+        optimization_suggestions: suggestions
+            .into_iter()
+            .map(|(title, description)| DockerfileOptimizationSuggestion { title, description })
+            .collect(),
+        cache_stability: dockerfile.cache_stability(),
+        stages: dockerfile.stages,
     })
 }
 
 #[tauri::command]
-async fn cleanup_layers_images() -> Result<String, String> {
-    // Remove all images tagged with 'layers'
-    let output = Command::new("docker")
-        .args(["image", "rm", "layers:latest"])
-        .output()
+#[tracing::instrument(skip_all)]
+async fn analyze_dockerfile(
+    content: String,
+    context_dir: Option<String>,
+) -> Result<DockerfileAnalysis, LayersError> {
+    dockerfile_analysis(&content, context_dir.as_deref().map(Path::new)).map_err(LayersError::from)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+async fn cleanup_layers_images(undo: tauri::State<'_, UndoHistory>) -> Result<String, LayersError> {
+    // Older versions tagged the selected image as layers:latest, remove the leftover tag
+    let action = undo::untag_action("layers:latest");
+    let output = audit::docker(&["image", "rm", "layers:latest"])
         .map_err(|e| format!("Failed to execute docker command: {}", e))?;
 
     if !output.status.success() {
         return Err(format!(
             "Failed to remove images: {}",
             String::from_utf8_lossy(&output.stderr)
-        ));
+        )
+        .into());
     }
+    undo.record("Removed tag layers:latest".to_string(), action);
 
     Ok("Successfully removed all images tagged with 'layers'".to_string())
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 async fn export_single_layer(
     window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    cache: tauri::State<'_, ExtractionCache>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+) -> Result<Vec<FileItem>, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let result = export_single_layer_task(&window, &task, &cache, &session, layer_id).await;
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
+
+// Exporting the container filesystem is the slow part, unless it's cached
+const EXPORT_LAYER_PHASES: &[Phase] = &[
+    phase("resolve", 0.05),
+    phase("fetch", 0.6),
+    phase("index", 0.2),
+    phase("analyze", 0.15),
+];
+
+async fn export_single_layer_task(
+    window: &tauri::Window,
+    task: &Task,
+    cache: &ExtractionCache,
+    session: &ImageSession,
     layer_id: String,
 ) -> Result<Vec<FileItem>, String> {
-    println!(
+    info!(
         "Exporting layer: '{}', length: {}",
         layer_id,
         layer_id.len()
     );
 
-    // Create a function to update status
-    let update_status = |message: &str, progress: f32, is_complete: bool, error: Option<String>| {
-        let _ = window.emit(
-            "task_status",
-            TaskStatus {
-                message: message.to_string(),
-                progress,
-                is_complete,
-                error,
-            },
-        );
-    };
+    let progress = PhaseProgress::new(window, task.id, EXPORT_LAYER_PHASES);
+    progress.begin("resolve", &format!("Exporting layer {}...", &layer_id));
 
-    update_status(
-        &format!("Exporting layer {}...", &layer_id),
-        0.0,
-        false,
-        None,
-    );
-
-    // First, ensure the /tmp/layers directory exists
-    let layers_dir = Path::new("/tmp/layers");
-    println!("Layers directory: {:?}", layers_dir);
+    // First, ensure the session directory exists
+    let layers_dir = session.dir();
+    debug!("Layers directory: {:?}", layers_dir);
 
     if !layers_dir.exists() {
-        println!("Creating layers directory: {:?}", layers_dir);
+        debug!("Creating layers directory: {:?}", layers_dir);
         fs::create_dir_all(layers_dir)
-            .map_err(|e| format!("Failed to create /tmp/layers directory: {}", e))?;
+            .map_err(|e| format!("Failed to create layers directory: {}", e))?;
     }
+    let image_id = session.image_id();
 
     // Use a generic layer name
     let layer_dir_name = "current_layer";
-    println!("Using generic layer directory name: {}", layer_dir_name);
+    debug!("Using generic layer directory name: {}", layer_dir_name);
 
     // Create a directory for this layer
     let layer_dir = layers_dir.join(layer_dir_name);
-    println!("Layer directory: {:?}", layer_dir);
+    debug!("Layer directory: {:?}", layer_dir);
 
     // Clean up any existing files for this layer
     if layer_dir.exists() {
-        println!("Cleaning up existing layer directory: {:?}", layer_dir);
+        debug!("Cleaning up existing layer directory: {:?}", layer_dir);
         fs::remove_dir_all(&layer_dir)
             .map_err(|e| format!("Failed to clean up layer directory: {}", e))?;
     }
 
-    println!("Creating layer directory: {:?}", layer_dir);
+    debug!("Creating layer directory: {:?}", layer_dir);
     fs::create_dir_all(&layer_dir)
         .map_err(|e| format!("Failed to create layer directory: {}", e))?;
+    task.track_path(&layer_dir);
 
-    update_status("Extracting layer contents...", 0.3, false, None);
+    progress.begin("fetch", "Extracting layer contents...");
 
     // Create a temporary container from the layer to extract its contents
-    println!("Creating temporary container from layer");
+    info!("Creating temporary container from layer");
 
-    // First, check if the image with tag layers:latest exists
-    let image_check = Command::new("docker")
-        .args(["images", "layers:latest", "-q"])
-        .output()
-        .map_err(|e| format!("Failed to check for layers:latest image: {}", e))?;
+    // Sessions export side by side, each needs its own container
+    let container_name = &format!("layer_export_{}", session.id());
+    let tar_path = layer_dir.join("fs.tar");
 
-    let image_id = String::from_utf8_lossy(&image_check.stdout)
-        .trim()
-        .to_string();
-    if image_id.is_empty() {
-        let error =
-            "No image found with tag layers:latest. Please select an image first.".to_string();
-        println!("Error: {}", error);
-        update_status("Error: No image found", 0.0, true, Some(error.clone()));
-        return Err(error);
-    }
+    // The same layers always export to the same filesystem, reuse a previous export
+    let cache_key = image_diff_ids(image_id)
+        .ok()
+        .and_then(|diff_ids| cache::chain_id(&diff_ids));
+    let restored = match &cache_key {
+        Some(key) => cache.restore(key, &tar_path).unwrap_or_else(|e| {
+            warn!("{}", e);
+            false
+        }),
+        None => false,
+    };
 
-    // Create a temporary container from the image
-    let container_name = "layer_export_container";
-    println!("Creating container: {}", container_name);
+    if !restored {
+        // Create a temporary container from the image
+        info!("Creating container: {}", container_name);
 
-    // Remove any existing container with the same name
-    let _ = Command::new("docker")
-        .args(["rm", "-f", &container_name])
-        .output();
+        // Remove any existing container with the same name
+        let _ = audit::docker(&["rm", "-f", container_name]);
 
-    // Create a new container but don't start it
-    let create_output = Command::new("docker")
-        .args(["create", "--name", &container_name, "layers:latest", "true"])
-        .output()
-        .map_err(|e| format!("Failed to create container: {}", e))?;
+        // Create a new container but don't start it
+        let create_output = task
+            .run(docker_command().args(["create", "--name", container_name, image_id, "true"]))
+            .map_err(|e| format!("Failed to create container: {}", e))?;
+        task.track_container(container_name);
 
-    if !create_output.status.success() {
-        let error = format!(
-            "Failed to create container: {}",
-            String::from_utf8_lossy(&create_output.stderr)
-        );
-        println!("Error: {}", error);
-        update_status("Error creating container", 0.2, true, Some(error.clone()));
-        return Err(error);
-    }
+        if !create_output.status.success() {
+            let error = format!(
+                "Failed to create container: {}",
+                String::from_utf8_lossy(&create_output.stderr)
+            );
+            error!("{}", error);
+            progress.fail(&error);
+            return Err(error);
+        }
 
-    update_status("Extracting layer contents...", 0.3, false, None);
+        progress.update("Extracting layer contents...", 0.2);
 
-    // Export the container's filesystem
-    let tar_path = layer_dir.join("fs.tar");
-    println!("Exporting container filesystem to: {:?}", tar_path);
+        // Export the container's filesystem
+        info!("Exporting container filesystem to: {:?}", tar_path);
+
+        let export_output = task
+            .run(docker_command().args([
+                "export",
+                "-o",
+                &tar_path.to_string_lossy(),
+                container_name,
+            ]))
+            .map_err(|e| format!("Failed to export container: {}", e))?;
 
-    let export_output = Command::new("docker")
-        .args(["export", "-o", &tar_path.to_string_lossy(), &container_name])
-        .output()
-        .map_err(|e| format!("Failed to export container: {}", e))?;
+        if !export_output.status.success() {
+            let error = format!(
+                "Failed to export container: {}",
+                String::from_utf8_lossy(&export_output.stderr)
+            );
+            error!("{}", error);
+            progress.fail(&error);
+            return Err(error);
+        }
 
-    if !export_output.status.success() {
-        let error = format!(
-            "Failed to export container: {}",
-            String::from_utf8_lossy(&export_output.stderr)
-        );
-        println!("Error: {}", error);
-        update_status("Error exporting container", 0.4, true, Some(error.clone()));
-        return Err(error);
+        if let Some(key) = &cache_key {
+            if let Err(e) = cache.store(key, &tar_path) {
+                warn!("{}", e);
+            }
+        }
     }
 
     // Create the extract directory but don't extract everything yet
     let extract_dir = layer_dir.join("fs");
-    println!("Creating extract directory: {:?}", extract_dir);
+    debug!("Creating extract directory: {:?}", extract_dir);
 
     // Ensure the extract directory exists
     fs::create_dir_all(&extract_dir)
         .map_err(|e| format!("Failed to create extract directory: {}", e))?;
 
-    update_status("Scanning filesystem...", 0.5, false, None);
-
-    // Instead of extracting everything, just list the contents of the tar file
-    let list_output = Command::new("tar")
-        .args(["-tf", &tar_path.to_string_lossy()])
-        .output()
-        .map_err(|e| format!("Failed to list tar contents: {}", e))?;
-
-    if !list_output.status.success() {
-        let error = format!(
-            "Failed to list tar contents: {}",
-            String::from_utf8_lossy(&list_output.stderr)
-        );
-        println!("Error: {}", error);
-        update_status("Error scanning filesystem", 0.6, true, Some(error.clone()));
+    progress.begin("index", "Scanning filesystem...");
+
+    // Extract only the top-level entries to save time and space, the rest is
+    // extracted as directories are opened
+    let report = |transfer: &Transfer| progress.update_transfer("Scanning filesystem...", transfer);
+    if let Err(error) = tar_extract::extract_entries(
+        task,
+        &tar_path,
+        &extract_dir,
+        true,
+        |path| path.components().count() == 1,
+        Some(&report),
+    ) {
+        error!("{}", error);
+        progress.fail(&error);
         return Err(error);
     }
+    exec_safety::strip_execute_bits(&extract_dir);
 
-    // Extract only the top-level directories to save time and space
-    let _extract_top_level = Command::new("tar")
-        .args([
-            "-xf",
-            &tar_path.to_string_lossy(),
-            "-C",
-            &extract_dir.to_string_lossy(),
-            "--no-recursion",
-            "--wildcards",
-            "*",
-            "bin",
-            "etc",
-            "usr",
-            "var",
-            "home",
-            "root",
-            "lib",
-            "opt",
-            "sbin",
-            "srv",
-            "tmp",
-        ])
-        .output()
-        .map_err(|e| format!("Failed to extract top-level directories: {}", e))?;
+    // Paths prefetched from the previously exported filesystem are stale
+    prefetch::clear_prefetched(&layer_dir);
 
     // Create a file to track which directories have been extracted
     let lazy_info_path = layer_dir.join("lazy_info.json");
@@ -862,20 +1079,18 @@ async fn export_single_layer(
         .map_err(|e| format!("Failed to write lazy info file: {}", e))?;
 
     // Clean up the container
-    println!("Removing container");
-    let _ = Command::new("docker")
-        .args(["rm", "-f", &container_name])
-        .output();
+    info!("Removing container");
+    let _ = audit::docker(&["rm", "-f", container_name]);
 
     // Get layer information
-    update_status("Getting layer information...", 0.7, false, None);
+    progress.begin("analyze", "Getting layer information...");
 
     // Get layer command from history
-    println!("Getting layer command from history");
-    let history_output = Command::new("docker")
+    debug!("Getting layer command from history");
+    let history_output = docker_command()
         .args([
             "history",
-            "layers:latest",
+            image_id,
             "--no-trunc",
             "--format",
             "{{.ID}}|{{.CreatedSince}}|{{.Size}}|{{.CreatedBy}}",
@@ -912,7 +1127,7 @@ async fn export_single_layer(
                     layer_size = parts[2].to_string();
                     layer_command = parts[3].to_string();
 
-                    println!("Found layer {} in history: ID={}", num, actual_layer_id);
+                    debug!("Found layer {} in history: ID={}", num, actual_layer_id);
                 }
             }
         }
@@ -930,7 +1145,7 @@ async fn export_single_layer(
     }
 
     // Write layer info to a file
-    println!("Writing layer info to file");
+    debug!("Writing layer info to file");
     fs::write(
         layer_dir.join("layer_info.txt"),
         format!(
@@ -941,11 +1156,11 @@ async fn export_single_layer(
     .map_err(|e| format!("Failed to write layer info file: {}", e))?;
 
     // Write command to a file
-    println!("Writing command to file");
+    debug!("Writing command to file");
     fs::write(layer_dir.join("command.txt"), &layer_command)
         .map_err(|e| format!("Failed to write command file: {}", e))?;
 
-    update_status("Scanning filesystem...", 0.8, false, None);
+    progress.update("Scanning filesystem...", 0.5);
 
     // Read the directory and create FileItem objects
     let mut files = Vec::new();
@@ -958,25 +1173,44 @@ async fn export_single_layer(
             .join("layer_info.txt")
             .to_string_lossy()
             .to_string(),
+        exact_path: None,
         size: Some("1KB".to_string()),
+        size_bytes: Some(1024),
+        link_target: None,
+        broken_link: false,
+        metadata: None,
+        attributes: None,
+        owner: None,
     });
 
     files.push(FileItem {
         name: "command.txt".to_string(),
         file_type: "file".to_string(),
         path: layer_dir.join("command.txt").to_string_lossy().to_string(),
+        exact_path: None,
         size: Some("512B".to_string()),
+        size_bytes: Some(512),
+        link_target: None,
+        broken_link: false,
+        metadata: None,
+        attributes: None,
+        owner: None,
     });
 
     // Add the tar file as a special file
+    let tar_size = fs::metadata(&tar_path).map(|m| m.len()).unwrap_or(0);
     files.push(FileItem {
         name: "fs.tar".to_string(),
         file_type: "file".to_string(),
         path: tar_path.to_string_lossy().to_string(),
-        size: Some(format!(
-            "{:.1}MB",
-            fs::metadata(&tar_path).map(|m| m.len()).unwrap_or(0) as f64 / (1024.0 * 1024.0)
-        )),
+        exact_path: None,
+        size: Some(format!("{:.1}MB", tar_size as f64 / (1024.0 * 1024.0))),
+        size_bytes: Some(tar_size),
+        link_target: None,
+        broken_link: false,
+        metadata: None,
+        attributes: None,
+        owner: None,
     });
 
     // Function to recursively read a directory and add files to the list
@@ -984,28 +1218,36 @@ async fn export_single_layer(
         dir: &Path,
         files: &mut Vec<FileItem>,
         base_path: &Path,
+        links: Option<&LinkIndex>,
         max_depth: usize,
         current_depth: usize,
     ) -> Result<(), String> {
-        println!("Reading directory: {:?} (depth: {})", dir, current_depth);
+        debug!("Reading directory: {:?} (depth: {})", dir, current_depth);
 
         // Check if directory exists
         if !dir.exists() {
-            println!("Directory does not exist: {:?}", dir);
+            debug!("Directory does not exist: {:?}", dir);
             return Ok(()); // Skip this directory but don't fail
         }
 
         // If we've reached the max depth, just add the directory but don't scan its contents
         if current_depth >= max_depth && max_depth > 0 {
-            println!("Reached max depth at {:?}, not scanning contents", dir);
+            debug!("Reached max depth at {:?}, not scanning contents", dir);
 
             // Add a placeholder to indicate there are more files
             if let Some(name) = dir.file_name() {
                 files.push(FileItem {
-                    name: name.to_string_lossy().to_string(),
+                    name: display_path(Path::new(name)),
                     file_type: "directory".to_string(),
-                    path: dir.to_string_lossy().to_string(),
+                    path: display_path(dir),
+                    exact_path: exact_path(dir),
                     size: Some("...".to_string()), // Indicate there's more to load
+                    size_bytes: None,
+                    link_target: None,
+                    broken_link: false,
+                    metadata: None,
+                    attributes: None,
+                    owner: None,
                 });
             }
 
@@ -1015,7 +1257,7 @@ async fn export_single_layer(
         let entries = match fs::read_dir(dir) {
             Ok(entries) => entries,
             Err(e) => {
-                println!("Error reading directory {}: {}", dir.display(), e);
+                warn!("Error reading directory {}: {}", dir.display(), e);
                 return Ok(()); // Skip this directory but don't fail
             }
         };
@@ -1024,61 +1266,25 @@ async fn export_single_layer(
             let entry = match entry {
                 Ok(entry) => entry,
                 Err(e) => {
-                    println!("Error reading directory entry: {}", e);
+                    warn!("Error reading directory entry: {}", e);
                     continue; // Skip this entry but continue with others
                 }
             };
 
             let path = entry.path();
-            let metadata = match fs::metadata(&path) {
-                Ok(metadata) => metadata,
-                Err(e) => {
-                    println!("Error reading file metadata for {:?}: {}", path, e);
-                    continue; // Skip this entry but continue with others
-                }
+            let Some((file_item, is_dir)) = scanned_file_item(&path, base_path, links) else {
+                continue; // Skip this entry but continue with others
             };
 
-            let file_name = match path.file_name() {
-                Some(name) => name.to_string_lossy().to_string(),
-                None => {
-                    println!("Invalid file name for {:?}", path);
-                    continue; // Skip this entry but continue with others
-                }
-            };
-
-            let file_type = if metadata.is_dir() {
-                "directory"
-            } else {
-                "file"
-            };
-
-            let size = if metadata.is_file() {
-                let size_bytes = metadata.len();
-                if size_bytes < 1024 {
-                    Some(format!("{}B", size_bytes))
-                } else if size_bytes < 1024 * 1024 {
-                    Some(format!("{:.1}KB", size_bytes as f64 / 1024.0))
-                } else {
-                    Some(format!("{:.1}MB", size_bytes as f64 / (1024.0 * 1024.0)))
-                }
-            } else {
-                None
-            };
-
-            println!("Adding file: {} ({})", file_name, file_type);
-            files.push(FileItem {
-                name: file_name,
-                file_type: file_type.to_string(),
-                path: path.to_string_lossy().to_string(),
-                size,
-            });
+            debug!("Adding file: {} ({})", file_item.name, file_item.file_type);
+            files.push(file_item);
 
-            // Recursively process subdirectories
-            if metadata.is_dir() && (max_depth == 0 || current_depth < max_depth) {
+            // Recursively process subdirectories, symlinks aren't followed
+            if is_dir && (max_depth == 0 || current_depth < max_depth) {
                 if let Err(e) =
-                    read_dir_recursive(&path, files, base_path, max_depth, current_depth + 1)
+                    read_dir_recursive(&path, files, base_path, links, max_depth, current_depth + 1)
                 {
-                    println!("Warning: {}", e);
+                    warn!("{}", e);
                     // Continue anyway, this is not critical
                 }
             }
@@ -1087,75 +1293,101 @@ async fn export_single_layer(
         Ok(())
     }
 
+    // Links come from the tar headers, their targets may not be extracted yet
+    let links = LinkIndex::from_tar(&tar_path)
+        .inspect_err(|e| warn!("{}", e))
+        .ok();
+
     // Read the extracted filesystem directory with a depth limit
-    println!("Reading extracted filesystem directory: {:?}", extract_dir);
-    if let Err(e) = read_dir_recursive(&extract_dir, &mut files, &extract_dir, 2, 0) {
-        println!("Warning: {}", e);
+    debug!("Reading extracted filesystem directory: {:?}", extract_dir);
+    if let Err(e) = read_dir_recursive(&extract_dir, &mut files, &extract_dir, links.as_ref(), 2, 0)
+    {
+        warn!("{}", e);
         // Continue anyway, we still have the layer info and command files
     }
 
-    update_status(&format!("Layer exported successfully"), 1.0, true, None);
+    progress.complete("Layer exported successfully");
 
-    println!("Successfully exported layer");
-    println!("Returning {} files", files.len());
+    info!("Successfully exported layer");
+    debug!("Returning {} files", files.len());
     Ok(files)
 }
 
 #[tauri::command]
-async fn extract_directory(dir_path: String, layer_id: String) -> Result<Vec<FileItem>, String> {
-    println!("Extracting directory: {}", dir_path);
+#[tracing::instrument(skip_all)]
+async fn extract_directory(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    dir_path: String,
+    layer_id: String,
+    sort: Option<SizeOrder>,
+) -> Result<Vec<FileItem>, LayersError> {
+    debug!("Extracting directory {} of {}", dir_path, layer_id);
 
     // Ensure the directory path is valid
-    let path = Path::new(&dir_path);
+    let path = &path_from_escaped(&dir_path);
     if !path.exists() {
-        return Err(format!("Directory does not exist: {}", dir_path));
+        return Err(format!("Directory does not exist: {}", dir_path).into());
     }
 
     // Get the layer directory
-    let layers_dir = Path::new("/tmp/layers");
+    let session = session.get(session_id.as_deref())?;
     let layer_dir_name = "current_layer";
-    let layer_dir = layers_dir.join(layer_dir_name);
+    let layer_dir = session.dir().join(layer_dir_name);
     let tar_path = layer_dir.join("fs.tar");
 
     // Check if the tar file exists
     if !tar_path.exists() {
-        return Err(format!("Tar file does not exist: {:?}", tar_path));
+        return Err(format!("Tar file does not exist: {:?}", tar_path).into());
     }
 
     // Get the relative path from the extract directory
     let extract_dir = layer_dir.join("fs");
     let rel_path = match path.strip_prefix(&extract_dir) {
-        Ok(p) => p.to_string_lossy().to_string(),
+        Ok(p) => p.to_path_buf(),
         Err(_) => {
             // If the path is not under the extract directory, it might be a direct path like "etc" or "usr"
             match path.file_name() {
-                Some(name) => name.to_string_lossy().to_string(),
-                None => return Err("Invalid directory path".to_string()),
+                Some(name) => PathBuf::from(name),
+                None => return Err("Invalid directory path".into()),
             }
         }
     };
 
-    println!("Relative path: {}", rel_path);
-
-    // Extract the specific directory from the tar file with all its contents
-    let extract_output = Command::new("tar")
-        .args([
-            "-xf",
-            &tar_path.to_string_lossy(),
-            "-C",
-            &extract_dir.to_string_lossy(),
-            &format!("{}*", if rel_path.is_empty() { "" } else { &rel_path }),
-        ])
-        .output()
-        .map_err(|e| format!("Failed to extract directory: {}", e))?;
+    debug!("Relative path: {}", display_path(&rel_path));
 
-    if !extract_output.status.success() {
-        let error = format!(
-            "Failed to extract directory: {}",
-            String::from_utf8_lossy(&extract_output.stderr)
+    // Directories prefetched after opening the image are already on disk
+    if prefetch::is_prefetched(&layer_dir, &entry_relative_path(&rel_path)) {
+        debug!("Directory was prefetched, skipping extraction");
+    } else {
+        // Extract the specific directory from the tar file with all its
+        // contents. Paths are compared as components so names that aren't
+        // UTF-8 still match.
+        let rel_path = tar_extract::container_relative(&rel_path);
+        let task = tasks.start();
+        let extraction = tar_extract::extract_entries(
+            &task,
+            &tar_path,
+            &extract_dir,
+            true,
+            |path| path.starts_with(&rel_path),
+            None,
         );
-        println!("Error: {}", error);
-        return Err(error);
+        tasks.finish(task.id);
+        let extraction = extraction.map_err(|e| {
+            LayersError::extraction_failed(&tar_path, format!("Failed to extract directory: {}", e))
+        })?;
+        // Names the host can't create, e.g. over its 255 byte limit, are
+        // skipped so the rest of the directory can still be browsed
+        if !extraction.skipped.is_empty() {
+            warn!(
+                "Skipped {} entries of {}",
+                extraction.skipped.len(),
+                display_path(&rel_path)
+            );
+        }
+        exec_safety::strip_execute_bits(&extract_dir.join(&rel_path));
     }
 
     // Read the directory contents recursively
@@ -1165,19 +1397,20 @@ async fn extract_directory(dir_path: String, layer_id: String) -> Result<Vec<Fil
         dir: &Path,
         files: &mut Vec<FileItem>,
         base_path: &Path,
+        links: Option<&LinkIndex>,
     ) -> Result<(), String> {
-        println!("Reading directory: {:?}", dir);
+        debug!("Reading directory: {:?}", dir);
 
         // Check if directory exists
         if !dir.exists() {
-            println!("Directory does not exist: {:?}", dir);
+            debug!("Directory does not exist: {:?}", dir);
             return Ok(()); // Skip this directory but don't fail
         }
 
         let entries = match fs::read_dir(dir) {
             Ok(entries) => entries,
             Err(e) => {
-                println!("Error reading directory {}: {}", dir.display(), e);
+                warn!("Error reading directory {}: {}", dir.display(), e);
                 return Ok(()); // Skip this directory but don't fail
             }
         };
@@ -1186,59 +1419,23 @@ async fn extract_directory(dir_path: String, layer_id: String) -> Result<Vec<Fil
             let entry = match entry {
                 Ok(entry) => entry,
                 Err(e) => {
-                    println!("Error reading directory entry: {}", e);
+                    warn!("Error reading directory entry: {}", e);
                     continue; // Skip this entry but continue with others
                 }
             };
 
             let path = entry.path();
-            let metadata = match fs::metadata(&path) {
-                Ok(metadata) => metadata,
-                Err(e) => {
-                    println!("Error reading file metadata for {:?}: {}", path, e);
-                    continue; // Skip this entry but continue with others
-                }
-            };
-
-            let file_name = match path.file_name() {
-                Some(name) => name.to_string_lossy().to_string(),
-                None => {
-                    println!("Invalid file name for {:?}", path);
-                    continue; // Skip this entry but continue with others
-                }
-            };
-
-            let file_type = if metadata.is_dir() {
-                "directory"
-            } else {
-                "file"
-            };
-
-            let size = if metadata.is_file() {
-                let size_bytes = metadata.len();
-                if size_bytes < 1024 {
-                    Some(format!("{}B", size_bytes))
-                } else if size_bytes < 1024 * 1024 {
-                    Some(format!("{:.1}KB", size_bytes as f64 / 1024.0))
-                } else {
-                    Some(format!("{:.1}MB", size_bytes as f64 / (1024.0 * 1024.0)))
-                }
-            } else {
-                None
+            let Some((file_item, is_dir)) = scanned_file_item(&path, base_path, links) else {
+                continue; // Skip this entry but continue with others
             };
 
-            println!("Adding file: {} ({})", file_name, file_type);
-            files.push(FileItem {
-                name: file_name,
-                file_type: file_type.to_string(),
-                path: path.to_string_lossy().to_string(),
-                size,
-            });
+            debug!("Adding file: {} ({})", file_item.name, file_item.file_type);
+            files.push(file_item);
 
-            // Recursively process subdirectories
-            if metadata.is_dir() {
-                if let Err(e) = read_dir_recursive(&path, files, base_path) {
-                    println!("Warning: {}", e);
+            // Recursively process subdirectories, symlinks aren't followed
+            if is_dir {
+                if let Err(e) = read_dir_recursive(&path, files, base_path, links) {
+                    warn!("{}", e);
                     // Continue anyway, this is not critical
                 }
             }
@@ -1247,31 +1444,48 @@ async fn extract_directory(dir_path: String, layer_id: String) -> Result<Vec<Fil
         Ok(())
     }
 
+    // Links come from the tar headers, their targets may not be extracted yet
+    let links = LinkIndex::from_tar(&tar_path)
+        .inspect_err(|e| warn!("{}", e))
+        .ok();
+
     // Read the extracted directory recursively
-    read_dir_recursive(path, &mut files, &extract_dir)
+    read_dir_recursive(path, &mut files, &extract_dir, links.as_ref())
         .map_err(|e| format!("Failed to read directory contents: {}", e))?;
 
-    println!(
+    info!(
         "Successfully extracted directory, found {} files",
         files.len()
     );
+    sort_by_size(&mut files, sort, |file| file.size_bytes);
     Ok(files)
 }
 
+// Flat listing kept for callers that still build the tree themselves,
+// file_tree::get_layer_files returns it already nested
 #[tauri::command]
-async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
-    println!("Getting files for layer: '{}'", layer_id);
+#[tracing::instrument(skip_all)]
+async fn get_layer_files_flat(
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+    sort: Option<SizeOrder>,
+) -> Result<Vec<FileItem>, LayersError> {
+    debug!("Getting files for layer: '{}'", layer_id);
 
     // Use a generic layer name
     let layer_dir_name = "current_layer";
-    println!("Using generic layer directory name: {}", layer_dir_name);
+    debug!("Using generic layer directory name: {}", layer_dir_name);
 
-    let layer_dir = Path::new("/tmp/layers").join(layer_dir_name);
-    println!("Layer directory: {:?}", layer_dir);
+    let layer_dir = session
+        .get(session_id.as_deref())?
+        .dir()
+        .join(layer_dir_name);
+    debug!("Layer directory: {:?}", layer_dir);
 
     if !layer_dir.exists() {
-        println!("Layer directory does not exist: {:?}", layer_dir);
-        return Err(format!("Layer directory does not exist"));
+        debug!("Layer directory does not exist: {:?}", layer_dir);
+        return Err("Layer directory does not exist".into());
     }
 
     // Read the directory and create FileItem objects
@@ -1285,14 +1499,28 @@ async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
             .join("layer_info.txt")
             .to_string_lossy()
             .to_string(),
+        exact_path: None,
         size: Some("1KB".to_string()),
+        size_bytes: Some(1024),
+        link_target: None,
+        broken_link: false,
+        metadata: None,
+        attributes: None,
+        owner: None,
     });
 
     files.push(FileItem {
         name: "command.txt".to_string(),
         file_type: "file".to_string(),
         path: layer_dir.join("command.txt").to_string_lossy().to_string(),
+        exact_path: None,
         size: Some("512B".to_string()),
+        size_bytes: Some(512),
+        link_target: None,
+        broken_link: false,
+        metadata: None,
+        attributes: None,
+        owner: None,
     });
 
     // Check if we have a tar file
@@ -1300,7 +1528,7 @@ async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
     let extract_dir = layer_dir.join("fs");
 
     if tar_path.exists() {
-        println!("Found tar file, scanning contents");
+        debug!("Found tar file, scanning contents");
 
         // Create the extract directory if it doesn't exist
         if !extract_dir.exists() {
@@ -1308,75 +1536,63 @@ async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
                 .map_err(|e| format!("Failed to create extract directory: {}", e))?;
         }
 
-        // List all entries in the tar file
-        let list_output = Command::new("tar")
-            .args(["-tf", &tar_path.to_string_lossy()])
-            .output()
+        // List all entries from the tar headers. Names are kept as bytes,
+        // tar -tf output splits names with newlines and quotes non-UTF-8 ones.
+        let tar_file = fs::File::open(&tar_path)
+            .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+        let mut archive = tar::Archive::new(tar_file);
+        let entries = archive
+            .entries()
             .map_err(|e| format!("Failed to list tar contents: {}", e))?;
-
-        if !list_output.status.success() {
-            let error = format!(
-                "Failed to list tar contents: {}",
-                String::from_utf8_lossy(&list_output.stderr)
-            );
-            println!("Error: {}", error);
-            return Err(error);
-        }
-
-        // Parse the output to get all file paths
-        let tar_contents = String::from_utf8_lossy(&list_output.stdout);
-        let mut path_map: std::collections::HashMap<String, bool> =
+        let mut path_map: std::collections::HashMap<PathBuf, bool> =
             std::collections::HashMap::new();
 
         // First pass: collect all paths and mark them as files or directories
-        for line in tar_contents.lines() {
-            let path = line.trim();
-            if path.is_empty() {
-                continue;
-            }
-
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
             // Skip special entries like "./" or "."
-            if path == "./" || path == "." {
+            let path: PathBuf = entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?
+                .components()
+                .filter(|c| matches!(c, std::path::Component::Normal(_)))
+                .collect();
+            if path.as_os_str().is_empty() {
                 continue;
             }
 
-            // Determine if it's a directory (ends with /)
-            let is_dir = path.ends_with('/');
-            let clean_path = if is_dir {
-                path.trim_end_matches('/')
-            } else {
-                path
-            };
-
             // Add to map
-            path_map.insert(clean_path.to_string(), is_dir);
+            path_map.insert(path.clone(), entry.header().entry_type().is_dir());
 
             // Also add all parent directories
-            let mut parent_path = Path::new(clean_path);
+            let mut parent_path = path.as_path();
             while let Some(parent) = parent_path.parent() {
-                if parent.to_string_lossy() == "." || parent.to_string_lossy().is_empty() {
+                if parent.as_os_str().is_empty() {
                     break;
                 }
-                path_map.insert(parent.to_string_lossy().to_string(), true);
+                path_map.insert(parent.to_path_buf(), true);
                 parent_path = parent;
             }
         }
 
+        // tar -tf doesn't tell links apart, the headers do
+        let links = LinkIndex::from_tar(&tar_path)
+            .inspect_err(|e| warn!("{}", e))
+            .ok();
+
         // Second pass: create FileItem objects for all paths
         for (path, is_dir) in path_map {
-            // Skip root
-            if path.is_empty() || path == "." {
-                continue;
-            }
-
             let full_path = extract_dir.join(&path);
-            let name = match Path::new(&path).file_name() {
-                Some(name) => name.to_string_lossy().to_string(),
+            let name = match path.file_name() {
+                Some(name) => display_path(Path::new(name)),
                 None => continue,
             };
 
-            // Check if the file/directory has been extracted
-            let exists = full_path.exists();
+            let relative_path = entry_relative_path(&path);
+            let link = links.as_ref().and_then(|links| links.get(&relative_path));
+
+            // Check if the file/directory has been extracted, without following links
+            let exists = fs::symlink_metadata(&full_path).is_ok();
 
             // For directories, check if they need to be loaded
             let needs_loading = is_dir && !exists;
@@ -1384,7 +1600,7 @@ async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
             // For files, only include if they exist or their parent directory needs loading
             if !is_dir && !exists {
                 // If the file doesn't exist, check if its parent directory needs loading
-                if let Some(parent) = Path::new(&path).parent() {
+                if let Some(parent) = path.parent() {
                     let parent_path = extract_dir.join(parent);
                     if !parent_path.exists() {
                         // Parent directory needs to be loaded first, so skip this file for now
@@ -1393,11 +1609,17 @@ async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
                 }
             }
 
-            // Get size for existing files
-            let size = if !is_dir && exists {
-                match fs::metadata(&full_path) {
-                    Ok(metadata) => {
-                        let size_bytes = metadata.len();
+            // Get size for existing files, a link's size is that of its target
+            let size_bytes = if link.is_none() && !is_dir && exists {
+                fs::metadata(&full_path).ok().map(|metadata| metadata.len())
+            } else {
+                None
+            };
+            let size = if link.is_some() {
+                None
+            } else if !is_dir && exists {
+                match size_bytes {
+                    Some(size_bytes) => {
                         if size_bytes < 1024 {
                             Some(format!("{}B", size_bytes))
                         } else if size_bytes < 1024 * 1024 {
@@ -1406,7 +1628,7 @@ async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
                             Some(format!("{:.1}MB", size_bytes as f64 / (1024.0 * 1024.0)))
                         }
                     }
-                    Err(_) => Some("unknown".to_string()),
+                    None => Some("unknown".to_string()),
                 }
             } else if needs_loading {
                 Some("click to load".to_string())
@@ -1417,9 +1639,31 @@ async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
             // Create the FileItem
             let file_item = FileItem {
                 name,
-                file_type: if is_dir { "directory" } else { "file" }.to_string(),
-                path: full_path.to_string_lossy().to_string(),
+                file_type: match link.map(|link| link.kind) {
+                    Some(LinkKind::Symlink) => "symlink",
+                    Some(LinkKind::Hardlink) => "hardlink",
+                    None if is_dir => "directory",
+                    None => "file",
+                }
+                .to_string(),
+                path: display_path(&full_path),
+                exact_path: exact_path(&full_path),
                 size,
+                size_bytes,
+                link_target: link.map(|link| link.target.clone()),
+                broken_link: link.is_some()
+                    && links
+                        .as_ref()
+                        .is_some_and(|links| links.is_broken(&relative_path)),
+                metadata: links
+                    .as_ref()
+                    .and_then(|links| links.metadata(&relative_path))
+                    .cloned(),
+                attributes: links
+                    .as_ref()
+                    .and_then(|links| links.attributes(&relative_path))
+                    .cloned(),
+                owner: links.as_ref().and_then(|links| links.owner(&relative_path)),
             };
 
             files.push(file_item);
@@ -1431,19 +1675,20 @@ async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
             dir: &Path,
             files: &mut Vec<FileItem>,
             base_path: &Path,
+            links: Option<&LinkIndex>,
         ) -> Result<(), String> {
-            println!("Reading directory: {:?}", dir);
+            debug!("Reading directory: {:?}", dir);
 
             // Check if directory exists
             if !dir.exists() {
-                println!("Directory does not exist: {:?}", dir);
+                debug!("Directory does not exist: {:?}", dir);
                 return Ok(()); // Skip this directory but don't fail
             }
 
             let entries = match fs::read_dir(dir) {
                 Ok(entries) => entries,
                 Err(e) => {
-                    println!("Error reading directory {}: {}", dir.display(), e);
+                    warn!("Error reading directory {}: {}", dir.display(), e);
                     return Ok(()); // Skip this directory but don't fail
                 }
             };
@@ -1452,59 +1697,23 @@ async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
                 let entry = match entry {
                     Ok(entry) => entry,
                     Err(e) => {
-                        println!("Error reading directory entry: {}", e);
+                        warn!("Error reading directory entry: {}", e);
                         continue; // Skip this entry but continue with others
                     }
                 };
 
                 let path = entry.path();
-                let metadata = match fs::metadata(&path) {
-                    Ok(metadata) => metadata,
-                    Err(e) => {
-                        println!("Error reading file metadata for {:?}: {}", path, e);
-                        continue; // Skip this entry but continue with others
-                    }
-                };
-
-                let file_name = match path.file_name() {
-                    Some(name) => name.to_string_lossy().to_string(),
-                    None => {
-                        println!("Invalid file name for {:?}", path);
-                        continue; // Skip this entry but continue with others
-                    }
-                };
-
-                let file_type = if metadata.is_dir() {
-                    "directory"
-                } else {
-                    "file"
+                let Some((file_item, is_dir)) = scanned_file_item(&path, base_path, links) else {
+                    continue; // Skip this entry but continue with others
                 };
 
-                let size = if metadata.is_file() {
-                    let size_bytes = metadata.len();
-                    if size_bytes < 1024 {
-                        Some(format!("{}B", size_bytes))
-                    } else if size_bytes < 1024 * 1024 {
-                        Some(format!("{:.1}KB", size_bytes as f64 / 1024.0))
-                    } else {
-                        Some(format!("{:.1}MB", size_bytes as f64 / (1024.0 * 1024.0)))
-                    }
-                } else {
-                    None
-                };
+                debug!("Adding file: {} ({})", file_item.name, file_item.file_type);
+                files.push(file_item);
 
-                println!("Adding file: {} ({})", file_name, file_type);
-                files.push(FileItem {
-                    name: file_name,
-                    file_type: file_type.to_string(),
-                    path: path.to_string_lossy().to_string(),
-                    size,
-                });
-
-                // Recursively process subdirectories
-                if metadata.is_dir() {
-                    if let Err(e) = read_dir_recursive(&path, files, base_path) {
-                        println!("Warning: {}", e);
+                // Recursively process subdirectories, symlinks aren't followed
+                if is_dir {
+                    if let Err(e) = read_dir_recursive(&path, files, base_path, links) {
+                        warn!("{}", e);
                         // Continue anyway, this is not critical
                     }
                 }
@@ -1514,68 +1723,102 @@ async fn get_layer_files(layer_id: String) -> Result<Vec<FileItem>, String> {
         }
 
         // Read the layer directory recursively
-        println!("Reading layer directory: {:?}", layer_dir);
-        if let Err(e) = read_dir_recursive(&layer_dir, &mut files, &layer_dir) {
-            println!("Warning: {}", e);
+        debug!("Reading layer directory: {:?}", layer_dir);
+        if let Err(e) = read_dir_recursive(&layer_dir, &mut files, &layer_dir, None) {
+            warn!("{}", e);
             // Continue anyway, we might still have some files
         }
     }
 
-    println!("Returning {} files", files.len());
+    sort_by_size(&mut files, sort, |file| file.size_bytes);
+    debug!("Returning {} files", files.len());
     Ok(files)
 }
 
-#[tauri::command]
-async fn read_layer_file(file_path: String) -> Result<String, String> {
-    println!("Reading file content from: {}", file_path);
-
-    // Check if the file exists
-    let path = Path::new(&file_path);
-    if !path.exists() {
-        return Err(format!("File does not exist: {}", file_path));
-    }
-
-    // Check if it's a file (not a directory)
-    let metadata =
-        fs::metadata(path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
-
-    if !metadata.is_file() {
-        return Err(format!("Path is not a file: {}", file_path));
-    }
+// FileItem for one entry of an extracted layer, and whether to descend into
+// it. Links are reported as links and never followed, so a link to a parent
+// directory can't loop and a link to a big file doesn't count its size.
+fn scanned_file_item(
+    path: &Path,
+    base_path: &Path,
+    links: Option<&LinkIndex>,
+) -> Option<(FileItem, bool)> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            warn!("Error reading file metadata for {:?}: {}", path, e);
+            return None;
+        }
+    };
 
-    // Check file size
-    let file_size = metadata.len();
-    if file_size > 10 * 1024 * 1024 {
-        // 10MB limit
-        return Err(format!(
-            "File is too large to display: {} ({} bytes)",
-            file_path, file_size
-        ));
-    }
+    let file_name = match path.file_name() {
+        Some(name) => display_path(Path::new(name)),
+        None => {
+            warn!("Invalid file name for {:?}", path);
+            return None;
+        }
+    };
 
-    // First read the file as bytes to check if it's binary
-    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let relative_path = path
+        .strip_prefix(base_path)
+        .map(entry_relative_path)
+        .unwrap_or_default();
+    // The tar headers know about hard links, the extracted files don't
+    let link = links.and_then(|links| links.get(&relative_path)).cloned();
+    let link = link.or_else(|| {
+        let target = fs::read_link(path).ok()?;
+        Some(LinkInfo {
+            kind: LinkKind::Symlink,
+            target: target.to_string_lossy().to_string(),
+        })
+    });
 
-    // Check if the file is likely binary by looking for null bytes or high concentration of non-ASCII characters
-    let is_likely_binary = is_binary_content(&bytes);
+    let file_type = match link.as_ref().map(|link| link.kind) {
+        Some(LinkKind::Symlink) => "symlink",
+        Some(LinkKind::Hardlink) => "hardlink",
+        None if metadata.is_dir() => "directory",
+        None => "file",
+    };
 
-    if is_likely_binary {
-        return Err(format!("Cannot display binary file: {}", file_path));
-    }
+    let broken_link = link.is_some()
+        && match links {
+            Some(links) => links.is_broken(&relative_path),
+            None => is_broken_on_disk(base_path, path),
+        };
 
-    // Convert bytes to string
-    match String::from_utf8(bytes) {
-        Ok(content) => {
-            println!(
-                "Successfully read file content, length: {} bytes",
-                content.len()
-            );
-            Ok(content)
+    let size_bytes = (link.is_none() && metadata.is_file()).then_some(metadata.len());
+    let size = if let Some(size_bytes) = size_bytes {
+        if size_bytes < 1024 {
+            Some(format!("{}B", size_bytes))
+        } else if size_bytes < 1024 * 1024 {
+            Some(format!("{:.1}KB", size_bytes as f64 / 1024.0))
+        } else {
+            Some(format!("{:.1}MB", size_bytes as f64 / (1024.0 * 1024.0)))
         }
-        Err(_) => Err(
-            "File contains invalid UTF-8 characters and cannot be displayed as text".to_string(),
-        ),
-    }
+    } else {
+        None
+    };
+
+    let file_item = FileItem {
+        name: file_name,
+        file_type: file_type.to_string(),
+        path: display_path(path),
+        exact_path: exact_path(path),
+        size,
+        size_bytes,
+        link_target: link.map(|link| link.target),
+        broken_link,
+        // The tar header has the owner the image was built with, extraction may not
+        metadata: links
+            .and_then(|links| links.metadata(&relative_path))
+            .cloned()
+            .or_else(|| FileMetadata::from_disk(&metadata)),
+        attributes: links
+            .and_then(|links| links.attributes(&relative_path))
+            .cloned(),
+        owner: links.and_then(|links| links.owner(&relative_path)),
+    };
+    Some((file_item, metadata.is_dir()))
 }
 
 // Helper function to determine if content is likely binary
@@ -1585,48 +1828,68 @@ fn is_binary_content(bytes: &[u8]) -> bool {
         return true;
     }
 
-    // Count non-ASCII characters
-    let non_ascii_count = bytes.iter().filter(|&&b| b > 127).count();
-
-    // If more than 30% of the first 1000 bytes are non-ASCII, consider it binary
+    // If too many of the first bytes are non-ASCII, consider it binary. The
+    // sample size and ratio come from layers.toml, 30% of 1000 by default.
+    let (sample_bytes, non_ascii_ratio) = config::binary_detection();
     if bytes.len() > 0 {
-        let sample_size = std::cmp::min(bytes.len(), 1000);
-        let ratio = non_ascii_count as f64 / sample_size as f64;
-        return ratio > 0.3;
+        let sample = &bytes[..std::cmp::min(bytes.len(), sample_bytes)];
+        let non_ascii_count = sample.iter().filter(|&&b| b > 127).count();
+        let ratio = non_ascii_count as f64 / sample.len() as f64;
+        return ratio > non_ascii_ratio;
     }
 
     false
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 async fn compare_layers(
     window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
     layer1_id: String,
     layer2_id: String,
-) -> Result<LayerDiff, String> {
-    println!("Comparing layers: {} and {}", layer1_id, layer2_id);
-
-    // Create a function to update status
-    let update_status = |message: &str, progress: f32, is_complete: bool, error: Option<String>| {
-        let _ = window.emit(
-            "task_status",
-            TaskStatus {
-                message: message.to_string(),
-                progress,
-                is_complete,
-                error,
-            },
-        );
+    fast_mode: Option<bool>,
+) -> Result<LayerDiff, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let hash_mode = if fast_mode.unwrap_or(false) {
+        HashMode::Fast
+    } else {
+        HashMode::Full
     };
 
-    update_status(
+    let task = tasks.start();
+    let result =
+        compare_layers_task(&window, &task, &session, layer1_id, layer2_id, hash_mode).await;
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
+
+const COMPARE_LAYERS_PHASES: &[Phase] = &[
+    phase("resolve", 0.05),
+    phase("fetch", 0.5),
+    phase("index", 0.4),
+    phase("analyze", 0.05),
+];
+
+async fn compare_layers_task(
+    window: &tauri::Window,
+    task: &Task,
+    session: &ImageSession,
+    layer1_id: String,
+    layer2_id: String,
+    hash_mode: HashMode,
+) -> Result<LayerDiff, String> {
+    info!("Comparing layers: {} and {}", layer1_id, layer2_id);
+
+    let progress = PhaseProgress::new(window, task.id, COMPARE_LAYERS_PHASES);
+    progress.begin(
+        "resolve",
         &format!(
             "Preparing to compare layers {} and {}...",
             &layer1_id, &layer2_id
         ),
-        0.0,
-        false,
-        None,
     );
 
     // Extract layer numbers from IDs
@@ -1641,42 +1904,16 @@ async fn compare_layers(
         .ok_or_else(|| "Invalid layer2_id format".to_string())?;
 
     // Ensure layer directories exist
-    let layers_dir = Path::new("/tmp/layers");
+    let layers_dir = session.dir();
 
-    // Check if we need to export the layers first
-    let layer1_dir = layers_dir.join(&layer1_id);
-    let layer2_dir = layers_dir.join(&layer2_id);
+    // Both sides are the filesystem as of their layer, built on first use
+    progress.begin("fetch", "Building layer filesystems...");
+    progress.update(&format!("Building layer {}...", layer1_num), 0.0);
+    ensure_layer_tar(task, session, &layer1_id)?;
+    progress.update(&format!("Building layer {}...", layer2_num), 0.3);
+    ensure_layer_tar(task, session, &layer2_id)?;
 
-    if !layer1_dir.exists() || !layer1_dir.join("fs.tar").exists() {
-        update_status(
-            &format!("Exporting layer {}...", &layer1_id),
-            0.1,
-            false,
-            None,
-        );
-
-        // Export the first layer
-        export_single_layer(window.clone(), layer1_id.clone()).await?;
-    }
-
-    if !layer2_dir.exists() || !layer2_dir.join("fs.tar").exists() {
-        update_status(
-            &format!("Exporting layer {}...", &layer2_id),
-            0.3,
-            false,
-            None,
-        );
-
-        // Export the second layer
-        export_single_layer(window.clone(), layer2_id.clone()).await?;
-    }
-
-    update_status(
-        "Creating temporary directories for comparison...",
-        0.5,
-        false,
-        None,
-    );
+    progress.update("Creating temporary directories for comparison...", 0.6);
 
     // Create temporary directories for each layer's filesystem
     let temp_dir = layers_dir.join("diff_temp");
@@ -1685,6 +1922,7 @@ async fn compare_layers(
             .map_err(|e| format!("Failed to clean up temp directory: {}", e))?;
     }
     fs::create_dir_all(&temp_dir).map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    task.track_path(&temp_dir);
 
     let layer1_extract_dir = temp_dir.join(format!("layer{}", layer1_num));
     let layer2_extract_dir = temp_dir.join(format!("layer{}", layer2_num));
@@ -1694,178 +1932,172 @@ async fn compare_layers(
     fs::create_dir_all(&layer2_extract_dir)
         .map_err(|e| format!("Failed to create layer2 extract directory: {}", e))?;
 
-    // Extract both layers' filesystems
-    update_status(
-        &format!("Extracting layer {}...", layer1_num),
-        0.6,
-        false,
-        None,
-    );
-    extract_layer_for_diff(layer1_id.clone(), &layer1_extract_dir)?;
-
-    update_status(
-        &format!("Extracting layer {}...", layer2_num),
+    // Extract both layers' filesystems in parallel
+    progress.update(
+        &format!("Extracting layers {} and {}...", layer1_num, layer2_num),
         0.7,
-        false,
-        None,
     );
-    extract_layer_for_diff(layer2_id.clone(), &layer2_extract_dir)?;
-
-    // Compute hashes for both layers
-    update_status(
+    let (layer1_extract, layer2_extract) = resources::install(|| {
+        rayon::join(
+            || extract_layer_for_diff(task, session, layer1_id.clone(), &layer1_extract_dir),
+            || extract_layer_for_diff(task, session, layer2_id.clone(), &layer2_extract_dir),
+        )
+    });
+    layer1_extract?;
+    layer2_extract?;
+
+    // Walk both trees first so the byte total covers both layers
+    let mut layer1_entries = Vec::new();
+    collect_hash_entries(
+        task,
+        &layer1_extract_dir,
+        &layer1_extract_dir,
+        &mut layer1_entries,
+    )?;
+    let mut layer2_entries = Vec::new();
+    collect_hash_entries(
+        task,
+        &layer2_extract_dir,
+        &layer2_extract_dir,
+        &mut layer2_entries,
+    )?;
+    let total_bytes = layer1_entries
+        .iter()
+        .chain(&layer2_entries)
+        .map(|(_, _, _, size)| hash_mode.bytes_read(*size))
+        .sum();
+    let transfer = Mutex::new(Transfer::new(Some(total_bytes)));
+
+    // Compute hashes for both layers, reporting the bytes hashed so far
+    progress.begin(
+        "index",
         &format!("Computing hashes for layer {}...", layer1_num),
-        0.8,
-        false,
-        None,
-    );
-    let layer1_hashes = compute_directory_hashes(&layer1_extract_dir)?;
-
-    update_status(
-        &format!("Computing hashes for layer {}...", layer2_num),
-        0.9,
-        false,
-        None,
     );
-    let layer2_hashes = compute_directory_hashes(&layer2_extract_dir)?;
+    let layer1_hashes =
+        compute_directory_hashes(task, layer1_entries, hash_mode, &transfer, &|transfer| {
+            progress.update_transfer(
+                &format!("Computing hashes for layer {}...", layer1_num),
+                transfer,
+            );
+        })?;
+    let layer2_hashes =
+        compute_directory_hashes(task, layer2_entries, hash_mode, &transfer, &|transfer| {
+            progress.update_transfer(
+                &format!("Computing hashes for layer {}...", layer2_num),
+                transfer,
+            );
+        })?;
 
     // Compare the hashes to find differences
-    update_status("Comparing layer contents...", 0.95, false, None);
+    task.check_cancelled()?;
+    progress.begin("analyze", "Comparing layer contents...");
     let diff = compare_hashes(layer1_hashes, layer2_hashes);
 
     // Clean up temporary directories
     let _ = fs::remove_dir_all(&temp_dir);
 
-    update_status("Comparison complete", 1.0, true, None);
+    progress.complete("Comparison complete");
     Ok(diff)
 }
 
-fn extract_layer_for_diff(layer_id: String, extract_dir: &Path) -> Result<(), String> {
-    // Get the layer directory
-    let layers_dir = Path::new("/tmp/layers");
-    let layer_dir_name = format!(
-        "layer_{}",
-        layer_id.strip_prefix("layer_").unwrap_or(&layer_id)
-    );
-    let layer_dir = layers_dir.join(&layer_dir_name);
-    let tar_path = layer_dir.join("fs.tar");
-
-    // Check if the tar file exists
-    if !tar_path.exists() {
-        println!(
-            "Tar file does not exist for layer {}, generating it...",
-            layer_id
-        );
-
-        // Create a temporary container from the image to extract its contents
-        // First, check if the image with tag layers:latest exists
-        let image_check = Command::new("docker")
-            .args(["images", "layers:latest", "-q"])
-            .output()
-            .map_err(|e| format!("Failed to check for layers:latest image: {}", e))?;
-
-        let image_id = String::from_utf8_lossy(&image_check.stdout)
-            .trim()
-            .to_string();
-        if image_id.is_empty() {
-            return Err(
-                "No image found with tag layers:latest. Please select an image first.".to_string(),
-            );
-        }
-
-        // Create a temporary container from the image
-        let container_name = format!("layer_diff_container_{}", layer_id);
-        println!("Creating container: {}", container_name);
-
-        // Remove any existing container with the same name
-        let _ = Command::new("docker")
-            .args(["rm", "-f", &container_name])
-            .output();
-
-        // Create a new container but don't start it
-        let create_output = Command::new("docker")
-            .args(["create", "--name", &container_name, "layers:latest", "true"])
-            .output()
-            .map_err(|e| format!("Failed to create container: {}", e))?;
-
-        if !create_output.status.success() {
-            let error = format!(
-                "Failed to create container: {}",
-                String::from_utf8_lossy(&create_output.stderr)
-            );
-            println!("Error: {}", error);
-            return Err(error);
-        }
-
-        // Ensure the layer directory exists
-        if !layer_dir.exists() {
-            fs::create_dir_all(&layer_dir)
-                .map_err(|e| format!("Failed to create layer directory: {}", e))?;
-        }
+fn extract_layer_for_diff(
+    task: &Task,
+    session: &ImageSession,
+    layer_id: String,
+    extract_dir: &Path,
+) -> Result<(), String> {
+    let tar_path = ensure_layer_tar(task, session, &layer_id)?;
 
-        // Export the container's filesystem
-        println!("Exporting container filesystem to: {:?}", tar_path);
+    // Extract the tar file to the extract directory
+    tar_extract::extract_entries(task, &tar_path, extract_dir, true, |_| true, None)
+        .map_err(|e| format!("Failed to extract layer {}: {}", layer_id, e))?;
+    exec_safety::strip_execute_bits(extract_dir);
 
-        let export_output = Command::new("docker")
-            .args(["export", "-o", &tar_path.to_string_lossy(), &container_name])
-            .output()
-            .map_err(|e| format!("Failed to export container: {}", e))?;
+    Ok(())
+}
 
-        if !export_output.status.success() {
-            let error = format!(
-                "Failed to export container: {}",
-                String::from_utf8_lossy(&export_output.stderr)
-            );
-            println!("Error: {}", error);
-            return Err(error);
+// Layer IDs name the layer directories of a session the commands work on,
+// "current_layer" for the selected layer or "layer_N" for a specific one
+pub(crate) fn layer_tar_path(
+    task: &Task,
+    session: &ImageSession,
+    layer_id: &str,
+) -> Result<PathBuf, String> {
+    if layer_id == "current_layer" {
+        let tar_path = session.dir().join("current_layer").join("fs.tar");
+        if !tar_path.exists() {
+            return Err(format!("Tar file does not exist: {:?}", tar_path));
         }
-
-        // Clean up the container
-        println!("Removing container");
-        let _ = Command::new("docker")
-            .args(["rm", "-f", &container_name])
-            .output();
+        return Ok(tar_path);
     }
+    ensure_layer_tar(task, session, layer_id)
+}
 
-    // Extract the tar file to the extract directory
-    let extract_output = Command::new("tar")
-        .args([
-            "-xf",
-            &tar_path.to_string_lossy(),
-            "-C",
-            &extract_dir.to_string_lossy(),
-        ])
-        .output()
-        .map_err(|e| format!("Failed to extract layer {}: {}", layer_id, e))?;
-
-    if !extract_output.status.success() {
-        return Err(format!(
-            "Failed to extract layer {}: {}",
-            layer_id,
-            String::from_utf8_lossy(&extract_output.stderr)
-        ));
-    }
+// Hashes the files of a tree walked by collect_hash_entries in parallel,
+// `transfer` counts the bytes read and is shared by every tree of the run
+fn compute_directory_hashes(
+    task: &Task,
+    entries: Vec<(PathBuf, String, bool, u64)>,
+    hash_mode: HashMode,
+    transfer: &Mutex<Transfer>,
+    on_progress: &(dyn Fn(&Transfer) + Sync),
+) -> Result<Vec<FileHash>, String> {
+    use rayon::prelude::*;
+
+    // Sized by the resource settings
+    resources::install(|| {
+        entries
+            .into_par_iter()
+            .map(|(path, rel_path, is_dir, size)| {
+                task.check_cancelled()?;
+
+                if is_dir {
+                    return Ok(FileHash {
+                        path: rel_path,
+                        hash: "directory".to_string(),
+                        algorithm: hash_mode.algorithm().to_string(),
+                        is_dir: true,
+                        size: 0,
+                    });
+                }
 
-    Ok(())
-}
+                let hash = compute_file_hash(&path, hash_mode, |bytes| {
+                    let mut transfer = transfer.lock().unwrap();
+                    transfer.advance(bytes);
+                    if transfer.due() {
+                        transfer.set_item(rel_path.clone());
+                        on_progress(&transfer);
+                    }
+                })?;
 
-fn compute_directory_hashes(dir: &Path) -> Result<Vec<FileHash>, String> {
-    let mut hashes = Vec::new();
-    compute_hashes_recursive(dir, dir, &mut hashes)?;
-    Ok(hashes)
+                Ok(FileHash {
+                    path: rel_path,
+                    hash,
+                    algorithm: hash_mode.algorithm().to_string(),
+                    is_dir: false,
+                    size,
+                })
+            })
+            .collect()
+    })
 }
 
-fn compute_hashes_recursive(
+// Collect (path, relative path, is_dir, size) for every directory and regular file
+fn collect_hash_entries(
+    task: &Task,
     base_dir: &Path,
     current_dir: &Path,
-    hashes: &mut Vec<FileHash>,
+    entries_out: &mut Vec<(std::path::PathBuf, String, bool, u64)>,
 ) -> Result<(), String> {
     let entries = fs::read_dir(current_dir)
         .map_err(|e| format!("Failed to read directory {:?}: {}", current_dir, e))?;
 
     for entry in entries {
+        task.check_cancelled()?;
         let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
         let path = entry.path();
-        let metadata = fs::metadata(&path)
+        // Links are skipped, a broken one would fail the walk and a loop never end it
+        let metadata = fs::symlink_metadata(&path)
             .map_err(|e| format!("Failed to read metadata for {:?}: {}", path, e))?;
 
         // Get relative path from base directory
@@ -1877,84 +2109,50 @@ fn compute_hashes_recursive(
 
         if metadata.is_dir() {
             // For directories, just record their existence and recurse
-            hashes.push(FileHash {
-                path: rel_path,
-                hash: "directory".to_string(),
-                is_dir: true,
-                size: 0,
-            });
-
-            compute_hashes_recursive(base_dir, &path, hashes)?;
+            entries_out.push((path.clone(), rel_path, true, 0));
+            collect_hash_entries(task, base_dir, &path, entries_out)?;
         } else if metadata.is_file() {
-            // For files, compute a hash
-            let hash = compute_file_hash(&path)?;
-
-            hashes.push(FileHash {
-                path: rel_path,
-                hash,
-                is_dir: false,
-                size: metadata.len(),
-            });
+            entries_out.push((path, rel_path, false, metadata.len()));
         }
     }
 
     Ok(())
 }
 
-fn compute_file_hash(path: &Path) -> Result<String, String> {
-    // For small files (< 1MB), hash the entire content
-    // For larger files, hash the first 4KB, last 4KB, and file size
-    // This is a compromise between accuracy and performance
-
-    let metadata =
-        fs::metadata(path).map_err(|e| format!("Failed to read metadata for {:?}: {}", path, e))?;
+// `on_read` is called with the bytes of the file read so far
+fn compute_file_hash(
+    path: &Path,
+    hash_mode: HashMode,
+    on_read: impl FnMut(u64),
+) -> Result<String, String> {
+    use std::io::{Read, Seek, SeekFrom};
 
-    let file_size = metadata.len();
+    const SAMPLE_SIZE: u64 = HashMode::SAMPLE_SIZE;
 
-    // Use a simple hash based on file size for very large files
-    if file_size > 10 * 1024 * 1024 {
-        // 10MB
-        return Ok(format!("size:{}", file_size));
-    }
-
-    // For smaller files, read portions of the file
     let mut file =
         fs::File::open(path).map_err(|e| format!("Failed to open file {:?}: {}", path, e))?;
-
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-    use std::io::{Read, Seek, SeekFrom};
-
-    let mut hasher = DefaultHasher::new();
-
-    // Hash file size
-    file_size.hash(&mut hasher);
-
-    // Hash first 4KB
-    let mut buffer = [0u8; 4096];
-    let bytes_read = file
-        .read(&mut buffer)
-        .map_err(|e| format!("Failed to read file {:?}: {}", path, e))?;
-
-    if bytes_read > 0 {
-        buffer[..bytes_read].hash(&mut hasher);
-    }
-
-    // If file is larger than 8KB, also hash last 4KB
-    if file_size > 8192 {
-        file.seek(SeekFrom::End(-4096))
-            .map_err(|e| format!("Failed to seek in file {:?}: {}", path, e))?;
-
-        let bytes_read = file
-            .read(&mut buffer)
+    let file_size = file
+        .metadata()
+        .map_err(|e| format!("Failed to read metadata for {:?}: {}", path, e))?
+        .len();
+
+    // Fast mode only samples the ends of large files, small files are always hashed in full
+    let digest = if hash_mode.samples(file_size) {
+        let mut head = vec![0u8; SAMPLE_SIZE as usize];
+        file.read_exact(&mut head)
             .map_err(|e| format!("Failed to read file {:?}: {}", path, e))?;
-
-        if bytes_read > 0 {
-            buffer[..bytes_read].hash(&mut hasher);
-        }
+        file.seek(SeekFrom::End(-(SAMPLE_SIZE as i64)))
+            .map_err(|e| format!("Failed to seek in file {:?}: {}", path, e))?;
+        let size = file_size.to_le_bytes();
+        let samples = ProgressReader::new(head.as_slice().chain(file), on_read);
+        digest_verify::sha256_reader(size.as_slice().chain(samples))
+    } else {
+        digest_verify::sha256_reader(ProgressReader::new(file, on_read))
     }
+    .map_err(|e| format!("Failed to read file {:?}: {}", path, e))?;
 
-    Ok(format!("{:x}", hasher.finish()))
+    // The algorithm is reported separately in FileHash
+    Ok(digest.trim_start_matches("sha256:").to_string())
 }
 
 fn compare_hashes(layer1_hashes: Vec<FileHash>, layer2_hashes: Vec<FileHash>) -> LayerDiff {
@@ -2014,22 +2212,173 @@ fn compare_hashes(layer1_hashes: Vec<FileHash>, layer2_hashes: Vec<FileHash>) ->
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .manage(TaskRegistry::default())
+        .manage(SessionState::default())
+        .manage(UndoHistory::default())
+        .manage(DockerfileWatchers::default())
+        .manage(LineIndexes::default())
+        .manage(RemoteImages::default())
+        .setup(|app| {
+            // Before anything else so every step of startup is logged
+            let logging = logging::init(&app.path().app_log_dir()?)?;
+            app.manage(logging);
+            // Read first, the cache and scan pool size themselves from it
+            config::init(app.path().app_config_dir()?.join("layers.toml"));
+            let cache_dir = app.path().app_data_dir()?.join("extraction_cache");
+            app.manage(ExtractionCache::new(cache_dir));
+            let analysis_dir = app.path().app_data_dir()?.join("analysis_cache");
+            app.manage(AnalysisCache::new(analysis_dir));
+            let resources_path = app.path().app_data_dir()?.join("resources.json");
+            app.manage(ResourceLimits::new(resources_path));
+            let scoring_path = app.path().app_data_dir()?.join("scoring.json");
+            app.manage(HealthScoring::new(scoring_path));
+            let trends_path = app.path().app_data_dir()?.join("trends.db");
+            app.manage(TrendStore::open(&trends_path)?);
+            ruleset_sync::init(
+                app.path().app_data_dir()?.join("ruleset_sync.json"),
+                app.path().app_data_dir()?.join("team_ruleset"),
+            );
+            let plugin_dir = app.path().app_data_dir()?.join("exporters");
+            app.manage(ExporterRegistry::new(plugin_dir));
+            let reports_dir = app.path().app_data_dir()?.join("reports");
+            app.manage(ReportStore::new(reports_dir));
+            audit::init(app.path().app_data_dir()?.join("audit.log"));
+            bookmarks::init(app.path().app_data_dir()?.join("bookmarks.json"));
+            tag_history::init(app.path().app_data_dir()?.join("tag_history.json"));
+            permissions::init(
+                app.handle().clone(),
+                app.path().app_data_dir()?.join("permissions.json"),
+            );
+            deep_link::listen(app)?;
+            Ok(())
+        })
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_deep_link::init())
         .invoke_handler(tauri::generate_handler![
             greet,
             inspect_docker_image,
             analyze_dockerfile,
+            dockerfile_lint::lint_dockerfile,
+            dockerfile_lint::list_lint_rules,
+            dockerfile_rewrite::optimize_dockerfile,
+            dockerfile_build::build_and_analyze_dockerfile,
+            dockerfile_watch::watch_dockerfile,
+            dockerfile_watch::unwatch_dockerfile,
             cleanup_layers_images,
             get_docker_images,
-            retag_image_for_layers,
+            session::select_image,
+            session::get_selected_image,
+            session::open_image_session,
+            session::close_image_session,
+            session::list_image_sessions,
             export_image_layers,
             export_single_layer,
-            get_layer_files,
-            read_layer_file,
+            get_layer_files_flat,
+            file_tree::get_layer_files,
+            file_preview::read_file_preview,
             extract_directory,
-            compare_layers
+            compare_layers,
+            pull::pull_image,
+            pull_time::estimate_pull_times,
+            clipboard::detect_clipboard_content,
+            cold_start::analyze_cold_start,
+            compare_presets::resolve_comparison_preset,
+            compare_presets::compare_with_preset,
+            config::get_settings,
+            config::update_settings,
+            digest_verify::verify_layer_digests,
+            tag_history::check_tag_mutation,
+            tag_history::list_tag_history,
+            tag_history::diff_tag_versions,
+            image_config::get_image_config,
+            timeline::get_image_timeline,
+            layer_mapping::map_dockerfile_to_layers,
+            layer_stats::get_layer_stats,
+            layer_stats::get_image_layers_page,
+            run_snippet::generate_run_snippets,
+            export::export_selected_paths,
+            export::archive_paths,
+            export::export_files,
+            rootfs_export::export_flattened_rootfs,
+            prefetch::prefetch_image_paths,
+            xattrs::scan_layer_attributes,
+            search_index::search_image_files,
+            selective_extract::extract_matching,
+            squash::simulate_squash,
+            ownership::check_file_ownership,
+            file_modes::find_setuid,
+            file_modes::find_world_writable,
+            grep::grep_layer,
+            grep::stop_grep,
+            content_index::build_content_index,
+            content_index::search_content_index,
+            remote_image::open_remote_image,
+            remote_image::list_remote_directory,
+            remote_image::read_remote_file,
+            services::inspect_services,
+            startup_check::check_image_startup,
+            runtime_writes::check_runtime_writes,
+            slim_proposal::propose_slim_image,
+            archive_loader::load_image_archive,
+            attribution::get_directory_attribution,
+            shell_lint::lint_shell_scripts,
+            cache::get_cache_stats,
+            cache::clear_cache,
+            cache::set_cache_compression,
+            analysis_cache::clear_analysis_cache,
+            resources::get_resource_settings,
+            resources::set_resource_settings,
+            provenance::audit_remote_downloads,
+            repo_trust::audit_package_repositories,
+            sbom::generate_sbom,
+            os_packages::list_layer_packages,
+            dependency_audit::audit_language_dependencies,
+            java_packages::audit_java_dependencies,
+            vulnerabilities::scan_image_vulnerabilities,
+            container_access::inspect_container_access,
+            secrets::detect_secrets,
+            size_breakdown::get_layer_size_breakdown,
+            tar_index::read_layer_file_range,
+            file_range::read_file_range,
+            file_range::get_line_index,
+            file_range::read_file_lines,
+            file_diff::diff_file_between_layers,
+            image_compare::compare_images,
+            audit::get_audit_log,
+            audit::export_audit_log,
+            bookmarks::add_bookmark,
+            bookmarks::list_bookmarks,
+            bookmarks::remove_bookmark,
+            logging::get_recent_logs,
+            logging::set_log_level,
+            permissions::get_docker_permissions,
+            permissions::set_docker_permission,
+            undo::list_undo_history,
+            undo::undo_action,
+            exporters::list_report_exporters,
+            exporters::reload_report_exporters,
+            reports::export_report,
+            reports::list_saved_reports,
+            reports::delete_saved_report,
+            reports::list_image_findings,
+            health::get_image_health,
+            health::get_scoring_weights,
+            health::set_scoring_weights,
+            ruleset_sync::get_ruleset_sync,
+            ruleset_sync::set_ruleset_sync,
+            ruleset_sync::sync_ruleset_now,
+            trends::get_image_trend,
+            trends::diff_trend_findings,
+            trends::export_trends,
+            trends::import_trends,
+            script_hook::run_user_script,
+            deep_link::create_finding_link,
+            deep_link::create_path_link,
+            deep_link::parse_deep_link,
+            deep_link::get_launch_deep_link,
+            tasks::cancel_task
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,180 @@
+use layers_core::layer_tar::entry_relative_path;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Component, Path};
+
+use crate::file_modes::FileMetadata;
+use crate::ownership::{capture_user_database, FileOwner, UserDatabase};
+use crate::xattrs::{read_entry_attributes, FileAttributes};
+
+// Longer chains are treated as loops
+pub(crate) const MAX_LINK_HOPS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LinkKind {
+    Symlink,
+    Hardlink,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct LinkInfo {
+    pub kind: LinkKind,
+    // As stored in the tar header
+    pub target: String,
+}
+
+// Parents the tar never lists on their own have neither
+#[derive(Default)]
+struct IndexedEntry {
+    link: Option<LinkInfo>,
+    metadata: Option<FileMetadata>,
+    attributes: Option<FileAttributes>,
+}
+
+// Paths of a layer tar with link, mode, owner and xattrs, read from the tar headers
+#[derive(Default)]
+pub(crate) struct LinkIndex {
+    entries: HashMap<String, IndexedEntry>,
+    users: UserDatabase,
+}
+
+impl LinkIndex {
+    pub(crate) fn from_tar(tar_path: &Path) -> Result<Self, String> {
+        let file = File::open(tar_path)
+            .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+        let mut archive = tar::Archive::new(file);
+        let mut entries = HashMap::new();
+        let mut users = UserDatabase::default();
+        for entry in archive
+            .entries()
+            .map_err(|e| format!("Failed to list tar contents: {}", e))?
+        {
+            let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+            let path = entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?;
+            let path = entry_relative_path(&path);
+            let entry_type = entry.header().entry_type();
+            let kind = if entry_type.is_symlink() {
+                Some(LinkKind::Symlink)
+            } else if entry_type.is_hard_link() {
+                Some(LinkKind::Hardlink)
+            } else {
+                None
+            };
+            let link = kind.and_then(|kind| {
+                let target = entry.link_name().ok()??;
+                Some(LinkInfo {
+                    kind,
+                    target: target.to_string_lossy().to_string(),
+                })
+            });
+            // Parents the tar never lists on their own still exist
+            let mut parent = Path::new(&path).parent();
+            while let Some(dir) = parent.filter(|dir| !dir.as_os_str().is_empty()) {
+                entries
+                    .entry(dir.to_string_lossy().to_string())
+                    .or_default();
+                parent = dir.parent();
+            }
+            let metadata = Some(FileMetadata::from_header(entry.header()));
+            let attributes = read_entry_attributes(&mut entry)
+                .map_err(|e| format!("Failed to read PAX header: {}", e))?;
+            capture_user_database(&mut users, &path, &mut entry)?;
+            entries.insert(
+                path,
+                IndexedEntry {
+                    link,
+                    metadata,
+                    attributes,
+                },
+            );
+        }
+        Ok(LinkIndex { entries, users })
+    }
+
+    // Link at a path relative to the root of the layer
+    pub(crate) fn get(&self, path: &str) -> Option<&LinkInfo> {
+        self.entries.get(path)?.link.as_ref()
+    }
+
+    // Mode and owner at a path relative to the root of the layer
+    pub(crate) fn metadata(&self, path: &str) -> Option<&FileMetadata> {
+        self.entries.get(path)?.metadata.as_ref()
+    }
+
+    // uid/gid at a path relative to the root of the layer, with their names
+    pub(crate) fn owner(&self, path: &str) -> Option<FileOwner> {
+        Some(
+            self.entries
+                .get(path)?
+                .metadata
+                .as_ref()?
+                .owner(&self.users),
+        )
+    }
+
+    // Capabilities, SELinux label and other xattrs at a path relative to the
+    // root of the layer
+    pub(crate) fn attributes(&self, path: &str) -> Option<&FileAttributes> {
+        self.entries.get(path)?.attributes.as_ref()
+    }
+
+    // Whether the link at `path` leads nowhere, a missing target or a loop
+    pub(crate) fn is_broken(&self, path: &str) -> bool {
+        let mut current = path.to_string();
+        for _ in 0..MAX_LINK_HOPS {
+            match self.entries.get(&current).map(|entry| &entry.link) {
+                None => return true,
+                Some(None) => return false,
+                Some(Some(link)) => current = resolve_link(&current, link),
+            }
+        }
+        true
+    }
+}
+
+// Link target relative to the image root, absolute symlinks point into the image
+pub(crate) fn resolve_link(path: &str, link: &LinkInfo) -> String {
+    let target = Path::new(&link.target);
+    let mut resolved: Vec<String> = match link.kind {
+        LinkKind::Symlink if !target.is_absolute() => {
+            let mut parent: Vec<String> = path.split('/').map(str::to_string).collect();
+            parent.pop();
+            parent
+        }
+        _ => Vec::new(),
+    };
+    for component in target.components() {
+        match component {
+            Component::Normal(name) => resolved.push(name.to_string_lossy().to_string()),
+            // ".." stops at the root, like it does in the container
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            _ => {}
+        }
+    }
+    resolved.join("/")
+}
+
+// LinkIndex::is_broken for a filesystem extracted to `root`
+pub(crate) fn is_broken_on_disk(root: &Path, path: &Path) -> bool {
+    let Ok(mut current) = path.strip_prefix(root).map(entry_relative_path) else {
+        return false;
+    };
+    for _ in 0..MAX_LINK_HOPS {
+        let full_path = root.join(&current);
+        match fs::read_link(&full_path) {
+            Ok(target) => {
+                let link = LinkInfo {
+                    kind: LinkKind::Symlink,
+                    target: target.to_string_lossy().to_string(),
+                };
+                current = resolve_link(&current, &link);
+            }
+            Err(_) => return fs::symlink_metadata(&full_path).is_err(),
+        }
+    }
+    true
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Registry};
+
+use crate::error::LayersError;
+
+// Log files are named layers.<date>.log, one per day
+const LOG_FILE_PREFIX: &str = "layers";
+const LOG_FILE_SUFFIX: &str = "log";
+// Older files are deleted when a new day starts
+const MAX_LOG_FILES: usize = 7;
+const DEFAULT_RECENT_LINES: usize = 500;
+
+// Log subscriber, managed as Tauri state so the level can be changed
+pub struct Logging {
+    dir: PathBuf,
+    level: reload::Handle<LevelFilter, Registry>,
+    // Flushes the file writer when the app exits
+    _guard: WorkerGuard,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecentLogs {
+    directory: String,
+    // Oldest first
+    lines: Vec<String>,
+}
+
+// Log to stdout and to daily files in `dir`, at info level until changed
+pub(crate) fn init(dir: &Path) -> Result<Logging, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)
+        .map_err(|e| format!("Failed to open log directory {:?}: {}", dir, e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let (filter, level) = reload::Layer::new(LevelFilter::INFO);
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(fmt::layer().with_writer(writer).with_ansi(false))
+        .try_init()
+        .map_err(|e| format!("Failed to set up logging: {}", e))?;
+    info!("Logging to {:?}", dir);
+    Ok(Logging {
+        dir: dir.to_path_buf(),
+        level,
+        _guard: guard,
+    })
+}
+
+fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX)
+                })
+        })
+        .collect();
+    // The date in the name sorts them oldest first
+    files.sort();
+    files
+}
+
+// The last lines written to the log files, to attach to bug reports
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_recent_logs(
+    logging: tauri::State<'_, Logging>,
+    lines: Option<usize>,
+) -> Result<RecentLogs, LayersError> {
+    let wanted = lines.unwrap_or(DEFAULT_RECENT_LINES);
+    let mut recent: Vec<String> = Vec::new();
+    for file in log_files(&logging.dir).iter().rev() {
+        if recent.len() >= wanted {
+            break;
+        }
+        let content = fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read log file {:?}: {}", file, e))?;
+        let mut file_lines: Vec<String> = content.lines().map(String::from).collect();
+        file_lines.append(&mut recent);
+        recent = file_lines;
+    }
+    let skip = recent.len().saturating_sub(wanted);
+    Ok(RecentLogs {
+        directory: logging.dir.to_string_lossy().to_string(),
+        lines: recent.split_off(skip),
+    })
+}
+
+// Change what gets logged while the app runs, e.g. "debug" while
+// reproducing a bug. One of off, error, warn, info, debug or trace.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_log_level(
+    logging: tauri::State<'_, Logging>,
+    level: String,
+) -> Result<(), LayersError> {
+    let filter: LevelFilter = level
+        .parse()
+        .map_err(|_| format!("Invalid log level: {}", level))?;
+    logging
+        .level
+        .modify(|current| *current = filter)
+        .map_err(|e| format!("Failed to set log level: {}", e))?;
+    info!("Log level set to {}", filter);
+    Ok(())
+}
@@ -0,0 +1,613 @@
+use layers_core::layer_tar::entry_relative_path;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::info;
+
+use crate::analysis_cache::AnalysisCache;
+use crate::error::LayersError;
+use crate::exec_safety;
+use crate::search_index::{read_saved_layers, SavedLayer};
+use crate::session::SessionState;
+use crate::tasks::{Task, TaskRegistry};
+
+pub(crate) const DPKG_STATUS_PATH: &str = "var/lib/dpkg/status";
+pub(crate) const APK_INSTALLED_PATH: &str = "lib/apk/db/installed";
+// rpm 4.16+ keeps its database in sqlite, older releases use BerkeleyDB
+pub(crate) const RPM_SQLITE_PATH: &str = "var/lib/rpm/rpmdb.sqlite";
+pub(crate) const RPM_BDB_PATH: &str = "var/lib/rpm/Packages";
+const DPKG_STATUS_D_PREFIX: &str = "var/lib/dpkg/status.d/";
+// Scratch copy of a layer's rpm database below the extraction root, sqlite
+// can't read from a stream
+const RPM_SCRATCH_FILE: &str = "layer_rpmdb.sqlite";
+// Bump when the database parsers change, cached package lists are keyed by it
+const ANALYZER_VERSION: u32 = 1;
+
+// rpm header tags, see rpmtag.h
+const RPMTAG_NAME: i32 = 1000;
+const RPMTAG_VERSION: i32 = 1001;
+const RPMTAG_RELEASE: i32 = 1002;
+const RPMTAG_EPOCH: i32 = 1003;
+const RPMTAG_LICENSE: i32 = 1014;
+const RPMTAG_ARCH: i32 = 1022;
+const RPMTAG_SOURCERPM: i32 = 1044;
+
+const RPM_INT32_TYPE: i32 = 4;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageFormat {
+    Deb,
+    Apk,
+    Rpm,
+}
+
+// A package recorded in one of the distribution package databases
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    pub architecture: Option<String>,
+    // Source package, e.g. "openssl" for libssl3
+    pub source: Option<String>,
+    pub license: Option<String>,
+    pub format: PackageFormat,
+}
+
+// dpkg status is a list of RFC 822 style stanzas, only installed packages count.
+// Distroless images keep one stanza per file in status.d without a Status field.
+pub(crate) fn parse_dpkg_status(content: &str) -> Vec<InstalledPackage> {
+    let mut packages = Vec::new();
+    for stanza in content.split("\n\n") {
+        let mut name = None;
+        let mut version = None;
+        let mut architecture = None;
+        let mut source = None;
+        let mut status: Option<String> = None;
+
+        for line in stanza.lines() {
+            // Continuation lines of multi-line fields start with whitespace
+            if line.starts_with(' ') || line.starts_with('\t') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key {
+                "Package" => name = Some(value),
+                "Version" => version = Some(value),
+                "Architecture" => architecture = Some(value),
+                // "Source: openssl (3.0.11-1)" when the versions differ
+                "Source" => source = value.split_whitespace().next().map(String::from),
+                "Status" => status = Some(value),
+                _ => {}
+            }
+        }
+
+        let installed = status.is_none_or(|s| s.ends_with(" installed"));
+        if let (Some(name), Some(version), true) = (name, version, installed) {
+            packages.push(InstalledPackage {
+                name,
+                version,
+                architecture,
+                source,
+                license: None,
+                format: PackageFormat::Deb,
+            });
+        }
+    }
+    packages
+}
+
+// apk's installed database uses single letter keys, one package per paragraph
+pub(crate) fn parse_apk_installed(content: &str) -> Vec<InstalledPackage> {
+    let mut packages = Vec::new();
+    for stanza in content.split("\n\n") {
+        let mut name = None;
+        let mut version = None;
+        let mut architecture = None;
+        let mut source = None;
+        let mut license = None;
+
+        for line in stanza.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.to_string();
+            match key {
+                "P" => name = Some(value),
+                "V" => version = Some(value),
+                "A" => architecture = Some(value),
+                "o" => source = Some(value),
+                "L" => license = Some(value),
+                _ => {}
+            }
+        }
+
+        if let (Some(name), Some(version)) = (name, version) {
+            packages.push(InstalledPackage {
+                name,
+                version,
+                architecture,
+                source,
+                license,
+                format: PackageFormat::Apk,
+            });
+        }
+    }
+    packages
+}
+
+fn read_i32(data: &[u8], offset: usize) -> Option<i32> {
+    data.get(offset..offset + 4)
+        .map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// An rpm header blob as stored in the database: index count, data length,
+// 16 byte index entries (tag, type, offset, count), then the data store
+pub(crate) fn parse_rpm_header(blob: &[u8]) -> Option<InstalledPackage> {
+    let index_count = usize::try_from(read_i32(blob, 0)?).ok()?;
+    let data_start = 8 + index_count * 16;
+    let data = blob.get(data_start..)?;
+
+    let mut name = None;
+    let mut version = None;
+    let mut release = None;
+    let mut epoch = None;
+    let mut license = None;
+    let mut architecture = None;
+    let mut source = None;
+
+    for i in 0..index_count {
+        let entry = 8 + i * 16;
+        let tag = read_i32(blob, entry)?;
+        let value_type = read_i32(blob, entry + 4)?;
+        let offset = usize::try_from(read_i32(blob, entry + 8)?).ok()?;
+
+        if value_type == RPM_INT32_TYPE {
+            if tag == RPMTAG_EPOCH {
+                epoch = read_i32(data, offset);
+            }
+            continue;
+        }
+
+        let string = || {
+            let bytes = data.get(offset..)?;
+            let end = bytes.iter().position(|b| *b == 0)?;
+            Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+        };
+        match tag {
+            RPMTAG_NAME => name = string(),
+            RPMTAG_VERSION => version = string(),
+            RPMTAG_RELEASE => release = string(),
+            RPMTAG_LICENSE => license = string(),
+            RPMTAG_ARCH => architecture = string(),
+            RPMTAG_SOURCERPM => source = string(),
+            _ => {}
+        }
+    }
+
+    let mut full_version = format!("{}-{}", version?, release?);
+    if let Some(epoch) = epoch.filter(|e| *e > 0) {
+        full_version = format!("{}:{}", epoch, full_version);
+    }
+    Some(InstalledPackage {
+        name: name?,
+        version: full_version,
+        architecture,
+        // "openssl-3.0.7-24.el9.src.rpm" -> "openssl"
+        source: source.and_then(|s| {
+            let s = s.strip_suffix(".src.rpm")?;
+            let (rest, _release) = s.rsplit_once('-')?;
+            let (name, _version) = rest.rsplit_once('-')?;
+            Some(name.to_string())
+        }),
+        license,
+        format: PackageFormat::Rpm,
+    })
+}
+
+// The sqlite database has to be on disk, callers extract it from the tar first
+pub(crate) fn read_rpm_sqlite(path: &Path) -> Result<Vec<InstalledPackage>, String> {
+    let connection =
+        rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("Failed to open rpm database {:?}: {}", path, e))?;
+    let mut statement = connection
+        .prepare("SELECT blob FROM Packages")
+        .map_err(|e| format!("Failed to query rpm database: {}", e))?;
+    let blobs = statement
+        .query_map([], |row| row.get::<_, Vec<u8>>(0))
+        .map_err(|e| format!("Failed to query rpm database: {}", e))?;
+
+    let mut packages = Vec::new();
+    for blob in blobs {
+        let blob = blob.map_err(|e| format!("Failed to read rpm database row: {}", e))?;
+        if let Some(package) = parse_rpm_header(&blob) {
+            packages.push(package);
+        }
+    }
+    Ok(packages)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackageUpgrade {
+    name: String,
+    format: PackageFormat,
+    // Any version change counts, downgrades included
+    old_version: String,
+    new_version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerPackages {
+    layer_id: String,
+    created_by: String,
+    added: Vec<InstalledPackage>,
+    upgraded: Vec<PackageUpgrade>,
+    removed: Vec<InstalledPackage>,
+    // Packages installed once this layer is applied
+    installed_count: usize,
+}
+
+// Package databases a single layer tar writes. Each database file is
+// replaced as a whole, so a layer that has one holds the complete list.
+#[derive(Default, Clone)]
+struct LayerDatabases {
+    dpkg_status: Option<Vec<InstalledPackage>>,
+    dpkg_status_d: Vec<(String, Vec<InstalledPackage>)>,
+    apk: Option<Vec<InstalledPackage>>,
+    rpm: Option<Vec<InstalledPackage>>,
+}
+
+fn read_layer_databases(reader: &mut dyn Read) -> io::Result<LayerDatabases> {
+    let mut archive = tar::Archive::new(reader);
+    let mut databases = LayerDatabases::default();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry_relative_path(&entry.path()?);
+        let is_database = path == DPKG_STATUS_PATH
+            || path.starts_with(DPKG_STATUS_D_PREFIX)
+            || path == APK_INSTALLED_PATH
+            || path == RPM_SQLITE_PATH;
+        if !is_database {
+            continue;
+        }
+
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        if path == RPM_SQLITE_PATH {
+            let scratch_path = exec_safety::extraction_root().join(RPM_SCRATCH_FILE);
+            fs::write(&scratch_path, &content)?;
+            let packages = read_rpm_sqlite(&scratch_path);
+            let _ = fs::remove_file(&scratch_path);
+            databases.rpm = Some(packages.map_err(io::Error::other)?);
+            continue;
+        }
+
+        let content = String::from_utf8_lossy(&content);
+        if path == DPKG_STATUS_PATH {
+            databases.dpkg_status = Some(parse_dpkg_status(&content));
+        } else if path == APK_INSTALLED_PATH {
+            databases.apk = Some(parse_apk_installed(&content));
+        } else {
+            databases
+                .dpkg_status_d
+                .push((path, parse_dpkg_status(&content)));
+        }
+    }
+    Ok(databases)
+}
+
+type PackageState = BTreeMap<(PackageFormat, String), InstalledPackage>;
+
+// Installed packages after applying layers in order
+#[derive(Default)]
+struct DatabaseState {
+    dpkg_status: Vec<InstalledPackage>,
+    dpkg_status_d: BTreeMap<String, Vec<InstalledPackage>>,
+    apk: Vec<InstalledPackage>,
+    rpm: Vec<InstalledPackage>,
+}
+
+impl DatabaseState {
+    fn apply(&mut self, layer: LayerDatabases) {
+        if let Some(packages) = layer.dpkg_status {
+            self.dpkg_status = packages;
+        }
+        self.dpkg_status_d.extend(layer.dpkg_status_d);
+        if let Some(packages) = layer.apk {
+            self.apk = packages;
+        }
+        if let Some(packages) = layer.rpm {
+            self.rpm = packages;
+        }
+    }
+
+    fn packages(&self) -> PackageState {
+        self.dpkg_status
+            .iter()
+            .chain(self.dpkg_status_d.values().flatten())
+            .chain(&self.apk)
+            .chain(&self.rpm)
+            .map(|p| ((p.format, p.name.clone()), p.clone()))
+            .collect()
+    }
+}
+
+fn diff_packages(
+    layer_id: String,
+    created_by: String,
+    before: &PackageState,
+    after: &PackageState,
+) -> LayerPackages {
+    let mut added = Vec::new();
+    let mut upgraded = Vec::new();
+    for (key, package) in after {
+        match before.get(key) {
+            None => added.push(package.clone()),
+            Some(old) if old.version != package.version => upgraded.push(PackageUpgrade {
+                name: package.name.clone(),
+                format: package.format,
+                old_version: old.version.clone(),
+                new_version: package.version.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    let removed = before
+        .iter()
+        .filter(|(key, _)| !after.contains_key(*key))
+        .map(|(_, package)| package.clone())
+        .collect();
+
+    LayerPackages {
+        layer_id,
+        created_by,
+        added,
+        upgraded,
+        removed,
+        installed_count: after.len(),
+    }
+}
+
+// Package databases of the last image read and its ID. Reading them takes a
+// full docker save, later clicks on the same image reuse them.
+static DATABASE_CACHE: Mutex<Option<(String, Vec<SavedLayer<LayerDatabases>>)>> = Mutex::new(None);
+
+fn load_layer_databases(
+    task: &Task,
+    image_id: &str,
+) -> Result<Vec<SavedLayer<LayerDatabases>>, String> {
+    if let Some((cached_id, layers)) = DATABASE_CACHE.lock().unwrap().as_ref() {
+        if cached_id == image_id {
+            return Ok(layers.clone());
+        }
+    }
+
+    let layers = read_saved_layers(task, image_id, read_layer_databases)?;
+    *DATABASE_CACHE.lock().unwrap() = Some((image_id.to_string(), layers.clone()));
+    Ok(layers)
+}
+
+// Read the package databases ahead of time, returns the number of layers
+pub(crate) fn prefetch_package_databases(task: &Task, image_id: &str) -> Result<usize, String> {
+    load_layer_databases(task, image_id).map(|layers| layers.len())
+}
+
+fn layer_number(layer_id: &str) -> Option<usize> {
+    layer_id.strip_prefix("layer_")?.parse().ok()
+}
+
+fn layer_packages(
+    layers: Vec<SavedLayer<LayerDatabases>>,
+    layer_id: String,
+    requested: usize,
+) -> LayerPackages {
+    let mut state = DatabaseState::default();
+    let mut before = PackageState::new();
+    for layer in layers {
+        state.apply(layer.contents);
+        let after = state.packages();
+
+        // Layers come oldest first, so numbers decrease as we go. Skipping past
+        // the requested number means it was a metadata-only instruction.
+        match layer_number(&layer.layer_id) {
+            Some(number) if number == requested => {
+                return diff_packages(layer.layer_id, layer.created_by, &before, &after);
+            }
+            Some(number) if number < requested => {
+                return diff_packages(layer_id, String::new(), &before, &before);
+            }
+            _ => {}
+        }
+        before = after;
+    }
+
+    // Metadata-only instructions after the last filesystem layer
+    diff_packages(layer_id, String::new(), &before, &before)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_layer_packages(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    analysis_cache: tauri::State<'_, AnalysisCache>,
+    layer_id: String,
+) -> Result<LayerPackages, LayersError> {
+    info!("Listing packages changed by layer {}", layer_id);
+    let requested =
+        layer_number(&layer_id).ok_or_else(|| format!("Invalid layer id: {}", layer_id))?;
+
+    let image_id = session.image_id()?;
+    let cache_key = analysis_cache.key(&image_id, "packages", ANALYZER_VERSION, &layer_id);
+    if let Some(packages) = cache_key.as_ref().and_then(|key| analysis_cache.get(key)) {
+        return Ok(packages);
+    }
+
+    // The layer_N tars hold everything up to the layer, the per layer changes need docker save
+    let task = tasks.start();
+    let layers = load_layer_databases(&task, &image_id);
+    tasks.finish(task.id);
+    let packages = layer_packages(layers?, layer_id, requested);
+
+    if let Some(key) = &cache_key {
+        analysis_cache.put(key, &packages);
+    }
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str) -> InstalledPackage {
+        InstalledPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            architecture: None,
+            source: None,
+            license: None,
+            format: PackageFormat::Deb,
+        }
+    }
+
+    // Header blob with string entries and an optional epoch, laid out like rpm does
+    fn rpm_header(strings: &[(i32, &str)], epoch: Option<i32>) -> Vec<u8> {
+        let mut index = Vec::new();
+        let mut data = Vec::new();
+        for (tag, value) in strings {
+            index.push((*tag, 6, data.len() as i32));
+            data.extend_from_slice(value.as_bytes());
+            data.push(0);
+        }
+        if let Some(epoch) = epoch {
+            index.push((RPMTAG_EPOCH, RPM_INT32_TYPE, data.len() as i32));
+            data.extend_from_slice(&epoch.to_be_bytes());
+        }
+
+        let mut blob = Vec::new();
+        blob.extend_from_slice(&(index.len() as i32).to_be_bytes());
+        blob.extend_from_slice(&(data.len() as i32).to_be_bytes());
+        for (tag, value_type, offset) in index {
+            blob.extend_from_slice(&tag.to_be_bytes());
+            blob.extend_from_slice(&value_type.to_be_bytes());
+            blob.extend_from_slice(&offset.to_be_bytes());
+            blob.extend_from_slice(&1i32.to_be_bytes());
+        }
+        blob.extend_from_slice(&data);
+        blob
+    }
+
+    #[test]
+    fn dpkg_status_keeps_installed_packages() {
+        let status = "Package: libssl3\n\
+                      Status: install ok installed\n\
+                      Architecture: amd64\n\
+                      Source: openssl (3.0.11-1)\n\
+                      Version: 3.0.11-1~deb12u2\n\
+                      Description: Secure Sockets Layer toolkit\n \
+                      Package: not-a-field\n\
+                      \n\
+                      Package: removed-tool\n\
+                      Status: deinstall ok config-files\n\
+                      Version: 1.0\n";
+        let packages = parse_dpkg_status(status);
+
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "libssl3");
+        assert_eq!(packages[0].version, "3.0.11-1~deb12u2");
+        assert_eq!(packages[0].architecture.as_deref(), Some("amd64"));
+        assert_eq!(packages[0].source.as_deref(), Some("openssl"));
+        assert_eq!(packages[0].format, PackageFormat::Deb);
+    }
+
+    #[test]
+    fn distroless_status_d_stanza_has_no_status() {
+        let packages = parse_dpkg_status("Package: base-files\nVersion: 12.4+deb12u5\n");
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "base-files");
+    }
+
+    #[test]
+    fn apk_installed_reads_single_letter_keys() {
+        let installed = "C:Q1abc=\nP:musl\nV:1.2.4-r2\nA:x86_64\no:musl\nL:MIT\n\n\
+                         P:busybox\nV:1.36.1-r5\n\nV:orphan-version\n";
+        let packages = parse_apk_installed(installed);
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "musl");
+        assert_eq!(packages[0].version, "1.2.4-r2");
+        assert_eq!(packages[0].architecture.as_deref(), Some("x86_64"));
+        assert_eq!(packages[0].source.as_deref(), Some("musl"));
+        assert_eq!(packages[0].license.as_deref(), Some("MIT"));
+        assert_eq!(packages[1].name, "busybox");
+        assert_eq!(packages[1].format, PackageFormat::Apk);
+    }
+
+    #[test]
+    fn rpm_header_joins_epoch_version_and_release() {
+        let blob = rpm_header(
+            &[
+                (RPMTAG_NAME, "openssl-libs"),
+                (RPMTAG_VERSION, "3.0.7"),
+                (RPMTAG_RELEASE, "24.el9"),
+                (RPMTAG_ARCH, "x86_64"),
+                (RPMTAG_LICENSE, "ASL 2.0"),
+                (RPMTAG_SOURCERPM, "openssl-3.0.7-24.el9.src.rpm"),
+            ],
+            Some(1),
+        );
+        let package = parse_rpm_header(&blob).unwrap();
+
+        assert_eq!(package.name, "openssl-libs");
+        assert_eq!(package.version, "1:3.0.7-24.el9");
+        assert_eq!(package.architecture.as_deref(), Some("x86_64"));
+        assert_eq!(package.license.as_deref(), Some("ASL 2.0"));
+        assert_eq!(package.source.as_deref(), Some("openssl"));
+        assert_eq!(package.format, PackageFormat::Rpm);
+    }
+
+    #[test]
+    fn rpm_header_without_release_or_truncated_is_skipped() {
+        let blob = rpm_header(&[(RPMTAG_NAME, "bash"), (RPMTAG_VERSION, "5.1")], None);
+        assert!(parse_rpm_header(&blob).is_none());
+        assert!(parse_rpm_header(&blob[..20]).is_none());
+        assert!(parse_rpm_header(&[]).is_none());
+    }
+
+    #[test]
+    fn diff_packages_sorts_changes() {
+        let state = |packages: &[InstalledPackage]| -> PackageState {
+            packages
+                .iter()
+                .map(|p| ((p.format, p.name.clone()), p.clone()))
+                .collect()
+        };
+        let before = state(&[package("curl", "7.88"), package("wget", "1.21")]);
+        let after = state(&[package("curl", "8.5"), package("jq", "1.6")]);
+        let layer = diff_packages(
+            "layer_1".to_string(),
+            "RUN apt-get".to_string(),
+            &before,
+            &after,
+        );
+
+        assert_eq!(layer.added.len(), 1);
+        assert_eq!(layer.added[0].name, "jq");
+        assert_eq!(layer.upgraded.len(), 1);
+        assert_eq!(layer.upgraded[0].old_version, "7.88");
+        assert_eq!(layer.upgraded[0].new_version, "8.5");
+        assert_eq!(layer.removed.len(), 1);
+        assert_eq!(layer.removed[0].name, "wget");
+        assert_eq!(layer.installed_count, 2);
+    }
+}
@@ -0,0 +1,234 @@
+use layers_core::layer_tar::entry_relative_path;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::findings::{SecurityFinding, Severity};
+use crate::layer_tar_path;
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+pub(crate) const PASSWD_PATH: &str = "etc/passwd";
+pub(crate) const GROUP_PATH: &str = "etc/group";
+
+// Ownership of a file resolved against the image's own user database
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileOwner {
+    uid: u64,
+    gid: u64,
+    user: Option<String>,
+    group: Option<String>,
+    // e.g. "nginx:nginx (101:101)", unknown names fall back to the number
+    pub(crate) display: String,
+    // The uid has no entry in the image's /etc/passwd
+    unknown_user: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnknownOwnerFile {
+    path: String,
+    owner: FileOwner,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OwnershipReport {
+    layer_id: String,
+    files_checked: usize,
+    // False when the image has no /etc/passwd, in which case nothing is flagged
+    has_passwd: bool,
+    unknown_owners: Vec<UnknownOwnerFile>,
+    findings: Vec<SecurityFinding>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct UserDatabase {
+    users: HashMap<u64, String>,
+    groups: HashMap<u64, String>,
+    has_passwd: bool,
+}
+
+// Both files are "name:password:id:..." with the numeric id in the third field
+fn parse_id_file(content: &str) -> HashMap<u64, String> {
+    content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?.trim();
+            let id = fields.nth(1)?.trim().parse().ok()?;
+            // First entry wins, like getpwuid
+            Some((id, name.to_string()))
+        })
+        .fold(HashMap::new(), |mut map, (id, name)| {
+            map.entry(id).or_insert(name);
+            map
+        })
+}
+
+impl UserDatabase {
+    pub(crate) fn set_passwd(&mut self, content: &str) {
+        self.users = parse_id_file(content);
+        self.has_passwd = true;
+    }
+
+    pub(crate) fn set_group(&mut self, content: &str) {
+        self.groups = parse_id_file(content);
+    }
+
+    pub(crate) fn resolve(&self, uid: u64, gid: u64) -> FileOwner {
+        let user = self.users.get(&uid).cloned();
+        let group = self.groups.get(&gid).cloned();
+        let display = format!(
+            "{}:{} ({}:{})",
+            user.clone().unwrap_or_else(|| uid.to_string()),
+            group.clone().unwrap_or_else(|| gid.to_string()),
+            uid,
+            gid
+        );
+        FileOwner {
+            uid,
+            gid,
+            user,
+            group,
+            display,
+            unknown_user: self.is_unknown_user(uid),
+        }
+    }
+
+    pub(crate) fn is_unknown_user(&self, uid: u64) -> bool {
+        self.has_passwd && !self.users.contains_key(&uid)
+    }
+}
+
+// Load passwd/group from the tar if the entry is one of them
+pub(crate) fn capture_user_database<R: Read>(
+    database: &mut UserDatabase,
+    relative_path: &str,
+    entry: &mut tar::Entry<R>,
+) -> Result<(), String> {
+    if relative_path != PASSWD_PATH && relative_path != GROUP_PATH {
+        return Ok(());
+    }
+    if !entry.header().entry_type().is_file() {
+        return Ok(());
+    }
+
+    // Comment fields aren't always UTF-8, that must not hide the names
+    let mut bytes = Vec::new();
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read /{}: {}", relative_path, e))?;
+    let content = String::from_utf8_lossy(&bytes);
+    if relative_path == PASSWD_PATH {
+        database.set_passwd(&content);
+    } else {
+        database.set_group(&content);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn check_file_ownership(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+) -> Result<OwnershipReport, LayersError> {
+    info!("Checking file ownership in layer {}", layer_id);
+
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let tar_path = layer_tar_path(&task, &session, &layer_id);
+    tasks.finish(task.id);
+    let tar_path = tar_path?;
+
+    let file = File::open(&tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+
+    // passwd may come after the files it describes, so resolve once at the end
+    let mut database = UserDatabase::default();
+    let mut owners: Vec<(String, u64, u64)> = Vec::new();
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let relative = entry_relative_path(
+            &entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?,
+        );
+        if relative.is_empty() {
+            continue;
+        }
+
+        let uid = entry.header().uid().unwrap_or(0);
+        let gid = entry.header().gid().unwrap_or(0);
+        capture_user_database(&mut database, &relative, &mut entry)?;
+        owners.push((format!("/{}", relative), uid, gid));
+    }
+
+    let mut unknown_owners = Vec::new();
+    for (path, uid, gid) in &owners {
+        if database.is_unknown_user(*uid) {
+            unknown_owners.push(UnknownOwnerFile {
+                path: path.clone(),
+                owner: database.resolve(*uid, *gid),
+            });
+        }
+    }
+
+    // One finding per uid keeps the list readable when whole trees are affected
+    let mut by_uid: HashMap<u64, Vec<&str>> = HashMap::new();
+    for file in &unknown_owners {
+        by_uid
+            .entry(file.owner.uid)
+            .or_default()
+            .push(file.path.as_str());
+    }
+    let mut uids: Vec<&u64> = by_uid.keys().collect();
+    uids.sort();
+
+    let findings = uids
+        .into_iter()
+        .map(|uid| {
+            let paths = &by_uid[uid];
+            SecurityFinding {
+                rule_id: "unknown-file-owner".to_string(),
+                severity: Severity::Low,
+                title: format!(
+                    "{} files owned by uid {} with no passwd entry",
+                    paths.len(),
+                    uid
+                ),
+                description: format!(
+                    "Files such as {} are owned by uid {}, which has no entry in /etc/passwd. \
+                     They usually come from a COPY or ADD of host files without --chown and \
+                     would be owned by whichever user gets that uid later.",
+                    paths[0], uid
+                ),
+                path: Some(paths[0].to_string()),
+                layer_id: Some(layer_id.clone()),
+            }
+        })
+        .collect();
+
+    info!(
+        "Checked {} files, {} owned by unknown users",
+        owners.len(),
+        unknown_owners.len()
+    );
+    Ok(OwnershipReport {
+        layer_id,
+        files_checked: owners.len(),
+        has_passwd: database.has_passwd,
+        unknown_owners,
+        findings,
+    })
+}
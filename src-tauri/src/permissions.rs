@@ -0,0 +1,318 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Mutex, OnceLock, RwLock};
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tracing::{info, warn};
+
+use crate::audit;
+use crate::error::LayersError;
+
+// Like the audit log, docker is driven from helpers without access to Tauri
+// state, so the app and settings file are set once at startup
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static SETTINGS_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+// One prompt at a time, parallel tasks asking for the same capability wait
+// for the first answer instead of prompting again
+static PROMPT_LOCK: Mutex<()> = Mutex::new(());
+
+// Changes the app makes to the docker daemon, each asked about on first use
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DockerCapability {
+    PullImages,
+    TagImages,
+    CreateContainers,
+    RunContainers,
+    BuildImages,
+    RemoveImages,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionDecision {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DockerPermission {
+    capability: DockerCapability,
+    title: String,
+    description: String,
+    // None until the user has been asked
+    decision: Option<PermissionDecision>,
+}
+
+impl DockerCapability {
+    const ALL: [DockerCapability; 6] = [
+        DockerCapability::PullImages,
+        DockerCapability::TagImages,
+        DockerCapability::CreateContainers,
+        DockerCapability::RunContainers,
+        DockerCapability::BuildImages,
+        DockerCapability::RemoveImages,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            DockerCapability::PullImages => "Pull images",
+            DockerCapability::TagImages => "Tag images",
+            DockerCapability::CreateContainers => "Create containers",
+            DockerCapability::RunContainers => "Run containers",
+            DockerCapability::BuildImages => "Build images",
+            DockerCapability::RemoveImages => "Remove images",
+        }
+    }
+
+    // What gets created and how it goes away again
+    fn description(&self) -> &'static str {
+        match self {
+            DockerCapability::PullImages => {
+                "Downloads images you open by name that aren't available locally into \
+                 docker's image store. They stay there like any pulled image until you \
+                 remove them with docker rmi."
+            }
+            DockerCapability::TagImages => {
+                "Adds the tag you ask for when opening an image, and restores tags \
+                 when a cleanup is undone. Only the tag is added, remove it with \
+                 docker rmi <image>:<tag>."
+            }
+            DockerCapability::CreateContainers => {
+                "Creates stopped containers named layer_export_*, layers_compare_* or \
+                 layers_sbom_container to export image filesystems. They never start \
+                 and are removed with docker rm as soon as the export finishes or the \
+                 task is cancelled."
+            }
+            DockerCapability::RunContainers => {
+                "Starts containers of the open image, named layers_startup_*, \
+                 layers_writes_* or layers_slim_*, for the startup, runtime writes and \
+                 slim image checks. Each runs on an internal network of the same name \
+                 with no route out, without capabilities and with resource limits. \
+                 The image's code does run, you're asked before every check. The \
+                 container and network are removed with docker rm and docker network \
+                 rm when the check ends or is cancelled."
+            }
+            DockerCapability::BuildImages => {
+                "Builds Dockerfiles from the analyzer. The resulting untagged images \
+                 and the build cache stay in docker until you prune them."
+            }
+            DockerCapability::RemoveImages => {
+                "Removes the layers:latest tag older versions of the app left behind \
+                 when you run Clean up images. Only the tag goes, the image it pointed \
+                 at is kept, and the cleanup can be undone."
+            }
+        }
+    }
+
+    // Capability a docker command line needs, None for read-only commands and
+    // for removing the app's own containers
+    fn for_docker_args(args: &[String]) -> Option<Self> {
+        let (subcommand, rest) = audit::docker_subcommand(args)?;
+        match subcommand {
+            // load and import add images to the store the way a pull does
+            "pull" | "load" | "import" => Some(DockerCapability::PullImages),
+            "tag" => Some(DockerCapability::TagImages),
+            "create" | "commit" => Some(DockerCapability::CreateContainers),
+            // Copying out of a container only reads it, copying into one
+            // changes it
+            "cp" if rest.last().is_some_and(|target| target.contains(':')) => {
+                Some(DockerCapability::CreateContainers)
+            }
+            "run" | "start" | "network create" | "network connect" => {
+                Some(DockerCapability::RunContainers)
+            }
+            "build" | "buildx" => Some(DockerCapability::BuildImages),
+            "rmi" => Some(DockerCapability::RemoveImages),
+            _ => None,
+        }
+    }
+}
+
+pub fn init(app: AppHandle, path: PathBuf) {
+    info!("Reading docker permissions from {:?}", path);
+    let _ = APP.set(app);
+    *SETTINGS_PATH.write().unwrap() = Some(path);
+}
+
+// A missing or unreadable file means nothing was decided yet
+fn load_decisions() -> HashMap<DockerCapability, PermissionDecision> {
+    SETTINGS_PATH
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|path| fs::read(path).ok())
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_decisions(decisions: &HashMap<DockerCapability, PermissionDecision>) -> Result<(), String> {
+    let Some(path) = SETTINGS_PATH.read().unwrap().clone() else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+    }
+    let content = serde_json::to_string_pretty(decisions)
+        .map_err(|e| format!("Failed to serialize permissions: {}", e))?;
+    audit::write_atomically(&path, content)
+        .map_err(|e| format!("Failed to save permissions: {}", e))
+}
+
+fn prompt(app: &AppHandle, capability: DockerCapability) -> PermissionDecision {
+    let allowed = app
+        .dialog()
+        .message(format!(
+            "{}\n\nYou're only asked once, the answer can be changed in Settings.",
+            capability.description()
+        ))
+        .title(format!(
+            "Allow Layers to {}?",
+            capability.title().to_lowercase()
+        ))
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Allow".to_string(),
+            "Don't allow".to_string(),
+        ))
+        .blocking_show();
+    if allowed {
+        PermissionDecision::Allow
+    } else {
+        PermissionDecision::Deny
+    }
+}
+
+// Errors unless the user allowed `capability`, asking on first use
+pub(crate) fn require(capability: DockerCapability) -> Result<(), String> {
+    // Without a window to ask in, e.g. before setup finished, nothing is gated
+    let Some(app) = APP.get() else {
+        return Ok(());
+    };
+    let _lock = PROMPT_LOCK.lock().unwrap();
+    let mut decisions = load_decisions();
+    let decision = match decisions.get(&capability) {
+        Some(decision) => *decision,
+        None => {
+            let decision = prompt(app, capability);
+            info!("Docker permission {:?}: {:?}", capability, decision);
+            decisions.insert(capability, decision);
+            if let Err(e) = save_decisions(&decisions) {
+                warn!("{}", e);
+            }
+            decision
+        }
+    };
+    match decision {
+        PermissionDecision::Allow => Ok(()),
+        PermissionDecision::Deny => Err(format!(
+            "Permission to {} was denied, it can be allowed in Settings",
+            capability.title().to_lowercase()
+        )),
+    }
+}
+
+// Gate for every docker command, checked where commands are spawned
+pub(crate) fn check_command(command: &Command) -> io::Result<()> {
+    if command.get_program() != "docker" {
+        return Ok(());
+    }
+    let args: Vec<String> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect();
+    match DockerCapability::for_docker_args(&args) {
+        Some(capability) => {
+            require(capability).map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e))
+        }
+        None => Ok(()),
+    }
+}
+
+// Every capability with the remembered decision, for the settings screen
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_docker_permissions() -> Result<Vec<DockerPermission>, LayersError> {
+    let decisions = load_decisions();
+    Ok(DockerCapability::ALL
+        .iter()
+        .map(|capability| DockerPermission {
+            capability: *capability,
+            title: capability.title().to_string(),
+            description: capability.description().to_string(),
+            decision: decisions.get(capability).copied(),
+        })
+        .collect())
+}
+
+// Changes a remembered decision, None asks again on next use
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_docker_permission(
+    capability: DockerCapability,
+    decision: Option<PermissionDecision>,
+) -> Result<(), LayersError> {
+    info!(
+        "Setting docker permission {:?} to {:?}",
+        capability, decision
+    );
+    let _lock = PROMPT_LOCK.lock().unwrap();
+    let mut decisions = load_decisions();
+    match decision {
+        Some(decision) => decisions.insert(capability, decision),
+        None => decisions.remove(&capability),
+    };
+    save_decisions(&decisions).map_err(LayersError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capability(command: &str) -> Option<DockerCapability> {
+        let args: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+        DockerCapability::for_docker_args(&args)
+    }
+
+    #[test]
+    fn adding_images_needs_the_pull_capability() {
+        assert_eq!(capability("pull nginx"), Some(DockerCapability::PullImages));
+        assert_eq!(
+            capability("load -i image.tar"),
+            Some(DockerCapability::PullImages)
+        );
+        assert_eq!(
+            capability("image load -i image.tar"),
+            Some(DockerCapability::PullImages)
+        );
+        assert_eq!(
+            capability("import rootfs.tar app"),
+            Some(DockerCapability::PullImages)
+        );
+    }
+
+    #[test]
+    fn copying_into_a_container_needs_the_create_capability() {
+        assert_eq!(
+            capability("cp trace.txt box:/tmp/trace.txt"),
+            Some(DockerCapability::CreateContainers)
+        );
+        assert_eq!(
+            capability("container cp - box:/tmp"),
+            Some(DockerCapability::CreateContainers)
+        );
+        assert_eq!(capability("cp box:/tmp/trace.txt -"), None);
+        assert_eq!(capability("cp box:/etc ./etc"), None);
+    }
+
+    #[test]
+    fn read_only_commands_need_nothing() {
+        assert_eq!(capability("image inspect nginx"), None);
+        assert_eq!(capability("history nginx"), None);
+        assert_eq!(capability("rm -f layers_compare_1"), None);
+    }
+}
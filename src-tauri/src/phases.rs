@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::Emitter;
+use tracing::warn;
+
+use crate::transfer::Transfer;
+use crate::TaskStatus;
+
+// A step of a long operation and its share of the whole
+pub(crate) struct Phase {
+    name: &'static str,
+    weight: f32,
+}
+
+pub(crate) const fn phase(name: &'static str, weight: f32) -> Phase {
+    Phase { name, weight }
+}
+
+// Sent with task_status so the UI can show which step is running
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PhaseStatus {
+    name: String,
+    index: usize,
+    // Every phase of the operation, in order
+    phases: Vec<String>,
+    // 0.0 to 1.0 within the phase
+    progress: f32,
+}
+
+// Emits task_status events with progress worked out from the phase weights
+pub(crate) struct PhaseProgress<'a> {
+    window: &'a tauri::Window,
+    task_id: u64,
+    phases: &'static [Phase],
+    current: AtomicUsize,
+}
+
+impl<'a> PhaseProgress<'a> {
+    pub(crate) fn new(window: &'a tauri::Window, task_id: u64, phases: &'static [Phase]) -> Self {
+        PhaseProgress {
+            window,
+            task_id,
+            phases,
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    // Moves on to the named phase, phases can be skipped but not revisited
+    pub(crate) fn begin(&self, name: &str, message: &str) {
+        if let Some(index) = self.phases.iter().position(|phase| phase.name == name) {
+            self.current.fetch_max(index, Ordering::Relaxed);
+        } else {
+            warn!("Unknown phase {}", name);
+        }
+        self.update(message, 0.0);
+    }
+
+    // Progress within the current phase
+    pub(crate) fn update(&self, message: &str, progress: f32) {
+        self.emit(message, progress.clamp(0.0, 1.0), false, None, None);
+    }
+
+    // Progress within the current phase from the bytes moved so far
+    pub(crate) fn update_transfer(&self, message: &str, transfer: &Transfer) {
+        let progress = transfer.fraction().unwrap_or(0.0);
+        self.emit(message, progress, false, None, Some(transfer));
+    }
+
+    pub(crate) fn complete(&self, message: &str) {
+        self.current
+            .store(self.phases.len().saturating_sub(1), Ordering::Relaxed);
+        self.emit(message, 1.0, true, None, None);
+    }
+
+    // Reports the error in the phase that failed
+    pub(crate) fn fail(&self, error: &str) {
+        self.emit(error, 0.0, true, Some(error.to_string()), None);
+    }
+
+    fn overall(&self, index: usize, progress: f32) -> f32 {
+        let total: f32 = self.phases.iter().map(|phase| phase.weight).sum();
+        if total <= 0.0 {
+            return progress;
+        }
+        let done: f32 = self.phases[..index].iter().map(|phase| phase.weight).sum();
+        (done + self.phases[index].weight * progress) / total
+    }
+
+    fn emit(
+        &self,
+        message: &str,
+        progress: f32,
+        is_complete: bool,
+        error: Option<String>,
+        transfer: Option<&Transfer>,
+    ) {
+        let index = self.current.load(Ordering::Relaxed);
+        let Some(current) = self.phases.get(index) else {
+            return;
+        };
+        let _ = self.window.emit(
+            "task_status",
+            TaskStatus {
+                task_id: self.task_id,
+                message: message.to_string(),
+                progress: self.overall(index, progress),
+                is_complete,
+                error,
+                phase: Some(PhaseStatus {
+                    name: current.name.to_string(),
+                    index,
+                    phases: self
+                        .phases
+                        .iter()
+                        .map(|phase| phase.name.to_string())
+                        .collect(),
+                    progress,
+                }),
+                transfer: transfer.map(Transfer::status),
+            },
+        );
+    }
+}
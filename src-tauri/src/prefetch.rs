@@ -0,0 +1,228 @@
+use layers_core::docker::inspect_image;
+use layers_core::layer_tar::entry_relative_path;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use tauri::Manager;
+use tracing::{info, warn};
+
+use crate::error::LayersError;
+use crate::exec_safety;
+use crate::finish_task;
+use crate::os_packages::prefetch_package_databases;
+use crate::phases::{phase, Phase, PhaseProgress};
+use crate::session::{ImageSession, SessionState};
+use crate::tar_index::load_or_build_index;
+use crate::tasks::{Task, TaskRegistry};
+
+const PREFETCHED_FILE: &str = "prefetched.json";
+// Package databases and configuration are opened in almost every session
+const DEFAULT_PATHS: [&str; 4] = ["etc", "var/lib/dpkg", "lib/apk/db", "var/lib/rpm"];
+// The working directory can hold a whole application, only a part of a very
+// large one is extracted ahead of time
+const MAX_PREFETCH_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrefetchPlan {
+    // Follow progress through task_status events with this id
+    task_id: u64,
+    // Container paths without a leading slash
+    paths: Vec<String>,
+}
+
+// Paths that were extracted completely, extract_directory can skip them
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct PrefetchedPaths {
+    paths: Vec<String>,
+}
+
+fn push_path(paths: &mut Vec<String>, path: &str) {
+    let path = path.trim_matches('/').to_string();
+    // An empty path would be the whole filesystem
+    if !path.is_empty() && !path.contains("..") && !paths.contains(&path) {
+        paths.push(path);
+    }
+}
+
+// Package databases, /etc, the working directory and any script the
+// entrypoint or command runs
+fn plan_paths(config: &serde_json::Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    for path in DEFAULT_PATHS {
+        push_path(&mut paths, path);
+    }
+
+    let workdir = config["WorkingDir"].as_str().unwrap_or("");
+    push_path(&mut paths, workdir);
+
+    let arguments = config["Entrypoint"]
+        .as_array()
+        .into_iter()
+        .chain(config["Cmd"].as_array())
+        .flatten()
+        .filter_map(|argument| argument.as_str());
+    // Shell form commands are one argument, e.g. "exec /app/start.sh --port 80"
+    for word in arguments.flat_map(str::split_whitespace) {
+        let word = word.trim_matches(|c| c == '"' || c == '\'' || c == ';');
+        if let Some(relative) = word.strip_prefix("./") {
+            push_path(&mut paths, &format!("{}/{}", workdir, relative));
+        } else if word.starts_with('/') && !word.starts_with("/bin/") && !word.starts_with("/usr/")
+        {
+            push_path(&mut paths, word);
+        }
+    }
+    paths
+}
+
+fn is_under(path: &str, prefix: &str) -> bool {
+    path == prefix
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Whether `path` was extracted along with everything below it
+pub(crate) fn is_prefetched(layer_dir: &Path, path: &str) -> bool {
+    let path = path.trim_matches('/');
+    fs::read(layer_dir.join(PREFETCHED_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_slice::<PrefetchedPaths>(&content).ok())
+        .is_some_and(|prefetched| prefetched.paths.iter().any(|prefix| is_under(path, prefix)))
+}
+
+pub(crate) fn clear_prefetched(layer_dir: &Path) {
+    let _ = fs::remove_file(layer_dir.join(PREFETCHED_FILE));
+}
+
+// Extract every entry under `paths` in one pass over the tar, returns the
+// paths that were extracted completely
+fn extract_paths(task: &Task, layer_dir: &Path, paths: &[String]) -> Result<Vec<String>, String> {
+    let tar_path = layer_dir.join("fs.tar");
+    let extract_dir = layer_dir.join("fs");
+    fs::create_dir_all(&extract_dir)
+        .map_err(|e| format!("Failed to create extract directory: {}", e))?;
+
+    let file = File::open(&tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+    archive.set_preserve_permissions(true);
+
+    let mut complete: Vec<String> = paths.to_vec();
+    let mut extracted_bytes = 0;
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for entry in entries {
+        task.check_cancelled()?;
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let path = entry_relative_path(
+            &entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?,
+        );
+        let Some(prefix) = paths.iter().find(|prefix| is_under(&path, prefix)) else {
+            continue;
+        };
+
+        if extracted_bytes + entry.size() > MAX_PREFETCH_BYTES {
+            complete.retain(|p| p != prefix);
+            continue;
+        }
+        extracted_bytes += entry.size();
+        // Hard links to files outside the prefetched paths have nothing to
+        // point at yet, they're extracted when their directory is opened
+        if let Err(e) = entry.unpack_in(&extract_dir) {
+            warn!("Failed to prefetch {}: {}", path, e);
+            complete.retain(|p| p != prefix);
+        }
+    }
+
+    for path in paths {
+        exec_safety::strip_execute_bits(&extract_dir.join(path));
+    }
+
+    info!(
+        "Prefetched {} bytes from {} paths",
+        extracted_bytes,
+        paths.len()
+    );
+    Ok(complete)
+}
+
+const PREFETCH_PHASES: &[Phase] = &[
+    phase("fetch", 0.4),
+    phase("index", 0.3),
+    phase("analyze", 0.3),
+];
+
+fn prefetch_task(
+    task: &Task,
+    progress: &PhaseProgress,
+    session: &ImageSession,
+    paths: &[String],
+) -> Result<(), String> {
+    let layer_dir = session.dir().join("current_layer");
+    let tar_path = layer_dir.join("fs.tar");
+    if !tar_path.exists() {
+        return Err(format!("Tar file does not exist: {:?}", tar_path));
+    }
+
+    progress.begin("fetch", "Prefetching common paths...");
+    let complete = extract_paths(task, &layer_dir, paths)?;
+    let content = serde_json::to_vec(&PrefetchedPaths { paths: complete })
+        .map_err(|e| format!("Failed to serialize prefetched paths: {}", e))?;
+    fs::write(layer_dir.join(PREFETCHED_FILE), content)
+        .map_err(|e| format!("Failed to write prefetched paths: {}", e))?;
+
+    // File previews read ranges through the offset index
+    progress.begin("index", "Indexing file offsets...");
+    load_or_build_index(task, &tar_path)?;
+
+    progress.begin("analyze", "Reading package databases...");
+    let layers = prefetch_package_databases(task, session.image_id())?;
+    info!("Prefetched package databases of {} layers", layers);
+    Ok(())
+}
+
+// Warm up what the first clicks after opening an image need. Returns right
+// away, the work runs in the background as a cancellable task.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn prefetch_image_paths(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+) -> Result<PrefetchPlan, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let inspect = inspect_image(session.image_id())?;
+    let paths = plan_paths(&inspect["Config"]);
+    info!("Prefetching paths: {:?}", paths);
+
+    let task: Arc<Task> = tasks.start();
+    let plan = PrefetchPlan {
+        task_id: task.id,
+        paths: paths.clone(),
+    };
+
+    thread::spawn(move || {
+        // Prefetching is best effort, the paths are still extracted on demand
+        let progress = PhaseProgress::new(&window, task.id, PREFETCH_PHASES);
+        let result = prefetch_task(&task, &progress, &session, &paths);
+        if let Err(e) = &result {
+            info!("Prefetching stopped: {}", e);
+        }
+        if !task.is_cancelled() {
+            match result {
+                Ok(()) => progress.complete("Prefetching finished"),
+                Err(e) => progress.fail(&e),
+            }
+        }
+        let tasks = window.state::<TaskRegistry>();
+        finish_task(&window, &tasks, &task);
+    });
+
+    Ok(plan)
+}
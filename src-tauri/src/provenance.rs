@@ -0,0 +1,331 @@
+use layers_core::docker::get_image_history;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::findings::{SecurityFinding, Severity};
+use crate::layer_mapping::normalize_created_by;
+use crate::session::SessionState;
+use crate::shell_lint::pipe_to_shell;
+
+// Path segments that point at whatever is newest when the image is built
+const FLOATING_REFS: [&str; 7] = [
+    "latest", "master", "main", "HEAD", "stable", "nightly", "edge",
+];
+
+// Well known install scripts and what to use instead
+const KNOWN_INSTALLERS: [(&str, &str); 4] = [
+    (
+        "get.docker.com",
+        "Install docker-ce from Docker's apt/yum repository with a pinned package version",
+    ),
+    (
+        "sh.rustup.rs",
+        "Download rustup-init for a specific version from static.rust-lang.org and check it against its .sha256 file",
+    ),
+    (
+        "deb.nodesource.com",
+        "Start from an official node image or install a pinned nodejs package version",
+    ),
+    (
+        "install.python-poetry.org",
+        "Install poetry with pip using a pinned version, e.g. pip install poetry==<version>",
+    ),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadTool {
+    Curl,
+    Wget,
+    Add,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteDownload {
+    layer_id: String,
+    instruction: String,
+    url: String,
+    tool: DownloadTool,
+    // The downloaded content is run as a script or binary
+    executed: bool,
+    // The same instruction checks a checksum or signature
+    checksum_verified: bool,
+    // The URL refers to a fixed version rather than whatever is newest
+    pinned: bool,
+    suggestion: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProvenanceReport {
+    image: String,
+    downloads: Vec<RemoteDownload>,
+    findings: Vec<SecurityFinding>,
+}
+
+pub(crate) fn url_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r#"https?://[^\s'"|;&)<>`]+"#).unwrap())
+}
+
+fn checksum_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"\b(sha(1|224|256|384|512)sum|shasum|md5sum)\b[^&;|]*\s(-c|--check)\b|\bgpg\b[^&;]*--verify|\bcosign\s+verify|\bminisign\b[^&;]*-V",
+        )
+        .unwrap()
+    })
+}
+
+fn version_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\d+\.\d+|/[0-9a-f]{40}(/|$)|@sha256:[0-9a-f]{64}").unwrap())
+}
+
+fn output_file_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| Regex::new(r"(?:\s-o|\s-O|--output|--output-document)[=\s]+(\S+)").unwrap())
+}
+
+pub(crate) fn is_pinned_url(url: &str) -> bool {
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    // Only look at the path, hosts like 10.0.0.1 aren't versions
+    let path = format!("/{}", url.splitn(4, '/').nth(3).unwrap_or(""));
+    let floating = path.split('/').any(|segment| {
+        FLOATING_REFS.contains(&segment) || segment.to_lowercase().contains("latest")
+    });
+    !floating && version_pattern().is_match(&path)
+}
+
+// File a download is saved to, if the command names one
+fn output_file(segment: &str, url: &str) -> Option<String> {
+    if let Some(captures) = output_file_pattern().captures(segment) {
+        let file = captures[1].trim_matches(['"', '\'']);
+        if file != "-" && !file.starts_with("http") {
+            return Some(file.to_string());
+        }
+    }
+    // curl -O and plain wget keep the remote file name
+    let remote_name = |segment: &str| {
+        segment
+            .split_whitespace()
+            .any(|w| w == "-O" || w == "--remote-name")
+    };
+    if segment.contains("wget") || remote_name(segment) {
+        return url
+            .split(['?', '#'])
+            .next()
+            .and_then(|u| u.rsplit('/').next())
+            .filter(|name| !name.is_empty())
+            .map(String::from);
+    }
+    None
+}
+
+fn runs_file(body: &str, file: &str) -> bool {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    ["sh ", "bash ", "chmod +x ", "chmod 755 ", "./"]
+        .iter()
+        .any(|prefix| {
+            body.contains(&format!("{}{}", prefix, file))
+                || body.contains(&format!("{}{}", prefix, name))
+        })
+}
+
+fn suggest(url: &str, executed: bool, checksum_verified: bool, pinned: bool) -> Option<String> {
+    if let Some((_, suggestion)) = KNOWN_INSTALLERS.iter().find(|(host, _)| url.contains(host)) {
+        return Some(suggestion.to_string());
+    }
+    if url.contains("/releases/latest/download/") {
+        return Some(format!(
+            "Download a tagged release instead: {}",
+            url.replace("/releases/latest/download/", "/releases/download/<tag>/")
+        ));
+    }
+    if url.contains("raw.githubusercontent.com") && !pinned {
+        let segments: Vec<&str> = url.splitn(7, '/').collect();
+        // https://raw.githubusercontent.com/<owner>/<repo>/<ref>/<path>
+        if segments.len() == 7 {
+            return Some(format!(
+                "Reference a commit instead of the {} branch: {}",
+                segments[5],
+                [&segments[..5], &["<commit-sha>", segments[6]]]
+                    .concat()
+                    .join("/")
+            ));
+        }
+    }
+    if executed && !checksum_verified {
+        return Some(
+            "Download to a file, verify it with `echo \"<sha256>  <file>\" | sha256sum -c -` and only then run it"
+                .to_string(),
+        );
+    }
+    if !pinned {
+        return Some(
+            "Download a versioned URL so every build fetches the same content".to_string(),
+        );
+    }
+    None
+}
+
+// Split a RUN body into the commands chained with && ; || but keep pipes together
+pub(crate) fn split_commands(body: &str) -> Vec<&str> {
+    body.split("&&")
+        .flat_map(|part| part.split(';'))
+        .flat_map(|part| part.split("||"))
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+fn audit_run(layer_id: &str, body: &str) -> Vec<RemoteDownload> {
+    let checksum_verified = checksum_pattern().is_match(body);
+    let mut downloads = Vec::new();
+
+    for segment in split_commands(body) {
+        let tool = if segment.contains("curl") {
+            DownloadTool::Curl
+        } else if segment.contains("wget") {
+            DownloadTool::Wget
+        } else {
+            continue;
+        };
+
+        for url in url_pattern().find_iter(segment).map(|m| m.as_str()) {
+            let executed = pipe_to_shell(segment)
+                || ["$(curl", "$(wget", "<(curl", "<(wget"]
+                    .iter()
+                    .any(|p| segment.contains(p))
+                || output_file(segment, url).is_some_and(|file| runs_file(body, &file));
+            let pinned = is_pinned_url(url);
+            downloads.push(RemoteDownload {
+                layer_id: layer_id.to_string(),
+                instruction: "RUN".to_string(),
+                url: url.to_string(),
+                tool,
+                executed,
+                checksum_verified,
+                pinned,
+                suggestion: suggest(url, executed, checksum_verified, pinned),
+            });
+        }
+    }
+    downloads
+}
+
+fn audit_add(layer_id: &str, args: &str) -> Vec<RemoteDownload> {
+    // ADD --checksum=sha256:... verifies remote files since Dockerfile 1.6
+    let checksum_verified = args.contains("--checksum=");
+    url_pattern()
+        .find_iter(args)
+        .map(|m| {
+            let url = m.as_str();
+            let pinned = is_pinned_url(url);
+            RemoteDownload {
+                layer_id: layer_id.to_string(),
+                instruction: "ADD".to_string(),
+                url: url.to_string(),
+                tool: DownloadTool::Add,
+                executed: false,
+                checksum_verified,
+                pinned,
+                suggestion: suggest(url, false, checksum_verified, pinned).or_else(|| {
+                    (!checksum_verified).then(|| {
+                        "Add --checksum=sha256:<digest> to the ADD instruction".to_string()
+                    })
+                }),
+            }
+        })
+        .collect()
+}
+
+fn download_findings(download: &RemoteDownload) -> Vec<SecurityFinding> {
+    let finding =
+        |rule_id: &str, severity: Severity, title: String, description: String| SecurityFinding {
+            rule_id: rule_id.to_string(),
+            severity,
+            title,
+            description,
+            path: None,
+            layer_id: Some(download.layer_id.clone()),
+        };
+
+    let mut findings = Vec::new();
+    if download.executed && !download.checksum_verified {
+        findings.push(finding(
+            "remote-code-execution",
+            Severity::High,
+            format!("Unverified remote code executed from {}", download.url),
+            "A downloaded script or binary is run without checking a checksum or signature. \
+             Whoever controls the server, or the network path to it, controls what runs in the build."
+                .to_string(),
+        ));
+    } else if !download.checksum_verified {
+        findings.push(finding(
+            "unverified-download",
+            Severity::Medium,
+            format!("Download from {} is not verified", download.url),
+            "The downloaded file isn't checked against a known checksum or signature, so a \
+             changed or tampered file ends up in the image unnoticed."
+                .to_string(),
+        ));
+    }
+    if !download.pinned {
+        findings.push(finding(
+            "unpinned-download",
+            if download.executed {
+                Severity::Medium
+            } else {
+                Severity::Low
+            },
+            format!("Download from {} is not pinned to a version", download.url),
+            "The URL doesn't refer to a fixed version, so rebuilding the image can fetch \
+             different content."
+                .to_string(),
+        ));
+    }
+    findings
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn audit_remote_downloads(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+) -> Result<ProvenanceReport, LayersError> {
+    let image = session.image_or_selected(image)?;
+    info!("Auditing remote downloads in {}", image);
+
+    let history = get_image_history(&image)?;
+    let mut downloads = Vec::new();
+    for (index, entry) in history.iter().enumerate() {
+        // History is newest first, like the layer_N numbering
+        let layer_id = format!("layer_{}", index + 1);
+        let (instruction, args) = normalize_created_by(&entry.created_by);
+        match instruction.as_str() {
+            "RUN" => downloads.extend(audit_run(&layer_id, &args)),
+            "ADD" => downloads.extend(audit_add(&layer_id, &args)),
+            _ => {}
+        }
+    }
+
+    let findings = downloads
+        .iter()
+        .flat_map(download_findings)
+        .collect::<Vec<_>>();
+    info!(
+        "Found {} remote downloads, {} findings",
+        downloads.len(),
+        findings.len()
+    );
+    Ok(ProvenanceReport {
+        image,
+        downloads,
+        findings,
+    })
+}
@@ -0,0 +1,686 @@
+use layers_core::docker::docker_command;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tracing::{info, warn};
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::permissions::{self, DockerCapability};
+use crate::tasks::{Task, TaskRegistry};
+use crate::transfer::Transfer;
+use crate::{finish_task, TaskStatus};
+
+// How often the pull is checked for cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Byte counts change constantly while downloading, don't flood the UI
+const EMIT_INTERVAL: Duration = Duration::from_millis(200);
+#[cfg(unix)]
+const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LayerPullStatus {
+    // Short layer digest as docker prints it
+    id: String,
+    status: String,
+    downloaded_bytes: u64,
+    // Unknown until the download starts, and for layers that already exist
+    total_bytes: Option<u64>,
+    complete: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PullProgress {
+    task_id: u64,
+    image: String,
+    // Latest message that isn't about a single layer, e.g. "Digest: sha256:..."
+    message: String,
+    layers: Vec<LayerPullStatus>,
+    downloaded_bytes: u64,
+    total_bytes: u64,
+    is_complete: bool,
+}
+
+// One status line of a pull, from the engine API or the docker CLI
+struct PullEvent {
+    id: Option<String>,
+    status: String,
+    current: Option<u64>,
+    total: Option<u64>,
+    error: Option<String>,
+}
+
+// What the reader threads send. The stream always ends with Finished, which
+// says whether it was read to the end.
+enum PullMessage {
+    Event(PullEvent),
+    Finished(Result<(), String>),
+}
+
+impl PullProgress {
+    fn apply(&mut self, event: PullEvent) -> bool {
+        let Some(id) = event.id.filter(|_| is_layer_status(&event.status)) else {
+            self.message = event.status;
+            return true;
+        };
+
+        let index = match self.layers.iter().position(|l| l.id == id) {
+            Some(index) => index,
+            None => {
+                self.layers.push(LayerPullStatus {
+                    id,
+                    status: String::new(),
+                    downloaded_bytes: 0,
+                    total_bytes: None,
+                    complete: false,
+                });
+                self.layers.len() - 1
+            }
+        };
+        let layer = &mut self.layers[index];
+        let changed = layer.status != event.status;
+        match event.status.as_str() {
+            "Downloading" => {
+                layer.downloaded_bytes = event.current.unwrap_or(layer.downloaded_bytes);
+                layer.total_bytes = event.total.or(layer.total_bytes);
+            }
+            // Extraction reports progress too, but over the download size
+            "Download complete" | "Verifying Checksum" | "Extracting" => {
+                layer.downloaded_bytes = layer.total_bytes.unwrap_or(layer.downloaded_bytes);
+            }
+            "Pull complete" | "Already exists" => {
+                layer.downloaded_bytes = layer.total_bytes.unwrap_or(layer.downloaded_bytes);
+                layer.complete = true;
+            }
+            _ => {}
+        }
+        layer.status = event.status;
+
+        self.downloaded_bytes = self.layers.iter().map(|l| l.downloaded_bytes).sum();
+        self.total_bytes = self.layers.iter().filter_map(|l| l.total_bytes).sum();
+        changed
+    }
+}
+
+// Lines like "latest: Pulling from library/alpine" carry an ID that isn't a layer
+fn is_layer_status(status: &str) -> bool {
+    !status.starts_with("Pulling from") && !status.starts_with("Digest:")
+}
+
+// The engine API pulls every tag when none is given, the CLI defaults to latest.
+// The tag is the colon after the last slash, "registry:5000/app" has none.
+pub(crate) fn split_reference(image: &str) -> (&str, &str) {
+    if image.contains('@') {
+        return (image, "");
+    }
+    let name_start = image.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match image[name_start..].rfind(':') {
+        Some(colon) => image.split_at(name_start + colon),
+        None => (image, ":latest"),
+    }
+}
+
+fn query_escape(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn parse_api_line(line: &str) -> Option<PullEvent> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    Some(PullEvent {
+        id: value["id"].as_str().map(|s| s.to_string()),
+        status: value["status"].as_str().unwrap_or_default().to_string(),
+        current: value["progressDetail"]["current"].as_u64(),
+        total: value["progressDetail"]["total"].as_u64(),
+        error: value["error"].as_str().map(|s| s.to_string()),
+    })
+}
+
+// "4abcf2066143: Download complete" from `docker pull` without a terminal
+fn parse_cli_line(line: &str) -> PullEvent {
+    let (id, status) = match line.split_once(": ") {
+        Some((id, status)) if !id.contains(' ') => (Some(id.to_string()), status),
+        _ => (None, line),
+    };
+    PullEvent {
+        id,
+        status: status.trim().to_string(),
+        current: None,
+        total: None,
+        error: None,
+    }
+}
+
+// Engine socket from DOCKER_HOST, None when docker is reached over TCP or SSH
+#[cfg(unix)]
+fn docker_socket() -> Option<String> {
+    match layers_core::docker::docker_host() {
+        Some(host) => host.strip_prefix("unix://").map(|path| path.to_string()),
+        None => Some(DEFAULT_SOCKET.to_string()),
+    }
+}
+
+// Registry a reference pulls from, the first component counts as a host only
+// when it looks like one, as docker decides it
+pub(crate) fn registry_server(image: &str) -> String {
+    match image.split_once('/') {
+        Some((host, _)) if host.contains('.') || host.contains(':') || host == "localhost" => {
+            host.to_string()
+        }
+        _ => "https://index.docker.io/v1/".to_string(),
+    }
+}
+
+// URL-safe base64, which the engine expects for X-Registry-Auth
+#[cfg(unix)]
+fn base64_url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let triple = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// (username, secret) for a registry from the credentials helper in layers.toml
+pub(crate) fn helper_credentials(server: &str) -> Option<(String, String)> {
+    let helper = crate::config::credentials_helper()?;
+    let mut child = Command::new(format!("docker-credential-{}", helper))
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| warn!("Failed to run credentials helper {}: {}", helper, e))
+        .ok()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(server.as_bytes());
+    }
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        warn!("No credentials for {} in {}", server, helper);
+        return None;
+    }
+
+    let credentials: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    Some((
+        credentials["Username"].as_str()?.to_string(),
+        credentials["Secret"].as_str()?.to_string(),
+    ))
+}
+
+// Login for the registry of `image`. The CLI reads its own config for this,
+// API pulls have to send it along.
+#[cfg(unix)]
+fn registry_auth(image: &str) -> Option<String> {
+    let server = registry_server(image);
+    let (username, password) = helper_credentials(&server)?;
+    let auth = serde_json::json!({
+        "username": username,
+        "password": password,
+        "serveraddress": server,
+    });
+    Some(base64_url(auth.to_string().as_bytes()))
+}
+
+// Stream the engine's pull progress, which unlike the CLI has byte counts.
+// Returns the stream so a cancelled pull can be shut down.
+#[cfg(unix)]
+fn start_api_pull(
+    image: &str,
+    events: Sender<PullMessage>,
+) -> Result<std::os::unix::net::UnixStream, String> {
+    use std::os::unix::net::UnixStream;
+
+    let socket = docker_socket().ok_or("DOCKER_HOST is not a unix socket")?;
+    let mut stream = UnixStream::connect(&socket)
+        .map_err(|e| format!("Failed to connect to {}: {}", socket, e))?;
+    let (name, tag) = split_reference(image);
+    let mut query = format!("fromImage={}", query_escape(name));
+    if let Some(tag) = tag.strip_prefix(':') {
+        query.push_str(&format!("&tag={}", query_escape(tag)));
+    }
+    let auth_header = registry_auth(image)
+        .map(|auth| format!("X-Registry-Auth: {}\r\n", auth))
+        .unwrap_or_default();
+    write!(
+        stream,
+        "POST /images/create?{} HTTP/1.1\r\nHost: docker\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+        query, auth_header
+    )
+    .map_err(|e| format!("Failed to request pull: {}", e))?;
+
+    let mut reader = BufReader::new(
+        stream
+            .try_clone()
+            .map_err(|e| format!("Failed to read pull progress: {}", e))?,
+    );
+    let mut status_line = String::new();
+    reader
+        .read_line(&mut status_line)
+        .map_err(|e| format!("Failed to read pull response: {}", e))?;
+    let mut chunked = false;
+    loop {
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .map_err(|e| format!("Failed to read pull response: {}", e))?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if header.eq_ignore_ascii_case("transfer-encoding: chunked") {
+            chunked = true;
+        }
+    }
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        let mut body = String::new();
+        let _ = reader.read_to_string(&mut body);
+        return Err(format!("Failed to pull {}: {}", image, body.trim()));
+    }
+
+    thread::spawn(move || {
+        let mut pending = Vec::new();
+        let mut final_status = false;
+        let result = read_pull_body(&mut reader, chunked, &mut |data: &[u8]| {
+            pending.extend_from_slice(data);
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                if let Some(event) = parse_api_line(&String::from_utf8_lossy(&line)) {
+                    // "Status: Downloaded newer image for ..." is the last line
+                    final_status |= event.status.starts_with("Status:");
+                    if events.send(PullMessage::Event(event)).is_err() {
+                        return false;
+                    }
+                }
+            }
+            true
+        });
+        let result = result.and_then(|()| {
+            if final_status {
+                Ok(())
+            } else {
+                Err("The pull stream ended before docker reported a final status".to_string())
+            }
+        });
+        let _ = events.send(PullMessage::Finished(result));
+    });
+    Ok(stream)
+}
+
+// Hands the body of a pull response to `on_data` until it ends. Errors when
+// the connection drops or a chunk is cut short, Ok once the body was read to
+// the end or `on_data` stops reading.
+fn read_pull_body<R: BufRead>(
+    reader: &mut R,
+    chunked: bool,
+    on_data: &mut dyn FnMut(&[u8]) -> bool,
+) -> Result<(), String> {
+    let failed = |e: std::io::Error| format!("Failed to read pull progress: {}", e);
+    if !chunked {
+        let mut buffer = [0u8; 8192];
+        loop {
+            let n = reader.read(&mut buffer).map_err(failed)?;
+            if n == 0 || !on_data(&buffer[..n]) {
+                return Ok(());
+            }
+        }
+    }
+    loop {
+        let mut size_line = String::new();
+        if reader.read_line(&mut size_line).map_err(failed)? == 0 {
+            return Err("The pull stream ended before its last chunk".to_string());
+        }
+        let size = usize::from_str_radix(size_line.trim(), 16)
+            .map_err(|_| format!("Invalid chunk size in pull stream: {:?}", size_line.trim()))?;
+        if size == 0 {
+            return Ok(());
+        }
+        let mut chunk = vec![0u8; size + 2];
+        reader.read_exact(&mut chunk).map_err(failed)?;
+        if !on_data(&chunk[..size]) {
+            return Ok(());
+        }
+    }
+}
+
+// Fallback through the CLI, e.g. for registries that need the CLI's
+// credentials. Reports layer states but no byte counts.
+fn start_cli_pull(
+    image: &str,
+    events: Sender<PullMessage>,
+) -> Result<(Child, JoinHandle<String>), String> {
+    let mut child = docker_command()
+        .args(["pull", image])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to pull docker image: {}", e))?;
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    thread::spawn(move || {
+        if let Some(stdout) = stdout {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if events
+                    .send(PullMessage::Event(parse_cli_line(&line)))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+        // The exit status says whether the pull worked
+        let _ = events.send(PullMessage::Finished(Ok(())));
+    });
+    // Only looked at if the pull fails, docker also prints warnings there
+    let stderr_reader = thread::spawn(move || {
+        let mut message = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut message);
+        }
+        message
+    });
+    Ok((child, stderr_reader))
+}
+
+fn needs_cli_credentials(error: &str) -> bool {
+    let error = error.to_lowercase();
+    error.contains("unauthorized")
+        || error.contains("denied")
+        || error.contains("authentication required")
+}
+
+fn emit_progress(window: &tauri::Window, progress: &PullProgress) {
+    let _ = window.emit("pull_progress", progress.clone());
+}
+
+// Feed events into the progress until the reader says the stream ended
+fn follow_pull(
+    window: &tauri::Window,
+    task: &Task,
+    progress: &mut PullProgress,
+    events: &Receiver<PullMessage>,
+    cancel: &mut dyn FnMut(),
+) -> Result<(), String> {
+    let mut last_emit = Instant::now();
+    let mut transfer = Transfer::new(None);
+    loop {
+        match events.recv_timeout(POLL_INTERVAL) {
+            Ok(PullMessage::Finished(result)) => return result,
+            Ok(PullMessage::Event(event)) => {
+                if let Some(error) = event.error {
+                    return Err(error);
+                }
+                let changed = progress.apply(event);
+                if changed || last_emit.elapsed() >= EMIT_INTERVAL {
+                    emit_progress(window, progress);
+                    last_emit = Instant::now();
+                }
+
+                // Totals only count layers whose download started
+                transfer.set(progress.downloaded_bytes, Some(progress.total_bytes));
+                if let Some(layer) = progress.layers.iter().find(|l| l.status == "Downloading") {
+                    transfer.set_item(layer.id.clone());
+                }
+                if transfer.due() {
+                    let message = format!("Pulling {}", progress.image);
+                    let fraction = transfer.fraction().unwrap_or(0.0);
+                    transfer.emit(window, task.id, &message, fraction);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(format!("Lost the progress of pulling {}", progress.image))
+            }
+        }
+
+        if task.is_cancelled() {
+            info!(
+                "Stopping pull of {} for cancelled task {}",
+                progress.image, task.id
+            );
+            cancel();
+            return Err(format!("Task {} was cancelled", task.id));
+        }
+    }
+}
+
+fn pull_with_cli(
+    window: &tauri::Window,
+    task: &Task,
+    progress: &mut PullProgress,
+) -> Result<(), String> {
+    let (sender, events) = channel();
+    let (mut child, stderr_reader) = start_cli_pull(&progress.image, sender)?;
+    let result = follow_pull(window, task, progress, &events, &mut || {
+        let _ = child.kill();
+    });
+    let status = child.wait();
+    result?;
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(format!(
+            "Failed to pull image: {}",
+            stderr_reader.join().unwrap_or_default().trim()
+        )),
+        Err(e) => Err(format!("Failed to pull docker image: {}", e)),
+    }
+}
+
+#[cfg(unix)]
+fn pull_with_api(
+    window: &tauri::Window,
+    task: &Task,
+    progress: &mut PullProgress,
+) -> Result<(), String> {
+    let (sender, events) = channel();
+    let stream = start_api_pull(&progress.image, sender)?;
+    // Closing the connection makes the daemon abort the pull
+    follow_pull(window, task, progress, &events, &mut || {
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    })
+}
+
+pub(crate) fn pull_task(
+    window: &tauri::Window,
+    task: &Task,
+    image: &str,
+) -> Result<PullProgress, String> {
+    let update_status = |message: &str, progress: f32, is_complete: bool, error: Option<String>| {
+        let _ = window.emit(
+            "task_status",
+            TaskStatus {
+                task_id: task.id,
+                message: message.to_string(),
+                progress,
+                is_complete,
+                error,
+                phase: None,
+                transfer: None,
+            },
+        );
+    };
+
+    // The API path pulls without the CLI, so it isn't caught by the command check
+    permissions::require(DockerCapability::PullImages)?;
+
+    update_status(&format!("Pulling {}", image), 0.0, false, None);
+
+    let mut progress = PullProgress {
+        task_id: task.id,
+        image: image.to_string(),
+        message: String::new(),
+        layers: Vec::new(),
+        downloaded_bytes: 0,
+        total_bytes: 0,
+        is_complete: false,
+    };
+
+    #[cfg(unix)]
+    let result = match pull_with_api(window, task, &mut progress) {
+        Err(e)
+            if !task.is_cancelled()
+                && (progress.layers.is_empty() || needs_cli_credentials(&e)) =>
+        {
+            info!(
+                "Pulling {} through the engine API failed ({}), using the CLI",
+                image, e
+            );
+            pull_with_cli(window, task, &mut progress)
+        }
+        result => result,
+    };
+    #[cfg(not(unix))]
+    let result = pull_with_cli(window, task, &mut progress);
+
+    audit::record("docker pull", image, &result);
+    match result {
+        Ok(()) => {
+            progress.is_complete = true;
+            emit_progress(window, &progress);
+            update_status(&format!("Pulled {}", image), 1.0, true, None);
+            Ok(progress)
+        }
+        Err(e) => {
+            update_status("Pull failed", 0.0, true, Some(e.clone()));
+            Err(e)
+        }
+    }
+}
+
+// Pull an image, streaming per-layer progress as `pull_progress` events.
+// Cancel it with cancel_task.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn pull_image(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    image: String,
+) -> Result<PullProgress, LayersError> {
+    info!("Pulling image {}", image);
+
+    let task = tasks.start();
+    let result = pull_task(&window, &task, &image);
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn read_body(body: &[u8], chunked: bool) -> (Result<(), String>, Vec<u8>) {
+        let mut data = Vec::new();
+        let result = read_pull_body(&mut Cursor::new(body), chunked, &mut |chunk: &[u8]| {
+            data.extend_from_slice(chunk);
+            true
+        });
+        (result, data)
+    }
+
+    #[test]
+    fn chunked_body_ends_at_the_last_chunk() {
+        let (result, data) = read_body(b"5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n", true);
+        assert_eq!(result, Ok(()));
+        assert_eq!(data, b"hello world");
+    }
+
+    #[test]
+    fn chunked_body_cut_short_is_an_error() {
+        // Connection dropped before the terminating chunk
+        let (result, data) = read_body(b"5\r\nhello\r\n", true);
+        assert!(result.is_err());
+        assert_eq!(data, b"hello");
+
+        // Chunk shorter than its size
+        assert!(read_body(b"a\r\nhello", true).0.is_err());
+    }
+
+    #[test]
+    fn invalid_chunk_size_is_an_error() {
+        let (result, _) = read_body(b"zz\r\nhello\r\n0\r\n\r\n", true);
+        assert!(result.unwrap_err().contains("Invalid chunk size"));
+    }
+
+    #[test]
+    fn plain_body_ends_at_eof() {
+        let (result, data) = read_body(b"{\"status\":\"Pulling fs layer\"}\n", false);
+        assert_eq!(result, Ok(()));
+        assert_eq!(data, b"{\"status\":\"Pulling fs layer\"}\n");
+    }
+
+    #[test]
+    fn split_reference_takes_the_tag_after_the_last_slash() {
+        assert_eq!(split_reference("nginx:1.25"), ("nginx", ":1.25"));
+        assert_eq!(split_reference("nginx"), ("nginx", ":latest"));
+        assert_eq!(
+            split_reference("registry:5000/team/app"),
+            ("registry:5000/team/app", ":latest")
+        );
+        assert_eq!(
+            split_reference("registry:5000/team/app:v2"),
+            ("registry:5000/team/app", ":v2")
+        );
+        assert_eq!(
+            split_reference("nginx@sha256:0123abcd"),
+            ("nginx@sha256:0123abcd", "")
+        );
+    }
+
+    #[test]
+    fn api_line_reads_progress_and_errors() {
+        let event = parse_api_line(
+            r#"{"status":"Downloading","progressDetail":{"current":512,"total":2048},"id":"4abcf2066143"}"#,
+        )
+        .unwrap();
+        assert_eq!(event.id.as_deref(), Some("4abcf2066143"));
+        assert_eq!(event.status, "Downloading");
+        assert_eq!((event.current, event.total), (Some(512), Some(2048)));
+        assert_eq!(event.error, None);
+
+        let event = parse_api_line(r#"{"error":"manifest unknown"}"#).unwrap();
+        assert_eq!(event.status, "");
+        assert_eq!(event.error.as_deref(), Some("manifest unknown"));
+
+        assert!(parse_api_line("not json").is_none());
+    }
+
+    #[test]
+    fn cli_line_splits_the_layer_id() {
+        let event = parse_cli_line("4abcf2066143: Download complete");
+        assert_eq!(event.id.as_deref(), Some("4abcf2066143"));
+        assert_eq!(event.status, "Download complete");
+
+        // Only a single word before ": " is taken as the ID
+        let event = parse_cli_line("Digest: sha256:0123abcd");
+        assert_eq!(event.id.as_deref(), Some("Digest"));
+        assert_eq!(event.status, "sha256:0123abcd");
+        let event = parse_cli_line("Using default tag: latest");
+        assert_eq!(event.id, None);
+        assert_eq!(event.status, "Using default tag: latest");
+    }
+}
@@ -0,0 +1,305 @@
+use layers_core::docker::{get_image_history, is_empty_history_entry};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::digest_verify::registry_layer_sizes;
+use crate::error::LayersError;
+use crate::session::SessionState;
+
+// Docker history reports uncompressed sizes, registries serve gzip blobs.
+// Typical filesystem content compresses to roughly 40% of its original size,
+// which is used for images the registry can't be asked about.
+pub(crate) const DEFAULT_COMPRESSION_RATIO: f64 = 0.4;
+
+// A layer is considered to dominate the pull if it accounts for at least this
+// share of the total estimated transfer time
+const DOMINANT_LAYER_SHARE: f64 = 0.25;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressedSizeSource {
+    // layers[].size of the registry manifest
+    Registry,
+    // Uncompressed sizes times the compression ratio, for images built
+    // locally or when the registry can't be reached
+    Estimated,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BandwidthProfile {
+    name: String,
+    bandwidth_mbps: f64,
+    // Fixed cost per blob (auth, manifest lookups, TCP/TLS setup)
+    per_layer_overhead_ms: u64,
+    // Number of blobs the daemon downloads in parallel (dockerd defaults to 3)
+    concurrent_downloads: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerPullEstimate {
+    layer_id: String,
+    command: String,
+    uncompressed_bytes: u64,
+    compressed_bytes: u64,
+    seconds: f64,
+    share: f64,
+    is_dominant: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfilePullEstimate {
+    profile: BandwidthProfile,
+    total_seconds: f64,
+    layers: Vec<LayerPullEstimate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PullTimeReport {
+    compressed_sizes: CompressedSizeSource,
+    // Only set when the compressed sizes are estimated
+    compression_ratio: Option<f64>,
+    total_uncompressed_bytes: u64,
+    total_compressed_bytes: u64,
+    profiles: Vec<ProfilePullEstimate>,
+}
+
+pub fn default_profiles() -> Vec<BandwidthProfile> {
+    vec![
+        BandwidthProfile {
+            name: "CI runner".to_string(),
+            bandwidth_mbps: 1000.0,
+            per_layer_overhead_ms: 150,
+            concurrent_downloads: 3,
+        },
+        BandwidthProfile {
+            name: "Developer laptop".to_string(),
+            bandwidth_mbps: 100.0,
+            per_layer_overhead_ms: 300,
+            concurrent_downloads: 3,
+        },
+        BandwidthProfile {
+            name: "Edge device".to_string(),
+            bandwidth_mbps: 10.0,
+            per_layer_overhead_ms: 800,
+            concurrent_downloads: 1,
+        },
+    ]
+}
+
+// Compressed size of each layer blob, base layer first. `uncompressed` are the
+// history sizes of the same layers, scaled by `ratio` when the registry
+// manifest can't be read or doesn't list the same layers.
+pub(crate) fn compressed_layer_sizes(
+    image: &str,
+    uncompressed: &[u64],
+    ratio: f64,
+) -> (Vec<u64>, CompressedSizeSource) {
+    match registry_layer_sizes(image) {
+        Ok(sizes) if sizes.len() == uncompressed.len() => (sizes, CompressedSizeSource::Registry),
+        Ok(sizes) => {
+            info!(
+                "Registry lists {} layers for {} but history has {}, estimating sizes",
+                sizes.len(),
+                image,
+                uncompressed.len()
+            );
+            (
+                scale_sizes(uncompressed, ratio),
+                CompressedSizeSource::Estimated,
+            )
+        }
+        Err(e) => {
+            info!("Estimating compressed sizes of {}: {}", image, e);
+            (
+                scale_sizes(uncompressed, ratio),
+                CompressedSizeSource::Estimated,
+            )
+        }
+    }
+}
+
+fn scale_sizes(uncompressed: &[u64], ratio: f64) -> Vec<u64> {
+    uncompressed
+        .iter()
+        .map(|size| (*size as f64 * ratio).round() as u64)
+        .collect()
+}
+
+fn estimate_for_profile(
+    profile: &BandwidthProfile,
+    layers: &[(String, String, u64, u64)],
+) -> ProfilePullEstimate {
+    let bytes_per_second = profile.bandwidth_mbps.max(0.001) * 1_000_000.0 / 8.0;
+    let overhead = profile.per_layer_overhead_ms as f64 / 1000.0;
+
+    let mut estimates: Vec<LayerPullEstimate> = layers
+        .iter()
+        .map(
+            |(id, command, uncompressed, compressed)| LayerPullEstimate {
+                layer_id: id.clone(),
+                command: command.clone(),
+                uncompressed_bytes: *uncompressed,
+                compressed_bytes: *compressed,
+                // Empty metadata layers are never downloaded
+                seconds: if *compressed == 0 {
+                    0.0
+                } else {
+                    *compressed as f64 / bytes_per_second + overhead
+                },
+                share: 0.0,
+                is_dominant: false,
+            },
+        )
+        .collect();
+
+    // Parallel downloads share the link, so bandwidth time is the sum of all
+    // blobs while the per-blob overhead is spread across the download slots
+    let transfer_seconds: f64 = estimates
+        .iter()
+        .map(|l| l.compressed_bytes as f64 / bytes_per_second)
+        .sum();
+    let blob_count = estimates.iter().filter(|l| l.compressed_bytes > 0).count();
+    let slots = profile.concurrent_downloads.max(1) as f64;
+    let total_seconds = transfer_seconds + (blob_count as f64 * overhead) / slots;
+
+    let layer_seconds: f64 = estimates.iter().map(|l| l.seconds).sum();
+    for estimate in &mut estimates {
+        if layer_seconds > 0.0 {
+            estimate.share = estimate.seconds / layer_seconds;
+        }
+        estimate.is_dominant = estimate.share >= DOMINANT_LAYER_SHARE;
+    }
+
+    ProfilePullEstimate {
+        profile: profile.clone(),
+        total_seconds,
+        layers: estimates,
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn estimate_pull_times(
+    session: tauri::State<'_, SessionState>,
+    profiles: Option<Vec<BandwidthProfile>>,
+    compression_ratio: Option<f64>,
+) -> Result<PullTimeReport, LayersError> {
+    let image_id = session.image_id()?;
+    info!("Estimating pull times for {}", image_id);
+
+    let compression_ratio = compression_ratio
+        .filter(|ratio| *ratio > 0.0 && *ratio <= 1.0)
+        .unwrap_or(DEFAULT_COMPRESSION_RATIO);
+
+    // History is newest first, pulls happen base layer first
+    let mut history = get_image_history(&image_id)?;
+    history.reverse();
+
+    // Metadata-only entries have no blob in the manifest
+    let has_blob: Vec<bool> = history
+        .iter()
+        .map(|entry| !is_empty_history_entry(&entry.created_by, entry.size_bytes))
+        .collect();
+    let uncompressed: Vec<u64> = history
+        .iter()
+        .zip(&has_blob)
+        .filter(|(_, has_blob)| **has_blob)
+        .map(|(entry, _)| entry.size_bytes)
+        .collect();
+    let (compressed, source) = compressed_layer_sizes(&image_id, &uncompressed, compression_ratio);
+    let mut compressed = compressed.into_iter();
+
+    let layers: Vec<(String, String, u64, u64)> = history
+        .into_iter()
+        .zip(has_blob)
+        .map(|(entry, has_blob)| {
+            let compressed = if has_blob {
+                compressed.next().unwrap_or(0)
+            } else {
+                0
+            };
+            (entry.id, entry.created_by, entry.size_bytes, compressed)
+        })
+        .collect();
+
+    let profiles = profiles
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(default_profiles);
+
+    Ok(PullTimeReport {
+        compressed_sizes: source,
+        compression_ratio: (source == CompressedSizeSource::Estimated).then_some(compression_ratio),
+        total_uncompressed_bytes: layers.iter().map(|l| l.2).sum(),
+        total_compressed_bytes: layers.iter().map(|l| l.3).sum(),
+        profiles: profiles
+            .iter()
+            .map(|profile| estimate_for_profile(profile, &layers))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(bandwidth_mbps: f64, overhead_ms: u64, concurrent: u32) -> BandwidthProfile {
+        BandwidthProfile {
+            name: "test".to_string(),
+            bandwidth_mbps,
+            per_layer_overhead_ms: overhead_ms,
+            concurrent_downloads: concurrent,
+        }
+    }
+
+    fn layer(id: &str, compressed: u64) -> (String, String, u64, u64) {
+        (id.to_string(), String::new(), compressed * 2, compressed)
+    }
+
+    #[test]
+    fn layer_time_is_transfer_plus_overhead() {
+        // 8 Mbit/s moves 1 MB a second
+        let estimate = estimate_for_profile(&profile(8.0, 500, 1), &[layer("a", 2_000_000)]);
+        assert!((estimate.layers[0].seconds - 2.5).abs() < 1e-9);
+        assert!((estimate.total_seconds - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parallel_downloads_spread_the_overhead() {
+        let layers = [
+            layer("a", 1_000_000),
+            layer("b", 1_000_000),
+            layer("c", 1_000_000),
+        ];
+        let serial = estimate_for_profile(&profile(8.0, 300, 1), &layers);
+        let parallel = estimate_for_profile(&profile(8.0, 300, 3), &layers);
+        assert!((serial.total_seconds - 3.9).abs() < 1e-9);
+        assert!((parallel.total_seconds - 3.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn empty_layers_take_no_time() {
+        let estimate = estimate_for_profile(
+            &profile(8.0, 300, 1),
+            &[layer("meta", 0), layer("a", 1_000_000)],
+        );
+        assert_eq!(estimate.layers[0].seconds, 0.0);
+        assert_eq!(estimate.layers[0].share, 0.0);
+        assert!((estimate.total_seconds - 1.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn large_layers_dominate() {
+        let estimate = estimate_for_profile(
+            &profile(8.0, 0, 3),
+            &[layer("base", 9_000_000), layer("app", 1_000_000)],
+        );
+        assert!(estimate.layers[0].is_dominant);
+        assert!(!estimate.layers[1].is_dominant);
+        assert!((estimate.layers[0].share - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn estimated_sizes_are_scaled_by_the_ratio() {
+        assert_eq!(scale_sizes(&[1000, 0, 3], 0.4), vec![400, 0, 1]);
+    }
+}
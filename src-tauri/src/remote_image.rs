@@ -0,0 +1,815 @@
+use flate2::read::GzDecoder;
+use layers_core::layer_tar::{OPAQUE_WHITEOUT, WHITEOUT_PREFIX};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::error::LayersError;
+use crate::is_binary_content;
+use crate::pull::{helper_credentials, registry_server};
+
+const MANIFEST_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+    application/vnd.oci.image.manifest.v1+json, \
+    application/vnd.docker.distribution.manifest.list.v2+json, \
+    application/vnd.docker.distribution.manifest.v2+json";
+const DOCKER_HUB: &str = "registry-1.docker.io";
+// Registries hand out tokens for about five minutes
+const TOKEN_LIFETIME: Duration = Duration::from_secs(240);
+// Gzip member at the end of an eStargz blob that points at the TOC
+const ESTARGZ_FOOTER_SIZE: u64 = 51;
+const ESTARGZ_TOC_DIGEST: &str = "containerd.io/snapshot/stargz/toc.digest";
+const ZSTD_CHUNKED_POSITION: &str = "io.github.containers.zstd-chunked.manifest-position";
+const DEFAULT_READ_LIMIT: u64 = 64 * 1024;
+const MAX_READ_LIMIT: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LazyFormat {
+    Estargz,
+    ZstdChunked,
+    // Plain gzip or zstd, only readable by downloading the whole blob
+    None,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemoteLayer {
+    digest: String,
+    size_bytes: u64,
+    format: LazyFormat,
+    // Entries in the TOC, None when the layer has none
+    files: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteImage {
+    reference: String,
+    manifest_digest: Option<String>,
+    layers: Vec<RemoteLayer>,
+    // Size of every blob, what a pull would download
+    total_bytes: u64,
+    // Transferred for manifests, TOCs and file reads so far
+    bytes_fetched: u64,
+    // Every layer has a TOC, so listings show the whole filesystem
+    complete: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemoteFile {
+    path: String,
+    name: String,
+    // "dir", "reg", "symlink", "hardlink", "char", "block" or "fifo", as the
+    // TOC spells them
+    file_type: String,
+    size_bytes: u64,
+    mode: u32,
+    link_target: Option<String>,
+    // Topmost layer with the path
+    layer_digest: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteDirectory {
+    path: String,
+    entries: Vec<RemoteFile>,
+    complete: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteFileContent {
+    path: String,
+    size_bytes: u64,
+    // Lossy UTF-8, empty for binary files
+    content: String,
+    is_binary: bool,
+    truncated: bool,
+    // Compressed bytes transferred for this read
+    bytes_fetched: u64,
+}
+
+// Entry of a stargz.index.json or zstd:chunked manifest, both use the same
+// format. Large files are split into a "reg" entry and "chunk" entries.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TocEntry {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    link_name: String,
+    #[serde(default)]
+    mode: u32,
+    // Where the compressed chunk starts in the blob
+    #[serde(default)]
+    offset: u64,
+    // Where it ends, zstd:chunked only
+    #[serde(default)]
+    end_offset: u64,
+    #[serde(default)]
+    chunk_offset: u64,
+    // 0 for the rest of the file
+    #[serde(default)]
+    chunk_size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Toc {
+    entries: Vec<TocEntry>,
+}
+
+struct LayerToc {
+    layer: RemoteLayer,
+    entries: Vec<TocEntry>,
+    // Start of the TOC, the end of the last chunk in eStargz blobs
+    toc_offset: u64,
+}
+
+struct Token {
+    value: String,
+    fetched: Instant,
+}
+
+// A registry repository, reached through curl like the other downloads
+struct Registry {
+    host: String,
+    repository: String,
+    credentials: Option<(String, String)>,
+    token: Mutex<Option<Token>>,
+    bytes_fetched: AtomicU64,
+}
+
+struct OpenedImage {
+    registry: Registry,
+    manifest_digest: Option<String>,
+    layers: Vec<LayerToc>,
+}
+
+// Images opened from a registry by reference, managed as Tauri state
+#[derive(Default)]
+pub struct RemoteImages {
+    images: Mutex<HashMap<String, Arc<OpenedImage>>>,
+}
+
+fn null_device() -> &'static str {
+    if cfg!(windows) {
+        "NUL"
+    } else {
+        "/dev/null"
+    }
+}
+
+// (registry host, repository, tag or digest), as docker expands references
+fn parse_reference(reference: &str) -> (String, String, String) {
+    let (name, version) = match reference.split_once('@') {
+        Some((name, digest)) => (name, digest.to_string()),
+        None => {
+            let name_start = reference.rfind('/').map(|i| i + 1).unwrap_or(0);
+            match reference[name_start..].rfind(':') {
+                Some(colon) => (
+                    &reference[..name_start + colon],
+                    reference[name_start + colon + 1..].to_string(),
+                ),
+                None => (reference, "latest".to_string()),
+            }
+        }
+    };
+    match name.split_once('/') {
+        Some((host, repository))
+            if host.contains('.') || host.contains(':') || host == "localhost" =>
+        {
+            (host.to_string(), repository.to_string(), version)
+        }
+        Some(_) => (DOCKER_HUB.to_string(), name.to_string(), version),
+        None => (DOCKER_HUB.to_string(), format!("library/{}", name), version),
+    }
+}
+
+// key="value" pairs of a WWW-Authenticate header
+fn auth_params(header: &str) -> HashMap<String, String> {
+    header
+        .trim()
+        .trim_start_matches("Bearer ")
+        .split(',')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            Some((
+                key.trim().to_lowercase(),
+                value.trim().trim_matches('"').to_string(),
+            ))
+        })
+        .collect()
+}
+
+// The registry picks where its credentials go, so the realm must be HTTPS.
+// A local registry without TLS may only name itself.
+fn validate_realm(realm: &str, host: &str) -> Result<(), String> {
+    let authority = |url: &str, scheme: &str| {
+        url.strip_prefix(scheme).map(|rest| {
+            rest.split(['/', '?', '#'])
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+    };
+    if authority(realm, "https://").is_some_and(|authority| !authority.is_empty()) {
+        return Ok(());
+    }
+    if host.starts_with("localhost") && authority(realm, "http://").as_deref() == Some(host) {
+        return Ok(());
+    }
+    Err(format!("{} sent an untrusted token realm: {}", host, realm))
+}
+
+// Quoted value of a curl config file option
+fn config_value(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// `secrets` are curl config options like `user = "..."`. They go in on stdin
+// since the command line of any process is readable by every local user.
+fn curl(command: &mut Command, secrets: &[String]) -> Result<Vec<u8>, String> {
+    command
+        .args(["--silent", "--show-error", "--location", "--fail"])
+        .args(["--max-time", "120"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if !secrets.is_empty() {
+        command.args(["--config", "-"]).stdin(Stdio::piped());
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(secrets.join("\n").as_bytes())
+            .map_err(|e| format!("Failed to pass credentials to curl: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Registry request failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+impl Registry {
+    fn new(reference: &str, host: String, repository: String) -> Self {
+        Registry {
+            credentials: helper_credentials(&registry_server(reference)),
+            host,
+            repository,
+            token: Mutex::new(None),
+            bytes_fetched: AtomicU64::new(0),
+        }
+    }
+
+    // Local registries usually run without TLS
+    fn api_root(&self) -> String {
+        let scheme = if self.host.starts_with("localhost") {
+            "http"
+        } else {
+            "https"
+        };
+        format!("{}://{}/v2/", scheme, self.host)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}/{}", self.api_root(), self.repository, path)
+    }
+
+    // Pull token for the repository, None for registries without token auth.
+    // The 401 of the API root names the service that hands them out.
+    fn fetch_token(&self) -> Result<Option<String>, String> {
+        let mut probe = Command::new("curl");
+        probe
+            .args(["--silent", "--max-time", "30", "--output", null_device()])
+            .args(["--write-out", "%header{www-authenticate}"])
+            .arg(self.api_root());
+        let output = probe
+            .output()
+            .map_err(|e| format!("Failed to run curl: {}", e))?;
+        let challenge = String::from_utf8_lossy(&output.stdout).to_string();
+        if !challenge.starts_with("Bearer") {
+            return Ok(None);
+        }
+
+        let params = auth_params(&challenge);
+        let realm = params
+            .get("realm")
+            .ok_or_else(|| format!("{} sent no token realm", self.host))?;
+        validate_realm(realm, &self.host)?;
+        let mut request = Command::new("curl");
+        // --url so a realm starting with a dash isn't read as an option
+        request.arg("--get").arg("--url").arg(realm);
+        if let Some(service) = params.get("service") {
+            request.args(["--data-urlencode", &format!("service={}", service)]);
+        }
+        request.args([
+            "--data-urlencode",
+            &format!("scope=repository:{}:pull", self.repository),
+        ]);
+        let secrets: Vec<String> = self
+            .credentials
+            .iter()
+            .map(|(username, secret)| {
+                format!(
+                    "user = {}",
+                    config_value(&format!("{}:{}", username, secret))
+                )
+            })
+            .collect();
+        let body: serde_json::Value = serde_json::from_slice(&curl(&mut request, &secrets)?)
+            .map_err(|e| format!("Failed to parse registry token: {}", e))?;
+        Ok(body["token"]
+            .as_str()
+            .or(body["access_token"].as_str())
+            .map(|token| token.to_string()))
+    }
+
+    fn authorization(&self) -> Result<Option<String>, String> {
+        let mut token = self.token.lock().unwrap();
+        if token
+            .as_ref()
+            .is_none_or(|token| token.fetched.elapsed() > TOKEN_LIFETIME)
+        {
+            *token = self.fetch_token()?.map(|value| Token {
+                value,
+                fetched: Instant::now(),
+            });
+        }
+        Ok(token
+            .as_ref()
+            .map(|token| format!("Authorization: Bearer {}", token.value)))
+    }
+
+    // GET below the repository, `range` is a half-open byte range
+    fn get(
+        &self,
+        path: &str,
+        accept: Option<&str>,
+        range: Option<(u64, u64)>,
+    ) -> Result<Vec<u8>, String> {
+        let mut command = Command::new("curl");
+        let secrets: Vec<String> = self
+            .authorization()?
+            .iter()
+            .map(|authorization| format!("header = {}", config_value(authorization)))
+            .collect();
+        if let Some(accept) = accept {
+            command.args(["--header", &format!("Accept: {}", accept)]);
+        }
+        if let Some((start, end)) = range {
+            // A registry that ignores the range would send the whole blob,
+            // stop it right away instead
+            command
+                .args(["--range", &format!("{}-{}", start, end - 1)])
+                .args(["--max-filesize", &(end - start).to_string()]);
+        }
+        let body = curl(command.arg(self.url(path)), &secrets)?;
+        if let Some((start, end)) = range {
+            if body.len() as u64 != end - start {
+                return Err(format!(
+                    "{} doesn't support partial blob downloads",
+                    self.host
+                ));
+            }
+        }
+        self.bytes_fetched
+            .fetch_add(body.len() as u64, Ordering::Relaxed);
+        Ok(body)
+    }
+
+    fn get_json(&self, path: &str) -> Result<serde_json::Value, String> {
+        serde_json::from_slice(&self.get(path, Some(MANIFEST_TYPES), None)?)
+            .map_err(|e| format!("Failed to parse {}: {}", path, e))
+    }
+}
+
+// Docker's names for the platform this runs on
+fn host_platform() -> (&'static str, &'static str) {
+    let architecture = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "arm" => "arm",
+        other => other,
+    };
+    ("linux", architecture)
+}
+
+// The image manifest, picking the host platform out of a multi-platform index
+fn fetch_manifest(
+    registry: &Registry,
+    version: &str,
+) -> Result<(serde_json::Value, Option<String>), String> {
+    let manifest = registry.get_json(&format!("manifests/{}", version))?;
+    let Some(manifests) = manifest["manifests"].as_array() else {
+        let digest = version.starts_with("sha256:").then(|| version.to_string());
+        return Ok((manifest, digest));
+    };
+    let (os, architecture) = host_platform();
+    let digest = manifests
+        .iter()
+        .find(|m| m["platform"]["os"] == os && m["platform"]["architecture"] == architecture)
+        .or(manifests.first())
+        .and_then(|m| m["digest"].as_str())
+        .ok_or_else(|| "Image index lists no manifests".to_string())?
+        .to_string();
+    let manifest = registry.get_json(&format!("manifests/{}", digest))?;
+    Ok((manifest, Some(digest)))
+}
+
+fn parse_toc(json: &[u8]) -> Result<Vec<TocEntry>, String> {
+    serde_json::from_slice::<Toc>(json)
+        .map(|toc| toc.entries)
+        .map_err(|e| format!("Failed to parse layer TOC: {}", e))
+}
+
+// The footer holds the TOC offset as "%016xSTARGZ" in the gzip extra field,
+// the TOC itself is a tar with one stargz.index.json in a gzip member
+fn fetch_estargz_toc(
+    registry: &Registry,
+    digest: &str,
+    size: u64,
+) -> Result<(Vec<TocEntry>, u64), String> {
+    let path = format!("blobs/{}", digest);
+    let footer_start = size.saturating_sub(ESTARGZ_FOOTER_SIZE);
+    let footer = registry.get(&path, None, Some((footer_start, size)))?;
+    if footer.get(32..38) != Some(b"STARGZ".as_slice()) {
+        return Err(format!("{} has no eStargz footer", digest));
+    }
+    let toc_offset = std::str::from_utf8(&footer[16..32])
+        .ok()
+        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+        .filter(|offset| *offset < footer_start)
+        .ok_or_else(|| format!("{} has an invalid eStargz footer", digest))?;
+
+    let compressed = registry.get(&path, None, Some((toc_offset, footer_start)))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(compressed.as_slice()));
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read eStargz TOC: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read eStargz TOC: {}", e))?;
+        if entry.path().is_ok_and(|p| p.ends_with("stargz.index.json")) {
+            let mut json = Vec::new();
+            entry
+                .read_to_end(&mut json)
+                .map_err(|e| format!("Failed to read eStargz TOC: {}", e))?;
+            return Ok((parse_toc(&json)?, toc_offset));
+        }
+    }
+    Err(format!("{} has no stargz.index.json", digest))
+}
+
+// The annotation is "offset:length:uncompressed length:type"
+fn fetch_zstd_chunked_toc(
+    registry: &Registry,
+    digest: &str,
+    position: &str,
+) -> Result<(Vec<TocEntry>, u64), String> {
+    let numbers: Vec<u64> = position
+        .split(':')
+        .take(2)
+        .filter_map(|n| n.parse().ok())
+        .collect();
+    let [offset, length] = numbers[..] else {
+        return Err(format!(
+            "Invalid zstd:chunked manifest position {}",
+            position
+        ));
+    };
+    let compressed = registry.get(
+        &format!("blobs/{}", digest),
+        None,
+        Some((offset, offset + length)),
+    )?;
+    let json = zstd::decode_all(compressed.as_slice())
+        .map_err(|e| format!("Failed to decompress zstd:chunked manifest: {}", e))?;
+    Ok((parse_toc(&json)?, offset))
+}
+
+fn open_image(reference: &str) -> Result<OpenedImage, String> {
+    let (host, repository, version) = parse_reference(reference);
+    let registry = Registry::new(reference, host, repository);
+    let (manifest, manifest_digest) = fetch_manifest(&registry, &version)?;
+
+    let descriptors = manifest["layers"]
+        .as_array()
+        .ok_or_else(|| format!("{} has no layers", reference))?;
+    let mut layers = Vec::new();
+    for descriptor in descriptors {
+        let digest = descriptor["digest"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let size_bytes = descriptor["size"].as_u64().unwrap_or(0);
+        let annotations = &descriptor["annotations"];
+        let toc = if let Some(position) = annotations[ZSTD_CHUNKED_POSITION].as_str() {
+            Some((
+                LazyFormat::ZstdChunked,
+                fetch_zstd_chunked_toc(&registry, &digest, position),
+            ))
+        } else if annotations[ESTARGZ_TOC_DIGEST].is_string() {
+            Some((
+                LazyFormat::Estargz,
+                fetch_estargz_toc(&registry, &digest, size_bytes),
+            ))
+        } else {
+            None
+        };
+
+        let (format, entries, toc_offset) = match toc {
+            Some((format, Ok((entries, toc_offset)))) => (format, entries, toc_offset),
+            Some((_, Err(e))) => {
+                warn!("{}", e);
+                (LazyFormat::None, Vec::new(), 0)
+            }
+            None => (LazyFormat::None, Vec::new(), 0),
+        };
+        layers.push(LayerToc {
+            layer: RemoteLayer {
+                files: (format != LazyFormat::None).then_some(entries.len()),
+                digest,
+                size_bytes,
+                format,
+            },
+            entries,
+            toc_offset,
+        });
+    }
+
+    Ok(OpenedImage {
+        registry,
+        manifest_digest,
+        layers,
+    })
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_start_matches("./").trim_matches('/').to_string()
+}
+
+impl OpenedImage {
+    fn complete(&self) -> bool {
+        self.layers
+            .iter()
+            .all(|layer| layer.layer.format != LazyFormat::None)
+    }
+
+    // Every path of the merged filesystem with its entry and layer, whiteouts
+    // applied. Chunk entries are left out.
+    fn merged(&self) -> BTreeMap<String, (&TocEntry, usize)> {
+        let mut files: BTreeMap<String, (&TocEntry, usize)> = BTreeMap::new();
+        for (index, layer) in self.layers.iter().enumerate() {
+            for entry in layer.entries.iter().filter(|e| e.kind != "chunk") {
+                let path = normalize(&entry.name);
+                let (parent, name) = path.rsplit_once('/').unwrap_or(("", &path));
+                if name == OPAQUE_WHITEOUT {
+                    let prefix = format!("{}/", parent);
+                    files.retain(|p, (_, i)| *i == index || !p.starts_with(&prefix));
+                } else if let Some(deleted) = name.strip_prefix(WHITEOUT_PREFIX) {
+                    let deleted = if parent.is_empty() {
+                        deleted.to_string()
+                    } else {
+                        format!("{}/{}", parent, deleted)
+                    };
+                    let prefix = format!("{}/", deleted);
+                    files.retain(|p, _| *p != deleted && !p.starts_with(&prefix));
+                } else if !path.is_empty() {
+                    files.insert(path, (entry, index));
+                }
+            }
+        }
+        files
+    }
+
+    fn remote_file(&self, path: &str, entry: &TocEntry, layer: usize) -> RemoteFile {
+        RemoteFile {
+            path: format!("/{}", path),
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            file_type: entry.kind.clone(),
+            size_bytes: entry.size,
+            mode: entry.mode,
+            link_target: (!entry.link_name.is_empty()).then(|| entry.link_name.clone()),
+            layer_digest: self.layers[layer].layer.digest.clone(),
+        }
+    }
+
+    // Decompressed bytes of a file up to `limit`, fetching only the chunks
+    // that cover them. Neighbouring chunks are one range request.
+    fn read(&self, layer: &LayerToc, name: &str, limit: u64) -> Result<Vec<u8>, String> {
+        let chunks: Vec<&TocEntry> = layer
+            .entries
+            .iter()
+            .filter(|e| (e.kind == "reg" || e.kind == "chunk") && e.name == name)
+            .filter(|e| e.chunk_offset < limit)
+            .collect();
+        let (Some(first), Some(last)) = (chunks.first(), chunks.last()) else {
+            return Ok(Vec::new());
+        };
+
+        // eStargz has no end offsets, a chunk ends where the next one starts
+        let chunk_end = |chunk: &TocEntry| {
+            if chunk.end_offset > 0 {
+                return chunk.end_offset;
+            }
+            layer
+                .entries
+                .iter()
+                .map(|e| e.offset)
+                .filter(|offset| *offset > chunk.offset)
+                .min()
+                .unwrap_or(layer.toc_offset)
+        };
+        let start = first.offset;
+        let end = chunk_end(last);
+        let compressed = self.registry.get(
+            &format!("blobs/{}", layer.layer.digest),
+            None,
+            Some((start, end)),
+        )?;
+
+        let mut content = Vec::new();
+        for chunk in &chunks {
+            let data =
+                &compressed[(chunk.offset - start) as usize..(chunk_end(chunk) - start) as usize];
+            let size = if chunk.chunk_size > 0 {
+                chunk.chunk_size
+            } else {
+                chunk.size.saturating_sub(chunk.chunk_offset)
+            };
+            let result = match layer.layer.format {
+                LazyFormat::ZstdChunked => zstd::stream::read::Decoder::new(data)
+                    .and_then(|decoder| decoder.take(size).read_to_end(&mut content)),
+                _ => GzDecoder::new(data).take(size).read_to_end(&mut content),
+            };
+            result.map_err(|e| format!("Failed to decompress {}: {}", name, e))?;
+        }
+        content.truncate(limit as usize);
+        Ok(content)
+    }
+}
+
+impl RemoteImages {
+    fn get(&self, reference: &str) -> Result<Arc<OpenedImage>, String> {
+        self.images
+            .lock()
+            .unwrap()
+            .get(reference)
+            .cloned()
+            .ok_or_else(|| format!("{} is not open", reference))
+    }
+}
+
+fn summary(reference: String, image: &OpenedImage) -> RemoteImage {
+    RemoteImage {
+        reference,
+        manifest_digest: image.manifest_digest.clone(),
+        total_bytes: image.layers.iter().map(|l| l.layer.size_bytes).sum(),
+        bytes_fetched: image.registry.bytes_fetched.load(Ordering::Relaxed),
+        complete: image.complete(),
+        layers: image.layers.iter().map(|l| l.layer.clone()).collect(),
+    }
+}
+
+// Reads the manifest and the TOC of every eStargz or zstd:chunked layer, no
+// file contents are downloaded yet
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn open_remote_image(
+    remote: tauri::State<'_, RemoteImages>,
+    reference: String,
+) -> Result<RemoteImage, LayersError> {
+    info!("Opening {} from its registry", reference);
+    let image = Arc::new(open_image(&reference)?);
+    remote
+        .images
+        .lock()
+        .unwrap()
+        .insert(reference.clone(), image.clone());
+
+    let summary = summary(reference, &image);
+    info!(
+        "Fetched {} of {} bytes, {} of {} layers have a TOC",
+        summary.bytes_fetched,
+        summary.total_bytes,
+        summary.layers.iter().filter(|l| l.files.is_some()).count(),
+        summary.layers.len()
+    );
+    Ok(summary)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_remote_directory(
+    remote: tauri::State<'_, RemoteImages>,
+    reference: String,
+    path: String,
+) -> Result<RemoteDirectory, LayersError> {
+    let image = remote.get(&reference)?;
+    let directory = normalize(&path);
+    let entries = image
+        .merged()
+        .iter()
+        .filter(|(p, _)| {
+            let parent = p.rsplit_once('/').map(|(parent, _)| parent).unwrap_or("");
+            parent == directory
+        })
+        .map(|(p, (entry, layer))| image.remote_file(p, entry, *layer))
+        .collect();
+
+    Ok(RemoteDirectory {
+        path: format!("/{}", directory),
+        entries,
+        complete: image.complete(),
+    })
+}
+
+// Reads the start of a file, fetching only the chunks that hold it
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn read_remote_file(
+    remote: tauri::State<'_, RemoteImages>,
+    reference: String,
+    path: String,
+    limit: Option<u64>,
+) -> Result<RemoteFileContent, LayersError> {
+    let image = remote.get(&reference)?;
+    let limit = limit.unwrap_or(DEFAULT_READ_LIMIT).clamp(1, MAX_READ_LIMIT);
+    let normalized = normalize(&path);
+    info!("Reading {} from {}", normalized, reference);
+
+    let merged = image.merged();
+    let (entry, layer) = merged
+        .get(&normalized)
+        .ok_or_else(|| format!("{} is not in {}", path, reference))?;
+    if entry.kind != "reg" {
+        return Err(format!("{} is not a regular file", path).into());
+    }
+
+    let fetched_before = image.registry.bytes_fetched.load(Ordering::Relaxed);
+    let bytes = image.read(&image.layers[*layer], &entry.name, limit)?;
+    let bytes_fetched = image.registry.bytes_fetched.load(Ordering::Relaxed) - fetched_before;
+    info!(
+        "Read {} bytes of {} with {} bytes fetched",
+        bytes.len(),
+        normalized,
+        bytes_fetched
+    );
+
+    let is_binary = is_binary_content(&bytes);
+    Ok(RemoteFileContent {
+        path: format!("/{}", normalized),
+        size_bytes: entry.size,
+        content: if is_binary {
+            String::new()
+        } else {
+            String::from_utf8_lossy(&bytes).to_string()
+        },
+        is_binary,
+        truncated: entry.size > bytes.len() as u64,
+        bytes_fetched,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_params_reads_a_bearer_challenge() {
+        let params = auth_params(
+            r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/alpine:pull""#,
+        );
+        assert_eq!(params["realm"], "https://auth.docker.io/token");
+        assert_eq!(params["service"], "registry.docker.io");
+        assert_eq!(params["scope"], "repository:library/alpine:pull");
+    }
+
+    #[test]
+    fn realms_must_be_https() {
+        assert!(validate_realm("https://auth.docker.io/token", DOCKER_HUB).is_ok());
+        assert!(validate_realm("http://auth.example.com/token", "example.com").is_err());
+        assert!(validate_realm("-o/home/u/.profile", "example.com").is_err());
+        assert!(validate_realm("https://", "example.com").is_err());
+        assert!(validate_realm("file:///etc/passwd", "example.com").is_err());
+    }
+
+    #[test]
+    fn local_registries_may_use_http_for_themselves_only() {
+        assert!(validate_realm("http://localhost:5000/token", "localhost:5000").is_ok());
+        assert!(validate_realm("http://localhost:5000", "localhost:5000").is_ok());
+        assert!(validate_realm("http://evil.example.com/token", "localhost:5000").is_err());
+        assert!(validate_realm("http://localhost:5000.evil.com/token", "localhost:5000").is_err());
+    }
+}
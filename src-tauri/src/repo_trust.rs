@@ -0,0 +1,507 @@
+use layers_core::docker::get_image_history;
+use layers_core::layer_tar::entry_relative_path;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::findings::{SecurityFinding, Severity};
+use crate::layer_mapping::normalize_created_by;
+use crate::layer_tar_path;
+use crate::provenance::{split_commands, url_pattern};
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+// Hosts of the repositories distributions ship with, anything else was added on top
+const OFFICIAL_HOSTS: [&str; 16] = [
+    "deb.debian.org",
+    "security.debian.org",
+    "archive.ubuntu.com",
+    "security.ubuntu.com",
+    "ports.ubuntu.com",
+    "alpinelinux.org",
+    "mirrorlist.centos.org",
+    "mirror.centos.org",
+    "vault.centos.org",
+    "cdn.redhat.com",
+    "cdn-ubi.redhat.com",
+    "fedoraproject.org",
+    "rockylinux.org",
+    "almalinux.org",
+    "yum.oracle.com",
+    "amazonaws.com",
+];
+
+// Keyring locations, listed so the frontend can show which keys an image trusts
+const KEYRING_DIRS: [&str; 4] = [
+    "etc/apt/trusted.gpg.d/",
+    "etc/apt/keyrings/",
+    "usr/share/keyrings/",
+    "etc/pki/rpm-gpg/",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageManager {
+    Apt,
+    Apk,
+    Yum,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackageRepository {
+    manager: PackageManager,
+    // File the repository is configured in
+    path: String,
+    url: String,
+    // "bookworm main" for apt, the section name for yum
+    name: Option<String>,
+    enabled: bool,
+    third_party: bool,
+    signature_check_disabled: bool,
+    signed_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyFetch {
+    layer_id: String,
+    command: String,
+    source: String,
+    insecure: bool,
+    reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RepositoryAudit {
+    repositories: Vec<PackageRepository>,
+    key_fetches: Vec<KeyFetch>,
+    keyrings: Vec<String>,
+    findings: Vec<SecurityFinding>,
+}
+
+fn is_config_path(path: &str) -> bool {
+    path == "etc/apt/sources.list"
+        || (path.starts_with("etc/apt/sources.list.d/")
+            && (path.ends_with(".list") || path.ends_with(".sources")))
+        || path.starts_with("etc/apt/apt.conf.d/")
+        || path == "etc/apk/repositories"
+        || (path.starts_with("etc/yum.repos.d/") && path.ends_with(".repo"))
+        || path == "etc/yum.conf"
+        || path == "etc/dnf/dnf.conf"
+}
+
+fn is_third_party(url: &str) -> bool {
+    let host = url
+        .split("://")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or(url);
+    !OFFICIAL_HOSTS
+        .iter()
+        .any(|official| host == *official || host.ends_with(&format!(".{}", official)))
+}
+
+fn repository(
+    manager: PackageManager,
+    path: &str,
+    url: &str,
+    name: Option<String>,
+    enabled: bool,
+    signature_check_disabled: bool,
+    signed_by: Option<String>,
+) -> PackageRepository {
+    PackageRepository {
+        manager,
+        path: format!("/{}", path),
+        url: url.to_string(),
+        name,
+        enabled,
+        third_party: is_third_party(url),
+        signature_check_disabled,
+        signed_by,
+    }
+}
+
+// One-line format: deb [arch=amd64 signed-by=/usr/share/keyrings/x.gpg] https://url suite components
+fn parse_apt_list(path: &str, content: &str) -> Vec<PackageRepository> {
+    let mut repositories = Vec::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut rest = match line.split_once(char::is_whitespace) {
+            Some(("deb", rest)) | Some(("deb-src", rest)) => rest.trim(),
+            _ => continue,
+        };
+
+        let mut options = HashMap::new();
+        if let Some(stripped) = rest.strip_prefix('[') {
+            let Some((inside, after)) = stripped.split_once(']') else {
+                continue;
+            };
+            for option in inside.split_whitespace() {
+                if let Some((key, value)) = option.split_once('=') {
+                    options.insert(key.to_string(), value.to_string());
+                }
+            }
+            rest = after.trim();
+        }
+
+        let mut fields = rest.split_whitespace();
+        let Some(url) = fields.next() else {
+            continue;
+        };
+        let name = fields.collect::<Vec<_>>().join(" ");
+        repositories.push(repository(
+            PackageManager::Apt,
+            path,
+            url,
+            (!name.is_empty()).then_some(name),
+            true,
+            options.get("trusted").map(String::as_str) == Some("yes")
+                || options.get("allow-insecure").map(String::as_str) == Some("yes"),
+            options.get("signed-by").cloned(),
+        ));
+    }
+    repositories
+}
+
+// deb822 format used by .sources files, stanzas separated by blank lines
+fn parse_apt_sources(path: &str, content: &str) -> Vec<PackageRepository> {
+    let mut repositories = Vec::new();
+    for stanza in content.split("\n\n") {
+        let fields: HashMap<String, String> = stanza
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+        let Some(uris) = fields.get("uris") else {
+            continue;
+        };
+
+        let name = [fields.get("suites"), fields.get("components")]
+            .iter()
+            .flatten()
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        for url in uris.split_whitespace() {
+            repositories.push(repository(
+                PackageManager::Apt,
+                path,
+                url,
+                (!name.is_empty()).then(|| name.clone()),
+                fields.get("enabled").map(String::as_str) != Some("no"),
+                fields.get("trusted").map(String::as_str) == Some("yes")
+                    || fields.get("allow-insecure").map(String::as_str) == Some("yes"),
+                fields.get("signed-by").cloned(),
+            ));
+        }
+    }
+    repositories
+}
+
+fn parse_apk_repositories(path: &str, content: &str) -> Vec<PackageRepository> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            // Tagged repositories look like "@edge https://..."
+            let (name, url) = match line.strip_prefix('@') {
+                Some(tagged) => {
+                    let (tag, url) = tagged
+                        .split_once(char::is_whitespace)
+                        .unwrap_or((tagged, ""));
+                    (Some(format!("@{}", tag)), url.trim())
+                }
+                None => (None, line),
+            };
+            repository(PackageManager::Apk, path, url, name, true, false, None)
+        })
+        .collect()
+}
+
+// INI style sections, gpgcheck defaults to the value in yum.conf/dnf.conf
+fn parse_yum_repo(path: &str, content: &str, default_gpgcheck: bool) -> Vec<PackageRepository> {
+    let mut sections: Vec<(String, HashMap<String, String>)> = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with(';') || line.is_empty() {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((section.to_string(), HashMap::new()));
+        } else if let (Some((_, values)), Some((key, value))) =
+            (sections.last_mut(), line.split_once('='))
+        {
+            values.insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let mut repositories = Vec::new();
+    for (name, values) in sections {
+        let Some(url) = ["baseurl", "mirrorlist", "metalink"]
+            .iter()
+            .find_map(|key| values.get(*key))
+            .and_then(|urls| urls.split_whitespace().next())
+        else {
+            continue;
+        };
+        let gpgcheck = values
+            .get("gpgcheck")
+            .map(|v| v != "0" && v.to_lowercase() != "false")
+            .unwrap_or(default_gpgcheck);
+        repositories.push(repository(
+            PackageManager::Yum,
+            path,
+            url,
+            Some(name),
+            values.get("enabled").map(String::as_str) != Some("0"),
+            !gpgcheck,
+            values.get("gpgkey").cloned(),
+        ));
+    }
+    repositories
+}
+
+// apt.conf settings that turn off signature verification for every repository
+fn apt_conf_disables_signatures(content: &str) -> bool {
+    content.lines().any(|line| {
+        let line = line.trim().to_lowercase();
+        !line.starts_with("//")
+            && (line.contains("allowunauthenticated") || line.contains("allowinsecurerepositories"))
+            && line.contains("true")
+    })
+}
+
+// Key downloads in RUN instructions, flagged when they can be tampered with in transit
+fn key_fetches(layer_id: &str, body: &str) -> Vec<KeyFetch> {
+    let mut fetches = Vec::new();
+    for segment in split_commands(body) {
+        let handles_keys = ["apt-key", "gpg", "rpm --import", "keyring", "trusted.gpg"]
+            .iter()
+            .any(|marker| segment.contains(marker));
+        if !handles_keys {
+            continue;
+        }
+
+        let tls_disabled = segment
+            .split_whitespace()
+            .any(|w| w == "-k" || w == "--insecure" || w == "--no-check-certificate");
+        let keyserver = segment
+            .split_whitespace()
+            .skip_while(|w| *w != "--keyserver")
+            .nth(1);
+
+        let mut sources: Vec<(String, Option<String>)> = url_pattern()
+            .find_iter(segment)
+            .map(|m| {
+                let url = m.as_str().to_string();
+                let reason = if url.starts_with("http://") {
+                    Some("Key downloaded over plain HTTP".to_string())
+                } else if tls_disabled {
+                    Some("Key downloaded with TLS certificate checks disabled".to_string())
+                } else {
+                    None
+                };
+                (url, reason)
+            })
+            .collect();
+        if let Some(keyserver) = keyserver {
+            let reason = if keyserver.starts_with("hkp://") || !keyserver.contains("://") {
+                Some("Key fetched from a keyserver over unencrypted hkp".to_string())
+            } else {
+                None
+            };
+            sources.push((keyserver.to_string(), reason));
+        }
+
+        for (source, reason) in sources {
+            fetches.push(KeyFetch {
+                layer_id: layer_id.to_string(),
+                command: segment.to_string(),
+                source,
+                insecure: reason.is_some(),
+                reason,
+            });
+        }
+    }
+    fetches
+}
+
+fn finding(
+    rule_id: &str,
+    severity: Severity,
+    title: String,
+    description: &str,
+    path: Option<String>,
+    layer_id: Option<String>,
+) -> SecurityFinding {
+    SecurityFinding {
+        rule_id: rule_id.to_string(),
+        severity,
+        title,
+        description: description.to_string(),
+        path,
+        layer_id,
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn audit_package_repositories(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+) -> Result<RepositoryAudit, LayersError> {
+    info!("Auditing package repositories");
+
+    // The final filesystem is what the running container will trust
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let tar_path = layer_tar_path(&task, &session, "current_layer");
+    tasks.finish(task.id);
+    let tar_path = tar_path?;
+
+    let file = File::open(&tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+
+    let mut configs: Vec<(String, String)> = Vec::new();
+    let mut keyrings = Vec::new();
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry_relative_path(
+            &entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?,
+        );
+
+        if KEYRING_DIRS.iter().any(|dir| path.starts_with(dir)) || path == "etc/apt/trusted.gpg" {
+            keyrings.push(format!("/{}", path));
+        }
+        if is_config_path(&path) {
+            let mut content = String::new();
+            entry
+                .read_to_string(&mut content)
+                .map_err(|e| format!("Failed to read /{}: {}", path, e))?;
+            configs.push((path, content));
+        }
+    }
+
+    let default_gpgcheck = !configs.iter().any(|(path, content)| {
+        (path == "etc/yum.conf" || path == "etc/dnf/dnf.conf")
+            && content.lines().any(|l| l.replace(' ', "") == "gpgcheck=0")
+    });
+
+    let mut repositories = Vec::new();
+    let mut findings = Vec::new();
+    for (path, content) in &configs {
+        if path.starts_with("etc/apt/apt.conf.d/") {
+            if apt_conf_disables_signatures(content) {
+                findings.push(finding(
+                    "apt-signatures-disabled",
+                    Severity::High,
+                    "apt is configured to accept unsigned packages".to_string(),
+                    "An apt.conf setting allows unauthenticated packages or insecure \
+                     repositories, so packages are installed without verifying who published them.",
+                    Some(format!("/{}", path)),
+                    None,
+                ));
+            }
+        } else if path.ends_with(".sources") {
+            repositories.extend(parse_apt_sources(path, content));
+        } else if path.starts_with("etc/apt/") {
+            repositories.extend(parse_apt_list(path, content));
+        } else if path == "etc/apk/repositories" {
+            repositories.extend(parse_apk_repositories(path, content));
+        } else if path.ends_with(".repo") {
+            repositories.extend(parse_yum_repo(path, content, default_gpgcheck));
+        }
+    }
+
+    for repo in repositories.iter().filter(|r| r.enabled) {
+        if repo.third_party {
+            findings.push(finding(
+                "third-party-repository",
+                Severity::Info,
+                format!("Third-party package repository {}", repo.url),
+                "Packages from this repository are trusted as much as the distribution's own. \
+                 Check that it is still needed and that its key is pinned with signed-by.",
+                Some(repo.path.clone()),
+                None,
+            ));
+        }
+        if repo.signature_check_disabled {
+            findings.push(finding(
+                "repository-signature-check-disabled",
+                Severity::High,
+                format!("Signature checking is disabled for {}", repo.url),
+                "Packages from this repository are installed without verifying their signature, \
+                 anyone who can tamper with the connection or the mirror can replace them.",
+                Some(repo.path.clone()),
+                None,
+            ));
+        }
+    }
+
+    // Keys and --allow-untrusted flags only show up in the build commands
+    let mut fetches = Vec::new();
+    for (index, entry) in get_image_history(session.image_id())?.iter().enumerate() {
+        let (instruction, body) = normalize_created_by(&entry.created_by);
+        if instruction != "RUN" {
+            continue;
+        }
+        let layer_id = format!("layer_{}", index + 1);
+        fetches.extend(key_fetches(&layer_id, &body));
+
+        let words: Vec<&str> = body.split_whitespace().collect();
+        if words.iter().any(|w| {
+            *w == "--allow-untrusted" || *w == "--allow-unauthenticated" || *w == "--nogpgcheck"
+        }) {
+            findings.push(finding(
+                "package-signatures-skipped",
+                Severity::High,
+                "Packages installed without signature verification".to_string(),
+                "A package manager was run with signature checks turned off for this install.",
+                None,
+                Some(layer_id),
+            ));
+        }
+    }
+
+    for fetch in fetches.iter().filter(|f| f.insecure) {
+        findings.push(finding(
+            "insecure-key-fetch",
+            Severity::High,
+            format!("Repository key fetched insecurely from {}", fetch.source),
+            fetch
+                .reason
+                .as_deref()
+                .unwrap_or("The key download can be tampered with in transit."),
+            None,
+            Some(fetch.layer_id.clone()),
+        ));
+    }
+
+    info!(
+        "Found {} repositories, {} key fetches, {} findings",
+        repositories.len(),
+        fetches.len(),
+        findings.len()
+    );
+    Ok(RepositoryAudit {
+        repositories,
+        key_fetches: fetches,
+        keyrings,
+        findings,
+    })
+}
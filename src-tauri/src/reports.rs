@@ -0,0 +1,448 @@
+use layers_core::docker::{get_image_history, image_diff_ids, is_empty_history_entry};
+use layers_core::dockerfile::Dockerfile;
+use layers_core::lint::{lint, LintIssue};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::archive_loader;
+use crate::audit;
+use crate::bookmarks::{self, Bookmark};
+use crate::error::LayersError;
+use crate::exporters::ExporterRegistry;
+use crate::findings::{finding_anchor, path_anchor, SecurityFinding};
+use crate::layer_stats::layer_limit_finding;
+use crate::ruleset_sync;
+use crate::search_index::{session_search_index, LayerChanges};
+use crate::session::{ImageSession, SessionState};
+use crate::startup_check::{saved_startup_check, StartupCheck};
+use crate::tasks::TaskRegistry;
+use crate::trends::TrendStore;
+
+// Bumped whenever a field is renamed or removed, exporters and plugins check
+// it before reading the rest
+pub(crate) const REPORT_SCHEMA_VERSION: u32 = 1;
+// Changed paths listed per layer, the counts cover the rest
+const LARGEST_CHANGES: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportImage {
+    pub(crate) reference: String,
+    pub(crate) image_id: String,
+}
+
+// A history entry, in build order like get_layer_stats
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportLayer {
+    // Matches the "layer_N" IDs of get_layer_stats
+    pub(crate) layer_id: String,
+    pub(crate) created_by: String,
+    pub(crate) size_bytes: u64,
+    // Metadata-only instructions don't add a filesystem layer
+    pub(crate) empty: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportPathChange {
+    pub(crate) anchor: String,
+    pub(crate) path: String,
+    pub(crate) change: ChangeKind,
+    pub(crate) size_bytes: u64,
+}
+
+// What a filesystem layer changed relative to the layers below it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportLayerDiff {
+    pub(crate) layer_id: String,
+    pub(crate) added_files: usize,
+    pub(crate) added_bytes: u64,
+    pub(crate) modified_files: usize,
+    pub(crate) modified_bytes: u64,
+    pub(crate) removed_files: usize,
+    pub(crate) removed_bytes: u64,
+    // Lower layer bytes this layer replaced or removed
+    pub(crate) wasted_bytes: u64,
+    // Biggest changes first
+    pub(crate) largest_changes: Vec<ReportPathChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportEfficiency {
+    // 0 to 100, the share of shipped file bytes still visible in the image
+    pub(crate) score: f64,
+    pub(crate) wasted_bytes: u64,
+    // File bytes in all layers, hidden ones included
+    pub(crate) shipped_bytes: u64,
+}
+
+// A layer whose tar could only be read in part. Diffs and findings of the
+// image leave out everything after the bad entry.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportPartialLayer {
+    pub(crate) layer_id: String,
+    pub(crate) error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportFinding {
+    // Stable ID to link to, see deep_link
+    pub(crate) anchor: String,
+    #[serde(flatten)]
+    pub(crate) finding: SecurityFinding,
+}
+
+// Everything an exporter gets, versioned with `schema_version`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Report {
+    // Name the report is saved under, see ReportStore
+    pub(crate) id: String,
+    pub(crate) schema_version: u32,
+    // Seconds since the epoch
+    pub(crate) generated_at: u64,
+    pub(crate) image: ReportImage,
+    pub(crate) layers: Vec<ReportLayer>,
+    pub(crate) total_bytes: u64,
+    pub(crate) diffs: Vec<ReportLayerDiff>,
+    pub(crate) efficiency: ReportEfficiency,
+    pub(crate) findings: Vec<ReportFinding>,
+    // Empty unless a Dockerfile was passed in
+    pub(crate) dockerfile_findings: Vec<LintIssue>,
+    // Last startup check of the image, None when it wasn't run
+    #[serde(default)]
+    pub(crate) startup: Option<StartupCheck>,
+    // Empty when every layer was read completely
+    #[serde(default)]
+    pub(crate) partial_layers: Vec<ReportPartialLayer>,
+    // Paths marked with add_bookmark, oldest first
+    #[serde(default)]
+    pub(crate) bookmarks: Vec<Bookmark>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportExportResult {
+    report_id: String,
+    destination: String,
+    format: String,
+    mime_type: String,
+    bytes_written: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedReport {
+    id: String,
+    reference: String,
+    image_id: String,
+    generated_at: u64,
+    efficiency_score: f64,
+    findings: usize,
+}
+
+impl ReportFinding {
+    fn new(finding: SecurityFinding) -> Self {
+        ReportFinding {
+            anchor: finding_anchor(&finding),
+            finding,
+        }
+    }
+}
+
+impl ReportLayerDiff {
+    fn new(changes: LayerChanges) -> Self {
+        let total = |files: &[(String, u64)]| files.iter().map(|(_, size)| size).sum();
+        let mut largest_changes: Vec<ReportPathChange> = [
+            (ChangeKind::Added, &changes.added),
+            (ChangeKind::Modified, &changes.modified),
+            (ChangeKind::Removed, &changes.removed),
+        ]
+        .into_iter()
+        .flat_map(|(change, files)| {
+            files.iter().map(move |(path, size)| ReportPathChange {
+                anchor: path_anchor(path),
+                path: path.clone(),
+                change,
+                size_bytes: *size,
+            })
+        })
+        .collect();
+        largest_changes.sort_by_key(|change| Reverse(change.size_bytes));
+        largest_changes.truncate(LARGEST_CHANGES);
+
+        ReportLayerDiff {
+            layer_id: changes.layer_id,
+            added_files: changes.added.len(),
+            added_bytes: total(&changes.added),
+            modified_files: changes.modified.len(),
+            modified_bytes: total(&changes.modified),
+            removed_files: changes.removed.len(),
+            removed_bytes: total(&changes.removed),
+            wasted_bytes: changes.hidden_bytes,
+            largest_changes,
+        }
+    }
+}
+
+fn efficiency(diffs: &[ReportLayerDiff]) -> ReportEfficiency {
+    let shipped_bytes: u64 = diffs
+        .iter()
+        .map(|diff| diff.added_bytes + diff.modified_bytes)
+        .sum();
+    let wasted_bytes: u64 = diffs.iter().map(|diff| diff.wasted_bytes).sum();
+    let score = if shipped_bytes == 0 {
+        100.0
+    } else {
+        100.0 * (1.0 - wasted_bytes as f64 / shipped_bytes as f64)
+    };
+    ReportEfficiency {
+        score: (score * 10.0).round() / 10.0,
+        wasted_bytes,
+        shipped_bytes,
+    }
+}
+
+// The report model of the selected image. Layer diffs come from the
+// session's file index, built now if the image hasn't been indexed yet.
+pub(crate) fn build_report(
+    tasks: &TaskRegistry,
+    image_session: &ImageSession,
+    dockerfile: Option<&str>,
+) -> Result<Report, String> {
+    let image_id = image_session.image_id().to_string();
+    let reference = image_session.reference().to_string();
+    let history = get_image_history(&image_id)?;
+    let filesystem_layers = image_diff_ids(&image_id)?.len();
+
+    let layers: Vec<ReportLayer> = history
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| ReportLayer {
+            layer_id: format!("layer_{}", index + 1),
+            created_by: entry.created_by.clone(),
+            size_bytes: entry.size_bytes,
+            empty: is_empty_history_entry(&entry.created_by, entry.size_bytes),
+        })
+        .collect();
+    let index = session_search_index(tasks, image_session, false)?;
+    let diffs: Vec<ReportLayerDiff> = index
+        .layer_changes()
+        .into_iter()
+        .map(ReportLayerDiff::new)
+        .collect();
+    // Archive images note partial layers in the session directory
+    let mut partial_layers = index.partial_layers();
+    for layer in archive_loader::partial_layers(image_session.dir()) {
+        if !partial_layers
+            .iter()
+            .any(|(layer_id, _)| *layer_id == layer.0)
+        {
+            partial_layers.push(layer);
+        }
+    }
+    let findings = layer_limit_finding(filesystem_layers)
+        .into_iter()
+        .map(ReportFinding::new)
+        // Findings the team ignores stay out of reports, trends and scores
+        .filter(|finding| !ruleset_sync::is_ignored(&finding.finding.rule_id, &finding.anchor))
+        .collect();
+    let dockerfile_findings = dockerfile
+        .map(|content| {
+            lint(
+                &Dockerfile::parse(content),
+                &ruleset_sync::lint_config(None),
+            )
+        })
+        .unwrap_or_default();
+
+    let generated_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let hex = image_id.rsplit(':').next().unwrap_or(&image_id);
+    let image_bookmarks = bookmarks::for_image(&image_id)?;
+    Ok(Report {
+        id: format!("{}-{}", &hex[..hex.len().min(12)], generated_at),
+        schema_version: REPORT_SCHEMA_VERSION,
+        generated_at,
+        image: ReportImage {
+            reference,
+            image_id,
+        },
+        total_bytes: layers.iter().map(|layer| layer.size_bytes).sum(),
+        layers,
+        efficiency: efficiency(&diffs),
+        diffs,
+        findings,
+        dockerfile_findings,
+        startup: saved_startup_check(image_session.dir()),
+        partial_layers: partial_layers
+            .into_iter()
+            .map(|(layer_id, error)| ReportPartialLayer { layer_id, error })
+            .collect(),
+        bookmarks: image_bookmarks,
+    })
+}
+
+// Generated reports kept as JSON in the app data directory, managed as Tauri state
+pub struct ReportStore {
+    dir: PathBuf,
+}
+
+impl ReportStore {
+    pub fn new(dir: PathBuf) -> Self {
+        ReportStore { dir }
+    }
+
+    // IDs come from the frontend, keep them from pointing outside the store
+    fn path(&self, id: &str) -> Result<PathBuf, String> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("Invalid report ID: {}", id));
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    fn save(&self, report: &Report) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create {:?}: {}", self.dir, e))?;
+        let content =
+            serde_json::to_vec(report).map_err(|e| format!("Failed to serialize report: {}", e))?;
+        fs::write(self.path(&report.id)?, content)
+            .map_err(|e| format!("Failed to save report: {}", e))
+    }
+
+    fn load(&self, id: &str) -> Result<Report, String> {
+        let content =
+            fs::read(self.path(id)?).map_err(|e| format!("Failed to read report {}: {}", id, e))?;
+        serde_json::from_slice(&content)
+            .map_err(|e| format!("Failed to parse report {}: {}", id, e))
+    }
+
+    // Newest first, reports saved by an incompatible build are skipped
+    fn list(&self) -> Vec<SavedReport> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut reports: Vec<SavedReport> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let content = fs::read(entry.path()).ok()?;
+                let report: Report = serde_json::from_slice(&content).ok()?;
+                Some(SavedReport {
+                    id: report.id,
+                    reference: report.image.reference,
+                    image_id: report.image.image_id,
+                    generated_at: report.generated_at,
+                    efficiency_score: report.efficiency.score,
+                    findings: report.findings.len() + report.dockerfile_findings.len(),
+                })
+            })
+            .collect();
+        reports.sort_by_key(|report| Reverse(report.generated_at));
+        reports
+    }
+}
+
+// Writes a report with any registered exporter, built in or loaded from a
+// plugin. Without a report ID a new one is generated for the selected image
+// and saved.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_report(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    exporters: tauri::State<'_, ExporterRegistry>,
+    store: tauri::State<'_, ReportStore>,
+    trends: tauri::State<'_, TrendStore>,
+    session_id: Option<String>,
+    format: String,
+    destination: String,
+    dockerfile: Option<String>,
+    report_id: Option<String>,
+) -> Result<ReportExportResult, LayersError> {
+    let report_exporter = exporters
+        .get(&format)
+        .ok_or_else(|| format!("No report exporter named {}", format))?;
+    let report = match &report_id {
+        Some(id) => store.load(id)?,
+        None => {
+            let session = session.get(session_id.as_deref())?;
+            let report = build_report(&tasks, &session, dockerfile.as_deref())?;
+            // Exporting still works when the report can't be kept
+            if let Err(e) = store.save(&report) {
+                warn!("{}", e);
+            }
+            if let Err(e) = trends.record(&report, None) {
+                warn!("{}", e);
+            }
+            report
+        }
+    };
+    let exported = report_exporter.export(&report)?;
+
+    let destination_path = Path::new(&destination);
+    let written = fs::write(destination_path, &exported.bytes);
+    audit::record_write(destination_path, &written);
+    written.map_err(|e| format!("Failed to write report: {}", e))?;
+    info!(
+        "Exported {} report of {} to {}",
+        format, report.image.reference, destination
+    );
+
+    Ok(ReportExportResult {
+        report_id: report.id,
+        destination,
+        format,
+        mime_type: exported.mime_type,
+        bytes_written: exported.bytes.len(),
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_saved_reports(
+    store: tauri::State<'_, ReportStore>,
+) -> Result<Vec<SavedReport>, LayersError> {
+    Ok(store.list())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn delete_saved_report(
+    store: tauri::State<'_, ReportStore>,
+    id: String,
+) -> Result<(), LayersError> {
+    let removed = store.path(&id).and_then(|path| {
+        fs::remove_file(path).map_err(|e| format!("Failed to delete report {}: {}", id, e))
+    });
+    audit::record("delete_report", &id, &removed);
+    removed.map_err(LayersError::from)
+}
+
+// Findings of the selected image as a report lists them, each with the anchor
+// its deep link points at
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_image_findings(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+) -> Result<Vec<ReportFinding>, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let report = build_report(&tasks, &session, None)?;
+    info!(
+        "Listing {} findings of {}",
+        report.findings.len(),
+        report.image.reference
+    );
+    Ok(report.findings)
+}
@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::thread;
+use tracing::{info, warn};
+
+use crate::audit;
+use crate::config;
+use crate::error::LayersError;
+
+// Task::run and the scan pool have no access to Tauri state, the applied
+// settings are kept here
+static LOW_PRIORITY: AtomicBool = AtomicBool::new(false);
+static POOL: RwLock<Option<Arc<rayon::ThreadPool>>> = RwLock::new(None);
+static PRIORITY_WRAPPER: OnceLock<Vec<String>> = OnceLock::new();
+
+// Niceness of throttled workers and tools, 19 is the lowest priority
+const LOW_PRIORITY_NICE: i32 = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceProfile {
+    #[default]
+    Normal,
+    // A quarter of the cores at low priority, whatever the other settings say
+    BatterySaver,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ResourceSettings {
+    // Worker threads for parallel scans, every core when unset
+    #[serde(default)]
+    max_threads: Option<usize>,
+    // Run scan workers and the tools they start at low CPU and IO priority
+    #[serde(default)]
+    low_priority: bool,
+    #[serde(default)]
+    profile: ResourceProfile,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResourceStatus {
+    settings: ResourceSettings,
+    cores: usize,
+    // What the settings and profile add up to
+    threads: usize,
+    low_priority: bool,
+}
+
+// Limits on what scans may use, managed as Tauri state
+pub struct ResourceLimits {
+    path: PathBuf,
+    settings: Mutex<ResourceSettings>,
+}
+
+impl ResourceSettings {
+    fn effective(&self, cores: usize) -> (usize, bool) {
+        // The extraction concurrency from layers.toml caps whatever is set here
+        let threads = self
+            .max_threads
+            .unwrap_or(cores)
+            .min(config::extraction_concurrency().unwrap_or(cores))
+            .clamp(1, cores);
+        match self.profile {
+            ResourceProfile::Normal => (threads, self.low_priority),
+            ResourceProfile::BatterySaver => (threads.min((cores / 4).max(1)), true),
+        }
+    }
+}
+
+impl ResourceLimits {
+    pub fn new(path: PathBuf) -> Self {
+        let settings: ResourceSettings = fs::read(&path)
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default();
+        let limits = ResourceLimits {
+            path,
+            settings: Mutex::new(settings),
+        };
+        if let Err(e) = limits.apply() {
+            warn!("Failed to apply resource limits: {}", e);
+        }
+        limits
+    }
+
+    fn status(&self) -> ResourceStatus {
+        let settings = self.settings.lock().unwrap().clone();
+        let cores = available_cores();
+        let (threads, low_priority) = settings.effective(cores);
+        ResourceStatus {
+            settings,
+            cores,
+            threads,
+            low_priority,
+        }
+    }
+
+    // A new pool is built on every change, threads that lowered their own
+    // priority can't raise it again
+    pub(crate) fn apply(&self) -> Result<(), String> {
+        let (threads, low_priority) = self.settings.lock().unwrap().effective(available_cores());
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("layers-scan-{}", index))
+            .start_handler(move |_| {
+                if low_priority {
+                    lower_thread_priority();
+                }
+            })
+            .build()
+            .map_err(|e| format!("Failed to start scan threads: {}", e))?;
+
+        *POOL.write().unwrap() = Some(Arc::new(pool));
+        LOW_PRIORITY.store(low_priority, Ordering::Relaxed);
+        info!(
+            "Scans use {} threads{}",
+            threads,
+            if low_priority { " at low priority" } else { "" }
+        );
+        Ok(())
+    }
+}
+
+fn available_cores() -> usize {
+    thread::available_parallelism()
+        .map(|cores| cores.get())
+        .unwrap_or(1)
+}
+
+// Run parallel scan work on the pool sized by the settings
+pub(crate) fn install<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    let pool = POOL.read().unwrap().clone();
+    match pool {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+// Linux priorities are per thread, so only the scan workers are affected
+#[cfg(target_os = "linux")]
+fn lower_thread_priority() {
+    // ioprio_set isn't wrapped by libc, the idle class only gets disk time
+    // no one else wants
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, 0, LOW_PRIORITY_NICE);
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        );
+    }
+}
+
+// The background band throttles both CPU and IO of the thread
+#[cfg(target_os = "macos")]
+fn lower_thread_priority() {
+    unsafe {
+        libc::setpriority(libc::PRIO_DARWIN_THREAD, 0, libc::PRIO_DARWIN_BG);
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn lower_thread_priority() {}
+
+// Whether a wrapper runs here, `true` stands in for the wrapped command
+fn wrapper_runs(wrapper: &[String]) -> bool {
+    let Some((program, args)) = wrapper.split_first() else {
+        return false;
+    };
+    Command::new(program)
+        .args(args)
+        .arg("true")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+// Program and arguments that lower the priority of a command, probed once.
+// Minimal hosts and some sandboxes have no ionice or refuse the idle class,
+// commands run under nice alone then, or unwrapped. Empty when nothing works.
+fn priority_wrapper() -> &'static [String] {
+    PRIORITY_WRAPPER.get_or_init(|| {
+        let nice = format!("nice -n {}", LOW_PRIORITY_NICE);
+        let candidates = if cfg!(target_os = "linux") {
+            vec![format!("ionice -c 3 {}", nice), nice]
+        } else if cfg!(target_os = "macos") {
+            vec!["taskpolicy -b".to_string()]
+        } else {
+            Vec::new()
+        };
+        let wrapper = candidates
+            .iter()
+            .map(|candidate| candidate.split(' ').map(str::to_string).collect::<Vec<_>>())
+            .find(|wrapper| wrapper_runs(wrapper))
+            .unwrap_or_default();
+        if wrapper.is_empty() && !candidates.is_empty() {
+            warn!("No priority tool works here, throttled tools run at normal priority");
+        }
+        wrapper
+    })
+}
+
+// Whether `program` names an executable file, directly or on the PATH
+fn on_path(program: &OsStr) -> bool {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return program.is_file();
+    }
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+// The same command run through nice and ionice, or taskpolicy on macOS.
+// None when throttling is off or no such tool works here.
+pub(crate) fn low_priority_command(command: &Command) -> Option<Command> {
+    if !LOW_PRIORITY.load(Ordering::Relaxed) {
+        return None;
+    }
+    // Wrapped, a missing program would look like a failed run of the wrapper
+    // instead of failing to spawn
+    if !on_path(command.get_program()) {
+        return None;
+    }
+    let (program, args) = priority_wrapper().split_first()?;
+
+    let mut wrapped = Command::new(program);
+    wrapped.args(args);
+    wrapped.arg(command.get_program()).args(command.get_args());
+    if let Some(dir) = command.get_current_dir() {
+        wrapped.current_dir(dir);
+    }
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value) => wrapped.env(key, value),
+            None => wrapped.env_remove(key),
+        };
+    }
+    Some(wrapped)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_resource_settings(
+    resources: tauri::State<'_, ResourceLimits>,
+) -> Result<ResourceStatus, LayersError> {
+    Ok(resources.status())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_resource_settings(
+    resources: tauri::State<'_, ResourceLimits>,
+    settings: ResourceSettings,
+) -> Result<ResourceStatus, LayersError> {
+    info!("Setting resource limits: {:?}", settings);
+
+    if let Some(parent) = resources.path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+    }
+    let content = serde_json::to_vec_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize resource settings: {}", e))?;
+    audit::write_atomically(&resources.path, content)
+        .map_err(|e| format!("Failed to save resource settings: {}", e))?;
+
+    *resources.settings.lock().unwrap() = settings;
+    resources.apply()?;
+    Ok(resources.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn on_path_finds_programs_by_name_and_path() {
+        assert!(on_path(OsStr::new("sh")));
+        assert!(on_path(OsStr::new("/bin/sh")));
+        assert!(!on_path(OsStr::new("layers-no-such-tool")));
+        assert!(!on_path(OsStr::new("/no/such/dir/sh")));
+    }
+
+    #[test]
+    fn the_priority_wrapper_runs_or_is_empty() {
+        let wrapper = priority_wrapper();
+        assert!(wrapper.is_empty() || wrapper_runs(wrapper));
+    }
+}
@@ -0,0 +1,191 @@
+use layers_core::docker::docker_command;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::archive_loader::{archive_layers, flatten_layers};
+use crate::error::LayersError;
+use crate::exec_safety;
+use crate::finish_task;
+use crate::phases::{phase, Phase, PhaseProgress};
+use crate::search_index::filesystem_layers;
+use crate::session::SessionState;
+use crate::tasks::{Task, TaskRegistry};
+
+const ROOTFS_EXPORT_PHASES: &[Phase] = &[
+    phase("fetch", 0.4),
+    phase("merge", 0.5),
+    phase("write", 0.1),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RootfsFormat {
+    #[default]
+    Tar,
+    Directory,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RootfsExportResult {
+    destination: String,
+    format: RootfsFormat,
+    // The filesystem layers that were applied, base layer first
+    layers: Vec<String>,
+    size_bytes: u64,
+}
+
+// Number of a "layer_N" ID, layer_1 is the newest
+pub(crate) fn layer_number(layer_id: &str) -> Result<usize, String> {
+    layer_id
+        .strip_prefix("layer_")
+        .unwrap_or(layer_id)
+        .parse()
+        .map_err(|_| format!("Invalid layer ID: {}", layer_id))
+}
+
+// Unpack `docker save` of the image into `dir` without a temporary tarball
+pub(crate) fn save_image(task: &Task, image: &str, dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    task.stream(docker_command().args(["save", image]), |stdout| {
+        tar::Archive::new(stdout)
+            .unpack(dir)
+            .map_err(|e| format!("Failed to unpack docker save output: {}", e))
+    })
+    .map_err(|e| format!("Failed to save image {}: {}", image, e))?;
+    exec_safety::strip_execute_bits(dir);
+    Ok(())
+}
+
+fn export_rootfs_task(
+    task: &Task,
+    progress: &PhaseProgress,
+    image: &str,
+    up_to_layer: &str,
+    destination: &Path,
+    format: RootfsFormat,
+) -> Result<RootfsExportResult, String> {
+    // Metadata-only entries have no layer of their own, exporting up to one
+    // applies the filesystem layers below it
+    let number = layer_number(up_to_layer)?;
+    let layers: Vec<String> = filesystem_layers(image)?
+        .into_iter()
+        .map(|(layer_id, _)| layer_id)
+        .filter(|layer_id| layer_number(layer_id).is_ok_and(|n| n >= number))
+        .collect();
+    if layers.is_empty() {
+        return Err(format!("No filesystem layers up to {}", up_to_layer));
+    }
+
+    progress.begin("fetch", "Saving image...");
+    let save_dir = std::env::temp_dir().join(format!("layers-rootfs-{}", task.id));
+    task.track_path(&save_dir);
+    let result = save_image(task, image, &save_dir)
+        .and_then(|()| archive_layers(&save_dir))
+        .and_then(|blobs| {
+            if blobs.len() < layers.len() {
+                return Err("Image has fewer layers than its history lists".to_string());
+            }
+            progress.begin("merge", &format!("Applying {} layers...", layers.len()));
+            match format {
+                RootfsFormat::Tar => {
+                    flatten_layers(task, &blobs[..layers.len()], destination).map(|_| ())
+                }
+                RootfsFormat::Directory => {
+                    let tar_path = save_dir.join("rootfs.tar");
+                    flatten_layers(task, &blobs[..layers.len()], &tar_path)?;
+                    progress.begin("write", "Writing directory...");
+                    unpack_rootfs(&tar_path, destination)
+                }
+            }
+        });
+    let _ = fs::remove_dir_all(&save_dir);
+    result?;
+
+    let size_bytes = match format {
+        RootfsFormat::Tar => fs::metadata(destination).map(|m| m.len()).unwrap_or(0),
+        RootfsFormat::Directory => directory_size(destination),
+    };
+    Ok(RootfsExportResult {
+        destination: destination.to_string_lossy().to_string(),
+        format,
+        layers,
+        size_bytes,
+    })
+}
+
+fn unpack_rootfs(tar_path: &Path, destination: &Path) -> Result<(), String> {
+    fs::create_dir_all(destination)
+        .map_err(|e| format!("Failed to create {:?}: {}", destination, e))?;
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+    archive.set_preserve_permissions(true);
+    archive
+        .unpack(destination)
+        .map_err(|e| format!("Failed to write {:?}: {}", destination, e))?;
+    exec_safety::strip_execute_bits(destination);
+    Ok(())
+}
+
+fn directory_size(dir: &Path) -> u64 {
+    let mut pending: Vec<PathBuf> = vec![dir.to_path_buf()];
+    let mut size = 0;
+    while let Some(path) = pending.pop() {
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = fs::read_dir(&path) {
+                pending.extend(entries.flatten().map(|entry| entry.path()));
+            }
+        } else {
+            size += metadata.len();
+        }
+    }
+    size
+}
+
+// Write the filesystem a container of the image would have seen right after
+// `up_to_layer` was built, as a single tar or a directory. Layers are
+// applied base first with their whiteouts.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn export_flattened_rootfs(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+    up_to_layer: String,
+    destination: String,
+    format: Option<RootfsFormat>,
+) -> Result<RootfsExportResult, LayersError> {
+    let image = session.image_or_selected(image)?;
+    let format = format.unwrap_or_default();
+    info!(
+        "Exporting rootfs of {} up to {} to {} ({:?})",
+        image, up_to_layer, destination, format
+    );
+
+    let task = tasks.start();
+    let progress = PhaseProgress::new(&window, task.id, ROOTFS_EXPORT_PHASES);
+    let result = export_rootfs_task(
+        &task,
+        &progress,
+        &image,
+        &up_to_layer,
+        Path::new(&destination),
+        format,
+    );
+    match &result {
+        Ok(export) => progress.complete(&format!(
+            "Exported {} layers to {}",
+            export.layers.len(),
+            destination
+        )),
+        Err(e) => progress.fail(e),
+    }
+    finish_task(&window, &tasks, &task);
+    Ok(result?)
+}
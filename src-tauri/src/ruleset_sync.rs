@@ -0,0 +1,432 @@
+use layers_core::lint::LintConfig;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::health::ScoringWeights;
+
+// Files a shared ruleset may hold, each one optional
+const LINT_FILE: &str = "lint.json";
+const SCORING_FILE: &str = "scoring.json";
+// Rule IDs or finding anchors to drop, one per line, "#" starts a comment
+const IGNORE_FILE: &str = "ignore";
+const RULESET_FILES: [&str; 3] = [LINT_FILE, SCORING_FILE, IGNORE_FILE];
+
+const DEFAULT_INTERVAL_MINUTES: u64 = 60;
+// How often the background thread checks whether a pull is due
+const TICK: Duration = Duration::from_secs(60);
+
+// Linting and scoring run in helpers without access to Tauri state, so the
+// synced ruleset lives here once it's loaded
+static RULESET: RwLock<Option<TeamRuleset>> = RwLock::new(None);
+static PATHS: RwLock<Option<(PathBuf, PathBuf)>> = RwLock::new(None);
+static STATUS: Mutex<SyncState> = Mutex::new(SyncState {
+    last_attempt_ms: None,
+    last_synced_ms: None,
+    last_error: None,
+    revision: None,
+});
+// Keeps the timer and "Sync now" from pulling at the same time
+static SYNC_LOCK: Mutex<()> = Mutex::new(());
+
+// Where the team ruleset comes from, a git repository or an HTTPS directory
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RulesetSyncSettings {
+    // None turns syncing off and goes back to the local settings
+    url: Option<String>,
+    interval_minutes: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TeamRuleset {
+    lint: Option<LintConfig>,
+    scoring: Option<ScoringWeights>,
+    ignored: Vec<String>,
+}
+
+struct SyncState {
+    last_attempt_ms: Option<u64>,
+    last_synced_ms: Option<u64>,
+    last_error: Option<String>,
+    // Commit of the checkout, None for HTTPS sources
+    revision: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RulesetSyncStatus {
+    url: Option<String>,
+    interval_minutes: u64,
+    last_synced_ms: Option<u64>,
+    last_error: Option<String>,
+    revision: Option<String>,
+    // Which parts of the local settings the team ruleset replaces
+    lint_config: bool,
+    scoring_weights: bool,
+    ignored: usize,
+}
+
+impl Default for RulesetSyncSettings {
+    fn default() -> Self {
+        RulesetSyncSettings {
+            url: None,
+            interval_minutes: DEFAULT_INTERVAL_MINUTES,
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn paths() -> Option<(PathBuf, PathBuf)> {
+    PATHS.read().unwrap().clone()
+}
+
+fn load_settings() -> RulesetSyncSettings {
+    paths()
+        .and_then(|(settings_path, _)| fs::read(settings_path).ok())
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default()
+}
+
+// Sources are fetched over TLS or SSH only, a ruleset from a plain http://
+// or git:// URL could be swapped on the way
+const SUPPORTED_SCHEMES: [&str; 3] = ["https://", "ssh://", "git@"];
+
+fn validate_url(url: &str) -> Result<(), String> {
+    if SUPPORTED_SCHEMES
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unsupported ruleset URL: {}, use https://, ssh:// or git@",
+            url
+        ))
+    }
+}
+
+// HTTPS URLs are directories of ruleset files unless they name a repository
+fn is_git_url(url: &str) -> bool {
+    url.starts_with("git@") || url.starts_with("ssh://") || url.ends_with(".git")
+}
+
+fn run(command: &mut Command) -> Result<String, String> {
+    let result = command.output();
+    audit::record_command(command, &result);
+    let output = result.map_err(|e| format!("Failed to run {:?}: {}", command.get_program(), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{:?} failed: {}",
+            command.get_program(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Clones on first use, or when the URL changed, and fast-forwards after that.
+// Returns the commit checked out.
+fn pull_git(url: &str, checkout: &Path) -> Result<String, String> {
+    let origin = run(Command::new("git").arg("-C").arg(checkout).args([
+        "config",
+        "--get",
+        "remote.origin.url",
+    ]))
+    .ok();
+    if origin.as_deref() == Some(url) {
+        run(Command::new("git")
+            .arg("-C")
+            .arg(checkout)
+            .args(["pull", "--ff-only", "--quiet"]))?;
+    } else {
+        if checkout.exists() {
+            fs::remove_dir_all(checkout)
+                .map_err(|e| format!("Failed to remove {:?}: {}", checkout, e))?;
+        }
+        run(Command::new("git")
+            // "--" keeps a URL starting with a dash from being read as an option
+            .args(["clone", "--depth", "1", "--quiet", "--", url])
+            .arg(checkout))?;
+    }
+    run(Command::new("git")
+        .arg("-C")
+        .arg(checkout)
+        .args(["rev-parse", "HEAD"]))
+}
+
+// Downloads every ruleset file next to `url`. A 404 means the team doesn't
+// share that file, so the local copy goes too.
+fn pull_https(url: &str, checkout: &Path) -> Result<(), String> {
+    fs::create_dir_all(checkout)
+        .map_err(|e| format!("Failed to create directory {:?}: {}", checkout, e))?;
+    for file in RULESET_FILES {
+        let target = checkout.join(file);
+        let partial = checkout.join(format!("{}.part", file));
+        let status = run(Command::new("curl")
+            .args(["--silent", "--show-error", "--location"])
+            // Redirects can't downgrade the download to plain http
+            .args(["--proto", "=https", "--proto-redir", "=https"])
+            .args(["--max-time", "30", "--write-out", "%{http_code}"])
+            .arg("--output")
+            .arg(&partial)
+            .arg(format!("{}/{}", url.trim_end_matches('/'), file)))?;
+        match status.as_str() {
+            "200" => {
+                fs::rename(&partial, &target)
+                    .map_err(|e| format!("Failed to save {:?}: {}", target, e))?;
+            }
+            "404" => {
+                let _ = fs::remove_file(&partial);
+                let _ = fs::remove_file(&target);
+            }
+            _ => {
+                let _ = fs::remove_file(&partial);
+                return Err(format!("Failed to download {}: HTTP {}", file, status));
+            }
+        }
+    }
+    Ok(())
+}
+
+// A file that doesn't parse fails the whole load, half a ruleset would grade
+// images differently from the rest of the team
+fn load_ruleset(checkout: &Path) -> Result<TeamRuleset, String> {
+    fn parse<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
+        match fs::read(path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map(Some)
+                .map_err(|e| format!("Failed to parse {:?}: {}", path, e)),
+            Err(_) => Ok(None),
+        }
+    }
+    let ignored = fs::read_to_string(checkout.join(IGNORE_FILE))
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    Ok(TeamRuleset {
+        lint: parse(&checkout.join(LINT_FILE))?,
+        scoring: parse(&checkout.join(SCORING_FILE))?,
+        ignored,
+    })
+}
+
+// Pulls the configured source and swaps in the new ruleset. Without a URL the
+// team ruleset is dropped.
+fn sync() -> Result<(), String> {
+    let _guard = SYNC_LOCK.lock().unwrap();
+    let Some((_, checkout)) = paths() else {
+        return Ok(());
+    };
+    let Some(url) = load_settings().url else {
+        *RULESET.write().unwrap() = None;
+        return Ok(());
+    };
+    info!("Syncing team ruleset from {}", url);
+    STATUS.lock().unwrap().last_attempt_ms = Some(now_ms());
+
+    // The settings file may have been edited by hand since it was saved
+    let result = validate_url(&url)
+        .and_then(|()| {
+            if is_git_url(&url) {
+                pull_git(&url, &checkout).map(Some)
+            } else {
+                pull_https(&url, &checkout).map(|_| None)
+            }
+        })
+        .and_then(|revision| Ok((revision, load_ruleset(&checkout)?)));
+
+    let mut status = STATUS.lock().unwrap();
+    match result {
+        Ok((revision, ruleset)) => {
+            *RULESET.write().unwrap() = Some(ruleset);
+            status.last_synced_ms = Some(now_ms());
+            status.last_error = None;
+            status.revision = revision;
+            Ok(())
+        }
+        // The previous ruleset stays in use until a pull succeeds
+        Err(e) => {
+            status.last_error = Some(e.clone());
+            Err(e)
+        }
+    }
+}
+
+fn is_due() -> bool {
+    let interval_ms = load_settings().interval_minutes.max(1) * 60_000;
+    STATUS
+        .lock()
+        .unwrap()
+        .last_attempt_ms
+        .is_none_or(|last| now_ms().saturating_sub(last) >= interval_ms)
+}
+
+// Loads the last synced ruleset and starts pulling in the background
+pub fn init(settings_path: PathBuf, checkout: PathBuf) {
+    info!("Reading team ruleset settings from {:?}", settings_path);
+    *PATHS.write().unwrap() = Some((settings_path, checkout.clone()));
+    if load_settings().url.is_some() {
+        match load_ruleset(&checkout) {
+            Ok(ruleset) => *RULESET.write().unwrap() = Some(ruleset),
+            Err(e) => warn!("{}", e),
+        }
+    }
+    thread::spawn(|| loop {
+        if load_settings().url.is_some() && is_due() {
+            if let Err(e) = sync() {
+                warn!("{}", e);
+            }
+        }
+        thread::sleep(TICK);
+    });
+}
+
+// The team's lint settings when it shares them, the local ones otherwise
+pub(crate) fn lint_config(local: Option<LintConfig>) -> LintConfig {
+    let ruleset = RULESET.read().unwrap();
+    let Some(ruleset) = ruleset.as_ref() else {
+        return local.unwrap_or_default();
+    };
+    let mut config = ruleset.lint.clone().or(local).unwrap_or_default();
+    config
+        .disabled_rules
+        .extend(ruleset.ignored.iter().cloned());
+    config
+}
+
+// The team's scoring weights, None when it doesn't share them
+pub(crate) fn scoring_weights() -> Option<ScoringWeights> {
+    RULESET.read().unwrap().as_ref()?.scoring.clone()
+}
+
+// Whether the team ignores a finding, by rule ID or by anchor
+pub(crate) fn is_ignored(rule_id: &str, anchor: &str) -> bool {
+    RULESET.read().unwrap().as_ref().is_some_and(|ruleset| {
+        ruleset
+            .ignored
+            .iter()
+            .any(|ignored| ignored == rule_id || ignored == anchor)
+    })
+}
+
+fn status() -> RulesetSyncStatus {
+    let settings = load_settings();
+    let ruleset = RULESET.read().unwrap().clone().unwrap_or_default();
+    let state = STATUS.lock().unwrap();
+    RulesetSyncStatus {
+        url: settings.url,
+        interval_minutes: settings.interval_minutes,
+        last_synced_ms: state.last_synced_ms,
+        last_error: state.last_error.clone(),
+        revision: state.revision.clone(),
+        lint_config: ruleset.lint.is_some(),
+        scoring_weights: ruleset.scoring.is_some(),
+        ignored: ruleset.ignored.len(),
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_ruleset_sync() -> Result<RulesetSyncStatus, LayersError> {
+    Ok(status())
+}
+
+// Saves the source and pulls from it right away, so a wrong URL shows up in
+// the settings screen instead of on the next timer tick
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn set_ruleset_sync(
+    settings: RulesetSyncSettings,
+) -> Result<RulesetSyncStatus, LayersError> {
+    info!("Setting team ruleset source: {:?}", settings);
+    let (settings_path, _) =
+        paths().ok_or_else(|| "Team ruleset sync is not initialized".to_string())?;
+    let settings = RulesetSyncSettings {
+        url: settings
+            .url
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty()),
+        interval_minutes: settings.interval_minutes.max(1),
+    };
+    if let Some(url) = &settings.url {
+        validate_url(url)?;
+    }
+
+    if let Some(parent) = settings_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+    }
+    let content = serde_json::to_vec_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize ruleset settings: {}", e))?;
+    audit::write_atomically(&settings_path, content)
+        .map_err(|e| format!("Failed to save ruleset settings: {}", e))?;
+
+    *STATUS.lock().unwrap() = SyncState {
+        last_attempt_ms: None,
+        last_synced_ms: None,
+        last_error: None,
+        revision: None,
+    };
+    // Rules from the previous source stop applying even if this pull fails
+    *RULESET.write().unwrap() = None;
+    sync()?;
+    Ok(status())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn sync_ruleset_now() -> Result<RulesetSyncStatus, LayersError> {
+    sync()?;
+    Ok(status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_tls_and_ssh_sources_are_accepted() {
+        for url in [
+            "https://example.com/rulesets/team",
+            "https://example.com/team/rules.git",
+            "ssh://git@example.com/team/rules.git",
+            "git@example.com:team/rules.git",
+        ] {
+            assert!(validate_url(url).is_ok(), "{}", url);
+        }
+        for url in [
+            "http://example.com/rulesets/team",
+            "git://example.com/team/rules.git",
+            "file:///tmp/rules.git",
+            "--upload-pack=touch /tmp/x;.git",
+            "rules.git",
+        ] {
+            assert!(validate_url(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn git_urls_are_told_apart_from_https_directories() {
+        assert!(is_git_url("https://example.com/team/rules.git"));
+        assert!(is_git_url("ssh://git@example.com/team/rules"));
+        assert!(is_git_url("git@example.com:team/rules"));
+        assert!(!is_git_url("https://example.com/rulesets/team"));
+        assert!(!is_git_url("https://example.com/rulesets/team/"));
+    }
+}
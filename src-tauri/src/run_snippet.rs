@@ -0,0 +1,299 @@
+use layers_core::docker::inspect_image;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::pull::split_reference;
+use crate::session::SessionState;
+
+// Environment variables every base image sets, repeating them is just noise
+const SKIPPED_ENV: [&str; 3] = ["PATH", "HOSTNAME", "HOME"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunSnippets {
+    image: String,
+    docker_run: String,
+    compose: String,
+}
+
+struct RunConfig {
+    image: String,
+    service_name: String,
+    ports: Vec<String>,
+    // (volume name, container path)
+    volumes: Vec<(String, String)>,
+    env: Vec<(String, String)>,
+    user: Option<String>,
+}
+
+fn object_keys(value: &serde_json::Value) -> Vec<String> {
+    let mut keys: Vec<String> = value
+        .as_object()
+        .map(|map| map.keys().cloned().collect())
+        .unwrap_or_default();
+    keys.sort();
+    keys
+}
+
+fn read_run_config(image: &str) -> Result<RunConfig, String> {
+    let inspect = inspect_image(image)?;
+    let config = &inspect["Config"];
+
+    // The session passes an image ID, snippets read better with the tag
+    let image_ref = inspect["RepoTags"]
+        .as_array()
+        .and_then(|tags| tags.first())
+        .and_then(|tag| tag.as_str())
+        .map(String::from)
+        .unwrap_or_else(|| image.to_string());
+
+    // "registry:5000/team/app:1.0" -> "app"
+    let (repository, _) = split_reference(&image_ref);
+    let repository = repository.split('@').next().unwrap_or(repository);
+    let service_name: String = repository
+        .rsplit('/')
+        .next()
+        .unwrap_or("app")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+
+    // "8080/tcp" -> "8080:8080", "53/udp" -> "53:53/udp"
+    let ports = object_keys(&config["ExposedPorts"])
+        .into_iter()
+        .map(|port| match port.split_once('/') {
+            Some((number, "tcp")) => format!("{}:{}", number, number),
+            Some((number, protocol)) => format!("{}:{}/{}", number, number, protocol),
+            None => format!("{}:{}", port, port),
+        })
+        .collect();
+
+    let service_name = if service_name.is_empty() {
+        "app".to_string()
+    } else {
+        service_name
+    };
+    let volumes = named_volumes(&service_name, object_keys(&config["Volumes"]));
+
+    let env = config["Env"]
+        .as_array()
+        .map(|vars| {
+            vars.iter()
+                .filter_map(|v| v.as_str())
+                .filter_map(|v| v.split_once('='))
+                .filter(|(key, _)| !SKIPPED_ENV.contains(key))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let user = config["User"]
+        .as_str()
+        .filter(|u| !u.is_empty())
+        .map(String::from);
+
+    Ok(RunConfig {
+        image: image_ref,
+        service_name,
+        ports,
+        volumes,
+        env,
+        user,
+    })
+}
+
+fn shell_quote(value: &str) -> String {
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if is_safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+// A named volume for every declared path, e.g. "/var/lib/data" ->
+// "app-var-lib-data". "/data" and "/data/" are the same path, names that
+// still collide, like those of "/var-lib" and "/var/lib", get a number.
+fn named_volumes(service_name: &str, paths: Vec<String>) -> Vec<(String, String)> {
+    let mut volumes: Vec<(String, String)> = Vec::new();
+    for path in paths {
+        let trimmed = path.trim_matches('/');
+        let path = format!("/{}", trimmed);
+        if volumes.iter().any(|(_, existing)| *existing == path) {
+            continue;
+        }
+        let suffix: String = if trimmed.is_empty() {
+            "root".to_string()
+        } else {
+            trimmed
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+                .collect()
+        };
+        let base = format!("{}-{}", service_name, suffix);
+        let mut name = base.clone();
+        let mut count = 1;
+        while volumes.iter().any(|(existing, _)| *existing == name) {
+            count += 1;
+            name = format!("{}-{}", base, count);
+        }
+        volumes.push((name, path));
+    }
+    volumes
+}
+
+fn render_docker_run(config: &RunConfig) -> String {
+    let mut args = vec![
+        "docker run --rm -it".to_string(),
+        format!("--name {}", config.service_name),
+    ];
+
+    for port in &config.ports {
+        args.push(format!("-p {}", port));
+    }
+    for (name, path) in &config.volumes {
+        args.push(format!("-v {}:{}", name, shell_quote(path)));
+    }
+    for (key, value) in &config.env {
+        args.push(format!("-e {}", shell_quote(&format!("{}={}", key, value))));
+    }
+    if let Some(user) = &config.user {
+        args.push(format!("--user {}", shell_quote(user)));
+    }
+    args.push(shell_quote(&config.image));
+
+    args.join(" \\\n  ")
+}
+
+// Compose interpolates "$VAR" in every value, "$$" is a literal dollar
+fn yaml_quote(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('$', "$$")
+    )
+}
+
+fn render_compose(config: &RunConfig) -> String {
+    let mut lines = vec![
+        "services:".to_string(),
+        format!("  {}:", config.service_name),
+        format!("    image: {}", yaml_quote(&config.image)),
+    ];
+
+    if let Some(user) = &config.user {
+        lines.push(format!("    user: {}", yaml_quote(user)));
+    }
+
+    if !config.ports.is_empty() {
+        lines.push("    ports:".to_string());
+        for port in &config.ports {
+            lines.push(format!("      - {}", yaml_quote(port)));
+        }
+    }
+
+    if !config.volumes.is_empty() {
+        lines.push("    volumes:".to_string());
+        for (name, path) in &config.volumes {
+            lines.push(format!(
+                "      - {}",
+                yaml_quote(&format!("{}:{}", name, path))
+            ));
+        }
+    }
+
+    if !config.env.is_empty() {
+        lines.push("    environment:".to_string());
+        for (key, value) in &config.env {
+            lines.push(format!("      {}: {}", key, yaml_quote(value)));
+        }
+    }
+
+    if !config.volumes.is_empty() {
+        lines.push("volumes:".to_string());
+        for (name, _) in &config.volumes {
+            lines.push(format!("  {}:", name));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn generate_run_snippets(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+) -> Result<RunSnippets, LayersError> {
+    let image = session.image_or_selected(image)?;
+    info!("Generating run snippets for {}", image);
+
+    let config = read_run_config(&image)?;
+
+    Ok(RunSnippets {
+        docker_run: render_docker_run(&config),
+        compose: render_compose(&config),
+        image: config.image,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_quote_leaves_safe_words_alone() {
+        assert_eq!(shell_quote("nginx:1.25"), "nginx:1.25");
+        assert_eq!(shell_quote("PATH=/usr/bin"), "PATH=/usr/bin");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("two words"), "'two words'");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn yaml_quote_escapes_quotes_and_interpolation() {
+        assert_eq!(yaml_quote("nginx"), "\"nginx\"");
+        assert_eq!(yaml_quote("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(yaml_quote("C:\\data"), "\"C:\\\\data\"");
+        assert_eq!(yaml_quote("${SECRET}"), "\"$${SECRET}\"");
+    }
+
+    #[test]
+    fn named_volumes_are_named_after_their_paths() {
+        let volumes = named_volumes(
+            "app",
+            vec![
+                "/var/lib/data".to_string(),
+                "/data/".to_string(),
+                "/data".to_string(),
+                "/".to_string(),
+            ],
+        );
+        assert_eq!(
+            volumes,
+            [
+                ("app-var-lib-data".to_string(), "/var/lib/data".to_string()),
+                ("app-data".to_string(), "/data".to_string()),
+                ("app-root".to_string(), "/".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn colliding_volume_names_get_a_number() {
+        let volumes = named_volumes("app", vec!["/var-lib".to_string(), "/var/lib".to_string()]);
+        assert_eq!(
+            volumes,
+            [
+                ("app-var-lib".to_string(), "/var-lib".to_string()),
+                ("app-var-lib-2".to_string(), "/var/lib".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::exec_safety::{self, ExecutionKind};
+use crate::findings::{SecurityFinding, Severity};
+use crate::finish_task;
+use crate::phases::{phase, Phase, PhaseProgress};
+use crate::session::SessionState;
+use crate::startup_check::docker_output;
+use crate::tasks::{Task, TaskRegistry};
+
+// Long enough for most apps to warm their caches and write their first logs
+const DEFAULT_DURATION_SECONDS: u64 = 30;
+const MAX_DURATION_SECONDS: u64 = 600;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Paths listed per location, the counts cover the rest
+const SAMPLE_PATHS: usize = 5;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RuntimeWritesOptions {
+    duration_seconds: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteKind {
+    // Scratch space that's fine to lose, e.g. /tmp
+    Temporary,
+    // Caches and compiled files the build could produce instead
+    Cache,
+    Logs,
+    // Config generated on start, e.g. from environment variables
+    Config,
+    // Anything else the app keeps, which is lost with the container
+    Data,
+}
+
+// Writes below one location of the container filesystem
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WriteLocation {
+    path: String,
+    kind: WriteKind,
+    added: usize,
+    changed: usize,
+    deleted: usize,
+    sample: Vec<String>,
+    suggestion: Option<String>,
+}
+
+// What a container wrote to its writable layer during a short run
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RuntimeWrites {
+    image_id: String,
+    duration_ms: u64,
+    // Stopped on its own before the run ended
+    exited: bool,
+    // Size of the writable layer, from docker inspect --size
+    bytes_written: Option<u64>,
+    locations: Vec<WriteLocation>,
+    findings: Vec<SecurityFinding>,
+}
+
+fn classify(path: &str) -> WriteKind {
+    let components: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    if ["/tmp/", "/var/tmp/", "/run/", "/var/run/", "/dev/shm/"]
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        WriteKind::Temporary
+    } else if path.ends_with(".pyc")
+        || components
+            .iter()
+            .any(|c| c.contains("cache") || *c == ".npm" || *c == ".m2" || *c == ".gradle")
+    {
+        WriteKind::Cache
+    } else if path.starts_with("/var/log/") || path.ends_with(".log") {
+        WriteKind::Logs
+    } else if path.starts_with("/etc/") {
+        WriteKind::Config
+    } else {
+        WriteKind::Data
+    }
+}
+
+// Where writes of a kind get grouped: the cache or log directory itself when
+// it's in the path, otherwise the first two levels
+fn location(path: &str, kind: WriteKind) -> String {
+    let components: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    let cache_dir = components
+        .iter()
+        .position(|c| c.contains("cache") || c.starts_with('.'));
+    let depth = match (kind, cache_dir) {
+        (WriteKind::Temporary, _) if components[0] == "var" || components[0] == "dev" => 2,
+        (WriteKind::Temporary, _) => 1,
+        (WriteKind::Cache, Some(i)) => i + 1,
+        // Files count towards their directory, unless they're right below the root
+        _ => 2.min(components.len().saturating_sub(1)).max(1),
+    };
+    format!("/{}", components[..depth].join("/"))
+}
+
+fn suggestion(kind: WriteKind, path: &str) -> Option<String> {
+    match kind {
+        WriteKind::Temporary => None,
+        WriteKind::Cache => Some(format!(
+            "Fill {} at build time, e.g. python -m compileall or a warm-up step in the Dockerfile, so every container doesn't redo it",
+            path
+        )),
+        WriteKind::Logs => Some(format!(
+            "Log to stdout and stderr instead of {}, or mount a volume there",
+            path
+        )),
+        WriteKind::Config => Some(format!(
+            "Render {} at build time or mount it, a read-only root filesystem would break this",
+            path
+        )),
+        WriteKind::Data => Some(format!(
+            "Add VOLUME {} so the data outlives the container",
+            path
+        )),
+    }
+}
+
+// "A /path", "C /path" and "D /path" lines of docker diff, without the
+// directories that only show up as changed because something below them did
+fn parse_diff(output: &str) -> Vec<(char, String)> {
+    let entries: Vec<(char, String)> = output
+        .lines()
+        .filter_map(|line| {
+            let (change, path) = line.split_once(' ')?;
+            Some((change.chars().next()?, path.to_string()))
+        })
+        .collect();
+    entries
+        .iter()
+        .filter(|(change, path)| {
+            *change != 'C'
+                || !entries
+                    .iter()
+                    .any(|(_, other)| other.starts_with(&format!("{}/", path)))
+        })
+        .cloned()
+        .collect()
+}
+
+fn group_writes(entries: &[(char, String)]) -> Vec<WriteLocation> {
+    let mut locations: BTreeMap<(WriteKind, String), WriteLocation> = BTreeMap::new();
+    for (change, path) in entries {
+        let kind = classify(path);
+        let key = location(path, kind);
+        let location = locations
+            .entry((kind, key.clone()))
+            .or_insert_with(|| WriteLocation {
+                suggestion: suggestion(kind, &key),
+                path: key,
+                kind,
+                added: 0,
+                changed: 0,
+                deleted: 0,
+                sample: Vec::new(),
+            });
+        match change {
+            'A' => location.added += 1,
+            'D' => location.deleted += 1,
+            _ => location.changed += 1,
+        }
+        if location.sample.len() < SAMPLE_PATHS {
+            location.sample.push(path.clone());
+        }
+    }
+    locations.into_values().collect()
+}
+
+fn write_findings(locations: &[WriteLocation]) -> Vec<SecurityFinding> {
+    locations
+        .iter()
+        .filter_map(|location| {
+            let (rule_id, severity, title) = match location.kind {
+                WriteKind::Temporary => return None,
+                WriteKind::Cache => (
+                    "runtime-cache-not-prebuilt",
+                    Severity::Info,
+                    format!("{} is filled at runtime", location.path),
+                ),
+                WriteKind::Logs => (
+                    "runtime-logs-in-container",
+                    Severity::Low,
+                    format!("Logs are written to {}", location.path),
+                ),
+                WriteKind::Config => (
+                    "runtime-config-written",
+                    Severity::Info,
+                    format!("Config in {} is written on start", location.path),
+                ),
+                WriteKind::Data => (
+                    "runtime-data-outside-volume",
+                    Severity::Low,
+                    format!("Data is written to {} outside a volume", location.path),
+                ),
+            };
+            Some(SecurityFinding {
+                rule_id: rule_id.to_string(),
+                severity,
+                title,
+                description: format!(
+                    "The container added {}, changed {} and deleted {} files below {} while it ran. {}.",
+                    location.added,
+                    location.changed,
+                    location.deleted,
+                    location.path,
+                    location.suggestion.as_deref().unwrap_or_default()
+                ),
+                path: Some(location.path.clone()),
+                layer_id: None,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn is_running(task: &Task, container_name: &str) -> Result<bool, String> {
+    let output = docker_output(
+        task,
+        &[
+            "container",
+            "inspect",
+            "--format",
+            "{{.State.Running}}",
+            container_name,
+        ],
+    )?;
+    Ok(output.trim() == "true")
+}
+
+// Lets the container run for `duration` or until it stops, whichever is first
+fn run_for(task: &Task, container_name: &str, duration: Duration) -> Result<bool, String> {
+    let started = Instant::now();
+    while started.elapsed() < duration {
+        task.check_cancelled()?;
+        if !is_running(task, container_name)? {
+            return Ok(true);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    Ok(false)
+}
+
+const RUNTIME_WRITES_PHASES: &[Phase] = &[
+    phase("prepare", 0.1),
+    phase("start", 0.1),
+    phase("observe", 0.5),
+    phase("analyze", 0.2),
+    phase("cleanup", 0.1),
+];
+
+fn runtime_writes_task(
+    window: &tauri::Window,
+    task: &Task,
+    image_id: &str,
+    duration: Duration,
+) -> Result<RuntimeWrites, String> {
+    let progress = PhaseProgress::new(window, task.id, RUNTIME_WRITES_PHASES);
+
+    let name = format!("layers_writes_{}", task.id);
+    // Same isolation as the startup check, nothing reaches out while it runs
+    progress.begin("prepare", "Creating isolated network");
+    docker_output(task, &["network", "create", "--internal", &name])?;
+
+    let result = (|| {
+        progress.begin("start", "Starting container");
+        let _ = audit::docker(&["rm", "-f", &name]);
+        task.track_container(&name);
+        let mut args = vec!["run", "--detach", "--name", &name, "--network", &name];
+        args.extend(exec_safety::RUN_LIMITS);
+        args.push(image_id);
+        docker_output(task, &args)?;
+
+        progress.begin("observe", "Watching what the container writes");
+        let started = Instant::now();
+        let exited = run_for(task, &name, duration)?;
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        progress.begin("analyze", "Comparing the container with the image");
+        let entries = parse_diff(&docker_output(task, &["diff", &name])?);
+        let bytes_written = docker_output(
+            task,
+            &[
+                "container",
+                "inspect",
+                "--size",
+                "--format",
+                "{{.SizeRw}}",
+                &name,
+            ],
+        )
+        .ok()
+        .and_then(|size| size.trim().parse().ok());
+
+        let locations = group_writes(&entries);
+        Ok(RuntimeWrites {
+            image_id: image_id.to_string(),
+            duration_ms,
+            exited,
+            bytes_written,
+            findings: write_findings(&locations),
+            locations,
+        })
+    })();
+
+    progress.begin("cleanup", "Removing container");
+    let _ = audit::docker(&["rm", "-f", &name]);
+    let _ = audit::docker(&["network", "rm", &name]);
+    result
+}
+
+// Runs the selected image for a while and reports what it writes outside its
+// volumes, with a VOLUME or build-time suggestion for each location
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn check_runtime_writes(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    options: Option<RuntimeWritesOptions>,
+) -> Result<RuntimeWrites, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let image_id = session.image_id().to_string();
+    let duration = options
+        .unwrap_or_default()
+        .duration_seconds
+        .unwrap_or(DEFAULT_DURATION_SECONDS)
+        .clamp(1, MAX_DURATION_SECONDS);
+    info!("Watching runtime writes of {} for {}s", image_id, duration);
+    exec_safety::confirm_execution(
+        &window,
+        ExecutionKind::RunImage,
+        &format!("Run {} for {}s", session.reference(), duration),
+    )?;
+
+    let task = tasks.start();
+    let result = runtime_writes_task(&window, &task, &image_id, Duration::from_secs(duration));
+    finish_task(&window, &tasks, &task);
+    let writes = result?;
+
+    info!(
+        "{} locations written at runtime, {:?} bytes",
+        writes.locations.len(),
+        writes.bytes_written
+    );
+    Ok(writes)
+}
@@ -0,0 +1,938 @@
+use layers_core::docker::docker_command;
+use layers_core::layer_tar::entry_relative_path;
+use object::{Object, ObjectSection};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::exec_safety;
+use crate::java_packages;
+use crate::os_packages::{
+    self, InstalledPackage, PackageFormat, APK_INSTALLED_PATH, DPKG_STATUS_PATH, RPM_BDB_PATH,
+    RPM_SQLITE_PATH,
+};
+use crate::phases::{phase, Phase, PhaseProgress};
+use crate::session::SessionState;
+use crate::tasks::{Task, TaskRegistry};
+use crate::{finish_task, layer_tar_path};
+
+// Scratch directory below the extraction root
+const SBOM_DIR: &str = "sbom";
+// Skip huge executables, build info sits in the first few megabytes anyway
+const MAX_BINARY_SIZE: u64 = 256 * 1024 * 1024;
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const GO_BUILDINFO_MAGIC: &[u8] = b"\xff Go buildinf:";
+// Section written by cargo-auditable
+const RUST_AUDIT_SECTION: &str = ".dep-v0";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SbomFormat {
+    Spdx,
+    CycloneDx,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Ecosystem {
+    Deb,
+    Apk,
+    Rpm,
+    Pypi,
+    Npm,
+    Golang,
+    Cargo,
+    Maven,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SbomComponent {
+    pub name: String,
+    pub version: String,
+    pub ecosystem: Ecosystem,
+    pub purl: String,
+    pub license: Option<String>,
+    // Source package of distribution packages, e.g. "openssl" for libssl3
+    pub source: Option<String>,
+    // Container path of the database, metadata file or binary it was found in
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Distro {
+    pub id: String,
+    pub version_id: Option<String>,
+    pub pretty_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct PackageInventory {
+    pub distro: Option<Distro>,
+    pub components: Vec<SbomComponent>,
+    // Databases that were found but couldn't be read
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SbomResult {
+    format: SbomFormat,
+    subject: String,
+    components: usize,
+    warnings: Vec<String>,
+    destination: Option<String>,
+    document: serde_json::Value,
+}
+
+// Dependency list embedded by cargo-auditable
+#[derive(Debug, Deserialize)]
+struct RustAuditData {
+    packages: Vec<RustAuditPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RustAuditPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    kind: Option<String>,
+}
+
+fn parse_os_release(content: &str) -> Option<Distro> {
+    let values: HashMap<&str, String> = content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim().trim_matches('"').to_string()))
+        .collect();
+    Some(Distro {
+        id: values.get("ID")?.clone(),
+        version_id: values.get("VERSION_ID").cloned(),
+        pretty_name: values.get("PRETTY_NAME").cloned(),
+    })
+}
+
+// Package URLs, see https://github.com/package-url/purl-spec
+fn os_purl(package: &InstalledPackage, distro: &str) -> String {
+    let kind = match package.format {
+        PackageFormat::Deb => "deb",
+        PackageFormat::Apk => "apk",
+        PackageFormat::Rpm => "rpm",
+    };
+    let mut purl = format!(
+        "pkg:{}/{}/{}@{}",
+        kind, distro, package.name, package.version
+    );
+    if let Some(architecture) = &package.architecture {
+        purl.push_str(&format!("?arch={}", architecture));
+    }
+    purl
+}
+
+pub(crate) fn component(
+    name: &str,
+    version: &str,
+    ecosystem: Ecosystem,
+    license: Option<String>,
+    path: &str,
+) -> SbomComponent {
+    let purl = match ecosystem {
+        // PyPI names are case insensitive and normalized to dashes
+        Ecosystem::Pypi => format!(
+            "pkg:pypi/{}@{}",
+            name.to_lowercase().replace(['_', '.'], "-"),
+            version
+        ),
+        Ecosystem::Npm => format!("pkg:npm/{}@{}", name.replace('@', "%40"), version),
+        Ecosystem::Golang => format!("pkg:golang/{}@{}", name, version),
+        Ecosystem::Cargo => format!("pkg:cargo/{}@{}", name, version),
+        // Maven names are group:artifact, or just the artifact when the group is unknown
+        Ecosystem::Maven => format!("pkg:maven/{}@{}", name.replace(':', "/"), version),
+        _ => format!("pkg:generic/{}@{}", name, version),
+    };
+    SbomComponent {
+        name: name.to_string(),
+        version: version.to_string(),
+        ecosystem,
+        purl,
+        license,
+        source: None,
+        path: format!("/{}", path),
+    }
+}
+
+// METADATA and PKG-INFO are email style headers, the body starts after a blank line
+fn parse_python_metadata(path: &str, content: &str) -> Option<SbomComponent> {
+    let mut name = None;
+    let mut version = None;
+    let mut license = None;
+    for line in content.lines() {
+        if line.is_empty() {
+            break;
+        }
+        match line.split_once(": ") {
+            Some(("Name", value)) => name = Some(value.trim()),
+            Some(("Version", value)) => version = Some(value.trim()),
+            Some(("License-Expression", value)) => license = Some(value.trim().to_string()),
+            Some(("License", value)) if license.is_none() && value.trim() != "UNKNOWN" => {
+                license = Some(value.trim().to_string())
+            }
+            _ => {}
+        }
+    }
+    Some(component(name?, version?, Ecosystem::Pypi, license, path))
+}
+
+// Only package.json files directly inside node_modules/<name> or node_modules/@scope/<name>
+fn is_npm_manifest(path: &str) -> bool {
+    let Some(package_dir) = path
+        .strip_suffix("/package.json")
+        .and_then(|dir| dir.rsplit_once("node_modules/").map(|(_, rest)| rest))
+    else {
+        return false;
+    };
+    let depth = package_dir.split('/').count();
+    depth == 1 || (depth == 2 && package_dir.starts_with('@'))
+}
+
+fn parse_npm_manifest(path: &str, content: &str) -> Option<SbomComponent> {
+    let manifest: serde_json::Value = serde_json::from_str(content).ok()?;
+    let license = match &manifest["license"] {
+        serde_json::Value::String(license) => Some(license.clone()),
+        serde_json::Value::Object(license) => license
+            .get("type")
+            .and_then(|t| t.as_str())
+            .map(String::from),
+        _ => None,
+    };
+    Some(component(
+        manifest["name"].as_str()?,
+        manifest["version"].as_str()?,
+        Ecosystem::Npm,
+        license,
+        path,
+    ))
+}
+
+fn read_uvarint(data: &[u8], cursor: &mut usize) -> Option<usize> {
+    let mut value: usize = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*cursor)?;
+        *cursor += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+        if shift > 63 {
+            return None;
+        }
+    }
+}
+
+// Go 1.18+ binaries embed the module graph after a "\xff Go buildinf:" header.
+// Older binaries store pointers instead of inline strings and are skipped.
+fn parse_go_buildinfo(path: &str, data: &[u8]) -> Vec<SbomComponent> {
+    let Some(start) = data
+        .windows(GO_BUILDINFO_MAGIC.len())
+        .position(|w| w == GO_BUILDINFO_MAGIC)
+    else {
+        return Vec::new();
+    };
+    let flags = data.get(start + 15).copied().unwrap_or(0);
+    if flags & 0x2 == 0 {
+        return Vec::new();
+    }
+
+    let mut cursor = start + 32;
+    let mut read_bytes = || {
+        let length = read_uvarint(data, &mut cursor)?;
+        let bytes = data.get(cursor..cursor + length)?;
+        cursor += length;
+        Some(bytes)
+    };
+    let Some(go_version) = read_bytes().map(|b| String::from_utf8_lossy(b).to_string()) else {
+        return Vec::new();
+    };
+    let mut modinfo = read_bytes().unwrap_or_default();
+    // The module info is wrapped in 16 byte sentinels
+    if modinfo.len() >= 33 && modinfo[modinfo.len() - 17] == b'\n' {
+        modinfo = &modinfo[16..modinfo.len() - 16];
+    }
+    let modinfo = String::from_utf8_lossy(modinfo);
+
+    let mut components = vec![component(
+        "stdlib",
+        go_version.trim_start_matches("go"),
+        Ecosystem::Golang,
+        None,
+        path,
+    )];
+    for line in modinfo.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if (fields[0] == "mod" || fields[0] == "dep") && fields.len() >= 3 {
+            components.push(component(
+                fields[1],
+                fields[2],
+                Ecosystem::Golang,
+                None,
+                path,
+            ));
+        }
+    }
+    components
+}
+
+fn parse_rust_audit_data(path: &str, data: &[u8]) -> Option<Vec<SbomComponent>> {
+    let file = object::File::parse(data).ok()?;
+    let section = file.section_by_name(RUST_AUDIT_SECTION)?;
+    let mut json = Vec::new();
+    flate2::read::ZlibDecoder::new(section.data().ok()?)
+        .read_to_end(&mut json)
+        .ok()?;
+    let audit: RustAuditData = serde_json::from_slice(&json).ok()?;
+    Some(
+        audit
+            .packages
+            .iter()
+            .filter(|p| p.kind.as_deref() != Some("build"))
+            .map(|p| component(&p.name, &p.version, Ecosystem::Cargo, None, path))
+            .collect(),
+    )
+}
+
+// Without cargo-auditable, panic locations still name the crates.io sources
+// a binary was built from, e.g. ".cargo/registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.197/"
+fn guess_rust_crates(path: &str, data: &[u8]) -> Vec<SbomComponent> {
+    static PATTERN: OnceLock<regex::bytes::Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        regex::bytes::Regex::new(
+            r"\.cargo/registry/src/[^/\x00]+/([A-Za-z0-9_-]+)-(\d+\.\d+\.\d+[A-Za-z0-9.+-]*)/",
+        )
+        .unwrap()
+    });
+    let crates: BTreeSet<(String, String)> = pattern
+        .captures_iter(data)
+        .map(|captures| {
+            (
+                String::from_utf8_lossy(&captures[1]).to_string(),
+                String::from_utf8_lossy(&captures[2]).to_string(),
+            )
+        })
+        .collect();
+    crates
+        .iter()
+        .map(|(name, version)| component(name, version, Ecosystem::Cargo, None, path))
+        .collect()
+}
+
+fn scan_binary(path: &str, data: &[u8]) -> Vec<SbomComponent> {
+    if !data.starts_with(ELF_MAGIC) {
+        return Vec::new();
+    }
+    let go = parse_go_buildinfo(path, data);
+    if !go.is_empty() {
+        return go;
+    }
+    parse_rust_audit_data(path, data).unwrap_or_else(|| guess_rust_crates(path, data))
+}
+
+fn read_entry<R: Read>(entry: &mut tar::Entry<R>, path: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read /{}: {}", path, e))?;
+    Ok(bytes)
+}
+
+// Find every package the filesystem in `tar_path` records, from distribution
+// databases, language package metadata and build info embedded in binaries
+pub(crate) fn scan_packages(
+    task: &Task,
+    tar_path: &Path,
+    include_binaries: bool,
+) -> Result<PackageInventory, String> {
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    scan_package_archive(task, file, include_binaries)
+}
+
+// Same as scan_packages for a tar that is read as a stream, e.g. a single
+// layer from `docker save`
+pub(crate) fn scan_package_archive<R: Read>(
+    task: &Task,
+    reader: R,
+    include_binaries: bool,
+) -> Result<PackageInventory, String> {
+    let mut archive = tar::Archive::new(reader);
+
+    let mut inventory = PackageInventory::default();
+    // OS packages get their purl namespace from os-release, which may come later
+    let mut os_packages: Vec<(String, InstalledPackage)> = Vec::new();
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file: {}", e))?;
+    for entry in entries {
+        task.check_cancelled()?;
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry_relative_path(
+            &entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?,
+        );
+
+        if path == "etc/os-release" || path == "usr/lib/os-release" {
+            let content = read_entry(&mut entry, &path)?;
+            if inventory.distro.is_none() {
+                inventory.distro = parse_os_release(&String::from_utf8_lossy(&content));
+            }
+        } else if path == DPKG_STATUS_PATH || path.starts_with("var/lib/dpkg/status.d/") {
+            let content = read_entry(&mut entry, &path)?;
+            for package in os_packages::parse_dpkg_status(&String::from_utf8_lossy(&content)) {
+                os_packages.push((path.clone(), package));
+            }
+        } else if path == APK_INSTALLED_PATH {
+            let content = read_entry(&mut entry, &path)?;
+            for package in os_packages::parse_apk_installed(&String::from_utf8_lossy(&content)) {
+                os_packages.push((path.clone(), package));
+            }
+        } else if path == RPM_SQLITE_PATH {
+            // sqlite needs a real file to open
+            let sbom_dir = exec_safety::extraction_root().join(SBOM_DIR);
+            let database = sbom_dir.join("rpmdb.sqlite");
+            fs::create_dir_all(&sbom_dir)
+                .map_err(|e| format!("Failed to create {:?}: {}", sbom_dir, e))?;
+            fs::write(&database, read_entry(&mut entry, &path)?)
+                .map_err(|e| format!("Failed to write {:?}: {}", database, e))?;
+            match os_packages::read_rpm_sqlite(&database) {
+                Ok(packages) => os_packages.extend(packages.into_iter().map(|p| (path.clone(), p))),
+                Err(e) => inventory.warnings.push(e),
+            }
+            let _ = fs::remove_file(&database);
+        } else if path == RPM_BDB_PATH {
+            inventory.warnings.push(format!(
+                "/{} is a BerkeleyDB rpm database, which isn't supported",
+                path
+            ));
+        } else if path.ends_with(".dist-info/METADATA") || path.ends_with(".egg-info/PKG-INFO") {
+            let content = read_entry(&mut entry, &path)?;
+            inventory.components.extend(parse_python_metadata(
+                &path,
+                &String::from_utf8_lossy(&content),
+            ));
+        } else if is_npm_manifest(&path) {
+            let content = read_entry(&mut entry, &path)?;
+            inventory.components.extend(parse_npm_manifest(
+                &path,
+                &String::from_utf8_lossy(&content),
+            ));
+        } else if java_packages::is_java_archive(&path) && entry.size() <= MAX_BINARY_SIZE {
+            let data = read_entry(&mut entry, &path)?;
+            inventory
+                .components
+                .extend(java_packages::scan_java_archive(&path, &data));
+        } else if include_binaries
+            && entry.header().mode().unwrap_or(0) & 0o111 != 0
+            && entry.size() <= MAX_BINARY_SIZE
+        {
+            let data = read_entry(&mut entry, &path)?;
+            inventory.components.extend(scan_binary(&path, &data));
+        }
+    }
+
+    let distro_id = inventory
+        .distro
+        .as_ref()
+        .map(|d| d.id.clone())
+        .unwrap_or_else(|| "unknown".to_string());
+    let os_components = os_packages
+        .into_iter()
+        .map(|(path, package)| SbomComponent {
+            purl: os_purl(&package, &distro_id),
+            ecosystem: match package.format {
+                PackageFormat::Deb => Ecosystem::Deb,
+                PackageFormat::Apk => Ecosystem::Apk,
+                PackageFormat::Rpm => Ecosystem::Rpm,
+            },
+            name: package.name,
+            version: package.version,
+            license: package.license,
+            source: package.source,
+            path: format!("/{}", path),
+        });
+    // Distribution packages first, like most SBOM tools list them
+    inventory.components = os_components.chain(inventory.components).collect();
+    Ok(inventory)
+}
+
+pub(crate) fn format_timestamp(secs: u64) -> String {
+    // Civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    let seconds_of_day = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+// Random-looking but reproducible for the same subject, time and contents
+fn document_uuid(subject: &str, timestamp: &str, components: &[SbomComponent]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(subject.as_bytes());
+    hasher.update(timestamp.as_bytes());
+    for component in components {
+        hasher.update(component.purl.as_bytes());
+    }
+    let mut bytes: Vec<u8> = hasher.finalize()[..16].to_vec();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn spdx_document(subject: &str, inventory: &PackageInventory) -> serde_json::Value {
+    let timestamp = format_timestamp(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    );
+    let uuid = document_uuid(subject, &timestamp, &inventory.components);
+
+    let packages: Vec<serde_json::Value> = inventory
+        .components
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let mut package = json!({
+                "name": c.name,
+                "SPDXID": format!("SPDXRef-Package-{}", i + 1),
+                "versionInfo": c.version,
+                "downloadLocation": "NOASSERTION",
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": "NOASSERTION",
+                "sourceInfo": format!("found in {}", c.path),
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": c.purl,
+                }],
+            });
+            // Recorded licenses are free text, not always valid SPDX expressions
+            if let Some(license) = &c.license {
+                package["licenseComments"] = json!(format!("Declared license: {}", license));
+            }
+            package
+        })
+        .collect();
+    let relationships: Vec<serde_json::Value> = (1..=packages.len())
+        .map(|i| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": format!("SPDXRef-Package-{}", i),
+            })
+        })
+        .collect();
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": subject,
+        "documentNamespace": format!("https://layers.local/spdx/{}-{}", subject.replace([':', '/'], "-"), uuid),
+        "creationInfo": {
+            "created": timestamp,
+            "creators": [format!("Tool: layers-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+fn cyclonedx_document(subject: &str, inventory: &PackageInventory) -> serde_json::Value {
+    let timestamp = format_timestamp(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    );
+    let uuid = document_uuid(subject, &timestamp, &inventory.components);
+
+    let mut components: Vec<serde_json::Value> = Vec::new();
+    if let Some(distro) = &inventory.distro {
+        components.push(json!({
+            "type": "operating-system",
+            "bom-ref": "os",
+            "name": distro.id,
+            "version": distro.version_id,
+            "description": distro.pretty_name,
+        }));
+    }
+    for (i, c) in inventory.components.iter().enumerate() {
+        let mut component = json!({
+            "type": "library",
+            "bom-ref": format!("component-{}", i + 1),
+            "name": c.name,
+            "version": c.version,
+            "purl": c.purl,
+            "properties": [{ "name": "layers:path", "value": c.path }],
+        });
+        if let Some(license) = &c.license {
+            component["licenses"] = json!([{ "license": { "name": license } }]);
+        }
+        components.push(component);
+    }
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid),
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp,
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "layers",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": { "type": "container", "bom-ref": "subject", "name": subject },
+        },
+        "components": components,
+    })
+}
+
+// Export an arbitrary image's filesystem, the selected image already has one
+fn export_image_tar(task: &Task, image: &str) -> Result<PathBuf, String> {
+    let sbom_dir = exec_safety::extraction_root().join(SBOM_DIR);
+    fs::create_dir_all(&sbom_dir).map_err(|e| format!("Failed to create {:?}: {}", sbom_dir, e))?;
+    let tar_path = sbom_dir.join("image.tar");
+    let container_name = "layers_sbom_container";
+
+    let _ = audit::docker(&["rm", "-f", container_name]);
+    let create_output = task
+        .run(docker_command().args(["create", "--name", container_name, image, "true"]))
+        .map_err(|e| format!("Failed to create container: {}", e))?;
+    task.track_container(container_name);
+    if !create_output.status.success() {
+        return Err(format!(
+            "Failed to create container: {}",
+            String::from_utf8_lossy(&create_output.stderr)
+        ));
+    }
+
+    task.track_path(&tar_path);
+    let export_output = task.run(docker_command().args([
+        "export",
+        "-o",
+        &tar_path.to_string_lossy(),
+        container_name,
+    ]));
+    let _ = audit::docker(&["rm", "-f", container_name]);
+    let export_output = export_output.map_err(|e| format!("Failed to export container: {}", e))?;
+    if !export_output.status.success() {
+        return Err(format!(
+            "Failed to export container: {}",
+            String::from_utf8_lossy(&export_output.stderr)
+        ));
+    }
+    Ok(tar_path)
+}
+
+const SBOM_PHASES: &[Phase] = &[
+    phase("fetch", 0.2),
+    phase("analyze", 0.7),
+    phase("write", 0.1),
+];
+
+#[allow(clippy::too_many_arguments)]
+async fn generate_sbom_task(
+    window: &tauri::Window,
+    task: &Task,
+    session: &SessionState,
+    session_id: Option<&str>,
+    layer_id: Option<String>,
+    image: Option<String>,
+    format: SbomFormat,
+    destination: Option<String>,
+) -> Result<SbomResult, String> {
+    let progress = PhaseProgress::new(window, task.id, SBOM_PHASES);
+    progress.begin("fetch", "Preparing filesystem...");
+    // An image given by name doesn't need an open session
+    let (subject, tar_path) = match (&layer_id, &image) {
+        (Some(layer_id), _) => (
+            layer_id.clone(),
+            layer_tar_path(task, &session.get(session_id)?, layer_id)?,
+        ),
+        (None, Some(image)) => (image.clone(), export_image_tar(task, image)?),
+        (None, None) => {
+            let session = session.get(session_id)?;
+            (
+                session.reference().to_string(),
+                layer_tar_path(task, &session, "current_layer")?,
+            )
+        }
+    };
+
+    progress.begin("analyze", "Scanning for packages...");
+    let inventory = scan_packages(task, &tar_path, true)?;
+    if layer_id.is_none() && image.is_some() {
+        let _ = fs::remove_file(&tar_path);
+    }
+
+    progress.begin("write", "Writing SBOM...");
+    let document = match format {
+        SbomFormat::Spdx => spdx_document(&subject, &inventory),
+        SbomFormat::CycloneDx => cyclonedx_document(&subject, &inventory),
+    };
+    if let Some(destination) = &destination {
+        let content = serde_json::to_string_pretty(&document)
+            .map_err(|e| format!("Failed to serialize SBOM: {}", e))?;
+        let written = fs::write(destination, content);
+        audit::record_write(Path::new(destination), &written);
+        written.map_err(|e| format!("Failed to write {}: {}", destination, e))?;
+    }
+
+    progress.complete(&format!("Found {} components", inventory.components.len()));
+    Ok(SbomResult {
+        format,
+        subject,
+        components: inventory.components.len(),
+        warnings: inventory.warnings,
+        destination,
+        document,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_sbom(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: Option<String>,
+    image: Option<String>,
+    format: SbomFormat,
+    destination: Option<String>,
+) -> Result<SbomResult, LayersError> {
+    info!("Generating {:?} SBOM", format);
+
+    let task = tasks.start();
+    let result = generate_sbom_task(
+        &window,
+        &task,
+        &session,
+        session_id.as_deref(),
+        layer_id,
+        image,
+        format,
+        destination,
+    )
+    .await;
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn purls(components: &[SbomComponent]) -> Vec<&str> {
+        components.iter().map(|c| c.purl.as_str()).collect()
+    }
+
+    #[test]
+    fn os_release_needs_an_id() {
+        let distro = parse_os_release(
+            "PRETTY_NAME=\"Debian GNU/Linux 12 (bookworm)\"\nID=debian\nVERSION_ID=\"12\"\n",
+        )
+        .unwrap();
+        assert_eq!(distro.id, "debian");
+        assert_eq!(distro.version_id.as_deref(), Some("12"));
+        assert_eq!(
+            distro.pretty_name.as_deref(),
+            Some("Debian GNU/Linux 12 (bookworm)")
+        );
+
+        assert!(parse_os_release("NAME=Unknown\n").is_none());
+    }
+
+    #[test]
+    fn os_purl_includes_the_architecture() {
+        let package = InstalledPackage {
+            name: "musl".to_string(),
+            version: "1.2.4-r2".to_string(),
+            architecture: Some("x86_64".to_string()),
+            source: None,
+            license: None,
+            format: PackageFormat::Apk,
+        };
+        assert_eq!(
+            os_purl(&package, "alpine"),
+            "pkg:apk/alpine/musl@1.2.4-r2?arch=x86_64"
+        );
+    }
+
+    #[test]
+    fn component_purls_follow_the_ecosystem() {
+        let purl = |name, ecosystem| component(name, "1.0", ecosystem, None, "app").purl;
+        assert_eq!(
+            purl("Flask_Login.ext", Ecosystem::Pypi),
+            "pkg:pypi/flask-login-ext@1.0"
+        );
+        assert_eq!(
+            purl("@types/node", Ecosystem::Npm),
+            "pkg:npm/%40types/node@1.0"
+        );
+        assert_eq!(
+            purl("com.google.guava:guava", Ecosystem::Maven),
+            "pkg:maven/com.google.guava/guava@1.0"
+        );
+        assert_eq!(
+            component("x", "1", Ecosystem::Cargo, None, "usr/bin/x").path,
+            "/usr/bin/x"
+        );
+    }
+
+    #[test]
+    fn python_metadata_stops_at_the_body() {
+        let metadata = "Metadata-Version: 2.1\nName: requests\nVersion: 2.31.0\n\
+                        License: Apache 2.0\n\nName: not-a-header\n";
+        let component =
+            parse_python_metadata("site-packages/requests.dist-info/METADATA", metadata).unwrap();
+        assert_eq!(component.name, "requests");
+        assert_eq!(component.version, "2.31.0");
+        assert_eq!(component.license.as_deref(), Some("Apache 2.0"));
+
+        let unknown = parse_python_metadata("METADATA", "Name: a\nVersion: 1\nLicense: UNKNOWN\n");
+        assert_eq!(unknown.unwrap().license, None);
+        let expression = "Name: a\nVersion: 1\nLicense-Expression: MIT\nLicense: Other\n";
+        assert_eq!(
+            parse_python_metadata("METADATA", expression)
+                .unwrap()
+                .license
+                .as_deref(),
+            Some("MIT")
+        );
+        assert!(parse_python_metadata("METADATA", "Name: a\n").is_none());
+    }
+
+    #[test]
+    fn npm_manifests_sit_directly_in_node_modules() {
+        assert!(is_npm_manifest("app/node_modules/express/package.json"));
+        assert!(is_npm_manifest("app/node_modules/@types/node/package.json"));
+        assert!(is_npm_manifest(
+            "app/node_modules/a/node_modules/b/package.json"
+        ));
+        assert!(!is_npm_manifest("app/package.json"));
+        assert!(!is_npm_manifest(
+            "app/node_modules/express/lib/package.json"
+        ));
+        assert!(!is_npm_manifest("app/node_modules/express/readme.md"));
+    }
+
+    #[test]
+    fn npm_manifest_license_can_be_an_object() {
+        let path = "node_modules/a/package.json";
+        let component =
+            parse_npm_manifest(path, r#"{"name":"a","version":"1.0.0","license":"MIT"}"#).unwrap();
+        assert_eq!(component.license.as_deref(), Some("MIT"));
+        let component = parse_npm_manifest(
+            path,
+            r#"{"name":"a","version":"1.0.0","license":{"type":"BSD-3-Clause"}}"#,
+        )
+        .unwrap();
+        assert_eq!(component.license.as_deref(), Some("BSD-3-Clause"));
+        assert!(parse_npm_manifest(path, r#"{"name":"a"}"#).is_none());
+        assert!(parse_npm_manifest(path, "not json").is_none());
+    }
+
+    #[test]
+    fn uvarint_reads_seven_bits_per_byte() {
+        let mut cursor = 0;
+        assert_eq!(read_uvarint(&[0x96, 0x01, 0x05], &mut cursor), Some(150));
+        assert_eq!(cursor, 2);
+        assert_eq!(read_uvarint(&[0x96, 0x01, 0x05], &mut cursor), Some(5));
+        assert_eq!(read_uvarint(&[0x80], &mut 0), None);
+    }
+
+    #[test]
+    fn go_buildinfo_lists_stdlib_and_modules() {
+        let modinfo = format!(
+            "{}path\texample.com/app\nmod\texample.com/app\t(devel)\t\ndep\tgolang.org/x/sys\tv0.15.0\th1:abc=\n{}",
+            "0".repeat(16),
+            "1".repeat(16)
+        );
+        let mut data = b"\x7fELF padding".to_vec();
+        data.extend_from_slice(GO_BUILDINFO_MAGIC);
+        data.extend_from_slice(&[8, 0x2]);
+        data.resize(data.len() + 16, 0);
+        data.push(8);
+        data.extend_from_slice(b"go1.22.1");
+        data.push(modinfo.len() as u8);
+        data.extend_from_slice(modinfo.as_bytes());
+
+        assert_eq!(
+            purls(&scan_binary("usr/bin/app", &data)),
+            [
+                "pkg:golang/stdlib@1.22.1",
+                "pkg:golang/example.com/app@(devel)",
+                "pkg:golang/golang.org/x/sys@v0.15.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn rust_crates_are_guessed_from_registry_paths() {
+        let data = b"\x7fELF\0/home/ci/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.197/src/de.rs\0\
+                     /home/ci/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.197/src/ser.rs\0\
+                     /home/ci/.cargo/registry/src/index.crates.io-6f17d22bba15001f/tokio-1.36.0/src/lib.rs";
+        assert_eq!(
+            purls(&scan_binary("usr/bin/app", data)),
+            ["pkg:cargo/serde@1.0.197", "pkg:cargo/tokio@1.36.0"]
+        );
+        assert!(scan_binary("usr/bin/script", b"#!/bin/sh\n").is_empty());
+    }
+}
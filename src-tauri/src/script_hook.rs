@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+use tauri::Emitter;
+use tracing::{info, warn};
+
+use crate::error::LayersError;
+use crate::exec_safety::{self, ExecutionKind};
+use crate::reports::build_report;
+use crate::session::{ImageSession, SessionState};
+use crate::tar_extract;
+use crate::tasks::{Task, TaskRegistry};
+use crate::transfer::Transfer;
+use crate::{finish_task, layer_tar_path, TaskStatus};
+
+// Below the extraction root
+const SCRIPTS_DIR: &str = "scripts";
+// Output beyond this is cut off, the result panel isn't a log viewer
+const MAX_OUTPUT: usize = 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScriptResult {
+    script: String,
+    layer_id: String,
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+    truncated: bool,
+    duration_ms: u64,
+}
+
+// Lossy text of a child's output, cut at MAX_OUTPUT
+fn output_text(bytes: &[u8]) -> (String, bool) {
+    let truncated = bytes.len() > MAX_OUTPUT;
+    let bytes = &bytes[..bytes.len().min(MAX_OUTPUT)];
+    (String::from_utf8_lossy(bytes).to_string(), truncated)
+}
+
+// Unpack the whole filesystem, the layer views only extract what's opened
+fn extract_root(
+    task: &Task,
+    tar_path: &Path,
+    root: &Path,
+    report: &dyn Fn(&Transfer),
+) -> Result<(), String> {
+    fs::create_dir_all(root).map_err(|e| format!("Failed to create {:?}: {}", root, e))?;
+    let extraction =
+        tar_extract::extract_entries(task, tar_path, root, false, |_| true, Some(report))
+            .map_err(|e| format!("Failed to extract filesystem: {}", e))?;
+    if !extraction.skipped.is_empty() {
+        // Device nodes and the like can't be created without root, the
+        // script still gets everything else
+        warn!(
+            "Extracting {:?} skipped {} entries",
+            tar_path,
+            extraction.skipped.len()
+        );
+    }
+    Ok(())
+}
+
+fn write_metadata(
+    tasks: &TaskRegistry,
+    session: &ImageSession,
+    layer_id: &str,
+    root: &Path,
+    path: &Path,
+) -> Result<(), String> {
+    let metadata = json!({
+        "layer_id": layer_id,
+        "root": root,
+        "report": build_report(tasks, session, None)?,
+    });
+    let content = serde_json::to_string_pretty(&metadata)
+        .map_err(|e| format!("Failed to serialize script metadata: {}", e))?;
+    fs::write(path, content).map_err(|e| format!("Failed to write script metadata: {}", e))
+}
+
+async fn run_user_script_task(
+    window: &tauri::Window,
+    tasks: &TaskRegistry,
+    task: &Task,
+    session: &ImageSession,
+    script_path: String,
+    layer_id: Option<String>,
+) -> Result<ScriptResult, String> {
+    let update_status = |message: &str, progress: f32, is_complete: bool, error: Option<String>| {
+        let _ = window.emit(
+            "task_status",
+            TaskStatus {
+                task_id: task.id,
+                message: message.to_string(),
+                progress,
+                is_complete,
+                error,
+                phase: None,
+                transfer: None,
+            },
+        );
+    };
+
+    let script = PathBuf::from(&script_path);
+    if !script.is_file() {
+        return Err(format!("Script not found: {}", script_path));
+    }
+    let layer_id = layer_id.unwrap_or_else(|| "current_layer".to_string());
+
+    update_status("Preparing filesystem...", 0.0, false, None);
+    let tar_path = layer_tar_path(task, session, &layer_id)?;
+
+    // Everything for this run lives in one directory, removed afterwards
+    let run_dir = exec_safety::extraction_root()
+        .join(SCRIPTS_DIR)
+        .join(format!("run_{}", task.id));
+    let _ = fs::remove_dir_all(&run_dir);
+    task.track_path(&run_dir);
+    let root = run_dir.join("root");
+    let work_dir = run_dir.join("work");
+    let metadata_path = run_dir.join("metadata.json");
+
+    update_status("Extracting filesystem...", 0.1, false, None);
+    // Extraction covers 0.1 to 0.5 of the run
+    extract_root(task, &tar_path, &root, &|transfer| {
+        let progress = 0.1 + 0.4 * transfer.fraction().unwrap_or(0.0);
+        transfer.emit(window, task.id, "Extracting filesystem...", progress);
+    })?;
+    exec_safety::strip_execute_bits(&root);
+    fs::create_dir_all(&work_dir).map_err(|e| format!("Failed to create {:?}: {}", work_dir, e))?;
+    write_metadata(tasks, session, &layer_id, &root, &metadata_path)?;
+
+    update_status("Running script...", 0.5, false, None);
+    info!("Running script {} against {}", script_path, layer_id);
+    let started = Instant::now();
+    // The script starts in an empty directory with a minimal environment, so
+    // it doesn't pick up the app's settings or write next to it by accident
+    let mut command = Command::new(&script);
+    command
+        .arg(&root)
+        .arg(&metadata_path)
+        .current_dir(&work_dir)
+        .env_clear()
+        .env("PATH", std::env::var("PATH").unwrap_or_default())
+        .env("HOME", &work_dir)
+        .env("TMPDIR", &work_dir)
+        .env("LAYERS_ROOT", &root)
+        .env("LAYERS_METADATA", &metadata_path);
+    let output = task.run(&mut command);
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let _ = fs::remove_dir_all(&run_dir);
+    let output = output.map_err(|e| format!("Failed to run script: {}", e))?;
+    task.check_cancelled()?;
+
+    let (stdout, stdout_truncated) = output_text(&output.stdout);
+    let (stderr, stderr_truncated) = output_text(&output.stderr);
+    let exit_code = output.status.code();
+    let message = match exit_code {
+        Some(0) => "Script finished".to_string(),
+        Some(code) => format!("Script exited with status {}", code),
+        None => "Script was terminated by a signal".to_string(),
+    };
+    update_status(&message, 1.0, true, None);
+
+    Ok(ScriptResult {
+        script: script_path,
+        layer_id,
+        exit_code,
+        stdout,
+        stderr,
+        truncated: stdout_truncated || stderr_truncated,
+        duration_ms,
+    })
+}
+
+// Runs a user-provided script with the merged root of a layer and a JSON
+// metadata file as arguments, for analyses the app doesn't have
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn run_user_script(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    script_path: String,
+    layer_id: Option<String>,
+) -> Result<ScriptResult, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    exec_safety::confirm_execution(
+        &window,
+        ExecutionKind::UserScript,
+        &format!("Run {}", script_path),
+    )?;
+
+    let task = tasks.start();
+    let result =
+        run_user_script_task(&window, &tasks, &task, &session, script_path, layer_id).await;
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
@@ -0,0 +1,758 @@
+use globset::{GlobBuilder, GlobMatcher};
+use layers_core::docker::{docker_command, get_image_history, is_empty_history_entry};
+use layers_core::layer_tar::{display_path, exact_path, OPAQUE_WHITEOUT, WHITEOUT_PREFIX};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, Read};
+use std::ops::RangeInclusive;
+use std::path::{Component, Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::blob;
+use crate::digest_verify::SaveManifestEntry;
+use crate::error::LayersError;
+use crate::session::{ImageSession, SessionState};
+use crate::tasks::{Task, TaskRegistry};
+
+// Written to the session directory of the indexed image
+const INDEX_FILE: &str = "file_index.json";
+const DEFAULT_SEARCH_LIMIT: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexedFile {
+    path: String,
+    // Exact form when the path isn't plain UTF-8, see FileItem.exact_path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exact_path: Option<String>,
+    size: u64,
+    is_dir: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexedLayer {
+    // Matches the "layer_N" IDs produced by export_image_layers
+    layer_id: String,
+    created_by: String,
+    files: Vec<IndexedFile>,
+    // Paths deleted by this layer through whiteout files
+    whiteouts: Vec<String>,
+    // Directories whose lower layer contents were hidden by an opaque whiteout
+    opaque_dirs: Vec<String>,
+    // Why the layer tar could only be read in part, the lists above stop at
+    // the bad entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    partial: Option<String>,
+}
+
+// What index_layer_tar could read of a layer
+#[derive(Default)]
+struct LayerListing {
+    files: Vec<IndexedFile>,
+    whiteouts: Vec<String>,
+    opaque_dirs: Vec<String>,
+    error: Option<String>,
+}
+
+// Files of every layer of an image, base layer first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchIndex {
+    image: String,
+    layers: Vec<IndexedLayer>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    Substring,
+    Glob,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct SearchOptions {
+    // Defaults to glob when the query contains wildcards, substring otherwise
+    mode: Option<SearchMode>,
+    #[serde(default)]
+    case_sensitive: bool,
+    #[serde(default)]
+    include_directories: bool,
+    limit: Option<usize>,
+    // Rebuild the index instead of using the one written during export
+    #[serde(default)]
+    rebuild: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchMatch {
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exact_path: Option<String>,
+    size: u64,
+    is_dir: bool,
+    // Layer that first added the path
+    introduced_in: String,
+    introduced_by: String,
+    // Later layers that replaced the file
+    modified_in: Vec<String>,
+    // Layer that removed the path, if it's not in the final image
+    deleted_in: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResult {
+    query: String,
+    mode: SearchMode,
+    matches: Vec<SearchMatch>,
+    truncated: bool,
+}
+
+// Absolute container path, and its exact form when the readable one loses
+// something
+fn normalize_path(path: &Path) -> (String, Option<String>) {
+    let path: PathBuf = Path::new("/")
+        .join(path)
+        .components()
+        .filter(|c| matches!(c, Component::RootDir | Component::Normal(_)))
+        .collect();
+    (display_path(&path), exact_path(&path))
+}
+
+// List a layer tar, separating whiteout markers from regular entries. A
+// truncated or corrupt tar keeps what was listed before the bad entry.
+fn index_layer_tar<R: Read>(reader: R) -> std::io::Result<LayerListing> {
+    let mut archive = tar::Archive::new(reader);
+    let mut listing = LayerListing::default();
+
+    for entry in archive.entries()? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                listing.error = Some(format!("Failed to read layer entry: {}", e));
+                break;
+            }
+        };
+        let (path, exact_path) = match entry.path() {
+            Ok(path) => normalize_path(&path),
+            Err(e) => {
+                listing.error = Some(format!("Failed to read layer entry path: {}", e));
+                break;
+            }
+        };
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", &path));
+
+        if name == OPAQUE_WHITEOUT {
+            let dir = if parent.is_empty() { "/" } else { parent };
+            listing.opaque_dirs.push(dir.to_string());
+        } else if let Some(deleted) = name.strip_prefix(WHITEOUT_PREFIX) {
+            listing.whiteouts.push(format!("{}/{}", parent, deleted));
+        } else if path != "/" {
+            listing.files.push(IndexedFile {
+                is_dir: entry.header().entry_type().is_dir(),
+                size: entry.size(),
+                path,
+                exact_path,
+            });
+        }
+    }
+
+    Ok(listing)
+}
+
+// The layer_N id and created_by of every history entry that changed the
+// filesystem, base layer first, so they line up with RootFS diff IDs
+pub(crate) fn filesystem_layers(image: &str) -> Result<Vec<(String, String)>, String> {
+    // History is newest first and has entries for metadata-only instructions
+    // too, walk it oldest first and keep the ones with a filesystem layer
+    let history = get_image_history(image)?;
+    Ok(history
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, entry)| !is_empty_history_entry(&entry.created_by, entry.size_bytes))
+        .map(|(index, entry)| (format!("layer_{}", index + 1), entry.created_by.clone()))
+        .collect())
+}
+
+// A filesystem layer read from `docker save`, paired with its history entry
+#[derive(Clone)]
+pub(crate) struct SavedLayer<T> {
+    pub layer_id: String,
+    pub created_by: String,
+    pub contents: T,
+    // Why the layer couldn't be read, `contents` is empty then
+    pub error: Option<String>,
+}
+
+// Stream `docker save` once and hand every layer tar in it to `read_layer`.
+// Layers are returned base layer first, one that can't be read carries the
+// reason instead of its contents.
+pub(crate) fn read_saved_layers<T: Default>(
+    task: &Task,
+    image: &str,
+    read_layer: impl FnMut(&mut dyn Read) -> std::io::Result<T>,
+) -> Result<Vec<SavedLayer<T>>, String> {
+    // A missing image ends the archive before its manifest, docker says why
+    let (manifest, mut contents) = task
+        .stream(docker_command().args(["save", image]), |stdout| {
+            read_save_archive(task, stdout, read_layer)
+        })
+        .map_err(|e| format!("Failed to save image {}: {}", image, e))?;
+
+    let manifest = manifest
+        .and_then(|m| m.into_iter().next())
+        .ok_or_else(|| "docker save archive has no manifest.json".to_string())?;
+
+    let filesystem_entries = filesystem_layers(image)?;
+    if filesystem_entries.len() != manifest.layers.len() {
+        info!(
+            "History has {} layer entries but the image has {} layers, layer attribution may be off",
+            filesystem_entries.len(),
+            manifest.layers.len()
+        );
+    }
+
+    Ok(manifest
+        .layers
+        .iter()
+        .enumerate()
+        .map(|(i, layer_path)| {
+            let (layer_id, created_by) = filesystem_entries
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| (layer_path.clone(), String::new()));
+            let (contents, error) = match contents.remove(layer_path) {
+                Some(Ok(contents)) => (contents, None),
+                Some(Err(e)) => (T::default(), Some(e)),
+                None => (
+                    T::default(),
+                    Some(format!("{} is missing from the archive", layer_path)),
+                ),
+            };
+            if let Some(error) = &error {
+                warn!("Failed to read {}: {}", layer_id, error);
+            }
+            SavedLayer {
+                layer_id,
+                created_by,
+                contents,
+                error,
+            }
+        })
+        .collect())
+}
+
+// What read_save_archive found, the manifest and every blob by archive path
+type SaveArchive<T> = (
+    Option<Vec<SaveManifestEntry>>,
+    HashMap<String, Result<T, String>>,
+);
+
+fn read_save_archive<T>(
+    task: &Task,
+    stdout: &mut dyn Read,
+    mut read_layer: impl FnMut(&mut dyn Read) -> std::io::Result<T>,
+) -> Result<SaveArchive<T>, String> {
+    let mut archive = tar::Archive::new(stdout);
+    let mut contents = HashMap::new();
+    let mut manifest: Option<Vec<SaveManifestEntry>> = None;
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read docker save archive: {}", e))?;
+    for entry in entries {
+        task.check_cancelled()?;
+
+        let mut entry = entry.map_err(|e| format!("Failed to read archive entry: {}", e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry
+            .path()
+            .map_err(|e| format!("Invalid archive entry path: {}", e))?
+            .to_string_lossy()
+            .to_string();
+
+        if path == "manifest.json" {
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .map_err(|e| format!("Failed to read manifest.json: {}", e))?;
+            manifest = Some(
+                serde_json::from_slice(&content)
+                    .map_err(|e| format!("Failed to parse manifest.json: {}", e))?,
+            );
+        } else if path.ends_with(".json") || path.ends_with("VERSION") || path == "repositories" {
+            continue;
+        } else {
+            // OCI layouts keep configs next to layers under blobs/, those
+            // simply fail to parse as a tar. Only failures of blobs the
+            // manifest lists as layers are reported. Layers can be gzip or
+            // zstd compressed.
+            let layer = blob::decompress(BufReader::new(&mut entry), None)
+                .and_then(|mut layer| read_layer(&mut layer))
+                .map_err(|e| e.to_string());
+            contents.insert(path, layer);
+        }
+    }
+    Ok((manifest, contents))
+}
+
+// List the contents of every layer of the image
+pub(crate) fn build_search_index(task: &Task, image: &str) -> Result<SearchIndex, String> {
+    let layers = read_saved_layers(task, image, |reader| index_layer_tar(reader))?
+        .into_iter()
+        .map(|layer| IndexedLayer {
+            layer_id: layer.layer_id,
+            created_by: layer.created_by,
+            files: layer.contents.files,
+            whiteouts: layer.contents.whiteouts,
+            opaque_dirs: layer.contents.opaque_dirs,
+            partial: layer.error.or(layer.contents.error),
+        })
+        .collect();
+
+    Ok(SearchIndex {
+        image: image.to_string(),
+        layers,
+    })
+}
+
+pub(crate) fn save_search_index(session_dir: &Path, index: &SearchIndex) -> Result<(), String> {
+    let content = serde_json::to_string(index)
+        .map_err(|e| format!("Failed to serialize file index: {}", e))?;
+    fs::write(session_dir.join(INDEX_FILE), content)
+        .map_err(|e| format!("Failed to write file index: {}", e))
+}
+
+fn load_search_index(session_dir: &Path) -> Result<Option<SearchIndex>, String> {
+    let index_path = session_dir.join(INDEX_FILE);
+    if !index_path.exists() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(&index_path).map_err(|e| format!("Failed to read file index: {}", e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse file index: {}", e))
+}
+
+// Index saved with the session, built and saved now if there is none
+pub(crate) fn session_search_index(
+    tasks: &TaskRegistry,
+    session: &ImageSession,
+    rebuild: bool,
+) -> Result<SearchIndex, String> {
+    if let Some(index) = load_search_index(session.dir())?.filter(|_| !rebuild) {
+        return Ok(index);
+    }
+    info!("No file index found, building one");
+    let task = tasks.start();
+    let index = build_search_index(&task, session.image_id());
+    tasks.finish(task.id);
+    let index = index?;
+    save_search_index(session.dir(), &index)?;
+    Ok(index)
+}
+
+// A file of the final image and the index of the layer that last wrote it
+pub(crate) struct MergedFile {
+    pub(crate) path: String,
+    pub(crate) size: u64,
+    pub(crate) layer: usize,
+}
+
+// Regular files a layer changed, as (path, size). Removed files have the
+// size they had before.
+pub(crate) struct LayerChanges {
+    pub(crate) layer_id: String,
+    pub(crate) added: Vec<(String, u64)>,
+    pub(crate) modified: Vec<(String, u64)>,
+    pub(crate) removed: Vec<(String, u64)>,
+    // Bytes of lower layer files the layer replaced or removed, still shipped
+    // with the image but no longer visible
+    pub(crate) hidden_bytes: u64,
+}
+
+// What squashing a range of layers into one would leave
+pub(crate) struct SquashedRange {
+    // Files the squashed layer would hold, with the range layer that last
+    // wrote them
+    pub(crate) files: Vec<MergedFile>,
+    // Paths of lower layers the squashed layer still has to delete
+    pub(crate) whiteouts: Vec<String>,
+    // File bytes the layers of the range ship now, hidden versions included
+    pub(crate) shipped_bytes: u64,
+}
+
+// Drop the files `keep` rejects, remembering them in `removed`
+fn remove_files(
+    files: &mut HashMap<&str, u64>,
+    removed: &mut Vec<(String, u64)>,
+    keep: impl Fn(&str) -> bool,
+) {
+    files.retain(|path, size| {
+        let kept = keep(path);
+        if !kept {
+            removed.push((path.to_string(), *size));
+        }
+        kept
+    });
+}
+
+impl SearchIndex {
+    // (layer_id, created_by) of every layer, base layer first
+    pub(crate) fn layer_info(&self) -> Vec<(&str, &str)> {
+        self.layers
+            .iter()
+            .map(|layer| (layer.layer_id.as_str(), layer.created_by.as_str()))
+            .collect()
+    }
+
+    // Index of a layer, base layer first
+    pub(crate) fn layer_position(&self, layer_id: &str) -> Option<usize> {
+        self.layers
+            .iter()
+            .position(|layer| layer.layer_id == layer_id)
+    }
+
+    // File bytes of every layer, hidden versions included
+    pub(crate) fn shipped_bytes(&self) -> u64 {
+        self.layers
+            .iter()
+            .flat_map(|layer| &layer.files)
+            .filter(|file| !file.is_dir)
+            .map(|file| file.size)
+            .sum()
+    }
+
+    // Replay the layers up to the end of `range`. Files written and removed
+    // again inside the range disappear, deletions of lower layer files stay
+    // as whiteouts.
+    pub(crate) fn squash_range(&self, range: RangeInclusive<usize>) -> SquashedRange {
+        let mut files: HashMap<&str, (u64, usize)> = HashMap::new();
+        let mut whiteouts: Vec<String> = Vec::new();
+        let mut shipped_bytes = 0;
+        let start = *range.start();
+        for (index, layer) in self.layers.iter().enumerate().take(range.end() + 1) {
+            let in_range = range.contains(&index);
+            let deletions = layer
+                .opaque_dirs
+                .iter()
+                .map(|dir| (dir.trim_end_matches('/'), true))
+                .chain(layer.whiteouts.iter().map(|path| (path.as_str(), false)));
+            for (deleted, opaque) in deletions {
+                let prefix = format!("{}/", deleted);
+                let mut hides_lower = false;
+                files.retain(|path, (_, writer)| {
+                    let kept = !path.starts_with(&prefix) && (opaque || *path != deleted);
+                    hides_lower |= !kept && *writer < start;
+                    kept
+                });
+                if in_range && hides_lower && !whiteouts.iter().any(|w| w == deleted) {
+                    whiteouts.push(deleted.to_string());
+                }
+            }
+            for file in layer.files.iter().filter(|file| !file.is_dir) {
+                files.insert(&file.path, (file.size, index));
+                if in_range {
+                    shipped_bytes += file.size;
+                }
+            }
+        }
+
+        let files: Vec<MergedFile> = files
+            .into_iter()
+            .filter(|(_, (_, layer))| range.contains(layer))
+            .map(|(path, (size, layer))| MergedFile {
+                path: path.to_string(),
+                size,
+                layer,
+            })
+            .collect();
+        // A file written again at a deleted path replaces the lower one anyway
+        whiteouts.retain(|deleted| !files.iter().any(|file| file.path == *deleted));
+        whiteouts.sort();
+        SquashedRange {
+            files,
+            whiteouts,
+            shipped_bytes,
+        }
+    }
+
+    // (layer_id, error) of the layers that could only be read in part
+    pub(crate) fn partial_layers(&self) -> Vec<(String, String)> {
+        self.layers
+            .iter()
+            .filter_map(|layer| Some((layer.layer_id.clone(), layer.partial.clone()?)))
+            .collect()
+    }
+
+    // What every layer changed relative to the layers below it
+    pub(crate) fn layer_changes(&self) -> Vec<LayerChanges> {
+        let mut files: HashMap<&str, u64> = HashMap::new();
+        let mut changes = Vec::new();
+        for layer in &self.layers {
+            let mut removed = Vec::new();
+            for dir in &layer.opaque_dirs {
+                let prefix = format!("{}/", dir.trim_end_matches('/'));
+                remove_files(&mut files, &mut removed, |path| !path.starts_with(&prefix));
+            }
+            for deleted in &layer.whiteouts {
+                let prefix = format!("{}/", deleted);
+                remove_files(&mut files, &mut removed, |path| {
+                    path != deleted && !path.starts_with(&prefix)
+                });
+            }
+
+            let mut hidden_bytes: u64 = removed.iter().map(|(_, size)| size).sum();
+            let mut added = Vec::new();
+            let mut modified = Vec::new();
+            for file in layer.files.iter().filter(|file| !file.is_dir) {
+                match files.insert(&file.path, file.size) {
+                    Some(previous) => {
+                        hidden_bytes += previous;
+                        modified.push((file.path.clone(), file.size));
+                    }
+                    None => added.push((file.path.clone(), file.size)),
+                }
+            }
+            changes.push(LayerChanges {
+                layer_id: layer.layer_id.clone(),
+                added,
+                modified,
+                removed,
+                hidden_bytes,
+            });
+        }
+        changes
+    }
+
+    // Regular files of the merged filesystem, with whiteouts applied
+    pub(crate) fn merged_files(&self) -> Vec<MergedFile> {
+        let mut files: HashMap<&str, (u64, usize)> = HashMap::new();
+        for (index, layer) in self.layers.iter().enumerate() {
+            for dir in &layer.opaque_dirs {
+                let prefix = format!("{}/", dir.trim_end_matches('/'));
+                files.retain(|path, _| !path.starts_with(&prefix));
+            }
+            for deleted in &layer.whiteouts {
+                let prefix = format!("{}/", deleted);
+                files.retain(|path, _| *path != deleted && !path.starts_with(&prefix));
+            }
+            for file in layer.files.iter().filter(|file| !file.is_dir) {
+                files.insert(&file.path, (file.size, index));
+            }
+        }
+
+        files
+            .into_iter()
+            .map(|(path, (size, layer))| MergedFile {
+                path: path.to_string(),
+                size,
+                layer,
+            })
+            .collect()
+    }
+}
+
+// Matches container paths like "/etc/passwd", shared by search and
+// selective extraction
+pub(crate) enum Matcher {
+    Substring(String),
+    // Patterns without a slash are matched against the file name only
+    Glob {
+        matcher: GlobMatcher,
+        name_only: bool,
+    },
+}
+
+impl Matcher {
+    pub(crate) fn new(query: &str, mode: SearchMode, case_sensitive: bool) -> Result<Self, String> {
+        match mode {
+            SearchMode::Substring => Ok(Matcher::Substring(if case_sensitive {
+                query.to_string()
+            } else {
+                query.to_lowercase()
+            })),
+            SearchMode::Glob => {
+                let glob = GlobBuilder::new(query)
+                    .case_insensitive(!case_sensitive)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| format!("Invalid glob pattern {}: {}", query, e))?;
+                Ok(Matcher::Glob {
+                    matcher: glob.compile_matcher(),
+                    name_only: !query.contains('/'),
+                })
+            }
+        }
+    }
+
+    pub(crate) fn is_match(&self, path: &str, case_sensitive: bool) -> bool {
+        match self {
+            Matcher::Substring(needle) if case_sensitive => path.contains(needle.as_str()),
+            Matcher::Substring(needle) => path.to_lowercase().contains(needle.as_str()),
+            Matcher::Glob { matcher, name_only } => {
+                if *name_only {
+                    matcher.is_match(path.rsplit('/').next().unwrap_or(path))
+                } else {
+                    matcher.is_match(path)
+                }
+            }
+        }
+    }
+}
+
+fn search_index(
+    index: &SearchIndex,
+    matcher: &Matcher,
+    options: &SearchOptions,
+) -> Vec<SearchMatch> {
+    // Replay the layers in order so every path knows where it came from
+    let mut matches: Vec<SearchMatch> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for layer in &index.layers {
+        for dir in &layer.opaque_dirs {
+            let prefix = format!("{}/", dir.trim_end_matches('/'));
+            for m in matches.iter_mut() {
+                if m.path.starts_with(&prefix) && m.deleted_in.is_none() {
+                    m.deleted_in = Some(layer.layer_id.clone());
+                }
+            }
+        }
+        for deleted in &layer.whiteouts {
+            let prefix = format!("{}/", deleted);
+            for m in matches.iter_mut() {
+                if (m.path == *deleted || m.path.starts_with(&prefix)) && m.deleted_in.is_none() {
+                    m.deleted_in = Some(layer.layer_id.clone());
+                }
+            }
+        }
+
+        for file in &layer.files {
+            if file.is_dir && !options.include_directories {
+                continue;
+            }
+            if !matcher.is_match(&file.path, options.case_sensitive) {
+                continue;
+            }
+
+            match positions.get(&file.path) {
+                // Re-adding a deleted path counts as introducing it again
+                Some(&i) if matches[i].deleted_in.is_none() => {
+                    let existing = &mut matches[i];
+                    existing.size = file.size;
+                    if !file.is_dir {
+                        existing.modified_in.push(layer.layer_id.clone());
+                    }
+                }
+                _ => {
+                    positions.insert(file.path.clone(), matches.len());
+                    matches.push(SearchMatch {
+                        path: file.path.clone(),
+                        exact_path: file.exact_path.clone(),
+                        size: file.size,
+                        is_dir: file.is_dir,
+                        introduced_in: layer.layer_id.clone(),
+                        introduced_by: layer.created_by.clone(),
+                        modified_in: Vec::new(),
+                        deleted_in: None,
+                    });
+                }
+            }
+        }
+    }
+
+    matches.sort_by(|a, b| a.path.cmp(&b.path));
+    matches
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn search_image_files(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<SearchResult, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let options = options.unwrap_or_default();
+    let mode = options.mode.unwrap_or_else(|| {
+        if query.contains(['*', '?', '[', '{']) {
+            SearchMode::Glob
+        } else {
+            SearchMode::Substring
+        }
+    });
+    info!("Searching image files for '{}' ({:?})", query, mode);
+
+    let index = session_search_index(&tasks, &session, options.rebuild)?;
+
+    let matcher = Matcher::new(&query, mode, options.case_sensitive)?;
+    let mut matches = search_index(&index, &matcher, &options);
+
+    let limit = options.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let truncated = matches.len() > limit;
+    matches.truncate(limit);
+
+    info!("Found {} matches for '{}'", matches.len(), query);
+    Ok(SearchResult {
+        query,
+        mode,
+        matches,
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{exotic_names, scratch_dir, write_exotic_tar};
+    use layers_core::layer_tar::{display_bytes, path_from_bytes, path_from_escaped};
+    use std::fs::File;
+
+    #[test]
+    fn exotic_names_can_be_searched() {
+        let dir = scratch_dir("search");
+        let tar_path = dir.join("layer.tar");
+        write_exotic_tar(&tar_path);
+
+        let listing = index_layer_tar(File::open(&tar_path).unwrap()).unwrap();
+        assert_eq!(listing.error, None);
+        let index = SearchIndex {
+            image: "fixture".to_string(),
+            layers: vec![IndexedLayer {
+                layer_id: "layer_1".to_string(),
+                created_by: "COPY data /data".to_string(),
+                files: listing.files,
+                whiteouts: listing.whiteouts,
+                opaque_dirs: listing.opaque_dirs,
+                partial: None,
+            }],
+        };
+        let options = SearchOptions::default();
+
+        let matcher = Matcher::new("/data/", SearchMode::Substring, false).unwrap();
+        let matches = search_index(&index, &matcher, &options);
+        assert_eq!(matches.len(), exotic_names().len());
+        for name in exotic_names() {
+            let expected = Path::new("/").join(path_from_bytes(&name));
+            assert!(
+                matches.iter().any(|m| {
+                    path_from_escaped(m.exact_path.as_deref().unwrap_or(&m.path)) == expected
+                }),
+                "{} is missing",
+                display_bytes(&name)
+            );
+        }
+
+        // Control characters are matched in their readable form
+        let matcher = Matcher::new("two\\nlines", SearchMode::Substring, true).unwrap();
+        assert_eq!(search_index(&index, &matcher, &options).len(), 1);
+        let matcher = Matcher::new("latin1-*", SearchMode::Glob, true).unwrap();
+        assert_eq!(search_index(&index, &matcher, &options).len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
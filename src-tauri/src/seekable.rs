@@ -0,0 +1,136 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+// Frames are compressed independently, so a reader of the seek table can
+// decompress only the frame a random read lands in
+const FRAME_SIZE: usize = 1024 * 1024;
+const COMPRESSION_LEVEL: i32 = 3;
+// Seek table layout of the zstd seekable format, so zstd's own tools can
+// still decompress the files
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A5E;
+const SEEKABLE_MAGIC: u32 = 0x8F92_EAB1;
+const FOOTER_SIZE: u64 = 9;
+
+// Fill as much of `buffer` as the reader has, short only at the end
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+// Compress `reader` into `destination` as seekable zstd, returns the size written
+pub(crate) fn compress_seekable<R: Read>(mut reader: R, destination: &Path) -> io::Result<u64> {
+    let mut output = BufWriter::new(File::create(destination)?);
+    let mut buffer = vec![0u8; FRAME_SIZE];
+    let mut frames: Vec<(u32, u32)> = Vec::new();
+    let mut written = 0;
+
+    loop {
+        let filled = read_full(&mut reader, &mut buffer)?;
+        if filled == 0 {
+            break;
+        }
+        let compressed = zstd::bulk::compress(&buffer[..filled], COMPRESSION_LEVEL)?;
+        output.write_all(&compressed)?;
+        written += compressed.len() as u64;
+        frames.push((compressed.len() as u32, filled as u32));
+        if filled < buffer.len() {
+            break;
+        }
+    }
+
+    // The seek table is a skippable frame, plain zstd decoders jump over it
+    let table_size = frames.len() as u32 * 8 + FOOTER_SIZE as u32;
+    output.write_all(&SKIPPABLE_MAGIC.to_le_bytes())?;
+    output.write_all(&table_size.to_le_bytes())?;
+    for (compressed_size, size) in &frames {
+        output.write_all(&compressed_size.to_le_bytes())?;
+        output.write_all(&size.to_le_bytes())?;
+    }
+    output.write_all(&(frames.len() as u32).to_le_bytes())?;
+    output.write_all(&[0])?;
+    output.write_all(&SEEKABLE_MAGIC.to_le_bytes())?;
+    output.flush()?;
+    Ok(written + 8 + table_size as u64)
+}
+
+// Decompress a whole seekable file, frames and seek table are ordinary zstd
+pub(crate) fn decompress_seekable(source: &Path, destination: &Path) -> io::Result<u64> {
+    let input = File::open(source)?;
+    let mut output = BufWriter::new(File::create(destination)?);
+    let mut decoder = zstd::stream::read::Decoder::new(input)?;
+    let size = io::copy(&mut decoder, &mut output)?;
+    output.flush()?;
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::scratch_dir;
+    use std::fs;
+
+    // Bytes that differ from frame to frame, so a frame read out of order shows
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i / 997 % 251) as u8).collect()
+    }
+
+    fn round_trip(name: &str, len: usize) -> Vec<u8> {
+        let dir = scratch_dir(name);
+        let compressed = dir.join("fs.tar.zst");
+        let restored = dir.join("fs.tar");
+        let original = data(len);
+
+        let written = compress_seekable(original.as_slice(), &compressed).unwrap();
+        assert_eq!(written, fs::metadata(&compressed).unwrap().len());
+        assert_eq!(
+            decompress_seekable(&compressed, &restored).unwrap(),
+            len as u64
+        );
+        assert_eq!(fs::read(&restored).unwrap(), original);
+
+        let file = fs::read(&compressed).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+        file
+    }
+
+    // Frame count and decompressed frame sizes from the seek table
+    fn seek_table(file: &[u8]) -> Vec<u32> {
+        let footer = &file[file.len() - FOOTER_SIZE as usize..];
+        assert_eq!(footer[5..9], SEEKABLE_MAGIC.to_le_bytes());
+        let count = u32::from_le_bytes(footer[0..4].try_into().unwrap()) as usize;
+        let table = &file[file.len() - FOOTER_SIZE as usize - count * 8..];
+        (0..count)
+            .map(|i| u32::from_le_bytes(table[i * 8 + 4..i * 8 + 8].try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn data_spanning_frames_round_trips() {
+        let file = round_trip("seekable-frames", FRAME_SIZE * 2 + FRAME_SIZE / 2);
+        assert_eq!(
+            seek_table(&file),
+            vec![FRAME_SIZE as u32, FRAME_SIZE as u32, FRAME_SIZE as u32 / 2]
+        );
+    }
+
+    #[test]
+    fn data_ending_on_a_frame_boundary_round_trips() {
+        let file = round_trip("seekable-boundary", FRAME_SIZE * 2);
+        assert_eq!(seek_table(&file), vec![FRAME_SIZE as u32; 2]);
+    }
+
+    #[test]
+    fn empty_data_round_trips() {
+        let file = round_trip("seekable-empty", 0);
+        assert!(seek_table(&file).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use layers_core::layer_tar::entry_relative_path;
+use std::fs::{self, File};
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::error::LayersError;
+use crate::exec_safety;
+use crate::file_tree::{filtered_tree, FileTreeNode};
+use crate::layer_tar_path;
+use crate::search_index::{Matcher, SearchMode};
+use crate::session::SessionState;
+use crate::tasks::{Task, TaskRegistry};
+
+// Patterns are matched against absolute container paths like search does,
+// "etc/**" is taken to mean "/etc/**"
+fn compile_globs(globs: &[String], case_sensitive: bool) -> Result<Vec<Matcher>, String> {
+    globs
+        .iter()
+        .map(|glob| {
+            let glob = if glob.contains('/') && !glob.starts_with(['/', '*']) {
+                format!("/{}", glob)
+            } else {
+                glob.clone()
+            };
+            Matcher::new(&glob, SearchMode::Glob, case_sensitive)
+        })
+        .collect()
+}
+
+fn matches_any(matchers: &[Matcher], path: &Path, case_sensitive: bool) -> bool {
+    let path = format!("/{}", entry_relative_path(path));
+    matchers
+        .iter()
+        .any(|matcher| matcher.is_match(&path, case_sensitive))
+}
+
+// Extract the entries matching any of the globs in one pass over the tar,
+// returns how many were extracted
+fn extract_entries(
+    task: &Task,
+    tar_path: &Path,
+    extract_dir: &Path,
+    matchers: &[Matcher],
+    case_sensitive: bool,
+) -> Result<usize, String> {
+    fs::create_dir_all(extract_dir)
+        .map_err(|e| format!("Failed to create extract directory: {}", e))?;
+
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+    archive.set_preserve_permissions(true);
+
+    let mut extracted = 0;
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for entry in entries {
+        task.check_cancelled()?;
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let path = entry
+            .path()
+            .map_err(|e| format!("Failed to read tar entry path: {}", e))?
+            .into_owned();
+        if !matches_any(matchers, &path, case_sensitive) {
+            continue;
+        }
+
+        // Hard links to files that didn't match have nothing to point at,
+        // they're extracted when their directory is opened
+        match entry.unpack_in(extract_dir) {
+            Ok(_) => {
+                exec_safety::strip_execute_bits(&extract_dir.join(&path));
+                extracted += 1;
+            }
+            Err(e) => warn!("Failed to extract {}: {}", path.display(), e),
+        }
+    }
+    Ok(extracted)
+}
+
+// Extract only the entries of a layer matching any of the globs, e.g.
+// "/etc/**" or "**/*.so", and return the tree of what matched. Globs
+// without a slash match file names, like in search_image_files.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn extract_matching(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+    globs: Vec<String>,
+    case_sensitive: Option<bool>,
+) -> Result<FileTreeNode, LayersError> {
+    info!("Extracting {:?} from layer {}", globs, layer_id);
+    if globs.is_empty() {
+        return Err("No glob patterns given".into());
+    }
+    // Case insensitive unless asked otherwise, like search
+    let case_sensitive = case_sensitive.unwrap_or_default();
+    let matchers = compile_globs(&globs, case_sensitive)?;
+
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let result = layer_tar_path(&task, &session, &layer_id).and_then(|tar_path| {
+        let extract_dir = tar_path.with_file_name("fs");
+        let extracted = extract_entries(&task, &tar_path, &extract_dir, &matchers, case_sensitive)?;
+        info!("Extracted {} matching entries", extracted);
+        filtered_tree(&tar_path, &extract_dir, |path| {
+            matches_any(&matchers, path, case_sensitive)
+        })
+    });
+    tasks.finish(task.id);
+    Ok(result?)
+}
@@ -0,0 +1,412 @@
+use layers_core::layer_tar::entry_relative_path;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::layer_tar_path;
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+// Service definitions are small text files, anything bigger is not one
+const MAX_DEFINITION_SIZE: u64 = 1024 * 1024;
+
+const SYSTEMD_DIRS: [&str; 3] = [
+    "etc/systemd/system/",
+    "lib/systemd/system/",
+    "usr/lib/systemd/system/",
+];
+const SUPERVISORD_CONFIGS: [&str; 3] = [
+    "etc/supervisord.conf",
+    "etc/supervisor/supervisord.conf",
+    "etc/supervisor/conf.d/",
+];
+const S6_DIRS: [&str; 3] = ["etc/services.d/", "etc/s6-overlay/s6-rc.d/", "etc/s6/"];
+const RUNIT_DIRS: [&str; 2] = ["etc/service/", "etc/sv/"];
+// System crontabs have a user column, per-user crontabs don't
+const SYSTEM_CRONTABS: [&str; 2] = ["etc/crontab", "etc/cron.d/"];
+const USER_CRONTAB_DIRS: [&str; 2] = ["var/spool/cron/crontabs/", "etc/crontabs/"];
+const CRON_PERIOD_DIRS: [(&str, &str); 4] = [
+    ("etc/cron.hourly/", "@hourly"),
+    ("etc/cron.daily/", "@daily"),
+    ("etc/cron.weekly/", "@weekly"),
+    ("etc/cron.monthly/", "@monthly"),
+];
+const INIT_D_DIR: &str = "etc/init.d/";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceKind {
+    SystemdService,
+    SystemdTimer,
+    Supervisord,
+    S6,
+    Runit,
+    Cron,
+    InitScript,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServiceDefinition {
+    kind: ServiceKind,
+    name: String,
+    // File the definition was read from
+    path: String,
+    description: Option<String>,
+    command: Option<String>,
+    // Cron expression or systemd OnCalendar value
+    schedule: Option<String>,
+    user: Option<String>,
+    // For systemd, whether a *.wants symlink enables the unit
+    enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServiceInventory {
+    layer_id: String,
+    services: Vec<ServiceDefinition>,
+    // Which supervisors were found, e.g. "systemd", "supervisord", "cron"
+    init_systems: Vec<String>,
+}
+
+fn is_definition_path(path: &str) -> bool {
+    SYSTEMD_DIRS
+        .iter()
+        .chain(SUPERVISORD_CONFIGS.iter())
+        .chain(S6_DIRS.iter())
+        .chain(RUNIT_DIRS.iter())
+        .chain(SYSTEM_CRONTABS.iter())
+        .chain(USER_CRONTAB_DIRS.iter())
+        .chain(std::iter::once(&INIT_D_DIR))
+        .any(|prefix| path.starts_with(prefix))
+        || CRON_PERIOD_DIRS
+            .iter()
+            .any(|(prefix, _)| path.starts_with(prefix))
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+// First command line of a run script, skipping the shebang and comments
+fn script_command(content: &str) -> Option<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.trim_start_matches("exec ").to_string())
+}
+
+fn parse_systemd_unit(
+    path: &str,
+    content: &str,
+    enabled_units: &HashSet<String>,
+) -> Option<ServiceDefinition> {
+    let name = file_name(path).to_string();
+    let kind = if name.ends_with(".service") {
+        ServiceKind::SystemdService
+    } else if name.ends_with(".timer") {
+        ServiceKind::SystemdTimer
+    } else {
+        return None;
+    };
+
+    let mut values: BTreeMap<&str, String> = BTreeMap::new();
+    for line in content.lines() {
+        if let Some((key, value)) = line.trim().split_once('=') {
+            // ExecStart may be repeated, the first one is the main command
+            values
+                .entry(key.trim())
+                .or_insert_with(|| value.trim().to_string());
+        }
+    }
+
+    Some(ServiceDefinition {
+        kind,
+        description: values.get("Description").cloned(),
+        command: values.get("ExecStart").cloned(),
+        schedule: values
+            .get("OnCalendar")
+            .or_else(|| values.get("OnUnitActiveSec"))
+            .cloned(),
+        user: values.get("User").cloned(),
+        enabled: Some(enabled_units.contains(&name)),
+        name,
+        path: format!("/{}", path),
+    })
+}
+
+fn parse_supervisord(path: &str, content: &str) -> Vec<ServiceDefinition> {
+    let mut services = Vec::new();
+    let mut current: Option<ServiceDefinition> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            services.extend(current.take());
+            if let Some(program) = section.strip_prefix("program:") {
+                current = Some(ServiceDefinition {
+                    kind: ServiceKind::Supervisord,
+                    name: program.trim().to_string(),
+                    path: format!("/{}", path),
+                    description: None,
+                    command: None,
+                    schedule: None,
+                    user: None,
+                    enabled: None,
+                });
+            }
+            continue;
+        }
+
+        let (Some(service), Some((key, value))) = (current.as_mut(), line.split_once('=')) else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match key.trim() {
+            "command" => service.command = Some(value),
+            "user" => service.user = Some(value),
+            "autostart" => service.enabled = Some(value != "false"),
+            _ => {}
+        }
+    }
+
+    services.extend(current);
+    services
+}
+
+fn parse_crontab(path: &str, content: &str, has_user_field: bool) -> Vec<ServiceDefinition> {
+    // Per-user crontabs are named after the user
+    let crontab_user = if has_user_field {
+        None
+    } else {
+        Some(file_name(path).to_string())
+    };
+
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        // Skip variable assignments like SHELL=/bin/sh or MAILTO=""
+        .filter(|line| {
+            let first = line.split_whitespace().next().unwrap_or("");
+            !first.contains('=') || first.starts_with('@')
+        })
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let schedule_fields = if line.starts_with('@') { 1 } else { 5 };
+            let schedule: Vec<&str> = fields.by_ref().take(schedule_fields).collect();
+            if schedule.len() < schedule_fields {
+                return None;
+            }
+            let user = if has_user_field {
+                Some(fields.next()?.to_string())
+            } else {
+                crontab_user.clone()
+            };
+            let command = fields.collect::<Vec<_>>().join(" ");
+            if command.is_empty() {
+                return None;
+            }
+
+            Some(ServiceDefinition {
+                kind: ServiceKind::Cron,
+                name: file_name(path).to_string(),
+                path: format!("/{}", path),
+                description: None,
+                command: Some(command),
+                schedule: Some(schedule.join(" ")),
+                user,
+                enabled: None,
+            })
+        })
+        .collect()
+}
+
+// s6 and runit both use <dir>/<service>/run scripts
+fn parse_run_script(
+    kind: ServiceKind,
+    dirs: &[&str],
+    path: &str,
+    content: &str,
+) -> Option<ServiceDefinition> {
+    let dir = dirs.iter().find(|dir| path.starts_with(*dir))?;
+    let rest = &path[dir.len()..];
+    let (name, script) = rest.split_once('/')?;
+    if script != "run" {
+        return None;
+    }
+
+    Some(ServiceDefinition {
+        kind,
+        name: name.to_string(),
+        path: format!("/{}", path),
+        description: None,
+        command: script_command(content),
+        schedule: None,
+        user: None,
+        enabled: None,
+    })
+}
+
+fn parse_definition(
+    path: &str,
+    content: &str,
+    enabled_units: &HashSet<String>,
+) -> Vec<ServiceDefinition> {
+    if SYSTEMD_DIRS.iter().any(|dir| path.starts_with(dir)) {
+        return parse_systemd_unit(path, content, enabled_units)
+            .into_iter()
+            .collect();
+    }
+    if SUPERVISORD_CONFIGS.iter().any(|dir| path.starts_with(dir)) {
+        return parse_supervisord(path, content);
+    }
+    if SYSTEM_CRONTABS.iter().any(|dir| path.starts_with(dir)) {
+        return parse_crontab(path, content, true);
+    }
+    if USER_CRONTAB_DIRS.iter().any(|dir| path.starts_with(dir)) {
+        return parse_crontab(path, content, false);
+    }
+    if let Some((_, schedule)) = CRON_PERIOD_DIRS
+        .iter()
+        .find(|(dir, _)| path.starts_with(dir))
+    {
+        // run-parts ignores dotfiles and the placeholder packages ship
+        let name = file_name(path);
+        if name.starts_with('.') {
+            return Vec::new();
+        }
+        return vec![ServiceDefinition {
+            kind: ServiceKind::Cron,
+            name: name.to_string(),
+            path: format!("/{}", path),
+            description: None,
+            command: Some(format!("/{}", path)),
+            schedule: Some(schedule.to_string()),
+            user: Some("root".to_string()),
+            enabled: None,
+        }];
+    }
+    if let Some(service) = parse_run_script(ServiceKind::S6, &S6_DIRS, path, content) {
+        return vec![service];
+    }
+    if let Some(service) = parse_run_script(ServiceKind::Runit, &RUNIT_DIRS, path, content) {
+        return vec![service];
+    }
+    if let Some(name) = path.strip_prefix(INIT_D_DIR) {
+        if !name.contains('/') && !name.starts_with('.') && name != "README" {
+            return vec![ServiceDefinition {
+                kind: ServiceKind::InitScript,
+                name: name.to_string(),
+                path: format!("/{}", path),
+                description: content
+                    .lines()
+                    .find_map(|l| l.trim().strip_prefix("# Short-Description:"))
+                    .map(|d| d.trim().to_string()),
+                command: None,
+                schedule: None,
+                user: None,
+                enabled: None,
+            }];
+        }
+    }
+    Vec::new()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn inspect_services(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+) -> Result<ServiceInventory, LayersError> {
+    info!("Inspecting service definitions in layer {}", layer_id);
+
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let tar_path = layer_tar_path(&task, &session, &layer_id);
+    tasks.finish(task.id);
+    let tar_path = tar_path?;
+
+    let file = File::open(&tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+
+    // Collect the files first, units are only enabled through *.wants
+    // symlinks that can appear anywhere in the tar
+    let mut definitions: Vec<(String, String)> = Vec::new();
+    let mut enabled_units = HashSet::new();
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let path = entry_relative_path(
+            &entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?,
+        );
+        if !is_definition_path(&path) {
+            continue;
+        }
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_symlink() {
+            let parent = path.rsplit_once('/').map(|(p, _)| p).unwrap_or("");
+            if parent.ends_with(".wants") || parent.ends_with(".requires") {
+                enabled_units.insert(file_name(&path).to_string());
+            }
+            continue;
+        }
+        if !entry_type.is_file() || entry.size() > MAX_DEFINITION_SIZE {
+            continue;
+        }
+
+        let mut content = Vec::new();
+        entry
+            .read_to_end(&mut content)
+            .map_err(|e| format!("Failed to read /{}: {}", path, e))?;
+        definitions.push((path, String::from_utf8_lossy(&content).to_string()));
+    }
+
+    let mut services: Vec<ServiceDefinition> = definitions
+        .iter()
+        .flat_map(|(path, content)| parse_definition(path, content, &enabled_units))
+        .collect();
+    services.sort_by(|a, b| a.path.cmp(&b.path).then_with(|| a.name.cmp(&b.name)));
+
+    let mut init_systems: Vec<String> = Vec::new();
+    for service in &services {
+        let system = match service.kind {
+            ServiceKind::SystemdService | ServiceKind::SystemdTimer => "systemd",
+            ServiceKind::Supervisord => "supervisord",
+            ServiceKind::S6 => "s6",
+            ServiceKind::Runit => "runit",
+            ServiceKind::Cron => "cron",
+            ServiceKind::InitScript => "sysvinit",
+        };
+        if !init_systems.iter().any(|s| s == system) {
+            init_systems.push(system.to_string());
+        }
+    }
+
+    info!(
+        "Found {} service definitions ({})",
+        services.len(),
+        init_systems.join(", ")
+    );
+    Ok(ServiceInventory {
+        layer_id,
+        services,
+        init_systems,
+    })
+}
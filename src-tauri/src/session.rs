@@ -0,0 +1,244 @@
+use layers_core::docker::inspect_image;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::exec_safety;
+use crate::undo::{UndoAction, UndoEntry, UndoHistory};
+
+// Every open image gets its own directory below this one, itself below the
+// extraction root
+const SESSIONS_DIR: &str = "sessions";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageSession {
+    session_id: String,
+    // Full image ID, docker takes it anywhere it takes an image name
+    image_id: String,
+    // What the user picked, e.g. "nginx:1.25", or the ID for untagged images
+    reference: String,
+    // Holds current_layer, the layer_N directories and the file index
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl ImageSession {
+    pub(crate) fn id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub(crate) fn image_id(&self) -> &str {
+        &self.image_id
+    }
+
+    pub(crate) fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+// Open images, managed as Tauri state, no session ID means the selected one
+#[derive(Default)]
+pub struct SessionState {
+    sessions: Mutex<HashMap<String, ImageSession>>,
+    selected: Mutex<Option<String>>,
+    next_id: AtomicU64,
+}
+
+impl SessionState {
+    // Start a session with an empty directory of its own
+    pub(crate) fn open(&self, image_id: String, reference: String) -> Result<ImageSession, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let sessions_dir = exec_safety::extraction_root().join(SESSIONS_DIR);
+        if id == 1 {
+            // Directories left behind by a previous run
+            let _ = fs::remove_dir_all(&sessions_dir);
+        }
+
+        let session_id = format!("session_{}", id);
+        let dir = sessions_dir.join(&session_id);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create session directory {:?}: {}", dir, e))?;
+
+        let session = ImageSession {
+            session_id: session_id.clone(),
+            image_id,
+            reference,
+            dir,
+        };
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session_id, session.clone());
+        info!(
+            "Opened {} for {} ({})",
+            session.session_id, session.reference, session.image_id
+        );
+        Ok(session)
+    }
+
+    // Open a session in place of the selected one, which is closed
+    pub(crate) fn open_selected(
+        &self,
+        image_id: String,
+        reference: String,
+    ) -> Result<ImageSession, String> {
+        let previous = self.selected.lock().unwrap().clone();
+        if let Some(previous) = previous {
+            self.close(&previous)?;
+        }
+        let session = self.open(image_id, reference)?;
+        *self.selected.lock().unwrap() = Some(session.session_id.clone());
+        Ok(session)
+    }
+
+    // Drop a session and everything extracted for it
+    pub(crate) fn close(&self, session_id: &str) -> Result<(), String> {
+        let session = self
+            .sessions
+            .lock()
+            .unwrap()
+            .remove(session_id)
+            .ok_or_else(|| format!("Session {} is not open", session_id))?;
+
+        let mut selected = self.selected.lock().unwrap();
+        if selected.as_deref() == Some(session_id) {
+            *selected = None;
+        }
+
+        info!("Closing {}, removing {:?}", session_id, session.dir);
+        if session.dir.exists() {
+            fs::remove_dir_all(&session.dir)
+                .map_err(|e| format!("Failed to remove session directory: {}", e))?;
+        }
+        Ok(())
+    }
+
+    // The given session, or the selected one when no ID is passed
+    pub(crate) fn get(&self, session_id: Option<&str>) -> Result<ImageSession, String> {
+        let selected = self.selected.lock().unwrap().clone();
+        let session_id = session_id
+            .map(String::from)
+            .or(selected)
+            .ok_or_else(|| "No image selected. Please select an image first.".to_string())?;
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&session_id)
+            .cloned()
+            .ok_or_else(|| format!("Session {} is not open", session_id))
+    }
+
+    pub(crate) fn image_id(&self) -> Result<String, String> {
+        self.get(None).map(|session| session.image_id)
+    }
+
+    // The user facing name, registries only know images by reference
+    pub(crate) fn image_reference(&self) -> Result<String, String> {
+        self.get(None).map(|session| session.reference)
+    }
+
+    // Commands that take an optional image fall back to the session's
+    pub(crate) fn image_or_selected(&self, image: Option<String>) -> Result<String, String> {
+        match image {
+            Some(image) => Ok(image),
+            None => self.image_id(),
+        }
+    }
+}
+
+// Full ID and a readable name of a local image
+pub(crate) fn resolve_image(image: &str) -> Result<(String, String), LayersError> {
+    if image.is_empty() {
+        return Err("Image ID is empty".into());
+    }
+
+    let inspect = inspect_image(image)?;
+    let id = inspect["Id"]
+        .as_str()
+        .ok_or_else(|| format!("No image ID in docker inspect output for {}", image))?
+        .to_string();
+    let reference = inspect["RepoTags"]
+        .as_array()
+        .and_then(|tags| tags.first())
+        .and_then(|tag| tag.as_str())
+        .map(String::from)
+        .unwrap_or_else(|| image.to_string());
+    Ok((id, reference))
+}
+
+// Open a session next to the ones already open, e.g. to compare two images
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn open_image_session(
+    session: tauri::State<'_, SessionState>,
+    image_id: String,
+) -> Result<ImageSession, LayersError> {
+    info!("Opening session for image '{}'", image_id);
+    let (id, reference) = resolve_image(&image_id)?;
+    Ok(session.open(id, reference)?)
+}
+
+// The extracted files are gone, undoing reopens the image in a new session
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn close_image_session(
+    session: tauri::State<'_, SessionState>,
+    undo: tauri::State<'_, UndoHistory>,
+    session_id: String,
+) -> Result<UndoEntry, LayersError> {
+    let closed = session.get(Some(&session_id))?;
+    session.close(&session_id)?;
+    Ok(undo.record(
+        format!("Closed session for {}", closed.reference),
+        UndoAction::ReopenSession {
+            image_id: closed.image_id,
+            reference: closed.reference,
+        },
+    ))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_image_sessions(
+    session: tauri::State<'_, SessionState>,
+) -> Result<Vec<ImageSession>, LayersError> {
+    let mut sessions: Vec<ImageSession> =
+        session.sessions.lock().unwrap().values().cloned().collect();
+    // Oldest first, "session_10" sorts before "session_2" as a string
+    sessions.sort_by_key(|s| {
+        s.session_id
+            .trim_start_matches("session_")
+            .parse::<u64>()
+            .unwrap_or(0)
+    });
+    Ok(sessions)
+}
+
+// Open the image as the one commands without a session ID use
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn select_image(
+    session: tauri::State<'_, SessionState>,
+    image_id: String,
+) -> Result<ImageSession, LayersError> {
+    info!("Selecting image '{}'", image_id);
+    let (id, reference) = resolve_image(&image_id)?;
+    Ok(session.open_selected(id, reference)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_selected_image(
+    session: tauri::State<'_, SessionState>,
+) -> Result<Option<ImageSession>, LayersError> {
+    Ok(session.get(None).ok())
+}
@@ -0,0 +1,580 @@
+use layers_core::docker::get_image_history;
+use layers_core::layer_tar::entry_relative_path;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::findings::{SecurityFinding, Severity};
+use crate::layer_mapping::normalize_created_by;
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+use crate::{is_binary_content, layer_tar_path};
+
+// Larger files are almost certainly not hand written scripts
+const MAX_SCRIPT_SIZE: u64 = 1024 * 1024;
+const SHELLS: [&str; 5] = ["sh", "bash", "ash", "dash", "ksh"];
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ShellLintOptions {
+    // Also run shellcheck when it is installed, the bundled rules run either way
+    #[serde(default)]
+    use_shellcheck: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ShellSource {
+    RunInstruction,
+    Script,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShellIssue {
+    source: ShellSource,
+    line: usize,
+    snippet: String,
+    finding: SecurityFinding,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShellLintReport {
+    layer_id: String,
+    run_instructions_checked: usize,
+    scripts_checked: usize,
+    // False when shellcheck wasn't requested or isn't installed
+    shellcheck_used: bool,
+    issues: Vec<ShellIssue>,
+}
+
+// Output format of `shellcheck -f json`
+#[derive(Debug, Deserialize)]
+struct ShellcheckComment {
+    line: usize,
+    level: String,
+    code: u32,
+    message: String,
+}
+
+struct Rule {
+    id: &'static str,
+    severity: Severity,
+    title: &'static str,
+    description: &'static str,
+    // The shellcheck check covering the same problem, skipped when shellcheck ran
+    shellcheck_code: Option<u32>,
+    matches: fn(&str) -> bool,
+}
+
+// Compile each rule's pattern once, scripts are checked line by line
+fn pattern(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("invalid shell lint pattern"))
+}
+
+pub(crate) fn pipe_to_shell(line: &str) -> bool {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    pattern(
+        &PATTERN,
+        r"\b(curl|wget)\b[^|;&]*\|\s*(sudo\s+)?(ba|da|k|z)?sh\b",
+    )
+    .is_match(line)
+}
+
+fn recursive_rm_of_variable(line: &str) -> bool {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    pattern(
+        &PATTERN,
+        r#"\brm\s+-[a-zA-Z]*[rR][a-zA-Z]*\s+"?\$\{?\w+\}?"?/"#,
+    )
+    .is_match(line)
+}
+
+fn world_writable_chmod(line: &str) -> bool {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    pattern(&PATTERN, r"\bchmod\s+(-R\s+)?0?777\b").is_match(line)
+}
+
+// Walk the line tracking quotes, so "$VAR" and '$VAR' aren't flagged
+fn unquoted_variable(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().collect();
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if !in_single => i += 1,
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            '#' if !in_single && !in_double && (i == 0 || chars[i - 1].is_whitespace()) => {
+                return false;
+            }
+            '$' if !in_single && !in_double => {
+                let next = chars.get(i + 1).copied().unwrap_or(' ');
+                // Assignments don't word split, FOO=$BAR is fine
+                let is_assignment = i > 0 && chars[i - 1] == '=';
+                if (next.is_ascii_alphabetic() || next == '_' || next == '{') && !is_assignment {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    false
+}
+
+fn uses_backticks(line: &str) -> bool {
+    let mut in_single = false;
+    for c in line.chars() {
+        match c {
+            '\'' => in_single = !in_single,
+            '`' if !in_single => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+const RULES: [Rule; 5] = [
+    Rule {
+        id: "shell-pipe-to-shell",
+        severity: Severity::High,
+        title: "Remote script piped into a shell",
+        description: "A script downloaded with curl or wget is executed directly. Its content \
+                      isn't pinned or verified, so a compromised or changed server changes the \
+                      image. Download it, check its checksum, then run it.",
+        shellcheck_code: None,
+        matches: pipe_to_shell,
+    },
+    Rule {
+        id: "shell-rm-variable-path",
+        severity: Severity::Medium,
+        title: "Recursive rm of a path built from a variable",
+        description: "If the variable is empty this removes from the filesystem root. \
+                      Use ${VAR:?} so the command fails instead.",
+        shellcheck_code: Some(2115),
+        matches: recursive_rm_of_variable,
+    },
+    Rule {
+        id: "shell-chmod-777",
+        severity: Severity::Medium,
+        title: "World writable permissions",
+        description: "chmod 777 lets any user in the container modify the files. \
+                      Grant write access to the owning user only.",
+        shellcheck_code: None,
+        matches: world_writable_chmod,
+    },
+    Rule {
+        id: "shell-unquoted-variable",
+        severity: Severity::Low,
+        title: "Unquoted variable expansion",
+        description: "Unquoted variables are split on whitespace and glob expanded. \
+                      Quote them unless splitting is intended.",
+        shellcheck_code: Some(2086),
+        matches: unquoted_variable,
+    },
+    Rule {
+        id: "shell-backticks",
+        severity: Severity::Info,
+        title: "Legacy backtick command substitution",
+        description: "Backticks don't nest and are easy to misquote, use $(...) instead.",
+        shellcheck_code: Some(2006),
+        matches: uses_backticks,
+    },
+];
+
+fn truncate_snippet(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(200) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+fn lint_with_rules(
+    script: &str,
+    source: ShellSource,
+    path: Option<&str>,
+    layer_id: &str,
+    shellcheck_used: bool,
+) -> Vec<ShellIssue> {
+    let mut issues = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        for rule in RULES.iter() {
+            if shellcheck_used && rule.shellcheck_code.is_some() {
+                continue;
+            }
+            if !(rule.matches)(line) {
+                continue;
+            }
+            issues.push(ShellIssue {
+                source,
+                line: index + 1,
+                snippet: truncate_snippet(line),
+                finding: SecurityFinding {
+                    rule_id: rule.id.to_string(),
+                    severity: rule.severity,
+                    title: rule.title.to_string(),
+                    description: rule.description.to_string(),
+                    path: path.map(String::from),
+                    layer_id: Some(layer_id.to_string()),
+                },
+            });
+        }
+    }
+    issues
+}
+
+fn shellcheck_severity(level: &str) -> Severity {
+    match level {
+        "error" => Severity::High,
+        "warning" => Severity::Medium,
+        "info" => Severity::Low,
+        _ => Severity::Info,
+    }
+}
+
+// Returns None when shellcheck can't be run, so callers fall back to the bundled rules
+fn run_shellcheck(script: &str, shell: &str) -> Option<Vec<ShellcheckComment>> {
+    let mut child = Command::new("shellcheck")
+        .args(["-f", "json", "-s", shell, "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    child.stdin.take()?.write_all(script.as_bytes()).ok()?;
+    let output = child.wait_with_output().ok()?;
+    // shellcheck exits with 1 when it found issues
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+fn lint_with_shellcheck(
+    script: &str,
+    shell: &str,
+    source: ShellSource,
+    path: Option<&str>,
+    layer_id: &str,
+) -> Option<Vec<ShellIssue>> {
+    let lines: Vec<&str> = script.lines().collect();
+    let comments = run_shellcheck(script, shell)?;
+    Some(
+        comments
+            .into_iter()
+            .map(|comment| ShellIssue {
+                source,
+                line: comment.line,
+                snippet: lines
+                    .get(comment.line.saturating_sub(1))
+                    .map(|l| truncate_snippet(l))
+                    .unwrap_or_default(),
+                finding: SecurityFinding {
+                    rule_id: format!("SC{}", comment.code),
+                    severity: shellcheck_severity(&comment.level),
+                    title: comment.message.clone(),
+                    description: format!(
+                        "{} See https://www.shellcheck.net/wiki/SC{}",
+                        comment.message, comment.code
+                    ),
+                    path: path.map(String::from),
+                    layer_id: Some(layer_id.to_string()),
+                },
+            })
+            .collect(),
+    )
+}
+
+// The shell a script is written for, from its extension or shebang
+fn script_shell(path: &str, content: &str) -> Option<String> {
+    let first_line = content.lines().next().unwrap_or("");
+    if let Some(shebang) = first_line.strip_prefix("#!") {
+        let mut words = shebang.split_whitespace();
+        let mut interpreter = words.next()?.rsplit('/').next()?;
+        // "#!/usr/bin/env bash"
+        if interpreter == "env" {
+            interpreter = words.next()?;
+        }
+        if SHELLS.contains(&interpreter) {
+            return Some(interpreter.to_string());
+        }
+        return None;
+    }
+    if path.ends_with(".sh") {
+        return Some("sh".to_string());
+    }
+    None
+}
+
+struct Linter {
+    use_shellcheck: bool,
+    shellcheck_used: bool,
+    issues: Vec<ShellIssue>,
+}
+
+impl Linter {
+    fn lint(
+        &mut self,
+        script: &str,
+        shell: &str,
+        source: ShellSource,
+        path: Option<&str>,
+        layer_id: &str,
+    ) {
+        let mut shellcheck_ran = false;
+        if self.use_shellcheck {
+            if let Some(issues) = lint_with_shellcheck(script, shell, source, path, layer_id) {
+                self.issues.extend(issues);
+                self.shellcheck_used = true;
+                shellcheck_ran = true;
+            }
+        }
+        self.issues.extend(lint_with_rules(
+            script,
+            source,
+            path,
+            layer_id,
+            shellcheck_ran,
+        ));
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn lint_shell_scripts(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+    options: Option<ShellLintOptions>,
+) -> Result<ShellLintReport, LayersError> {
+    info!("Linting shell scripts in layer {}", layer_id);
+    let options = options.unwrap_or_default();
+
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let tar_path = layer_tar_path(&task, &session, &layer_id);
+    tasks.finish(task.id);
+    let tar_path = tar_path?;
+
+    let mut linter = Linter {
+        use_shellcheck: options.use_shellcheck,
+        shellcheck_used: false,
+        issues: Vec::new(),
+    };
+
+    // RUN bodies of every instruction, attributed to the layer they produced
+    let history = get_image_history(session.image_id())?;
+    let mut run_instructions_checked = 0;
+    for (index, entry) in history.iter().enumerate() {
+        let (instruction, body) = normalize_created_by(&entry.created_by);
+        if instruction != "RUN" || body.is_empty() {
+            continue;
+        }
+        run_instructions_checked += 1;
+
+        linter.lint(
+            &body,
+            "sh",
+            ShellSource::RunInstruction,
+            None,
+            &format!("layer_{}", index + 1),
+        );
+    }
+
+    let file = File::open(&tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+    let mut scripts_checked = 0;
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        if !entry.header().entry_type().is_file() || entry.size() > MAX_SCRIPT_SIZE {
+            continue;
+        }
+        let path = format!(
+            "/{}",
+            entry_relative_path(
+                &entry
+                    .path()
+                    .map_err(|e| format!("Failed to read tar entry path: {}", e))?
+            )
+        );
+
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if is_binary_content(&bytes) {
+            continue;
+        }
+        let content = String::from_utf8_lossy(&bytes);
+        let Some(shell) = script_shell(&path, &content) else {
+            continue;
+        };
+
+        scripts_checked += 1;
+        linter.lint(
+            &content,
+            &shell,
+            ShellSource::Script,
+            Some(&path),
+            &layer_id,
+        );
+    }
+
+    if options.use_shellcheck && !linter.shellcheck_used {
+        info!("shellcheck is not available, only the bundled rules were used");
+    }
+    info!(
+        "Linted {} RUN instructions and {} scripts, found {} issues",
+        run_instructions_checked,
+        scripts_checked,
+        linter.issues.len()
+    );
+    Ok(ShellLintReport {
+        layer_id,
+        run_instructions_checked,
+        scripts_checked,
+        shellcheck_used: linter.shellcheck_used,
+        issues: linter.issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_ids(script: &str, shellcheck_used: bool) -> Vec<String> {
+        lint_with_rules(
+            script,
+            ShellSource::Script,
+            None,
+            "layer_1",
+            shellcheck_used,
+        )
+        .into_iter()
+        .map(|issue| issue.finding.rule_id)
+        .collect()
+    }
+
+    #[test]
+    fn pipe_to_shell_matches_downloads_run_by_a_shell() {
+        assert!(pipe_to_shell("curl -fsSL https://get.example.com | sh"));
+        assert!(pipe_to_shell(
+            "wget -qO- https://example.com/install | sudo bash"
+        ));
+        assert!(pipe_to_shell("curl -s https://x.io/i.sh |zsh -s -- --yes"));
+        assert!(!pipe_to_shell(
+            "curl -fsSLo install.sh https://get.example.com"
+        ));
+        assert!(!pipe_to_shell("curl https://example.com | tar xz"));
+        assert!(!pipe_to_shell("curl https://example.com; cat x | sh"));
+    }
+
+    #[test]
+    fn recursive_rm_matches_variable_paths_only() {
+        assert!(recursive_rm_of_variable("rm -rf $PREFIX/lib"));
+        assert!(recursive_rm_of_variable("rm -fR \"${BUILD_DIR}/\""));
+        assert!(!recursive_rm_of_variable("rm -rf /tmp/build"));
+        assert!(!recursive_rm_of_variable("rm -f $TMPFILE/"));
+        assert!(!recursive_rm_of_variable("rm -rf \"${BUILD_DIR:?}\""));
+    }
+
+    #[test]
+    fn world_writable_chmod_matches_777() {
+        assert!(world_writable_chmod("chmod 777 /app"));
+        assert!(world_writable_chmod("chmod -R 0777 /data"));
+        assert!(!world_writable_chmod("chmod 755 /app"));
+        assert!(!world_writable_chmod("chmod 7777 /app"));
+    }
+
+    #[test]
+    fn unquoted_variable_ignores_quoted_and_assigned_ones() {
+        assert!(unquoted_variable("cp $SRC /dst"));
+        assert!(unquoted_variable("echo \"ok\" ${NAME}"));
+        assert!(!unquoted_variable("cp \"$SRC\" /dst"));
+        assert!(!unquoted_variable("echo '$NOT_EXPANDED'"));
+        assert!(!unquoted_variable("PATH=$HOME/bin"));
+        assert!(!unquoted_variable("echo \\$LITERAL"));
+        assert!(!unquoted_variable("echo $1 $? $$"));
+        assert!(!unquoted_variable("make # uses $CC"));
+        assert!(unquoted_variable("echo a#b $X"));
+    }
+
+    #[test]
+    fn backticks_outside_single_quotes_are_flagged() {
+        assert!(uses_backticks("VERSION=`cat VERSION`"));
+        assert!(uses_backticks("echo \"built `date`\""));
+        assert!(!uses_backticks("echo '`literal`'"));
+        assert!(!uses_backticks("VERSION=$(cat VERSION)"));
+    }
+
+    #[test]
+    fn comment_lines_are_skipped_and_lines_counted() {
+        let issues = lint_with_rules(
+            "#!/bin/sh\n# curl https://x | sh\nchmod 777 /app\n",
+            ShellSource::Script,
+            Some("/entrypoint.sh"),
+            "layer_2",
+            false,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 3);
+        assert_eq!(issues[0].snippet, "chmod 777 /app");
+        assert_eq!(issues[0].finding.rule_id, "shell-chmod-777");
+        assert_eq!(issues[0].finding.path.as_deref(), Some("/entrypoint.sh"));
+        assert_eq!(issues[0].finding.layer_id.as_deref(), Some("layer_2"));
+    }
+
+    #[test]
+    fn rules_shellcheck_covers_are_skipped_after_it_ran() {
+        let script = "cp $SRC /dst\nV=`date`\ncurl https://x | sh";
+        assert_eq!(
+            rule_ids(script, false),
+            vec![
+                "shell-unquoted-variable",
+                "shell-backticks",
+                "shell-pipe-to-shell"
+            ]
+        );
+        assert_eq!(rule_ids(script, true), vec!["shell-pipe-to-shell"]);
+    }
+
+    #[test]
+    fn script_shell_reads_shebangs_and_extensions() {
+        assert_eq!(
+            script_shell("/entrypoint", "#!/bin/bash\nset -e"),
+            Some("bash".to_string())
+        );
+        assert_eq!(
+            script_shell("/run", "#!/usr/bin/env ash\n"),
+            Some("ash".to_string())
+        );
+        assert_eq!(
+            script_shell("/setup.sh", "set -e\n"),
+            Some("sh".to_string())
+        );
+        assert_eq!(script_shell("/app.py", "#!/usr/bin/env python3\n"), None);
+        assert_eq!(script_shell("/tool.sh", "#!/usr/bin/python\n"), None);
+        assert_eq!(script_shell("/README", "hello"), None);
+    }
+
+    #[test]
+    fn long_snippets_are_truncated() {
+        let line = format!("  {}  ", "é".repeat(300));
+        let snippet = truncate_snippet(&line);
+        assert_eq!(snippet, format!("{}...", "é".repeat(200)));
+        assert_eq!(truncate_snippet("  short  "), "short");
+    }
+}
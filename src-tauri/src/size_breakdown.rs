@@ -0,0 +1,185 @@
+use layers_core::layer_tar::{entry_relative_path, WHITEOUT_PREFIX};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::layer_tar_path;
+use crate::search_index::read_saved_layers;
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+const DEFAULT_MAX_DEPTH: usize = 4;
+// Children below this share of the layer are merged into one node, a treemap
+// can't show them anyway
+const MIN_NODE_FRACTION: f64 = 0.001;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SizeNode {
+    name: String,
+    // Container path, empty for the merged "smaller items" nodes
+    path: String,
+    size_bytes: u64,
+    file_count: usize,
+    // Largest first
+    children: Vec<SizeNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerSizeBreakdown {
+    layer_id: String,
+    created_by: String,
+    total_bytes: u64,
+    file_count: usize,
+    root: SizeNode,
+}
+
+#[derive(Default)]
+struct SizeTree {
+    size: u64,
+    file_count: usize,
+    children: BTreeMap<String, SizeTree>,
+}
+
+impl SizeTree {
+    fn add(&mut self, path: &str, size: u64) {
+        self.size += size;
+        self.file_count += 1;
+        if let Some((name, rest)) = path.split_once('/') {
+            self.children
+                .entry(name.to_string())
+                .or_default()
+                .add(rest, size);
+        } else if !path.is_empty() {
+            let leaf = self.children.entry(path.to_string()).or_default();
+            leaf.size += size;
+            leaf.file_count += 1;
+        }
+    }
+
+    fn to_node(&self, name: &str, path: &str, depth: usize, min_size: u64) -> SizeNode {
+        let mut children = Vec::new();
+        if depth > 0 {
+            let mut small_size = 0;
+            let mut small_files = 0;
+            let mut small_count = 0;
+            for (child_name, child) in &self.children {
+                if child.size < min_size {
+                    small_size += child.size;
+                    small_files += child.file_count;
+                    small_count += 1;
+                    continue;
+                }
+                children.push(child.to_node(
+                    child_name,
+                    &format!("{}/{}", path.trim_end_matches('/'), child_name),
+                    depth - 1,
+                    min_size,
+                ));
+            }
+            if small_count > 0 {
+                children.push(SizeNode {
+                    name: format!("{} smaller items", small_count),
+                    path: String::new(),
+                    size_bytes: small_size,
+                    file_count: small_files,
+                    children: Vec::new(),
+                });
+            }
+            children.sort_by_key(|child| Reverse(child.size_bytes));
+        }
+        SizeNode {
+            name: name.to_string(),
+            path: path.to_string(),
+            size_bytes: self.size,
+            file_count: self.file_count,
+            children,
+        }
+    }
+}
+
+// Only regular files take up space, whiteouts and directories are skipped
+fn read_file_sizes<R: Read>(reader: R) -> io::Result<Vec<(String, u64)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry_relative_path(&entry.path()?);
+        if path
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| name.starts_with(WHITEOUT_PREFIX))
+        {
+            continue;
+        }
+        files.push((path, entry.size()));
+    }
+    Ok(files)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_layer_size_breakdown(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+    max_depth: Option<usize>,
+) -> Result<LayerSizeBreakdown, LayersError> {
+    info!("Building size breakdown for layer {}", layer_id);
+
+    // The layer_N tars hold everything up to the layer, what a single layer
+    // added needs docker save
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let files = if layer_id == "current_layer" {
+        layer_tar_path(&task, &session, &layer_id).and_then(|tar_path| {
+            let file = File::open(&tar_path)
+                .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+            read_file_sizes(file)
+                .map(|files| (String::new(), files))
+                .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))
+        })
+    } else {
+        read_saved_layers(&task, session.image_id(), |reader| read_file_sizes(reader)).and_then(
+            |layers| {
+                // Metadata-only instructions have no filesystem layer and add nothing
+                let Some(layer) = layers.into_iter().find(|layer| layer.layer_id == layer_id)
+                else {
+                    return Ok(Default::default());
+                };
+                match layer.error {
+                    Some(e) => Err(format!("Failed to read {}: {}", layer_id, e)),
+                    None => Ok((layer.created_by, layer.contents)),
+                }
+            },
+        )
+    };
+    tasks.finish(task.id);
+    let (created_by, files) = files?;
+
+    let mut tree = SizeTree::default();
+    for (path, size) in &files {
+        tree.add(path, *size);
+    }
+    let min_size = (tree.size as f64 * MIN_NODE_FRACTION) as u64;
+    let root = tree.to_node("/", "/", max_depth.unwrap_or(DEFAULT_MAX_DEPTH), min_size);
+
+    info!(
+        "Layer {} has {} files totalling {} bytes",
+        layer_id, tree.file_count, tree.size
+    );
+    Ok(LayerSizeBreakdown {
+        layer_id,
+        created_by,
+        total_bytes: tree.size,
+        file_count: tree.file_count,
+        root,
+    })
+}
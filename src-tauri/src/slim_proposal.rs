@@ -0,0 +1,612 @@
+use layers_core::docker::{docker_command, inspect_image};
+use layers_core::layer_tar::entry_relative_path;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::exec_safety::{self, ExecutionKind};
+use crate::links::{resolve_link, LinkInfo, LinkKind, MAX_LINK_HOPS};
+use crate::phases::{phase, Phase, PhaseProgress};
+use crate::runtime_writes::is_running;
+use crate::session::{ImageSession, SessionState};
+use crate::startup_check::docker_output;
+use crate::tasks::{Task, TaskRegistry};
+use crate::{finish_task, layer_tar_path, run_config, ImageRunConfig};
+
+const DEFAULT_DURATION_SECONDS: u64 = 30;
+const MAX_DURATION_SECONDS: u64 = 600;
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+// Written inside the container, then copied out with docker cp
+const TRACE_FILE: &str = "/tmp/.layers_trace";
+const STRACE_PATHS: [&str; 3] = ["usr/bin/strace", "bin/strace", "usr/local/bin/strace"];
+// Read by libc or TLS stacks on demand, a short run may never touch them
+const ALWAYS_KEEP: [&str; 10] = [
+    "etc/passwd",
+    "etc/group",
+    "etc/nsswitch.conf",
+    "etc/hosts",
+    "etc/resolv.conf",
+    "etc/localtime",
+    "etc/ssl/",
+    "etc/pki/",
+    "usr/share/ca-certificates/",
+    "usr/share/zoneinfo/",
+];
+// Never read at runtime by anything but a person
+const PRUNABLE: [&str; 8] = [
+    "usr/share/doc/",
+    "usr/share/man/",
+    "usr/share/info/",
+    "usr/share/locale/",
+    "usr/include/",
+    "var/cache/",
+    "var/lib/apt/lists/",
+    "var/log/",
+];
+const BINARY_DIRS: [&str; 5] = ["bin/", "sbin/", "usr/bin/", "usr/sbin/", "usr/local/bin/"];
+const LARGEST_REMOVED: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct SlimOptions {
+    duration_seconds: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileMethod {
+    // Every file opened or executed, the image ships strace
+    Strace,
+    // Binaries and libraries mapped by the running processes
+    Procfs,
+    // No profile, only files nothing needs at runtime are dropped
+    None,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemovedFile {
+    path: String,
+    size_bytes: u64,
+}
+
+// Files a minimal image would keep and a Dockerfile that builds it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlimProposal {
+    image_id: String,
+    profile: ProfileMethod,
+    duration_ms: u64,
+    // Stopped on its own before the run ended
+    exited: bool,
+    // Distinct paths the profile saw
+    accessed_paths: usize,
+    total_files: usize,
+    kept_files: usize,
+    total_bytes: u64,
+    kept_bytes: u64,
+    reduction_bytes: u64,
+    reduction_percent: f64,
+    // COPY sources of the Dockerfile, whole directories where nothing in them
+    // was dropped
+    copy_paths: Vec<String>,
+    largest_removed: Vec<RemovedFile>,
+    dockerfile: String,
+    warnings: Vec<String>,
+}
+
+// Non-directory entries of the final filesystem, by path relative to the root
+#[derive(Default)]
+struct Inventory {
+    files: BTreeMap<String, (u64, Option<LinkInfo>)>,
+    dirs: BTreeSet<String>,
+}
+
+fn read_inventory(tar_path: &Path) -> Result<Inventory, String> {
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+    let mut inventory = Inventory::default();
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to list tar contents: {}", e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let path = entry_relative_path(
+            &entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?,
+        );
+        let entry_type = entry.header().entry_type();
+        if path.is_empty() {
+            continue;
+        }
+        if entry_type.is_dir() {
+            inventory.dirs.insert(path);
+            continue;
+        }
+        let kind = if entry_type.is_symlink() {
+            Some(LinkKind::Symlink)
+        } else if entry_type.is_hard_link() {
+            Some(LinkKind::Hardlink)
+        } else {
+            None
+        };
+        let link = kind.and_then(|kind| {
+            let target = entry.link_name().ok()??;
+            Some(LinkInfo {
+                kind,
+                target: target.to_string_lossy().to_string(),
+            })
+        });
+        inventory.files.insert(path, (entry.size(), link));
+    }
+    Ok(inventory)
+}
+
+// First quoted argument of every successful call in strace output, e.g.
+// openat(AT_FDCWD, "/etc/ld.so.cache", O_RDONLY|O_CLOEXEC) = 3
+fn parse_trace(trace: &str, working_dir: &str) -> HashSet<String> {
+    trace
+        .lines()
+        .filter(|line| !line.contains("= -1"))
+        .filter_map(|line| {
+            let start = line.find('"')? + 1;
+            let end = start + line[start..].find('"')?;
+            let path = &line[start..end];
+            let path = if path.starts_with('/') {
+                path.to_string()
+            } else {
+                format!("{}/{}", working_dir, path)
+            };
+            Some(entry_relative_path(Path::new(&path)))
+        })
+        .filter(|path| !path.is_empty())
+        .collect()
+}
+
+// File-backed mappings of /proc/*/maps, the last column
+fn parse_maps(maps: &str) -> impl Iterator<Item = String> + '_ {
+    maps.lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .filter(|path| path.starts_with('/'))
+        .map(|path| entry_relative_path(Path::new(path)))
+}
+
+// Maps of every process when the image has a shell, of the entrypoint
+// process otherwise. None when not even cat is there.
+fn sample_maps(task: &Task, container_name: &str) -> Option<String> {
+    let attempts: [&[&str]; 2] = [
+        &["sh", "-c", "cat /proc/[0-9]*/maps 2>/dev/null"],
+        &["cat", "/proc/1/maps"],
+    ];
+    attempts.iter().find_map(|command| {
+        task.run(
+            docker_command()
+                .arg("exec")
+                .arg(container_name)
+                .args(*command),
+        )
+        .ok()
+        .filter(|output| output.status.success() && !output.stdout.is_empty())
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+    })
+}
+
+// docker cp writes a tar to stdout, with the one file in it
+fn copy_trace(task: &Task, container_name: &str) -> Result<String, String> {
+    let output = task
+        .run(docker_command().args(["cp", &format!("{}:{}", container_name, TRACE_FILE), "-"]))
+        .map_err(|e| format!("Failed to copy the trace: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to copy the trace: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let mut archive = tar::Archive::new(output.stdout.as_slice());
+    let mut trace = String::new();
+    archive
+        .entries()
+        .and_then(|mut entries| match entries.next() {
+            Some(entry) => entry?.read_to_string(&mut trace).map(|_| ()),
+            None => Ok(()),
+        })
+        .map_err(|e| format!("Failed to read the trace: {}", e))?;
+    Ok(trace)
+}
+
+struct Profile {
+    method: ProfileMethod,
+    accessed: HashSet<String>,
+    exited: bool,
+    duration_ms: u64,
+}
+
+// Runs the image on an internal network under strace when it has one,
+// sampling the processes' memory maps otherwise
+fn profile_run(
+    task: &Task,
+    name: &str,
+    image_id: &str,
+    config: &ImageRunConfig,
+    strace: Option<&str>,
+    duration: Duration,
+) -> Result<Profile, String> {
+    let command: Vec<&str> = config
+        .entrypoint
+        .iter()
+        .chain(config.cmd.iter())
+        .map(String::as_str)
+        .collect();
+    let strace = strace.filter(|_| !command.is_empty());
+
+    let mut args = vec!["run", "--detach", "--name", name, "--network", name];
+    args.extend(exec_safety::RUN_LIMITS);
+    let strace_path = strace.map(|path| format!("/{}", path));
+    if let Some(strace_path) = &strace_path {
+        // Added back after --cap-drop ALL, strace can't attach without it
+        args.extend(["--cap-add", "SYS_PTRACE", "--entrypoint", strace_path]);
+    }
+    args.push(image_id);
+    if strace.is_some() {
+        args.extend([
+            "-f",
+            "-qq",
+            "-o",
+            TRACE_FILE,
+            "-e",
+            "trace=file,execve",
+            "--",
+        ]);
+        args.extend(&command);
+    }
+    let _ = audit::docker(&["rm", "-f", name]);
+    task.track_container(name);
+    docker_output(task, &args)?;
+
+    let mut method = if strace.is_some() {
+        ProfileMethod::Strace
+    } else {
+        ProfileMethod::Procfs
+    };
+    let mut accessed = HashSet::new();
+    let mut sampled = false;
+    let mut exited = false;
+    let started = Instant::now();
+    while started.elapsed() < duration {
+        task.check_cancelled()?;
+        if !is_running(task, name)? {
+            exited = true;
+            break;
+        }
+        if method == ProfileMethod::Procfs {
+            if let Some(maps) = sample_maps(task, name) {
+                accessed.extend(parse_maps(&maps));
+                sampled = true;
+            }
+        }
+        thread::sleep(SAMPLE_INTERVAL);
+    }
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    match method {
+        ProfileMethod::Strace => {
+            let working_dir = if config.working_dir.is_empty() {
+                "/"
+            } else {
+                &config.working_dir
+            };
+            accessed = parse_trace(&copy_trace(task, name)?, working_dir);
+        }
+        ProfileMethod::Procfs if !sampled => method = ProfileMethod::None,
+        _ => {}
+    }
+    Ok(Profile {
+        method,
+        accessed,
+        exited,
+        duration_ms,
+    })
+}
+
+fn matches_any(path: &str, prefixes: &[&str]) -> bool {
+    prefixes
+        .iter()
+        .any(|prefix| match prefix.strip_suffix('/') {
+            Some(dir) => path.starts_with(prefix) || path == dir,
+            None => path == *prefix,
+        })
+}
+
+// Executables and shared libraries, the files a memory map profile can vouch for
+fn is_binary(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    BINARY_DIRS.iter().any(|dir| {
+        path.strip_prefix(dir)
+            .is_some_and(|rest| !rest.contains('/'))
+    }) || name.ends_with(".so")
+        || name.contains(".so.")
+}
+
+// The profile decides for the files it can speak for, the rest is kept
+// unless nothing at runtime reads it
+fn keep_set(inventory: &Inventory, profile: &Profile) -> BTreeSet<String> {
+    let mut keep: BTreeSet<String> = inventory
+        .files
+        .keys()
+        .filter(|path| {
+            profile.accessed.contains(*path)
+                || matches_any(path, &ALWAYS_KEEP)
+                || match profile.method {
+                    ProfileMethod::Strace => false,
+                    ProfileMethod::Procfs => !matches_any(path, &PRUNABLE) && !is_binary(path),
+                    ProfileMethod::None => !matches_any(path, &PRUNABLE),
+                }
+        })
+        .cloned()
+        .collect();
+
+    // Links are only useful with what they point to
+    let links: Vec<String> = keep.iter().cloned().collect();
+    for path in links {
+        let mut current = path;
+        for _ in 0..MAX_LINK_HOPS {
+            let Some((_, Some(link))) = inventory.files.get(&current) else {
+                break;
+            };
+            current = resolve_link(&current, link);
+            if inventory.files.contains_key(&current) {
+                keep.insert(current.clone());
+            }
+        }
+    }
+    keep
+}
+
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices('/').map(move |(i, _)| &path[..i])
+}
+
+// The shortest list of paths to COPY: a directory when all of its files are
+// kept, the files themselves otherwise
+fn copy_paths(inventory: &Inventory, keep: &BTreeSet<String>) -> Vec<String> {
+    let mut totals: HashMap<&str, (usize, usize)> = HashMap::new();
+    for path in inventory.files.keys() {
+        let kept = keep.contains(path);
+        for dir in ancestors(path) {
+            let counts = totals.entry(dir).or_default();
+            counts.0 += 1;
+            counts.1 += kept as usize;
+        }
+    }
+    let complete = |dir: &str| totals.get(dir).is_some_and(|(total, kept)| total == kept);
+
+    let mut paths: BTreeSet<String> = keep
+        .iter()
+        .map(|path| {
+            ancestors(path)
+                .find(|dir| complete(dir))
+                .unwrap_or(path)
+                .to_string()
+        })
+        .collect();
+    // Scratch has no /tmp, programs expect one
+    if inventory.dirs.contains("tmp") {
+        paths.insert("tmp".to_string());
+    }
+    paths.into_iter().map(|path| format!("/{}", path)).collect()
+}
+
+fn dockerfile_arguments(values: &[String]) -> String {
+    serde_json::to_string(values).unwrap_or_default()
+}
+
+fn slim_dockerfile(
+    image_id: &str,
+    method: ProfileMethod,
+    config: &ImageRunConfig,
+    paths: &[String],
+) -> String {
+    let mut lines = vec![
+        format!(
+            "# Candidate minimal image, proposed from a {:?} profile of {}.",
+            method, image_id
+        ),
+        "# Files the profiled run didn't need are left out, test it before shipping.".to_string(),
+        format!("FROM {} AS source", image_id),
+        String::new(),
+        "FROM scratch".to_string(),
+    ];
+    for path in paths {
+        if path.contains(char::is_whitespace) {
+            lines.push(format!(
+                "COPY --from=source {}",
+                dockerfile_arguments(&[path.clone(), path.clone()])
+            ));
+        } else {
+            lines.push(format!("COPY --from=source {} {}", path, path));
+        }
+    }
+    for variable in &config.env {
+        if let Some((key, value)) = variable.split_once('=') {
+            lines.push(format!(
+                "ENV {}={}",
+                key,
+                serde_json::to_string(value).unwrap_or_default()
+            ));
+        }
+    }
+    if !config.working_dir.is_empty() {
+        lines.push(format!("WORKDIR {}", config.working_dir));
+    }
+    if !config.user.is_empty() {
+        lines.push(format!("USER {}", config.user));
+    }
+    for port in &config.exposed_ports {
+        lines.push(format!("EXPOSE {}", port));
+    }
+    if !config.entrypoint.is_empty() {
+        lines.push(format!(
+            "ENTRYPOINT {}",
+            dockerfile_arguments(&config.entrypoint)
+        ));
+    }
+    if !config.cmd.is_empty() {
+        lines.push(format!("CMD {}", dockerfile_arguments(&config.cmd)));
+    }
+    lines.join("\n") + "\n"
+}
+
+fn slim_warnings(profile: &Profile) -> Vec<String> {
+    let mut warnings = Vec::new();
+    match profile.method {
+        ProfileMethod::Strace => warnings.push(
+            "Only files the run touched are kept, code paths it didn't reach may need more"
+                .to_string(),
+        ),
+        ProfileMethod::Procfs => warnings.push(
+            "The image has no strace, only executables and libraries were profiled. Programs \
+             started later than the run may be missing."
+                .to_string(),
+        ),
+        ProfileMethod::None => warnings.push(
+            "The container couldn't be profiled, only documentation, caches and similar files \
+             were dropped"
+                .to_string(),
+        ),
+    }
+    if profile.exited {
+        warnings.push(
+            "The container stopped before the run ended, the profile may be incomplete".to_string(),
+        );
+    }
+    warnings
+}
+
+const SLIM_PROPOSAL_PHASES: &[Phase] = &[
+    phase("index", 0.05),
+    phase("prepare", 0.05),
+    phase("observe", 0.7),
+    phase("cleanup", 0.1),
+    phase("analyze", 0.1),
+];
+
+fn slim_proposal_task(
+    window: &tauri::Window,
+    task: &Task,
+    session: &ImageSession,
+    image_id: &str,
+    duration: Duration,
+) -> Result<SlimProposal, String> {
+    let progress = PhaseProgress::new(window, task.id, SLIM_PROPOSAL_PHASES);
+    progress.begin("index", "Reading the image filesystem");
+    let inventory = read_inventory(&layer_tar_path(task, session, "current_layer")?)?;
+    let config = run_config(&inspect_image(image_id)?["Config"]);
+    let strace = STRACE_PATHS
+        .into_iter()
+        .find(|path| inventory.files.contains_key(*path));
+
+    let name = format!("layers_slim_{}", task.id);
+    progress.begin("prepare", "Creating isolated network");
+    docker_output(task, &["network", "create", "--internal", &name])?;
+    progress.begin("observe", "Profiling the container");
+    let profile = profile_run(task, &name, image_id, &config, strace, duration);
+    progress.begin("cleanup", "Removing container");
+    let _ = audit::docker(&["rm", "-f", &name]);
+    let _ = audit::docker(&["network", "rm", &name]);
+    let profile = profile?;
+
+    progress.begin("analyze", "Proposing a minimal file set");
+    let keep = keep_set(&inventory, &profile);
+    let total_bytes: u64 = inventory.files.values().map(|(size, _)| size).sum();
+    let kept_bytes: u64 = keep
+        .iter()
+        .filter_map(|path| inventory.files.get(path))
+        .map(|(size, _)| size)
+        .sum();
+    let mut removed: Vec<RemovedFile> = inventory
+        .files
+        .iter()
+        .filter(|(path, _)| !keep.contains(*path))
+        .map(|(path, (size, _))| RemovedFile {
+            path: format!("/{}", path),
+            size_bytes: *size,
+        })
+        .collect();
+    removed.sort_by_key(|file| Reverse(file.size_bytes));
+    removed.truncate(LARGEST_REMOVED);
+
+    let copy_paths = copy_paths(&inventory, &keep);
+    let reduction_bytes = total_bytes - kept_bytes;
+    Ok(SlimProposal {
+        image_id: image_id.to_string(),
+        profile: profile.method,
+        duration_ms: profile.duration_ms,
+        exited: profile.exited,
+        accessed_paths: profile.accessed.len(),
+        total_files: inventory.files.len(),
+        kept_files: keep.len(),
+        total_bytes,
+        kept_bytes,
+        reduction_bytes,
+        reduction_percent: if total_bytes > 0 {
+            reduction_bytes as f64 / total_bytes as f64 * 100.0
+        } else {
+            0.0
+        },
+        dockerfile: slim_dockerfile(image_id, profile.method, &config, &copy_paths),
+        copy_paths,
+        largest_removed: removed,
+        warnings: slim_warnings(&profile),
+    })
+}
+
+// Proposes a minimal version of the selected image from a short profiled run,
+// docker-slim style but without needing docker-slim. Nothing is built, the
+// Dockerfile is for the user to try.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn propose_slim_image(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    options: Option<SlimOptions>,
+) -> Result<SlimProposal, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let image_id = session.image_id().to_string();
+    let duration = options
+        .unwrap_or_default()
+        .duration_seconds
+        .unwrap_or(DEFAULT_DURATION_SECONDS)
+        .clamp(1, MAX_DURATION_SECONDS);
+    info!("Profiling {} for a slim image proposal", image_id);
+    exec_safety::confirm_execution(
+        &window,
+        ExecutionKind::RunImage,
+        &format!("Profile {} for {}s", session.reference(), duration),
+    )?;
+
+    let task = tasks.start();
+    let result = slim_proposal_task(
+        &window,
+        &task,
+        &session,
+        &image_id,
+        Duration::from_secs(duration),
+    );
+    finish_task(&window, &tasks, &task);
+    let proposal = result?;
+
+    info!(
+        "Keeping {} of {} files, {} of {} bytes",
+        proposal.kept_files, proposal.total_files, proposal.kept_bytes, proposal.total_bytes
+    );
+    Ok(proposal)
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::search_index::{build_search_index, session_search_index, SearchIndex};
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+// Files of the squashed layer listed in the result, the counts cover the rest
+const LARGEST_FILES: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SquashedFile {
+    path: String,
+    size_bytes: u64,
+    // Layer of the range that last wrote the file
+    layer_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SquashSimulation {
+    image: String,
+    // The squashed layers, base layer first
+    layers: Vec<String>,
+    // File bytes the layers ship now, hidden versions included
+    original_bytes: u64,
+    // File bytes of the single layer replacing them
+    squashed_bytes: u64,
+    saved_bytes: u64,
+    // File bytes of all layers of the image, before and after
+    image_bytes: u64,
+    squashed_image_bytes: u64,
+    file_count: usize,
+    // Biggest first
+    largest_files: Vec<SquashedFile>,
+    // Lower layer paths the squashed layer still deletes
+    whiteouts: Vec<String>,
+}
+
+// Accepts "layer_3" as well as "3", like ensure_layer_tar
+fn layer_position(index: &SearchIndex, layer_id: &str) -> Result<usize, String> {
+    let layer_id = format!(
+        "layer_{}",
+        layer_id.strip_prefix("layer_").unwrap_or(layer_id)
+    );
+    index
+        .layer_position(&layer_id)
+        .ok_or_else(|| format!("{} is not a filesystem layer of the image", layer_id))
+}
+
+// What the image would look like with the layers from `from_layer` to
+// `to_layer` squashed into one. The final filesystem stays the same, the
+// savings come from files the range writes and then replaces or removes.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn simulate_squash(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+    from_layer: String,
+    to_layer: String,
+) -> Result<SquashSimulation, LayersError> {
+    let image = session.image_or_selected(image)?;
+    info!(
+        "Simulating squash of {} to {} in {}",
+        from_layer, to_layer, image
+    );
+
+    // The selected image has its index saved with the session
+    let selected = session.get(None).ok();
+    let index = match selected.filter(|selected| selected.image_id() == image) {
+        Some(selected) => session_search_index(&tasks, &selected, false)?,
+        None => {
+            let task = tasks.start();
+            let index = build_search_index(&task, &image);
+            tasks.finish(task.id);
+            index?
+        }
+    };
+
+    let from = layer_position(&index, &from_layer)?;
+    let to = layer_position(&index, &to_layer)?;
+    // Layers can be given newest first too
+    let range = from.min(to)..=from.max(to);
+    let layer_info = index.layer_info();
+    let layers: Vec<String> = layer_info[range.clone()]
+        .iter()
+        .map(|(layer_id, _)| layer_id.to_string())
+        .collect();
+
+    let squashed = index.squash_range(range);
+    let squashed_bytes: u64 = squashed.files.iter().map(|file| file.size).sum();
+    let saved_bytes = squashed.shipped_bytes.saturating_sub(squashed_bytes);
+    let image_bytes = index.shipped_bytes();
+
+    let mut files = squashed.files;
+    files.sort_by_key(|file| Reverse(file.size));
+    let file_count = files.len();
+    let largest_files = files
+        .into_iter()
+        .take(LARGEST_FILES)
+        .map(|file| SquashedFile {
+            path: file.path,
+            size_bytes: file.size,
+            layer_id: layer_info[file.layer].0.to_string(),
+        })
+        .collect();
+
+    info!(
+        "Squashing {} layers would save {} bytes",
+        layers.len(),
+        saved_bytes
+    );
+    Ok(SquashSimulation {
+        image,
+        layers,
+        original_bytes: squashed.shipped_bytes,
+        squashed_bytes,
+        saved_bytes,
+        image_bytes,
+        squashed_image_bytes: image_bytes - saved_bytes,
+        file_count,
+        largest_files,
+        whiteouts: squashed.whiteouts,
+    })
+}
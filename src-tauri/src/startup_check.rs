@@ -0,0 +1,438 @@
+use layers_core::docker::{docker_command, inspect_image};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::exec_safety::{self, ExecutionKind};
+use crate::findings::{SecurityFinding, Severity};
+use crate::finish_task;
+use crate::phases::{phase, Phase, PhaseProgress};
+use crate::session::SessionState;
+use crate::tasks::{Task, TaskRegistry};
+
+// The check ends as soon as the container is ready, a generous timeout only
+// costs time on images that never get there
+const DEFAULT_TIMEOUT_SECONDS: u64 = 120;
+const MAX_TIMEOUT_SECONDS: u64 = 600;
+// Without a healthcheck or ports there's nothing to wait for, a container
+// still up after this long counts as started
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Startup output is what explains a slow or failed boot, the rest is noise
+const LOG_LINES: usize = 100;
+// Kept in the image session so reports include the last check
+const SAVED_CHECK_FILE: &str = "startup_check.json";
+// Socket state of a listening TCP socket in /proc/net/tcp
+const TCP_LISTEN: &str = "0A";
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct StartupCheckOptions {
+    timeout_seconds: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupOutcome {
+    // Still up when the check ended, without a healthcheck to ask
+    Running,
+    Healthy,
+    Unhealthy,
+    // Stopped on its own before the check ended
+    Exited,
+    // The healthcheck was still starting at the timeout
+    TimedOut,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PortCheck {
+    pub(crate) port: u16,
+    pub(crate) protocol: String,
+    // None when the sockets couldn't be read, e.g. distroless images without
+    // a cat binary, or for UDP ports
+    pub(crate) listening: Option<bool>,
+}
+
+// What happened when the image was started on a network with no way out
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartupCheck {
+    pub(crate) image_id: String,
+    pub(crate) outcome: StartupOutcome,
+    // Entrypoint and arguments the container ran
+    pub(crate) command: Vec<String>,
+    pub(crate) exit_code: Option<i64>,
+    // Last healthcheck status, None without a HEALTHCHECK
+    pub(crate) health: Option<String>,
+    // From start until healthy or listening on every exposed port, None when
+    // that never happened or there was nothing to wait for
+    pub(crate) ready_ms: Option<u64>,
+    // How long the check watched the container
+    pub(crate) duration_ms: u64,
+    pub(crate) ports: Vec<PortCheck>,
+    // First LOG_LINES lines of output, both streams with docker timestamps
+    pub(crate) logs: Vec<String>,
+    pub(crate) logs_truncated: bool,
+    pub(crate) findings: Vec<SecurityFinding>,
+}
+
+// What `docker inspect` says about the container
+struct ContainerState {
+    running: bool,
+    command: Vec<String>,
+    exit_code: Option<i64>,
+    health: Option<String>,
+}
+
+pub(crate) fn docker_output(task: &Task, args: &[&str]) -> Result<String, String> {
+    let output = task
+        .run(docker_command().args(args))
+        .map_err(|e| format!("Failed to run docker {}: {}", args[0], e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "docker {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn container_state(task: &Task, container_name: &str) -> Result<ContainerState, String> {
+    let output = docker_output(task, &["container", "inspect", container_name])?;
+    let inspect: Vec<serde_json::Value> = serde_json::from_str(&output)
+        .map_err(|e| format!("Failed to parse docker inspect output: {}", e))?;
+    let state = &inspect.first().ok_or("Container not found")?["State"];
+    let running = state["Running"].as_bool().unwrap_or(false);
+    let command = std::iter::once(&inspect[0]["Path"])
+        .chain(inspect[0]["Args"].as_array().into_iter().flatten())
+        .filter_map(|arg| arg.as_str())
+        .map(str::to_string)
+        .collect();
+    Ok(ContainerState {
+        running,
+        command,
+        exit_code: state["ExitCode"].as_i64().filter(|_| !running),
+        health: state["Health"]["Status"].as_str().map(str::to_string),
+    })
+}
+
+// "8080/tcp" keys of the image config
+fn exposed_ports(config: &serde_json::Value) -> Vec<(u16, String)> {
+    let mut ports: Vec<(u16, String)> = config["ExposedPorts"]
+        .as_object()
+        .map(|ports| {
+            ports
+                .keys()
+                .filter_map(|key| {
+                    let (port, protocol) = key.split_once('/').unwrap_or((key, "tcp"));
+                    Some((port.parse().ok()?, protocol.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    ports.sort();
+    ports
+}
+
+// Ports with a listening TCP socket inside the container, read from procfs
+// since the image may not ship netstat or ss
+fn listening_ports(task: &Task, container_name: &str) -> Option<Vec<u16>> {
+    let output = task
+        .run(docker_command().args([
+            "exec",
+            container_name,
+            "cat",
+            "/proc/net/tcp",
+            "/proc/net/tcp6",
+        ]))
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let (_, port) = fields.get(1)?.rsplit_once(':')?;
+                (*fields.get(3)? == TCP_LISTEN)
+                    .then(|| u16::from_str_radix(port, 16).ok())
+                    .flatten()
+            })
+            .collect(),
+    )
+}
+
+fn port_checks(ports: &[(u16, String)], listening: Option<&[u16]>) -> Vec<PortCheck> {
+    ports
+        .iter()
+        .map(|(port, protocol)| PortCheck {
+            port: *port,
+            protocol: protocol.clone(),
+            listening: listening
+                .filter(|_| protocol == "tcp")
+                .map(|listening| listening.contains(port)),
+        })
+        .collect()
+}
+
+fn startup_findings(
+    outcome: StartupOutcome,
+    exit_code: Option<i64>,
+    ports: &[PortCheck],
+) -> Vec<SecurityFinding> {
+    let mut findings = Vec::new();
+    let mut finding = |rule_id: &str, severity, title: String, description: &str| {
+        findings.push(SecurityFinding {
+            rule_id: rule_id.to_string(),
+            severity,
+            title,
+            description: description.to_string(),
+            path: None,
+            layer_id: None,
+        });
+    };
+    match outcome {
+        StartupOutcome::Exited => finding(
+            "container-exits-on-start",
+            Severity::High,
+            format!(
+                "Container exited right after starting (exit code {})",
+                exit_code.unwrap_or_default()
+            ),
+            "The image passes static checks but its default command stops on its own. Check the logs for the crash.",
+        ),
+        StartupOutcome::Unhealthy => finding(
+            "healthcheck-failing",
+            Severity::High,
+            "The image's HEALTHCHECK reports unhealthy".to_string(),
+            "The container starts but its own healthcheck fails with no network access.",
+        ),
+        StartupOutcome::TimedOut => finding(
+            "healthcheck-never-healthy",
+            Severity::Medium,
+            "The image's HEALTHCHECK never passed".to_string(),
+            "The container was still starting when the check gave up.",
+        ),
+        StartupOutcome::Running | StartupOutcome::Healthy => {}
+    }
+    if outcome != StartupOutcome::Exited {
+        for port in ports.iter().filter(|port| port.listening == Some(false)) {
+            finding(
+                "exposed-port-not-listening",
+                Severity::Medium,
+                format!(
+                    "Nothing listens on EXPOSE'd port {}/{}",
+                    port.port, port.protocol
+                ),
+                "The port is declared in the image but no process in the container listens on it.",
+            );
+        }
+    }
+    findings
+}
+
+// How a container settled, see wait_for_startup
+struct Startup {
+    outcome: StartupOutcome,
+    state: ContainerState,
+    listening: Option<Vec<u16>>,
+    ready: Option<Duration>,
+}
+
+// Polls the container until it settles: exited, healthy or unhealthy with a
+// healthcheck, listening on every exposed port or past the grace period
+// without one
+fn wait_for_startup(
+    task: &Task,
+    container_name: &str,
+    ports: &[(u16, String)],
+    has_healthcheck: bool,
+    timeout: Duration,
+) -> Result<Startup, String> {
+    let started = Instant::now();
+    let settled = |outcome, state, listening, ready: bool| {
+        Ok(Startup {
+            outcome,
+            state,
+            listening,
+            ready: ready.then(|| started.elapsed()),
+        })
+    };
+    let tcp_ports: Vec<u16> = ports
+        .iter()
+        .filter(|(_, protocol)| protocol == "tcp")
+        .map(|(port, _)| *port)
+        .collect();
+    loop {
+        task.check_cancelled()?;
+        let state = container_state(task, container_name)?;
+        if !state.running {
+            return settled(StartupOutcome::Exited, state, None, false);
+        }
+        let listening = listening_ports(task, container_name);
+        let all_listening = listening
+            .as_ref()
+            .is_some_and(|listening| tcp_ports.iter().all(|port| listening.contains(port)));
+        let elapsed = started.elapsed();
+
+        if has_healthcheck {
+            match state.health.as_deref() {
+                Some("healthy") => return settled(StartupOutcome::Healthy, state, listening, true),
+                Some("unhealthy") => {
+                    return settled(StartupOutcome::Unhealthy, state, listening, false)
+                }
+                _ if elapsed >= timeout => {
+                    return settled(StartupOutcome::TimedOut, state, listening, false)
+                }
+                _ => {}
+            }
+        } else if !tcp_ports.is_empty() && all_listening {
+            return settled(StartupOutcome::Running, state, listening, true);
+        } else if (tcp_ports.is_empty() && elapsed >= GRACE_PERIOD) || elapsed >= timeout {
+            return settled(StartupOutcome::Running, state, listening, false);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// The first lines the container printed, stdout and stderr merged back in
+// order by their timestamps
+fn startup_logs(task: &Task, container_name: &str) -> (Vec<String>, bool) {
+    let Ok(output) = task.run(docker_command().args(["logs", "--timestamps", container_name]))
+    else {
+        return (Vec::new(), false);
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut lines: Vec<&str> = stdout.lines().chain(stderr.lines()).collect();
+    lines.sort_by_key(|line| line.split(' ').next().unwrap_or_default());
+    let truncated = lines.len() > LOG_LINES;
+    (
+        lines
+            .into_iter()
+            .take(LOG_LINES)
+            .map(str::to_string)
+            .collect(),
+        truncated,
+    )
+}
+
+// Result of the last check of an image session, for reports
+pub(crate) fn saved_startup_check(session_dir: &Path) -> Option<StartupCheck> {
+    fs::read(session_dir.join(SAVED_CHECK_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+}
+
+const STARTUP_CHECK_PHASES: &[Phase] = &[
+    phase("prepare", 0.1),
+    phase("start", 0.1),
+    phase("observe", 0.7),
+    phase("cleanup", 0.1),
+];
+
+fn startup_check_task(
+    window: &tauri::Window,
+    task: &Task,
+    image_id: &str,
+    timeout: Duration,
+) -> Result<StartupCheck, String> {
+    let progress = PhaseProgress::new(window, task.id, STARTUP_CHECK_PHASES);
+
+    let config = inspect_image(image_id)?["Config"].clone();
+    let ports = exposed_ports(&config);
+    let has_healthcheck = config["Healthcheck"]["Test"]
+        .as_array()
+        .and_then(|test| test.first())
+        .and_then(|kind| kind.as_str())
+        .is_some_and(|kind| kind != "NONE");
+
+    let name = format!("layers_startup_{}", task.id);
+    // Internal networks have no route out, whatever the image tries to reach
+    progress.begin("prepare", "Creating isolated network");
+    docker_output(task, &["network", "create", "--internal", &name])?;
+
+    let result = (|| {
+        progress.begin("start", "Starting container");
+        let _ = audit::docker(&["rm", "-f", &name]);
+        task.track_container(&name);
+        let mut args = vec!["run", "--detach", "--name", &name, "--network", &name];
+        args.extend(exec_safety::RUN_LIMITS);
+        args.push(image_id);
+        docker_output(task, &args)?;
+
+        progress.begin("observe", "Waiting for the container to start");
+        let started = Instant::now();
+        let startup = wait_for_startup(task, &name, &ports, has_healthcheck, timeout)?;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let (logs, logs_truncated) = startup_logs(task, &name);
+
+        let ports = port_checks(&ports, startup.listening.as_deref());
+        let state = startup.state;
+        Ok(StartupCheck {
+            image_id: image_id.to_string(),
+            outcome: startup.outcome,
+            command: state.command,
+            exit_code: state.exit_code,
+            health: state.health,
+            ready_ms: startup.ready.map(|ready| ready.as_millis() as u64),
+            duration_ms,
+            findings: startup_findings(startup.outcome, state.exit_code, &ports),
+            ports,
+            logs,
+            logs_truncated,
+        })
+    })();
+
+    progress.begin("cleanup", "Removing container");
+    let _ = audit::docker(&["rm", "-f", &name]);
+    let _ = audit::docker(&["network", "rm", &name]);
+    result
+}
+
+// Optional dynamic check of the selected image: once confirmed, runs it with
+// no network access, no capabilities and resource limits, and records whether
+// it stays up, passes its healthcheck and listens on the ports it EXPOSEs
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn check_image_startup(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    options: Option<StartupCheckOptions>,
+) -> Result<StartupCheck, LayersError> {
+    let image_session = session.get(session_id.as_deref())?;
+    let image_id = image_session.image_id().to_string();
+    let timeout = options
+        .unwrap_or_default()
+        .timeout_seconds
+        .unwrap_or(DEFAULT_TIMEOUT_SECONDS)
+        .clamp(1, MAX_TIMEOUT_SECONDS);
+    info!("Checking that {} starts", image_id);
+    exec_safety::confirm_execution(
+        &window,
+        ExecutionKind::RunImage,
+        &format!("Start {}", image_session.reference()),
+    )?;
+
+    let task = tasks.start();
+    let result = startup_check_task(&window, &task, &image_id, Duration::from_secs(timeout));
+    finish_task(&window, &tasks, &task);
+    let check = result?;
+
+    // The report shows the trace even when it's exported much later
+    let saved = serde_json::to_vec_pretty(&check)
+        .map_err(|e| e.to_string())
+        .and_then(|content| {
+            fs::write(image_session.dir().join(SAVED_CHECK_FILE), content)
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = saved {
+        warn!("Failed to save startup check: {}", e);
+    }
+    Ok(check)
+}
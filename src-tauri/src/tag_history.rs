@@ -0,0 +1,262 @@
+use layers_core::docker::{docker_command, image_diff_ids};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::pull::split_reference;
+
+// Comparison presets read the history without Tauri state, so the file
+// location is set once at startup
+static HISTORY_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+// Keeps concurrent checks from dropping each other's observations
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TagObservation {
+    image_id: String,
+    repo_digest: Option<String>,
+    first_seen: u64,
+    last_seen: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagMutationCheck {
+    reference: String,
+    current: TagObservation,
+    previous: Option<TagObservation>,
+    moved: bool,
+    // Whether the previously seen image still exists locally so it can be diffed
+    previous_available: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagVersionDiff {
+    reference: String,
+    old_image_id: String,
+    new_image_id: String,
+    shared_layers: Vec<String>,
+    removed_layers: Vec<String>,
+    added_layers: Vec<String>,
+}
+
+// Every digest seen for every tag, oldest observation first
+type TagHistory = HashMap<String, Vec<TagObservation>>;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn init(path: PathBuf) {
+    info!("Reading tag history from {:?}", path);
+    *HISTORY_PATH.write().unwrap() = Some(path);
+}
+
+fn history_path() -> Result<PathBuf, String> {
+    HISTORY_PATH
+        .read()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| "Tag history is not available".to_string())
+}
+
+fn load_history(path: &Path) -> Result<TagHistory, String> {
+    if !path.exists() {
+        return Ok(TagHistory::new());
+    }
+
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read tag history: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse tag history: {}", e))
+}
+
+fn save_history(path: &Path, history: &TagHistory) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+    }
+    let content = serde_json::to_string_pretty(history)
+        .map_err(|e| format!("Failed to serialize tag history: {}", e))?;
+    audit::write_atomically(path, content)
+        .map_err(|e| format!("Failed to write tag history: {}", e))
+}
+
+fn image_exists(image: &str) -> bool {
+    docker_command()
+        .args(["image", "inspect", image, "--format", "{{.Id}}"])
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn resolve_tag(reference: &str) -> Result<(String, Option<String>), String> {
+    let output = docker_command()
+        .args([
+            "image",
+            "inspect",
+            reference,
+            "--format",
+            "{{.Id}}|{{json .RepoDigests}}",
+        ])
+        .output()
+        .map_err(|e| format!("Failed to inspect image {}: {}", reference, e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to inspect image {}: {}",
+            reference,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (image_id, repo_digests) = stdout
+        .trim()
+        .split_once('|')
+        .ok_or_else(|| format!("Unexpected inspect output for {}", reference))?;
+
+    // Prefer the digest from the same repository as the tag
+    let (repository, _) = split_reference(reference);
+    let repo_digests: Vec<String> = serde_json::from_str(repo_digests).unwrap_or_default();
+    let repo_digest = repo_digests
+        .iter()
+        .find(|d| d.starts_with(&format!("{}@", repository)))
+        .or_else(|| repo_digests.first())
+        .cloned();
+
+    Ok((image_id.to_string(), repo_digest))
+}
+
+// Latest image the tag pointed to before `image_id` that still exists locally
+pub(crate) fn previous_local_image(
+    reference: &str,
+    image_id: &str,
+) -> Result<Option<String>, String> {
+    let history = load_history(&history_path()?)?;
+    Ok(history
+        .get(reference)
+        .into_iter()
+        .flatten()
+        .rev()
+        .map(|observation| &observation.image_id)
+        .find(|id| *id != image_id && image_exists(id))
+        .cloned())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn check_tag_mutation(reference: String) -> Result<TagMutationCheck, LayersError> {
+    info!("Checking tag {} for mutation", reference);
+
+    let (image_id, repo_digest) = resolve_tag(&reference)?;
+    let guard = WRITE_LOCK.lock().unwrap();
+    let path = history_path()?;
+    let mut history = load_history(&path)?;
+    let observations = history.entry(reference.clone()).or_default();
+
+    let previous = observations.last().cloned();
+    let moved = previous
+        .as_ref()
+        .map(|p| p.image_id != image_id)
+        .unwrap_or(false);
+
+    let timestamp = now();
+    match observations.last_mut() {
+        Some(last) if !moved => last.last_seen = timestamp,
+        _ => observations.push(TagObservation {
+            image_id: image_id.clone(),
+            repo_digest: repo_digest.clone(),
+            first_seen: timestamp,
+            last_seen: timestamp,
+        }),
+    }
+    let current = observations.last().cloned().unwrap();
+
+    save_history(&path, &history)?;
+    drop(guard);
+
+    if moved {
+        info!(
+            "Tag {} moved from {} to {}",
+            reference,
+            previous.as_ref().map(|p| p.image_id.as_str()).unwrap_or(""),
+            image_id
+        );
+    }
+
+    let previous_available = moved
+        && previous
+            .as_ref()
+            .map(|p| image_exists(&p.image_id))
+            .unwrap_or(false);
+
+    Ok(TagMutationCheck {
+        reference,
+        current,
+        // Only report the previous observation when it differs from the current one
+        previous: if moved { previous } else { None },
+        moved,
+        previous_available,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_tag_history(reference: String) -> Result<Vec<TagObservation>, LayersError> {
+    Ok(load_history(&history_path()?)?
+        .remove(&reference)
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn diff_tag_versions(reference: String) -> Result<TagVersionDiff, LayersError> {
+    let history = load_history(&history_path()?)?;
+    let observations = history
+        .get(&reference)
+        .filter(|o| o.len() >= 2)
+        .ok_or_else(|| format!("Tag {} has not moved since it was first seen", reference))?;
+
+    let old = &observations[observations.len() - 2];
+    let new = &observations[observations.len() - 1];
+
+    if !image_exists(&old.image_id) {
+        return Err(format!(
+            "Previous image {} for {} is no longer available locally",
+            old.image_id, reference
+        )
+        .into());
+    }
+
+    let old_layers = image_diff_ids(&old.image_id)?;
+    let new_layers = image_diff_ids(&new.image_id)?;
+
+    Ok(TagVersionDiff {
+        reference,
+        old_image_id: old.image_id.clone(),
+        new_image_id: new.image_id.clone(),
+        shared_layers: new_layers
+            .iter()
+            .filter(|l| old_layers.contains(l))
+            .cloned()
+            .collect(),
+        removed_layers: old_layers
+            .iter()
+            .filter(|l| !new_layers.contains(l))
+            .cloned()
+            .collect(),
+        added_layers: new_layers
+            .iter()
+            .filter(|l| !old_layers.contains(l))
+            .cloned()
+            .collect(),
+    })
+}
@@ -0,0 +1,44 @@
+use layers_core::layer_tar::{display_path, unpack_entries};
+use std::cell::RefCell;
+use std::fs::File;
+use std::path::Path;
+use tracing::warn;
+
+use crate::tasks::{ProgressReader, Task};
+use crate::transfer::Transfer;
+
+pub(crate) use layers_core::layer_tar::{container_relative, Extraction};
+
+// layer_tar::unpack_entries until the task is cancelled, `report` gets the bytes read
+pub(crate) fn extract_entries(
+    task: &Task,
+    tar_path: &Path,
+    destination: &Path,
+    preserve_permissions: bool,
+    keep: impl Fn(&Path) -> bool,
+    report: Option<&dyn Fn(&Transfer)>,
+) -> Result<Extraction, String> {
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let transfer = RefCell::new(Transfer::new(file.metadata().ok().map(|m| m.len())));
+    let file = ProgressReader::new(file, |bytes| {
+        let mut transfer = transfer.borrow_mut();
+        transfer.advance(bytes);
+        if let Some(report) = report.filter(|_| transfer.due()) {
+            report(&transfer);
+        }
+    });
+
+    let extraction = unpack_entries(file, destination, preserve_permissions, |path| {
+        task.check_cancelled()?;
+        if !keep(path) {
+            return Ok(false);
+        }
+        transfer.borrow_mut().set_item(display_path(path));
+        Ok(true)
+    })?;
+    for (path, error) in &extraction.skipped {
+        warn!("Skipped {}: {}", path, error);
+    }
+    Ok(extraction)
+}
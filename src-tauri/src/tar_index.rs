@@ -0,0 +1,232 @@
+use layers_core::layer_tar::entry_relative_path;
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, info, warn};
+
+use crate::error::LayersError;
+use crate::layer_tar_path;
+use crate::session::SessionState;
+use crate::tasks::{Task, TaskRegistry};
+
+pub(crate) const DEFAULT_RANGE_LENGTH: u64 = 64 * 1024;
+pub(crate) const MAX_RANGE_LENGTH: u64 = 4 * 1024 * 1024;
+// Hard links can point at other hard links, but never in long chains
+const MAX_LINK_HOPS: usize = 8;
+
+// Where a file's data starts in the uncompressed tar stream
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct IndexedEntry {
+    pub offset: u64,
+    pub size: u64,
+    // Hard links have no data of their own
+    pub link_target: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct TarIndex {
+    // Identify the tar the index was built from, a re-export invalidates it
+    tar_size: u64,
+    tar_modified: u64,
+    entries: HashMap<String, IndexedEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileRange {
+    pub(crate) path: String,
+    pub(crate) offset: u64,
+    // Bytes actually returned, less than requested at the end of the file
+    pub(crate) length: u64,
+    pub(crate) total_size: u64,
+    pub(crate) eof: bool,
+    pub(crate) is_binary: bool,
+    // Decoded lossily, a range can start or end inside a UTF-8 sequence
+    pub(crate) content: String,
+}
+
+fn index_path(tar_path: &Path) -> PathBuf {
+    tar_path.with_extension("index.json")
+}
+
+fn tar_identity(tar_path: &Path) -> Result<(u64, u64), String> {
+    let metadata = fs::metadata(tar_path)
+        .map_err(|e| format!("Failed to read metadata of {:?}: {}", tar_path, e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok((metadata.len(), modified))
+}
+
+fn build_index(task: &Task, tar_path: &Path) -> Result<TarIndex, String> {
+    let (tar_size, tar_modified) = tar_identity(tar_path)?;
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let entries = index_entries(task, tar_path, file)?;
+
+    Ok(TarIndex {
+        tar_size,
+        tar_modified,
+        entries,
+    })
+}
+
+fn index_entries<R: Read>(
+    task: &Task,
+    tar_path: &Path,
+    reader: R,
+) -> Result<HashMap<String, IndexedEntry>, String> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = HashMap::new();
+
+    let tar_entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for entry in tar_entries {
+        task.check_cancelled()?;
+        let entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let entry_type = entry.header().entry_type();
+        let path = entry_relative_path(
+            &entry
+                .path()
+                .map_err(|e| format!("Failed to read tar entry path: {}", e))?,
+        );
+
+        if entry_type.is_hard_link() {
+            let target = entry
+                .link_name()
+                .ok()
+                .flatten()
+                .map(|t| entry_relative_path(&t));
+            entries.insert(
+                path,
+                IndexedEntry {
+                    offset: 0,
+                    size: 0,
+                    link_target: target,
+                },
+            );
+        } else if entry_type.is_file() && !entry_type.is_gnu_sparse() {
+            // Sparse files aren't stored contiguously and can't be read by offset
+            entries.insert(
+                path,
+                IndexedEntry {
+                    offset: entry.raw_file_position(),
+                    size: entry.size(),
+                    link_target: None,
+                },
+            );
+        }
+    }
+    Ok(entries)
+}
+
+// Load the index saved next to the tar, building it on first use
+pub(crate) fn load_or_build_index(task: &Task, tar_path: &Path) -> Result<TarIndex, String> {
+    let index_path = index_path(tar_path);
+    let identity = tar_identity(tar_path)?;
+    if let Some(index) = fs::read(&index_path)
+        .ok()
+        .and_then(|content| serde_json::from_slice::<TarIndex>(&content).ok())
+        .filter(|index| (index.tar_size, index.tar_modified) == identity)
+    {
+        return Ok(index);
+    }
+
+    info!("Building offset index for {:?}", tar_path);
+    let index = build_index(task, tar_path)?;
+    match serde_json::to_vec(&index) {
+        Ok(content) => {
+            // A missing index only costs a rebuild next time
+            if let Err(e) = fs::write(&index_path, content) {
+                warn!("Failed to save index {:?}: {}", index_path, e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize index: {}", e),
+    }
+    Ok(index)
+}
+
+impl TarIndex {
+    // Look up a file by container path, following hard links to their data
+    pub(crate) fn lookup(&self, path: &str) -> Option<&IndexedEntry> {
+        let mut entry = self.entries.get(path.trim_start_matches('/'))?;
+        for _ in 0..MAX_LINK_HOPS {
+            match &entry.link_target {
+                Some(target) => entry = self.entries.get(target)?,
+                None => return Some(entry),
+            }
+        }
+        None
+    }
+}
+
+// Read `length` bytes from `offset` within an indexed file without going
+// through the rest of the tar
+pub(crate) fn read_range(
+    tar_path: &Path,
+    entry: &IndexedEntry,
+    offset: u64,
+    length: u64,
+) -> Result<Vec<u8>, String> {
+    let start = entry.offset + offset.min(entry.size);
+    let end = entry.offset + (offset.saturating_add(length)).min(entry.size);
+
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    // Safety: layer tars are only ever replaced by writing a new file, never
+    // truncated in place, so the mapping stays valid while we read from it
+    let map = unsafe { Mmap::map(&file) }
+        .map_err(|e| format!("Failed to map tar file {:?}: {}", tar_path, e))?;
+    map.get(start as usize..end as usize)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| format!("Index for {:?} is out of date", tar_path))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn read_layer_file_range(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+    path: String,
+    offset: Option<u64>,
+    length: Option<u64>,
+) -> Result<FileRange, LayersError> {
+    let offset = offset.unwrap_or(0);
+    let length = length.unwrap_or(DEFAULT_RANGE_LENGTH).min(MAX_RANGE_LENGTH);
+    debug!(
+        "Reading {} bytes at {} of {} in layer {}",
+        length, offset, path, layer_id
+    );
+
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let index = layer_tar_path(&task, &session, &layer_id)
+        .and_then(|tar_path| Ok((load_or_build_index(&task, &tar_path)?, tar_path)));
+    tasks.finish(task.id);
+    let (index, tar_path) = index?;
+
+    let entry = index
+        .lookup(&path)
+        .ok_or_else(|| format!("File not found in layer {}: {}", layer_id, path))?;
+    let bytes = read_range(&tar_path, entry, offset, length)?;
+
+    let length = bytes.len() as u64;
+    Ok(FileRange {
+        path,
+        offset,
+        length,
+        total_size: entry.size,
+        eof: offset + length >= entry.size,
+        is_binary: bytes.iter().take(8000).any(|b| *b == 0),
+        content: String::from_utf8_lossy(&bytes).to_string(),
+    })
+}
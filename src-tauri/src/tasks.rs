@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::exec_safety;
+use crate::permissions;
+use crate::resources::low_priority_command;
+
+// How often a running child process is checked for cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Registry of running long-running tasks, managed as Tauri state
+#[derive(Default)]
+pub struct TaskRegistry {
+    next_id: AtomicU64,
+    tasks: Mutex<HashMap<u64, Arc<Task>>>,
+}
+
+// A single cancellable task and the partial artifacts it has produced so far
+#[derive(Default)]
+pub struct Task {
+    pub id: u64,
+    cancelled: AtomicBool,
+    cleanup_paths: Mutex<Vec<PathBuf>>,
+    containers: Mutex<Vec<String>>,
+}
+
+impl TaskRegistry {
+    pub fn start(&self) -> Arc<Task> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let task = Arc::new(Task {
+            id,
+            ..Default::default()
+        });
+
+        self.tasks.lock().unwrap().insert(id, task.clone());
+        debug!("Started task {}", id);
+        task
+    }
+
+    pub fn finish(&self, id: u64) {
+        self.tasks.lock().unwrap().remove(&id);
+        debug!("Finished task {}", id);
+    }
+
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.tasks.lock().unwrap().get(&id) {
+            Some(task) => {
+                task.cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Task {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn check_cancelled(&self) -> Result<(), LayersError> {
+        if self.is_cancelled() {
+            Err(self.cancelled())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn cancelled(&self) -> LayersError {
+        LayersError::Cancelled {
+            message: format!("Task {} was cancelled", self.id),
+        }
+    }
+
+    // Error a task's command returns. A cancelled task reports that, whatever
+    // the helper that stopped on it returned.
+    pub fn error(&self, error: impl Into<LayersError>) -> LayersError {
+        if self.is_cancelled() {
+            self.cancelled()
+        } else {
+            error.into()
+        }
+    }
+
+    // Remember a file or directory that should be removed if the task is cancelled
+    pub fn track_path(&self, path: impl Into<PathBuf>) {
+        self.cleanup_paths.lock().unwrap().push(path.into());
+    }
+
+    // Remember a container that should be removed if the task is cancelled
+    pub fn track_container(&self, name: &str) {
+        self.containers.lock().unwrap().push(name.to_string());
+    }
+
+    // Remove everything the task produced before it was cancelled
+    pub fn cleanup(&self) {
+        for container in self.containers.lock().unwrap().drain(..) {
+            info!("Removing container {} from cancelled task", container);
+            let _ = audit::docker(&["rm", "-f", &container]);
+        }
+
+        for path in self.cleanup_paths.lock().unwrap().drain(..) {
+            info!("Removing {:?} from cancelled task", path);
+            let _ = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+        }
+    }
+
+    // Drop-in replacement for `Command::output` that kills the child process
+    // as soon as the task is cancelled
+    pub fn run(&self, command: &mut Command) -> Result<Output, LayersError> {
+        let result = self.run_child(command);
+        audit::record_command(command, &result);
+        result.map_err(|e| self.command_error(command, e))
+    }
+
+    // `run` for output too large for memory, `read` consumes stdout as it streams
+    pub fn stream<T>(
+        &self,
+        command: &mut Command,
+        read: impl FnOnce(&mut dyn Read) -> Result<T, String>,
+    ) -> Result<T, LayersError> {
+        let mut read_result = None;
+        let result = self.stream_child(command, |stdout| {
+            let result = read(stdout);
+            let ok = result.is_ok();
+            read_result = Some(result);
+            ok
+        });
+        audit::record_command(command, &result);
+        let output = result.map_err(|e| self.command_error(command, e))?;
+        let read_result = read_result.unwrap_or_else(|| Err("No output was read".to_string()));
+
+        // The child says nothing when it was only killed after a failed read
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if !output.status.success() && (read_result.is_ok() || !stderr.is_empty()) {
+            return Err(if stderr.is_empty() {
+                format!("{:?} exited with {}", command.get_program(), output.status).into()
+            } else {
+                stderr.into()
+            });
+        }
+        Ok(read_result?)
+    }
+
+    fn command_error(&self, command: &Command, e: io::Error) -> LayersError {
+        if self.is_cancelled() {
+            self.cancelled()
+        } else if command.get_program() == "docker" && e.kind() == io::ErrorKind::NotFound {
+            LayersError::DaemonUnavailable {
+                message: format!("Docker CLI not found: {}", e),
+            }
+        } else {
+            e.to_string().into()
+        }
+    }
+
+    // Gates and throttling every child of a task goes through
+    fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+        if self.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "task cancelled"));
+        }
+        exec_safety::check_command(command)?;
+        permissions::check_command(command)?;
+
+        // Throttled by the resource settings
+        let mut throttled = low_priority_command(command);
+        let command = throttled.as_mut().unwrap_or(command);
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+    }
+
+    // Hands stdout to `read`, which returns whether it read all it needed.
+    // stdout is empty in the output, stderr is drained alongside.
+    fn stream_child(
+        &self,
+        command: &mut Command,
+        read: impl FnOnce(&mut dyn Read) -> bool,
+    ) -> io::Result<Output> {
+        let mut child = self.spawn(command)?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| io::Error::other("Failed to capture output"))?;
+        let mut stderr = child.stderr.take();
+        let stderr_reader = thread::spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(err) = stderr.as_mut() {
+                let _ = err.read_to_end(&mut buffer);
+            }
+            buffer
+        });
+
+        let child = Mutex::new(child);
+        let done = AtomicBool::new(false);
+        let read_all = thread::scope(|scope| {
+            // Kills the child on cancellation even while `read` waits on it
+            scope.spawn(|| {
+                while !done.load(Ordering::SeqCst) {
+                    if self.is_cancelled() {
+                        info!("Killing child process of cancelled task {}", self.id);
+                        let _ = child.lock().unwrap().kill();
+                        return;
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+            });
+            let read_all = read(&mut stdout);
+            done.store(true, Ordering::SeqCst);
+            read_all
+        });
+
+        let mut child = child.into_inner().unwrap();
+        if !read_all {
+            // Nobody reads the rest of the output, the child would block on it
+            let _ = child.kill();
+        }
+        drop(stdout);
+        let status = child.wait()?;
+        let stderr = stderr_reader.join().unwrap_or_default();
+        if self.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "task cancelled"));
+        }
+        Ok(Output {
+            status,
+            stdout: Vec::new(),
+            stderr,
+        })
+    }
+
+    fn run_child(&self, command: &mut Command) -> io::Result<Output> {
+        let mut child = self.spawn(command)?;
+
+        // Drain the pipes on separate threads so a chatty child can't block on a full pipe
+        let mut stdout = child.stdout.take();
+        let mut stderr = child.stderr.take();
+        let stdout_reader = thread::spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(out) = stdout.as_mut() {
+                let _ = out.read_to_end(&mut buffer);
+            }
+            buffer
+        });
+        let stderr_reader = thread::spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(err) = stderr.as_mut() {
+                let _ = err.read_to_end(&mut buffer);
+            }
+            buffer
+        });
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+
+            if self.is_cancelled() {
+                info!("Killing child process of cancelled task {}", self.id);
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "task cancelled"));
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        };
+
+        Ok(Output {
+            status,
+            stdout: stdout_reader.join().unwrap_or_default(),
+            stderr: stderr_reader.join().unwrap_or_default(),
+        })
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn cancel_task(
+    tasks: tauri::State<'_, TaskRegistry>,
+    task_id: u64,
+) -> Result<(), LayersError> {
+    info!("Cancelling task {}", task_id);
+
+    if tasks.cancel(task_id) {
+        Ok(())
+    } else {
+        Err(format!("No running task with ID {}", task_id).into())
+    }
+}
+
+// Passes reads through and reports how many bytes each one returned, so
+// streaming work can report progress
+pub(crate) struct ProgressReader<R, F> {
+    inner: R,
+    on_read: F,
+}
+
+impl<R: Read, F: FnMut(u64)> ProgressReader<R, F> {
+    pub(crate) fn new(inner: R, on_read: F) -> Self {
+        ProgressReader { inner, on_read }
+    }
+}
+
+impl<R: Read, F: FnMut(u64)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        (self.on_read)(n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::scratch_dir;
+
+    #[test]
+    fn progress_reader_reports_every_read() {
+        let data = vec![7u8; 10_000];
+        let mut total = 0;
+        let mut reads = 0;
+        let mut copied = Vec::new();
+        ProgressReader::new(data.as_slice(), |bytes| {
+            total += bytes;
+            reads += 1;
+        })
+        .read_to_end(&mut copied)
+        .unwrap();
+
+        assert_eq!(copied, data);
+        assert_eq!(total, data.len() as u64);
+        assert!(reads > 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_returns_the_output() {
+        let task = TaskRegistry::default().start();
+        let output = task
+            .run(Command::new("sh").args(["-c", "echo out; echo err >&2"]))
+            .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn cancel_kills_and_reaps_the_child() {
+        let dir = scratch_dir("task-cancel");
+        let pid_file = dir.join("pid");
+        let registry = Arc::new(TaskRegistry::default());
+        let task = registry.start();
+
+        let canceller = {
+            let registry = registry.clone();
+            let pid_file = pid_file.clone();
+            let id = task.id;
+            thread::spawn(move || {
+                while !pid_file.exists() {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                registry.cancel(id);
+            })
+        };
+        let script = format!("echo $$ > {}; exec sleep 30", pid_file.display());
+        let started = std::time::Instant::now();
+        let result = task.run(Command::new("sh").args(["-c", &script]));
+        canceller.join().unwrap();
+
+        assert!(matches!(result, Err(LayersError::Cancelled { .. })));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        // A killed child that wasn't waited for would linger as a zombie
+        let pid = fs::read_to_string(&pid_file).unwrap();
+        assert!(!PathBuf::from(format!("/proc/{}", pid.trim())).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn run_refuses_to_start_after_cancel() {
+        let registry = TaskRegistry::default();
+        let task = registry.start();
+        registry.cancel(task.id);
+
+        let result = task.run(Command::new("sh").args(["-c", "exit 0"]));
+        assert!(matches!(result, Err(LayersError::Cancelled { .. })));
+    }
+
+    #[test]
+    fn cleanup_removes_tracked_paths() {
+        let dir = scratch_dir("task-cleanup");
+        let file = dir.join("partial.tar");
+        let extracted = dir.join("extracted");
+        fs::write(&file, b"partial").unwrap();
+        fs::create_dir_all(extracted.join("etc")).unwrap();
+        fs::write(extracted.join("etc/hostname"), b"box").unwrap();
+
+        let task = TaskRegistry::default().start();
+        task.track_path(&file);
+        task.track_path(&extracted);
+        task.cleanup();
+
+        assert!(!file.exists());
+        assert!(!extracted.exists());
+        assert!(dir.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_reads_stdout_and_reports_failed_exits() {
+        let task = TaskRegistry::default().start();
+        let read = task
+            .stream(Command::new("sh").args(["-c", "echo out"]), |stdout| {
+                let mut out = String::new();
+                stdout.read_to_string(&mut out).map_err(|e| e.to_string())?;
+                Ok(out)
+            })
+            .unwrap();
+        assert_eq!(read, "out\n");
+
+        let failed = task.stream(
+            Command::new("sh").args(["-c", "echo 'no such image' >&2; exit 1"]),
+            |stdout| {
+                let mut out = Vec::new();
+                stdout.read_to_end(&mut out).map_err(|e| e.to_string())
+            },
+        );
+        assert_eq!(failed.unwrap_err().message(), "no such image");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn stream_kills_the_child_when_the_read_fails() {
+        let task = TaskRegistry::default().start();
+        let started = std::time::Instant::now();
+        let result: Result<(), _> = task.stream(
+            Command::new("sh").args(["-c", "echo start; exec sleep 30"]),
+            |_| Err("Bad output".to_string()),
+        );
+
+        assert_eq!(result.unwrap_err().message(), "Bad output");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
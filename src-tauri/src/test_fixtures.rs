@@ -0,0 +1,49 @@
+// Layer tars for the unit tests
+use layers_core::layer_tar::path_from_bytes;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Names a tar can hold that a plain String path mangles: bytes that aren't
+// UTF-8, control characters, backslashes and a name near the length limit
+pub(crate) fn exotic_names() -> Vec<Vec<u8>> {
+    vec![
+        b"data/latin1-\xe9t\xe9.txt".to_vec(),
+        b"data/two\nlines.txt".to_vec(),
+        b"data/tab\tand\rreturn.txt".to_vec(),
+        b"data/back\\slash.txt".to_vec(),
+        "data/next\u{85}line.txt".as_bytes().to_vec(),
+        format!("data/{}.txt", "long".repeat(60)).into_bytes(),
+    ]
+}
+
+// Empty directory for one test, under the system temp directory
+pub(crate) fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("layers-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// A "data" directory with one file per exotic name, each holding its own name
+pub(crate) fn write_exotic_tar(path: &Path) {
+    let mut builder = tar::Builder::new(File::create(path).unwrap());
+
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Directory);
+    header.set_mode(0o755);
+    header.set_size(0);
+    builder
+        .append_data(&mut header, "data", io::empty())
+        .unwrap();
+
+    for name in exotic_names() {
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(name.len() as u64);
+        builder
+            .append_data(&mut header, path_from_bytes(&name), name.as_slice())
+            .unwrap();
+    }
+    builder.finish().unwrap();
+}
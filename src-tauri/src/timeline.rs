@@ -0,0 +1,187 @@
+use layers_core::docker::{docker_command, parse_docker_size};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::health::days_since_epoch;
+use crate::session::SessionState;
+
+// Steps further apart than this come from different builds, e.g. the base
+// image and the image built on it, so the gap isn't time spent on the step
+const BUILD_GAP_SECONDS: i64 = 60 * 60;
+// A step taking at least this share of the build time or size dominates it
+const DOMINANT_SHARE: f64 = 0.25;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimelineStep {
+    // <missing> for steps of pulled images
+    id: String,
+    created_by: String,
+    created: String,
+    // Unix seconds, None when docker has no timestamp for the step
+    created_at: Option<i64>,
+    // Seconds since the previous step finished, None for the first step and
+    // at build boundaries
+    delta_seconds: Option<i64>,
+    // First step of a new build, see BUILD_GAP_SECONDS
+    build_boundary: bool,
+    size_bytes: u64,
+    // Zero size steps like ENV or LABEL, nothing was written to a layer
+    cache_hit: bool,
+    time_share: f64,
+    size_share: f64,
+    dominant: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageTimeline {
+    image: String,
+    // Oldest first
+    steps: Vec<TimelineStep>,
+    // Sum of the deltas within builds
+    build_seconds: i64,
+    total_bytes: u64,
+    builds: usize,
+}
+
+// Unix seconds of an RFC 3339 timestamp like "2024-03-01T10:15:00+01:00"
+fn unix_seconds(timestamp: &str) -> Option<i64> {
+    let days = days_since_epoch(timestamp)? as i64;
+    let time = timestamp.get(11..19)?;
+    let mut parts = time.split(':').map(|part| part.parse::<i64>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+
+    // Fractional seconds come before the zone
+    let zone = timestamp[19..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = match zone.chars().next() {
+        Some(sign @ ('+' | '-')) => {
+            let hours = zone.get(1..3)?.parse::<i64>().ok()?;
+            let minutes = zone.get(4..6)?.parse::<i64>().ok()?;
+            let offset = hours * 3600 + minutes * 60;
+            if sign == '-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => 0,
+    };
+    Some(days * 86400 + hours * 3600 + minutes * 60 + seconds - offset)
+}
+
+// Steps of the image's history, oldest first
+fn read_history(image: &str) -> Result<Vec<(String, String, u64, String)>, String> {
+    // Without --human=false CreatedAt is "2 weeks ago"
+    let output = docker_command()
+        .args([
+            "history",
+            image,
+            "--no-trunc",
+            "--human=false",
+            "--format",
+            "{{.ID}}|{{.CreatedAt}}|{{.Size}}|{{.CreatedBy}}",
+        ])
+        .output()
+        .map_err(|e| format!("Failed to get image history: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to get image history: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .rev()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.splitn(4, '|').collect();
+            if parts.len() < 4 {
+                return None;
+            }
+            Some((
+                parts[0].to_string(),
+                parts[1].to_string(),
+                parse_docker_size(parts[2]),
+                parts[3].to_string(),
+            ))
+        })
+        .collect())
+}
+
+fn share(part: f64, total: f64) -> f64 {
+    if total > 0.0 {
+        part / total
+    } else {
+        0.0
+    }
+}
+
+// History of an image as a timeline of build steps, with the time each step
+// took and the size it added
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_image_timeline(
+    session: tauri::State<'_, SessionState>,
+    image: Option<String>,
+) -> Result<ImageTimeline, LayersError> {
+    let image = session.image_or_selected(image)?;
+    info!("Building timeline of {}", image);
+
+    let mut steps = Vec::new();
+    let mut previous: Option<i64> = None;
+    let mut builds = 0;
+    for (id, created, size_bytes, created_by) in read_history(&image)? {
+        let created_at = unix_seconds(&created);
+        // Steps taken from the build cache keep the time of the build that
+        // ran them, so a step can look older than the one before it
+        let delta = match (previous, created_at) {
+            (Some(previous), Some(created_at)) => Some((created_at - previous).max(0)),
+            _ => None,
+        };
+        let build_boundary = match delta {
+            Some(delta) => delta > BUILD_GAP_SECONDS,
+            None => created_at.is_some() && previous.is_none(),
+        };
+        if build_boundary {
+            builds += 1;
+        }
+        previous = created_at.or(previous);
+
+        steps.push(TimelineStep {
+            cache_hit: size_bytes == 0,
+            id,
+            created_by,
+            created,
+            created_at,
+            delta_seconds: delta.filter(|_| !build_boundary),
+            build_boundary,
+            size_bytes,
+            time_share: 0.0,
+            size_share: 0.0,
+            dominant: false,
+        });
+    }
+
+    let build_seconds: i64 = steps.iter().filter_map(|step| step.delta_seconds).sum();
+    let total_bytes: u64 = steps.iter().map(|step| step.size_bytes).sum();
+    for step in &mut steps {
+        step.time_share = share(step.delta_seconds.unwrap_or(0) as f64, build_seconds as f64);
+        step.size_share = share(step.size_bytes as f64, total_bytes as f64);
+        step.dominant = step.time_share >= DOMINANT_SHARE || step.size_share >= DOMINANT_SHARE;
+    }
+
+    info!(
+        "{} steps in {} builds, {}s of build time",
+        steps.len(),
+        builds,
+        build_seconds
+    );
+    Ok(ImageTimeline {
+        image,
+        steps,
+        build_seconds,
+        total_bytes,
+        builds,
+    })
+}
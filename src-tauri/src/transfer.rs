@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+use crate::TaskStatus;
+
+// Byte counts change on every read, events are sent at most this often
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+// The rate right after starting says little, no ETA before this
+const ETA_WARMUP: Duration = Duration::from_secs(1);
+
+// Sent with task_status by operations that know how many bytes they move
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransferStatus {
+    bytes_processed: u64,
+    // None while the size isn't known yet
+    bytes_total: Option<u64>,
+    // Layer, file or blob being worked on
+    current_item: Option<String>,
+    eta_seconds: Option<f64>,
+}
+
+// Counts the bytes of a transfer and works out an ETA from the average rate
+pub(crate) struct Transfer {
+    started: Instant,
+    last_emit: Option<Instant>,
+    processed: u64,
+    total: Option<u64>,
+    current_item: Option<String>,
+}
+
+impl Transfer {
+    pub(crate) fn new(total: Option<u64>) -> Self {
+        Transfer {
+            started: Instant::now(),
+            last_emit: None,
+            processed: 0,
+            total: total.filter(|total| *total > 0),
+            current_item: None,
+        }
+    }
+
+    pub(crate) fn advance(&mut self, bytes: u64) {
+        self.processed += bytes;
+    }
+
+    // For sources that report running totals, e.g. docker pull
+    pub(crate) fn set(&mut self, processed: u64, total: Option<u64>) {
+        self.processed = processed;
+        self.total = total.filter(|total| *total > 0);
+    }
+
+    pub(crate) fn set_item(&mut self, item: impl Into<String>) {
+        self.current_item = Some(item.into());
+    }
+
+    // 0.0 to 1.0, or None when the total isn't known
+    pub(crate) fn fraction(&self) -> Option<f32> {
+        self.total
+            .map(|total| (self.processed as f64 / total as f64).min(1.0) as f32)
+    }
+
+    // True when enough time passed since the last event, and marks it sent
+    pub(crate) fn due(&mut self) -> bool {
+        if self
+            .last_emit
+            .is_some_and(|last| last.elapsed() < EMIT_INTERVAL)
+        {
+            return false;
+        }
+        self.last_emit = Some(Instant::now());
+        true
+    }
+
+    fn eta_seconds(&self) -> Option<f64> {
+        let elapsed = self.started.elapsed();
+        let total = self.total?;
+        if elapsed < ETA_WARMUP || self.processed == 0 {
+            return None;
+        }
+        let rate = self.processed as f64 / elapsed.as_secs_f64();
+        Some(total.saturating_sub(self.processed) as f64 / rate)
+    }
+
+    pub(crate) fn status(&self) -> TransferStatus {
+        TransferStatus {
+            bytes_processed: self.processed,
+            bytes_total: self.total,
+            current_item: self.current_item.clone(),
+            eta_seconds: self.eta_seconds(),
+        }
+    }
+
+    // task_status for operations without phases, `progress` is the overall
+    // one the caller works out from fraction()
+    pub(crate) fn emit(&self, window: &tauri::Window, task_id: u64, message: &str, progress: f32) {
+        let _ = window.emit(
+            "task_status",
+            TaskStatus {
+                task_id,
+                message: message.to_string(),
+                progress,
+                is_complete: false,
+                error: None,
+                phase: None,
+                transfer: Some(self.status()),
+            },
+        );
+    }
+}
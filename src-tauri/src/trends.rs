@@ -0,0 +1,615 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::findings::Severity;
+use crate::reports::Report;
+
+// Bumped with every schema change, see migrate
+const SCHEMA_VERSION: i32 = 2;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS snapshots (
+        id INTEGER PRIMARY KEY,
+        image_id TEXT NOT NULL,
+        reference TEXT NOT NULL,
+        first_seen INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL,
+        total_bytes INTEGER NOT NULL,
+        wasted_bytes INTEGER NOT NULL,
+        efficiency_score REAL NOT NULL,
+        layer_count INTEGER NOT NULL,
+        health_score INTEGER,
+        critical INTEGER NOT NULL,
+        high INTEGER NOT NULL,
+        medium INTEGER NOT NULL,
+        low INTEGER NOT NULL,
+        info INTEGER NOT NULL,
+        UNIQUE (image_id, reference)
+    );
+    CREATE INDEX IF NOT EXISTS snapshots_reference ON snapshots (reference, first_seen);
+    CREATE TABLE IF NOT EXISTS snapshot_findings (
+        snapshot_id INTEGER NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+        anchor TEXT NOT NULL,
+        rule_id TEXT NOT NULL,
+        severity TEXT NOT NULL,
+        title TEXT NOT NULL,
+        PRIMARY KEY (snapshot_id, anchor)
+    );
+";
+
+// Version 2 records where a snapshot came from, imports from teammates
+// keep their own rows next to the local ones
+const SOURCE_MIGRATION: &str = "
+    PRAGMA foreign_keys = OFF;
+    BEGIN;
+    CREATE TABLE snapshots_new (
+        id INTEGER PRIMARY KEY,
+        source TEXT NOT NULL DEFAULT '',
+        image_id TEXT NOT NULL,
+        reference TEXT NOT NULL,
+        first_seen INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL,
+        total_bytes INTEGER NOT NULL,
+        wasted_bytes INTEGER NOT NULL,
+        efficiency_score REAL NOT NULL,
+        layer_count INTEGER NOT NULL,
+        health_score INTEGER,
+        critical INTEGER NOT NULL,
+        high INTEGER NOT NULL,
+        medium INTEGER NOT NULL,
+        low INTEGER NOT NULL,
+        info INTEGER NOT NULL,
+        UNIQUE (source, image_id, reference)
+    );
+    INSERT INTO snapshots_new (id, image_id, reference, first_seen, last_seen, total_bytes,
+        wasted_bytes, efficiency_score, layer_count, health_score, critical, high, medium, low, info)
+    SELECT id, image_id, reference, first_seen, last_seen, total_bytes, wasted_bytes,
+        efficiency_score, layer_count, health_score, critical, high, medium, low, info
+    FROM snapshots;
+    DROP TABLE snapshots;
+    ALTER TABLE snapshots_new RENAME TO snapshots;
+    CREATE INDEX snapshots_reference ON snapshots (reference, first_seen);
+    COMMIT;
+    PRAGMA foreign_keys = ON;
+";
+
+// Metrics of one image digest under one reference. Analyzing it again
+// updates the metrics and last_seen, first_seen orders the tag timeline.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrendSnapshot {
+    // Local row ID, not kept across export and import
+    #[serde(default)]
+    id: i64,
+    // Who recorded it, empty for this machine
+    #[serde(default)]
+    source: String,
+    image_id: String,
+    reference: String,
+    // Seconds since the epoch
+    first_seen: u64,
+    last_seen: u64,
+    total_bytes: u64,
+    wasted_bytes: u64,
+    efficiency_score: f64,
+    layer_count: usize,
+    // None until the health summary was computed for it
+    health_score: Option<u32>,
+    critical: usize,
+    high: usize,
+    medium: usize,
+    low: usize,
+    info: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrendFinding {
+    anchor: String,
+    rule_id: String,
+    severity: String,
+    title: String,
+}
+
+// A snapshot with its findings, as written by export_trends
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportedSnapshot {
+    #[serde(flatten)]
+    snapshot: TrendSnapshot,
+    findings: Vec<TrendFinding>,
+}
+
+// File format of export_trends, plain JSON so imports don't depend on the schema
+#[derive(Debug, Serialize, Deserialize)]
+struct TrendExport {
+    schema_version: i32,
+    exported_at: u64,
+    // Name the snapshots of the exporting machine are imported under
+    source: String,
+    snapshots: Vec<ExportedSnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrendExportResult {
+    destination: String,
+    snapshots: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrendImportResult {
+    source: String,
+    // New digests, and ones with a newer analysis than recorded before
+    added: usize,
+    updated: usize,
+    // Already recorded with the same or a newer analysis
+    skipped: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrendFindingsDiff {
+    from_id: i64,
+    to_id: i64,
+    added: Vec<TrendFinding>,
+    resolved: Vec<TrendFinding>,
+}
+
+// Metrics of every analyzed image over time in SQLite, managed as Tauri state
+pub struct TrendStore {
+    connection: Mutex<Connection>,
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "info",
+        Severity::Low => "low",
+        Severity::Medium => "medium",
+        Severity::High => "high",
+        Severity::Critical => "critical",
+    }
+}
+
+fn migrate(connection: &Connection) -> rusqlite::Result<()> {
+    connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+    let version: i32 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version < 1 {
+        connection.execute_batch(SCHEMA)?;
+    }
+    if version < 2 {
+        connection.execute_batch(SOURCE_MIGRATION)?;
+    }
+    if version < SCHEMA_VERSION {
+        connection.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))?;
+    }
+    Ok(())
+}
+
+fn snapshot_from_row(row: &rusqlite::Row) -> rusqlite::Result<TrendSnapshot> {
+    Ok(TrendSnapshot {
+        id: row.get(0)?,
+        image_id: row.get(1)?,
+        reference: row.get(2)?,
+        first_seen: row.get(3)?,
+        last_seen: row.get(4)?,
+        total_bytes: row.get(5)?,
+        wasted_bytes: row.get(6)?,
+        efficiency_score: row.get(7)?,
+        layer_count: row.get(8)?,
+        health_score: row.get(9)?,
+        critical: row.get(10)?,
+        high: row.get(11)?,
+        medium: row.get(12)?,
+        low: row.get(13)?,
+        info: row.get(14)?,
+        source: row.get(15)?,
+    })
+}
+
+const SNAPSHOT_COLUMNS: &str = "id, image_id, reference, first_seen, last_seen, total_bytes, \
+     wasted_bytes, efficiency_score, layer_count, health_score, critical, high, medium, low, info, \
+     source";
+
+// The findings of the latest analysis replace the earlier ones
+fn replace_findings(
+    connection: &Connection,
+    snapshot_id: i64,
+    findings: &[TrendFinding],
+) -> Result<(), String> {
+    connection
+        .execute(
+            "DELETE FROM snapshot_findings WHERE snapshot_id = ?1",
+            params![snapshot_id],
+        )
+        .map_err(|e| format!("Failed to record trend findings: {}", e))?;
+    for finding in findings {
+        connection
+            .execute(
+                "INSERT OR IGNORE INTO snapshot_findings
+                     (snapshot_id, anchor, rule_id, severity, title)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    snapshot_id,
+                    finding.anchor,
+                    finding.rule_id,
+                    finding.severity,
+                    finding.title,
+                ],
+            )
+            .map_err(|e| format!("Failed to record trend findings: {}", e))?;
+    }
+    Ok(())
+}
+
+fn query_findings(connection: &Connection, snapshot_id: i64) -> Result<Vec<TrendFinding>, String> {
+    let mut statement = connection
+        .prepare(
+            "SELECT anchor, rule_id, severity, title FROM snapshot_findings
+             WHERE snapshot_id = ?1 ORDER BY anchor",
+        )
+        .map_err(|e| format!("Failed to query trend findings: {}", e))?;
+    let rows = statement
+        .query_map(params![snapshot_id], |row| {
+            Ok(TrendFinding {
+                anchor: row.get(0)?,
+                rule_id: row.get(1)?,
+                severity: row.get(2)?,
+                title: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query trend findings: {}", e))?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| format!("Failed to read trend findings: {}", e))
+}
+
+// Merges one imported snapshot, the newer analysis of a digest wins
+fn merge_snapshot(
+    connection: &Connection,
+    imported: &ExportedSnapshot,
+) -> Result<Option<bool>, String> {
+    let snapshot = &imported.snapshot;
+    let existing: Option<(i64, u64)> = connection
+        .query_row(
+            "SELECT id, last_seen FROM snapshots
+             WHERE source = ?1 AND image_id = ?2 AND reference = ?3",
+            params![snapshot.source, snapshot.image_id, snapshot.reference],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to merge trends: {}", e))?;
+    if let Some((id, last_seen)) = existing {
+        connection
+            .execute(
+                "UPDATE snapshots SET first_seen = MIN(first_seen, ?2) WHERE id = ?1",
+                params![id, snapshot.first_seen],
+            )
+            .map_err(|e| format!("Failed to merge trends: {}", e))?;
+        if last_seen >= snapshot.last_seen {
+            return Ok(None);
+        }
+    }
+
+    let snapshot_id: i64 = connection
+        .query_row(
+            "INSERT INTO snapshots (source, image_id, reference, first_seen, last_seen,
+                 total_bytes, wasted_bytes, efficiency_score, layer_count, health_score,
+                 critical, high, medium, low, info)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+             ON CONFLICT (source, image_id, reference) DO UPDATE SET
+                 last_seen = excluded.last_seen,
+                 total_bytes = excluded.total_bytes,
+                 wasted_bytes = excluded.wasted_bytes,
+                 efficiency_score = excluded.efficiency_score,
+                 layer_count = excluded.layer_count,
+                 health_score = COALESCE(excluded.health_score, health_score),
+                 critical = excluded.critical,
+                 high = excluded.high,
+                 medium = excluded.medium,
+                 low = excluded.low,
+                 info = excluded.info
+             RETURNING id",
+            params![
+                snapshot.source,
+                snapshot.image_id,
+                snapshot.reference,
+                snapshot.first_seen,
+                snapshot.last_seen,
+                snapshot.total_bytes,
+                snapshot.wasted_bytes,
+                snapshot.efficiency_score,
+                snapshot.layer_count,
+                snapshot.health_score,
+                snapshot.critical,
+                snapshot.high,
+                snapshot.medium,
+                snapshot.low,
+                snapshot.info,
+            ],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to merge trends: {}", e))?;
+    replace_findings(connection, snapshot_id, &imported.findings)?;
+    Ok(Some(existing.is_some()))
+}
+
+// Name this machine's snapshots are exported under when none is given
+fn default_source() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+impl TrendStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+        }
+        let connection = Connection::open(path)
+            .map_err(|e| format!("Failed to open trend database {:?}: {}", path, e))?;
+        migrate(&connection).map_err(|e| format!("Failed to set up trend database: {}", e))?;
+        Ok(TrendStore {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    // Records a report's metrics over an earlier analysis of the digest, None keeps the old score
+    pub(crate) fn record(&self, report: &Report, health_score: Option<u32>) -> Result<(), String> {
+        let count = |severity: Severity| {
+            report
+                .findings
+                .iter()
+                .filter(|finding| finding.finding.severity == severity)
+                .count()
+        };
+        let layer_count = report.layers.iter().filter(|layer| !layer.empty).count();
+
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection
+            .transaction()
+            .map_err(|e| format!("Failed to record trend: {}", e))?;
+        let snapshot_id: i64 = transaction
+            .query_row(
+                "INSERT INTO snapshots (image_id, reference, first_seen, last_seen, total_bytes,
+                     wasted_bytes, efficiency_score, layer_count, health_score,
+                     critical, high, medium, low, info)
+                 VALUES (?1, ?2, ?3, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                 ON CONFLICT (source, image_id, reference) DO UPDATE SET
+                     last_seen = excluded.last_seen,
+                     total_bytes = excluded.total_bytes,
+                     wasted_bytes = excluded.wasted_bytes,
+                     efficiency_score = excluded.efficiency_score,
+                     layer_count = excluded.layer_count,
+                     health_score = COALESCE(excluded.health_score, health_score),
+                     critical = excluded.critical,
+                     high = excluded.high,
+                     medium = excluded.medium,
+                     low = excluded.low,
+                     info = excluded.info
+                 RETURNING id",
+                params![
+                    report.image.image_id,
+                    report.image.reference,
+                    report.generated_at,
+                    report.total_bytes,
+                    report.efficiency.wasted_bytes,
+                    report.efficiency.score,
+                    layer_count,
+                    health_score,
+                    count(Severity::Critical),
+                    count(Severity::High),
+                    count(Severity::Medium),
+                    count(Severity::Low),
+                    count(Severity::Info),
+                ],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to record trend: {}", e))?;
+
+        let findings: Vec<TrendFinding> = report
+            .findings
+            .iter()
+            .map(|finding| TrendFinding {
+                anchor: finding.anchor.clone(),
+                rule_id: finding.finding.rule_id.clone(),
+                severity: severity_name(finding.finding.severity).to_string(),
+                title: finding.finding.title.clone(),
+            })
+            .collect();
+        replace_findings(&transaction, snapshot_id, &findings)?;
+        transaction
+            .commit()
+            .map_err(|e| format!("Failed to record trend: {}", e))
+    }
+
+    fn snapshots(&self, reference: Option<&str>) -> Result<Vec<TrendSnapshot>, String> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT {} FROM snapshots WHERE ?1 IS NULL OR reference = ?1
+                 ORDER BY reference, first_seen",
+                SNAPSHOT_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to query trends: {}", e))?;
+        let rows = statement
+            .query_map(params![reference], snapshot_from_row)
+            .map_err(|e| format!("Failed to query trends: {}", e))?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| format!("Failed to read trends: {}", e))
+    }
+
+    // Snapshots of a reference seen since a point in time, with findings
+    fn export(
+        &self,
+        reference: Option<&str>,
+        since: Option<u64>,
+    ) -> Result<Vec<ExportedSnapshot>, String> {
+        let snapshots: Vec<TrendSnapshot> = self
+            .snapshots(reference)?
+            .into_iter()
+            .filter(|snapshot| since.is_none_or(|since| snapshot.last_seen >= since))
+            .collect();
+        let connection = self.connection.lock().unwrap();
+        snapshots
+            .into_iter()
+            .map(|snapshot| {
+                let findings = query_findings(&connection, snapshot.id)?;
+                Ok(ExportedSnapshot { snapshot, findings })
+            })
+            .collect()
+    }
+
+    fn import(&self, export: TrendExport) -> Result<TrendImportResult, String> {
+        let mut result = TrendImportResult {
+            source: export.source.clone(),
+            added: 0,
+            updated: 0,
+            skipped: 0,
+        };
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection
+            .transaction()
+            .map_err(|e| format!("Failed to merge trends: {}", e))?;
+        for mut imported in export.snapshots {
+            // The exporter's own snapshots are filed under its name, the ones
+            // it imported itself keep theirs
+            if imported.snapshot.source.is_empty() {
+                imported.snapshot.source = export.source.clone();
+            }
+            match merge_snapshot(&transaction, &imported)? {
+                Some(true) => result.updated += 1,
+                Some(false) => result.added += 1,
+                None => result.skipped += 1,
+            }
+        }
+        transaction
+            .commit()
+            .map_err(|e| format!("Failed to merge trends: {}", e))?;
+        Ok(result)
+    }
+
+    fn findings(&self, snapshot_id: i64) -> Result<Vec<TrendFinding>, String> {
+        let connection = self.connection.lock().unwrap();
+        let exists = connection
+            .query_row(
+                "SELECT 1 FROM snapshots WHERE id = ?1",
+                params![snapshot_id],
+                |_| Ok(()),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to query trends: {}", e))?;
+        if exists.is_none() {
+            return Err(format!("No trend snapshot with ID {}", snapshot_id));
+        }
+        query_findings(&connection, snapshot_id)
+    }
+}
+
+// Every recorded digest of a reference in the order they were first seen,
+// for the tag timeline and regression charts. All references without one.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_image_trend(
+    trends: tauri::State<'_, TrendStore>,
+    reference: Option<String>,
+) -> Result<Vec<TrendSnapshot>, LayersError> {
+    trends
+        .snapshots(reference.as_deref())
+        .map_err(LayersError::from)
+}
+
+// Findings that appeared or went away between two recorded snapshots,
+// matched by their stable anchor
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn diff_trend_findings(
+    trends: tauri::State<'_, TrendStore>,
+    from_id: i64,
+    to_id: i64,
+) -> Result<TrendFindingsDiff, LayersError> {
+    let from = trends.findings(from_id)?;
+    let to = trends.findings(to_id)?;
+    let from_anchors: HashSet<&str> = from.iter().map(|f| f.anchor.as_str()).collect();
+    let to_anchors: HashSet<&str> = to.iter().map(|f| f.anchor.as_str()).collect();
+    Ok(TrendFindingsDiff {
+        from_id,
+        to_id,
+        added: to
+            .iter()
+            .filter(|f| !from_anchors.contains(f.anchor.as_str()))
+            .cloned()
+            .collect(),
+        resolved: from
+            .iter()
+            .filter(|f| !to_anchors.contains(f.anchor.as_str()))
+            .cloned()
+            .collect(),
+    })
+}
+
+// Writes the trend database, or the snapshots of one reference seen since a
+// point in time, to a file teammates can import
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn export_trends(
+    trends: tauri::State<'_, TrendStore>,
+    destination: String,
+    source: Option<String>,
+    reference: Option<String>,
+    since: Option<u64>,
+) -> Result<TrendExportResult, LayersError> {
+    let snapshots = trends.export(reference.as_deref(), since)?;
+    let count = snapshots.len();
+    let export = TrendExport {
+        schema_version: SCHEMA_VERSION,
+        exported_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        source: source
+            .filter(|source| !source.trim().is_empty())
+            .unwrap_or_else(default_source),
+        snapshots,
+    };
+    let content = serde_json::to_vec_pretty(&export)
+        .map_err(|e| format!("Failed to serialize trends: {}", e))?;
+    let destination_path = Path::new(&destination);
+    let written = fs::write(destination_path, content);
+    audit::record_write(destination_path, &written);
+    written.map_err(|e| format!("Failed to write trends: {}", e))?;
+    info!("Exported {} trend snapshots to {}", count, destination);
+
+    Ok(TrendExportResult {
+        destination,
+        snapshots: count,
+    })
+}
+
+// Merges a file written by export_trends on another machine. Importing the
+// same file again changes nothing.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn import_trends(
+    trends: tauri::State<'_, TrendStore>,
+    path: String,
+) -> Result<TrendImportResult, LayersError> {
+    let content = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let export: TrendExport = serde_json::from_slice(&content)
+        .map_err(|e| format!("Failed to parse trend export {}: {}", path, e))?;
+    if export.schema_version > SCHEMA_VERSION {
+        return Err(LayersError::from(format!(
+            "{} was exported by a newer version of Layers",
+            path
+        )));
+    }
+    let result = trends.import(export)?;
+    info!(
+        "Imported trends from {}: {} added, {} updated, {} skipped",
+        result.source, result.added, result.updated, result.skipped
+    );
+    Ok(result)
+}
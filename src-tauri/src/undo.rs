@@ -0,0 +1,142 @@
+use layers_core::docker::inspect_image;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::audit;
+use crate::error::LayersError;
+use crate::session::SessionState;
+
+// What it takes to put things back
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UndoAction {
+    // A tag removed from an image that still exists under another name
+    Retag { image_id: String, tag: String },
+    // A closed session, reopening it re-extracts from the extraction cache
+    ReopenSession { image_id: String, reference: String },
+    // Deleted for good, listed so the UI can say so
+    Irreversible,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UndoEntry {
+    action_id: u64,
+    timestamp: u64,
+    description: String,
+    action: UndoAction,
+    reversible: bool,
+    undone: bool,
+}
+
+// Destructive actions of this run that can be taken back, managed as Tauri state
+#[derive(Default)]
+pub struct UndoHistory {
+    entries: Mutex<Vec<UndoEntry>>,
+    next_id: AtomicU64,
+}
+
+impl UndoHistory {
+    pub(crate) fn record(&self, description: String, action: UndoAction) -> UndoEntry {
+        let entry = UndoEntry {
+            action_id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            description,
+            reversible: !matches!(action, UndoAction::Irreversible),
+            action,
+            undone: false,
+        };
+        info!("Recorded action {}: {}", entry.action_id, entry.description);
+        self.entries.lock().unwrap().push(entry.clone());
+        entry
+    }
+}
+
+// Full ID and tag count of an image, None when it doesn't exist
+fn image_tags(image: &str) -> Option<(String, usize)> {
+    let inspect = inspect_image(image).ok()?;
+    let image_id = inspect["Id"].as_str()?.to_string();
+    let tags = inspect["RepoTags"].as_array().map(|tags| tags.len());
+    Some((image_id, tags.unwrap_or(0)))
+}
+
+// The action for removing a tag. Removing the last tag of an image deletes
+// it, which can't be undone.
+pub(crate) fn untag_action(tag: &str) -> UndoAction {
+    match image_tags(tag) {
+        Some((image_id, tags)) if tags > 1 => UndoAction::Retag {
+            image_id,
+            tag: tag.to_string(),
+        },
+        _ => UndoAction::Irreversible,
+    }
+}
+
+// Newest first
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_undo_history(
+    undo: tauri::State<'_, UndoHistory>,
+) -> Result<Vec<UndoEntry>, LayersError> {
+    let mut entries = undo.entries.lock().unwrap().clone();
+    entries.reverse();
+    Ok(entries)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn undo_action(
+    undo: tauri::State<'_, UndoHistory>,
+    session: tauri::State<'_, SessionState>,
+    action_id: u64,
+) -> Result<UndoEntry, LayersError> {
+    let entry = undo
+        .entries
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|entry| entry.action_id == action_id)
+        .cloned()
+        .ok_or_else(|| format!("No action with ID {}", action_id))?;
+    if entry.undone {
+        return Err(format!("'{}' was already undone", entry.description).into());
+    }
+    info!("Undoing action {}: {}", action_id, entry.description);
+
+    match &entry.action {
+        UndoAction::Retag { image_id, tag } => {
+            let output = audit::docker(&["tag", image_id, tag])
+                .map_err(|e| format!("Failed to tag image: {}", e))?;
+            if !output.status.success() {
+                return Err(format!(
+                    "Failed to restore tag {}: {}",
+                    tag,
+                    String::from_utf8_lossy(&output.stderr)
+                )
+                .into());
+            }
+        }
+        UndoAction::ReopenSession {
+            image_id,
+            reference,
+        } => {
+            session.open(image_id.clone(), reference.clone())?;
+        }
+        UndoAction::Irreversible => {
+            return Err(format!("'{}' can't be undone", entry.description).into());
+        }
+    }
+
+    let mut entries = undo.entries.lock().unwrap();
+    let entry = entries
+        .iter_mut()
+        .find(|entry| entry.action_id == action_id)
+        .ok_or_else(|| format!("No action with ID {}", action_id))?;
+    entry.undone = true;
+    Ok(entry.clone())
+}
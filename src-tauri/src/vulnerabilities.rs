@@ -0,0 +1,1092 @@
+use layers_core::docker::image_diff_ids;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::Manager;
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::findings::Severity;
+use crate::java_packages;
+use crate::phases::{phase, Phase, PhaseProgress};
+use crate::sbom::{scan_package_archive, scan_packages, Distro, Ecosystem, SbomComponent};
+use crate::search_index::{filesystem_layers, read_saved_layers};
+use crate::session::{ImageSession, SessionState};
+use crate::tasks::{Task, TaskRegistry};
+use crate::{finish_task, layer_tar_path};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VulnerabilityScanner {
+    Trivy,
+    Grype,
+    // Offline matching against a downloaded OSV database
+    Osv,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct VulnerabilityScanOptions {
+    // Defaults to Trivy, then Grype, then the OSV database, whichever is available
+    scanner: Option<VulnerabilityScanner>,
+    // Directory with OSV JSON files or the per ecosystem all.zip exports,
+    // defaults to "osv" in the app data directory
+    osv_database: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Vulnerability {
+    id: String,
+    aliases: Vec<String>,
+    package: String,
+    installed_version: String,
+    fixed_version: Option<String>,
+    // None when the source doesn't rate the vulnerability
+    severity: Option<Severity>,
+    summary: String,
+    path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerVulnerabilities {
+    layer_id: String,
+    created_by: String,
+    vulnerabilities: Vec<Vulnerability>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VulnerabilityReport {
+    image: String,
+    scanner: VulnerabilityScanner,
+    // Newest layer first, only layers that introduced something vulnerable
+    layers: Vec<LayerVulnerabilities>,
+    // Matches the scanner couldn't tie to a layer
+    unattributed: Vec<Vulnerability>,
+    total: usize,
+    warnings: Vec<String>,
+}
+
+// Output of `trivy image --format json`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TrivyReport {
+    #[serde(default)]
+    results: Vec<TrivyResult>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TrivyResult {
+    #[serde(default)]
+    vulnerabilities: Vec<TrivyVulnerability>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TrivyVulnerability {
+    #[serde(rename = "VulnerabilityID")]
+    vulnerability_id: String,
+    pkg_name: String,
+    pkg_path: Option<String>,
+    installed_version: String,
+    fixed_version: Option<String>,
+    severity: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+    layer: Option<TrivyLayer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrivyLayer {
+    #[serde(rename = "DiffID")]
+    diff_id: Option<String>,
+}
+
+// Output of `grype -o json`
+#[derive(Debug, Deserialize)]
+struct GrypeReport {
+    #[serde(default)]
+    matches: Vec<GrypeMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GrypeMatch {
+    vulnerability: GrypeVulnerability,
+    #[serde(default)]
+    related_vulnerabilities: Vec<GrypeRelated>,
+    artifact: GrypeArtifact,
+}
+
+#[derive(Debug, Deserialize)]
+struct GrypeVulnerability {
+    id: String,
+    severity: Option<String>,
+    description: Option<String>,
+    fix: Option<GrypeFix>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GrypeFix {
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GrypeRelated {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GrypeArtifact {
+    name: String,
+    version: String,
+    #[serde(default)]
+    locations: Vec<GrypeLocation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GrypeLocation {
+    path: String,
+    #[serde(rename = "layerID")]
+    layer_id: Option<String>,
+}
+
+// OSV schema, see https://ossf.github.io/osv-schema/
+#[derive(Debug, Deserialize)]
+struct OsvEntry {
+    id: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    details: String,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+    database_specific: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvAffected {
+    package: Option<OsvPackage>,
+    #[serde(default)]
+    ranges: Vec<OsvRange>,
+    #[serde(default)]
+    versions: Vec<String>,
+    ecosystem_specific: Option<serde_json::Value>,
+    database_specific: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvPackage {
+    ecosystem: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvRange {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<OsvEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvEvent {
+    introduced: Option<String>,
+    fixed: Option<String>,
+    last_affected: Option<String>,
+    limit: Option<String>,
+}
+
+fn parse_severity(severity: &str) -> Option<Severity> {
+    match severity.to_lowercase().as_str() {
+        "critical" => Some(Severity::Critical),
+        "high" | "important" => Some(Severity::High),
+        "medium" | "moderate" => Some(Severity::Medium),
+        "low" | "negligible" | "unimportant" => Some(Severity::Low),
+        _ => None,
+    }
+}
+
+fn is_installed(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+// Scanners report layers by RootFS diff ID, map those to layer_N
+fn layer_ids_by_diff_id(image: &str) -> Result<HashMap<String, String>, String> {
+    Ok(image_diff_ids(image)?
+        .into_iter()
+        .zip(
+            filesystem_layers(image)?
+                .into_iter()
+                .map(|(layer_id, _)| layer_id),
+        )
+        .collect())
+}
+
+fn scan_with_trivy(task: &Task, image: &str) -> Result<Vec<LayerMatch>, String> {
+    let layer_ids = layer_ids_by_diff_id(image)?;
+    let output = task
+        .run(Command::new("trivy").args([
+            "image",
+            "--format",
+            "json",
+            "--quiet",
+            "--scanners",
+            "vuln",
+            image,
+        ]))
+        .map_err(|e| format!("Failed to run trivy: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "trivy failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let report: TrivyReport = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse trivy output: {}", e))?;
+
+    Ok(report
+        .results
+        .into_iter()
+        .flat_map(|result| result.vulnerabilities)
+        .map(|v| {
+            let layer_id = v
+                .layer
+                .and_then(|layer| layer.diff_id)
+                .and_then(|diff_id| layer_ids.get(&diff_id).cloned());
+            let vulnerability = Vulnerability {
+                id: v.vulnerability_id,
+                aliases: Vec::new(),
+                package: v.pkg_name,
+                installed_version: v.installed_version,
+                fixed_version: v.fixed_version.filter(|f| !f.is_empty()),
+                severity: v.severity.as_deref().and_then(parse_severity),
+                summary: v.title.or(v.description).unwrap_or_default(),
+                path: v
+                    .pkg_path
+                    .map(|p| format!("/{}", p.trim_start_matches('/'))),
+            };
+            (layer_id, vulnerability)
+        })
+        .collect())
+}
+
+fn scan_with_grype(task: &Task, image: &str) -> Result<Vec<LayerMatch>, String> {
+    let layer_ids = layer_ids_by_diff_id(image)?;
+    let output = task
+        .run(Command::new("grype").args([&format!("docker:{}", image), "-o", "json", "--quiet"]))
+        .map_err(|e| format!("Failed to run grype: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "grype failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let report: GrypeReport = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse grype output: {}", e))?;
+
+    Ok(report
+        .matches
+        .into_iter()
+        .map(|m| {
+            let location = m.artifact.locations.into_iter().next();
+            let layer_id = location
+                .as_ref()
+                .and_then(|l| l.layer_id.as_ref())
+                .and_then(|diff_id| layer_ids.get(diff_id).cloned());
+            let vulnerability = Vulnerability {
+                aliases: m
+                    .related_vulnerabilities
+                    .into_iter()
+                    .map(|r| r.id)
+                    .filter(|id| *id != m.vulnerability.id)
+                    .collect(),
+                id: m.vulnerability.id,
+                package: m.artifact.name,
+                installed_version: m.artifact.version,
+                fixed_version: m
+                    .vulnerability
+                    .fix
+                    .and_then(|fix| fix.versions.into_iter().next()),
+                severity: m.vulnerability.severity.as_deref().and_then(parse_severity),
+                summary: m.vulnerability.description.unwrap_or_default(),
+                path: location.map(|l| l.path),
+            };
+            (layer_id, vulnerability)
+        })
+        .collect())
+}
+
+// OSV ecosystem name of a component, None when OSV doesn't cover it
+fn osv_ecosystem(ecosystem: Ecosystem, distro: Option<&Distro>) -> Option<&'static str> {
+    let distro_id = distro.map(|d| d.id.as_str()).unwrap_or("");
+    match ecosystem {
+        Ecosystem::Deb if distro_id == "ubuntu" => Some("Ubuntu"),
+        Ecosystem::Deb => Some("Debian"),
+        Ecosystem::Apk if distro_id == "wolfi" => Some("Wolfi"),
+        Ecosystem::Apk if distro_id == "chainguard" => Some("Chainguard"),
+        Ecosystem::Apk => Some("Alpine"),
+        Ecosystem::Rpm => match distro_id {
+            "rocky" => Some("Rocky Linux"),
+            "almalinux" => Some("AlmaLinux"),
+            "rhel" => Some("Red Hat"),
+            "sles" => Some("SUSE"),
+            id if id.starts_with("opensuse") => Some("openSUSE"),
+            _ => None,
+        },
+        Ecosystem::Pypi => Some("PyPI"),
+        Ecosystem::Npm => Some("npm"),
+        Ecosystem::Golang => Some("Go"),
+        Ecosystem::Cargo => Some("crates.io"),
+        Ecosystem::Maven => Some("Maven"),
+    }
+}
+
+// "Debian:12", "Alpine:v3.19" and "Ubuntu:22.04:LTS" are per release
+fn release_matches(osv_ecosystem: &str, distro: Option<&Distro>) -> bool {
+    let Some((base, release)) = osv_ecosystem.split_once(':') else {
+        return true;
+    };
+    let Some(version_id) = distro.and_then(|d| d.version_id.as_deref()) else {
+        return true;
+    };
+    let major = version_id.split('.').next().unwrap_or(version_id);
+    if base == "Alpine" {
+        let major_minor: Vec<&str> = version_id.split('.').take(2).collect();
+        return release == format!("v{}", major_minor.join("."));
+    }
+    release
+        .split(':')
+        .any(|part| part == version_id || part == major)
+}
+
+// Name OSV files the component under. Debian and Alpine advisories are
+// per source package, PyPI names are case and separator insensitive.
+fn osv_name(component: &SbomComponent) -> String {
+    match component.ecosystem {
+        Ecosystem::Deb | Ecosystem::Apk => component
+            .source
+            .clone()
+            .unwrap_or_else(|| component.name.clone()),
+        Ecosystem::Pypi => component.name.to_lowercase().replace(['_', '.'], "-"),
+        _ => component.name.clone(),
+    }
+}
+
+fn osv_package_name(ecosystem: &str, name: &str) -> String {
+    match ecosystem {
+        "PyPI" => name.to_lowercase().replace(['_', '.'], "-"),
+        _ => name.to_string(),
+    }
+}
+
+fn split_epoch(version: &str) -> (u64, &str) {
+    match version.split_once(':') {
+        Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => {
+            (epoch.parse().unwrap_or(0), rest)
+        }
+        _ => (0, version),
+    }
+}
+
+// dpkg's verrevcmp: ~ sorts before everything, letters before other symbols
+fn dpkg_verrevcmp(a: &str, b: &str) -> Ordering {
+    let order = |c: Option<u8>| -> i32 {
+        match c {
+            Some(b'~') => -1,
+            Some(c) if c.is_ascii_digit() => 0,
+            Some(c) if c.is_ascii_alphabetic() => c as i32,
+            Some(c) => c as i32 + 256,
+            None => 0,
+        }
+    };
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !a[i].is_ascii_digit()) || (j < b.len() && !b[j].is_ascii_digit()) {
+            let (ac, bc) = (order(a.get(i).copied()), order(b.get(j).copied()));
+            if ac != bc {
+                return ac.cmp(&bc);
+            }
+            i += 1;
+            j += 1;
+        }
+        while i < a.len() && a[i] == b'0' {
+            i += 1;
+        }
+        while j < b.len() && b[j] == b'0' {
+            j += 1;
+        }
+        let mut first_diff = Ordering::Equal;
+        while i < a.len() && a[i].is_ascii_digit() && j < b.len() && b[j].is_ascii_digit() {
+            if first_diff == Ordering::Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if i < a.len() && a[i].is_ascii_digit() {
+            return Ordering::Greater;
+        }
+        if j < b.len() && b[j].is_ascii_digit() {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+    Ordering::Equal
+}
+
+// [epoch:]upstream[-revision]
+fn compare_dpkg(a: &str, b: &str) -> Ordering {
+    let ((a_epoch, a), (b_epoch, b)) = (split_epoch(a), split_epoch(b));
+    let (a_upstream, a_revision) = a.rsplit_once('-').unwrap_or((a, ""));
+    let (b_upstream, b_revision) = b.rsplit_once('-').unwrap_or((b, ""));
+    a_epoch
+        .cmp(&b_epoch)
+        .then_with(|| dpkg_verrevcmp(a_upstream, b_upstream))
+        .then_with(|| dpkg_verrevcmp(a_revision, b_revision))
+}
+
+// rpmvercmp style comparison of digit and letter runs, used for everything
+// without a dedicated scheme. Trailing letters mark a pre-release, 1.0rc1 < 1.0.
+fn compare_segments(a: &str, b: &str) -> Ordering {
+    let segments = |version: &str| -> Vec<String> {
+        let version = version.trim_start_matches('v');
+        // Semver build metadata doesn't take part in ordering
+        let version = version.split('+').next().unwrap_or(version);
+        let mut segments: Vec<String> = Vec::new();
+        let mut previous: Option<char> = None;
+        for c in version.chars() {
+            let same_kind = previous.is_some_and(|p| {
+                (p.is_ascii_digit() && c.is_ascii_digit())
+                    || (p.is_ascii_alphabetic() && c.is_ascii_alphabetic())
+            });
+            if c == '~' {
+                segments.push("~".to_string());
+            } else if !c.is_ascii_alphanumeric() {
+                previous = None;
+                continue;
+            } else if same_kind {
+                segments.last_mut().unwrap().push(c);
+            } else {
+                segments.push(c.to_string());
+            }
+            previous = Some(c);
+        }
+        segments
+    };
+    let ((a_epoch, a), (b_epoch, b)) = (split_epoch(a), split_epoch(b));
+    if a_epoch != b_epoch {
+        return a_epoch.cmp(&b_epoch);
+    }
+    let (a, b) = (segments(a), segments(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = match (a.get(i), b.get(i)) {
+            (Some(x), Some(y)) if x == "~" || y == "~" => (y == "~").cmp(&(x == "~")),
+            (Some(x), Some(y)) => match (x.parse::<u128>(), y.parse::<u128>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Greater,
+                (Err(_), Ok(_)) => Ordering::Less,
+                (Err(_), Err(_)) => x.cmp(y),
+            },
+            (Some(x), None) if x.parse::<u128>().is_ok() => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(y)) if y.parse::<u128>().is_ok() => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+fn compare_versions(ecosystem: Ecosystem, a: &str, b: &str) -> Ordering {
+    match ecosystem {
+        Ecosystem::Deb => compare_dpkg(a, b),
+        Ecosystem::Maven => java_packages::compare_versions(a, b),
+        _ => compare_segments(a, b),
+    }
+}
+
+// The fixed version, if any, when `version` falls in one of the affected ranges
+fn affected_by(
+    affected: &OsvAffected,
+    ecosystem: Ecosystem,
+    version: &str,
+) -> Option<Option<String>> {
+    let cmp = |a: &str, b: &str| compare_versions(ecosystem, a, b);
+    let mut first_fix = None;
+    let mut is_affected = affected.versions.iter().any(|v| v == version);
+
+    // Git ranges are commit hashes, only the explicit version list covers them
+    for range in affected.ranges.iter().filter(|r| r.kind != "GIT") {
+        let mut introduced: Option<&str> = None;
+        for event in &range.events {
+            if let Some(start) = &event.introduced {
+                introduced = Some(start);
+                continue;
+            }
+            let Some(start) = introduced.take() else {
+                continue;
+            };
+            let after_start = start == "0" || cmp(version, start) != Ordering::Less;
+            if let Some(fixed) = event.fixed.as_ref().or(event.limit.as_ref()) {
+                if after_start && cmp(version, fixed) == Ordering::Less {
+                    is_affected = true;
+                    if event.fixed.is_some() {
+                        first_fix = first_fix.or(Some(fixed.clone()));
+                    }
+                }
+            } else if let Some(last) = &event.last_affected {
+                if after_start && cmp(version, last) != Ordering::Greater {
+                    is_affected = true;
+                }
+            }
+        }
+        // An introduced event without an end affects every later version
+        if let Some(start) = introduced {
+            if start == "0" || cmp(version, start) != Ordering::Less {
+                is_affected = true;
+            }
+        }
+    }
+    is_affected.then_some(first_fix)
+}
+
+fn osv_severity(entry: &OsvEntry, affected: &OsvAffected) -> Option<Severity> {
+    [
+        affected.ecosystem_specific.as_ref(),
+        affected.database_specific.as_ref(),
+        entry.database_specific.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|value| {
+        value["severity"]
+            .as_str()
+            .or_else(|| value["urgency"].as_str())
+            .and_then(parse_severity)
+    })
+}
+
+fn read_osv_files(database: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries =
+        fs::read_dir(database).map_err(|e| format!("Failed to read {:?}: {}", database, e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            read_osv_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == "json" || ext == "zip")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Load the advisories that mention one of `wanted` (ecosystem, name)
+fn load_osv_database(
+    task: &Task,
+    database: &Path,
+    wanted: &HashSet<(String, String)>,
+) -> Result<Vec<OsvEntry>, String> {
+    let mut files = Vec::new();
+    read_osv_files(database, &mut files)?;
+    if files.is_empty() {
+        return Err(format!(
+            "No OSV database found in {:?}, download the ecosystem exports from \
+             https://osv-vulnerabilities.storage.googleapis.com/ and place the all.zip files there",
+            database
+        ));
+    }
+
+    let is_wanted = |entry: &OsvEntry| {
+        entry.affected.iter().any(|affected| {
+            affected.package.as_ref().is_some_and(|p| {
+                let base = p.ecosystem.split(':').next().unwrap_or(&p.ecosystem);
+                wanted.contains(&(base.to_string(), osv_package_name(base, &p.name)))
+            })
+        })
+    };
+
+    let mut entries = Vec::new();
+    for file in files {
+        task.check_cancelled()?;
+        if file.extension().is_some_and(|ext| ext == "json") {
+            let content =
+                fs::read(&file).map_err(|e| format!("Failed to read {:?}: {}", file, e))?;
+            if let Ok(entry) = serde_json::from_slice::<OsvEntry>(&content) {
+                if is_wanted(&entry) {
+                    entries.push(entry);
+                }
+            }
+            continue;
+        }
+
+        let archive = File::open(&file).map_err(|e| format!("Failed to open {:?}: {}", file, e))?;
+        let mut archive = zip::ZipArchive::new(archive)
+            .map_err(|e| format!("Failed to read {:?}: {}", file, e))?;
+        for i in 0..archive.len() {
+            if i % 1000 == 0 {
+                task.check_cancelled()?;
+            }
+            let Ok(mut advisory) = archive.by_index(i) else {
+                continue;
+            };
+            let mut content = Vec::with_capacity(advisory.size() as usize);
+            if advisory.read_to_end(&mut content).is_err() {
+                continue;
+            }
+            if let Ok(entry) = serde_json::from_slice::<OsvEntry>(&content) {
+                if is_wanted(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+// A vulnerability and the layer_N that introduced it, if known
+type LayerMatch = (Option<String>, Vulnerability);
+
+type ComponentKey = (Ecosystem, String, String, String);
+
+fn component_key(component: &SbomComponent) -> ComponentKey {
+    (
+        component.ecosystem,
+        component.name.clone(),
+        component.version.clone(),
+        component.path.clone(),
+    )
+}
+
+fn scan_with_osv(
+    task: &Task,
+    session: &ImageSession,
+    database: &Path,
+    progress: &PhaseProgress,
+) -> Result<(Vec<LayerMatch>, Vec<String>), String> {
+    progress.begin("resolve", "Listing installed packages...");
+    let tar_path = layer_tar_path(task, session, "current_layer")?;
+    let inventory = scan_packages(task, &tar_path, true)?;
+    let distro = inventory.distro.as_ref();
+
+    // Scan each layer on its own to find the one that installed each version
+    progress.begin("index", "Attributing packages to layers...");
+    let layers = read_saved_layers(task, session.image_id(), |reader| {
+        scan_package_archive(task, reader, true).map_err(io::Error::other)
+    })?;
+    // Layers come base first, so the first layer listing a version installed it
+    let mut introduced_in: HashMap<ComponentKey, String> = HashMap::new();
+    for layer in &layers {
+        for component in &layer.contents.components {
+            introduced_in
+                .entry(component_key(component))
+                .or_insert_with(|| layer.layer_id.clone());
+        }
+    }
+
+    progress.begin("fetch", "Loading OSV database...");
+    let wanted: HashSet<(String, String)> = inventory
+        .components
+        .iter()
+        .filter_map(|c| {
+            let ecosystem = osv_ecosystem(c.ecosystem, distro)?;
+            Some((
+                ecosystem.to_string(),
+                osv_package_name(ecosystem, &osv_name(c)),
+            ))
+        })
+        .collect();
+    let entries = load_osv_database(task, database, &wanted)?;
+    let mut by_package: HashMap<(String, String), Vec<&OsvEntry>> = HashMap::new();
+    for entry in &entries {
+        for package in entry.affected.iter().filter_map(|a| a.package.as_ref()) {
+            let base = package
+                .ecosystem
+                .split(':')
+                .next()
+                .unwrap_or(&package.ecosystem);
+            by_package
+                .entry((base.to_string(), osv_package_name(base, &package.name)))
+                .or_default()
+                .push(entry);
+        }
+    }
+
+    progress.begin("analyze", "Matching packages against advisories...");
+    let mut matches = Vec::new();
+    for component in &inventory.components {
+        let Some(ecosystem) = osv_ecosystem(component.ecosystem, distro) else {
+            continue;
+        };
+        let name = osv_package_name(ecosystem, &osv_name(component));
+        let Some(candidates) = by_package.get(&(ecosystem.to_string(), name.clone())) else {
+            continue;
+        };
+        let mut seen = HashSet::new();
+        for entry in candidates {
+            let affected = entry.affected.iter().find_map(|affected| {
+                let package = affected.package.as_ref()?;
+                let matches_package = package.ecosystem.split(':').next() == Some(ecosystem)
+                    && osv_package_name(ecosystem, &package.name) == name
+                    && release_matches(&package.ecosystem, distro);
+                if !matches_package {
+                    return None;
+                }
+                let fixed = affected_by(affected, component.ecosystem, &component.version)?;
+                Some((affected, fixed))
+            });
+            let Some((affected, fixed_version)) = affected else {
+                continue;
+            };
+            if !seen.insert(&entry.id) {
+                continue;
+            }
+            matches.push((
+                introduced_in.get(&component_key(component)).cloned(),
+                Vulnerability {
+                    id: entry.id.clone(),
+                    aliases: entry.aliases.clone(),
+                    package: component.name.clone(),
+                    installed_version: component.version.clone(),
+                    fixed_version,
+                    severity: osv_severity(entry, affected),
+                    summary: if entry.summary.is_empty() {
+                        entry.details.lines().next().unwrap_or("").to_string()
+                    } else {
+                        entry.summary.clone()
+                    },
+                    path: Some(component.path.clone()),
+                },
+            ));
+        }
+    }
+    Ok((matches, inventory.warnings))
+}
+
+// trivy and grype do everything in the analyze phase
+const VULNERABILITY_SCAN_PHASES: &[Phase] = &[
+    phase("resolve", 0.15),
+    phase("index", 0.3),
+    phase("fetch", 0.2),
+    phase("analyze", 0.35),
+];
+
+async fn scan_image_vulnerabilities_task(
+    window: &tauri::Window,
+    task: &Task,
+    session: &ImageSession,
+    options: VulnerabilityScanOptions,
+) -> Result<VulnerabilityReport, String> {
+    let progress = PhaseProgress::new(window, task.id, VULNERABILITY_SCAN_PHASES);
+
+    let scanner = match options.scanner {
+        Some(scanner) => scanner,
+        None if is_installed("trivy") => VulnerabilityScanner::Trivy,
+        None if is_installed("grype") => VulnerabilityScanner::Grype,
+        None => VulnerabilityScanner::Osv,
+    };
+    let image = session.image_id().to_string();
+    let reference = session.reference().to_string();
+    info!(
+        "Scanning {} for vulnerabilities with {:?}",
+        reference, scanner
+    );
+
+    let (matches, warnings) = match scanner {
+        VulnerabilityScanner::Trivy => {
+            progress.begin("analyze", "Running trivy...");
+            (scan_with_trivy(task, &image)?, Vec::new())
+        }
+        VulnerabilityScanner::Grype => {
+            progress.begin("analyze", "Running grype...");
+            (scan_with_grype(task, &image)?, Vec::new())
+        }
+        VulnerabilityScanner::Osv => {
+            let database = match options.osv_database {
+                Some(database) => PathBuf::from(database),
+                None => window
+                    .path()
+                    .app_data_dir()
+                    .map_err(|e| format!("Failed to find app data directory: {}", e))?
+                    .join("osv"),
+            };
+            scan_with_osv(task, session, &database, &progress)?
+        }
+    };
+
+    // Group by the layer that introduced the vulnerable package
+    progress.update("Grouping by layer...", 0.9);
+    let total = matches.len();
+    let mut grouped: HashMap<String, Vec<Vulnerability>> = HashMap::new();
+    let mut unattributed = Vec::new();
+    for (layer_id, vulnerability) in matches {
+        match layer_id {
+            Some(layer_id) => grouped.entry(layer_id).or_default().push(vulnerability),
+            None => unattributed.push(vulnerability),
+        }
+    }
+
+    // filesystem_layers is base first, the report lists the newest layer first
+    let layers: Vec<LayerVulnerabilities> = filesystem_layers(&image)?
+        .into_iter()
+        .rev()
+        .filter_map(|(layer_id, created_by)| {
+            let mut vulnerabilities = grouped.remove(&layer_id)?;
+            vulnerabilities.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.id.cmp(&b.id)));
+            Some(LayerVulnerabilities {
+                layer_id,
+                created_by,
+                vulnerabilities,
+            })
+        })
+        .collect();
+
+    info!(
+        "Found {} vulnerabilities across {} layers",
+        total,
+        layers.len()
+    );
+    Ok(VulnerabilityReport {
+        image: reference,
+        scanner,
+        layers,
+        unattributed,
+        total,
+        warnings,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn scan_image_vulnerabilities(
+    window: tauri::Window,
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    options: Option<VulnerabilityScanOptions>,
+) -> Result<VulnerabilityReport, LayersError> {
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let result =
+        scan_image_vulnerabilities_task(&window, &task, &session, options.unwrap_or_default())
+            .await;
+    finish_task(&window, &tasks, &task);
+    result.map_err(|e| task.error(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distro(id: &str, version_id: &str) -> Distro {
+        Distro {
+            id: id.to_string(),
+            version_id: Some(version_id.to_string()),
+            pretty_name: None,
+        }
+    }
+
+    fn affected(value: serde_json::Value) -> OsvAffected {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn dpkg_versions_order_like_dpkg() {
+        for (lower, higher) in [
+            ("1.0~rc1", "1.0"),
+            ("1.0", "1.0a"),
+            ("1.0a", "1.0+"),
+            ("1.2-3", "1.2-10"),
+            ("2.0", "1:0.9"),
+            ("3.0.2-0ubuntu1.10", "3.0.2-0ubuntu1.12"),
+            ("1.1.1n-0+deb11u4", "1.1.1n-0+deb11u5"),
+            ("9", "10"),
+        ] {
+            assert_eq!(
+                compare_dpkg(lower, higher),
+                Ordering::Less,
+                "{} < {}",
+                lower,
+                higher
+            );
+            assert_eq!(
+                compare_dpkg(higher, lower),
+                Ordering::Greater,
+                "{} > {}",
+                higher,
+                lower
+            );
+        }
+        assert_eq!(compare_dpkg("1.0-1", "1.00-1"), Ordering::Equal);
+        assert_eq!(compare_dpkg("0:1.0", "1.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn segment_versions_order_numerically() {
+        for (lower, higher) in [
+            ("1.9", "1.10"),
+            ("1.0rc1", "1.0"),
+            ("1.0", "1.0.1"),
+            ("1.2.3~beta", "1.2.3"),
+            ("2.31-r0", "2.31-r1"),
+            ("1.0", "1:0.1"),
+        ] {
+            assert_eq!(
+                compare_segments(lower, higher),
+                Ordering::Less,
+                "{} < {}",
+                lower,
+                higher
+            );
+            assert_eq!(
+                compare_segments(higher, lower),
+                Ordering::Greater,
+                "{} > {}",
+                higher,
+                lower
+            );
+        }
+        assert_eq!(compare_segments("v1.2.3", "1.2.3"), Ordering::Equal);
+        assert_eq!(compare_segments("1.2.3+build.5", "1.2.3"), Ordering::Equal);
+    }
+
+    #[test]
+    fn ranges_with_a_fix_report_it() {
+        let openssl = affected(serde_json::json!({
+            "ranges": [{ "type": "ECOSYSTEM", "events": [
+                { "introduced": "0" }, { "fixed": "3.0.11-1~deb12u2" }
+            ] }]
+        }));
+        assert_eq!(
+            affected_by(&openssl, Ecosystem::Deb, "3.0.11-1~deb12u1"),
+            Some(Some("3.0.11-1~deb12u2".to_string()))
+        );
+        assert_eq!(
+            affected_by(&openssl, Ecosystem::Deb, "3.0.11-1~deb12u2"),
+            None
+        );
+        assert_eq!(affected_by(&openssl, Ecosystem::Deb, "3.0.13-1"), None);
+    }
+
+    #[test]
+    fn ranges_cover_only_their_versions() {
+        let library = affected(serde_json::json!({
+            "ranges": [{ "type": "SEMVER", "events": [
+                { "introduced": "1.2.0" }, { "fixed": "1.2.5" },
+                { "introduced": "2.0.0" }, { "last_affected": "2.1.0" },
+                { "introduced": "3.0.0" }
+            ] }]
+        }));
+        let check = |version| affected_by(&library, Ecosystem::Npm, version);
+        assert_eq!(check("1.1.9"), None);
+        assert_eq!(check("1.2.0"), Some(Some("1.2.5".to_string())));
+        assert_eq!(check("1.2.5"), None);
+        assert_eq!(check("2.1.0"), Some(None));
+        assert_eq!(check("2.1.1"), None);
+        // No end, every later version is affected
+        assert_eq!(check("3.4.0"), Some(None));
+    }
+
+    #[test]
+    fn git_ranges_only_match_listed_versions() {
+        let library = affected(serde_json::json!({
+            "ranges": [{ "type": "GIT", "events": [
+                { "introduced": "0" }, { "fixed": "abc123" }
+            ] }],
+            "versions": ["1.4.0"]
+        }));
+        assert_eq!(affected_by(&library, Ecosystem::Pypi, "1.4.0"), Some(None));
+        assert_eq!(affected_by(&library, Ecosystem::Pypi, "1.5.0"), None);
+    }
+
+    #[test]
+    fn releases_match_the_image_distro() {
+        let bookworm = distro("debian", "12");
+        assert!(release_matches("Debian:12", Some(&bookworm)));
+        assert!(!release_matches("Debian:11", Some(&bookworm)));
+        assert!(release_matches("Debian", Some(&bookworm)));
+
+        let alpine = distro("alpine", "3.19.1");
+        assert!(release_matches("Alpine:v3.19", Some(&alpine)));
+        assert!(!release_matches("Alpine:v3.1", Some(&alpine)));
+
+        let jammy = distro("ubuntu", "22.04");
+        assert!(release_matches("Ubuntu:22.04:LTS", Some(&jammy)));
+        assert!(!release_matches("Ubuntu:20.04:LTS", Some(&jammy)));
+        // Without a release to compare, every advisory applies
+        assert!(release_matches("Ubuntu:20.04:LTS", None));
+    }
+
+    #[test]
+    fn ecosystems_and_names_follow_osv() {
+        assert_eq!(
+            osv_ecosystem(Ecosystem::Deb, Some(&distro("ubuntu", "22.04"))),
+            Some("Ubuntu")
+        );
+        assert_eq!(osv_ecosystem(Ecosystem::Deb, None), Some("Debian"));
+        assert_eq!(
+            osv_ecosystem(Ecosystem::Apk, Some(&distro("wolfi", "20230201"))),
+            Some("Wolfi")
+        );
+        assert_eq!(
+            osv_ecosystem(Ecosystem::Rpm, Some(&distro("fedora", "39"))),
+            None
+        );
+
+        let component = SbomComponent {
+            name: "libssl3".to_string(),
+            version: "3.0.11-1".to_string(),
+            ecosystem: Ecosystem::Deb,
+            purl: String::new(),
+            license: None,
+            source: Some("openssl".to_string()),
+            path: "/var/lib/dpkg/status".to_string(),
+        };
+        assert_eq!(osv_name(&component), "openssl");
+        let component = SbomComponent {
+            name: "Zope.Interface".to_string(),
+            ecosystem: Ecosystem::Pypi,
+            source: None,
+            ..component
+        };
+        assert_eq!(osv_name(&component), "zope-interface");
+        assert_eq!(osv_package_name("PyPI", "zope_interface"), "zope-interface");
+        assert_eq!(osv_package_name("npm", "Left_Pad"), "Left_Pad");
+    }
+
+    #[test]
+    fn severities_are_read_from_any_specific_field() {
+        assert_eq!(parse_severity("MODERATE"), Some(Severity::Medium));
+        assert_eq!(parse_severity("unimportant"), Some(Severity::Low));
+        assert_eq!(parse_severity("unknown"), None);
+
+        let entry: OsvEntry = serde_json::from_value(serde_json::json!({
+            "id": "DSA-1",
+            "database_specific": { "severity": "low" }
+        }))
+        .unwrap();
+        let urgent = affected(serde_json::json!({ "ecosystem_specific": { "urgency": "high" } }));
+        assert_eq!(osv_severity(&entry, &urgent), Some(Severity::High));
+        let plain = affected(serde_json::json!({}));
+        assert_eq!(osv_severity(&entry, &plain), Some(Severity::Low));
+    }
+}
@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Component, Path};
+use tracing::info;
+
+use crate::error::LayersError;
+use crate::findings::{SecurityFinding, Severity};
+use crate::layer_tar_path;
+use crate::session::SessionState;
+use crate::tasks::TaskRegistry;
+
+// Index in this table is the capability bit number from linux/capability.h
+const CAPABILITY_NAMES: [&str; 41] = [
+    "cap_chown",
+    "cap_dac_override",
+    "cap_dac_read_search",
+    "cap_fowner",
+    "cap_fsetid",
+    "cap_kill",
+    "cap_setgid",
+    "cap_setuid",
+    "cap_setpcap",
+    "cap_linux_immutable",
+    "cap_net_bind_service",
+    "cap_net_broadcast",
+    "cap_net_admin",
+    "cap_net_raw",
+    "cap_ipc_lock",
+    "cap_ipc_owner",
+    "cap_sys_module",
+    "cap_sys_rawio",
+    "cap_sys_chroot",
+    "cap_sys_ptrace",
+    "cap_sys_pacct",
+    "cap_sys_admin",
+    "cap_sys_boot",
+    "cap_sys_nice",
+    "cap_sys_resource",
+    "cap_sys_time",
+    "cap_sys_tty_config",
+    "cap_mknod",
+    "cap_lease",
+    "cap_audit_write",
+    "cap_audit_control",
+    "cap_setfcap",
+    "cap_mac_override",
+    "cap_mac_admin",
+    "cap_syslog",
+    "cap_wake_alarm",
+    "cap_block_suspend",
+    "cap_audit_read",
+    "cap_perfmon",
+    "cap_bpf",
+    "cap_checkpoint_restore",
+];
+
+// Capabilities that effectively grant root when set on a file
+const DANGEROUS_CAPABILITIES: [&str; 12] = [
+    "cap_sys_admin",
+    "cap_sys_module",
+    "cap_sys_ptrace",
+    "cap_sys_rawio",
+    "cap_dac_override",
+    "cap_dac_read_search",
+    "cap_setuid",
+    "cap_setgid",
+    "cap_setfcap",
+    "cap_chown",
+    "cap_fowner",
+    "cap_bpf",
+];
+
+// Binding low ports is the one capability images commonly ship on purpose
+const DEFAULT_ALLOWED_CAPABILITIES: [&str; 1] = ["cap_net_bind_service"];
+
+const XATTR_PREFIX: &str = "SCHILY.xattr.";
+const CAPABILITY_XATTR: &str = "security.capability";
+const SELINUX_XATTR: &str = "security.selinux";
+// GNU tar stores the SELinux context under its own key when run with --selinux
+const GNU_SELINUX_KEY: &str = "RHT.security.selinux";
+
+const VFS_CAP_REVISION_MASK: u32 = 0xFF00_0000;
+const VFS_CAP_REVISION_1: u32 = 0x0100_0000;
+const VFS_CAP_REVISION_3: u32 = 0x0300_0000;
+const VFS_CAP_FLAGS_EFFECTIVE: u32 = 0x0000_0001;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileCapabilities {
+    permitted: Vec<String>,
+    inheritable: Vec<String>,
+    effective: bool,
+    // Only set for namespaced (v3) capabilities
+    root_uid: Option<u32>,
+    // getcap style rendering, e.g. "cap_net_bind_service=ep"
+    display: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FileAttributes {
+    xattrs: BTreeMap<String, String>,
+    selinux_label: Option<String>,
+    capabilities: Option<FileCapabilities>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttributedFile {
+    path: String,
+    attributes: FileAttributes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttributeScan {
+    layer_id: String,
+    files: Vec<AttributedFile>,
+    findings: Vec<SecurityFinding>,
+}
+
+fn capability_names(mask: u64) -> Vec<String> {
+    CAPABILITY_NAMES
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| mask & (1u64 << bit) != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// Decode a struct vfs_cap_data / vfs_ns_cap_data xattr value
+fn parse_capabilities(data: &[u8]) -> Option<FileCapabilities> {
+    let magic = read_u32(data, 0)?;
+    let revision = magic & VFS_CAP_REVISION_MASK;
+
+    let (permitted, inheritable) = if revision == VFS_CAP_REVISION_1 {
+        (read_u32(data, 4)? as u64, read_u32(data, 8)? as u64)
+    } else {
+        // v2 and v3 split each set into a low and high 32 bit word
+        let permitted = read_u32(data, 4)? as u64 | (read_u32(data, 12)? as u64) << 32;
+        let inheritable = read_u32(data, 8)? as u64 | (read_u32(data, 16)? as u64) << 32;
+        (permitted, inheritable)
+    };
+    let root_uid = if revision == VFS_CAP_REVISION_3 {
+        read_u32(data, 20)
+    } else {
+        None
+    };
+
+    let effective = magic & VFS_CAP_FLAGS_EFFECTIVE != 0;
+    let permitted = capability_names(permitted);
+    let inheritable = capability_names(inheritable);
+
+    let mut parts = Vec::new();
+    if !permitted.is_empty() {
+        let flags = if effective { "ep" } else { "p" };
+        parts.push(format!("{}={}", permitted.join(","), flags));
+    }
+    if !inheritable.is_empty() {
+        parts.push(format!("{}+i", inheritable.join(",")));
+    }
+
+    Some(FileCapabilities {
+        display: parts.join(" "),
+        permitted,
+        inheritable,
+        effective,
+        root_uid,
+    })
+}
+
+// Labels and most xattrs are text, anything else is shown as hex
+fn display_xattr_value(value: &[u8]) -> String {
+    let value = value.strip_suffix(&[0]).unwrap_or(value);
+    match std::str::from_utf8(value) {
+        Ok(text) if text.chars().all(|c| !c.is_control()) => text.to_string(),
+        _ => value.iter().map(|b| format!("{:02x}", b)).collect(),
+    }
+}
+
+// Read the PAX extended header of a tar entry, returns None when the entry
+// carries no attributes worth showing
+pub(crate) fn read_entry_attributes<R: Read>(
+    entry: &mut tar::Entry<R>,
+) -> io::Result<Option<FileAttributes>> {
+    let Some(extensions) = entry.pax_extensions()? else {
+        return Ok(None);
+    };
+
+    let mut attributes = FileAttributes::default();
+    for extension in extensions {
+        let extension = extension?;
+        let Ok(key) = extension.key() else {
+            continue;
+        };
+        let value = extension.value_bytes();
+
+        if key == GNU_SELINUX_KEY {
+            attributes.selinux_label = Some(display_xattr_value(value));
+            continue;
+        }
+
+        let Some(name) = key.strip_prefix(XATTR_PREFIX) else {
+            continue;
+        };
+        match name {
+            CAPABILITY_XATTR => attributes.capabilities = parse_capabilities(value),
+            SELINUX_XATTR => attributes.selinux_label = Some(display_xattr_value(value)),
+            _ => {}
+        }
+        attributes
+            .xattrs
+            .insert(name.to_string(), display_xattr_value(value));
+    }
+
+    if attributes.xattrs.is_empty() && attributes.selinux_label.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(attributes))
+}
+
+fn capability_findings(
+    path: &str,
+    capabilities: &FileCapabilities,
+    allowed: &[String],
+    layer_id: &str,
+) -> Vec<SecurityFinding> {
+    capabilities
+        .permitted
+        .iter()
+        .chain(capabilities.inheritable.iter())
+        .filter(|cap| !allowed.contains(cap))
+        .map(|cap| {
+            let severity = if DANGEROUS_CAPABILITIES.contains(&cap.as_str()) {
+                Severity::High
+            } else {
+                Severity::Medium
+            };
+            SecurityFinding {
+                rule_id: "unexpected-capability".to_string(),
+                severity,
+                title: format!("{} grants {}", path, cap),
+                description: format!(
+                    "{} has file capabilities \"{}\". Any user who can execute it gains {}, \
+                     drop it with setcap -r unless the binary needs it.",
+                    path, capabilities.display, cap
+                ),
+                path: Some(path.to_string()),
+                layer_id: Some(layer_id.to_string()),
+            }
+        })
+        .collect()
+}
+
+fn scan_tar(tar_path: &Path, layer_id: &str, allowed: &[String]) -> Result<AttributeScan, String> {
+    let file = File::open(tar_path)
+        .map_err(|e| format!("Failed to open tar file {:?}: {}", tar_path, e))?;
+    let mut archive = tar::Archive::new(file);
+
+    let mut files = Vec::new();
+    let mut findings = Vec::new();
+
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read tar file {:?}: {}", tar_path, e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {}", e))?;
+        let Some(attributes) = read_entry_attributes(&mut entry)
+            .map_err(|e| format!("Failed to read PAX header: {}", e))?
+        else {
+            continue;
+        };
+
+        let path = entry
+            .path()
+            .map_err(|e| format!("Failed to read tar entry path: {}", e))?
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let path = format!("/{}", path);
+
+        if let Some(capabilities) = &attributes.capabilities {
+            findings.extend(capability_findings(&path, capabilities, allowed, layer_id));
+        }
+        files.push(AttributedFile { path, attributes });
+    }
+
+    Ok(AttributeScan {
+        layer_id: layer_id.to_string(),
+        files,
+        findings,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn scan_layer_attributes(
+    tasks: tauri::State<'_, TaskRegistry>,
+    session: tauri::State<'_, SessionState>,
+    session_id: Option<String>,
+    layer_id: String,
+    allowed_capabilities: Option<Vec<String>>,
+) -> Result<AttributeScan, LayersError> {
+    info!("Scanning extended attributes of layer {}", layer_id);
+
+    let session = session.get(session_id.as_deref())?;
+    let task = tasks.start();
+    let tar_path = layer_tar_path(&task, &session, &layer_id);
+    tasks.finish(task.id);
+    let tar_path = tar_path?;
+
+    let allowed = allowed_capabilities.unwrap_or_else(|| {
+        DEFAULT_ALLOWED_CAPABILITIES
+            .iter()
+            .map(|c| c.to_string())
+            .collect()
+    });
+    let scan = scan_tar(&tar_path, &layer_id, &allowed)?;
+
+    info!(
+        "Found {} files with extended attributes, {} findings",
+        scan.files.len(),
+        scan.findings.len()
+    );
+    Ok(scan)
+}
@@ -1,6 +1,6 @@
 import { useEffect, useCallback, useState } from "react";
-import { Loader2, FileIcon, DiffIcon } from "lucide-react";
-import type { DockerfileAnalysis } from "./utils/types";
+import { Loader2, FileIcon, DiffIcon, InfoIcon } from "lucide-react";
+import type { DeepLinkTarget, DockerfileAnalysis } from "./utils/types";
 import "./App.css";
 import useLayersStore from "./store/useLayersStore";
 import type { TreeNode } from "./components/TreeView";
@@ -16,9 +16,18 @@ import {
 } from "@/components/ui/resizable";
 import { LayerFiles } from "./components/LayerFiles";
 import FileViewer from "./components/FileViewer";
-import { Toaster } from "sonner";
+import { Toaster, toast } from "sonner";
 import { ComparisonView } from "./components/ComparisonView";
+import { TagMovedBanner } from "./components/TagMovedBanner";
+import { ImageDetailsSheet } from "./components/ImageDetailsSheet";
+import { PullProgressPanel } from "./components/PullProgressPanel";
+import { ImageHealthCard } from "./components/ImageHealthCard";
 import { Dock, DockIcon } from "./components/magicui/dock";
+import { useClipboardImport } from "./hooks/useClipboardImport";
+import { useDeepLinks } from "./hooks/useDeepLinks";
+import { invoke } from "@tauri-apps/api/core";
+import { ask } from "@tauri-apps/plugin-dialog";
+import { errorMessage } from "@/lib/utils";
 
 // Fallback Dockerfile content in case resource loading fails
 const FALLBACK_DOCKERFILE = `# Using Alpine Linux as the base image (~5MB vs ~72MB for Ubuntu)
@@ -68,11 +77,25 @@ function App() {
 		setAnalysis,
 		darkMode,
 		selectedFile,
+		setSelectedFile,
 		selectedFileContent,
 		setSelectedFileContent,
 		isComparisonMode,
 		toggleComparisonMode,
+		fetchAvailableImages,
+		selectImageAndProcessLayers,
+		selectedImageId,
 	} = useLayersStore();
+	const [isImageDetailsOpen, setIsImageDetailsOpen] = useState(false);
+	const [linkedFindingAnchor, setLinkedFindingAnchor] = useState<
+		string | null
+	>(null);
+	const [isHealthCardOpen, setIsHealthCardOpen] = useState(false);
+
+	// Every opened image starts with its health summary
+	useEffect(() => {
+		setIsHealthCardOpen(dockerImage !== null);
+	}, [dockerImage]);
 
 	// Load sample Dockerfile on component mount
 	const loadSampleDockerfile = useCallback(async () => {
@@ -116,34 +139,78 @@ function App() {
 		loadSampleDockerfile();
 	}, [loadSampleDockerfile]);
 
-	// Function to analyze the Dockerfile content
+	// Size estimates, cache stability and stages from the backend analyzer
 	const handleAnalyzeDockerfile = useCallback(
-		(content: string) => {
-			// Here you would typically call a backend service to analyze the Dockerfile
-			// For now, we'll just set a mock analysis
-			console.log("Analyzing Dockerfile:", content);
-			// Mock analysis result based on the actual type
-			const mockAnalysis: DockerfileAnalysis = {
-				layerImpact: [
-					{
-						lineNumber: 1,
-						instruction: "FROM",
-						impact: "Base image selection",
-					},
-				],
-				optimizationSuggestions: [
-					{
-						title: "Use multi-stage builds",
-						description:
-							"Consider using multi-stage builds to reduce final image size.",
-					},
-				],
-			};
-			setAnalysis(mockAnalysis);
+		async (content: string) => {
+			try {
+				setAnalysis(
+					await invoke<DockerfileAnalysis>("analyze_dockerfile", { content }),
+				);
+			} catch (error) {
+				console.error("Failed to analyze Dockerfile:", error);
+				setAnalysis(null);
+				toast.error(errorMessage(error, "Failed to analyze Dockerfile"));
+			}
 		},
 		[setAnalysis],
 	);
 
+	// Show the pasted Dockerfile instead of the open file, with the analyzer's
+	// results below it
+	const handleAnalyzeFromClipboard = useCallback(
+		async (content: string) => {
+			setSelectedFile(null);
+			setDockerfileContent(content);
+			await handleAnalyzeDockerfile(content);
+		},
+		[setSelectedFile, setDockerfileContent, handleAnalyzeDockerfile],
+	);
+
+	// Pulls the image first if it isn't available locally
+	const handleInspectFromClipboard = useCallback(
+		async (reference: string) => {
+			try {
+				await invoke("inspect_docker_image", { imageName: reference });
+				await fetchAvailableImages();
+				await selectImageAndProcessLayers(reference);
+			} catch (error) {
+				console.error("Failed to inspect image from clipboard:", error);
+				setError(`Failed to inspect ${reference}`);
+			}
+		},
+		[fetchAvailableImages, selectImageAndProcessLayers, setError],
+	);
+
+	useClipboardImport(handleAnalyzeFromClipboard, handleInspectFromClipboard);
+
+	// A link a teammate shared, open the image and say what it points at
+	const handleOpenDeepLink = useCallback(
+		async (target: DeepLinkTarget) => {
+			// Opening pulls the image if it isn't local, links come from anywhere
+			const confirmed = await ask(
+				`Open ${target.image}? It will be pulled if it isn't available locally.`,
+				{ title: "Open shared link", kind: "info" },
+			);
+			if (!confirmed) {
+				return;
+			}
+			await handleInspectFromClipboard(target.image);
+			if (target.kind === "finding") {
+				setLinkedFindingAnchor(target.anchor);
+				setIsImageDetailsOpen(true);
+			}
+			toast(`Opened link to ${target.image}`, {
+				description:
+					target.kind === "path"
+						? target.path
+						: `Finding ${target.anchor.replace("finding-", "")}`,
+			});
+		},
+		[handleInspectFromClipboard],
+	);
+
+	useDeepLinks(handleOpenDeepLink);
+
 	// Generate tree view data from layers if available
 	const treeViewData: TreeNode[] = [];
 	if (dockerImage?.layers) {
@@ -185,6 +252,7 @@ function App() {
 				<div className="flex-1 flex overflow-hidden">
 					<main className="flex-1 flex flex-col overflow-hidden relative">
 						<>
+							<TagMovedBanner />
 							{isComparisonMode ? (
 								<ResizablePanelGroup
 									direction="horizontal"
@@ -257,6 +325,16 @@ function App() {
 								</ResizablePanelGroup>
 							)}
 
+							<PullProgressPanel />
+
+							{isHealthCardOpen && dockerImage && (
+								<ImageHealthCard
+									key={selectedImageId ?? dockerImage.name}
+									imageName={dockerImage.name}
+									onClose={() => setIsHealthCardOpen(false)}
+								/>
+							)}
+
 							{/* Floating dock at the bottom */}
 							<div className="absolute bottom-4 left-1/2 transform -translate-x-1/2 z-10">
 								<Dock
@@ -277,6 +355,14 @@ function App() {
 									>
 										<DiffIcon className="h-4 w-4 text-foreground" />
 									</DockIcon>
+									{dockerImage && (
+										<DockIcon
+											className="bg-background/80 border border-border"
+											onClick={() => setIsImageDetailsOpen(true)}
+										>
+											<InfoIcon className="h-4 w-4 text-foreground" />
+										</DockIcon>
+									)}
 								</Dock>
 							</div>
 						</>
@@ -284,6 +370,14 @@ function App() {
 				</div>
 				<StatusBar />
 			</div>
+			<ImageDetailsSheet
+				open={isImageDetailsOpen}
+				onOpenChange={(open) => {
+					setIsImageDetailsOpen(open);
+					if (!open) setLinkedFindingAnchor(null);
+				}}
+				focusedAnchor={linkedFindingAnchor}
+			/>
 			<Toaster />
 		</SidebarProvider>
 	);
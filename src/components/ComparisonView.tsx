@@ -18,13 +18,52 @@ import {
 	PlusIcon,
 	MinusIcon,
 	PencilIcon,
+	GitCompare,
 } from "lucide-react";
 import { toast } from "sonner";
 import useLayersStore from "@/store/useLayersStore";
+import { errorMessage } from "@/lib/utils";
+import type { ComparisonPreset } from "@/utils/types";
+
+const PRESETS: { preset: ComparisonPreset; label: string }[] = [
+	{ preset: "base_vs_final", label: "Base image vs this image" },
+	{ preset: "previous_tag_vs_current", label: "Previous tag vs current" },
+];
+
+// One-click comparisons of the opened image against a related one
+function PresetButtons() {
+	const { compareWithPreset, dockerImage, isComparing } = useLayersStore();
+
+	const runPreset = async (preset: ComparisonPreset) => {
+		try {
+			await compareWithPreset(preset);
+		} catch (error) {
+			toast.error(errorMessage(error, "Failed to compare images"));
+		}
+	};
+
+	return (
+		<div className="flex gap-2">
+			{PRESETS.map(({ preset, label }) => (
+				<Button
+					key={preset}
+					variant="outline"
+					size="sm"
+					onClick={() => runPreset(preset)}
+					disabled={!dockerImage || isComparing}
+				>
+					<GitCompare className="h-4 w-4 mr-2" />
+					{label}
+				</Button>
+			))}
+		</div>
+	);
+}
 
 export function ComparisonView() {
 	const {
 		comparisonResult,
+		comparisonPair,
 		isComparing,
 		selectedLayersForComparison,
 		dockerImage,
@@ -109,7 +148,7 @@ export function ComparisonView() {
 			<div className="h-full flex items-center justify-center">
 				<div className="flex flex-col items-center text-center p-8">
 					<Loader2 className="h-8 w-8 text-primary animate-spin mb-4" />
-					<h3 className="text-lg font-medium">Comparing layers...</h3>
+					<h3 className="text-lg font-medium">Comparing...</h3>
 					<p className="text-sm text-muted-foreground mt-2">
 						This may take a moment depending on the size of the layers
 					</p>
@@ -125,47 +164,59 @@ export function ComparisonView() {
 					<h3 className="text-lg font-medium mb-2">
 						No comparison results yet
 					</h3>
-					<p className="text-sm text-muted-foreground">
+					<p className="text-sm text-muted-foreground mb-4">
 						Select two layers from the sidebar and click "Compare" to see the
-						differences between them.
+						differences between them, or compare the whole image with a
+						related one.
 					</p>
+					<PresetButtons />
 				</div>
 			</div>
 		);
 	}
 
 	const { added, removed, modified, unchanged } = comparisonResult;
-	const layer1 = selectedLayersForComparison[0]
-		? getLayerName(selectedLayersForComparison[0])
-		: "Layer 1";
-	const layer2 = selectedLayersForComparison[1]
-		? getLayerName(selectedLayersForComparison[1])
-		: "Layer 2";
+	const layer1 = comparisonPair
+		? comparisonPair.label_a
+		: selectedLayersForComparison[0]
+			? getLayerName(selectedLayersForComparison[0])
+			: "Layer 1";
+	const layer2 = comparisonPair
+		? comparisonPair.label_b
+		: selectedLayersForComparison[1]
+			? getLayerName(selectedLayersForComparison[1])
+			: "Layer 2";
 
 	return (
 		<div className="h-full flex flex-col p-4">
 			<div className="flex items-center justify-between mb-4">
 				<div>
-					<h2 className="text-xl font-bold">Layer Comparison</h2>
+					<h2 className="text-xl font-bold">
+						{comparisonPair ? "Image Comparison" : "Layer Comparison"}
+					</h2>
 					<div className="text-sm text-muted-foreground mt-1">
 						Comparing {layer1} with {layer2}
+						{comparisonPair && ` (found by ${comparisonPair.resolved_by})`}
 					</div>
 				</div>
-				<Button
-					variant="outline"
-					size="sm"
-					onClick={() => {
-						const summary = `Comparison between ${layer1} and ${layer2}:
+				<div className="flex gap-2">
+					<PresetButtons />
+					<Button
+						variant="outline"
+						size="sm"
+						onClick={() => {
+							const summary = `Comparison between ${layer1} and ${layer2}:
 - Added: ${added.length} files
 - Removed: ${removed.length} files
 - Modified: ${modified.length} files
 - Unchanged: ${unchanged.length} files`;
-						copyToClipboard(summary);
-					}}
-				>
-					<Copy className="h-4 w-4 mr-2" />
-					Copy Summary
-				</Button>
+							copyToClipboard(summary);
+						}}
+					>
+						<Copy className="h-4 w-4 mr-2" />
+						Copy Summary
+					</Button>
+				</div>
 			</div>
 
 			<div className="grid grid-cols-4 gap-4 mb-4">
@@ -0,0 +1,112 @@
+import { useState, useEffect, useCallback, type FC } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Loader2 } from "lucide-react";
+import { Button } from "@/components/ui/button";
+import { errorMessage } from "@/lib/utils";
+import type {
+	FilePreview as FilePreviewPage,
+	HexRow,
+	HighlightedSpan,
+} from "../utils/types";
+
+type FilePreviewProps = {
+	// Path of the extracted file, as passed to read_file_range
+	path: string;
+};
+
+// Read-only preview of any file: highlighted text, or a hex dump for binary
+// files. Large files are loaded a page at a time.
+const FilePreview: FC<FilePreviewProps> = ({ path }) => {
+	const [pages, setPages] = useState<FilePreviewPage[]>([]);
+	const [isLoading, setIsLoading] = useState(false);
+	const [error, setError] = useState<string | null>(null);
+
+	const loadPage = useCallback(
+		async (offset: number) => {
+			setIsLoading(true);
+			try {
+				const page = await invoke<FilePreviewPage>("read_file_preview", {
+					path,
+					offset,
+				});
+				setPages((pages) => (offset === 0 ? [page] : [...pages, page]));
+				setError(null);
+			} catch (error) {
+				setError(errorMessage(error, "Failed to read file"));
+			} finally {
+				setIsLoading(false);
+			}
+		},
+		[path],
+	);
+
+	useEffect(() => {
+		setPages([]);
+		loadPage(0);
+	}, [loadPage]);
+
+	const last = pages[pages.length - 1];
+	const lines = pages.flatMap((page) =>
+		page.content.kind === "text" ? page.content.lines : [],
+	);
+	const rows = pages.flatMap((page) =>
+		page.content.kind === "hex" ? page.content.rows : [],
+	);
+
+	if (error) {
+		return <div className="p-4 text-sm text-red-500">{error}</div>;
+	}
+
+	return (
+		<div className="h-full w-full overflow-auto font-fira-code text-sm">
+			{last?.content.kind === "hex" ? (
+				<table className="m-3">
+					<tbody>
+						{rows.map((row: HexRow) => (
+							<tr key={row.offset}>
+								<td className="pr-4 text-gray-500">
+									{row.offset.toString(16).padStart(8, "0")}
+								</td>
+								<td className="pr-4 whitespace-pre">{row.hex}</td>
+								<td className="whitespace-pre text-gray-500">{row.ascii}</td>
+							</tr>
+						))}
+					</tbody>
+				</table>
+			) : (
+				<pre className="m-3 leading-6 bg-[#2b303b] rounded-md p-3">
+					{lines.map((line, i) => (
+						<div key={i}>
+							{line.spans.map((span: HighlightedSpan, j) => (
+								<span
+									key={j}
+									style={{
+										color: span.color,
+										fontWeight: span.bold ? "bold" : undefined,
+										fontStyle: span.italic ? "italic" : undefined,
+									}}
+								>
+									{span.text}
+								</span>
+							))}
+							{line.spans.length === 0 && "\n"}
+						</div>
+					))}
+				</pre>
+			)}
+			{isLoading && <Loader2 className="m-3 h-4 w-4 animate-spin" />}
+			{last && !last.eof && !isLoading && (
+				<Button
+					variant="outline"
+					size="sm"
+					className="m-3"
+					onClick={() => loadPage(last.offset + last.length)}
+				>
+					Load more
+				</Button>
+			)}
+		</div>
+	);
+};
+
+export default FilePreview;
@@ -4,9 +4,17 @@ import {
 	useCallback,
 	type FC,
 	type ChangeEvent,
+	type SyntheticEvent,
 	useRef,
 } from "react";
-import type { DockerfileAnalysis, FileItem } from "../utils/types";
+import type {
+	DeepLink,
+	DockerfileAnalysis,
+	FileItem,
+	InstructionLayerMapping,
+} from "../utils/types";
+import { invoke } from "@tauri-apps/api/core";
+import { toast } from "sonner";
 import { Button } from "@/components/ui/button";
 import {
 	Loader2,
@@ -15,8 +23,13 @@ import {
 	Edit,
 	Check,
 	AlertTriangle,
+	Link,
+	Gauge,
 } from "lucide-react";
 import { cn } from "@/lib/utils";
+import useLayersStore from "@/store/useLayersStore";
+import FilePreview from "./FilePreview";
+import LargeFileView from "./LargeFileView";
 
 // Define tooltips for common Dockerfile commands
 const DOCKERFILE_TOOLTIPS: Record<string, string> = {
@@ -66,6 +79,7 @@ const editorStyles = {
 	commentLine: "text-gray-500",
 	instructionLine: "text-blue-600 dark:text-blue-400 font-semibold",
 	lineNumber: "h-6 text-xs text-right pr-2 text-gray-400 dark:text-gray-500",
+	mappedLine: "bg-blue-100 dark:bg-blue-900/40",
 };
 
 // Determine file type based on file name or explicit type
@@ -87,6 +101,132 @@ const getFileType = (
 	return "text";
 };
 
+// The final stage instruction that spans a 1-based line, instructions
+// continued with a backslash start at their first line
+const mappingAtLine = (
+	mappings: InstructionLayerMapping[],
+	line: number,
+): InstructionLayerMapping | null => {
+	let found: InstructionLayerMapping | null = null;
+	for (const mapping of mappings) {
+		if (mapping.line_number > line) break;
+		found = mapping;
+	}
+	return found;
+};
+
+// Permissions, owner and PAX attributes of the file, from the tar header
+const FileMetadataBar: FC<{ file: FileItem }> = ({ file }) => {
+	const attributes = file.attributes;
+	// SELinux labels are listed as security.selinux too
+	const xattrs = Object.entries(attributes?.xattrs ?? {}).filter(
+		([name]) => name !== "security.capability" && name !== "security.selinux",
+	);
+	if (!file.permissions && !file.owner && !attributes) return null;
+
+	return (
+		<div className="px-2 py-1 border-b border-gray-200 dark:border-gray-700 flex flex-wrap gap-x-4 gap-y-1 text-xs font-mono text-gray-500">
+			{file.permissions && <span>{file.permissions}</span>}
+			{file.owner && <span>{file.owner.display}</span>}
+			{attributes?.capabilities && (
+				<span
+					className="text-amber-600 dark:text-amber-400"
+					title={`Permitted: ${attributes.capabilities.permitted.join(", ") || "none"}`}
+				>
+					{attributes.capabilities.display}
+				</span>
+			)}
+			{attributes?.selinux_label && (
+				<span title="SELinux label">{attributes.selinux_label}</span>
+			)}
+			{xattrs.map(([name, value]) => (
+				<span key={name} className="truncate max-w-full" title={value}>
+					{name}={value}
+				</span>
+			))}
+		</div>
+	);
+};
+
+// Estimated sizes, cache stability and stages from analyze_dockerfile
+const DockerfileAnalysisPanel: FC<{ analysis: DockerfileAnalysis }> = ({
+	analysis,
+}) => {
+	const stabilityByLine = new Map(
+		analysis.cacheStability.map((entry) => [entry.lineNumber, entry]),
+	);
+
+	return (
+		<div className="max-h-64 overflow-y-auto border-t border-gray-200 dark:border-gray-700 p-2 text-xs space-y-3">
+			<table className="w-full">
+				<thead className="text-left text-gray-500">
+					<tr>
+						<th className="font-normal">Line</th>
+						<th className="font-normal">Instruction</th>
+						<th className="font-normal">Impact</th>
+						<th className="font-normal text-right">Estimate</th>
+						<th className="font-normal text-right">Cache</th>
+					</tr>
+				</thead>
+				<tbody className="font-mono">
+					{analysis.layerImpact.map((item) => {
+						const stability = stabilityByLine.get(item.lineNumber);
+						return (
+							<tr key={item.lineNumber}>
+								<td className="text-gray-400">{item.lineNumber}</td>
+								<td>{item.instruction}</td>
+								<td className="font-sans">{item.impact}</td>
+								<td
+									className="text-right"
+									title={item.predictionBasis.join("\n") || undefined}
+								>
+									{item.predictedMb === null
+										? "?"
+										: `~${item.predictedMb.toFixed(1)} MB`}
+								</td>
+								<td
+									className="text-right"
+									title={stability?.reasons.join("\n") || undefined}
+								>
+									{stability ? `${Math.round(stability.score * 100)}%` : ""}
+								</td>
+							</tr>
+						);
+					})}
+				</tbody>
+			</table>
+
+			{analysis.stages.length > 1 && (
+				<ul className="space-y-1">
+					{analysis.stages.map((stage) => (
+						<li key={stage.index}>
+							<span className="font-medium">
+								Stage {stage.index}
+								{stage.name && ` (${stage.name})`}
+							</span>{" "}
+							<span className="text-gray-500">
+								from {stage.base}, line {stage.lineNumber}
+								{!stage.inFinalImage && ", builder only"}
+							</span>
+						</li>
+					))}
+				</ul>
+			)}
+
+			{analysis.optimizationSuggestions.length > 0 && (
+				<ul className="space-y-1">
+					{analysis.optimizationSuggestions.map((suggestion) => (
+						<li key={suggestion.title}>
+							<span className="font-medium">{suggestion.title}:</span>{" "}
+							<span className="text-gray-500">{suggestion.description}</span>
+						</li>
+					))}
+				</ul>
+			)}
+		</div>
+	);
+};
+
 // Check if content is an error message from the backend
 const isBinaryFileError = (content: string): boolean => {
 	const binaryErrorPatterns = [
@@ -117,6 +257,48 @@ const FileViewer: FC<FileViewerProps> = ({
 
 	const fileType = getFileType(file?.name, explicitFileType);
 	const isBinaryError = isBinaryFileError(content);
+	const isTooLarge = content.includes("File is too large to display");
+
+	const { dockerImage, setHighlightedLayerId } = useLayersStore();
+	const [layerMappings, setLayerMappings] = useState<
+		InstructionLayerMapping[]
+	>([]);
+	const [cursorLine, setCursorLine] = useState<number | null>(null);
+	const activeMapping =
+		cursorLine === null ? null : mappingAtLine(layerMappings, cursorLine);
+
+	// Match the Dockerfile against the opened image's history, after typing
+	// settles
+	useEffect(() => {
+		if (fileType !== "dockerfile" || !dockerImage || !content) {
+			setLayerMappings([]);
+			return;
+		}
+		let cancelled = false;
+		const timer = setTimeout(() => {
+			invoke<InstructionLayerMapping[]>("map_dockerfile_to_layers", {
+				content,
+			})
+				.then((mappings) => !cancelled && setLayerMappings(mappings))
+				.catch((error) => {
+					console.error("Failed to map Dockerfile to layers:", error);
+					if (!cancelled) setLayerMappings([]);
+				});
+		}, 500);
+		return () => {
+			cancelled = true;
+			clearTimeout(timer);
+		};
+	}, [fileType, dockerImage, content]);
+
+	// Highlight the layer the instruction under the cursor produced
+	useEffect(() => {
+		setHighlightedLayerId(activeMapping?.layer_id ?? null);
+	}, [activeMapping?.layer_id, setHighlightedLayerId]);
+
+	useEffect(() => {
+		return () => setHighlightedLayerId(null);
+	}, [setHighlightedLayerId]);
 
 	// Load FiraCode font
 	useEffect(() => {
@@ -147,6 +329,12 @@ const FileViewer: FC<FileViewerProps> = ({
 		onChange?.(newContent);
 	};
 
+	const handleSelect = (e: SyntheticEvent<HTMLTextAreaElement>) => {
+		if (fileType !== "dockerfile") return;
+		const { value, selectionStart } = e.currentTarget;
+		setCursorLine(value.slice(0, selectionStart).split("\n").length);
+	};
+
 	const handleSave = () => {
 		console.log("FileViewer save clicked");
 		setIsSaving(true);
@@ -164,19 +352,35 @@ const FileViewer: FC<FileViewerProps> = ({
 	};
 
 	const handleDownload = () => {
-		if (!content || !file?.name) return;
+		if (!content) return;
 
 		const blob = new Blob([content], { type: "text/plain" });
 		const url = URL.createObjectURL(blob);
 		const a = document.createElement("a");
 		a.href = url;
-		a.download = file.name.split("/").pop() || "file.txt";
+		a.download = (file?.name ?? "Dockerfile").split("/").pop() || "file.txt";
 		document.body.appendChild(a);
 		a.click();
 		document.body.removeChild(a);
 		URL.revokeObjectURL(url);
 	};
 
+	// Copy a layers:// link a teammate can open straight to this file
+	const handleCopyLink = async () => {
+		if (!file?.path) return;
+
+		try {
+			const link = await invoke<DeepLink>("create_path_link", {
+				path: file.path,
+			});
+			await navigator.clipboard.writeText(link.url);
+			toast("Link copied", { description: link.url });
+		} catch (error) {
+			console.error("Failed to copy link:", error);
+			toast.error("Failed to copy link to this file");
+		}
+	};
+
 	// Sync scroll between textarea and line numbers
 	useEffect(() => {
 		const textarea = editorRef.current;
@@ -205,6 +409,12 @@ const FileViewer: FC<FileViewerProps> = ({
 					let lineClass = editorStyles.lineNumber;
 
 					if (fileType === "dockerfile") {
+						if (
+							activeMapping?.layer_id &&
+							activeMapping.line_number === i + 1
+						) {
+							lineClass = cn(lineClass, editorStyles.mappedLine);
+						}
 						if (line.trim().startsWith("#")) {
 							lineClass = cn(lineClass, editorStyles.commentLine);
 						} else if (
@@ -232,10 +442,10 @@ const FileViewer: FC<FileViewerProps> = ({
 				})}
 			</div>
 		);
-	}, [content, fileType, isBinaryError]);
+	}, [content, fileType, isBinaryError, activeMapping]);
 
-	// If no file is selected
-	if (!file) {
+	// If no file is selected, the Dockerfile editor has none
+	if (!file && fileType !== "dockerfile") {
 		return (
 			<div className="h-full w-full flex items-center justify-center text-gray-500">
 				<p>Select a file to view its contents</p>
@@ -244,7 +454,7 @@ const FileViewer: FC<FileViewerProps> = ({
 	}
 
 	// If file is a directory
-	if (file.type === "directory" || file.file_type === "directory") {
+	if (file?.type === "directory" || file?.file_type === "directory") {
 		return (
 			<div className="h-full w-full flex items-center justify-center text-gray-500">
 				<p>This is a directory. Select a file to view its contents.</p>
@@ -256,12 +466,34 @@ const FileViewer: FC<FileViewerProps> = ({
 		<div className="h-full w-full flex flex-col">
 			<div className="p-2 border-b border-gray-200 dark:border-gray-700 bg-gray-50 dark:bg-gray-900 flex justify-between items-center">
 				<div className="font-medium truncate">
-					{file.name}
-					{file.size && (
+					{file?.name ?? "Dockerfile"}
+					{file?.size && (
 						<span className="ml-2 text-xs text-gray-500">{file.size}</span>
 					)}
+					{activeMapping?.layer_id && (
+						<span
+							className="ml-2 text-xs text-blue-600 dark:text-blue-400"
+							title={activeMapping.created_by ?? undefined}
+						>
+							Line {activeMapping.line_number} built{" "}
+							{activeMapping.layer_id.replace("_", " ")}
+							{activeMapping.size && ` (${activeMapping.size})`}
+							{activeMapping.confidence === "partial" && ", partial match"}
+						</span>
+					)}
 				</div>
 				<div className="flex gap-2">
+					{fileType === "dockerfile" && onAnalyze && (
+						<Button
+							variant="outline"
+							size="sm"
+							onClick={() => onAnalyze(content)}
+							className="flex items-center gap-1"
+						>
+							<Gauge className="h-4 w-4" />
+							Analyze
+						</Button>
+					)}
 					{!isReadOnly && !isBinaryError && (
 						<Button
 							variant="outline"
@@ -288,11 +520,30 @@ const FileViewer: FC<FileViewerProps> = ({
 							Download
 						</Button>
 					)}
+					{file?.path && (
+						<Button
+							variant="outline"
+							size="sm"
+							onClick={handleCopyLink}
+							className="flex items-center gap-1"
+						>
+							<Link className="h-4 w-4" />
+							Copy link
+						</Button>
+					)}
 				</div>
 			</div>
 
+			{file && <FileMetadataBar file={file} />}
+
 			<div className="flex-grow flex overflow-hidden">
-				{isBinaryError ? (
+				{isTooLarge && file?.path ? (
+					// Large text files are scrolled through by line instead
+					<LargeFileView path={file.exact_path ?? file.path} />
+				) : isBinaryError && file?.path ? (
+					// Binary files get the paged hex preview instead
+					<FilePreview path={file.exact_path ?? file.path} />
+				) : isBinaryError ? (
 					<div className="w-full h-full flex flex-col items-center justify-center p-6 text-center">
 						<AlertTriangle className="h-12 w-12 text-amber-500 mb-4" />
 						<h3 className="text-lg font-semibold mb-2">Cannot Display File</h3>
@@ -310,6 +561,7 @@ const FileViewer: FC<FileViewerProps> = ({
 							ref={editorRef}
 							value={content || ""}
 							onChange={handleChange}
+							onSelect={handleSelect}
 							readOnly={isReadOnly || !isEditing}
 							spellCheck={false}
 							className={editorStyles.textarea}
@@ -318,6 +570,10 @@ const FileViewer: FC<FileViewerProps> = ({
 				)}
 			</div>
 
+			{fileType === "dockerfile" && analysis && (
+				<DockerfileAnalysisPanel analysis={analysis} />
+			)}
+
 			{isEditing && !isReadOnly && !isBinaryError && (
 				<div className="p-4 border-t border-gray-200 dark:border-gray-700 bg-gray-50 dark:bg-gray-900">
 					<div className="flex justify-end">
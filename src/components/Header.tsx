@@ -5,6 +5,7 @@ import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Moon, Sun, FolderOpen, Trash2 } from "lucide-react";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "@/lib/utils";
 import useLayersStore from "@/store/useLayersStore";
 
 type HeaderProps = {
@@ -52,7 +53,9 @@ const Header: FC<HeaderProps> = ({
 			setIsLoading(false);
 		} catch (error) {
 			setIsLoading(false);
-			setError(`Failed to clean up images: ${error}`);
+			setError(
+				`Failed to clean up images: ${errorMessage(error, "Unknown error")}`,
+			);
 			console.error("Error cleaning up images:", error);
 		}
 	};
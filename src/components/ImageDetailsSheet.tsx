@@ -0,0 +1,448 @@
+import { useEffect, useRef, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Check, Copy, Link, Loader2 } from "lucide-react";
+import { toast } from "sonner";
+import { Badge } from "@/components/ui/badge";
+import { Button } from "@/components/ui/button";
+import {
+	Sheet,
+	SheetContent,
+	SheetDescription,
+	SheetHeader,
+	SheetTitle,
+} from "@/components/ui/sheet";
+import { Tabs, TabsContent, TabsList, TabsTrigger } from "@/components/ui/tabs";
+import { cn, errorMessage, formatBytes } from "@/lib/utils";
+import useLayersStore from "@/store/useLayersStore";
+import type {
+	DeepLink,
+	PullTimeReport,
+	ReportFinding,
+	RunSnippets,
+	ServiceDefinition,
+	ServiceInventory,
+	ServiceKind,
+	Severity,
+} from "@/utils/types";
+
+interface ImageDetailsSheetProps {
+	open: boolean;
+	onOpenChange: (open: boolean) => void;
+	// Anchor of a finding a deep link pointed at, shown in the findings tab
+	focusedAnchor?: string | null;
+}
+
+// Runs a command for the opened image whenever the sheet is shown, null args
+// wait until there is something to run it on
+function useImageCommand<T>(
+	command: string,
+	open: boolean,
+	args: Record<string, unknown> | null = {},
+) {
+	const selectedImageId = useLayersStore((state) => state.selectedImageId);
+	const [data, setData] = useState<T | null>(null);
+	const [error, setError] = useState<string | null>(null);
+	const [isLoading, setIsLoading] = useState(false);
+	const argsKey = JSON.stringify(args);
+
+	useEffect(() => {
+		const args = JSON.parse(argsKey);
+		if (!open || !selectedImageId || !args) return;
+		let cancelled = false;
+		setIsLoading(true);
+		setError(null);
+		invoke<T>(command, args)
+			.then((result) => !cancelled && setData(result))
+			.catch((error) => {
+				console.error(`Error running ${command}:`, error);
+				if (!cancelled) {
+					setData(null);
+					setError(errorMessage(error, `Failed to run ${command}`));
+				}
+			})
+			.finally(() => !cancelled && setIsLoading(false));
+		return () => {
+			cancelled = true;
+		};
+	}, [command, open, selectedImageId, argsKey]);
+
+	return { data, error, isLoading };
+}
+
+function TabState({
+	isLoading,
+	error,
+}: {
+	isLoading: boolean;
+	error: string | null;
+}) {
+	if (isLoading) {
+		return (
+			<div className="flex items-center justify-center py-8 text-gray-500">
+				<Loader2 className="h-6 w-6 text-blue-500 animate-spin" />
+			</div>
+		);
+	}
+	return error ? <div className="text-sm text-red-500">{error}</div> : null;
+}
+
+function CopyableSnippet({ title, text }: { title: string; text: string }) {
+	const [copied, setCopied] = useState(false);
+
+	const copy = () => {
+		navigator.clipboard
+			.writeText(text)
+			.then(() => {
+				setCopied(true);
+				setTimeout(() => setCopied(false), 2000);
+			})
+			.catch((err) => console.error("Failed to copy snippet:", err));
+	};
+
+	return (
+		<div className="space-y-2">
+			<div className="flex items-center justify-between">
+				<span className="text-sm font-medium">{title}</span>
+				<Button variant="outline" size="sm" onClick={copy}>
+					{copied ? (
+						<Check className="h-4 w-4 text-green-500" />
+					) : (
+						<Copy className="h-4 w-4" />
+					)}
+					{copied ? "Copied" : "Copy"}
+				</Button>
+			</div>
+			<pre className="text-xs font-mono bg-gray-50 dark:bg-gray-800 rounded-md p-3 overflow-auto whitespace-pre">
+				{text}
+			</pre>
+		</div>
+	);
+}
+
+function RunSnippetsTab({ open }: { open: boolean }) {
+	const { data, error, isLoading } = useImageCommand<RunSnippets>(
+		"generate_run_snippets",
+		open,
+	);
+
+	if (isLoading || error || !data) {
+		return <TabState isLoading={isLoading} error={error} />;
+	}
+	return (
+		<div className="space-y-4">
+			<CopyableSnippet title="docker run" text={data.docker_run} />
+			<CopyableSnippet title="Compose service" text={data.compose} />
+		</div>
+	);
+}
+
+const SERVICE_KINDS: Record<ServiceKind, string> = {
+	systemd_service: "systemd service",
+	systemd_timer: "systemd timer",
+	supervisord: "supervisord",
+	s6: "s6",
+	runit: "runit",
+	cron: "cron",
+	init_script: "init.d",
+};
+
+function ServiceRow({ service }: { service: ServiceDefinition }) {
+	return (
+		<div className="border rounded-md p-3 space-y-1 text-sm">
+			<div className="flex items-center gap-2">
+				<span className="font-medium truncate flex-1">{service.name}</span>
+				{service.enabled !== null && (
+					<Badge variant={service.enabled ? "default" : "outline"}>
+						{service.enabled ? "enabled" : "disabled"}
+					</Badge>
+				)}
+				<Badge variant="secondary">{SERVICE_KINDS[service.kind]}</Badge>
+			</div>
+			{service.description && (
+				<div className="text-muted-foreground">{service.description}</div>
+			)}
+			{service.command && (
+				<div className="font-mono text-xs break-all">{service.command}</div>
+			)}
+			<div className="text-xs text-muted-foreground">
+				{service.schedule && <span className="mr-3">{service.schedule}</span>}
+				{service.user && <span className="mr-3">as {service.user}</span>}
+				<span className="font-mono">/{service.path}</span>
+			</div>
+		</div>
+	);
+}
+
+function ServicesTab({ open }: { open: boolean }) {
+	const dockerImage = useLayersStore((state) => state.dockerImage);
+	// The last layer holds the filesystem the container starts with
+	const lastLayer = dockerImage?.layers[dockerImage.layers.length - 1];
+	const { data, error, isLoading } = useImageCommand<ServiceInventory>(
+		"inspect_services",
+		open,
+		lastLayer ? { layerId: lastLayer.id } : null,
+	);
+
+	if (isLoading || error || !data) {
+		return <TabState isLoading={isLoading} error={error} />;
+	}
+	if (data.services.length === 0) {
+		return (
+			<div className="text-sm text-gray-500">
+				No systemd units, supervisor programs, cron entries or init scripts
+				in this image
+			</div>
+		);
+	}
+	return (
+		<div className="space-y-2">
+			<div className="flex flex-wrap gap-1">
+				{data.init_systems.map((system) => (
+					<Badge key={system} variant="outline">
+						{system}
+					</Badge>
+				))}
+			</div>
+			{/* One file can define several programs or cron entries */}
+			{data.services.map((service, index) => (
+				<ServiceRow key={`${service.path}:${index}`} service={service} />
+			))}
+		</div>
+	);
+}
+
+const SEVERITY_VARIANTS: Record<
+	Severity,
+	"destructive" | "default" | "secondary" | "outline"
+> = {
+	critical: "destructive",
+	high: "destructive",
+	medium: "default",
+	low: "secondary",
+	info: "outline",
+};
+
+function FindingRow({
+	finding,
+	focused,
+}: {
+	finding: ReportFinding;
+	focused: boolean;
+}) {
+	const ref = useRef<HTMLDivElement>(null);
+
+	useEffect(() => {
+		if (focused) ref.current?.scrollIntoView({ block: "center" });
+	}, [focused]);
+
+	// A layers:// link a teammate can open straight to this finding
+	const copyLink = async () => {
+		try {
+			const link = await invoke<DeepLink>("create_finding_link", { finding });
+			await navigator.clipboard.writeText(link.url);
+			toast("Link copied", { description: link.url });
+		} catch (error) {
+			console.error("Failed to copy finding link:", error);
+			toast.error(errorMessage(error, "Failed to copy link to this finding"));
+		}
+	};
+
+	return (
+		<div
+			ref={ref}
+			id={finding.anchor}
+			className={cn(
+				"border rounded-md p-3 space-y-1 text-sm",
+				focused && "ring-2 ring-blue-400 dark:ring-blue-600",
+			)}
+		>
+			<div className="flex items-center gap-2">
+				<Badge variant={SEVERITY_VARIANTS[finding.severity]}>
+					{finding.severity}
+				</Badge>
+				<span className="font-medium truncate flex-1">{finding.title}</span>
+				<Button
+					variant="ghost"
+					size="icon"
+					className="h-7 w-7"
+					onClick={copyLink}
+					title="Copy link to this finding"
+				>
+					<Link className="h-4 w-4" />
+				</Button>
+			</div>
+			<div className="text-muted-foreground">{finding.description}</div>
+			<div className="text-xs text-muted-foreground">
+				{finding.path && (
+					<span className="font-mono mr-3">{finding.path}</span>
+				)}
+				{finding.layer_id && <span className="mr-3">{finding.layer_id}</span>}
+				<span className="font-mono">{finding.rule_id}</span>
+			</div>
+		</div>
+	);
+}
+
+function FindingsTab({
+	open,
+	focusedAnchor,
+}: {
+	open: boolean;
+	focusedAnchor?: string | null;
+}) {
+	const { data, error, isLoading } = useImageCommand<ReportFinding[]>(
+		"list_image_findings",
+		open,
+	);
+
+	if (isLoading || error || !data) {
+		return <TabState isLoading={isLoading} error={error} />;
+	}
+	if (data.length === 0) {
+		return <div className="text-sm text-gray-500">No findings</div>;
+	}
+	return (
+		<div className="space-y-2">
+			{data.map((finding) => (
+				<FindingRow
+					key={finding.anchor}
+					finding={finding}
+					focused={finding.anchor === focusedAnchor}
+				/>
+			))}
+		</div>
+	);
+}
+
+function formatSeconds(seconds: number): string {
+	if (seconds < 60) return `${seconds.toFixed(1)}s`;
+	return `${Math.floor(seconds / 60)}m ${Math.round(seconds % 60)}s`;
+}
+
+function PullTimeTab({ open }: { open: boolean }) {
+	const { data, error, isLoading } = useImageCommand<PullTimeReport>(
+		"estimate_pull_times",
+		open,
+	);
+	const [profileName, setProfileName] = useState<string | null>(null);
+
+	if (isLoading || error || !data) {
+		return <TabState isLoading={isLoading} error={error} />;
+	}
+	const estimate =
+		data.profiles.find((p) => p.profile.name === profileName) ??
+		data.profiles[0];
+	if (!estimate) return null;
+
+	return (
+		<div className="space-y-3 text-sm">
+			<div className="text-muted-foreground">
+				{formatBytes(data.total_uncompressed_bytes)} on disk,{" "}
+				{data.compression_ratio === null
+					? `${formatBytes(data.total_compressed_bytes)} to download according to the registry`
+					: `about ${formatBytes(data.total_compressed_bytes)} to download, estimated at a ${Math.round(data.compression_ratio * 100)}% compression ratio`}
+			</div>
+			<div className="flex flex-wrap gap-2">
+				{data.profiles.map(({ profile, total_seconds }) => (
+					<Button
+						key={profile.name}
+						variant={
+							profile.name === estimate.profile.name ? "default" : "outline"
+						}
+						size="sm"
+						onClick={() => setProfileName(profile.name)}
+						title={`${profile.bandwidth_mbps} Mbit/s, ${profile.concurrent_downloads} parallel downloads`}
+					>
+						{profile.name}
+						<span className="opacity-70">{formatSeconds(total_seconds)}</span>
+					</Button>
+				))}
+			</div>
+			{/* Layers that dominate the cold start are highlighted */}
+			<div className="space-y-1">
+				{/* History IDs repeat as <missing> for pulled images */}
+				{estimate.layers.map((layer, index) => (
+					<div
+						key={`${layer.layer_id}:${index}`}
+						className={cn(
+							"rounded-md px-2 py-1",
+							layer.is_dominant && "bg-amber-50 dark:bg-amber-950",
+						)}
+					>
+						<div className="flex items-center gap-2">
+							<span className="font-mono text-xs truncate flex-1">
+								{layer.command || layer.layer_id}
+							</span>
+							<span className="text-xs text-muted-foreground">
+								{formatBytes(layer.compressed_bytes)}
+							</span>
+							<span
+								className={cn(
+									"text-xs w-16 text-right",
+									layer.is_dominant && "font-semibold text-amber-600",
+								)}
+							>
+								{formatSeconds(layer.seconds)}
+							</span>
+						</div>
+						<div className="h-1 bg-gray-200 dark:bg-gray-700 rounded-full mt-1">
+							<div
+								className={cn(
+									"h-full rounded-full",
+									layer.is_dominant ? "bg-amber-500" : "bg-blue-500",
+								)}
+								style={{ width: `${layer.share * 100}%` }}
+							/>
+						</div>
+					</div>
+				))}
+			</div>
+		</div>
+	);
+}
+
+// Image wide views that don't belong to a single layer
+export function ImageDetailsSheet({
+	open,
+	onOpenChange,
+	focusedAnchor,
+}: ImageDetailsSheetProps) {
+	const dockerImage = useLayersStore((state) => state.dockerImage);
+	const [tab, setTab] = useState("run");
+
+	// Opening a link to a finding goes straight to it
+	useEffect(() => {
+		if (focusedAnchor) setTab("findings");
+	}, [focusedAnchor]);
+
+	return (
+		<Sheet open={open} onOpenChange={onOpenChange}>
+			<SheetContent className="sm:max-w-2xl w-full overflow-auto">
+				<SheetHeader>
+					<SheetTitle>Image details</SheetTitle>
+					<SheetDescription>{dockerImage?.name}</SheetDescription>
+				</SheetHeader>
+				<Tabs value={tab} onValueChange={setTab} className="px-4 pb-4">
+					<TabsList>
+						<TabsTrigger value="run">Run</TabsTrigger>
+						<TabsTrigger value="services">Services</TabsTrigger>
+						<TabsTrigger value="pull">Pull time</TabsTrigger>
+						<TabsTrigger value="findings">Findings</TabsTrigger>
+					</TabsList>
+					<TabsContent value="run">
+						<RunSnippetsTab open={open} />
+					</TabsContent>
+					<TabsContent value="services">
+						<ServicesTab open={open} />
+					</TabsContent>
+					<TabsContent value="pull">
+						<PullTimeTab open={open} />
+					</TabsContent>
+					<TabsContent value="findings">
+						<FindingsTab open={open} focusedAnchor={focusedAnchor} />
+					</TabsContent>
+				</Tabs>
+			</SheetContent>
+		</Sheet>
+	);
+}
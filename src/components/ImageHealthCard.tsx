@@ -0,0 +1,279 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { HeartPulse, Loader2, Save, SlidersHorizontal, X } from "lucide-react";
+import { toast } from "sonner";
+import { Badge } from "@/components/ui/badge";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import {
+	Card,
+	CardAction,
+	CardContent,
+	CardDescription,
+	CardHeader,
+	CardTitle,
+} from "@/components/ui/card";
+import { cn, errorMessage, formatBytes } from "@/lib/utils";
+import type {
+	ImageHealth,
+	ScoringWeights,
+	SeverityCounts,
+} from "@/utils/types";
+
+interface ImageHealthCardProps {
+	imageName: string;
+	onClose: () => void;
+}
+
+const GRADE_COLORS: Record<ImageHealth["grade"], string> = {
+	A: "bg-green-500",
+	B: "bg-lime-500",
+	C: "bg-amber-500",
+	D: "bg-orange-500",
+	F: "bg-red-500",
+};
+
+const SEVERITIES: Array<keyof SeverityCounts> = [
+	"critical",
+	"high",
+	"medium",
+	"low",
+	"info",
+];
+
+const WEIGHT_LABELS: Record<keyof ScoringWeights, string> = {
+	wasted_percent: "Per % of wasted bytes",
+	size_gb: "Per GB of image size",
+	critical: "Per critical finding",
+	high: "Per high finding",
+	medium: "Per medium finding",
+	low: "Per low finding",
+	info: "Per info finding",
+	root_user: "Runs as root",
+	stale_base: "Base older than 180 days",
+	outdated_base: "Base older than a year",
+};
+
+function Stat({ label, value }: { label: string; value: string }) {
+	return (
+		<div>
+			<div className="text-xs text-muted-foreground">{label}</div>
+			<div className="font-medium">{value}</div>
+		</div>
+	);
+}
+
+// Points each factor takes off, saved for every image scored from now on
+function WeightSettings({ onSaved }: { onSaved: () => void }) {
+	const [weights, setWeights] = useState<ScoringWeights | null>(null);
+	const [isSaving, setIsSaving] = useState(false);
+
+	useEffect(() => {
+		invoke<ScoringWeights>("get_scoring_weights")
+			.then(setWeights)
+			.catch((error) => {
+				console.error("Error loading scoring weights:", error);
+				toast.error(errorMessage(error, "Failed to load the scoring weights"));
+			});
+	}, []);
+
+	if (!weights) {
+		return <Loader2 className="h-4 w-4 text-blue-500 animate-spin" />;
+	}
+
+	const handleSave = async () => {
+		setIsSaving(true);
+		try {
+			setWeights(
+				await invoke<ScoringWeights>("set_scoring_weights", { weights }),
+			);
+			onSaved();
+		} catch (error) {
+			console.error("Error saving scoring weights:", error);
+			toast.error(errorMessage(error, "Failed to save the scoring weights"));
+		} finally {
+			setIsSaving(false);
+		}
+	};
+
+	return (
+		<div className="space-y-1.5 border-t pt-3">
+			{(Object.keys(WEIGHT_LABELS) as Array<keyof ScoringWeights>).map(
+				(key) => (
+					<label key={key} className="flex items-center gap-2">
+						<span className="flex-1 text-xs">{WEIGHT_LABELS[key]}</span>
+						<Input
+							type="number"
+							min={0}
+							step={0.1}
+							className="h-7 w-20 text-right"
+							value={weights[key]}
+							onChange={(e) =>
+								setWeights({
+									...weights,
+									[key]: Math.max(0, Number(e.target.value) || 0),
+								})
+							}
+						/>
+					</label>
+				),
+			)}
+			<div className="text-xs text-muted-foreground">
+				A synced team ruleset with scoring weights takes precedence
+			</div>
+			<Button
+				size="sm"
+				className="w-full"
+				onClick={handleSave}
+				disabled={isSaving}
+			>
+				{isSaving ? (
+					<Loader2 className="h-4 w-4 animate-spin" />
+				) : (
+					<Save className="h-4 w-4" />
+				)}
+				Save and rescore
+			</Button>
+		</div>
+	);
+}
+
+// Scored overview of the image that was just opened
+export function ImageHealthCard({ imageName, onClose }: ImageHealthCardProps) {
+	const [health, setHealth] = useState<ImageHealth | null>(null);
+	const [error, setError] = useState<string | null>(null);
+	const [isEditingWeights, setIsEditingWeights] = useState(false);
+	// Bumped to score the image again after the weights change
+	const [revision, setRevision] = useState(0);
+
+	useEffect(() => {
+		let cancelled = false;
+		setError(null);
+		invoke<ImageHealth>("get_image_health")
+			.then((health) => !cancelled && setHealth(health))
+			.catch((error) => {
+				console.error("Error computing image health:", error);
+				if (!cancelled) {
+					setError(errorMessage(error, "Failed to compute the image health"));
+				}
+			});
+		return () => {
+			cancelled = true;
+		};
+	}, [revision]);
+
+	const foundSeverities = SEVERITIES.filter(
+		(severity) => (health?.findings[severity] ?? 0) > 0,
+	);
+
+	return (
+		<Card className="absolute top-4 right-4 z-20 w-80 gap-3 py-4 bg-background/95 backdrop-blur-md shadow-lg text-sm">
+			<CardHeader className="px-4">
+				<CardTitle className="flex items-center gap-2">
+					<HeartPulse className="h-4 w-4 text-primary" />
+					Image health
+				</CardTitle>
+				<CardDescription className="truncate" title={imageName}>
+					{imageName}
+				</CardDescription>
+				<CardAction className="flex gap-1">
+					<Button
+						variant={isEditingWeights ? "secondary" : "ghost"}
+						size="icon"
+						className="h-7 w-7"
+						onClick={() => setIsEditingWeights(!isEditingWeights)}
+						title="Scoring weights"
+					>
+						<SlidersHorizontal className="h-4 w-4" />
+					</Button>
+					<Button
+						variant="ghost"
+						size="icon"
+						className="h-7 w-7"
+						onClick={onClose}
+						title="Close"
+					>
+						<X className="h-4 w-4" />
+					</Button>
+				</CardAction>
+			</CardHeader>
+			<CardContent className="px-4 space-y-3">
+				{error ? (
+					<div className="text-red-500">{error}</div>
+				) : !health ? (
+					<div className="flex items-center gap-2 text-muted-foreground">
+						<Loader2 className="h-4 w-4 text-blue-500 animate-spin" />
+						Indexing the image...
+					</div>
+				) : (
+					<>
+						<div className="flex items-center gap-3">
+							<div
+								className={cn(
+									"h-12 w-12 rounded-md flex items-center justify-center text-2xl font-bold text-white",
+									GRADE_COLORS[health.grade],
+								)}
+							>
+								{health.grade}
+							</div>
+							<div>
+								<div className="text-2xl font-semibold">{health.score}</div>
+								<div className="text-xs text-muted-foreground">out of 100</div>
+							</div>
+						</div>
+						<div className="grid grid-cols-2 gap-2">
+							<Stat label="Size" value={formatBytes(health.total_bytes)} />
+							<Stat
+								label="Wasted"
+								value={`${formatBytes(health.wasted_bytes)} (${(100 - health.efficiency_score).toFixed(1)}%)`}
+							/>
+							<Stat label="Layers" value={String(health.layer_count)} />
+							<Stat
+								label="User"
+								value={health.runs_as_root ? "root" : health.user}
+							/>
+							{health.base && (
+								<Stat
+									label="Base layers"
+									value={`${health.base.age_days} days old`}
+								/>
+							)}
+						</div>
+						<div className="flex flex-wrap gap-1">
+							{foundSeverities.map((severity) => (
+								<Badge
+									key={severity}
+									variant={
+										severity === "critical" || severity === "high"
+											? "destructive"
+											: "secondary"
+									}
+								>
+									{health.findings[severity]} {severity}
+								</Badge>
+							))}
+						</div>
+						{health.breakdown.length > 0 && (
+							<div className="space-y-1">
+								{/* Largest first, together they explain the score */}
+								{health.breakdown.map((factor) => (
+									<div key={factor.factor} className="flex gap-2 text-xs">
+										<span className="flex-1 truncate" title={factor.detail}>
+											{factor.detail}
+										</span>
+										<span className="font-mono text-red-500">
+											-{factor.points}
+										</span>
+									</div>
+								))}
+							</div>
+						)}
+					</>
+				)}
+				{isEditingWeights && (
+					<WeightSettings onSaved={() => setRevision(revision + 1)} />
+				)}
+			</CardContent>
+		</Card>
+	);
+}
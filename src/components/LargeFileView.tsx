@@ -0,0 +1,140 @@
+import { useState, useEffect, useRef, type FC, type UIEvent } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Loader2 } from "lucide-react";
+import { errorMessage } from "@/lib/utils";
+import type { FileLines, LineIndexInfo } from "../utils/types";
+
+// Matches leading-6
+const LINE_HEIGHT = 24;
+// Lines fetched around the visible ones, small scrolls stay inside them
+const WINDOW_LINES = 1000;
+// Browsers stop growing elements somewhere past 30M pixels, beyond this the
+// scrollbar maps to lines proportionally instead of one line per row
+const MAX_SCROLL_HEIGHT = 10_000_000;
+
+type LargeFileViewProps = {
+	// Path of the extracted file
+	path: string;
+};
+
+// Read-only view of text files too big to load whole. Only the lines on
+// screen are kept, fetched by line number through the backend's line index.
+const LargeFileView: FC<LargeFileViewProps> = ({ path }) => {
+	const [info, setInfo] = useState<LineIndexInfo | null>(null);
+	const [chunk, setChunk] = useState<FileLines | null>(null);
+	const [error, setError] = useState<string | null>(null);
+	const [scrollTop, setScrollTop] = useState(0);
+	const [height, setHeight] = useState(0);
+	const containerRef = useRef<HTMLDivElement>(null);
+	const requested = useRef<number | null>(null);
+
+	useEffect(() => {
+		setInfo(null);
+		setChunk(null);
+		setError(null);
+		requested.current = null;
+		containerRef.current?.scrollTo({ top: 0 });
+		invoke<LineIndexInfo>("get_line_index", { path })
+			.then(setInfo)
+			.catch((error) => setError(errorMessage(error, "Failed to index file")));
+	}, [path]);
+
+	useEffect(() => {
+		const container = containerRef.current;
+		if (!container) return;
+		const observer = new ResizeObserver(() =>
+			setHeight(container.clientHeight),
+		);
+		observer.observe(container);
+		return () => observer.disconnect();
+	}, []);
+
+	const lineCount = info?.line_count ?? 0;
+	const visible = Math.ceil(height / LINE_HEIGHT) + 1;
+	const scrollHeight = Math.min(lineCount * LINE_HEIGHT, MAX_SCROLL_HEIGHT);
+	const lastFirst = Math.max(0, lineCount - visible + 1);
+	const first =
+		scrollHeight > height
+			? Math.min(
+					lastFirst,
+					Math.round((scrollTop / (scrollHeight - height)) * lastFirst),
+				)
+			: 0;
+
+	useEffect(() => {
+		if (!info) return;
+		if (
+			chunk &&
+			first >= chunk.start_line &&
+			(chunk.eof || first + visible <= chunk.start_line + chunk.lines.length)
+		) {
+			return;
+		}
+		const start = Math.max(0, first - Math.floor(WINDOW_LINES / 2));
+		if (requested.current === start) return;
+		requested.current = start;
+		invoke<FileLines>("read_file_lines", {
+			path,
+			startLine: start,
+			count: WINDOW_LINES,
+		})
+			.then((lines) => {
+				// Scrolled on while this was loading, a newer request wins
+				if (requested.current === start) setChunk(lines);
+			})
+			.catch((error) => setError(errorMessage(error, "Failed to read lines")));
+	}, [info, chunk, first, visible, path]);
+
+	const handleScroll = (e: UIEvent<HTMLDivElement>) =>
+		setScrollTop(e.currentTarget.scrollTop);
+
+	if (error) {
+		return <div className="p-4 text-sm text-red-500">{error}</div>;
+	}
+
+	const rows = [];
+	for (let line = first; line < Math.min(first + visible, lineCount); line++) {
+		const text =
+			chunk && line >= chunk.start_line
+				? chunk.lines[line - chunk.start_line]
+				: undefined;
+		rows.push(
+			<div key={line} className="flex h-6 whitespace-pre">
+				<span className="w-20 shrink-0 pr-3 text-right text-gray-400 dark:text-gray-500 select-none">
+					{line + 1}
+				</span>
+				<span>{text ?? ""}</span>
+			</div>,
+		);
+	}
+
+	return (
+		<div className="h-full w-full flex flex-col font-fira-code text-sm">
+			<div className="px-3 py-1 text-xs text-gray-500 border-b border-gray-200 dark:border-gray-700">
+				{info ? (
+					`${lineCount.toLocaleString()} lines, ${info.total_size.toLocaleString()} bytes`
+				) : (
+					<span className="flex items-center gap-2">
+						<Loader2 className="h-3 w-3 animate-spin" />
+						Indexing lines...
+					</span>
+				)}
+			</div>
+			<div
+				ref={containerRef}
+				className="relative flex-grow overflow-auto"
+				onScroll={handleScroll}
+			>
+				<div style={{ height: scrollHeight }} />
+				<div
+					className="absolute left-0 right-0 overflow-hidden leading-6"
+					style={{ top: scrollTop, height }}
+				>
+					{rows}
+				</div>
+			</div>
+		</div>
+	);
+};
+
+export default LargeFileView;
@@ -9,10 +9,23 @@ import {
 	ChevronRight,
 	ChevronDown,
 	Search,
+	FolderOutput,
+	FileArchive,
+	X,
 } from "lucide-react";
-import { cn } from "@/lib/utils";
+import { invoke } from "@tauri-apps/api/core";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import { toast } from "sonner";
+import { cn, errorMessage, formatBytes } from "@/lib/utils";
 import useLayersStore from "@/store/useLayersStore";
-import type { FileItem } from "../utils/types";
+import { Button } from "@/components/ui/button";
+import type {
+	AttributionResult,
+	DirectoryAttribution,
+	ExportFormat,
+	ExportResult,
+	FileItem,
+} from "../utils/types";
 import { useEffect, useState, useMemo } from "react";
 import { useWindowSize } from "../hooks/useWindowSize";
 import { FixedSizeList as List } from "react-window";
@@ -32,6 +45,8 @@ interface TreeNode {
 	children: TreeNode[];
 	isExpanded?: boolean;
 	file: FileItem;
+	// Path sent to export_selected_paths, exact when the name isn't plain
+	exportPath: string;
 }
 
 export function LayerFiles({ className }: LayerFilesProps) {
@@ -48,6 +63,7 @@ export function LayerFiles({ className }: LayerFilesProps) {
 		taskStatus,
 		extractDirectory,
 		loadingDirectories,
+		selectedImageId,
 	} = useLayersStore();
 
 	const [fileTree, setFileTree] = React.useState<TreeNode[]>([]);
@@ -58,6 +74,16 @@ export function LayerFiles({ className }: LayerFilesProps) {
 	const [listHeight, setListHeight] = useState(500);
 	const [searchQuery, setSearchQuery] = useState("");
 	const [filteredFileTree, setFilteredFileTree] = useState<TreeNode[]>([]);
+	// Checked nodes by id, with the path each one exports
+	const [checkedPaths, setCheckedPaths] = useState<Map<string, string>>(
+		new Map(),
+	);
+	const [isExporting, setIsExporting] = useState(false);
+	// Layers the bytes below each directory come from, by container path
+	const [attributions, setAttributions] = useState<
+		Map<string, DirectoryAttribution>
+	>(new Map());
+	const requestedAttributions = React.useRef(new Set<string>());
 
 	// Determine if we're in a loading state
 	const isLoading =
@@ -69,9 +95,41 @@ export function LayerFiles({ className }: LayerFilesProps) {
 			console.log("Fetching files for layer ID:", selectedLayerId);
 			getLayerFiles("current_layer"); // Always use the generic layer name
 			setSelectedFile(null); // Reset selected file when layer changes
+			setCheckedPaths(new Map());
 		}
 	}, [selectedLayerId, getLayerFiles, setSelectedFile]);
 
+	// Attributions are of the image's final filesystem
+	React.useEffect(() => {
+		if (!selectedImageId) return;
+		requestedAttributions.current = new Set();
+		setAttributions(new Map());
+	}, [selectedImageId]);
+
+	// Each request brings the rows of a directory's subdirectories
+	const loadAttribution = React.useCallback((path: string) => {
+		if (requestedAttributions.current.has(path)) return;
+		requestedAttributions.current.add(path);
+		invoke<AttributionResult>("get_directory_attribution", { path })
+			.then((result) =>
+				setAttributions((previous) => {
+					const next = new Map(previous);
+					for (const child of result.children) {
+						next.set(child.path, child);
+					}
+					return next;
+				}),
+			)
+			.catch((error) => {
+				console.error(`Error attributing ${path} to layers:`, error);
+				requestedAttributions.current.delete(path);
+			});
+	}, []);
+
+	React.useEffect(() => {
+		if (selectedLayerFiles.length > 0) loadAttribution("/");
+	}, [selectedLayerFiles, loadAttribution]);
+
 	// Build file tree when files change
 	React.useEffect(() => {
 		if (selectedLayerFiles.length > 0) {
@@ -144,6 +202,51 @@ export function LayerFiles({ className }: LayerFilesProps) {
 		[setSelectedFile, loadFileContent],
 	);
 
+	const toggleChecked = (node: TreeNode) => {
+		const next = new Map(checkedPaths);
+		if (next.has(node.id)) {
+			next.delete(node.id);
+		} else {
+			next.set(node.id, node.exportPath);
+		}
+		setCheckedPaths(next);
+	};
+
+	const exportChecked = async (format: ExportFormat) => {
+		const destination =
+			format === "zip"
+				? await save({
+						defaultPath: "export.zip",
+						filters: [{ name: "Zip archive", extensions: ["zip"] }],
+					})
+				: await open({ directory: true, multiple: false });
+		if (!destination) return;
+
+		setIsExporting(true);
+		try {
+			const result = await invoke<ExportResult>("export_selected_paths", {
+				paths: [...checkedPaths.values()],
+				destination,
+				format,
+			});
+			const skipped = result.skipped?.length ?? 0;
+			toast.success(
+				`Exported ${result.files_exported} files and ${result.directories_exported} directories`,
+				{
+					description: skipped
+						? `${result.destination}, ${skipped} entries could not be written`
+						: result.destination,
+				},
+			);
+			setCheckedPaths(new Map());
+		} catch (error) {
+			console.error("Error exporting selected paths:", error);
+			toast.error(errorMessage(error, "Failed to export selected paths"));
+		} finally {
+			setIsExporting(false);
+		}
+	};
+
 	const toggleFolder = (folder: FileItem, event: React.MouseEvent) => {
 		event.stopPropagation();
 		const path = folder.path;
@@ -155,7 +258,7 @@ export function LayerFiles({ className }: LayerFilesProps) {
 			!loadingDirectories.has(path)
 		) {
 			// Extract the directory before expanding it
-			extractDirectory(path);
+			extractDirectory(path, folder.exact_path);
 		}
 
 		const newExpandedFolders = new Set(expandedFolders);
@@ -244,7 +347,46 @@ export function LayerFiles({ className }: LayerFilesProps) {
 							</span>
 						</button>
 					)}
-				</div>
+
+				{/* Export the checked paths */}
+				{checkedPaths.size > 0 && (
+					<div className="flex items-center gap-2 mt-3 text-sm">
+						<span className="flex-1 text-muted-foreground">
+							{checkedPaths.size} selected
+						</span>
+						<Button
+							variant="outline"
+							size="sm"
+							onClick={() => exportChecked("directory")}
+							disabled={isExporting}
+						>
+							<FolderOutput className="h-4 w-4" />
+							Export to folder
+						</Button>
+						<Button
+							variant="outline"
+							size="sm"
+							onClick={() => exportChecked("zip")}
+							disabled={isExporting}
+						>
+							{isExporting ? (
+								<Loader2 className="h-4 w-4 animate-spin" />
+							) : (
+								<FileArchive className="h-4 w-4" />
+							)}
+							Export as zip
+						</Button>
+						<Button
+							variant="ghost"
+							size="icon"
+							className="h-7 w-7"
+							onClick={() => setCheckedPaths(new Map())}
+							title="Clear selection"
+						>
+							<X className="h-4 w-4" />
+						</Button>
+					</div>
+				)}
 			</div>
 
 			{/* Scrollable content area */}
@@ -258,6 +400,10 @@ export function LayerFiles({ className }: LayerFilesProps) {
 							onFileSelect={handleFileSelect}
 							selectedFilePath={selectedFile?.path}
 							disabled={Boolean(isLoading)}
+							checkedIds={checkedPaths}
+							onToggleChecked={toggleChecked}
+							attributions={attributions}
+							onExpandDirectory={loadAttribution}
 						/>
 					))}
 				</div>
@@ -285,35 +431,42 @@ function FileTreeNode({
 	onFileSelect,
 	selectedFilePath,
 	disabled = false,
+	checkedIds,
+	onToggleChecked,
+	parentChecked = false,
+	attributions,
+	onExpandDirectory,
 }: {
 	node: TreeNode;
 	level: number;
 	onFileSelect: (file: FileItem) => void;
 	selectedFilePath?: string;
 	disabled?: boolean;
+	checkedIds: Map<string, string>;
+	onToggleChecked: (node: TreeNode) => void;
+	// A checked directory exports everything below it
+	parentChecked?: boolean;
+	attributions: Map<string, DirectoryAttribution>;
+	onExpandDirectory: (path: string) => void;
 }) {
 	const [expanded, setExpanded] = React.useState(node.isExpanded || false);
 	const [copied, setCopied] = React.useState(false);
 	const isDirectory = node.type === "directory" || node.children.length > 0;
 	const isSelected = node.path === selectedFilePath;
+	const isChecked = parentChecked || checkedIds.has(node.id);
+	const attribution = isDirectory ? attributions.get(`/${node.id}`) : undefined;
 
 	const toggleExpand = (e: React.MouseEvent | React.KeyboardEvent) => {
 		if (disabled) return;
 		e.stopPropagation();
+		if (!expanded) onExpandDirectory(`/${node.id}`);
 		setExpanded(!expanded);
 	};
 
 	const copyPath = (e: React.MouseEvent | React.KeyboardEvent) => {
 		e.stopPropagation();
 		// Extract the absolute path without considering workdir
-		// Remove the /tmp/layers/[layer_id] prefix if it exists
-		let absolutePath = node.path;
-		const pathParts = node.path.split("/");
-		if (pathParts.length > 3 && pathParts[1] === "layers") {
-			// This is a path like /tmp/layers/[layer_id]/path/to/file
-			// We want to extract /path/to/file
-			absolutePath = `/${pathParts.slice(3).join("/")}`;
-		}
+		const absolutePath = `/${containerPathParts(node.path).join("/")}`;
 
 		navigator.clipboard
 			.writeText(absolutePath)
@@ -362,6 +515,16 @@ function FileTreeNode({
 				aria-expanded={isDirectory ? expanded : undefined}
 				aria-disabled={disabled}
 			>
+				<input
+					type="checkbox"
+					className="flex-shrink-0 mr-2"
+					checked={isChecked}
+					disabled={disabled || parentChecked}
+					onClick={(e) => e.stopPropagation()}
+					onKeyDown={(e) => e.stopPropagation()}
+					onChange={() => onToggleChecked(node)}
+					aria-label={`Select ${node.name} for export`}
+				/>
 				<div className="flex-shrink-0 mr-2">
 					{isDirectory ? (
 						<div className="flex items-center">
@@ -381,6 +544,35 @@ function FileTreeNode({
 
 				<div className="flex-1 truncate">{node.name}</div>
 
+				{attribution && <AttributionBar attribution={attribution} />}
+
+				{node.file.attributes?.capabilities && !isDirectory && (
+					<span
+						className="text-xs text-amber-600 dark:text-amber-400 mr-2"
+						title={node.file.attributes.capabilities.display}
+					>
+						caps
+					</span>
+				)}
+
+				{!isDirectory && node.file.owner && (
+					<div
+						className={cn(
+							"text-xs mr-2 truncate max-w-40",
+							node.file.owner.unknown_user
+								? "text-amber-600 dark:text-amber-400"
+								: "text-gray-500",
+						)}
+						title={
+							node.file.owner.unknown_user
+								? `${node.file.owner.display}, not in the image's /etc/passwd`
+								: node.file.owner.display
+						}
+					>
+						{node.file.owner.display}
+					</div>
+				)}
+
 				{node.size && (
 					<div className="text-xs text-gray-500 mr-2">{node.size}</div>
 				)}
@@ -410,6 +602,11 @@ function FileTreeNode({
 							onFileSelect={onFileSelect}
 							selectedFilePath={selectedFilePath}
 							disabled={disabled}
+							checkedIds={checkedIds}
+							onToggleChecked={onToggleChecked}
+							parentChecked={isChecked}
+							attributions={attributions}
+							onExpandDirectory={onExpandDirectory}
 						/>
 					))}
 				</div>
@@ -418,6 +615,66 @@ function FileTreeNode({
 	);
 }
 
+const LAYER_COLORS = [
+	"bg-blue-500",
+	"bg-emerald-500",
+	"bg-amber-500",
+	"bg-violet-500",
+	"bg-rose-500",
+	"bg-cyan-500",
+	"bg-lime-500",
+	"bg-orange-500",
+];
+
+function layerColor(layerId: string): string {
+	const number = Number(layerId.replace("layer_", ""));
+	return LAYER_COLORS[number % LAYER_COLORS.length];
+}
+
+// Bytes below a directory stacked by the layer that wrote them, base layer
+// on the left. Colors follow the layer number, so they match across rows.
+function AttributionBar({
+	attribution,
+}: {
+	attribution: DirectoryAttribution;
+}) {
+	const summary = attribution.layers
+		.map(
+			(layer) =>
+				`${layer.layer_id}: ${formatBytes(layer.bytes)} (${Math.round(layer.fraction * 100)}%) ${layer.created_by}`,
+		)
+		.join("\n");
+
+	return (
+		<div
+			className="flex h-1.5 w-20 flex-shrink-0 mr-2 rounded-full overflow-hidden bg-gray-200 dark:bg-gray-700"
+			title={`${formatBytes(attribution.total_bytes)} in ${attribution.file_count} files of the final image\n${summary}`}
+		>
+			{attribution.layers.map((layer) => (
+				<div
+					key={layer.layer_id}
+					className={layerColor(layer.layer_id)}
+					style={{ width: `${layer.fraction * 100}%` }}
+				/>
+			))}
+		</div>
+	);
+}
+
+// Path components inside the container of a file below the extraction root,
+// e.g. /tmp/layers/sessions/session_1/current_layer/fs/etc/passwd, or the
+// same below %TEMP% on Windows
+function containerPathParts(path: string): string[] {
+	const parts = path.split(/[\\/]/).filter(Boolean);
+	const layers = parts.indexOf("layers");
+	if (layers < 0) {
+		return parts;
+	}
+	const fs = parts.indexOf("fs", layers);
+	// Metadata files like layer_info.txt sit next to fs
+	return fs >= 0 ? parts.slice(fs + 1) : parts.slice(-1);
+}
+
 // Helper function to build a file tree from flat file list
 function buildFileTree(files: FileItem[]): TreeNode[] {
 	const root: TreeNode[] = [];
@@ -425,20 +682,7 @@ function buildFileTree(files: FileItem[]): TreeNode[] {
 
 	// First pass: create all nodes
 	for (const file of files) {
-		const pathParts = file.path.split("/").filter(Boolean);
-		let relativePath: string[] = [];
-
-		// Handle paths with /tmp/layers/[layer_id] prefix
-		if (
-			pathParts.length > 3 &&
-			pathParts[0] === "tmp" &&
-			pathParts[1] === "layers"
-		) {
-			// Skip the /tmp/layers/current_layer/fs part
-			relativePath = pathParts.slice(4);
-		} else {
-			relativePath = pathParts;
-		}
+		const relativePath = containerPathParts(file.path);
 
 		// Skip special files at the root level
 		if (
@@ -472,6 +716,10 @@ function buildFileTree(files: FileItem[]): TreeNode[] {
 				size: isLeaf ? file.size?.toString() : undefined,
 				children: [],
 				isExpanded: false,
+				// Directories only seen through their files get their container path
+				exportPath: isLeaf
+					? (file.exact_path ?? file.path)
+					: `/${currentPath}`,
 				file: isLeaf
 					? file
 					: {
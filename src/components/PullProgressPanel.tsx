@@ -0,0 +1,115 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Download, Loader2, X } from "lucide-react";
+import { toast } from "sonner";
+import { Button } from "@/components/ui/button";
+import { Progress } from "@/components/ui/progress";
+import { errorMessage, formatBytes } from "@/lib/utils";
+import type { PullProgress } from "@/utils/types";
+
+// Only the fields of task_status that end a pull
+type TaskEnd = {
+	task_id: number;
+	is_complete: boolean;
+};
+
+function percent(downloaded: number, total: number | null): number {
+	return total ? Math.min(100, (downloaded / total) * 100) : 0;
+}
+
+// Per-layer download bars of a running pull, started by pull_image or by
+// inspect_docker_image for an image that isn't local
+export function PullProgressPanel() {
+	const [pull, setPull] = useState<PullProgress | null>(null);
+	const [isCancelling, setIsCancelling] = useState(false);
+
+	useEffect(() => {
+		const stopProgress = listen<PullProgress>("pull_progress", (event) => {
+			setPull(event.payload.is_complete ? null : event.payload);
+		});
+		// Failed and cancelled pulls end with the task, not a last progress event
+		const stopTask = listen<TaskEnd>("task_status", (event) => {
+			if (event.payload.is_complete) {
+				setPull((pull) =>
+					pull?.task_id === event.payload.task_id ? null : pull,
+				);
+			}
+		});
+		return () => {
+			stopProgress.then((stop) => stop());
+			stopTask.then((stop) => stop());
+		};
+	}, []);
+
+	useEffect(() => {
+		if (!pull) setIsCancelling(false);
+	}, [pull]);
+
+	if (!pull) return null;
+
+	const handleCancel = async () => {
+		setIsCancelling(true);
+		try {
+			await invoke("cancel_task", { taskId: pull.task_id });
+		} catch (error) {
+			console.error("Error cancelling pull:", error);
+			toast.error(errorMessage(error, "Failed to cancel the pull"));
+			setIsCancelling(false);
+		}
+	};
+
+	return (
+		<div className="absolute bottom-20 right-4 z-20 w-96 rounded-md border bg-background/95 backdrop-blur-md shadow-lg p-3 text-sm">
+			<div className="flex items-center gap-2">
+				<Download className="h-4 w-4 text-blue-500 flex-shrink-0" />
+				<span className="font-medium truncate flex-1">
+					Pulling {pull.image}
+				</span>
+				<Button
+					variant="ghost"
+					size="icon"
+					className="h-7 w-7"
+					onClick={handleCancel}
+					disabled={isCancelling}
+					title="Cancel pull"
+				>
+					{isCancelling ? (
+						<Loader2 className="h-4 w-4 animate-spin" />
+					) : (
+						<X className="h-4 w-4" />
+					)}
+				</Button>
+			</div>
+			<div className="flex justify-between text-xs text-muted-foreground mt-1">
+				<span className="truncate">{pull.message}</span>
+				<span>
+					{formatBytes(pull.downloaded_bytes)} of{" "}
+					{formatBytes(pull.total_bytes)}
+				</span>
+			</div>
+			<Progress
+				value={percent(pull.downloaded_bytes, pull.total_bytes)}
+				className="h-2 mt-1"
+			/>
+			<div className="mt-2 space-y-1.5 max-h-48 overflow-auto">
+				{pull.layers.map((layer) => (
+					<div key={layer.id}>
+						<div className="flex justify-between text-xs">
+							<span className="font-mono">{layer.id}</span>
+							<span className="text-muted-foreground">{layer.status}</span>
+						</div>
+						<Progress
+							value={
+								layer.complete
+									? 100
+									: percent(layer.downloaded_bytes, layer.total_bytes)
+							}
+							className="h-1"
+						/>
+					</div>
+				))}
+			</div>
+		</div>
+	);
+}
@@ -10,7 +10,7 @@ import {
 import type { TreeNode } from "./TreeView";
 import type { DockerLayer, FileItem } from "../utils/types";
 // Using cn for conditional className styling
-import { cn } from "@/lib/utils";
+import { cn, errorMessage } from "@/lib/utils";
 
 import {
 	Collapsible,
@@ -35,6 +35,12 @@ import useLayersStore from "@/store/useLayersStore";
 import { invoke } from "@tauri-apps/api/core";
 import { toast } from "sonner";
 
+// Long layer lists are rendered a page at a time
+const LAYER_PAGE_SIZE = 50;
+// Docker refuses to stack more layers than this
+const MAX_LAYERS = 127;
+const LAYER_WARNING_THRESHOLD = 100;
+
 interface AppSidebarProps {
 	dockerLayers?: DockerLayer[];
 	treeViewData?: TreeNode[];
@@ -76,8 +82,19 @@ export function AppSidebar({
 		clearLayersForComparison,
 		isComparing,
 		compareLayers,
+		highlightedLayerId,
 	} = useLayersStore();
 
+	const [visibleLayerCount, setVisibleLayerCount] =
+		React.useState(LAYER_PAGE_SIZE);
+
+	// Start from the first page again when another image is opened
+	React.useEffect(() => {
+		setVisibleLayerCount(LAYER_PAGE_SIZE);
+	}, [selectedImageId]);
+
+	const isNearLayerLimit = dockerLayers.length >= LAYER_WARNING_THRESHOLD;
+
 	// Set up theme for dark/light mode (will work with shadcn's theming)
 	React.useEffect(() => {
 		if (darkMode) {
@@ -170,15 +187,15 @@ export function AppSidebar({
 			}
 		} catch (error) {
 			console.error("Error comparing layers:", error);
-			const errorMessage = `Failed to compare layers: ${error}`;
+			const message = errorMessage(error, "Unknown error");
+			const errorDetails = `Failed to compare layers: ${message}`;
 			toast.error("Layer comparison failed", {
 				description:
-					String(error).substring(0, 100) +
-					(String(error).length > 100 ? "..." : ""),
+					message.substring(0, 100) + (message.length > 100 ? "..." : ""),
 				action: {
 					label: "Copy Error",
 					onClick: () => {
-						navigator.clipboard.writeText(errorMessage);
+						navigator.clipboard.writeText(errorDetails);
 						toast.info("Error details copied to clipboard");
 					},
 				},
@@ -307,6 +324,23 @@ export function AppSidebar({
 									<SidebarGroupLabel className="font-semibold">
 										Docker Layers
 									</SidebarGroupLabel>
+									{dockerLayers.length > 0 && (
+										<span
+											className={cn(
+												"ml-2 text-xs px-1.5 py-0.5 rounded-full",
+												isNearLayerLimit
+													? "bg-amber-100 text-amber-800 dark:bg-amber-900/40 dark:text-amber-300"
+													: "bg-gray-200 dark:bg-gray-700",
+											)}
+											title={
+												isNearLayerLimit
+													? `Close to Docker's limit of ${MAX_LAYERS} layers`
+													: undefined
+											}
+										>
+											{dockerLayers.length}
+										</span>
+									)}
 								</div>
 								<div className="flex items-center">
 									<Button
@@ -371,66 +405,99 @@ export function AppSidebar({
 								<SidebarGroupContent className="p-2">
 									{dockerLayers.length > 0 ? (
 										<SidebarMenu className="overflow-y-auto space-y-1">
-											{[...dockerLayers].reverse().map((layer, index) => {
-												// Calculate layer number (from top to bottom, starting with 1)
-												const layerNumber = index + 1;
-												// Create a layer ID that includes the number
-												const numberedLayerId = `layer_${layerNumber}`;
+											{[...dockerLayers]
+												.reverse()
+												.slice(0, visibleLayerCount)
+												.map((layer, index) => {
+													// Calculate layer number (from top to bottom, starting with 1)
+													const layerNumber = index + 1;
+													// Create a layer ID that includes the number
+													const numberedLayerId = `layer_${layerNumber}`;
 
-												// Determine if this layer is selected for comparison
-												const isSelectedForComparison =
-													isComparisonMode &&
-													selectedLayersForComparison.includes(numberedLayerId);
+													// Determine if this layer is selected for comparison
+													const isSelectedForComparison =
+														isComparisonMode &&
+														selectedLayersForComparison.includes(numberedLayerId);
 
-												return (
-													<SidebarMenuItem
-														key={layer.id}
-														data-active={
-															isComparisonMode
-																? isSelectedForComparison
-																: layer.id === selectedLayerId ||
-																	numberedLayerId === selectedLayerId
-														}
-														onClick={() => handleSelectLayer(numberedLayerId)}
-														className={cn(
-															"hover:bg-gray-100 dark:hover:bg-gray-800 transition-colors py-1.5 px-3 rounded-md my-0.5 cursor-pointer",
-															isComparisonMode &&
-																isSelectedForComparison &&
-																"bg-blue-100 dark:bg-blue-900/30 border border-blue-200 dark:border-blue-800",
-															!isComparisonMode &&
-																(layer.id === selectedLayerId ||
-																	numberedLayerId === selectedLayerId) &&
-																"data-[active=true]:bg-accent",
-														)}
-													>
-														<div className="flex flex-col flex-1 min-w-0">
-															<div className="flex items-center">
-																<span
-																	className={cn(
-																		"font-medium mr-2 text-xs px-1.5 py-0.5 rounded-full",
-																		isSelectedForComparison
-																			? "bg-blue-200 dark:bg-blue-800"
-																			: "bg-gray-200 dark:bg-gray-700",
+													return (
+														<SidebarMenuItem
+															key={layer.id}
+															data-active={
+																isComparisonMode
+																	? isSelectedForComparison
+																	: layer.id === selectedLayerId ||
+																		numberedLayerId === selectedLayerId
+															}
+															onClick={() => handleSelectLayer(numberedLayerId)}
+															className={cn(
+																"hover:bg-gray-100 dark:hover:bg-gray-800 transition-colors py-1.5 px-3 rounded-md my-0.5 cursor-pointer",
+																isComparisonMode &&
+																	isSelectedForComparison &&
+																	"bg-blue-100 dark:bg-blue-900/30 border border-blue-200 dark:border-blue-800",
+																!isComparisonMode &&
+																	(layer.id === selectedLayerId ||
+																		numberedLayerId === selectedLayerId) &&
+																	"data-[active=true]:bg-accent",
+																// Built by the Dockerfile instruction under the cursor
+																numberedLayerId === highlightedLayerId &&
+																	"ring-2 ring-blue-400 dark:ring-blue-600",
+															)}
+														>
+															<div className="flex flex-col flex-1 min-w-0">
+																<div className="flex items-center">
+																	<span
+																		className={cn(
+																			"font-medium mr-2 text-xs px-1.5 py-0.5 rounded-full",
+																			isSelectedForComparison
+																				? "bg-blue-200 dark:bg-blue-800"
+																				: "bg-gray-200 dark:bg-gray-700",
+																		)}
+																	>
+																		{layerNumber}
+																	</span>
+																	<span className="font-medium truncate">
+																		{layer.command
+																			? layer.command.substring(0, 30) +
+																				(layer.command.length > 30 ? "..." : "")
+																			: "Base Layer"}
+																	</span>
+																</div>
+																<div className="flex justify-end items-center">
+																	{layer.partial && (
+																		<span
+																			className="mr-auto text-xs text-amber-600 dark:text-amber-400"
+																			title={layer.partial}
+																		>
+																			Partial
+																		</span>
 																	)}
-																>
-																	{layerNumber}
-																</span>
-																<span className="font-medium truncate">
-																	{layer.command
-																		? layer.command.substring(0, 30) +
-																			(layer.command.length > 30 ? "..." : "")
-																		: "Base Layer"}
-																</span>
+																	<SidebarMenuBadge className="text-xs">
+																		{layer.size}
+																	</SidebarMenuBadge>
+																</div>
 															</div>
-															<div className="flex justify-end items-center">
-																<SidebarMenuBadge className="text-xs">
-																	{layer.size}
-																</SidebarMenuBadge>
-															</div>
-														</div>
-													</SidebarMenuItem>
-												);
-											})}
+														</SidebarMenuItem>
+													);
+												})}
+											{dockerLayers.length > visibleLayerCount && (
+												<Button
+													variant="ghost"
+													size="sm"
+													className="w-full h-7 text-xs text-muted-foreground"
+													onClick={() =>
+														setVisibleLayerCount(
+															(count) => count + LAYER_PAGE_SIZE,
+														)
+													}
+												>
+													Show{" "}
+													{Math.min(
+														LAYER_PAGE_SIZE,
+														dockerLayers.length - visibleLayerCount,
+													)}{" "}
+													more of {dockerLayers.length - visibleLayerCount}
+												</Button>
+											)}
 										</SidebarMenu>
 									) : (
 										<div className="px-3 py-2 text-sm text-muted-foreground bg-gray-50 dark:bg-gray-800 rounded-md text-center">
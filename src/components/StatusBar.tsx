@@ -1,4 +1,4 @@
-import { cn } from "@/lib/utils";
+import { cn, formatBytes } from "@/lib/utils";
 import useLayersStore from "@/store/useLayersStore";
 import { Loader2, AlertCircle, CheckCircle } from "lucide-react";
 import { Progress } from "@/components/ui/progress";
@@ -7,6 +7,11 @@ interface StatusBarProps {
 	className?: string;
 }
 
+function formatEta(seconds: number): string {
+	if (seconds < 60) return `${Math.ceil(seconds)}s left`;
+	return `${Math.floor(seconds / 60)}m ${Math.ceil(seconds % 60)}s left`;
+}
+
 export function StatusBar({ className }: StatusBarProps) {
 	const { taskStatus, isLoading } = useLayersStore();
 
@@ -44,6 +49,37 @@ export function StatusBar({ className }: StatusBarProps) {
 							{taskStatus.message}
 						</span>
 					</div>
+					{!taskStatus.isComplete && taskStatus.phase && (
+						<ol className="flex items-center gap-1 ml-2 text-xs">
+							{taskStatus.phase.phases.map((name, index) => (
+								<li
+									key={name}
+									className={cn(
+										"px-1.5 rounded",
+										index < taskStatus.phase!.index
+											? "text-green-500"
+											: index === taskStatus.phase!.index
+												? "bg-blue-500/10 text-blue-500 font-medium"
+												: "text-muted-foreground",
+									)}
+								>
+									{name}
+								</li>
+							))}
+						</ol>
+					)}
+					{!taskStatus.isComplete && taskStatus.bytes_processed != null && (
+						<span
+							className="text-xs text-muted-foreground ml-2 truncate max-w-xs"
+							title={taskStatus.current_item ?? undefined}
+						>
+							{formatBytes(taskStatus.bytes_processed)}
+							{taskStatus.bytes_total != null &&
+								` / ${formatBytes(taskStatus.bytes_total)}`}
+							{taskStatus.eta_seconds != null &&
+								` · ${formatEta(taskStatus.eta_seconds)}`}
+						</span>
+					)}
 					{!taskStatus.isComplete && (
 						<Progress
 							value={taskStatus.progress * 100}
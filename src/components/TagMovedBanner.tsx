@@ -0,0 +1,114 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { AlertTriangle, GitCompare, Loader2, X } from "lucide-react";
+import { toast } from "sonner";
+import { Button } from "@/components/ui/button";
+import { Badge } from "@/components/ui/badge";
+import { errorMessage } from "@/lib/utils";
+import useLayersStore from "@/store/useLayersStore";
+import type { TagVersionDiff } from "@/utils/types";
+
+function shortId(id: string): string {
+	return id.replace(/^sha256:/, "").substring(0, 12);
+}
+
+function LayerList({ title, layers }: { title: string; layers: string[] }) {
+	return (
+		<div className="min-w-0">
+			<div className="font-medium mb-1">
+				{title} <Badge variant="secondary">{layers.length}</Badge>
+			</div>
+			<ul className="font-mono text-xs text-muted-foreground space-y-0.5">
+				{layers.map((layer) => (
+					<li key={layer} title={layer} className="truncate">
+						{shortId(layer)}
+					</li>
+				))}
+			</ul>
+		</div>
+	);
+}
+
+// Shown when a re-opened tag points at a different image than last time
+export function TagMovedBanner() {
+	const { tagMutation, dismissTagMutation } = useLayersStore();
+	const [diff, setDiff] = useState<TagVersionDiff | null>(null);
+	const [isDiffing, setIsDiffing] = useState(false);
+
+	if (!tagMutation) return null;
+
+	// A diff for another tag is stale once a new warning comes in
+	const shownDiff = diff?.reference === tagMutation.reference ? diff : null;
+
+	const handleCompare = async () => {
+		setIsDiffing(true);
+		try {
+			setDiff(
+				await invoke<TagVersionDiff>("diff_tag_versions", {
+					reference: tagMutation.reference,
+				}),
+			);
+		} catch (error) {
+			console.error("Error diffing tag versions:", error);
+			toast.error(errorMessage(error, "Failed to compare tag versions"));
+		} finally {
+			setIsDiffing(false);
+		}
+	};
+
+	const handleDismiss = () => {
+		setDiff(null);
+		dismissTagMutation();
+	};
+
+	return (
+		<div className="flex-shrink-0 border-b border-amber-300 bg-amber-50 dark:border-amber-700 dark:bg-amber-950 px-4 py-2 text-sm">
+			<div className="flex items-center gap-2">
+				<AlertTriangle className="h-4 w-4 text-amber-500 flex-shrink-0" />
+				<span className="flex-1">
+					<span className="font-medium">{tagMutation.reference}</span> now
+					points at {shortId(tagMutation.current.image_id)}
+					{tagMutation.previous &&
+						`, it was ${shortId(tagMutation.previous.image_id)} when you last opened it`}
+				</span>
+				{tagMutation.previous_available ? (
+					!shownDiff && (
+						<Button
+							variant="outline"
+							size="sm"
+							onClick={handleCompare}
+							disabled={isDiffing}
+						>
+							{isDiffing ? (
+								<Loader2 className="h-4 w-4 mr-1 animate-spin" />
+							) : (
+								<GitCompare className="h-4 w-4 mr-1" />
+							)}
+							Compare
+						</Button>
+					)
+				) : (
+					<span className="text-muted-foreground">
+						The previous image is no longer available locally
+					</span>
+				)}
+				<Button
+					variant="ghost"
+					size="icon"
+					className="h-7 w-7"
+					onClick={handleDismiss}
+					title="Dismiss"
+				>
+					<X className="h-4 w-4" />
+				</Button>
+			</div>
+			{shownDiff && (
+				<div className="grid grid-cols-3 gap-4 mt-2 max-h-40 overflow-auto">
+					<LayerList title="Added" layers={shownDiff.added_layers} />
+					<LayerList title="Removed" layers={shownDiff.removed_layers} />
+					<LayerList title="Shared" layers={shownDiff.shared_layers} />
+				</div>
+			)}
+		</div>
+	);
+}
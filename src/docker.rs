@@ -1,43 +1,26 @@
 use anyhow::{anyhow, Result};
+use layers_core::docker::{
+    docker_command, get_image_history, history_layers, image_diff_ids, ImageLayer,
+};
+use layers_core::layer_tar::{container_relative, unpack_entries};
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
-use std::process::Command;
+use std::process::Stdio;
 use tempfile::TempDir;
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct DockerLayer {
-    pub id: String,
-    pub created_by: String,
-    pub size: u64,
-    pub created_at: String,
-}
+use tracing::warn;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DockerImage {
     pub id: String,
     pub tags: Vec<String>,
-    pub layers: Vec<DockerLayer>,
+    // Filesystem layers, base layer first
+    pub layers: Vec<ImageLayer>,
 }
 
 pub fn inspect_image(image_name: &str) -> Result<DockerImage> {
-    let output = Command::new("docker")
-        .args(["inspect", image_name])
-        .output()?;
-
-    if !output.status.success() {
-        return Err(anyhow!(
-            "Failed to inspect image: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
-    }
-
-    let inspect_output: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)?;
-    if inspect_output.is_empty() {
-        return Err(anyhow!("No image found with name: {}", image_name));
-    }
-
-    let image_data = &inspect_output[0];
+    let image_data = layers_core::docker::inspect_image(image_name).map_err(anyhow::Error::msg)?;
 
     let id = image_data["Id"]
         .as_str()
@@ -53,208 +36,108 @@ pub fn inspect_image(image_name: &str) -> Result<DockerImage> {
         })
         .unwrap_or_default();
 
-    let layers = image_data["RootFS"]["Layers"]
+    let diff_ids = image_data["RootFS"]["Layers"]
         .as_array()
         .ok_or_else(|| anyhow!("Failed to get image layers"))?
         .iter()
-        .enumerate()
-        .map(|(i, layer)| {
-            let layer_id = layer
+        .map(|layer| {
+            layer
                 .as_str()
-                .ok_or_else(|| anyhow!("Failed to get layer ID"))?
-                .to_string();
-
-            Ok(DockerLayer {
-                id: layer_id,
-                created_by: format!("Layer {}", i + 1), // Simplified for now
-                size: 0,                                // We'll get this from history
-                created_at: "".to_string(),             // We'll get this from history
-            })
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("Failed to get layer ID"))
         })
         .collect::<Result<Vec<_>>>()?;
 
+    // Commands and sizes come from the history, metadata-only entries have no
+    // files to show
+    let history = get_image_history(image_name).map_err(anyhow::Error::msg)?;
+    let mut layers: Vec<ImageLayer> = history_layers(&history, &diff_ids)
+        .into_iter()
+        .filter(|layer| !layer.empty)
+        .collect();
+    layers.reverse();
+
     Ok(DockerImage { id, tags, layers })
 }
 
-pub fn get_image_history(image_name: &str) -> Result<Vec<DockerLayer>> {
-    let output = Command::new("docker")
-        .args([
-            "history",
-            "--no-trunc",
-            "--format",
-            "{{.ID}}|{{.CreatedBy}}|{{.Size}}|{{.CreatedAt}}",
-            image_name,
-        ])
-        .output()?;
-
-    if !output.status.success() {
-        return Err(anyhow!(
-            "Failed to get image history: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
-    }
-
-    let history_output = String::from_utf8_lossy(&output.stdout);
-    let layers = history_output
-        .lines()
-        .filter(|line| !line.is_empty())
-        .map(|line| {
-            let parts: Vec<&str> = line.split('|').collect();
-            if parts.len() < 4 {
-                return Err(anyhow!("Invalid history line format"));
-            }
-
-            let size_str = parts[2].trim();
-            let size = if size_str.ends_with('B') {
-                // Parse size like "10MB", "1.5KB", etc.
-                let size_num = size_str
-                    .trim_end_matches(|c: char| c.is_alphabetic() || c == 'B')
-                    .trim();
-
-                let multiplier = if size_str.ends_with("KB") {
-                    1024
-                } else if size_str.ends_with("MB") {
-                    1024 * 1024
-                } else if size_str.ends_with("GB") {
-                    1024 * 1024 * 1024
-                } else {
-                    1
-                };
-
-                size_num.parse::<f64>().unwrap_or(0.0) as u64 * multiplier
-            } else {
-                size_str.parse().unwrap_or(0)
-            };
-
-            Ok(DockerLayer {
-                id: parts[0].to_string(),
-                created_by: parts[1].to_string(),
-                size,
-                created_at: parts[3].to_string(),
-            })
-        })
-        .collect::<Result<Vec<_>>>()?;
-
-    Ok(layers)
+// Entry of the manifest.json written by `docker save`
+#[derive(Debug, Deserialize)]
+struct SaveManifestEntry {
+    #[serde(rename = "Layers")]
+    layers: Vec<String>,
 }
 
+// The files of one filesystem layer, `layer_id` is its RootFS diff ID. The
+// image is saved once to find the layer's blob, only that blob is unpacked.
 pub fn extract_layer_files(image_name: &str, layer_id: &str) -> Result<TempDir> {
-    let temp_dir = TempDir::new()?;
-    let temp_path = temp_dir
-        .path()
-        .to_str()
-        .ok_or_else(|| anyhow!("Failed to get temp dir path"))?;
-
-    // Create a temporary container
-    let container_id = {
-        let output = Command::new("docker")
-            .args(["create", image_name])
-            .output()?;
-
-        if !output.status.success() {
-            return Err(anyhow!(
-                "Failed to create container: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
-
-        String::from_utf8_lossy(&output.stdout).trim().to_string()
-    };
-
-    // Ensure container is removed at the end
-    let _cleanup = scopeguard::guard(container_id.clone(), |id| {
-        let _ = Command::new("docker").args(["rm", &id]).output();
-    });
-
-    // Export the container
-    let output = Command::new("docker")
-        .args([
-            "export",
-            "-o",
-            &format!("{}/container.tar", temp_path),
-            &container_id,
-        ])
-        .output()?;
+    let diff_ids = image_diff_ids(image_name).map_err(anyhow::Error::msg)?;
+    let index = diff_ids
+        .iter()
+        .position(|diff_id| diff_id == layer_id)
+        .ok_or_else(|| anyhow!("{} has no layer {}", image_name, layer_id))?;
 
-    if !output.status.success() {
-        return Err(anyhow!(
-            "Failed to export container: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
+    let temp_dir = TempDir::new()?;
+    let save_dir = temp_dir.path().join("image");
+    save_image(image_name, &save_dir)?;
+
+    let manifest: Vec<SaveManifestEntry> =
+        serde_json::from_slice(&fs::read(save_dir.join("manifest.json"))?)
+            .map_err(|e| anyhow!("Failed to parse manifest.json: {}", e))?;
+    let blob = manifest
+        .first()
+        .and_then(|entry| entry.layers.get(index))
+        .ok_or_else(|| anyhow!("docker save archive has no blob for {}", layer_id))?;
+
+    let extract_dir = temp_dir.path().join("extracted");
+    let reader = decompress(BufReader::new(File::open(
+        save_dir.join(container_relative(Path::new(blob))),
+    )?))?;
+    let extraction = unpack_entries(reader, &extract_dir, true, |_| Ok(true))
+        .map_err(|e| anyhow!("Failed to extract layer: {}", e))?;
+    for (path, error) in &extraction.skipped {
+        warn!("Skipped {}: {}", path, error);
     }
+    fs::remove_dir_all(&save_dir)?;
 
-    // Extract the layer files
-    // This is a simplified approach - in a real implementation,
-    // we would need to identify the specific layer files
-    let extract_dir = Path::new(temp_path).join("extracted");
-    fs::create_dir_all(&extract_dir)?;
-
-    let output = Command::new("tar")
-        .args([
-            "-xf",
-            &format!("{}/container.tar", temp_path),
-            "-C",
-            extract_dir.to_str().unwrap(),
-        ])
-        .output()?;
+    Ok(temp_dir)
+}
 
+// Unpack `docker save` of the image into `dir` without a temporary tarball
+fn save_image(image_name: &str, dir: &Path) -> Result<()> {
+    let mut child = docker_command()
+        .args(["save", image_name])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to capture docker save output"))?;
+    let unpacked = unpack_entries(stdout, dir, false, |_| Ok(true));
+    if unpacked.is_err() {
+        // Nobody reads the rest of the archive, docker would block on it
+        let _ = child.kill();
+    }
+    let output = child.wait_with_output()?;
     if !output.status.success() {
         return Err(anyhow!(
-            "Failed to extract container: {}",
-            String::from_utf8_lossy(&output.stderr)
+            "Failed to save image: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
         ));
     }
-
-    Ok(temp_dir)
+    unpacked.map_err(|e| anyhow!("Failed to unpack docker save output: {}", e))?;
+    Ok(())
 }
 
-pub fn diff_layers(layer1_path: &Path, layer2_path: &Path) -> Result<Vec<(String, String)>> {
-    let mut differences = Vec::new();
-
-    // Walk through files in layer1
-    for entry in walkdir::WalkDir::new(layer1_path) {
-        let entry = entry?;
-        if entry.file_type().is_file() {
-            let rel_path = entry.path().strip_prefix(layer1_path)?;
-            let layer2_file = layer2_path.join(rel_path);
-
-            if layer2_file.exists() {
-                // Both files exist, compare them
-                let content1 = fs::read_to_string(entry.path())?;
-                let content2 = fs::read_to_string(&layer2_file)?;
-
-                if content1 != content2 {
-                    differences.push((
-                        rel_path.to_string_lossy().to_string(),
-                        format!("Modified: {}", rel_path.display()),
-                    ));
-                }
-            } else {
-                // File exists in layer1 but not in layer2
-                differences.push((
-                    rel_path.to_string_lossy().to_string(),
-                    format!("Removed: {}", rel_path.display()),
-                ));
-            }
-        }
-    }
-
-    // Find files that exist only in layer2
-    for entry in walkdir::WalkDir::new(layer2_path) {
-        let entry = entry?;
-        if entry.file_type().is_file() {
-            let rel_path = entry.path().strip_prefix(layer2_path)?;
-            let layer1_file = layer1_path.join(rel_path);
-
-            if !layer1_file.exists() {
-                // File exists in layer2 but not in layer1
-                differences.push((
-                    rel_path.to_string_lossy().to_string(),
-                    format!("Added: {}", rel_path.display()),
-                ));
-            }
-        }
-    }
-
-    Ok(differences)
+// Layer blobs are plain tars for the classic image store, gzip or zstd
+// compressed for the containerd one
+fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> Result<Box<dyn Read + 'a>> {
+    let magic = reader.fill_buf()?;
+    Ok(if magic.starts_with(&[0x1f, 0x8b]) {
+        Box::new(flate2::bufread::MultiGzDecoder::new(reader))
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Box::new(zstd::stream::read::Decoder::with_buffer(reader)?)
+    } else {
+        Box::new(reader)
+    })
 }
@@ -1,7 +1,14 @@
-use anyhow::Result;
-use gpui::{div, prelude::*, rgb, FontWeight, IntoElement};
+use gpui::{
+    div, prelude::*, rgb, ClipboardItem, Context, Div, FocusHandle, Focusable, IntoElement,
+    KeyDownEvent, MouseButton, MouseDownEvent, Window,
+};
 use regex::Regex;
 use std::collections::HashMap;
+use std::ops::Range;
+
+// Undo steps kept per editor, older ones are dropped
+const MAX_UNDO: usize = 200;
+const INSTRUCTION_PATTERN: &str = r"^\s*(FROM|RUN|CMD|LABEL|EXPOSE|ENV|ADD|COPY|ENTRYPOINT|VOLUME|USER|WORKDIR|ARG|ONBUILD|STOPSIGNAL|HEALTHCHECK|SHELL)\s+";
 
 /// Define tooltip information for Dockerfile commands
 pub struct DockerfileCommand {
@@ -182,7 +189,7 @@ pub fn parse_dockerfile_blocks(content: &str) -> Vec<(usize, usize, String)> {
     let mut current_instruction = String::new();
 
     // Regular expression to match Dockerfile instructions
-    let re = Regex::new(r"^\s*(FROM|RUN|CMD|LABEL|EXPOSE|ENV|ADD|COPY|ENTRYPOINT|VOLUME|USER|WORKDIR|ARG|ONBUILD|STOPSIGNAL|HEALTHCHECK|SHELL)\s+").unwrap();
+    let re = Regex::new(INSTRUCTION_PATTERN).unwrap();
 
     for (i, line) in content.lines().enumerate() {
         // Skip empty lines and comments
@@ -217,29 +224,451 @@ pub fn parse_dockerfile_blocks(content: &str) -> Vec<(usize, usize, String)> {
     blocks
 }
 
-/// Function to render Dockerfile with syntax highlighting and tooltips
-pub fn render_dockerfile_with_highlighting(content: &str) -> Result<impl IntoElement> {
-    // Get the map of Dockerfile commands
-    let commands = get_dockerfile_commands();
+// Kinds of edits, consecutive ones of the same kind are undone together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Typing,
+    Deleting,
+    Other,
+}
 
-    // Parse the Dockerfile content into blocks
-    let blocks = parse_dockerfile_blocks(content);
+#[derive(Debug, Clone)]
+struct Snapshot {
+    text: String,
+    cursor: usize,
+    anchor: usize,
+}
 
-    // Regular expression to match Dockerfile instructions
-    let re = Regex::new(r"^\s*(FROM|RUN|CMD|LABEL|EXPOSE|ENV|ADD|COPY|ENTRYPOINT|VOLUME|USER|WORKDIR|ARG|ONBUILD|STOPSIGNAL|HEALTHCHECK|SHELL)\s+").unwrap();
+// Editor text with cursor, selection and undo, offsets are bytes on char boundaries
+#[derive(Debug, Clone, Default)]
+pub struct EditorBuffer {
+    text: String,
+    cursor: usize,
+    // Other end of the selection, equal to the cursor when nothing is selected
+    anchor: usize,
+    undo_stack: Vec<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    last_edit: Option<EditKind>,
+}
 
-    // Create elements for each line
-    let line_elements = content
-        .lines()
-        .enumerate()
-        .map(|(i, line)| {
-            // Determine if this line is the start of an instruction
-            let mut instruction = String::new();
-            if let Some(captures) = re.captures(line) {
-                if let Some(instr) = captures.get(1) {
-                    instruction = instr.as_str().to_string();
+impl EditorBuffer {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn selection(&self) -> Range<usize> {
+        self.cursor.min(self.anchor)..self.cursor.max(self.anchor)
+    }
+
+    pub fn selected_text(&self) -> &str {
+        &self.text[self.selection()]
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            text: self.text.clone(),
+            cursor: self.cursor,
+            anchor: self.anchor,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.text = snapshot.text;
+        self.cursor = snapshot.cursor;
+        self.anchor = snapshot.anchor;
+        self.last_edit = None;
+    }
+
+    // Saves the state before an edit, unless it continues the previous one
+    fn begin_edit(&mut self, kind: EditKind) {
+        if kind == EditKind::Other || self.last_edit != Some(kind) {
+            self.undo_stack.push(self.snapshot());
+            if self.undo_stack.len() > MAX_UNDO {
+                self.undo_stack.remove(0);
+            }
+        }
+        self.redo_stack.clear();
+        self.last_edit = Some(kind);
+    }
+
+    fn replace_selection(&mut self, text: &str) {
+        let selection = self.selection();
+        self.text.replace_range(selection.clone(), text);
+        self.cursor = selection.start + text.len();
+        self.anchor = self.cursor;
+    }
+
+    // Replaces the selection, typed characters extend the same undo step
+    pub fn insert(&mut self, text: &str) {
+        let kind = if text.chars().count() == 1 && text != "\n" && self.anchor == self.cursor {
+            EditKind::Typing
+        } else {
+            EditKind::Other
+        };
+        self.begin_edit(kind);
+        self.replace_selection(text);
+    }
+
+    // Deletes the selection, or the character before the cursor
+    pub fn backspace(&mut self) {
+        if self.anchor == self.cursor {
+            if self.cursor == 0 {
+                return;
+            }
+            self.anchor = self.prev_boundary(self.cursor);
+        }
+        self.begin_edit(EditKind::Deleting);
+        self.replace_selection("");
+    }
+
+    // Deletes the selection, or the character after the cursor
+    pub fn delete(&mut self) {
+        if self.anchor == self.cursor {
+            if self.cursor == self.text.len() {
+                return;
+            }
+            self.anchor = self.next_boundary(self.cursor);
+        }
+        self.begin_edit(EditKind::Deleting);
+        self.replace_selection("");
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.undo_stack.pop() else {
+            return false;
+        };
+        self.redo_stack.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.redo_stack.pop() else {
+            return false;
+        };
+        self.undo_stack.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.cursor = self.text.len();
+        self.last_edit = None;
+    }
+
+    // Moves the cursor, keeping the anchor when extending the selection
+    pub fn move_to(&mut self, offset: usize, extend: bool) {
+        self.cursor = offset.min(self.text.len());
+        if !extend {
+            self.anchor = self.cursor;
+        }
+        self.last_edit = None;
+    }
+
+    pub fn move_left(&mut self, extend: bool) {
+        // Without shift, a selection collapses to its start
+        let offset = if !extend && self.anchor != self.cursor {
+            self.selection().start
+        } else {
+            self.prev_boundary(self.cursor)
+        };
+        self.move_to(offset, extend);
+    }
+
+    pub fn move_right(&mut self, extend: bool) {
+        let offset = if !extend && self.anchor != self.cursor {
+            self.selection().end
+        } else {
+            self.next_boundary(self.cursor)
+        };
+        self.move_to(offset, extend);
+    }
+
+    pub fn move_up(&mut self, extend: bool) {
+        let start = self.line_start(self.cursor);
+        let offset = if start == 0 {
+            0
+        } else {
+            let column = self.text[start..self.cursor].chars().count();
+            self.offset_in_line(self.line_start(start - 1), column)
+        };
+        self.move_to(offset, extend);
+    }
+
+    pub fn move_down(&mut self, extend: bool) {
+        let end = self.line_end(self.cursor);
+        let offset = if end == self.text.len() {
+            end
+        } else {
+            let column = self.text[self.line_start(self.cursor)..self.cursor]
+                .chars()
+                .count();
+            self.offset_in_line(end + 1, column)
+        };
+        self.move_to(offset, extend);
+    }
+
+    pub fn move_to_line_start(&mut self, extend: bool) {
+        self.move_to(self.line_start(self.cursor), extend);
+    }
+
+    pub fn move_to_line_end(&mut self, extend: bool) {
+        self.move_to(self.line_end(self.cursor), extend);
+    }
+
+    fn prev_boundary(&self, offset: usize) -> usize {
+        self.text[..offset]
+            .chars()
+            .next_back()
+            .map(|c| offset - c.len_utf8())
+            .unwrap_or(0)
+    }
+
+    fn next_boundary(&self, offset: usize) -> usize {
+        self.text[offset..]
+            .chars()
+            .next()
+            .map(|c| offset + c.len_utf8())
+            .unwrap_or(offset)
+    }
+
+    fn line_start(&self, offset: usize) -> usize {
+        self.text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0)
+    }
+
+    fn line_end(&self, offset: usize) -> usize {
+        self.text[offset..]
+            .find('\n')
+            .map(|i| offset + i)
+            .unwrap_or(self.text.len())
+    }
+
+    // Offset of `column` characters into the line starting at `start`,
+    // clamped to the line's end
+    fn offset_in_line(&self, start: usize, column: usize) -> usize {
+        let end = self.line_end(start);
+        self.text[start..end]
+            .char_indices()
+            .nth(column)
+            .map(|(i, _)| start + i)
+            .unwrap_or(end)
+    }
+}
+
+// Editable Dockerfile view with highlighting and tooltips
+pub struct DockerfileEditor {
+    buffer: EditorBuffer,
+    focus_handle: FocusHandle,
+    commands: HashMap<String, DockerfileCommand>,
+    instruction_re: Regex,
+}
+
+impl DockerfileEditor {
+    pub fn new(content: &str, cx: &mut Context<Self>) -> Self {
+        Self {
+            buffer: EditorBuffer::new(content),
+            focus_handle: cx.focus_handle(),
+            commands: get_dockerfile_commands(),
+            instruction_re: Regex::new(INSTRUCTION_PATTERN).unwrap(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        self.buffer.text()
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        let keystroke = &event.keystroke;
+        let modifiers = &keystroke.modifiers;
+        let extend = modifiers.shift;
+        let buffer = &mut self.buffer;
+
+        // Cmd on macOS, Ctrl elsewhere
+        if modifiers.secondary() {
+            match keystroke.key.as_str() {
+                "a" => buffer.select_all(),
+                "z" if extend => {
+                    buffer.redo();
+                }
+                "z" => {
+                    buffer.undo();
+                }
+                "y" => {
+                    buffer.redo();
+                }
+                "c" | "x" => {
+                    if buffer.selection().is_empty() {
+                        return;
+                    }
+                    cx.write_to_clipboard(ClipboardItem::new_string(
+                        buffer.selected_text().to_string(),
+                    ));
+                    if keystroke.key == "x" {
+                        buffer.delete();
+                    }
                 }
+                "v" => {
+                    let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) else {
+                        return;
+                    };
+                    buffer.insert(&text.replace("\r\n", "\n"));
+                }
+                _ => return,
+            }
+            cx.stop_propagation();
+            cx.notify();
+            return;
+        }
+
+        match keystroke.key.as_str() {
+            "left" => buffer.move_left(extend),
+            "right" => buffer.move_right(extend),
+            "up" => buffer.move_up(extend),
+            "down" => buffer.move_down(extend),
+            "home" => buffer.move_to_line_start(extend),
+            "end" => buffer.move_to_line_end(extend),
+            "backspace" => buffer.backspace(),
+            "delete" => buffer.delete(),
+            "enter" => buffer.insert("\n"),
+            // Dockerfiles are indented with spaces
+            "tab" => buffer.insert("    "),
+            _ => match keystroke.key_char.as_deref() {
+                Some(text) if !modifiers.control && !modifiers.platform => buffer.insert(text),
+                _ => return,
+            },
+        }
+        cx.stop_propagation();
+        cx.notify();
+    }
+
+    // Clicking a line focuses the editor and puts the cursor at its end
+    fn on_line_click(
+        &mut self,
+        line_end: usize,
+        event: &MouseDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        window.focus(&self.focus_handle);
+        self.buffer.move_to(line_end, event.modifiers.shift);
+        cx.notify();
+    }
+
+    // The line split where the selection starts and ends, with the cursor
+    // drawn between two pieces
+    fn render_line_text(&self, line: &str, start: usize, focused: bool) -> Div {
+        let end = start + line.len();
+        let selection = self.buffer.selection();
+        let cursor = self.buffer.cursor();
+        let mut splits = vec![start, end];
+        for offset in [selection.start, selection.end, cursor] {
+            if offset > start && offset < end {
+                splits.push(offset);
             }
+        }
+        splits.sort();
+        splits.dedup();
+
+        let mut text = div().flex().whitespace_nowrap();
+        for piece in splits.windows(2) {
+            if focused && piece[0] == cursor {
+                text = text.child(render_cursor());
+            }
+            let selected = piece[0] >= selection.start && piece[1] <= selection.end;
+            text = text.child(
+                div()
+                    .when(selected, |piece| piece.bg(rgb(0x3b82f6)))
+                    .child(line[piece[0] - start..piece[1] - start].to_string()),
+            );
+        }
+        if focused && cursor == end {
+            text = text.child(render_cursor());
+        }
+        text
+    }
+
+    fn render_tooltip(&self, instruction: &str, cmd_info: &DockerfileCommand) -> Div {
+        div()
+            .absolute()
+            .left_0()
+            .top_full()
+            .mt_2()
+            .w_96()
+            .p_4()
+            .bg(rgb(0x1e293b))
+            .border_1()
+            .border_color(rgb(0x3b82f6))
+            .rounded_md()
+            .shadow_lg()
+            .invisible()
+            .group_hover("tooltip", |s| s.visible())
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .child(
+                        div()
+                            .text_lg()
+                            .text_color(rgb(0x3b82f6))
+                            .child(instruction.to_string()),
+                    )
+                    .child(div().child(cmd_info.description.clone()))
+                    .child(div().mt_2().text_color(rgb(0xf59e0b)).child("Side Effect:"))
+                    .child(div().child(cmd_info.side_effect.clone()))
+                    .child(div().mt_2().text_color(rgb(0x10b981)).child("Example:"))
+                    .child(
+                        div()
+                            .p_2()
+                            .bg(rgb(0x374151))
+                            .rounded_md()
+                            .child(cmd_info.example.clone()),
+                    ),
+            )
+    }
+}
+
+fn render_cursor() -> Div {
+    div().w(gpui::px(2.0)).h_5().bg(rgb(0xe2e8f0))
+}
+
+impl Focusable for DockerfileEditor {
+    fn focus_handle(&self, _cx: &gpui::App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for DockerfileEditor {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let text = self.buffer.text().to_string();
+        let focused = self.focus_handle.is_focused(window);
+        let blocks = parse_dockerfile_blocks(&text);
+
+        // split keeps the empty line after a trailing newline, the cursor
+        // can sit there
+        let mut start = 0;
+        let mut line_elements = Vec::new();
+        for (i, line) in text.split('\n').enumerate() {
+            let line_end = start + line.len();
+
+            // Determine if this line is the start of an instruction
+            let instruction = self
+                .instruction_re
+                .captures(line)
+                .and_then(|captures| captures.get(1))
+                .map(|instruction| instruction.as_str().to_string());
 
             // Determine the background color based on block
             let bg_color = blocks
@@ -248,92 +677,52 @@ pub fn render_dockerfile_with_highlighting(content: &str) -> Result<impl IntoEle
                 .map(|_| rgb(0x1a202c)) // Slightly lighter background for blocks
                 .unwrap_or(rgb(0x2d3748)); // Default background
 
-            // Create element for this line
-            let line_element =
+            let line_text = self.render_line_text(line, start, focused);
+            let content = match instruction
+                .as_ref()
+                .and_then(|instruction| Some((instruction, self.commands.get(instruction)?)))
+            {
+                // Line with Dockerfile instruction - add tooltip
+                Some((instruction, cmd_info)) => div()
+                    .relative()
+                    .group("tooltip")
+                    .child(line_text.text_color(rgb(0x3b82f6))) // Highlight instruction
+                    .child(self.render_tooltip(instruction, cmd_info)),
+                None => line_text,
+            };
+
+            line_elements.push(
                 div()
                     .flex()
+                    .h_6()
                     .py_1()
                     .px_2()
                     .bg(bg_color)
-                    .child(div().flex_grow().child(if instruction.is_empty() {
-                        // Regular line
-                        div().child(line.to_string())
-                    } else if let Some(cmd_info) = commands.get(&instruction) {
-                        // Line with Dockerfile instruction - add tooltip
-                        div()
-                            .relative()
-                            .group("tooltip")
-                            .child(
-                                div().child(line.to_string()).text_color(rgb(0x3b82f6)), // Highlight instruction
-                            )
-                            .child(
-                                div()
-                                    .absolute()
-                                    .left_0()
-                                    .top_full()
-                                    .mt_2()
-                                    .w_96()
-                                    .p_4()
-                                    .bg(rgb(0x1e293b))
-                                    .border_1()
-                                    .border_color(rgb(0x3b82f6))
-                                    .rounded_md()
-                                    .shadow_lg()
-                                    .visibility_hidden() // Use visibility_hidden instead of display_none
-                                    .group_hover(|s| s.visibility_visible()) // Use visibility_visible instead of display
-                                    .child(
-                                        div()
-                                            .flex()
-                                            .flex_col()
-                                            .gap_2()
-                                            .child(
-                                                div()
-                                                    .text_lg()
-                                                    .text_color(rgb(0x3b82f6))
-                                                    .child(instruction.clone()),
-                                            )
-                                            .child(div().child(cmd_info.description.clone()))
-                                            .child(
-                                                div()
-                                                    .mt_2()
-                                                    .text_color(rgb(0xf59e0b))
-                                                    .child("Side Effect:"),
-                                            )
-                                            .child(div().child(cmd_info.side_effect.clone()))
-                                            .child(
-                                                div()
-                                                    .mt_2()
-                                                    .text_color(rgb(0x10b981))
-                                                    .child("Example:"),
-                                            )
-                                            .child(
-                                                div()
-                                                    .p_2()
-                                                    .bg(rgb(0x374151))
-                                                    .rounded_md()
-                                                    .child(cmd_info.example.clone()),
-                                            ),
-                                    ),
-                            )
-                    } else {
-                        // Instruction without tooltip info
-                        div().child(line.to_string())
-                    }));
-
-            line_element
-        })
-        .collect::<Vec<_>>();
-
-    // Create a container for all lines
-    let editor = div()
-        .flex()
-        .flex_col()
-        .w_full()
-        .h_full()
-        .overflow_y_visible() // Use overflow_y_visible instead of overflow_y_auto
-        .bg(rgb(0x2d3748))
-        .text_color(rgb(0xe2e8f0))
-        .children(line_elements);
-
-    Ok(editor)
+                    .cursor_text()
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, event, window, cx| {
+                            this.on_line_click(line_end, event, window, cx)
+                        }),
+                    )
+                    .child(div().flex_grow().child(content)),
+            );
+            start = line_end + 1;
+        }
+
+        // Create a container for all lines
+        div()
+            .id("dockerfile-editor")
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .flex()
+            .flex_col()
+            .w_full()
+            .h_full()
+            .overflow_y_scroll()
+            .bg(rgb(0x2d3748))
+            .text_color(rgb(0xe2e8f0))
+            .font_family("monospace")
+            .children(line_elements)
+    }
 }
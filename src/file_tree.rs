@@ -0,0 +1,327 @@
+use anyhow::Result;
+use gpui::{div, prelude::*, px, rgb, Context, IntoElement, SharedString, Task, Window};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use tracing::warn;
+
+use crate::docker;
+use crate::ui::{
+    THEME_BG_ACCENT_HOVER, THEME_BG_DESTRUCTIVE, THEME_TEXT_MUTED, THEME_TEXT_PRIMARY,
+    THEME_TEXT_SECONDARY,
+};
+
+// Indentation per directory level
+const INDENT: f32 = 16.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Directory,
+    File,
+    Symlink,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileNode {
+    pub name: String,
+    // Relative to the root of the extracted filesystem
+    pub path: PathBuf,
+    pub kind: FileKind,
+    // Everything below it for directories
+    pub size: u64,
+}
+
+enum Extraction {
+    Loading,
+    Ready(TempDir),
+    Failed(String),
+}
+
+// Tree of a layer's files, directories are listed when first expanded
+pub struct FileTree {
+    extraction: Extraction,
+    // Children of every directory listed so far, by relative path
+    children: HashMap<PathBuf, Vec<FileNode>>,
+    expanded: HashSet<PathBuf>,
+    // Directories being listed
+    loading: HashSet<PathBuf>,
+    // Why a directory couldn't be listed, shown in place of its children
+    errors: HashMap<PathBuf, String>,
+    _extract_task: Task<()>,
+}
+
+impl FileTree {
+    pub fn new(image_name: String, layer_id: String, cx: &mut Context<Self>) -> Self {
+        let extraction =
+            cx.background_spawn(async move { docker::extract_layer_files(&image_name, &layer_id) });
+        let extract_task = cx.spawn(async move |this, cx| {
+            let result = extraction.await;
+            let _ = this.update(cx, |this, cx| {
+                match result {
+                    Ok(dir) => {
+                        this.extraction = Extraction::Ready(dir);
+                        this.expand(PathBuf::new(), cx);
+                    }
+                    Err(err) => this.extraction = Extraction::Failed(format!("Error: {}", err)),
+                }
+                cx.notify();
+            });
+        });
+
+        Self {
+            extraction: Extraction::Loading,
+            children: HashMap::new(),
+            expanded: HashSet::new(),
+            loading: HashSet::new(),
+            errors: HashMap::new(),
+            _extract_task: extract_task,
+        }
+    }
+
+    fn root(&self) -> Option<PathBuf> {
+        match &self.extraction {
+            Extraction::Ready(dir) => Some(dir.path().join("extracted")),
+            _ => None,
+        }
+    }
+
+    fn expand(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        self.expanded.insert(path.clone());
+        if self.children.contains_key(&path) || !self.loading.insert(path.clone()) {
+            return;
+        }
+        let Some(root) = self.root() else {
+            return;
+        };
+        // Expanding again retries a listing that failed
+        self.errors.remove(&path);
+        // Directory sizes walk everything below, keep that off the UI thread
+        let listing = cx.background_spawn({
+            let path = path.clone();
+            async move { list_directory(&root, &path) }
+        });
+        cx.spawn(async move |this, cx| {
+            let result = listing.await;
+            let _ = this.update(cx, |this, cx| {
+                this.loading.remove(&path);
+                match result {
+                    Ok(children) => {
+                        this.children.insert(path, children);
+                    }
+                    Err(err) => {
+                        warn!("Failed to list {:?}: {}", path, err);
+                        this.errors.insert(path, format!("Error: {}", err));
+                    }
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    fn toggle(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        if !self.expanded.remove(&path) {
+            self.expand(path, cx);
+        }
+        cx.notify();
+    }
+
+    // Rows of the visible nodes below `path`, depth first
+    fn render_children(
+        &self,
+        path: &Path,
+        depth: usize,
+        rows: &mut Vec<gpui::AnyElement>,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(children) = self.children.get(path) else {
+            if self.loading.contains(path) {
+                rows.push(
+                    div()
+                        .pl(px(INDENT * depth as f32))
+                        .text_color(rgb(THEME_TEXT_MUTED))
+                        .child("Loading...")
+                        .into_any_element(),
+                );
+            } else if let Some(error) = self.errors.get(path) {
+                rows.push(
+                    div()
+                        .pl(px(INDENT * depth as f32))
+                        .text_color(rgb(THEME_BG_DESTRUCTIVE))
+                        .child(error.clone())
+                        .into_any_element(),
+                );
+            }
+            return;
+        };
+        for node in children {
+            let expanded = self.expanded.contains(&node.path);
+            rows.push(
+                self.render_node(node, depth, expanded, cx)
+                    .into_any_element(),
+            );
+            if node.kind == FileKind::Directory && expanded {
+                self.render_children(&node.path, depth + 1, rows, cx);
+            }
+        }
+    }
+
+    fn render_node(
+        &self,
+        node: &FileNode,
+        depth: usize,
+        expanded: bool,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let is_directory = node.kind == FileKind::Directory;
+        let path = node.path.clone();
+        div()
+            .id(SharedString::from(node.path.to_string_lossy().to_string()))
+            .flex()
+            .items_center()
+            .gap_2()
+            .pl(px(INDENT * depth as f32))
+            .pr_2()
+            .py_1()
+            .hover(|s| s.bg(rgb(THEME_BG_ACCENT_HOVER)))
+            .when(is_directory, |row| {
+                row.cursor_pointer().on_click(
+                    cx.listener(move |this, _, _window, cx| this.toggle(path.clone(), cx)),
+                )
+            })
+            .child(div().w_3().text_color(rgb(THEME_TEXT_MUTED)).child(
+                match (is_directory, expanded) {
+                    (true, true) => "▾",
+                    (true, false) => "▸",
+                    (false, _) => "",
+                },
+            ))
+            .child(div().child(file_icon(node, expanded)))
+            .child(
+                div()
+                    .flex_grow()
+                    .text_color(rgb(THEME_TEXT_PRIMARY))
+                    .child(node.name.clone()),
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(rgb(THEME_TEXT_SECONDARY))
+                    .child(format_size(node.size)),
+            )
+    }
+}
+
+impl Render for FileTree {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let container = div()
+            .id("file-tree")
+            .flex()
+            .flex_col()
+            .max_h_96()
+            .overflow_y_scroll()
+            .text_sm();
+        match &self.extraction {
+            Extraction::Loading => container
+                .text_color(rgb(THEME_TEXT_MUTED))
+                .child("Extracting layer..."),
+            Extraction::Failed(error) => container
+                .text_color(rgb(THEME_BG_DESTRUCTIVE))
+                .child(error.clone()),
+            Extraction::Ready(_) => {
+                let mut rows = Vec::new();
+                self.render_children(Path::new(""), 0, &mut rows, cx);
+                if rows.is_empty() {
+                    container
+                        .text_color(rgb(THEME_TEXT_MUTED))
+                        .child("No files in this layer")
+                } else {
+                    container.children(rows)
+                }
+            }
+        }
+    }
+}
+
+// Entries of one directory, directories first and then by name
+fn list_directory(root: &Path, path: &Path) -> Result<Vec<FileNode>> {
+    let mut nodes = Vec::new();
+    for entry in fs::read_dir(root.join(path))? {
+        let entry = entry?;
+        let metadata = entry.path().symlink_metadata()?;
+        let kind = if metadata.file_type().is_symlink() {
+            FileKind::Symlink
+        } else if metadata.is_dir() {
+            FileKind::Directory
+        } else {
+            FileKind::File
+        };
+        let size = match kind {
+            FileKind::Directory => directory_size(&entry.path()),
+            _ => metadata.len(),
+        };
+        nodes.push(FileNode {
+            name: entry.file_name().to_string_lossy().to_string(),
+            path: path.join(entry.file_name()),
+            kind,
+            size,
+        });
+    }
+    nodes.sort_by(|a, b| {
+        (a.kind != FileKind::Directory, &a.name).cmp(&(b.kind != FileKind::Directory, &b.name))
+    });
+    Ok(nodes)
+}
+
+// Bytes of the regular files below a directory, symlinks aren't followed
+fn directory_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+fn file_icon(node: &FileNode, expanded: bool) -> &'static str {
+    match node.kind {
+        FileKind::Directory if expanded => "📂",
+        FileKind::Directory => "📁",
+        FileKind::Symlink => "🔗",
+        FileKind::File => {
+            let extension = Path::new(&node.name)
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            match extension.as_str() {
+                "sh" | "bash" | "py" | "rb" | "pl" | "js" | "ts" | "go" | "rs" | "java" | "c"
+                | "h" => "📜",
+                "json" | "yaml" | "yml" | "toml" | "ini" | "conf" | "cfg" | "xml" => "⚙️",
+                "tar" | "gz" | "tgz" | "zip" | "xz" | "bz2" | "zst" | "deb" | "rpm" | "apk"
+                | "jar" => "📦",
+                "png" | "jpg" | "jpeg" | "gif" | "svg" | "ico" | "webp" => "🖼️",
+                "so" | "a" | "o" => "🧩",
+                "pem" | "crt" | "key" => "🔑",
+                "md" | "txt" | "rst" => "📝",
+                _ => "📄",
+            }
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
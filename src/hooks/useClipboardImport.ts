@@ -0,0 +1,55 @@
+import { useEffect, useRef } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { toast } from "sonner";
+import type { ClipboardContent } from "../utils/types";
+
+// Offer to analyze a Dockerfile or inspect an image reference found on the
+// clipboard whenever the window regains focus
+export function useClipboardImport(
+	onAnalyze: (content: string) => void,
+	onInspect: (reference: string) => void,
+) {
+	// Only offer each clipboard content once
+	const lastSeen = useRef<string | null>(null);
+
+	useEffect(() => {
+		async function handleFocus() {
+			let text: string;
+			try {
+				text = await navigator.clipboard.readText();
+			} catch {
+				// Clipboard access denied or empty
+				return;
+			}
+			if (!text || text === lastSeen.current) {
+				return;
+			}
+			lastSeen.current = text;
+
+			const content = await invoke<ClipboardContent>(
+				"detect_clipboard_content",
+				{ text },
+			).catch(() => null);
+			if (content?.kind === "dockerfile") {
+				toast("Dockerfile on clipboard", {
+					description: `${content.instructions} instructions${content.base_image ? `, based on ${content.base_image}` : ""}`,
+					action: {
+						label: "Analyze from clipboard",
+						onClick: () => onAnalyze(content.content),
+					},
+				});
+			} else if (content?.kind === "image_reference") {
+				toast("Image reference on clipboard", {
+					description: content.reference,
+					action: {
+						label: "Inspect from clipboard",
+						onClick: () => onInspect(content.reference),
+					},
+				});
+			}
+		}
+
+		window.addEventListener("focus", handleFocus);
+		return () => window.removeEventListener("focus", handleFocus);
+	}, [onAnalyze, onInspect]);
+}
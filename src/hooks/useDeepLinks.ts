@@ -0,0 +1,21 @@
+import { useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type { DeepLinkTarget } from "../utils/types";
+
+// Open layers:// links, both the one the app was launched with and ones
+// opened while it is running
+export function useDeepLinks(onOpen: (target: DeepLinkTarget) => void) {
+	useEffect(() => {
+		invoke<DeepLinkTarget | null>("get_launch_deep_link")
+			.then((target) => target && onOpen(target))
+			.catch((error) => console.error("Failed to read launch link:", error));
+
+		const unlisten = listen<DeepLinkTarget>("deep_link_opened", (event) =>
+			onOpen(event.payload),
+		);
+		return () => {
+			unlisten.then((stop) => stop());
+		};
+	}, [onOpen]);
+}
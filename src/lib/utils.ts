@@ -1,6 +1,38 @@
 import { clsx, type ClassValue } from "clsx"
 import { twMerge } from "tailwind-merge"
 
+import type { LayersError } from "../utils/types"
+
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs))
 }
+
+export function formatBytes(bytes: number): string {
+  const units = ["B", "KB", "MB", "GB"]
+  let value = bytes
+  let unit = 0
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024
+    unit++
+  }
+  return `${value.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`
+}
+
+const recoveryHints: Partial<Record<LayersError["kind"], string>> = {
+  daemon_unavailable: "Start Docker and try again.",
+  image_not_found: "Pull the image or pick another one from the list.",
+  permission_denied: "Check that your user can access Docker and the file.",
+}
+
+// Readable message for a rejected command, with a hint on how to fix it
+export function errorMessage(error: unknown, fallback: string): string {
+  if (typeof error === "string") {
+    return error
+  }
+  const layersError = error as LayersError | null
+  if (!layersError?.message) {
+    return fallback
+  }
+  const hint = recoveryHints[layersError.kind]
+  return hint ? `${layersError.message}. ${hint}` : layersError.message
+}
@@ -1,9 +1,16 @@
 mod docker;
 mod dockerfile;
 mod dockerfile_editor;
+mod file_tree;
 mod ui;
 
-use gpui::{div, prelude::*, rgb, App, Context, FontWeight, Window};
+use dockerfile_editor::DockerfileEditor;
+use file_tree::FileTree;
+use gpui::{
+    div, prelude::*, px, rgb, Animation, AnimationExt, App, Application, ClickEvent, Context,
+    Entity, FocusHandle, FontWeight, KeyDownEvent, MouseButton, Task, Window,
+};
+use std::time::Duration;
 use ui::{ActiveTab, LayersApp};
 
 // Import theme constants from ui module
@@ -12,64 +19,132 @@ use ui::{
     THEME_BG_SECONDARY, THEME_BORDER, THEME_TEXT_MUTED, THEME_TEXT_PRIMARY, THEME_TEXT_SECONDARY,
 };
 
+// Shown until the user types their own Dockerfile
+const EXAMPLE_DOCKERFILE: &str = "# Enter your Dockerfile here\nFROM ubuntu:latest\n\nRUN apt-get update && apt-get install -y curl\n\nCOPY . /app\n\nCMD [\"echo\", \"Hello World\"]";
+
 struct AppState {
     app: LayersApp,
+    dockerfile_editor: Entity<DockerfileEditor>,
+    // Image name field in the header, typed into while focused
+    image_input: FocusHandle,
+    // Pending inspection, dropping it only discards the result, the docker
+    // inspect process itself runs to completion
+    inspection: Option<Task<()>>,
+    // Files of the selected layer
+    file_tree: Option<Entity<FileTree>>,
 }
 
 impl AppState {
-    fn new() -> Self {
+    fn new(cx: &mut Context<Self>) -> Self {
         Self {
             app: LayersApp::new(),
+            dockerfile_editor: cx.new(|cx| DockerfileEditor::new(EXAMPLE_DOCKERFILE, cx)),
+            image_input: cx.focus_handle(),
+            inspection: None,
+            file_tree: None,
         }
     }
 
-    fn inspect_image(&mut self, image_name: &str) {
+    // docker inspect can take seconds, it runs on the background executor so
+    // the window keeps rendering
+    fn inspect_image(&mut self, image_name: &str, cx: &mut Context<Self>) {
         let image_name = image_name.to_string();
         self.app.set_loading(true);
         self.app.set_image_name(image_name.clone());
+        cx.notify();
 
-        match docker::inspect_image(&image_name) {
-            Ok(image) => {
-                self.app.set_image(image);
-            }
-            Err(err) => {
-                self.app.set_error(format!("Error: {}", err));
-            }
-        }
+        let inspection = cx.background_spawn(async move { docker::inspect_image(&image_name) });
+        // Replacing an earlier inspection drops its stale result, its docker
+        // inspect still finishes on the background executor
+        self.inspection = Some(cx.spawn(async move |this, cx| {
+            let result = inspection.await;
+            let _ = this.update(cx, |this, cx| {
+                match result {
+                    Ok(image) => {
+                        this.app.set_image(image);
+                    }
+                    Err(err) => {
+                        this.app.set_error(format!("Error: {}", err));
+                    }
+                }
+                this.inspection = None;
+                cx.notify();
+            });
+        }));
     }
 
     fn analyze_dockerfile(&mut self, content: &str) {
-        let temp_path = std::env::temp_dir().join("temp_dockerfile");
-        std::fs::write(&temp_path, content).unwrap_or_else(|_| {
-            self.app
-                .set_error("Failed to write temporary Dockerfile".to_string());
-        });
+        let dockerfile = dockerfile::Dockerfile::parse(content);
+        let analysis = dockerfile::analyze(&dockerfile);
+        self.app.set_dockerfile(dockerfile);
+        self.app.set_dockerfile_analysis(analysis);
+    }
 
-        match dockerfile::Dockerfile::parse(&temp_path) {
-            Ok(dockerfile) => {
-                self.app.set_dockerfile(dockerfile);
+    fn select_layer(&mut self, index: usize, cx: &mut Context<Self>) {
+        self.app.select_layer(index);
+        let image_name = self.app.image_name.clone();
+        self.file_tree = self
+            .app
+            .image
+            .as_ref()
+            .and_then(|image| image.layers.get(index))
+            .map(|layer| {
+                let layer_id = layer.id.clone();
+                cx.new(|cx| FileTree::new(image_name, layer_id, cx))
+            });
+    }
+
+    fn switch_tab(&mut self, tab: ActiveTab) {
+        self.app.switch_tab(tab);
+    }
 
-                // Now we can use the analyze method directly
-                let analysis = self.app.dockerfile.as_ref().unwrap().analyze();
-                self.app.set_dockerfile_analysis(analysis);
+    // Inspect or Analyze, depending on the active tab
+    fn submit(&mut self, cx: &mut Context<Self>) {
+        match self.app.active_tab {
+            ActiveTab::ImageInspector => {
+                let image_name = self.app.image_name.trim().to_string();
+                if image_name.is_empty() {
+                    return;
+                }
+                self.inspect_image(&image_name, cx);
             }
-            Err(err) => {
-                self.app
-                    .set_error(format!("Failed to parse Dockerfile: {}", err));
+            ActiveTab::DockerfileAnalyzer => {
+                let content = self.dockerfile_editor.read(cx).text().to_string();
+                self.analyze_dockerfile(&content);
             }
         }
+        cx.notify();
+    }
 
-        // Clean up
-        let _ = std::fs::remove_file(temp_path);
+    fn on_submit(&mut self, _event: &ClickEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        self.submit(cx);
     }
 
-    fn switch_tab(&mut self, tab: ActiveTab) {
-        self.app.switch_tab(tab);
+    fn on_image_name_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let keystroke = &event.keystroke;
+        match keystroke.key.as_str() {
+            "backspace" => {
+                self.app.image_name.pop();
+            }
+            "enter" => return self.submit(cx),
+            _ => match keystroke.key_char.as_deref() {
+                Some(text) if !keystroke.modifiers.control && !keystroke.modifiers.platform => {
+                    self.app.image_name.push_str(text)
+                }
+                _ => return,
+            },
+        }
+        cx.notify();
     }
 }
 
 impl Render for AppState {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         div()
             .flex()
             .flex_col()
@@ -78,20 +153,20 @@ impl Render for AppState {
             .text_color(rgb(THEME_TEXT_PRIMARY))
             .p_4()
             .gap_4()
-            .child(self.render_header())
-            .child(self.render_tabs())
+            .child(self.render_header(cx))
+            .child(self.render_tabs(cx))
             .child(
                 div()
                     .flex()
                     .flex_grow()
                     .gap_4()
-                    .child(self.render_content()),
+                    .child(self.render_content(cx)),
             )
     }
 }
 
 impl AppState {
-    fn render_header(&self) -> impl IntoElement {
+    fn render_header(&self, cx: &mut Context<Self>) -> impl IntoElement {
         div()
             .flex()
             .items_center()
@@ -111,6 +186,13 @@ impl AppState {
                     .gap_2()
                     .child(match self.app.active_tab {
                         ActiveTab::ImageInspector => div()
+                            .id("image-name")
+                            .track_focus(&self.image_input)
+                            .on_key_down(cx.listener(Self::on_image_name_key_down))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|this, _, window, _cx| window.focus(&this.image_input)),
+                            )
                             .flex_grow()
                             .min_w_64()
                             .px_3()
@@ -123,7 +205,8 @@ impl AppState {
                                 "Enter image name...".into()
                             } else {
                                 self.app.image_name.to_string()
-                            }),
+                            })
+                            .into_any_element(),
                         ActiveTab::DockerfileAnalyzer => div()
                             .flex_grow()
                             .min_w_64()
@@ -133,10 +216,13 @@ impl AppState {
                             .border_1()
                             .border_color(rgb(THEME_BORDER))
                             .text_color(rgb(THEME_TEXT_SECONDARY))
-                            .child("Enter Dockerfile content..."),
+                            .child("Enter Dockerfile content...")
+                            .into_any_element(),
                     })
                     .child(
                         div()
+                            .id("submit")
+                            .on_click(cx.listener(Self::on_submit))
                             .px_4()
                             .py_2()
                             .bg(rgb(THEME_BG_ACCENT))
@@ -150,7 +236,7 @@ impl AppState {
             )
     }
 
-    fn render_tabs(&self) -> impl IntoElement {
+    fn render_tabs(&self, cx: &mut Context<Self>) -> impl IntoElement {
         div()
             .flex()
             .w_full()
@@ -159,6 +245,11 @@ impl AppState {
             .border_color(rgb(THEME_BORDER))
             .child(
                 div()
+                    .id("tab-image-inspector")
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.switch_tab(ActiveTab::ImageInspector);
+                        cx.notify();
+                    }))
                     .px_4()
                     .py_2()
                     .bg(if self.app.active_tab == ActiveTab::ImageInspector {
@@ -178,6 +269,11 @@ impl AppState {
             )
             .child(
                 div()
+                    .id("tab-dockerfile-analyzer")
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.switch_tab(ActiveTab::DockerfileAnalyzer);
+                        cx.notify();
+                    }))
                     .px_4()
                     .py_2()
                     .bg(if self.app.active_tab == ActiveTab::DockerfileAnalyzer {
@@ -197,31 +293,24 @@ impl AppState {
             )
     }
 
-    fn render_content(&self) -> impl IntoElement {
+    fn render_content(&self, cx: &mut Context<Self>) -> impl IntoElement {
         match self.app.active_tab {
             ActiveTab::ImageInspector => div()
                 .flex()
                 .flex_grow()
                 .h_full()
-                .children(vec![self.render_sidebar(), self.render_main_content()]),
-            ActiveTab::DockerfileAnalyzer => div().flex().flex_grow().h_full().children(vec![
-                self.render_dockerfile_editor(),
-                self.render_dockerfile_analysis(),
-            ]),
+                .child(self.render_sidebar(cx))
+                .child(self.render_main_content()),
+            ActiveTab::DockerfileAnalyzer => div()
+                .flex()
+                .flex_grow()
+                .h_full()
+                .child(self.render_dockerfile_editor())
+                .child(self.render_dockerfile_analysis()),
         }
     }
 
     fn render_dockerfile_editor(&self) -> impl IntoElement {
-        // Get the content to display
-        let content = if self.app.dockerfile_content.is_empty() {
-            String::from("# Enter your Dockerfile here\nFROM ubuntu:latest\n\nRUN apt-get update && apt-get install -y curl\n\nCOPY . /app\n\nCMD [\"echo\", \"Hello World\"]")
-        } else {
-            self.app.dockerfile_content.to_string()
-        };
-
-        // Create the editor with syntax highlighting and tooltips
-        let editor_result = dockerfile_editor::render_dockerfile_with_highlighting(&content);
-
         // Container for the editor
         div()
             .flex()
@@ -254,22 +343,8 @@ impl AppState {
                     .flex_col()
                     .flex_grow()
                     .overflow_hidden() // Prevent overflow
-                    .child(if let Ok(editor) = editor_result {
-                        editor.into()
-                    } else {
-                        // Fallback to simple editor if highlighting fails
-                        div()
-                            .flex_grow()
-                            .p_3()
-                            .bg(rgb(THEME_BG_MUTED))
-                            .border_1()
-                            .border_color(rgb(THEME_BORDER))
-                            .text_color(rgb(THEME_TEXT_PRIMARY))
-                            .child(content)
-                            .into()
-                    }),
+                    .child(self.dockerfile_editor.clone()),
             )
-            .into()
     }
 
     fn render_analysis_results(&self) -> impl IntoElement {
@@ -295,7 +370,8 @@ impl AppState {
                     .flex_col()
                     .p_4()
                     .gap_4()
-                    .overflow_y_auto()
+                    .id("analysis-results")
+                    .overflow_y_scroll()
                     .children(
                         self.app
                             .dockerfile_analysis
@@ -319,7 +395,6 @@ impl AppState {
                             .collect::<Vec<_>>(),
                     ),
             )
-            .into()
     }
 
     fn render_dockerfile_analysis(&self) -> impl IntoElement {
@@ -334,8 +409,8 @@ impl AppState {
                 .bg(rgb(THEME_BG_SECONDARY))
                 .border_1()
                 .border_color(rgb(THEME_BORDER))
-                .child("Loading...")
-                .into()
+                .child(loading_indicator())
+                .into_any_element()
         } else if let Some(error) = &self.app.error_message {
             div()
                 .flex()
@@ -355,9 +430,9 @@ impl AppState {
                         .border_color(rgb(THEME_BG_DESTRUCTIVE))
                         .child(error.to_string()),
                 )
-                .into()
+                .into_any_element()
         } else if self.app.dockerfile.is_some() {
-            self.render_analysis_results()
+            self.render_analysis_results().into_any_element()
         } else {
             div()
                 .flex()
@@ -370,11 +445,11 @@ impl AppState {
                 .border_1()
                 .border_color(rgb(THEME_BORDER))
                 .child("Enter a Dockerfile and click Analyze")
-                .into()
+                .into_any_element()
         }
     }
 
-    fn render_sidebar(&self) -> impl IntoElement {
+    fn render_sidebar(&self, cx: &mut Context<Self>) -> impl IntoElement {
         div()
             .flex()
             .flex_col()
@@ -398,12 +473,11 @@ impl AppState {
                     .flex_grow()
                     .p_2()
                     .gap_2()
-                    .children(self.render_layers()),
+                    .child(self.render_layers(cx)),
             )
-            .into()
     }
 
-    fn render_layers(&self) -> impl IntoElement {
+    fn render_layers(&self, cx: &mut Context<Self>) -> impl IntoElement {
         div()
             .flex()
             .flex_col()
@@ -414,7 +488,8 @@ impl AppState {
                     .bg(rgb(THEME_BG_MUTED))
                     .border_1()
                     .border_color(rgb(THEME_BORDER))
-                    .child("Loading...")]
+                    .child(loading_indicator())
+                    .into_any_element()]
             } else if let Some(error) = &self.app.error_message {
                 vec![div()
                     .p_3()
@@ -422,7 +497,8 @@ impl AppState {
                     .bg(rgb(THEME_BG_MUTED))
                     .border_1()
                     .border_color(rgb(THEME_BG_DESTRUCTIVE))
-                    .child(error.to_string())]
+                    .child(error.to_string())
+                    .into_any_element()]
             } else if let Some(image) = &self.app.image {
                 image
                     .layers
@@ -432,6 +508,11 @@ impl AppState {
                         let is_selected = self.app.selected_layer == Some(i);
 
                         div()
+                            .id(("layer", i))
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.select_layer(i, cx);
+                                cx.notify();
+                            }))
                             .p_3()
                             .bg(if is_selected {
                                 rgb(THEME_BG_ACCENT)
@@ -464,10 +545,11 @@ impl AppState {
                                             .text_color(rgb(THEME_TEXT_SECONDARY))
                                             .child(format!(
                                                 "Size: {:.2} MB",
-                                                layer.size as f64 / 1_000_000.0
+                                                layer.size_bytes as f64 / 1_000_000.0
                                             )),
                                     ),
                             )
+                            .into_any_element()
                     })
                     .collect()
             } else {
@@ -476,7 +558,8 @@ impl AppState {
                     .bg(rgb(THEME_BG_MUTED))
                     .border_1()
                     .border_color(rgb(THEME_BORDER))
-                    .child("No image loaded")]
+                    .child("No image loaded")
+                    .into_any_element()]
             })
     }
 
@@ -492,8 +575,8 @@ impl AppState {
                 .bg(rgb(THEME_BG_SECONDARY))
                 .border_1()
                 .border_color(rgb(THEME_BORDER))
-                .child("Loading...")
-                .into()
+                .child(loading_indicator())
+                .into_any_element()
         } else if let Some(error) = &self.app.error_message {
             div()
                 .flex()
@@ -513,9 +596,9 @@ impl AppState {
                         .border_color(rgb(THEME_BG_DESTRUCTIVE))
                         .child(error.to_string()),
                 )
-                .into()
+                .into_any_element()
         } else if self.app.image.is_some() && self.app.selected_layer.is_some() {
-            self.render_layer_details()
+            self.render_layer_details().into_any_element()
         } else {
             div()
                 .flex()
@@ -528,7 +611,7 @@ impl AppState {
                 .border_1()
                 .border_color(rgb(THEME_BORDER))
                 .child("Select a layer to view details")
-                .into()
+                .into_any_element()
         }
     }
 
@@ -559,7 +642,8 @@ impl AppState {
                     .flex_col()
                     .p_4()
                     .gap_4()
-                    .overflow_y_auto()
+                    .id("layer-details")
+                    .overflow_y_scroll()
                     .child(
                         div()
                             .flex()
@@ -585,7 +669,7 @@ impl AppState {
                                 div().flex().justify_between().child("Size:").child(
                                     div().text_color(rgb(THEME_TEXT_SECONDARY)).child(format!(
                                         "{:.2} MB",
-                                        layer.size as f64 / 1_000_000.0
+                                        layer.size_bytes as f64 / 1_000_000.0
                                     )),
                                 ),
                             )
@@ -615,7 +699,7 @@ impl AppState {
                                     .border_color(rgb(THEME_BORDER))
                                     .text_color(rgb(THEME_TEXT_SECONDARY))
                                     .child(
-                                        layer.command.clone().unwrap_or_else(|| "N/A".to_string()),
+                                        layer.created_by.clone(),
                                     ),
                             ),
                     )
@@ -628,43 +712,32 @@ impl AppState {
                             .bg(rgb(THEME_BG_MUTED))
                             .border_1()
                             .border_color(rgb(THEME_BORDER))
-                            .child(div().font_weight(FontWeight::BOLD).child("Files Changed"))
-                            .child(if let Some(files) = &layer.files {
-                                div()
-                                    .flex()
-                                    .flex_col()
-                                    .gap_1()
-                                    .max_h_64()
-                                    .overflow_y_auto()
-                                    .children(
-                                        files
-                                            .iter()
-                                            .map(|file| {
-                                                div()
-                                                    .p_1()
-                                                    .text_sm()
-                                                    .text_color(rgb(THEME_TEXT_SECONDARY))
-                                                    .child(file.clone())
-                                            })
-                                            .collect::<Vec<_>>(),
-                                    )
-                            } else {
-                                div()
+                            .child(div().font_weight(FontWeight::BOLD).child("Files"))
+                            .child(match &self.file_tree {
+                                Some(file_tree) => file_tree.clone().into_any_element(),
+                                None => div()
                                     .text_color(rgb(THEME_TEXT_MUTED))
                                     .child("No file information available")
+                                    .into_any_element(),
                             }),
                     ),
             )
-            .into()
     }
 }
 
-fn main() {
-    gpui::App::new().run(|cx| {
-        let app_state = cx.new_model(|_cx| AppState::new());
+// Pulses while docker work runs in the background
+fn loading_indicator() -> impl IntoElement {
+    div().child("Loading...").with_animation(
+        "loading",
+        Animation::new(Duration::from_millis(1200)).repeat(),
+        |label, delta| label.opacity(0.4 + 0.6 * (delta * std::f32::consts::PI).sin()),
+    )
+}
 
+fn main() {
+    Application::new().run(|cx: &mut App| {
         cx.open_window(
-            WindowOptions {
+            gpui::WindowOptions {
                 window_bounds: Some(gpui::WindowBounds::Windowed(gpui::Bounds {
                     origin: Default::default(),
                     size: gpui::Size {
@@ -674,7 +747,8 @@ fn main() {
                 })),
                 ..Default::default()
             },
-            |cx| cx.new_view(|_cx| app_state.clone()),
-        );
+            |_window, cx| cx.new(AppState::new),
+        )
+        .unwrap();
     });
 }
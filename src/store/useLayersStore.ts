@@ -5,16 +5,47 @@ import type {
 	DockerImageInfo,
 	DockerfileAnalysis,
 	DockerImage,
+	FileRange,
+	ComparisonPair,
+	ComparisonPreset,
+	PhaseStatus,
+	PresetComparison,
+	TagMutationCheck,
 } from "../utils/types";
 import type { TreeNode } from "../components/TreeView";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "../lib/utils";
 import { listen } from "@tauri-apps/api/event";
 
+// Files up to this size open in the editor, bigger ones are scrolled through
+// a window of lines at a time
+const EDITABLE_FILE_SIZE = 4 * 1024 * 1024;
+
+// Image IDs, with or without the sha256: prefix
+const IMAGE_ID = /^(sha256:)?[0-9a-f]{12,64}$/;
+
+// Tag an image was opened by, null for untagged images and bare IDs
+function imageReference(imageId: string, images: DockerImage[]): string | null {
+	const image = images.find((image) => image.id === imageId);
+	if (image) {
+		return image.repository === "<none>" || image.tag === "<none>"
+			? null
+			: `${image.repository}:${image.tag}`;
+	}
+	return IMAGE_ID.test(imageId) ? null : imageId;
+}
+
 export interface TaskStatus {
 	message: string;
 	progress: number; // 0.0 to 1.0
 	isComplete: boolean;
 	error?: string | null;
+	phase?: PhaseStatus | null; // Set for operations with several steps
+	// Set by extraction, hashing and pulls
+	bytes_processed?: number;
+	bytes_total?: number | null;
+	current_item?: string | null;
+	eta_seconds?: number | null;
 }
 
 export interface LayersState {
@@ -23,11 +54,15 @@ export interface LayersState {
 	selectedLayerId: string | null;
 	selectedLayerNumber: number | null;
 	selectedFile: FileItem | null;
+	// The layer produced by the Dockerfile instruction under the cursor
+	highlightedLayerId: string | null;
 
 	// Available Docker images
 	availableImages: DockerImage[];
 	isLoadingImages: boolean;
 	selectedImageId: string | null;
+	// Set when the opened tag points at a different image than last time
+	tagMutation: TagMutationCheck | null;
 
 	// Dockerfile content and analysis
 	dockerfileContent: string;
@@ -64,11 +99,14 @@ export interface LayersState {
 		modified: string[];
 		unchanged: string[];
 	} | null;
+	// The images a preset compared, null for a comparison of two layers
+	comparisonPair: ComparisonPair | null;
 
 	// Actions
 	setDockerImage: (image: DockerImageInfo | null) => void;
 	setSelectedLayerId: (id: string | null) => void;
 	setSelectedLayerNumber: (number: number | null) => void;
+	setHighlightedLayerId: (id: string | null) => void;
 	setSelectedFile: (file: FileItem | null) => void;
 	setDockerfileContent: (content: string) => void;
 	setAnalysis: (analysis: DockerfileAnalysis | null) => void;
@@ -83,6 +121,8 @@ export interface LayersState {
 	setAvailableImages: (images: DockerImage[]) => void;
 	setSelectedImageId: (id: string | null) => void;
 	fetchAvailableImages: () => Promise<void>;
+	checkTagMutation: (reference: string) => Promise<void>;
+	dismissTagMutation: () => void;
 
 	// Docker layer actions
 	selectImageAndProcessLayers: (imageId: string) => Promise<void>;
@@ -90,7 +130,7 @@ export interface LayersState {
 	// Layer files actions
 	exportSingleLayer: (layerId: string) => Promise<void>;
 	getLayerFiles: (layerId: string) => Promise<void>;
-	extractDirectory: (dirPath: string) => Promise<void>; // New function to extract a directory on demand
+	extractDirectory: (dirPath: string, exactPath?: string) => Promise<void>; // New function to extract a directory on demand
 	setSelectedLayerFiles: (files: FileItem[]) => void;
 
 	// File content actions
@@ -113,6 +153,7 @@ export interface LayersState {
 			unchanged: string[];
 		} | null,
 	) => void;
+	compareWithPreset: (preset: ComparisonPreset) => Promise<void>;
 	compareLayers: () => Promise<{
 		added: string[];
 		removed: string[];
@@ -140,6 +181,8 @@ const useLayersStore = create<LayersState>((set, get) => ({
 	availableImages: [],
 	isLoadingImages: false,
 	selectedImageId: null,
+	tagMutation: null,
+	highlightedLayerId: null,
 
 	// Layer files
 	selectedLayerFiles: [],
@@ -155,11 +198,13 @@ const useLayersStore = create<LayersState>((set, get) => ({
 	selectedLayersForComparison: [],
 	isComparing: false,
 	comparisonResult: null,
+	comparisonPair: null,
 
 	// Actions
 	setDockerImage: (image) => set({ dockerImage: image }),
 	setSelectedLayerId: (id) => set({ selectedLayerId: id }),
 	setSelectedLayerNumber: (number) => set({ selectedLayerNumber: number }),
+	setHighlightedLayerId: (id) => set({ highlightedLayerId: id }),
 	setSelectedFile: (file) => set({ selectedFile: file }),
 	setDockerfileContent: (content) => {
 		console.log(
@@ -193,12 +238,31 @@ const useLayersStore = create<LayersState>((set, get) => ({
 			console.error("Error fetching Docker images:", error);
 			set({
 				error:
-					typeof error === "string" ? error : "Failed to fetch Docker images",
+					errorMessage(error, "Failed to fetch Docker images"),
 				isLoadingImages: false,
 			});
 		}
 	},
 
+	checkTagMutation: async (reference) => {
+		try {
+			const check = await invoke<TagMutationCheck>("check_tag_mutation", {
+				reference,
+			});
+			// The user may have opened another image in the meantime
+			const { selectedImageId, availableImages } = get();
+			const opened = selectedImageId
+				? imageReference(selectedImageId, availableImages)
+				: null;
+			if (check.moved && opened === reference) {
+				set({ tagMutation: check });
+			}
+		} catch (error) {
+			console.error("Error checking tag history:", error);
+		}
+	},
+	dismissTagMutation: () => set({ tagMutation: null }),
+
 	// Docker layer actions
 	selectImageAndProcessLayers: async (imageId) => {
 		try {
@@ -221,6 +285,8 @@ const useLayersStore = create<LayersState>((set, get) => ({
 				isLoading: true,
 				error: null,
 				selectedImageId: imageId,
+				tagMutation: null,
+				highlightedLayerId: null,
 				taskStatus: {
 					message: "Starting image processing...",
 					progress: 0,
@@ -229,10 +295,10 @@ const useLayersStore = create<LayersState>((set, get) => ({
 				},
 			});
 
-			// Step 1: Retag the image
+			// Step 1: Select the image for this session
 			set({
 				taskStatus: {
-					message: "Tagging image with 'layers' tag...",
+					message: "Selecting image...",
 					progress: 0.1,
 					isComplete: false,
 					error: null,
@@ -240,21 +306,23 @@ const useLayersStore = create<LayersState>((set, get) => ({
 			});
 
 			try {
-				const tagResult = await invoke<string>("retag_image_for_layers", {
-					imageId,
-				});
-				console.log("Tag result:", tagResult);
-			} catch (tagError) {
-				console.error("Error tagging image:", tagError);
+				await invoke("select_image", { imageId });
+
+				// Warn when a tag opened before now points at another image
+				const reference = imageReference(imageId, get().availableImages);
+				if (reference) {
+					get().checkTagMutation(reference);
+				}
+			} catch (selectError) {
+				console.error("Error selecting image:", selectError);
 				set({
-					error:
-						typeof tagError === "string" ? tagError : "Failed to tag image",
+					error: errorMessage(selectError, "Failed to select image"),
 					isLoading: false,
 					taskStatus: {
-						message: "Error tagging image",
+						message: "Error selecting image",
 						progress: 0,
 						isComplete: true,
-						error: typeof tagError === "string" ? tagError : "Unknown error",
+						error: errorMessage(selectError, "Unknown error"),
 					},
 				});
 				return;
@@ -290,17 +358,13 @@ const useLayersStore = create<LayersState>((set, get) => ({
 			} catch (exportError) {
 				console.error("Error exporting image layers:", exportError);
 				set({
-					error:
-						typeof exportError === "string"
-							? exportError
-							: "Failed to export image layers",
+					error: errorMessage(exportError, "Failed to export image layers"),
 					isLoading: false,
 					taskStatus: {
 						message: "Error exporting image layers",
 						progress: 0,
 						isComplete: true,
-						error:
-							typeof exportError === "string" ? exportError : "Unknown error",
+						error: errorMessage(exportError, "Unknown error"),
 					},
 				});
 			}
@@ -310,13 +374,13 @@ const useLayersStore = create<LayersState>((set, get) => ({
 			console.error("Error processing image layers:", error);
 			set({
 				error:
-					typeof error === "string" ? error : "Failed to process image layers",
+					errorMessage(error, "Failed to process image layers"),
 				isLoading: false,
 				taskStatus: {
 					message: "Error processing image layers",
 					progress: 0,
 					isComplete: true,
-					error: typeof error === "string" ? error : "Unknown error",
+					error: errorMessage(error, "Unknown error"),
 				},
 			});
 		}
@@ -376,16 +440,21 @@ const useLayersStore = create<LayersState>((set, get) => ({
 
 			// Also load the files for this layer
 			await get().getLayerFiles("current_layer");
+
+			// Warm up package databases, /etc and the app directory in the background
+			invoke("prefetch_image_paths").catch((error) =>
+				console.error("Error prefetching paths:", error),
+			);
 		} catch (error) {
 			console.error("Error exporting layer:", error);
 			set({
-				error: typeof error === "string" ? error : "Failed to export layer",
+				error: errorMessage(error, "Failed to export layer"),
 				isLoading: false,
 				taskStatus: {
 					message: "Error exporting layer",
 					progress: 0,
 					isComplete: true,
-					error: typeof error === "string" ? error : "Unknown error",
+					error: errorMessage(error, "Unknown error"),
 				},
 			});
 		}
@@ -405,7 +474,7 @@ const useLayersStore = create<LayersState>((set, get) => ({
 				selectedLayerId: layerId,
 			});
 
-			const files = await invoke<FileItem[]>("get_layer_files", {
+			const files = await invoke<FileItem[]>("get_layer_files_flat", {
 				layerId: genericLayerId,
 			});
 
@@ -421,12 +490,12 @@ const useLayersStore = create<LayersState>((set, get) => ({
 			set({
 				selectedLayerFiles: [],
 				isLoadingLayerFiles: false,
-				error: typeof error === "string" ? error : "Failed to get layer files",
+				error: errorMessage(error, "Failed to get layer files"),
 			});
 		}
 	},
 
-	extractDirectory: async (dirPath) => {
+	extractDirectory: async (dirPath, exactPath) => {
 		try {
 			const { selectedLayerId, selectedLayerFiles } = get();
 
@@ -444,7 +513,7 @@ const useLayersStore = create<LayersState>((set, get) => ({
 
 			// Call the backend to extract the directory
 			const files = await invoke<FileItem[]>("extract_directory", {
-				dirPath,
+				dirPath: exactPath ?? dirPath,
 				layerId: selectedLayerId,
 			});
 
@@ -473,7 +542,7 @@ const useLayersStore = create<LayersState>((set, get) => ({
 			console.error("Error extracting directory:", error);
 			set({
 				error:
-					typeof error === "string" ? error : "Failed to extract directory",
+					errorMessage(error, "Failed to extract directory"),
 				loadingDirectories: new Set(
 					[...get().loadingDirectories].filter((d) => d !== dirPath),
 				),
@@ -502,9 +571,18 @@ const useLayersStore = create<LayersState>((set, get) => ({
 
 			try {
 				// Call the Rust backend function to read the file
-				const content = await invoke<string>("read_layer_file", {
-					filePath: file.path,
+				const range = await invoke<FileRange>("read_file_range", {
+					path: file.exact_path ?? file.path,
+					offset: 0,
+					length: EDITABLE_FILE_SIZE,
 				});
+				// FileViewer picks the hex or the line view from these messages
+				let content = range.content;
+				if (range.is_binary) {
+					content = `Cannot display binary file: ${file.path}`;
+				} else if (!range.eof) {
+					content = `File is too large to display: ${file.path} (${range.total_size} bytes)`;
+				}
 
 				set({
 					selectedFileContent: content,
@@ -518,7 +596,7 @@ const useLayersStore = create<LayersState>((set, get) => ({
 					selectedFileContent: `Error reading file: ${error}`,
 					isLoadingFileContent: false,
 					error:
-						typeof error === "string" ? error : "Failed to read file content",
+						errorMessage(error, "Failed to read file content"),
 				});
 			}
 		} catch (error) {
@@ -527,7 +605,7 @@ const useLayersStore = create<LayersState>((set, get) => ({
 				selectedFileContent: `Error loading file content: ${error}`,
 				isLoadingFileContent: false,
 				error:
-					typeof error === "string" ? error : "Failed to load file content",
+					errorMessage(error, "Failed to load file content"),
 			});
 		}
 	},
@@ -580,6 +658,34 @@ const useLayersStore = create<LayersState>((set, get) => ({
 	clearLayersForComparison: () => set({ selectedLayersForComparison: [] }),
 	setIsComparing: (isComparing) => set({ isComparing }),
 	setComparisonResult: (result) => set({ comparisonResult: result }),
+	compareWithPreset: async (preset) => {
+		set({ isComparing: true, error: null });
+
+		try {
+			const { pair, comparison } = await invoke<PresetComparison>(
+				"compare_with_preset",
+				{ preset, options: { include_unchanged: true } },
+			);
+			set({
+				comparisonResult: {
+					added: comparison.added,
+					removed: comparison.removed,
+					modified: comparison.modified.map((change) => change.path),
+					unchanged: comparison.unchanged,
+				},
+				comparisonPair: pair,
+				isComparing: false,
+			});
+		} catch (error) {
+			console.error("Error comparing with preset:", error);
+			set({
+				error: errorMessage(error, "Failed to compare images"),
+				isComparing: false,
+			});
+			throw error;
+		}
+	},
+
 	compareLayers: async () => {
 		const { selectedLayersForComparison } = get();
 
@@ -603,6 +709,7 @@ const useLayersStore = create<LayersState>((set, get) => ({
 
 			set({
 				comparisonResult: result,
+				comparisonPair: null,
 				isComparing: false,
 			});
 
@@ -610,7 +717,7 @@ const useLayersStore = create<LayersState>((set, get) => ({
 		} catch (error) {
 			console.error("Error comparing layers:", error);
 			set({
-				error: typeof error === "string" ? error : "Failed to compare layers",
+				error: errorMessage(error, "Failed to compare layers"),
 				isComparing: false,
 			});
 			return null;
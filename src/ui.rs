@@ -1,6 +1,5 @@
 use crate::docker::DockerImage;
 use crate::dockerfile::Dockerfile;
-use crate::dockerfile_editor;
 
 // Define some theme colors for consistency
 pub const THEME_BG_PRIMARY: u32 = 0x18181b; // Zinc 950
@@ -13,10 +12,8 @@ pub const THEME_BG_DESTRUCTIVE: u32 = 0xef4444; // Red 500
 pub const THEME_TEXT_PRIMARY: u32 = 0xfafafa; // Zinc 50
 pub const THEME_TEXT_SECONDARY: u32 = 0xa1a1aa; // Zinc 400
 pub const THEME_TEXT_MUTED: u32 = 0x71717a; // Zinc 500
-pub const THEME_TEXT_ACCENT: u32 = 0x3b82f6; // Blue 500
 
 pub const THEME_BORDER: u32 = 0x3f3f46; // Zinc 700
-pub const THEME_BORDER_FOCUS: u32 = 0x3b82f6; // Blue 500
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ActiveTab {
@@ -33,7 +30,6 @@ pub struct LayersApp {
     pub loading: bool,
     pub error_message: Option<String>,
     pub dockerfile: Option<Dockerfile>,
-    pub dockerfile_content: String,
     pub dockerfile_analysis: Vec<(String, String)>,
 }
 
@@ -47,7 +43,6 @@ impl LayersApp {
             loading: false,
             error_message: None,
             dockerfile: None,
-            dockerfile_content: String::new(),
             dockerfile_analysis: Vec::new(),
         }
     }
@@ -84,6 +79,10 @@ impl LayersApp {
         self.dockerfile_analysis = analysis;
     }
     
+    pub fn select_layer(&mut self, index: usize) {
+        self.selected_layer = Some(index);
+    }
+    
     pub fn switch_tab(&mut self, tab: ActiveTab) {
         self.active_tab = tab;
     }
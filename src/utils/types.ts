@@ -3,18 +3,69 @@
 export interface FileItem {
 	name: string;
 	path: string;
+	// Exact form of path when it isn't plain UTF-8, send it back instead
+	exact_path?: string;
 	is_dir: boolean;
 	size: number;
 	depth?: number;
 	needs_loading?: boolean;
+	// Bytes, unset for directories and links
+	size_bytes?: number;
+	// Where a symlink or hard link points, as stored in the image
+	link_target?: string;
+	// The link points to nothing in the image, or loops
+	broken_link?: boolean;
+	// From the tar header, mode includes the setuid, setgid and sticky bits
+	mode?: number;
+	// ls style, e.g. "-rwsr-xr-x"
+	permissions?: string;
+	uid?: number;
+	gid?: number;
+	// Seconds since the epoch
+	mtime?: number;
+	// From the PAX header, unset when the entry has none
+	attributes?: FileAttributes;
+	owner?: FileOwner;
 }
 
+// uid/gid resolved against the image's /etc/passwd and /etc/group
+export type FileOwner = {
+	uid: number;
+	gid: number;
+	user: string | null;
+	group: string | null;
+	// e.g. "nginx:nginx (101:101)"
+	display: string;
+	// The uid has no entry in the image's /etc/passwd
+	unknown_user: boolean;
+};
+
+// Decoded security.capability xattr
+export type FileCapabilities = {
+	permitted: string[];
+	inheritable: string[];
+	effective: boolean;
+	root_uid: number | null;
+	// getcap style, e.g. "cap_net_bind_service=ep"
+	display: string;
+};
+
+export type FileAttributes = {
+	// Without the SCHILY.xattr. prefix, binary values as hex
+	xattrs: Record<string, string>;
+	selinux_label: string | null;
+	capabilities: FileCapabilities | null;
+};
+
 export type DockerLayer = {
 	id: string;
 	name: string;
 	command: string;
 	size: string;
+	size_bytes: number;
 	createdAt: string;
+	// Why the layer could only be read in part
+	partial?: string;
 	files: FileItem[];
 };
 
@@ -23,7 +74,19 @@ export type DockerImageInfo = {
 	name: string;
 	created: string;
 	size: string;
+	size_bytes: number;
 	layers: DockerLayer[];
+	config?: ImageRunConfig;
+};
+
+export type ImageRunConfig = {
+	entrypoint: string[];
+	cmd: string[];
+	env: string[];
+	working_dir: string;
+	user: string;
+	exposed_ports: string[];
+	labels: Record<string, string>;
 };
 
 export type DockerImage = {
@@ -32,6 +95,8 @@ export type DockerImage = {
 	tag: string;
 	created: string;
 	size: string;
+	// Approximate, docker images only prints rounded sizes
+	size_bytes: number;
 };
 
 export type DockerfileAnalysis = {
@@ -39,9 +104,976 @@ export type DockerfileAnalysis = {
 		lineNumber: number;
 		instruction: string;
 		impact: string;
+		// Estimated layer size before building, null if unknown
+		predictedMb: number | null;
+		predictionBasis: string[];
 	}>;
 	optimizationSuggestions: Array<{
 		title: string;
 		description: string;
 	}>;
+	// 0 rebuilt on nearly every build, 1 practically always cached
+	cacheStability: Array<{
+		lineNumber: number;
+		instruction: string;
+		score: number;
+		reasons: string[];
+	}>;
+	stages: BuildStage[];
+};
+
+// Final stage instruction matched to the history entry it produced, see
+// map_dockerfile_to_layers
+export type InstructionLayerMapping = {
+	line_number: number;
+	instruction: string;
+	arguments: string;
+	layer_id: string | null;
+	history_id: string | null;
+	created_by: string | null;
+	size: string | null;
+	size_bytes: number | null;
+	confidence: "exact" | "partial" | null;
+};
+
+// A FROM and its instructions, stages not in the final image are builder-only
+export type BuildStage = {
+	index: number;
+	name: string | null;
+	base: string;
+	baseStage: number | null;
+	lineNumber: number;
+	instructions: Array<{
+		instruction: string;
+		arguments: string;
+		line_number: number;
+	}>;
+	copiesFrom: number[];
+	copiesFromImages: string[];
+	inFinalImage: boolean;
+};
+
+// Commands reject with this, `kind` tells the UI which recovery to offer
+export type LayersError = {
+	kind:
+		| "daemon_unavailable"
+		| "image_not_found"
+		| "extraction_failed"
+		| "permission_denied"
+		| "cancelled"
+		| "other";
+	message: string;
+	image?: string | null;
+	path?: string | null;
+};
+
+// An open image, commands take `session_id` to work on one other than the selected image
+export type ImageSession = {
+	session_id: string;
+	image_id: string;
+	reference: string;
+};
+
+// Saved with set_resource_settings, the battery saver profile overrides the other fields
+export type ResourceSettings = {
+	max_threads: number | null;
+	low_priority: boolean;
+	profile: "normal" | "battery_saver";
+};
+
+export type ResourceStatus = {
+	settings: ResourceSettings;
+	cores: number;
+	threads: number;
+	low_priority: boolean;
+};
+
+// One line of the audit log, returned newest first by get_audit_log
+export type AuditEntry = {
+	timestamp_ms: number;
+	operation: string;
+	target: string;
+	success: boolean;
+	error: string | null;
+};
+
+// A destructive action of this run, undo_action takes it back when `reversible` is set
+export type UndoEntry = {
+	action_id: number;
+	timestamp: number;
+	description: string;
+	action:
+		| { kind: "retag"; image_id: string; tag: string }
+		| { kind: "reopen_session"; image_id: string; reference: string }
+		| { kind: "irreversible" };
+	reversible: boolean;
+	undone: boolean;
+};
+
+// Aggregates from get_layer_stats, enough to judge images with a hundred layers
+export type LayerStats = {
+	history_entries: number;
+	filesystem_layers: number;
+	empty_layers: number;
+	total_bytes: number;
+	average_bytes: number;
+	largest: Array<{ layer_id: string; created_by: string; size_bytes: number }>;
+	layer_limit: number;
+	remaining_layers: number;
+	findings: Array<{
+		rule_id: string;
+		severity: "info" | "low" | "medium" | "high" | "critical";
+		title: string;
+		description: string;
+		path: string | null;
+		layer_id: string | null;
+	}>;
+};
+
+// Payload of the pull_progress event and result of pull_image
+export type PullProgress = {
+	task_id: number;
+	image: string;
+	message: string;
+	layers: Array<{
+		id: string;
+		status: string;
+		downloaded_bytes: number;
+		total_bytes: number | null;
+		complete: boolean;
+	}>;
+	downloaded_bytes: number;
+	total_bytes: number;
+	is_complete: boolean;
+};
+
+// One row of get_directory_attribution, layers are the segments of a stacked bar
+export type DirectoryAttribution = {
+	path: string;
+	total_bytes: number;
+	file_count: number;
+	layers: Array<{
+		layer_id: string;
+		created_by: string;
+		bytes: number;
+		fraction: number;
+	}>;
+};
+
+export type AttributionResult = {
+	directory: DirectoryAttribution;
+	children: DirectoryAttribution[];
+};
+
+export type ComparisonPreset = "base_vs_final" | "previous_tag_vs_current";
+
+// The two images a comparison preset resolved to, pass them to compare_images
+export type ComparisonPair = {
+	preset: ComparisonPreset;
+	image_a: string;
+	image_b: string;
+	label_a: string;
+	label_b: string;
+	resolved_by: string;
+};
+
+// A path whose content, mode, owner, link target or type differs
+export type FileChange = {
+	path: string;
+	size_a: number;
+	size_b: number;
+	changes: string[];
+};
+
+// A config value that differs, env and labels get one entry per key
+export type MetadataChange = {
+	field: string;
+	key: string | null;
+	value_a: string | null;
+	value_b: string | null;
+};
+
+// Result of compare_images, side A is the older image
+export type ImageComparison = {
+	image_a: string;
+	image_b: string;
+	added: string[];
+	removed: string[];
+	modified: FileChange[];
+	// Only filled when include_unchanged is set
+	unchanged: string[];
+	unchanged_count: number;
+	size_a: number;
+	size_b: number;
+	metadata: MetadataChange[];
+	shared_base_layers: number | null;
+};
+
+// Result of compare_with_preset
+export type PresetComparison = {
+	pair: ComparisonPair;
+	comparison: ImageComparison;
+};
+
+// What detect_clipboard_content recognized in clipboard text
+export type ClipboardContent =
+	| {
+			kind: "dockerfile";
+			content: string;
+			instructions: number;
+			base_image: string | null;
+	  }
+	| { kind: "image_reference"; reference: string }
+	| { kind: "unrecognized" };
+
+export type LintSeverity = "style" | "info" | "warning" | "error";
+
+// A lint_dockerfile result, lineNumber is 1-based like the editor's
+export type LintIssue = {
+	ruleId: string;
+	severity: LintSeverity;
+	lineNumber: number;
+	instruction: string;
+	message: string;
+};
+
+export type LintConfig = {
+	disabled_rules?: string[];
+	severity_overrides?: Record<string, LintSeverity>;
+	min_severity?: LintSeverity | null;
+};
+
+// Where a layers:// link points, anchor matches the id in exported reports
+export type DeepLinkTarget =
+	| { kind: "finding"; image: string; anchor: string }
+	| { kind: "path"; image: string; path: string; anchor: string };
+
+export type DeepLink = {
+	url: string;
+	anchor: string;
+};
+
+export type Severity = "info" | "low" | "medium" | "high" | "critical";
+
+// Shared by all the scanners, see list_image_findings
+export type SecurityFinding = {
+	rule_id: string;
+	severity: Severity;
+	title: string;
+	description: string;
+	path: string | null;
+	layer_id: string | null;
+};
+
+// A finding as reports list it, the anchor is what its deep link points at
+export type ReportFinding = SecurityFinding & {
+	anchor: string;
+};
+
+export type RewriteKind = "merge_runs" | "cleanup" | "reorder_copy" | "multi_stage";
+
+// One change optimize_dockerfile proposes, pass its id in `rejected` to
+// leave it out of the rewritten content
+export type RewriteChange = {
+	id: string;
+	kind: RewriteKind;
+	title: string;
+	description: string;
+	lineNumbers: number[];
+	before: string;
+	after: string;
+	applied: boolean;
+};
+
+export type DockerfileRewrite = {
+	content: string;
+	changes: RewriteChange[];
+};
+
+// A report format from list_report_exporters, built in or a WASM plugin
+export type ExporterInfo = {
+	id: string;
+	name: string;
+	extension: string;
+	builtin: boolean;
+};
+
+export type ReportExportResult = {
+	report_id: string;
+	destination: string;
+	format: string;
+	mime_type: string;
+	bytes_written: number;
+};
+
+// A report kept by export_report, listed newest first
+export type SavedReport = {
+	id: string;
+	reference: string;
+	image_id: string;
+	generated_at: number;
+	efficiency_score: number;
+	findings: number;
+};
+
+export type BuildStepStatus = "pending" | "running" | "cached" | "done" | "failed";
+
+// Emitted as "build_progress" while build_and_analyze_dockerfile runs
+export type BuildProgress = {
+	task_id: number;
+	steps: Array<{
+		line_number: number;
+		instruction: string;
+		arguments: string;
+		stage: number;
+		status: BuildStepStatus;
+		duration_ms: number | null;
+	}>;
+	message: string;
+	is_complete: boolean;
+};
+
+// Real layer size and duration of an instruction, size is null for
+// builder-only stages
+export type MeasuredInstruction = {
+	line_number: number;
+	instruction: string;
+	arguments: string;
+	stage: number;
+	size_bytes: number | null;
+	predicted_bytes: number | null;
+	duration_ms: number | null;
+	cached: boolean;
+};
+
+export type BuildAnalysis = {
+	image_id: string;
+	build_duration_ms: number;
+	total_bytes: number;
+	instructions: MeasuredInstruction[];
+};
+
+// Output of a user script run by run_user_script, stdout and stderr are cut
+// off at 1 MiB
+export type ScriptResult = {
+	script: string;
+	layer_id: string;
+	exit_code: number | null;
+	stdout: string;
+	stderr: string;
+	truncated: boolean;
+	duration_ms: number;
+};
+
+export type DockerCapability =
+	| "pull_images"
+	| "tag_images"
+	| "create_containers"
+	| "run_containers"
+	| "build_images"
+	| "remove_images";
+
+export type PermissionDecision = "allow" | "deny";
+
+// A daemon-mutating capability and the decision remembered for it, null
+// until the first prompt
+export type DockerPermission = {
+	capability: DockerCapability;
+	title: string;
+	description: string;
+	decision: PermissionDecision | null;
+};
+
+// Emitted as "dockerfile_analysis_updated" for files passed to
+// watch_dockerfile, which also returns the first one
+export type DockerfileAnalysisUpdate = {
+	path: string;
+	content: string;
+	analysis: DockerfileAnalysis | null;
+	lint_issues: LintIssue[];
+	error: string | null;
+};
+
+// Summary card shown after opening an image, see get_image_health
+export type ScoreFactor = {
+	factor: string;
+	detail: string;
+	points: number;
+};
+
+// Points taken off the health score, see get_scoring_weights
+export type ScoringWeights = {
+	wasted_percent: number;
+	size_gb: number;
+	critical: number;
+	high: number;
+	medium: number;
+	low: number;
+	info: number;
+	root_user: number;
+	stale_base: number;
+	outdated_base: number;
+};
+
+export type SeverityCounts = {
+	critical: number;
+	high: number;
+	medium: number;
+	low: number;
+	info: number;
+};
+
+export type BaseFreshness = {
+	created: string;
+	age_days: number;
+	stale: boolean;
+};
+
+export type ImageHealth = {
+	score: number;
+	grade: "A" | "B" | "C" | "D" | "F";
+	breakdown: ScoreFactor[];
+	total_bytes: number;
+	wasted_bytes: number;
+	efficiency_score: number;
+	layer_count: number;
+	findings: SeverityCounts;
+	base: BaseFreshness | null;
+	user: string;
+	runs_as_root: boolean;
+};
+
+// Metrics of one image digest as recorded in the trend database, see
+// get_image_trend
+export type TrendSnapshot = {
+	id: number;
+	// Teammate the snapshot was imported from, empty for this machine
+	source: string;
+	image_id: string;
+	reference: string;
+	first_seen: number;
+	last_seen: number;
+	total_bytes: number;
+	wasted_bytes: number;
+	efficiency_score: number;
+	layer_count: number;
+	health_score: number | null;
+	critical: number;
+	high: number;
+	medium: number;
+	low: number;
+	info: number;
+};
+
+export type TrendFinding = {
+	anchor: string;
+	rule_id: string;
+	severity: "info" | "low" | "medium" | "high" | "critical";
+	title: string;
+};
+
+export type TrendFindingsDiff = {
+	from_id: number;
+	to_id: number;
+	added: TrendFinding[];
+	resolved: TrendFinding[];
+};
+
+export type TrendExportResult = {
+	destination: string;
+	snapshots: number;
+};
+
+export type TrendImportResult = {
+	source: string;
+	added: number;
+	updated: number;
+	skipped: number;
+};
+
+// A page of a file from read_file_preview, highlighted text or a hex dump
+export type HighlightedSpan = {
+	text: string;
+	color: string;
+	bold: boolean;
+	italic: boolean;
+};
+
+export type HexRow = {
+	offset: number;
+	hex: string;
+	ascii: string;
+};
+
+export type PreviewContent =
+	| {
+			kind: "text";
+			language: string | null;
+			lines: { spans: HighlightedSpan[] }[];
+	  }
+	| { kind: "hex"; rows: HexRow[] };
+
+export type FilePreview = {
+	path: string;
+	offset: number;
+	length: number;
+	total_size: number;
+	eof: boolean;
+	content: PreviewContent;
+};
+
+// Team ruleset pulled from a git repository or an HTTPS directory
+export type RulesetSyncSettings = {
+	url: string | null;
+	interval_minutes: number;
+};
+
+export type RulesetSyncStatus = {
+	url: string | null;
+	interval_minutes: number;
+	last_synced_ms: number | null;
+	last_error: string | null;
+	revision: string | null;
+	lint_config: boolean;
+	scoring_weights: boolean;
+	ignored: number;
+};
+
+// Result of check_image_startup, the image run on an isolated network
+export type StartupOutcome =
+	| "running"
+	| "healthy"
+	| "unhealthy"
+	| "exited"
+	| "timed_out";
+
+export type PortCheck = {
+	port: number;
+	protocol: string;
+	listening: boolean | null;
+};
+
+export type StartupCheck = {
+	image_id: string;
+	outcome: StartupOutcome;
+	command: string[];
+	exit_code: number | null;
+	health: string | null;
+	ready_ms: number | null;
+	duration_ms: number;
+	ports: PortCheck[];
+	logs: string[];
+	logs_truncated: boolean;
+	findings: Array<{
+		rule_id: string;
+		severity: "info" | "low" | "medium" | "high" | "critical";
+		title: string;
+		description: string;
+		path: string | null;
+		layer_id: string | null;
+	}>;
+};
+
+// Part of a file from read_file_range or read_layer_file_range
+export type FileRange = {
+	path: string;
+	offset: number;
+	length: number;
+	total_size: number;
+	eof: boolean;
+	is_binary: boolean;
+	content: string;
+};
+
+// Line access to large files, see get_line_index and read_file_lines
+export type LineIndexInfo = {
+	path: string;
+	total_size: number;
+	line_count: number;
+};
+
+export type FileLines = {
+	path: string;
+	start_line: number;
+	offset: number;
+	lines: string[];
+	line_count: number;
+	eof: boolean;
+};
+
+export type ModeScan = {
+	layer_id: string;
+	files_checked: number;
+	files: Array<{
+		path: string;
+		mode: number;
+		permissions: string;
+		uid: number;
+		gid: number;
+		mtime: number;
+		owner: FileOwner;
+	}>;
+	findings: Array<{
+		rule_id: string;
+		severity: "info" | "low" | "medium" | "high" | "critical";
+		title: string;
+		description: string;
+		path: string | null;
+		layer_id: string | null;
+	}>;
+};
+
+export type RuntimeWrites = {
+	image_id: string;
+	duration_ms: number;
+	// Stopped on its own before the run ended
+	exited: boolean;
+	bytes_written: number | null;
+	locations: Array<{
+		path: string;
+		kind: "temporary" | "cache" | "logs" | "config" | "data";
+		added: number;
+		changed: number;
+		deleted: number;
+		sample: string[];
+		suggestion: string | null;
+	}>;
+	findings: Array<{
+		rule_id: string;
+		severity: "info" | "low" | "medium" | "high" | "critical";
+		title: string;
+		description: string;
+		path: string | null;
+		layer_id: string | null;
+	}>;
+};
+
+// Optional sort argument of get_docker_images, get_layer_files_flat and
+// extract_directory
+export type SizeOrder = "ascending" | "descending";
+
+export type SlimProposal = {
+	image_id: string;
+	profile: "strace" | "procfs" | "none";
+	duration_ms: number;
+	exited: boolean;
+	accessed_paths: number;
+	total_files: number;
+	kept_files: number;
+	total_bytes: number;
+	kept_bytes: number;
+	reduction_bytes: number;
+	reduction_percent: number;
+	copy_paths: string[];
+	largest_removed: Array<{ path: string; size_bytes: number }>;
+	dockerfile: string;
+	warnings: string[];
+};
+
+// Contents of layers.toml, from get_settings and update_settings
+export type Settings = {
+	docker_host: string | null;
+	cache_max_bytes: number | null;
+	extraction_concurrency: number | null;
+	credentials_helper: string | null;
+	binary_detection: {
+		sample_bytes: number;
+		non_ascii_ratio: number;
+	};
+};
+
+// From build_content_index, null when the image was already indexed
+export type ContentIndexJob = {
+	task_id: number | null;
+};
+
+export type ContentSearchResult = {
+	query: string;
+	matches: Array<{
+		path: string;
+		score: number;
+		snippet: string;
+		// Byte ranges of the matched terms in snippet
+		highlights: Array<[number, number]>;
+	}>;
+	total_hits: number;
+};
+
+// An image opened from its registry with open_remote_image. eStargz and
+// zstd:chunked layers are browsed through their TOC, file by file.
+export type RemoteImage = {
+	reference: string;
+	manifest_digest: string | null;
+	layers: Array<{
+		digest: string;
+		size_bytes: number;
+		format: "estargz" | "zstd_chunked" | "none";
+		files: number | null;
+	}>;
+	total_bytes: number;
+	bytes_fetched: number;
+	complete: boolean;
+};
+
+export type RemoteFile = {
+	path: string;
+	name: string;
+	file_type: string;
+	size_bytes: number;
+	mode: number;
+	link_target: string | null;
+	layer_digest: string;
+};
+
+export type RemoteDirectory = {
+	path: string;
+	entries: RemoteFile[];
+	complete: boolean;
+};
+
+export type RemoteFileContent = {
+	path: string;
+	size_bytes: number;
+	content: string;
+	is_binary: boolean;
+	truncated: boolean;
+	bytes_fetched: number;
+};
+
+export type PhaseStatus = {
+	name: string;
+	index: number;
+	// Every phase of the operation, in order
+	phases: string[];
+	// 0.0 to 1.0 within the phase
+	progress: number;
+};
+
+export type TimelineStep = {
+	id: string;
+	created_by: string;
+	created: string;
+	created_at: number | null;
+	delta_seconds: number | null;
+	build_boundary: boolean;
+	size_bytes: number;
+	cache_hit: boolean;
+	time_share: number;
+	size_share: number;
+	dominant: boolean;
+};
+
+export type ImageTimeline = {
+	image: string;
+	steps: TimelineStep[];
+	build_seconds: number;
+	total_bytes: number;
+	builds: number;
+};
+
+export type SquashedFile = {
+	path: string;
+	size_bytes: number;
+	layer_id: string;
+};
+
+// Result of simulate_squash
+export type SquashSimulation = {
+	image: string;
+	layers: string[];
+	original_bytes: number;
+	squashed_bytes: number;
+	saved_bytes: number;
+	image_bytes: number;
+	squashed_image_bytes: number;
+	file_count: number;
+	largest_files: SquashedFile[];
+	whiteouts: string[];
+};
+
+export type RootfsFormat = "tar" | "directory";
+
+// Result of export_flattened_rootfs
+export type RootfsExportResult = {
+	destination: string;
+	format: RootfsFormat;
+	layers: string[];
+	size_bytes: number;
+};
+
+export type HealthcheckConfig = {
+	test: string[];
+	interval_seconds: number | null;
+	timeout_seconds: number | null;
+	start_period_seconds: number | null;
+	retries: number | null;
+};
+
+export type ConfigChange = {
+	field: string;
+	key: string | null;
+	value: string;
+	// What an earlier history entry had set
+	previous: string | null;
+};
+
+export type HistoryConfigChanges = {
+	layer_id: string;
+	created_by: string;
+	instruction: string;
+	changes: ConfigChange[];
+};
+
+// Result of get_image_config
+export type ImageConfig = ImageRunConfig & {
+	image: string;
+	volumes: string[];
+	stop_signal: string;
+	healthcheck: HealthcheckConfig | null;
+	// Oldest first
+	history: HistoryConfigChanges[];
+};
+
+export type RecentLogs = {
+	directory: string;
+	// Oldest first
+	lines: string[];
+};
+
+export type LogLevel = "off" | "error" | "warn" | "info" | "debug" | "trace";
+
+// A path marked with add_bookmark, "/" with a layer pins the whole layer
+export type Bookmark = {
+	id: number;
+	image_id: string;
+	reference: string;
+	// "layer_N", null for the whole image
+	layer_id: string | null;
+	path: string;
+	note: string;
+	// Seconds since the epoch
+	created_at: number;
+};
+
+// One image a tag pointed to, times are seconds since the epoch
+export type TagObservation = {
+	image_id: string;
+	repo_digest: string | null;
+	first_seen: number;
+	last_seen: number;
+};
+
+// Result of check_tag_mutation
+export type TagMutationCheck = {
+	reference: string;
+	current: TagObservation;
+	// Only set when the tag moved
+	previous: TagObservation | null;
+	moved: boolean;
+	// Whether the previous image is still local and can be diffed
+	previous_available: boolean;
+};
+
+// Layers of the previous image a tag pointed to against the current one
+export type TagVersionDiff = {
+	reference: string;
+	old_image_id: string;
+	new_image_id: string;
+	shared_layers: string[];
+	removed_layers: string[];
+	added_layers: string[];
+};
+
+// Where export_selected_paths writes the selected paths
+export type ExportFormat = "directory" | "zip";
+
+// Result of export_selected_paths
+export type ExportResult = {
+	destination: string;
+	files_exported: number;
+	directories_exported: number;
+	bytes_exported: number;
+	// Entries the destination can't hold, e.g. names over 255 bytes
+	skipped?: string[];
+};
+
+// Result of generate_run_snippets
+export type RunSnippets = {
+	image: string;
+	docker_run: string;
+	// A compose service stanza
+	compose: string;
+};
+
+export type ServiceKind =
+	| "systemd_service"
+	| "systemd_timer"
+	| "supervisord"
+	| "s6"
+	| "runit"
+	| "cron"
+	| "init_script";
+
+// A unit, supervisor program, cron entry or init script baked into the image
+export type ServiceDefinition = {
+	kind: ServiceKind;
+	name: string;
+	// File the definition was read from
+	path: string;
+	description: string | null;
+	command: string | null;
+	// Cron expression or systemd OnCalendar value
+	schedule: string | null;
+	user: string | null;
+	// For systemd, whether a *.wants symlink enables the unit
+	enabled: boolean | null;
+};
+
+// Result of inspect_services
+export type ServiceInventory = {
+	layer_id: string;
+	services: ServiceDefinition[];
+	// e.g. "systemd", "supervisord", "cron"
+	init_systems: string[];
+};
+
+// A network an image is pulled over, e.g. a CI runner or an edge device
+export type BandwidthProfile = {
+	name: string;
+	bandwidth_mbps: number;
+	// Fixed cost per blob for auth, manifest lookups and connection setup
+	per_layer_overhead_ms: number;
+	concurrent_downloads: number;
+};
+
+export type LayerPullEstimate = {
+	layer_id: string;
+	command: string;
+	uncompressed_bytes: number;
+	compressed_bytes: number;
+	seconds: number;
+	// Of the profile's total time, 0.0 to 1.0
+	share: number;
+	is_dominant: boolean;
+};
+
+export type ProfilePullEstimate = {
+	profile: BandwidthProfile;
+	total_seconds: number;
+	// Base layer first
+	layers: LayerPullEstimate[];
+};
+
+// Where compressed layer sizes come from: the registry manifest, or the
+// uncompressed sizes times a compression ratio
+export type CompressedSizeSource = "registry" | "estimated";
+
+// Result of estimate_pull_times
+export type PullTimeReport = {
+	compressed_sizes: CompressedSizeSource;
+	// Only set when the compressed sizes are estimated
+	compression_ratio: number | null;
+	total_uncompressed_bytes: number;
+	total_compressed_bytes: number;
+	profiles: ProfilePullEstimate[];
 };